@@ -0,0 +1,30 @@
+#!/bin/bash
+# Compile check of each supported feature combination, see the feature matrix in src/lib.rs.
+set -euo pipefail
+cd "$(dirname "$0")/.."
+
+combos=(
+	""
+	"bsh"
+	"bsh,browser-login"
+	"subzero"
+	"miele"
+	"miele,keyvault"
+	"auth"
+	"tower"
+	"bsh,subzero,miele"
+	"bsh,tower,telemetry-tracing"
+)
+
+cargo clippy --all-targets -- -D warnings
+for features in "${combos[@]}"; do
+	echo "--- --no-default-features --features \"$features\""
+	cargo clippy --all-targets --no-default-features --features "$features" -- -D warnings
+done
+cargo clippy --all-targets --all-features -- -D warnings
+
+# only `browser-login` may pull in playwright.
+if cargo tree --no-default-features --features "bsh,subzero" --edges normal | grep -q playwright; then
+	echo "playwright is a dependency without the browser-login feature" >&2
+	exit 1
+fi
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use eggersmann_app_server_auth::User;
+use serde::{Deserialize, Serialize};
+
+use super::{telemetry, AvailabilityRequest, AvailabilityRequestUser, AvailabilityResponse};
+
+///
+/// # `AccessPolicy`
+/// Decides which vendors a user may look up.
+///
+pub trait AccessPolicy: Send + Sync {
+	///
+	/// Whether `user` may look up availability at `manufacturer`.
+	///
+	fn allows(&self, user: &AvailabilityRequestUser, manufacturer: &str) -> bool;
+}
+
+///
+/// # `AllowAll`
+/// Access policy letting every user look up every vendor.
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AllowAll;
+
+impl AccessPolicy for AllowAll {
+	fn allows(&self, _user: &AvailabilityRequestUser, _manufacturer: &str) -> bool {
+		true
+	}
+}
+
+///
+/// # `RolePolicy`
+/// Access policy keyed by job title. A title maps to the vendors it may look up, `*` meaning all of them;
+/// users whose title is not listed get the default vendors.
+///
+/// ## Example
+/// ```
+/// use eggersmann_app_server_appliance_availability::access::{AccessPolicy, RolePolicy};
+/// use eggersmann_app_server_appliance_availability::AvailabilityRequestUser;
+///
+/// let policy = RolePolicy::new(vec!["miele".to_string()]).with_role("Designer", vec!["*".to_string()]);
+/// let designer = AvailabilityRequestUser::new("0000-0000".to_string()).with_job_title("Designer".to_string());
+/// let intern = AvailabilityRequestUser::new("0000-0001".to_string());
+/// assert!(policy.allows(&designer, "bsh"));
+/// assert!(!policy.allows(&intern, "bsh"));
+/// ```
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct RolePolicy {
+	pub roles: HashMap<String, Vec<String>>,
+	pub default: Vec<String>,
+}
+
+impl RolePolicy {
+	///
+	/// # `RolePolicy::new`
+	/// Create a policy where every user may look up the `default` vendors.
+	///
+	#[must_use]
+	pub fn new(default: Vec<String>) -> Self {
+		Self { roles: HashMap::new(), default }
+	}
+
+	#[must_use]
+	pub fn with_role(mut self, job_title: &str, manufacturers: Vec<String>) -> Self {
+		self.roles.insert(job_title.to_lowercase(), manufacturers);
+		self
+	}
+}
+
+impl AccessPolicy for RolePolicy {
+	fn allows(&self, user: &AvailabilityRequestUser, manufacturer: &str) -> bool {
+		let allowed = user.job_title.as_ref().and_then(|job_title| self.roles.get(&job_title.to_lowercase())).unwrap_or(&self.default);
+		allowed.iter().any(|allowed| allowed == "*" || allowed.eq_ignore_ascii_case(manufacturer))
+	}
+}
+
+///
+/// # `UserPreferences`
+/// Lookup preferences stored for a user by the app server.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct UserPreferences {
+	pub preferred_language: Option<String>,
+}
+
+impl UserPreferences {
+	#[must_use]
+	pub fn with_preferred_language(mut self, preferred_language: String) -> Self {
+		self.preferred_language = Some(preferred_language);
+		self
+	}
+}
+
+///
+/// # `PreferenceStore`
+/// Where the preferences of a user are read from.
+///
+pub trait PreferenceStore: Send + Sync {
+	///
+	/// The preferences of the user with `user_id`, `None` if they have none.
+	///
+	fn preferences(&self, user_id: &str) -> Option<UserPreferences>;
+}
+
+impl<S: BuildHasher + Send + Sync> PreferenceStore for HashMap<String, UserPreferences, S> {
+	fn preferences(&self, user_id: &str) -> Option<UserPreferences> {
+		self.get(user_id).cloned()
+	}
+}
+
+///
+/// # `AuditRecord`
+/// Who looked up what, and whether it was allowed and answered.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct AuditRecord {
+	pub user_id: String,
+	pub manufacturer: String,
+	pub showroom: String,
+	pub model_number: String,
+	pub allowed: bool,
+	/// The v1 answer of an allowed lookup, or its error.
+	pub outcome: Option<String>,
+	pub at: DateTime<Utc>,
+}
+
+///
+/// # `AuditSink`
+/// Receives an `AuditRecord` for every lookup made through `AvailabilityService`.
+///
+pub trait AuditSink: Send + Sync {
+	fn record(&self, record: &AuditRecord);
+}
+
+///
+/// # `TelemetryAudit`
+/// Audit sink publishing each record as an `availability.audit` telemetry event.
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TelemetryAudit;
+
+impl AuditSink for TelemetryAudit {
+	fn record(&self, record: &AuditRecord) {
+		telemetry::event("availability.audit", &[("user_id", record.user_id.clone()), ("manufacturer", record.manufacturer.clone()), ("showroom", record.showroom.clone()), ("model_number", record.model_number.clone()), ("allowed", record.allowed.to_string()), ("outcome", record.outcome.clone().unwrap_or_default())]);
+	}
+}
+
+///
+/// # `AvailabilityService`
+/// Entry point for the app server: looks up availability on behalf of an authenticated `User`, applying
+/// their preferences, the access policy and the audit sink in one call.
+///
+/// By default every user may look up every vendor, no preferences are stored and audit records go to
+/// telemetry.
+///
+#[derive(Clone)]
+pub struct AvailabilityService {
+	policy: Arc<dyn AccessPolicy>,
+	preferences: Option<Arc<dyn PreferenceStore>>,
+	audit: Arc<dyn AuditSink>,
+}
+
+impl Default for AvailabilityService {
+	fn default() -> Self {
+		Self { policy: Arc::new(AllowAll), preferences: None, audit: Arc::new(TelemetryAudit) }
+	}
+}
+
+impl AvailabilityService {
+	///
+	/// # `AvailabilityService::new`
+	/// Create a service with the default policy, preferences and audit sink.
+	///
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	#[must_use]
+	pub fn with_policy(mut self, policy: Arc<dyn AccessPolicy>) -> Self {
+		self.policy = policy;
+		self
+	}
+
+	#[must_use]
+	pub fn with_preferences(mut self, preferences: Arc<dyn PreferenceStore>) -> Self {
+		self.preferences = Some(preferences);
+		self
+	}
+
+	#[must_use]
+	pub fn with_audit(mut self, audit: Arc<dyn AuditSink>) -> Self {
+		self.audit = audit;
+		self
+	}
+
+	///
+	/// # `AvailabilityService::availability_for_user`
+	/// Look up availability for `user`.
+	///
+	/// ## Inputs
+	/// * `user`: `User` - The authenticated user.
+	/// * `manufacturer`: &str - The manufacturer of the appliance.
+	/// * `showroom`: &str - The showroom location of the user.
+	/// * `model_number`: &str - The model of the appliance.
+	///
+	/// # Errors
+	/// Returns an error if the access policy denies the user the vendor, or if the lookup fails.
+	///
+	pub async fn availability_for_user(&self, user: User, manufacturer: &str, showroom: &str, model_number: &str) -> Result<AvailabilityResponse, String> {
+		let mut req = AvailabilityRequest::new(manufacturer.to_string(), showroom.to_string(), model_number.to_string()).add_user(user);
+		if let Some(user) = req.user.as_mut() {
+			if let Some(preferred_language) = self.preferences.as_ref().and_then(|preferences| preferences.preferences(&user.id)).and_then(|preferences| preferences.preferred_language) {
+				user.preferred_language = Some(preferred_language);
+			}
+		}
+		let user_id = req.user.as_ref().map(|user| user.id.clone()).unwrap_or_default();
+		let audit = |allowed: bool, outcome: Option<String>| {
+			self.audit.record(&AuditRecord { user_id: user_id.clone(), manufacturer: manufacturer.to_lowercase(), showroom: showroom.to_string(), model_number: model_number.to_string(), allowed, outcome, at: Utc::now() });
+		};
+
+		if !req.user.as_ref().is_some_and(|user| self.policy.allows(user, &manufacturer.to_lowercase())) {
+			audit(false, None);
+			return Err(format!("User {user_id} may not look up {manufacturer} availability."));
+		}
+
+		match req.parse_manufacturer().get_warehouse().get_time().into_answered().await {
+			Ok(req) => {
+				let response = req.response();
+				audit(true, Some(response.v1()));
+				Ok(response)
+			}
+			Err(e) => {
+				audit(true, Some(e.clone()));
+				Err(e)
+			}
+		}
+	}
+}
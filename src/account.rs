@@ -0,0 +1,130 @@
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use super::cache::TtlCache;
+use super::memory::Footprint;
+use super::telemetry;
+
+///
+/// How long the outcome of an account check is trusted before the vendor is checked again.
+///
+pub const ACCOUNT_CHECK_TTL: Duration = Duration::from_hours(1);
+
+///
+/// # `AccountIssue`
+/// An account-level block at a vendor, such as a credit hold. Returned instead of availability for every
+/// lookup against that vendor while it lasts.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct AccountIssue {
+	pub manufacturer: String,
+	/// What the vendor portal reported, e.g. `credit hold`.
+	pub reason: String,
+	pub checked_at: DateTime<Utc>,
+}
+
+impl AccountIssue {
+	///
+	/// # `AccountIssue::new`
+	/// Create an issue checked now.
+	///
+	#[must_use]
+	pub fn new(manufacturer: String, reason: String) -> Self {
+		Self { manufacturer, reason, checked_at: Utc::now() }
+	}
+
+	///
+	/// Text shown in place of the availability by the v1 response.
+	///
+	#[must_use]
+	pub fn message(&self) -> String {
+		format!("The {} account has an issue: {}. Contact the vendor before quoting.", self.manufacturer, self.reason)
+	}
+}
+
+impl Footprint for AccountIssue {
+	fn heap_bytes(&self) -> usize {
+		self.manufacturer.heap_bytes() + self.reason.heap_bytes()
+	}
+}
+
+fn account_checks() -> &'static TtlCache<String, Option<AccountIssue>> {
+	static CHECKS: OnceLock<TtlCache<String, Option<AccountIssue>>> = OnceLock::new();
+	CHECKS.get_or_init(|| TtlCache::new(ACCOUNT_CHECK_TTL))
+}
+
+///
+/// # `account_issue`
+/// The cached issue with the account at `manufacturer`, if its last check found one.
+///
+#[must_use]
+pub fn account_issue(manufacturer: &str) -> Option<AccountIssue> {
+	account_checks().get(&manufacturer.to_lowercase()).flatten()
+}
+
+///
+/// # `clear_account_checks`
+/// Forget every account check, so the next lookup per vendor checks again.
+///
+pub fn clear_account_checks() {
+	account_checks().clear();
+}
+
+///
+/// # Record Account Issue
+/// Remember an issue found outside the pre-check, e.g. a credit hold rejection of a lookup.
+///
+pub fn record(issue: AccountIssue) {
+	account_checks().insert(issue.manufacturer.to_lowercase(), Some(issue));
+}
+
+///
+/// # Checked Account
+/// The issue with the account at `manufacturer`, running `check` at most once per `ACCOUNT_CHECK_TTL`.
+///
+/// Checks are serialized, so a burst of lookups waits for one check instead of each running its own. A
+/// check that fails is not cached and lets the lookup go ahead.
+///
+pub async fn checked<F>(manufacturer: &str, check: F) -> Option<AccountIssue>
+where
+	F: Future<Output = Result<Option<AccountIssue>, String>>,
+{
+	static CHECKING: Mutex<()> = Mutex::const_new(());
+
+	let key = manufacturer.to_lowercase();
+	if let Some(outcome) = account_checks().get(&key) {
+		return outcome;
+	}
+	let _checking = CHECKING.lock().await;
+	if let Some(outcome) = account_checks().get(&key) {
+		return outcome;
+	}
+	match check.await {
+		Ok(outcome) => {
+			if let Some(issue) = &outcome {
+				telemetry::event("account.issue", &[("manufacturer", key.clone()), ("reason", issue.reason.clone())]);
+			}
+			account_checks().insert(key, outcome.clone());
+			outcome
+		}
+		Err(e) => {
+			telemetry::event("account.check.failed", &[("manufacturer", key), ("error", e)]);
+			None
+		}
+	}
+}
+
+///
+/// # Find Account Block
+/// The first of `phrases` that appears in the text of a vendor page, compared case-insensitively.
+///
+pub fn find_block<'a>(page: &str, phrases: &[&'a str]) -> Option<&'a str> {
+	let page = page.to_lowercase();
+	phrases.iter().find(|phrase| page.contains(*phrase)).copied()
+}
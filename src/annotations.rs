@@ -0,0 +1,277 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{telemetry, AvailabilityRequest};
+
+///
+/// File the annotations are kept in when `FileAnnotationStore::default` is used.
+///
+pub const DEFAULT_ANNOTATIONS_FILE: &str = "/easfiles/appliances/annotations.json";
+
+///
+/// # `Annotation`
+/// A note a showroom manager attached to a model, e.g. "display model available in Houston". A note with
+/// a `showroom` is only shown to lookups from that showroom.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Annotation {
+	pub id: String,
+	pub manufacturer: String,
+	pub model_number: String,
+	pub note: String,
+	pub author: String,
+	pub created_at: DateTime<Utc>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub showroom: Option<String>,
+}
+
+impl Annotation {
+	///
+	/// # `Annotation::new`
+	/// A note on `model_number` by `manufacturer`, written now by `author`.
+	///
+	#[must_use]
+	pub fn new(manufacturer: &str, model_number: &str, note: String, author: String) -> Self {
+		static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+		let created_at = Utc::now();
+		let (manufacturer, model_number) = key(manufacturer, model_number);
+		Self {
+			id: format!("{}-{:06}", created_at.format("%Y%m%dT%H%M%S"), SEQUENCE.fetch_add(1, Ordering::Relaxed) % 1_000_000),
+			manufacturer,
+			model_number,
+			note,
+			author,
+			created_at,
+			showroom: None,
+		}
+	}
+
+	#[must_use]
+	pub fn with_showroom(mut self, showroom: &str) -> Self {
+		self.showroom = Some(showroom.to_lowercase());
+		self
+	}
+
+	///
+	/// Whether the note is shown to a lookup from `showroom`.
+	///
+	fn shown_at(&self, showroom: Option<&str>) -> bool {
+		self.showroom.as_deref().is_none_or(|scope| showroom.is_some_and(|showroom| scope.eq_ignore_ascii_case(showroom)))
+	}
+}
+
+///
+/// Manufacturer and model number the way annotations are keyed, so `hblp651ruc ` finds the notes on
+/// `HBLP651RUC`.
+///
+fn key(manufacturer: &str, model_number: &str) -> (String, String) {
+	(manufacturer.trim().to_lowercase(), model_number.trim().to_uppercase())
+}
+
+///
+/// # `AnnotationStore`
+/// Where model notes are kept. Instances of a multi-instance deployment see each other's notes by
+/// sharing a store.
+///
+#[async_trait::async_trait]
+pub trait AnnotationStore: Send + Sync {
+	///
+	/// Every note on `model_number` by `manufacturer`, oldest first.
+	///
+	async fn annotations(&self, manufacturer: &str, model_number: &str) -> Result<Vec<Annotation>, String>;
+
+	///
+	/// Keep `annotation`.
+	///
+	async fn add(&self, annotation: Annotation) -> Result<(), String>;
+
+	///
+	/// Delete the note `id`, `false` if there is none.
+	///
+	async fn remove(&self, id: &str) -> Result<bool, String>;
+}
+
+type AnnotationTable = BTreeMap<String, Vec<Annotation>>;
+
+fn table_key(manufacturer: &str, model_number: &str) -> String {
+	let (manufacturer, model_number) = key(manufacturer, model_number);
+	format!("{manufacturer}/{model_number}")
+}
+
+fn table_annotations(table: &AnnotationTable, manufacturer: &str, model_number: &str) -> Vec<Annotation> {
+	table.get(&table_key(manufacturer, model_number)).cloned().unwrap_or_default()
+}
+
+fn table_add(table: &mut AnnotationTable, annotation: Annotation) {
+	table.entry(table_key(&annotation.manufacturer, &annotation.model_number)).or_default().push(annotation);
+}
+
+fn table_remove(table: &mut AnnotationTable, id: &str) -> bool {
+	let mut removed = false;
+	for annotations in table.values_mut() {
+		let before = annotations.len();
+		annotations.retain(|annotation| annotation.id != id);
+		removed |= annotations.len() < before;
+	}
+	table.retain(|_, annotations| !annotations.is_empty());
+	removed
+}
+
+///
+/// # `InMemoryAnnotationStore`
+/// Notes kept in process memory, lost on restart. For tests and single instances without a disk.
+///
+#[derive(Debug, Default)]
+pub struct InMemoryAnnotationStore {
+	table: Mutex<AnnotationTable>,
+}
+
+#[async_trait::async_trait]
+impl AnnotationStore for InMemoryAnnotationStore {
+	async fn annotations(&self, manufacturer: &str, model_number: &str) -> Result<Vec<Annotation>, String> {
+		Ok(table_annotations(&self.table.lock().unwrap_or_else(std::sync::PoisonError::into_inner), manufacturer, model_number))
+	}
+
+	async fn add(&self, annotation: Annotation) -> Result<(), String> {
+		table_add(&mut self.table.lock().unwrap_or_else(std::sync::PoisonError::into_inner), annotation);
+		Ok(())
+	}
+
+	async fn remove(&self, id: &str) -> Result<bool, String> {
+		Ok(table_remove(&mut self.table.lock().unwrap_or_else(std::sync::PoisonError::into_inner), id))
+	}
+}
+
+///
+/// # `FileAnnotationStore`
+/// Notes kept in a JSON file at `path`, keyed by manufacturer and model, which is created on the first
+/// note. Every change rewrites the file and moves it into place, so a reader never sees half a table.
+///
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct FileAnnotationStore {
+	pub path: PathBuf,
+	lock: Mutex<()>,
+}
+
+impl FileAnnotationStore {
+	#[must_use]
+	pub fn new(path: impl Into<PathBuf>) -> Self {
+		Self { path: path.into(), lock: Mutex::new(()) }
+	}
+
+	fn read(&self) -> Result<AnnotationTable, String> {
+		match fs::read_to_string(&self.path) {
+			Ok(contents) => serde_json::from_str(&contents).map_err(|e| format!("Failed to parse {}: {e}", self.path.display())),
+			Err(e) if e.kind() == ErrorKind::NotFound => Ok(AnnotationTable::new()),
+			Err(e) => Err(format!("Failed to read {}: {e:?}", self.path.display())),
+		}
+	}
+
+	fn write(&self, table: &AnnotationTable) -> Result<(), String> {
+		if let Some(dir) = self.path.parent() {
+			fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e:?}", dir.display()))?;
+		}
+		let contents = serde_json::to_string_pretty(table).map_err(|e| format!("Failed to serialize annotations: {e}"))?;
+		let tmp = self.path.with_extension("tmp");
+		fs::write(&tmp, contents).map_err(|e| format!("Failed to write {}: {e:?}", tmp.display()))?;
+		fs::rename(&tmp, &self.path).map_err(|e| format!("Failed to replace {}: {e:?}", self.path.display()))
+	}
+
+	///
+	/// Read the table, change it and write it back while holding the lock, so two changes do not lose one.
+	///
+	fn update<T>(&self, change: impl FnOnce(&mut AnnotationTable) -> T) -> Result<T, String> {
+		let _guard = self.lock.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+		let mut table = self.read()?;
+		let result = change(&mut table);
+		self.write(&table)?;
+		Ok(result)
+	}
+}
+
+impl Default for FileAnnotationStore {
+	fn default() -> Self {
+		Self::new(DEFAULT_ANNOTATIONS_FILE)
+	}
+}
+
+#[async_trait::async_trait]
+impl AnnotationStore for FileAnnotationStore {
+	async fn annotations(&self, manufacturer: &str, model_number: &str) -> Result<Vec<Annotation>, String> {
+		Ok(table_annotations(&self.read()?, manufacturer, model_number))
+	}
+
+	async fn add(&self, annotation: Annotation) -> Result<(), String> {
+		self.update(|table| table_add(table, annotation))
+	}
+
+	async fn remove(&self, id: &str) -> Result<bool, String> {
+		self.update(|table| table_remove(table, id))
+	}
+}
+
+fn store_slot() -> &'static RwLock<Option<Arc<dyn AnnotationStore>>> {
+	static STORE: OnceLock<RwLock<Option<Arc<dyn AnnotationStore>>>> = OnceLock::new();
+	STORE.get_or_init(|| RwLock::new(None))
+}
+
+///
+/// # `set_annotation_store`
+/// Install the store model notes are read from and written to. Responses carry no notes until one is set.
+///
+/// ## Example
+/// ```
+/// use std::sync::Arc;
+/// use eggersmann_app_server_appliance_availability::annotations::{set_annotation_store, InMemoryAnnotationStore};
+///
+/// set_annotation_store(Arc::new(InMemoryAnnotationStore::default()));
+/// ```
+///
+pub fn set_annotation_store(store: Arc<dyn AnnotationStore>) {
+	*store_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = Some(store);
+}
+
+///
+/// # `annotation_store`
+/// The installed annotation store, if any.
+///
+pub fn annotation_store() -> Option<Arc<dyn AnnotationStore>> {
+	store_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
+
+///
+/// # `annotate`
+/// Attach `annotation` to its model in the installed store.
+///
+/// # Errors
+/// Returns an error if no store is installed or the store fails.
+///
+pub async fn annotate(annotation: Annotation) -> Result<(), String> {
+	let store = annotation_store().ok_or_else(|| "No annotation store is installed.".to_string())?;
+	let labels = [("manufacturer", annotation.manufacturer.clone())];
+	store.add(annotation).await?;
+	telemetry::counter("annotations.added", 1, &labels);
+	Ok(())
+}
+
+///
+/// Merge the notes shown to `req` into it. A store that fails leaves the response without notes.
+///
+pub(crate) async fn merge(mut req: AvailabilityRequest) -> AvailabilityRequest {
+	let Some(store) = annotation_store() else { return req };
+	let (Some(manufacturer), Some(model_number)) = (req.manufacturer.as_ref().map(ToString::to_string), req.model_number.clone()) else { return req };
+	match store.annotations(&manufacturer, &model_number).await {
+		Ok(annotations) => req.annotations = annotations.into_iter().filter(|annotation| annotation.shown_at(req.showroom.as_deref())).collect(),
+		Err(e) => telemetry::event("annotations.lookup.failed", &[("manufacturer", manufacturer), ("model_number", model_number), ("error", e)]),
+	}
+	req
+}
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use super::{AccountIssue, Availability, AvailabilityError, AvailabilityRequest, CatalogVersion, ProductInfo, Rejection, RequestOptions};
+
+///
+/// # `BackendAnswer`
+/// What a manufacturer backend found for a request.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BackendAnswer {
+	/// The availability read from the vendor's answer, its `raw` text becoming the v1 answer.
+	pub availability: Option<Availability>,
+	pub product: Option<ProductInfo>,
+	pub rejection: Option<Rejection>,
+	pub catalog_version: Option<CatalogVersion>,
+	/// Set instead of the availability when the account at the vendor is blocked.
+	pub account_issue: Option<AccountIssue>,
+}
+
+impl BackendAnswer {
+	///
+	/// # `BackendAnswer::new`
+	/// Create an answer with the availability the vendor reported.
+	///
+	#[must_use]
+	pub fn new(availability: Availability) -> Self {
+		Self { availability: Some(availability), ..Self::default() }
+	}
+
+	///
+	/// # `BackendAnswer::account_issue`
+	/// Create an answer blocked by an issue with the account at the vendor.
+	///
+	#[must_use]
+	pub fn account_issue(issue: AccountIssue) -> Self {
+		Self { account_issue: Some(issue), ..Self::default() }
+	}
+
+	#[must_use]
+	pub fn with_product(mut self, product: Option<ProductInfo>) -> Self {
+		self.product = product;
+		self
+	}
+
+	#[must_use]
+	pub fn with_rejection(mut self, rejection: Option<Rejection>) -> Self {
+		self.rejection = rejection;
+		self
+	}
+
+	#[must_use]
+	pub fn with_catalog_version(mut self, catalog_version: Option<CatalogVersion>) -> Self {
+		self.catalog_version = catalog_version;
+		self
+	}
+}
+
+///
+/// # `ManufacturerBackend`
+/// Looks up availability at one manufacturer. Backends are found by name in the registry, so a new
+/// manufacturer is added with `register_backend` instead of another match arm.
+///
+/// ## Example
+/// ```
+/// use std::sync::Arc;
+/// use eggersmann_app_server_appliance_availability::backend::{register_backend, BackendAnswer, ManufacturerBackend};
+/// use eggersmann_app_server_appliance_availability::{Availability, AvailabilityError, AvailabilityRequest, AvailabilityStatus};
+///
+/// struct Fisher;
+///
+/// #[async_trait::async_trait]
+/// impl ManufacturerBackend for Fisher {
+/// 	fn name(&self) -> &str {
+/// 		"fisher"
+/// 	}
+///
+/// 	async fn availability(&self, req: &AvailabilityRequest) -> Result<BackendAnswer, AvailabilityError> {
+/// 		Ok(BackendAnswer::new(Availability::new(AvailabilityStatus::InStock, format!("Found: {}, in stock", req.model_number.clone().unwrap_or_default())).with_quantity(4)))
+/// 	}
+/// }
+///
+/// register_backend(Arc::new(Fisher));
+/// ```
+///
+#[async_trait::async_trait]
+pub trait ManufacturerBackend: Send + Sync {
+	///
+	/// Lowercase name requests use as their manufacturer, e.g. `bsh`.
+	///
+	fn name(&self) -> &str;
+
+	///
+	/// Look up the availability of the model of `req`, which has been through `get_warehouse` and `get_time`.
+	///
+	async fn availability(&self, req: &AvailabilityRequest) -> Result<BackendAnswer, AvailabilityError>;
+
+	///
+	/// Log in to the vendor portal again and save the session. Backends without a session do nothing.
+	///
+	async fn login(&self) -> Result<(), AvailabilityError> {
+		Ok(())
+	}
+
+	///
+	/// Whether a lookup adds to and removes from a cart the dealer's staff share, as the `SubZero` cart
+	/// fallback does. Lookups nobody aimed at the vendor, such as `discovery::lookup_any_manufacturer`,
+	/// leave such backends out.
+	///
+	fn mutates_cart(&self) -> bool {
+		false
+	}
+
+	///
+	/// `availability` within the timeout and deadline of `options`, which also cut off every HTTP call
+	/// made for it.
+	///
+	async fn availability_with(&self, req: &AvailabilityRequest, options: &RequestOptions) -> Result<BackendAnswer, AvailabilityError> {
+		options.run(self.availability(req)).await
+	}
+
+	///
+	/// `login` within the timeout and deadline of `options`, the browser login included.
+	///
+	async fn login_with(&self, options: &RequestOptions) -> Result<(), AvailabilityError> {
+		options.run(self.login()).await
+	}
+}
+
+#[allow(clippy::vec_init_then_push)]
+#[cfg_attr(not(any(feature = "bsh", feature = "subzero", feature = "miele", feature = "liebherr", feature = "fisher-paykel", feature = "jennair", feature = "monogram", feature = "true-residential", feature = "dacor", feature = "bertazzoni")), allow(unused_mut))]
+fn builtin_backends() -> HashMap<String, Arc<dyn ManufacturerBackend>> {
+	let mut backends: Vec<Arc<dyn ManufacturerBackend>> = Vec::new();
+	#[cfg(feature = "bsh")]
+	backends.push(Arc::new(super::bsh::BshBackend::default()));
+	#[cfg(feature = "bsh")]
+	backends.push(Arc::new(super::bsh::BshBackend::new().with_brand(super::bsh::BshBrand::Thermador)));
+	#[cfg(feature = "bsh")]
+	backends.push(Arc::new(super::bsh::BshBackend::new().with_brand(super::bsh::BshBrand::Gaggenau)));
+	#[cfg(feature = "subzero")]
+	backends.push(Arc::new(super::subzero::SubzeroBackend::default()));
+	#[cfg(feature = "subzero")]
+	backends.push(Arc::new(super::subzero::WolfBackend::default()));
+	#[cfg(feature = "miele")]
+	backends.push(Arc::new(super::miele::MieleBackend));
+	#[cfg(feature = "liebherr")]
+	backends.push(Arc::new(super::liebherr::LiebherrBackend::default()));
+	#[cfg(feature = "fisher-paykel")]
+	backends.push(Arc::new(super::fisher_paykel::FisherPaykelBackend::default()));
+	#[cfg(feature = "jennair")]
+	backends.push(Arc::new(super::jennair::JennAirBackend::default()));
+	#[cfg(feature = "monogram")]
+	backends.push(Arc::new(super::monogram::MonogramBackend::default()));
+	#[cfg(feature = "true-residential")]
+	backends.push(Arc::new(super::true_residential::TrueResidentialBackend::default()));
+	#[cfg(feature = "dacor")]
+	backends.push(Arc::new(super::dacor::DacorBackend::default()));
+	#[cfg(feature = "bertazzoni")]
+	backends.push(Arc::new(super::bertazzoni::BertazzoniBackend));
+	backends.into_iter().map(|backend| (backend.name().to_lowercase(), backend)).collect()
+}
+
+fn registry() -> &'static RwLock<HashMap<String, Arc<dyn ManufacturerBackend>>> {
+	static REGISTRY: OnceLock<RwLock<HashMap<String, Arc<dyn ManufacturerBackend>>>> = OnceLock::new();
+	REGISTRY.get_or_init(|| RwLock::new(builtin_backends()))
+}
+
+///
+/// # `register_backend`
+/// Answer requests for `backend.name()` with `backend`, replacing any backend of that name.
+///
+pub fn register_backend(backend: Arc<dyn ManufacturerBackend>) {
+	registry().write().unwrap_or_else(std::sync::PoisonError::into_inner).insert(backend.name().to_lowercase(), backend);
+}
+
+///
+/// # `unregister_backend`
+/// Stop answering requests for `manufacturer`.
+///
+pub fn unregister_backend(manufacturer: &str) {
+	registry().write().unwrap_or_else(std::sync::PoisonError::into_inner).remove(&manufacturer.to_lowercase());
+}
+
+///
+/// # `backend`
+/// The backend answering requests for `manufacturer`.
+///
+#[must_use]
+pub fn backend(manufacturer: &str) -> Option<Arc<dyn ManufacturerBackend>> {
+	registry().read().unwrap_or_else(std::sync::PoisonError::into_inner).get(&manufacturer.to_lowercase()).cloned()
+}
+
+///
+/// # `registered_backends`
+/// Names of every registered backend, sorted.
+///
+#[must_use]
+pub fn registered_backends() -> Vec<String> {
+	let mut names: Vec<String> = registry().read().unwrap_or_else(std::sync::PoisonError::into_inner).keys().cloned().collect();
+	names.sort();
+	names
+}
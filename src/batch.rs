@@ -0,0 +1,191 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use super::cache::ResultKey;
+use super::history::HistoryRecord;
+use super::timezone::business_today;
+use super::{AvailabilityRequest, AvailabilityResponse};
+
+///
+/// # `BatchLine`
+/// One line of a bill of materials.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct BatchLine {
+	pub request: AvailabilityRequest,
+	pub quantity: u32,
+}
+
+impl BatchLine {
+	///
+	/// # `BatchLine::new`
+	/// Create a line for `quantity` units of the requested model.
+	///
+	#[must_use]
+	pub const fn new(request: AvailabilityRequest, quantity: u32) -> Self {
+		Self { request, quantity }
+	}
+}
+
+///
+/// # `BatchOptions`
+/// How a batch is looked up.
+///
+/// * `aggregate_quantities` - Ask the vendor for the summed quantity of identical lines, and for the line
+///   quantities when the total is not available, instead of one unit per distinct line.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct BatchOptions {
+	pub aggregate_quantities: bool,
+}
+
+impl BatchOptions {
+	#[must_use]
+	pub const fn with_aggregate_quantities(mut self, aggregate_quantities: bool) -> Self {
+		self.aggregate_quantities = aggregate_quantities;
+		self
+	}
+}
+
+///
+/// # `BatchLineResult`
+/// The answer for one line of a batch.
+///
+/// Identical lines (same vendor, model and warehouse) share one lookup. `group_lines` and `group_quantity`
+/// describe all lines that shared it, so the quantity check can be made against the total demand rather
+/// than the line alone.
+///
+/// With `BatchOptions::aggregate_quantities`, `aggregate_feasible` tells whether the whole group can be
+/// supplied and `line_feasible` whether this line alone can; both are `None` otherwise or when the lookup
+/// failed.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct BatchLineResult {
+	/// Index of the line in the batch.
+	pub line: usize,
+	pub quantity: u32,
+	pub group_lines: usize,
+	pub group_quantity: u32,
+	pub aggregate_feasible: Option<bool>,
+	pub line_feasible: Option<bool>,
+	pub response: Option<AvailabilityResponse>,
+	pub error: Option<String>,
+}
+
+///
+/// Group requests by their result cache key, keeping first-occurrence order. Requests without a key are
+/// kept on their own.
+///
+pub(crate) fn group_requests(requests: &[AvailabilityRequest]) -> Vec<Vec<usize>> {
+	let mut groups: Vec<Vec<usize>> = Vec::new();
+	let mut by_key: HashMap<ResultKey, usize> = HashMap::new();
+	for (i, request) in requests.iter().enumerate() {
+		let Some(key) = request.cache_key() else {
+			groups.push(vec![i]);
+			continue;
+		};
+		if let Some(&group) = by_key.get(&key) {
+			groups[group].push(i);
+		} else {
+			by_key.insert(key, groups.len());
+			groups.push(vec![i]);
+		}
+	}
+	groups
+}
+
+///
+/// Copy the vendor answer of a shared lookup onto another line's request.
+///
+pub(crate) fn fan_out(answer: &AvailabilityRequest, mut line: AvailabilityRequest) -> AvailabilityRequest {
+	line.availability.clone_from(&answer.availability);
+	line.availability_detail.clone_from(&answer.availability_detail);
+	line.product.clone_from(&answer.product);
+	line.cached_at.clone_from(&answer.cached_at);
+	line.restricted.clone_from(&answer.restricted);
+	line.rejection.clone_from(&answer.rejection);
+	line.account_issue.clone_from(&answer.account_issue);
+	line.catalog_version.clone_from(&answer.catalog_version);
+	line.request_id.clone_from(&answer.request_id);
+	line
+}
+
+///
+/// # `lookup_batch`
+/// Look up every line of a batch, asking the vendor once per distinct vendor, model and warehouse and
+/// fanning the answer back out to all lines that share it.
+///
+/// ## Outputs
+/// Vec<BatchLineResult> - One result per line, in line order.
+///
+pub async fn lookup_batch(lines: Vec<BatchLine>) -> Vec<BatchLineResult> {
+	lookup_batch_with(lines, BatchOptions::default()).await
+}
+
+///
+/// # `lookup_batch_with`
+/// `lookup_batch` with explicit options.
+///
+pub async fn lookup_batch_with(lines: Vec<BatchLine>, options: BatchOptions) -> Vec<BatchLineResult> {
+	let quantities: Vec<u32> = lines.iter().map(|line| line.quantity).collect();
+	let requests: Vec<AvailabilityRequest> = lines.into_iter().map(|line| line.request.parse_manufacturer().get_warehouse().get_time()).collect();
+
+	let mut results: Vec<Option<BatchLineResult>> = vec![None; requests.len()];
+	for group in group_requests(&requests) {
+		let group_quantity: u32 = group.iter().map(|&i| quantities[i]).sum();
+		let result = |i: usize, answer: &Result<AvailabilityRequest, String>, aggregate_feasible: Option<bool>, line_feasible: Option<bool>| {
+			let (response, error) = match answer {
+				Ok(answer) => (Some(fan_out(answer, requests[i].clone()).response()), None),
+				Err(e) => (None, Some(e.clone())),
+			};
+			BatchLineResult { line: i, quantity: quantities[i], group_lines: group.len(), group_quantity, aggregate_feasible, line_feasible, response, error }
+		};
+
+		if !options.aggregate_quantities {
+			let answer = requests[group[0]].clone().into_answered().await;
+			for &i in &group {
+				results[i] = Some(result(i, &answer, None, None));
+			}
+			continue;
+		}
+
+		let aggregate = requests[group[0]].clone().with_quantity(group_quantity.max(1)).into_answered().await;
+		let aggregate_feasible = aggregate.as_ref().ok().map(|answer| feasible(&answer.response(), group_quantity));
+		if aggregate_feasible != Some(false) {
+			// the whole group fits, so does every line of it.
+			for &i in &group {
+				results[i] = Some(result(i, &aggregate, aggregate_feasible, aggregate_feasible));
+			}
+			continue;
+		}
+
+		let mut by_quantity: HashMap<u32, Result<AvailabilityRequest, String>> = HashMap::new();
+		for &i in &group {
+			if let Entry::Vacant(entry) = by_quantity.entry(quantities[i]) {
+				entry.insert(requests[i].clone().with_quantity(quantities[i].max(1)).into_answered().await);
+			}
+			let answer = &by_quantity[&quantities[i]];
+			let line_feasible = answer.as_ref().ok().map(|answer| feasible(&answer.response(), quantities[i]));
+			results[i] = Some(result(i, answer, aggregate_feasible, line_feasible));
+		}
+	}
+	results.into_iter().flatten().collect()
+}
+
+///
+/// Whether an answer means `quantity` is in stock. When the vendor confirmed the quantity in tranches, the
+/// tranches due by the day of the answer must add up to it.
+///
+fn feasible(response: &AvailabilityResponse, quantity: u32) -> bool {
+	if response.restricted.is_some() {
+		return false;
+	}
+	let answered_at = response.answered_at().unwrap_or_else(Utc::now);
+	response.availability_detail.as_ref().filter(|availability| !availability.tranches.is_empty()).map_or_else(|| HistoryRecord::from_response(response, answered_at).is_some_and(|record| record.in_stock), |availability| availability.quantity_by(business_today(response.showroom.as_deref(), answered_at)) >= quantity.max(1))
+}
@@ -0,0 +1,549 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use office::{DataType, Excel, Range};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+
+use super::backend::{BackendAnswer, ManufacturerBackend};
+use super::catalog::{normalize_model, CachedCatalog, CatalogItem, CatalogSnapshot};
+use super::{Availability, AvailabilityError, AvailabilityRequest, AvailabilityStatus, CatalogVersion, ProductInfo};
+use crate::history::parse_available_date;
+use crate::http_client;
+use crate::memory::{self, Footprint};
+use crate::schedule::{self, Workload};
+use crate::telemetry;
+
+const BERTAZZONI_DATA_PATH: &str = "/easfiles/appliances/data/";
+const BERTAZZONI_FILE_NAME: &str = "bertazzoni_availability.xlsx";
+
+///
+/// # `BertazzoniFeedConfig`
+/// Where the distributor's availability workbook is downloaded. `urls` are tried in order until one
+/// answers. A catalog older than `max_age` is downloaded again on the next lookup; after every URL failed,
+/// lookups keep the previous catalog and the download is not tried again for `retry_interval`.
+///
+/// ## Example
+/// ```
+/// use std::time::Duration;
+/// use eggersmann_app_server_appliance_availability::{set_bertazzoni_feed_config, BertazzoniFeedConfig};
+///
+/// set_bertazzoni_feed_config(BertazzoniFeedConfig::new(vec!["https://distributor.example.com/bertazzoni.xlsx".to_string()]).with_max_age(Duration::from_secs(30 * 60)));
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BertazzoniFeedConfig {
+	pub urls: Vec<String>,
+	pub max_age: Duration,
+	pub retry_interval: Duration,
+}
+
+impl BertazzoniFeedConfig {
+	#[must_use]
+	pub const fn new(urls: Vec<String>) -> Self {
+		Self { urls, max_age: Duration::from_hours(1), retry_interval: Duration::from_mins(5) }
+	}
+
+	#[must_use]
+	pub fn with_mirror(mut self, url: String) -> Self {
+		self.urls.push(url);
+		self
+	}
+
+	#[must_use]
+	pub const fn with_max_age(mut self, max_age: Duration) -> Self {
+		self.max_age = max_age;
+		self
+	}
+
+	#[must_use]
+	pub const fn with_retry_interval(mut self, retry_interval: Duration) -> Self {
+		self.retry_interval = retry_interval;
+		self
+	}
+}
+
+impl Default for BertazzoniFeedConfig {
+	fn default() -> Self {
+		Self::new(vec!["https://dealers.bertazzoni.com/us/feeds/availability.xlsx".to_string()])
+	}
+}
+
+fn config_slot() -> &'static RwLock<BertazzoniFeedConfig> {
+	static CONFIG: OnceLock<RwLock<BertazzoniFeedConfig>> = OnceLock::new();
+	CONFIG.get_or_init(|| RwLock::new(BertazzoniFeedConfig::default()))
+}
+
+///
+/// # `set_bertazzoni_feed_config`
+/// Configure where the distributor feed is downloaded and how long it is kept.
+///
+pub fn set_bertazzoni_feed_config(config: BertazzoniFeedConfig) {
+	*config_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = config;
+}
+
+///
+/// # `bertazzoni_feed_config`
+/// Where the distributor feed is downloaded and how long it is kept.
+///
+#[must_use]
+pub fn bertazzoni_feed_config() -> BertazzoniFeedConfig {
+	config_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
+
+///
+/// # `BertazzoniBackend`
+/// Bertazzoni lookups against the distributor's availability feed, registered as `bertazzoni`. The feed
+/// needs no session, so `login` refreshes the catalog instead.
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BertazzoniBackend;
+
+#[async_trait::async_trait]
+impl ManufacturerBackend for BertazzoniBackend {
+	fn name(&self) -> &'static str {
+		"bertazzoni"
+	}
+
+	async fn availability(&self, req: &AvailabilityRequest) -> Result<BackendAnswer, AvailabilityError> {
+		let (availability, product, catalog_version) = bertazzoni_lookup(req.clone()).await?;
+		Ok(BackendAnswer::new(availability).with_product(product).with_catalog_version(catalog_version))
+	}
+
+	async fn login(&self) -> Result<(), AvailabilityError> {
+		refresh_bertazzoni_catalog().await?;
+		Ok(())
+	}
+}
+
+///
+/// # Bertazzoni Lookup
+/// Gets the availability of a Bertazzoni appliance at the request's warehouse from the distributor feed,
+/// together with its description and the version of the feed it was read from. The model is matched
+/// fuzzily against the model numbers and descriptions of the warehouse's rows.
+///
+/// # Errors
+/// Returns `AvailabilityError::VendorUnavailable` if the feed cannot be loaded, and
+/// `AvailabilityError::Other` for a request without warehouse or model number.
+///
+pub async fn bertazzoni_lookup(req: AvailabilityRequest) -> Result<(Availability, Option<ProductInfo>, Option<CatalogVersion>), AvailabilityError> {
+	let today = req.business_today();
+	let warehouse = req.warehouse.clone().ok_or_else(|| AvailabilityError::Other("No warehouse found.".to_string()))?;
+	let model_number = req.model_number.clone().ok_or_else(|| AvailabilityError::Other("No model number found.".to_string()))?;
+	let catalog = bertazzoni_catalog().await.map_err(AvailabilityError::VendorUnavailable)?;
+	let version = Some(catalog.catalog_version());
+
+	let rows: Vec<&BertazzoniRow> = catalog.rows.iter().filter(|row| row.warehouse.trim().eq_ignore_ascii_case(warehouse.trim())).collect();
+	let Some(row) = best_match(&rows, &model_number) else {
+		return Ok((Availability::not_found(), None, version));
+	};
+	let (availability, product) = row_availability(row, today);
+	Ok((availability, product, version))
+}
+
+///
+/// The availability of a feed row. Stock on hand makes it in stock whatever the ETA says; otherwise the
+/// incoming quantity is expected on the ETA. A status of discontinued wins.
+///
+fn row_availability(row: &BertazzoniRow, today: NaiveDate) -> (Availability, Option<ProductInfo>) {
+	let on_hand = quantity(&row.available_qty).filter(|quantity| *quantity > 0);
+	let mut availability = Availability::stock(row.model_number.trim(), on_hand, parse_available_date(&row.eta), today);
+	if on_hand.is_none() {
+		availability.quantity = quantity(&row.incoming_qty).filter(|quantity| *quantity > 0);
+	}
+	if row.status.to_lowercase().contains("discontinued") {
+		availability.status = AvailabilityStatus::Discontinued;
+	}
+	(availability, row.product())
+}
+
+fn quantity(value: &str) -> Option<u32> {
+	value.trim().split('.').next().and_then(|whole| whole.parse::<u32>().ok())
+}
+
+///
+/// Fuzzy match the requested model number against the model numbers and descriptions of `rows`. An exact
+/// model number wins outright; `None` if nothing matches at all.
+///
+fn best_match<'a>(rows: &[&'a BertazzoniRow], model_number: &str) -> Option<&'a BertazzoniRow> {
+	let wanted = normalize_model(&urlencoding::decode(model_number).map_or_else(|_| model_number.to_string(), std::borrow::Cow::into_owned));
+	if let Some(exact) = rows.iter().find(|row| normalize_model(&row.model_number) == wanted) {
+		return Some(exact);
+	}
+	let matcher = SkimMatcherV2::default();
+	let wanted = wanted.to_lowercase();
+	let squash = |text: &str| text.to_lowercase().chars().filter(|c| !c.is_whitespace()).collect::<String>();
+	rows.iter()
+		.filter_map(|row| {
+			let score = matcher.fuzzy_match(&squash(&row.model_number), &wanted).unwrap_or(0) + matcher.fuzzy_match(&squash(&row.description), &wanted).unwrap_or(0);
+			(score > 0).then_some((score, *row))
+		})
+		.max_by_key(|(score, _)| *score)
+		.map(|(_, row)| row)
+}
+
+///
+/// # `BertazzoniCatalog`
+/// The parsed distributor feed, one row per model and warehouse. Like the Miele catalog it is immutable
+/// once built and swapped in whole; a download with the same SHA-256 digest keeps the rows and version
+/// and only moves `loaded_at`.
+///
+#[derive(Debug, Clone)]
+pub struct BertazzoniCatalog {
+	pub loaded_at: DateTime<Utc>,
+	pub version: u64,
+	/// Hex SHA-256 digest of the feed workbook.
+	pub digest: String,
+	rows: Arc<Vec<BertazzoniRow>>,
+}
+
+impl BertazzoniCatalog {
+	///
+	/// # `BertazzoniCatalog::catalog_version`
+	/// The version and digest of the feed, as reported on responses.
+	///
+	#[must_use]
+	pub fn catalog_version(&self) -> CatalogVersion {
+		CatalogVersion::new(self.version, self.digest.clone())
+	}
+}
+
+fn catalog_slot() -> &'static RwLock<Option<Arc<BertazzoniCatalog>>> {
+	static CATALOG: OnceLock<RwLock<Option<Arc<BertazzoniCatalog>>>> = OnceLock::new();
+	CATALOG.get_or_init(|| RwLock::new(None))
+}
+
+///
+/// When every URL last failed, for the retry interval.
+///
+fn last_failure() -> &'static RwLock<Option<DateTime<Utc>>> {
+	static LAST_FAILURE: OnceLock<RwLock<Option<DateTime<Utc>>>> = OnceLock::new();
+	LAST_FAILURE.get_or_init(|| RwLock::new(None))
+}
+
+///
+/// Only one refresh downloads and parses the feed at a time.
+///
+static REFRESH_LOCK: Mutex<()> = Mutex::const_new(());
+
+///
+/// # `current_bertazzoni_catalog`
+/// The catalog currently in memory, without refreshing it.
+///
+#[must_use]
+pub fn current_bertazzoni_catalog() -> Option<Arc<BertazzoniCatalog>> {
+	catalog_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
+
+fn is_fresh(catalog: &BertazzoniCatalog) -> bool {
+	(Utc::now() - catalog.loaded_at).to_std().is_ok_and(|age| age < bertazzoni_feed_config().max_age)
+}
+
+fn backing_off() -> bool {
+	let failed = *last_failure().read().unwrap_or_else(std::sync::PoisonError::into_inner);
+	failed.is_some_and(|failed| (Utc::now() - failed).to_std().is_ok_and(|since| since < bertazzoni_feed_config().retry_interval))
+}
+
+///
+/// The in-memory catalog, refreshed first if it is missing or stale.
+///
+async fn bertazzoni_catalog() -> Result<Arc<BertazzoniCatalog>, String> {
+	if let Some(catalog) = current_bertazzoni_catalog().filter(|catalog| is_fresh(catalog)) {
+		return Ok(catalog);
+	}
+
+	let _refresh = REFRESH_LOCK.lock().await;
+	// another request may have refreshed while we waited for the lock.
+	if let Some(catalog) = current_bertazzoni_catalog().filter(|catalog| is_fresh(catalog)) {
+		return Ok(catalog);
+	}
+	let previous = current_bertazzoni_catalog();
+	if let Some(previous) = previous.clone().filter(|_| backing_off() || !schedule::may_run(Workload::Bulk, None, Some("bertazzoni"), Utc::now())) {
+		return Ok(previous);
+	}
+	match refresh_bertazzoni_catalog_locked().await {
+		Ok(catalog) => Ok(catalog),
+		Err(e) => previous.ok_or(e),
+	}
+}
+
+///
+/// # Refresh Bertazzoni Catalog
+/// Download the distributor feed, parse it and atomically replace the in-memory catalog.
+///
+/// # Errors
+/// Returns an error if the feed cannot be downloaded from any URL or parsed. The previous catalog stays in
+/// place.
+///
+pub async fn refresh_bertazzoni_catalog() -> Result<Arc<BertazzoniCatalog>, String> {
+	let _refresh = REFRESH_LOCK.lock().await;
+	refresh_bertazzoni_catalog_locked().await
+}
+
+async fn refresh_bertazzoni_catalog_locked() -> Result<Arc<BertazzoniCatalog>, String> {
+	let feed = download_feed().await?;
+	let digest = format!("{:x}", Sha256::digest(&feed));
+	let current = current_bertazzoni_catalog();
+	if let Some(current) = current.as_ref().filter(|catalog| catalog.digest == digest) {
+		telemetry::counter("bertazzoni.catalog.unchanged", 1, &[]);
+		let catalog = Arc::new(BertazzoniCatalog { loaded_at: Utc::now(), ..(**current).clone() });
+		catalog_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner).replace(catalog.clone());
+		return Ok(catalog);
+	}
+
+	let file_path = save_feed(&feed)?;
+	let version = current.map_or(1, |current| current.version + 1);
+	let rows = parse_feed(&file_path)?;
+	let catalog = Arc::new(BertazzoniCatalog { loaded_at: Utc::now(), version, digest, rows: Arc::new(rows) });
+	memory::check_catalog_budget("bertazzoni", catalog.heap_bytes());
+	catalog_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner).replace(catalog.clone());
+	Ok(catalog)
+}
+
+///
+/// Download the feed workbook from the first configured URL that answers.
+///
+async fn download_feed() -> Result<Vec<u8>, String> {
+	let mut error = "No Bertazzoni feed URL is configured.".to_string();
+	for url in bertazzoni_feed_config().urls {
+		match download_feed_from(&url).await {
+			Ok(feed) => {
+				*last_failure().write().unwrap_or_else(std::sync::PoisonError::into_inner) = None;
+				return Ok(feed);
+			}
+			Err(e) => {
+				telemetry::event("bertazzoni.feed.download_failed", &[("url", url), ("error", e.clone())]);
+				error = e;
+			}
+		}
+	}
+	*last_failure().write().unwrap_or_else(std::sync::PoisonError::into_inner) = Some(Utc::now());
+	Err(error)
+}
+
+async fn download_feed_from(url: &str) -> Result<Vec<u8>, String> {
+	let response = http_client::client().get(url).send().await.map_err(|e| format!("Failed to get Bertazzoni availability feed: {e:?}"))?;
+	if !response.status().is_success() {
+		return Err(format!("Failed to get Bertazzoni availability feed: {}", response.status()));
+	}
+	http_client::bytes(response).await.map_err(|e| format!("Failed to get Bertazzoni availability feed: {e:?}"))
+}
+
+///
+/// Write the feed to a temporary file and move it into place once complete.
+///
+fn save_feed(feed: &[u8]) -> Result<PathBuf, String> {
+	let file_path = Path::new(BERTAZZONI_DATA_PATH).join(BERTAZZONI_FILE_NAME);
+	let tmp_path = file_path.with_extension("xlsx.part");
+
+	let mut file = File::create(&tmp_path).map_err(|e| format!("Failed to create Bertazzoni availability feed: {e:?}"))?;
+	file.write_all(feed).map_err(|e| format!("Failed to write Bertazzoni availability feed to file: {e:?}"))?;
+	fs::rename(&tmp_path, &file_path).map_err(|e| format!("Failed to write Bertazzoni availability feed to file: {e:?}"))?;
+	Ok(file_path)
+}
+
+///
+/// Read the first worksheet of the feed. Columns are found by their header.
+///
+fn parse_feed(file_path: &Path) -> Result<Vec<BertazzoniRow>, String> {
+	let mut excel = Excel::open(file_path).map_err(|e| format!("Failed to open Bertazzoni availability feed: {e:?}"))?;
+	let sheet = excel.sheet_names().map_err(|e| format!("Failed to read Bertazzoni availability feed: {e:?}"))?.into_iter().next().ok_or_else(|| "Bertazzoni availability feed has no worksheet.".to_string())?;
+	let range = excel.worksheet_range(&sheet).map_err(|e| format!("Failed to read Bertazzoni availability feed: {e:?}"))?;
+	parse_sheet(&range)
+}
+
+fn cell_value(cell: &DataType) -> String {
+	match cell {
+		DataType::String(s) => s.clone(),
+		DataType::Float(f) => f.to_string(),
+		DataType::Int(i) => i.to_string(),
+		DataType::Bool(b) => b.to_string(),
+		_ => String::new(),
+	}
+}
+
+fn parse_sheet(range: &Range) -> Result<Vec<BertazzoniRow>, String> {
+	parse_rows(range.rows().map(|row| row.iter().map(cell_value).collect()))
+}
+
+///
+/// Read the rows of the worksheet as text, the first being the header.
+///
+fn parse_rows(mut rows: impl Iterator<Item = Vec<String>>) -> Result<Vec<BertazzoniRow>, String> {
+	let Some(header_row) = rows.next() else { return Err("Failed to get row from Bertazzoni availability feed.".to_string()) };
+	let headers: Vec<String> = header_row.iter().map(|header| header.trim().to_lowercase()).collect();
+
+	Ok(rows
+		.map(|row| {
+			let mut item = BertazzoniRow::default();
+			for (i, value) in row.into_iter().enumerate() {
+				match headers.get(i).map_or("", String::as_str) {
+					"model" | "model number" | "item" => item.model_number = value,
+					"description" => item.description = value,
+					"category" | "product line" => item.category = value,
+					"warehouse" | "location" => item.warehouse = value,
+					"available" | "available qty" | "on hand" => item.available_qty = value,
+					"incoming" | "incoming qty" | "on order" => item.incoming_qty = value,
+					"eta" | "next available date" => item.eta = value,
+					"status" => item.status = value,
+					_ => {}
+				}
+			}
+			item
+		})
+		.filter(|item| !item.model_number.trim().is_empty())
+		.collect())
+}
+
+///
+/// One row of the distributor feed.
+///
+#[derive(Debug, Clone, Default)]
+struct BertazzoniRow {
+	model_number: String,
+	description: String,
+	category: String,
+	warehouse: String,
+	available_qty: String,
+	incoming_qty: String,
+	eta: String,
+	status: String,
+}
+
+impl BertazzoniRow {
+	fn product(&self) -> Option<ProductInfo> {
+		if self.description.trim().is_empty() {
+			return None;
+		}
+		let mut product = ProductInfo::new(self.description.trim().to_string()).with_brand("Bertazzoni".to_string());
+		if !self.category.trim().is_empty() {
+			product = product.with_category(self.category.trim().to_string());
+		}
+		Some(product)
+	}
+}
+
+impl CachedCatalog for BertazzoniCatalog {
+	fn snapshot(&self) -> CatalogSnapshot {
+		let mut warehouses: Vec<String> = self.rows.iter().map(|row| row.warehouse.trim().to_string()).filter(|warehouse| !warehouse.is_empty()).collect();
+		warehouses.sort();
+		warehouses.dedup();
+		CatalogSnapshot { manufacturer: "bertazzoni".to_string(), loaded_at: self.loaded_at, version: Some(self.version), digest: Some(self.digest.clone()), warehouses, items: self.rows.len() }
+	}
+
+	fn models(&self, warehouse: Option<&str>) -> Vec<String> {
+		let mut models: Vec<String> = self.rows.iter().filter(|row| warehouse.is_none_or(|warehouse| row.warehouse.trim().eq_ignore_ascii_case(warehouse))).map(|row| row.model_number.trim().to_string()).collect();
+		models.sort();
+		models.dedup();
+		models
+	}
+
+	fn item(&self, warehouse: &str, model_number: &str) -> Option<CatalogItem> {
+		let model_number = normalize_model(model_number);
+		let row = self.rows.iter().find(|row| row.warehouse.trim().eq_ignore_ascii_case(warehouse) && normalize_model(&row.model_number) == model_number)?;
+		let text = |value: &str| Some(value.trim().to_string()).filter(|value| !value.is_empty());
+		let mut item = CatalogItem::new("bertazzoni".to_string(), row.warehouse.trim().to_string(), row.model_number.trim().to_string());
+		item.description = text(&row.description);
+		item.category = text(&row.category);
+		item.available_qty = quantity(&row.available_qty);
+		item.next_available_qty = quantity(&row.incoming_qty);
+		item.next_available_date = parse_available_date(&row.eta);
+		Some(item)
+	}
+}
+
+impl Footprint for BertazzoniRow {
+	fn heap_bytes(&self) -> usize {
+		[&self.model_number, &self.description, &self.category, &self.warehouse, &self.available_qty, &self.incoming_qty, &self.eta, &self.status].iter().map(|field| field.heap_bytes()).sum()
+	}
+}
+
+impl Footprint for BertazzoniCatalog {
+	fn heap_bytes(&self) -> usize {
+		self.rows.heap_bytes()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const FEED: &str = include_str!("../tests/fixtures/bertazzoni/availability.tsv");
+
+	fn feed_rows() -> Vec<BertazzoniRow> {
+		parse_rows(FEED.lines().map(|line| line.split('\t').map(str::to_string).collect())).unwrap()
+	}
+
+	fn row<'a>(rows: &'a [BertazzoniRow], model_number: &str, warehouse: &str) -> &'a BertazzoniRow {
+		rows.iter().find(|row| row.model_number == model_number && row.warehouse == warehouse).unwrap()
+	}
+
+	#[test]
+	fn feed_columns_are_found_by_their_header() {
+		let rows = feed_rows();
+		// the subtotal row has no model number.
+		assert_eq!(rows.len(), 5);
+		let range = row(&rows, "PROF365RFSXT", "FL");
+		assert_eq!((range.category.as_str(), range.available_qty.as_str(), range.incoming_qty.as_str(), range.eta.as_str()), ("Professional", "0", "4", "05/12/2030"));
+		assert!(parse_rows(std::iter::empty()).is_err());
+	}
+
+	#[test]
+	fn row_availability_follows_stock_then_eta() {
+		let rows = feed_rows();
+		let today = NaiveDate::from_ymd_opt(2030, 1, 2).unwrap();
+
+		let (availability, product) = row_availability(row(&rows, "PROF365RFSXT", "TX"), today);
+		assert_eq!((availability.status, availability.quantity, availability.raw.as_str()), (AvailabilityStatus::InStock, Some(3), "Found: PROF365RFSXT, In stock: 3"));
+		let product = product.unwrap();
+		assert_eq!((product.brand.as_deref(), product.category.as_deref()), (Some("Bertazzoni"), Some("Professional")));
+
+		let (availability, _) = row_availability(row(&rows, "PROF365RFSXT", "FL"), today);
+		assert_eq!((availability.status, availability.quantity, availability.available_date), (AvailabilityStatus::Backordered, Some(4), NaiveDate::from_ymd_opt(2030, 5, 12)));
+		assert_eq!(availability.raw, "Found: PROF365RFSXT, Available: 05/12/2030");
+		let (availability, _) = row_availability(row(&rows, "MAST366RTBXT", "TX"), today);
+		assert_eq!(availability.available_date, NaiveDate::from_ymd_opt(2030, 7, 1));
+		// an ETA that has passed is in stock.
+		let (availability, _) = row_availability(row(&rows, "MAST366RTBXT", "TX"), NaiveDate::from_ymd_opt(2030, 7, 1).unwrap());
+		assert_eq!(availability.status, AvailabilityStatus::InStock);
+
+		let (availability, _) = row_availability(row(&rows, "HER30BISXT", "TX"), today);
+		assert_eq!((availability.status, availability.quantity, availability.raw.as_str()), (AvailabilityStatus::Unknown, None, "Next availability for HER30BISXT is unknown."));
+		let (availability, _) = row_availability(row(&rows, "KU30PRO1XV", "TX"), today);
+		assert_eq!(availability.status, AvailabilityStatus::Discontinued);
+	}
+
+	#[test]
+	fn exact_model_wins_over_fuzzy_matches() {
+		let rows = feed_rows();
+		let houston: Vec<&BertazzoniRow> = rows.iter().filter(|row| row.warehouse == "TX").collect();
+		assert_eq!(best_match(&houston, "prof365rfsxt").unwrap().model_number, "PROF365RFSXT");
+		assert_eq!(best_match(&houston, "MAST366RTB%20XT").unwrap().model_number, "MAST366RTBXT");
+		assert_eq!(best_match(&houston, "undermount hood").unwrap().model_number, "KU30PRO1XV");
+		assert!(best_match(&houston, "ZZZZ").is_none());
+	}
+
+	#[tokio::test]
+	async fn lookup_answers_from_the_catalog_in_memory() {
+		let catalog = BertazzoniCatalog { loaded_at: Utc::now(), version: 7, digest: "d1".to_string(), rows: Arc::new(feed_rows()) };
+		catalog_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner).replace(Arc::new(catalog));
+		let request = |showroom: &str, model_number: &str| AvailabilityRequest::new("Bertazzoni".to_string(), showroom.to_string(), model_number.to_string()).parse_manufacturer().get_warehouse();
+
+		let (availability, product, version) = bertazzoni_lookup(request("houston", "PROF365RFSXT")).await.unwrap();
+		assert_eq!((availability.status, availability.quantity), (AvailabilityStatus::InStock, Some(3)));
+		assert!(product.is_some());
+		assert_eq!(version, Some(CatalogVersion::new(7, "d1".to_string())));
+		let (availability, _, _) = bertazzoni_lookup(request("florida", "PROF365RFSXT")).await.unwrap();
+		assert_eq!(availability.status, AvailabilityStatus::Backordered);
+		// a model only stocked in another warehouse is not found.
+		let (availability, product, _) = bertazzoni_lookup(request("florida", "HER30BISXT")).await.unwrap();
+		assert_eq!((availability.status, product), (AvailabilityStatus::NotFound, None));
+	}
+}
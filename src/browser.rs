@@ -0,0 +1,155 @@
+use std::sync::{OnceLock, RwLock};
+
+use playwright::api::{Browser, BrowserContext};
+use playwright::Playwright;
+use tokio::sync::Mutex;
+
+use crate::telemetry;
+
+///
+/// # `BrowserPoolConfig`
+/// How many browser contexts are kept for reuse between browser-based logins. The browser itself is
+/// launched on the first login and kept until `close_browser_pool`.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BrowserPoolConfig {
+	/// Contexts kept once a login is done; more are closed.
+	pub max_idle_contexts: usize,
+}
+
+impl Default for BrowserPoolConfig {
+	fn default() -> Self {
+		Self { max_idle_contexts: 2 }
+	}
+}
+
+impl BrowserPoolConfig {
+	#[must_use]
+	pub const fn with_max_idle_contexts(mut self, max_idle_contexts: usize) -> Self {
+		self.max_idle_contexts = max_idle_contexts;
+		self
+	}
+}
+
+fn config_slot() -> &'static RwLock<BrowserPoolConfig> {
+	static CONFIG: OnceLock<RwLock<BrowserPoolConfig>> = OnceLock::new();
+	CONFIG.get_or_init(|| RwLock::new(BrowserPoolConfig::default()))
+}
+
+///
+/// # `set_browser_pool_config`
+/// Set how many browser contexts are kept for reuse.
+///
+pub fn set_browser_pool_config(config: BrowserPoolConfig) {
+	*config_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = config;
+}
+
+///
+/// # `browser_pool_config`
+/// The browser pool configuration in use.
+///
+#[must_use]
+pub fn browser_pool_config() -> BrowserPoolConfig {
+	*config_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+///
+/// The running driver and browser with the contexts waiting for the next login. The driver is kept so the
+/// browser it launched stays connected.
+///
+struct Pool {
+	_playwright: Playwright,
+	browser: Browser,
+	idle: Vec<BrowserContext>,
+}
+
+fn pool() -> &'static Mutex<Option<Pool>> {
+	static POOL: OnceLock<Mutex<Option<Pool>>> = OnceLock::new();
+	POOL.get_or_init(|| Mutex::new(None))
+}
+
+///
+/// Start the driver, downloading it if needed, and launch a headless Chromium.
+///
+async fn launch() -> Result<Pool, String> {
+	let playwright = Playwright::initialize().await.map_err(|e| format!("Failed to initialize playwright: {e:?}"))?;
+	playwright.prepare().map_err(|e| format!("Failed to prepare playwright: {e:?}"))?;
+	let browser = playwright.chromium().launcher().headless(true).launch().await.map_err(|e| format!("Failed to launch chromium: {e:?}"))?;
+	telemetry::counter("browser.launched", 1, &[]);
+	Ok(Pool { _playwright: playwright, browser, idle: Vec::new() })
+}
+
+///
+/// # Browser Context
+/// A context without cookies for a login: an idle one of the pool if there is one, otherwise a new one of
+/// the shared browser. The browser is launched on first use, and again if it went away. Hand the context
+/// back with `release`, or `discard` it after a failed login.
+///
+/// # Errors
+/// Returns an error if the browser cannot be launched or cannot open a context. A browser that cannot
+/// open a context is dropped, so the next login launches a new one.
+///
+pub(crate) async fn context() -> Result<BrowserContext, String> {
+	let mut slot = pool().lock().await;
+	let current = match slot.take() {
+		Some(current) if current.browser.exists() => current,
+		_ => launch().await?,
+	};
+	let running = slot.insert(current);
+	let (browser, idle) = (running.browser.clone(), running.idle.pop());
+	drop(slot);
+	if let Some(context) = idle {
+		if context.clear_cookies().await.is_ok() {
+			telemetry::counter("browser.context.reused", 1, &[]);
+			return Ok(context);
+		}
+		discard(context).await;
+	}
+	match browser.context_builder().build().await {
+		Ok(context) => Ok(context),
+		Err(e) => {
+			pool().lock().await.take();
+			Err(format!("Failed to build context: {e:?}"))
+		}
+	}
+}
+
+///
+/// Give a context back after a login. Its cookies are cleared, and it is closed instead if the pool
+/// already holds `max_idle_contexts`.
+///
+pub(crate) async fn release(context: BrowserContext) {
+	let mut slot = pool().lock().await;
+	match slot.as_mut() {
+		Some(pool) if pool.idle.len() < browser_pool_config().max_idle_contexts && context.clear_cookies().await.is_ok() => pool.idle.push(context),
+		_ => {
+			drop(slot);
+			discard(context).await;
+		}
+	}
+}
+
+///
+/// Close a context instead of keeping it, e.g. after a login that failed halfway.
+///
+pub(crate) async fn discard(context: BrowserContext) {
+	if let Err(e) = context.close().await {
+		telemetry::event("browser.context.close_failed", &[("error", format!("{e:?}"))]);
+	}
+}
+
+///
+/// # `close_browser_pool`
+/// Close the idle contexts and the browser, e.g. on shutdown. The next browser-based login launches a new
+/// browser.
+///
+pub async fn close_browser_pool() {
+	let Some(pool) = pool().lock().await.take() else { return };
+	for context in pool.idle {
+		discard(context).await;
+	}
+	if let Err(e) = pool.browser.close().await {
+		telemetry::event("browser.close_failed", &[("error", format!("{e:?}"))]);
+	}
+}
@@ -1,14 +1,199 @@
-use std::fs::File;
-use std::io::Write;
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
 
-use chrono::Local;
+use chrono::{DateTime, NaiveDate};
 use eggersmann_app_server_auth::BSHJWTTokenClaims;
-use playwright::Playwright;
+#[cfg(feature = "browser-login")]
+use playwright::api::BrowserContext;
 use reqwest::header::{self, HeaderMap, HeaderValue};
-use reqwest::{Body, Client};
+use reqwest::Body;
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::{json, Value};
 
-use super::AvailabilityRequest;
+use super::account::{self, AccountIssue};
+use super::backend::{BackendAnswer, ManufacturerBackend};
+use super::{Availability, AvailabilityError, AvailabilityRequest, OrderLine, ProductInfo, Rejection, RejectionReason, ScheduleLine, Tranche};
+#[cfg(feature = "browser-login")]
+use crate::browser;
+use crate::cookies::{SessionCookie, SessionJar};
+use crate::credentials::SecretString;
+use crate::http_client::{self, HttpClient};
+use crate::telemetry;
+use crate::tokens;
+
+///
+/// Name the BSH session token saved by `bsh_login` is kept under in the `TokenStore`.
+///
+pub const BSH_TOKEN: &str = "bsh_cookies.json";
+
+const BSH_PORTAL_URL: &str = "https://b2bportal.bsh-partner.com";
+const BSH_SERVICE_URL: &str = "https://b2bportal-cloud.bsh-partner.com/sap/opu/odata/bshb2b/SD_OM_SRV/";
+const BSH_SIMULATE_URL: &str = "https://b2bportal-cloud.bsh-partner.com/sap/opu/odata/bshb2b/SD_OM_SRV/SOSimulate";
+
+///
+/// Domain the saved BSH cookies are sent to: the portal and its `OData` service are separate hosts.
+///
+const BSH_COOKIE_DOMAIN: &str = "bsh-partner.com";
+
+///
+/// # `BshBrand`
+/// A brand ordered through the BSH B2B portal. Each is its own manufacturer (`bsh`, `thermador`,
+/// `gaggenau`) and is simulated under its own brand code and sold-to account, see `BshBrandConfig`.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum BshBrand {
+	#[default]
+	Bosch,
+	Thermador,
+	Gaggenau,
+}
+
+impl BshBrand {
+	///
+	/// The manufacturer the brand is requested and registered as.
+	///
+	#[must_use]
+	pub const fn manufacturer(self) -> &'static str {
+		match self {
+			Self::Bosch => "bsh",
+			Self::Thermador => "thermador",
+			Self::Gaggenau => "gaggenau",
+		}
+	}
+
+	///
+	/// The brand of a parsed manufacturer, `None` for a manufacturer not ordered through BSH.
+	///
+	#[must_use]
+	pub fn from_manufacturer(manufacturer: &str) -> Option<Self> {
+		[Self::Bosch, Self::Thermador, Self::Gaggenau].into_iter().find(|brand| brand.manufacturer().eq_ignore_ascii_case(manufacturer))
+	}
+
+	///
+	/// The brand `req` is for, Bosch unless it asks for another BSH brand.
+	///
+	fn of_request(req: &AvailabilityRequest) -> Self {
+		req.manufacturer.as_ref().and_then(|manufacturer| Self::from_manufacturer(manufacturer.as_str())).unwrap_or_default()
+	}
+}
+
+///
+/// # `BshBrandConfig`
+/// The portal brand code (`Brand` of the simulation and material requests) and the `SoldTo` account a
+/// brand is ordered under.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct BshBrandConfig {
+	pub brand_code: String,
+	pub sold_to: String,
+}
+
+impl BshBrandConfig {
+	#[must_use]
+	pub const fn new(brand_code: String, sold_to: String) -> Self {
+		Self { brand_code, sold_to }
+	}
+
+	fn default_for(brand: BshBrand) -> Self {
+		let brand_code = match brand {
+			BshBrand::Bosch => "A00",
+			BshBrand::Thermador => "A16",
+			BshBrand::Gaggenau => "A03",
+		};
+		Self::new(brand_code.to_string(), DEFAULT_SOLD_TO.to_string())
+	}
+}
+
+///
+/// The eggersmann sold-to account every brand is ordered under unless `set_bsh_brand_config` says otherwise.
+///
+const DEFAULT_SOLD_TO: &str = "5010011875";
+
+fn brand_configs() -> &'static RwLock<HashMap<BshBrand, BshBrandConfig>> {
+	static BRANDS: OnceLock<RwLock<HashMap<BshBrand, BshBrandConfig>>> = OnceLock::new();
+	BRANDS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+///
+/// # `set_bsh_brand_config`
+/// Set the brand code and sold-to account `brand` is simulated under.
+///
+pub fn set_bsh_brand_config(brand: BshBrand, config: BshBrandConfig) {
+	brand_configs().write().unwrap_or_else(std::sync::PoisonError::into_inner).insert(brand, config);
+}
+
+///
+/// # `bsh_brand_config`
+/// The brand code and sold-to account `brand` is simulated under.
+///
+#[must_use]
+pub fn bsh_brand_config(brand: BshBrand) -> BshBrandConfig {
+	brand_configs().read().unwrap_or_else(std::sync::PoisonError::into_inner).get(&brand).cloned().unwrap_or_else(|| BshBrandConfig::default_for(brand))
+}
+
+///
+/// # `BshBackend`
+/// BSH lookups through the sales order simulation, registered under the manufacturer of `brand`. The CSRF
+/// token fetch and the simulation go through `client`, the shared client unless one is given. Every brand
+/// shares the BSH session and account.
+///
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct BshBackend {
+	pub client: HttpClient,
+	pub brand: BshBrand,
+}
+
+impl BshBackend {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	#[must_use]
+	pub fn with_client(mut self, client: HttpClient) -> Self {
+		self.client = client;
+		self
+	}
+
+	#[must_use]
+	pub const fn with_brand(mut self, brand: BshBrand) -> Self {
+		self.brand = brand;
+		self
+	}
+}
+
+#[async_trait::async_trait]
+impl ManufacturerBackend for BshBackend {
+	fn name(&self) -> &'static str {
+		self.brand.manufacturer()
+	}
+
+	async fn availability(&self, req: &AvailabilityRequest) -> Result<BackendAnswer, AvailabilityError> {
+		let (username, password) = crate::secrets::vendor_credentials("bsh").await?;
+		if let Some(issue) = account::checked("bsh", http_client::scope(self.client.clone(), bsh_account_check(username.clone(), password.clone()))).await {
+			return Ok(BackendAnswer::account_issue(issue));
+		}
+		let (simulation, product) = http_client::scope(self.client.clone(), bsh_lookup(req.clone(), username, password)).await?;
+		let BshSimulation { availability, rejection, tranches, line } = simulation;
+		if let Some(rejection) = rejection.as_ref().filter(|rejection| rejection.reason == RejectionReason::CreditHold) {
+			account::record(AccountIssue::new("bsh".to_string(), rejection.reason.description().to_string()));
+		}
+		let today = req.business_today();
+		// the order is simulated for exactly the requested item.
+		let availability = Availability::from_text(&availability, today).with_tranches(tranches, today).with_rejection(rejection.as_ref()).with_order_line(line).with_model_number(req.model_number.as_deref().unwrap_or_default());
+		Ok(BackendAnswer::new(availability).with_product(product).with_rejection(rejection))
+	}
+
+	async fn login(&self) -> Result<(), AvailabilityError> {
+		let (username, password) = crate::secrets::vendor_credentials("bsh").await?;
+		bsh_login(username, password).await?;
+		Ok(())
+	}
+}
 
 ///
 /// # BSH Availability
@@ -21,164 +206,760 @@ use super::AvailabilityRequest;
 /// String - The availability of the BSH appliances.
 ///
 /// # Errors
-/// todo
-#[allow(clippy::too_many_lines)]
-pub async fn bsh_availability(req: AvailabilityRequest, username: String, password: String) -> Result<String, String> {
-	let token = get_bsh_token().await;
-	let token = if let Ok(token) = token {
-		token
-	} else {
-		bsh_login(username, password).await?;
-		match get_bsh_token().await {
-			Ok(token) => token,
-			Err(e) => {
-				return Ok(format!("Faild to login to BSH website: {e:?}"));
-			}
-		}
-	};
+/// Returns an error if the login fails or the portal cannot be reached or answers with something other
+/// than a simulation. A model BSH rejects is not an error.
+pub async fn bsh_availability(req: AvailabilityRequest, username: SecretString, password: SecretString) -> Result<String, String> {
+	Ok(bsh_simulate(req, username, password).await?.availability)
+}
+
+///
+/// The answer of a sales order simulation: the availability text, the rejection of the line, the
+/// tranches the quantity was confirmed in and the line as BSH simulated it.
+///
+#[derive(Debug, Clone)]
+pub struct BshSimulation {
+	availability: String,
+	rejection: Option<Rejection>,
+	tranches: Vec<Tranche>,
+	line: Option<OrderLine>,
+}
+
+///
+/// # `LineItem`
+/// One line of an order simulated with `bsh_availability_multi`: a model and the quantity of it wanted.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct LineItem {
+	pub model_number: String,
+	pub quantity: u32,
+}
+
+impl LineItem {
+	#[must_use]
+	pub fn new(model_number: &str, quantity: u32) -> Self {
+		Self { model_number: model_number.trim().to_uppercase(), quantity: quantity.max(1) }
+	}
+}
+
+///
+/// # `LineItemAvailability`
+/// The answer to one `LineItem` of a simulated order: its availability, with the tranches BSH confirmed
+/// the quantity in, and the rejection of the line if BSH refused it.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct LineItemAvailability {
+	pub model_number: String,
+	pub quantity: u32,
+	pub availability: Availability,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub rejection: Option<Rejection>,
+}
 
-	let cookies: String = {
-		let mut cookies: String = String::new();
-		for cookie in &token.bsh_cookies {
-			cookies.push_str(&cookie.name);
-			cookies.push('=');
-			cookies.push_str(&cookie.value);
-			cookies.push_str("; ");
+///
+/// # BSH Availability Multi
+/// Simulates an order of several `items` in one call to the portal, so a package of appliances costs one
+/// simulation instead of one per model. The showroom, warehouse, brand and requested delivery date are
+/// those of `req`; its model number and quantity are ignored. The net price of each line is only kept if
+/// `req` may see pricing, see `AvailabilityRequest::pricing_allowed`.
+///
+/// ## Outputs
+/// `Vec<LineItemAvailability>` - The availability of each item, in the order of `items`.
+///
+/// # Errors
+/// Returns an error if the login fails or the portal cannot be reached or answers with something other
+/// than a simulation. A model BSH rejects is not an error.
+///
+pub async fn bsh_availability_multi(req: &AvailabilityRequest, items: &[LineItem], username: SecretString, password: SecretString) -> Result<Vec<LineItemAvailability>, AvailabilityError> {
+	if items.is_empty() {
+		return Ok(Vec::new());
+	}
+	let order = bsh_simulate_order(req, items, username, password).await?;
+	let today = req.business_today();
+	let pricing_allowed = req.pricing_allowed();
+	Ok(items
+		.iter()
+		.enumerate()
+		.map(|(index, item)| {
+			let BshSimulation { availability, rejection, tranches, line } = bsh_item_simulation(&order, index);
+			let availability = Availability::from_text(&availability, today).with_tranches(tranches, today).with_rejection(rejection.as_ref()).with_order_line(line).with_model_number(&item.model_number);
+			let availability = if pricing_allowed { availability } else { availability.without_pricing() };
+			LineItemAvailability { model_number: item.model_number.clone(), quantity: item.quantity, availability, rejection }
+		})
+		.collect())
+}
+
+///
+/// # BSH Simulate
+/// Runs the sales order simulation behind `bsh_availability`, returning the rejection of the line and
+/// its confirmed tranches along with the availability text.
+///
+async fn bsh_simulate(req: AvailabilityRequest, username: SecretString, password: SecretString) -> Result<BshSimulation, AvailabilityError> {
+	let item = LineItem { model_number: req.model_number.clone().unwrap_or_default(), quantity: req.requested_quantity() };
+	let order = bsh_simulate_order(&req, &[item], username, password).await?;
+	Ok(bsh_item_simulation(&order, 0))
+}
+
+///
+/// Simulate an order of `items` and return the portal's answer.
+///
+/// A session the portal turns away is logged in again once and the simulation repeated, so stale cookies
+/// do not surface as a parse error.
+///
+async fn bsh_simulate_order(req: &AvailabilityRequest, items: &[LineItem], username: SecretString, password: SecretString) -> Result<BshOrderSimulation, AvailabilityError> {
+	let session = bsh_session(username.clone(), password.clone()).await?;
+
+	let (session, result) = match bsh_simulate_with(req, items, &session).await {
+		Err(AvailabilityError::SessionExpired) => {
+			telemetry::counter("session.refreshed", 1, &[("manufacturer", "bsh".to_string())]);
+			bsh_login(username.clone(), password.clone()).await?;
+			let session = bsh_session(username, password).await?;
+			let result = bsh_simulate_with(req, items, &session).await;
+			(session, result)
 		}
-		cookies
+		result => (session, result),
 	};
+	if result.is_ok() {
+		save_bsh_session(&session).await;
+	}
+	result
+}
+
+///
+/// SAP item number of the line at `index`, numbered in steps of ten as the portal's own orders are.
+///
+fn item_number(index: usize) -> String {
+	format!("{:06}", (index + 1) * 10)
+}
 
+///
+/// Run the sales order simulation of `items` with `session`.
+///
+/// # Errors
+/// Returns `SessionExpired` if the portal turns the session away, `VendorUnavailable` if the portal cannot
+/// be reached or answers with something other than a simulation, and `Other` if the request cannot be
+/// built.
+///
+#[allow(clippy::too_many_lines)]
+async fn bsh_simulate_with(req: &AvailabilityRequest, items: &[LineItem], session: &SessionJar) -> Result<BshOrderSimulation, AvailabilityError> {
 	//get x_csrf_token
-	let client = Client::new();
+	let client = http_client::client();
 	let mut headers = HeaderMap::new();
 
 	// Set cookie in headers
-	match HeaderValue::from_str(&cookies) {
-		Ok(cookie) => headers.insert(header::COOKIE, cookie),
-		Err(e) => return Ok(format!("Failed to create cookie header: {e:?}")),
-	};
+	session.apply(&mut headers, BSH_SERVICE_URL);
 
 	// Set x-csrf-token in headers
 	match HeaderValue::from_str(" Fetch") {
 		Ok(x_csrf_token) => headers.insert("x-csrf-token", x_csrf_token),
-		Err(e) => return Ok(format!("Failed to create x_csrf_token header: {e:?}")),
+		Err(e) => return Err(AvailabilityError::Other(format!("Failed to create x_csrf_token header: {e:?}"))),
 	};
-	let today = Local::now().format("%Y%m%d").to_string();
+	let requested_date = req.requested_delivery_date().format("%Y%m%d").to_string();
 	let x_csrf_token: String = {
-		let resp = match client.get("https://b2bportal-cloud.bsh-partner.com/sap/opu/odata/bshb2b/SD_OM_SRV/").headers(headers).send().await {
+		let resp = match client.get(BSH_SERVICE_URL).headers(headers).send().await {
 			Ok(resp) => resp,
-			Err(e) => return Ok(format!("Failed to get x_csrf_token: {e:?}")),
+			Err(e) => return Err(AvailabilityError::VendorUnavailable(format!("Failed to get x_csrf_token: {e:?}"))),
 		};
-		resp.headers().get("x-csrf-token").map_or_else(
-			|| Ok(format!("Failed to get x_csrf_token")),
-			|x_csrf_token| match x_csrf_token.to_str() {
-				Ok(x_csrf_token) => Ok::<String, String>(x_csrf_token.to_string()),
-				Err(e) => Ok(format!("Failed to convert x_csrf_token to string: {e:?}")),
-			},
-		)?
+		session.capture(&resp);
+		if http_client::auth_rejected(&resp) {
+			return Err(AvailabilityError::SessionExpired);
+		}
+		let Some(x_csrf_token) = resp.headers().get("x-csrf-token") else { return Err(AvailabilityError::VendorUnavailable("Failed to get x_csrf_token".to_string())) };
+		x_csrf_token.to_str().map_err(|e| AvailabilityError::VendorUnavailable(format!("Failed to convert x_csrf_token to string: {e:?}")))?.to_string()
 	};
 
 	//get availability
+	let brand = bsh_brand_config(BshBrand::of_request(req));
 	let data = json!({
 		"Country": "US",
-		"Brand": "A00",
+		"Brand": brand.brand_code,
 		"Submodule": "APPS",
 		"DocCategory": "ASTD",
 		"PurchNo": "",
-		"ReqDateH": today,
+		"ReqDateH": requested_date,
 		"ComplDlv": "",
-		"SoldTo": "5010011875",
-		"Language": "EN",
+		"SoldTo": brand.sold_to,
+		"Language": request_language(req),
 		"ShipTo": req.warehouse.clone(),
-		"SOSimulateToItem": [
-			{
-				"Submodule": "APPS",
-				"Material": req.model_number.clone(),
-				"ReqQty": "1",
-				"ReqDateI": today
-			}
-		]
+		"SOSimulateToItem": items.iter().enumerate().map(|(index, item)| json!({
+			"ItmNumber": item_number(index),
+			"Submodule": "APPS",
+			"Material": item.model_number,
+			"ReqQty": item.quantity.to_string(),
+			"ReqDateI": requested_date
+		})).collect::<Vec<_>>()
 	})
 	.to_string();
 
 	headers = HeaderMap::new();
 
 	// Set cookie in headers
-	match HeaderValue::from_str(&cookies.clone()) {
-		Ok(cookie) => headers.insert(header::COOKIE, cookie),
-		Err(e) => return Ok(format!("Failed to create cookie header: {e:?}")),
-	};
+	session.apply(&mut headers, BSH_SIMULATE_URL);
 
 	// Set x-csrf-token in headers
 	match HeaderValue::from_str(&x_csrf_token) {
 		Ok(x_csrf_token) => headers.insert("x-csrf-token", x_csrf_token),
-		Err(e) => return Ok(format!("Failed to create x_csrf_token header: {e:?}")),
+		Err(e) => return Err(AvailabilityError::Other(format!("Failed to create x_csrf_token header: {e:?}"))),
 	};
 
 	// Set content-type in headers
 	match HeaderValue::from_str("application/json") {
 		Ok(content_type) => headers.insert(header::CONTENT_TYPE, content_type),
-		Err(e) => return Ok(format!("Failed to create content-type header: {e:?}")),
+		Err(e) => return Err(AvailabilityError::Other(format!("Failed to create content-type header: {e:?}"))),
 	};
 
 	// Set accept in headers
 	match HeaderValue::from_str("application/json") {
 		Ok(accept) => headers.insert(header::ACCEPT, accept),
-		Err(e) => return Ok(format!("Failed to create accept header: {e:?}")),
+		Err(e) => return Err(AvailabilityError::Other(format!("Failed to create accept header: {e:?}"))),
 	};
 
 	// Set data in headers
 	match HeaderValue::from_str(&data) {
 		Ok(data) => headers.insert("data", data),
-		Err(e) => return Ok(format!("Failed to create data header: {e:?}")),
+		Err(e) => return Err(AvailabilityError::Other(format!("Failed to create data header: {e:?}"))),
 	};
 
-	let response = match client.post("https://b2bportal-cloud.bsh-partner.com/sap/opu/odata/bshb2b/SD_OM_SRV/SOSimulate").headers(headers).body(Body::from(data)).send().await {
+	let response = match client.post(BSH_SIMULATE_URL).headers(headers).body(Body::from(data)).send().await {
 		Ok(response) => response,
-		Err(e) => return Ok(format!("Failed to get availability response: {e:?}")),
+		Err(e) => return Err(AvailabilityError::VendorUnavailable(format!("Failed to get availability response: {e:?}"))),
 	};
-	let response_text = match response.text().await {
-		Ok(response_text) => response_text,
-		Err(e) => return Ok(format!("Failed to get availability response text: {e:?}")),
-	};
-	let response_data: serde_json::Value = match serde_json::from_str(&response_text) {
-		Ok(response_data) => response_data,
-		Err(e) => return Ok(format!("Failed to parse availability response text: {e:?}")),
-	};
-	let mut availability = response_data["d"]["SOSimulateToItem"]["results"][0]["AvailBackorder"].to_string();
+	session.capture(&response);
+	if http_client::auth_rejected(&response) {
+		return Err(AvailabilityError::SessionExpired);
+	}
+	let response_text = http_client::text(response).await?;
+	parse_simulation(&response_text)
+}
 
-	if availability.contains("\\n") || availability.contains('\n') {
-		availability = availability.replace("\\n", "");
-		availability = availability.replace('\n', "");
+///
+/// Parse the answer of `SOSimulate`.
+///
+/// # Errors
+/// Returns `SessionExpired` for the portal's login page, and `VendorUnavailable` for an `OData` error
+/// payload or anything else that is not a simulation, so a failed simulation is not read as an unknown
+/// model.
+///
+fn parse_simulation(response_text: &str) -> Result<BshOrderSimulation, AvailabilityError> {
+	match serde_json::from_str::<BshAnswer>(response_text) {
+		Ok(BshAnswer::Simulation { d }) => Ok(d),
+		Ok(BshAnswer::Error { error }) => Err(AvailabilityError::VendorUnavailable(format!("BSH simulation failed: {} {}", error.code, error.message.value).trim_end().to_string())),
+		Err(_) if is_login_page(response_text) => Err(AvailabilityError::SessionExpired),
+		Err(e) => Err(AvailabilityError::VendorUnavailable(format!("Failed to parse availability response text: {e:?}"))),
 	}
+}
+
+///
+/// # BSH Order Simulation
+/// The `d` of a `SOSimulate` answer. Every part is optional, so an answer missing one reads as an order
+/// without it rather than failing.
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+struct BshOrderSimulation {
+	#[serde(rename = "SOSimulateToItem", default)]
+	items: Option<BshResults<BshSimulatedItem>>,
+	#[serde(rename = "SOSimulateToSchedule", default)]
+	schedule: Option<BshResults<BshScheduleLine>>,
+	#[serde(rename = "SOSimulateToReturn", default)]
+	messages: Option<BshResults<BshMessage>>,
+}
+
+///
+/// A `SOSimulate` answer: the simulation in `d`, or the `error` of the SAP gateway.
+///
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum BshAnswer {
+	Simulation { d: BshOrderSimulation },
+	Error { error: BshGatewayError },
+}
+
+#[derive(Debug, Deserialize)]
+struct BshGatewayError {
+	#[serde(default)]
+	code: String,
+	#[serde(default)]
+	message: BshGatewayMessage,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct BshGatewayMessage {
+	#[serde(default)]
+	value: String,
+}
+
+///
+/// The `results` of an `OData` navigation property, `None` when the portal deferred it instead of
+/// expanding it.
+///
+#[derive(Debug, Clone, Deserialize)]
+struct BshResults<T> {
+	#[serde(default = "Option::default")]
+	results: Option<Vec<T>>,
+}
 
-	if availability.contains("\\r") || availability.contains('\r') {
-		availability = availability.replace("\\r", "");
-		availability = availability.replace('\r', "");
+impl<T> BshResults<T> {
+	fn rows(&self) -> Option<&[T]> {
+		self.results.as_deref()
 	}
+}
+
+///
+/// One item of the simulated order. SAP sends numbers as text, e.g. `"2.000"`.
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+struct BshSimulatedItem {
+	#[serde(rename = "ItmNumber", default, deserialize_with = "sap_text")]
+	item_number: Option<String>,
+	#[serde(rename = "Material", default, deserialize_with = "sap_text")]
+	material: Option<String>,
+	#[serde(rename = "ReqQty", default, deserialize_with = "sap_text")]
+	requested_quantity: Option<String>,
+	#[serde(rename = "ConfirQty", default, deserialize_with = "sap_text")]
+	confirmed_quantity: Option<String>,
+	#[serde(rename = "AvailBackorder", default, deserialize_with = "sap_text")]
+	availability: Option<String>,
+	#[serde(rename = "ReasonRej", default, deserialize_with = "sap_text")]
+	rejection_code: Option<String>,
+	#[serde(rename = "ReasonRejText", default, deserialize_with = "sap_text")]
+	rejection_text: Option<String>,
+	#[serde(rename = "Plant", default, deserialize_with = "sap_text")]
+	plant: Option<String>,
+	#[serde(rename = "NetPrice", default, deserialize_with = "sap_text")]
+	net_price: Option<String>,
+	#[serde(rename = "Currency", default, deserialize_with = "sap_text")]
+	currency: Option<String>,
+	#[serde(rename = "DlvDate", default, deserialize_with = "sap_text")]
+	delivery_date: Option<String>,
+	#[serde(rename = "ItemToSchedule", default)]
+	schedule: Option<BshResults<BshScheduleLine>>,
+}
+
+///
+/// One schedule line of the simulated order, naming its item when the lines of all items come together.
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+struct BshScheduleLine {
+	#[serde(rename = "ItmNumber", default, deserialize_with = "sap_text")]
+	item_number: Option<String>,
+	#[serde(rename = "ReqQty", default, deserialize_with = "sap_text")]
+	requested_quantity: Option<String>,
+	#[serde(rename = "ConfirQty", default, deserialize_with = "sap_text")]
+	confirmed_quantity: Option<String>,
+	#[serde(rename = "ConfQty", default, deserialize_with = "sap_text")]
+	confirmed_quantity_short: Option<String>,
+	#[serde(rename = "DlvDate", default, deserialize_with = "sap_text")]
+	delivery_date: Option<String>,
+	#[serde(rename = "ReqDate", default, deserialize_with = "sap_text")]
+	requested_date: Option<String>,
+}
+
+///
+/// One message of the simulation log.
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+struct BshMessage {
+	#[serde(rename = "ItmNumber", default, deserialize_with = "sap_text")]
+	item_number: Option<String>,
+	#[serde(rename = "Type", default, deserialize_with = "sap_text")]
+	kind: Option<String>,
+	#[serde(rename = "Id", default, deserialize_with = "sap_text")]
+	id: Option<String>,
+	#[serde(rename = "Number", default, deserialize_with = "sap_text")]
+	number: Option<String>,
+	#[serde(rename = "Message", default, deserialize_with = "sap_text")]
+	message: Option<String>,
+}
+
+///
+/// A scalar of the SAP gateway as text, whether it was sent as a string or a number. Anything else reads
+/// as missing.
+///
+fn sap_text<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+	Ok(match Value::deserialize(deserializer)? {
+		Value::String(text) => Some(text),
+		Value::Number(number) => Some(number.to_string()),
+		_ => None,
+	})
+}
 
-	if availability.contains(' ') {
-		availability = availability.replace(' ', "");
+///
+/// The first of `values` that is not blank, trimmed.
+///
+fn sap_field<'a>(values: impl IntoIterator<Item = Option<&'a str>>) -> Option<&'a str> {
+	values.into_iter().flatten().map(str::trim).find(|value| !value.is_empty())
+}
+
+///
+/// A quantity of the SAP gateway, e.g. `2.000`, rounded to whole units.
+///
+fn sap_quantity(value: &str) -> Option<u32> {
+	let quantity = value.trim().parse::<f64>().ok()?;
+	#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+	Some(quantity.round().max(0.0) as u32)
+}
+
+impl BshScheduleLine {
+	fn confirmed_quantity(&self) -> Option<u32> {
+		sap_field([self.confirmed_quantity.as_deref(), self.confirmed_quantity_short.as_deref()]).and_then(sap_quantity)
 	}
 
+	fn date(&self) -> Option<NaiveDate> {
+		sap_field([self.delivery_date.as_deref(), self.requested_date.as_deref()]).and_then(sap_date)
+	}
+
+	///
+	/// The tranche the line confirms, `None` for a line nothing was confirmed on.
+	///
+	fn tranche(&self) -> Option<Tranche> {
+		let quantity = self.confirmed_quantity().filter(|quantity| *quantity > 0)?;
+		Some(Tranche::new(quantity, self.date()?))
+	}
+
+	fn schedule_line(&self) -> ScheduleLine {
+		ScheduleLine { requested_quantity: sap_field([self.requested_quantity.as_deref()]).and_then(sap_quantity), confirmed_quantity: self.confirmed_quantity(), date: self.date() }
+	}
+}
+
+impl BshSimulatedItem {
+	///
+	/// The line as it is kept on the `Availability`, with the schedule lines `schedule` of the item. An item
+	/// without a delivery date of its own is delivered with its last confirmed schedule line.
+	///
+	fn order_line(&self, schedule: &[&BshScheduleLine]) -> OrderLine {
+		let text = |value: Option<&str>| sap_field([value]).map(str::to_string);
+		let schedule: Vec<ScheduleLine> = schedule.iter().map(|line| line.schedule_line()).collect();
+		let last_confirmed = schedule.iter().filter(|line| line.confirmed_quantity.is_some_and(|quantity| quantity > 0)).filter_map(|line| line.date).max();
+		OrderLine {
+			item_number: text(self.item_number.as_deref()),
+			material: text(self.material.as_deref()),
+			requested_quantity: sap_field([self.requested_quantity.as_deref()]).and_then(sap_quantity),
+			confirmed_quantity: sap_field([self.confirmed_quantity.as_deref()]).and_then(sap_quantity),
+			plant: text(self.plant.as_deref()),
+			net_price: text(self.net_price.as_deref()),
+			currency: text(self.currency.as_deref()),
+			delivery_date: sap_field([self.delivery_date.as_deref()]).and_then(sap_date).or(last_confirmed),
+			schedule,
+		}
+	}
+}
+
+///
+/// # BSH Item Simulation
+/// The availability text, rejection, tranches and order line of the line at `index` of a simulated order.
+/// The line is matched on its item number, and on its position if the portal left the number out. A model
+/// BSH does not know is answered with `V1_NOT_FOUND`.
+///
+fn bsh_item_simulation(order: &BshOrderSimulation, index: usize) -> BshSimulation {
+	let number = item_number(index);
+	let items = order.items.as_ref().and_then(BshResults::rows).unwrap_or_default();
+	let item = items.iter().find(|item| item.item_number.as_deref().is_some_and(|found| same_item(found, &number))).or_else(|| items.get(index));
+	let schedule: Vec<&BshScheduleLine> = order.schedule.as_ref().and_then(BshResults::rows).or_else(|| item?.schedule.as_ref()?.rows()).into_iter().flatten().filter(|line| belongs_to(line.item_number.as_deref(), &number)).collect();
+	BshSimulation {
+		availability: availability_text(item.and_then(|item| item.availability.as_deref())),
+		rejection: bsh_rejection(order, item, &number),
+		tranches: schedule.iter().filter_map(|line| line.tranche()).collect(),
+		line: item.map(|item| item.order_line(&schedule)),
+	}
+}
+
+///
+/// The `AvailBackorder` text of an item as `bsh_availability` has always answered it: quoted, without
+/// line breaks or spaces. A missing or too short text is answered with `V1_NOT_FOUND`.
+///
+fn availability_text(text: Option<&str>) -> String {
+	let availability = text.map_or_else(|| "null".to_string(), |text| Value::String(text.to_string()).to_string()).replace("\\n", "").replace("\\r", "").replace(' ', "");
 	if availability.len() < 10 {
-		availability = "Model availablility not found.".to_string();
+		return "Model availablility not found.".to_string();
+	}
+	availability
+}
+
+///
+/// Whether two SAP item numbers name the same line, `10` and `000010` being the same.
+///
+fn same_item(found: &str, number: &str) -> bool {
+	found.trim().trim_start_matches('0') == number.trim_start_matches('0')
+}
+
+///
+/// Whether a message or schedule line naming the item `found` belongs to the line `number`. One that names
+/// no line belongs to every line.
+///
+fn belongs_to(found: Option<&str>, number: &str) -> bool {
+	sap_field([found]).is_none_or(|found| same_item(found, number))
+}
+
+///
+/// Whether a body that should have been JSON is the portal's login form, served in place of the answer
+/// to an expired session.
+///
+fn is_login_page(body: &str) -> bool {
+	let body = body.trim_start().to_lowercase();
+	body.starts_with('<') && ["type=\"password\"", "j_username", "logon", "sign in"].iter().any(|marker| body.contains(marker))
+}
+
+///
+/// # BSH Rejection
+/// The rejection of the simulated line `item`: its rejection reason if SAP set one, otherwise the first
+/// error message of the simulation for the line whose code or text has a known meaning.
+///
+fn bsh_rejection(order: &BshOrderSimulation, item: Option<&BshSimulatedItem>, number: &str) -> Option<Rejection> {
+	if let Some((code, item)) = item.and_then(|item| Some((sap_field([item.rejection_code.as_deref()])?, item))) {
+		return Some(Rejection::new(code.to_string(), item.rejection_text.clone()));
+	}
+	order.messages.as_ref()?.rows()?.iter().filter(|message| matches!(message.kind.as_deref(), Some("E" | "A")) && belongs_to(message.item_number.as_deref(), number)).find_map(|message| {
+		let code = format!("{}/{}", message.id.as_deref().unwrap_or_default().trim(), message.number.as_deref().unwrap_or_default().trim());
+		let rejection = Rejection::new(code, message.message.clone());
+		(rejection.reason != RejectionReason::Unknown).then_some(rejection)
+	})
+}
+
+///
+/// A date of the SAP gateway, either `YYYYMMDD` or an `OData` `/Date(milliseconds)/`.
+///
+fn sap_date(value: &str) -> Option<NaiveDate> {
+	if let Some(millis) = value.strip_prefix("/Date(").and_then(|rest| rest.strip_suffix(")/")) {
+		let millis = millis.split(['+', '-']).next()?.parse::<i64>().ok()?;
+		return DateTime::from_timestamp_millis(millis).map(|at| at.date_naive());
+	}
+	NaiveDate::parse_from_str(value, "%Y%m%d").ok()
+}
+
+///
+/// # BSH Lookup
+/// Gets the availability of a BSH appliance together with its material description and the rejection of the
+/// line, if BSH refused it.
+///
+/// # Errors
+/// Returns `AvailabilityError::VendorUnavailable` if the portal cannot be reached or its answer cannot be
+/// read, and `AvailabilityError::Other` for a failed login.
+///
+pub async fn bsh_lookup(req: AvailabilityRequest, username: SecretString, password: SecretString) -> Result<(BshSimulation, Option<ProductInfo>), AvailabilityError> {
+	let model_number = req.model_number.clone();
+	let language = req.description_language().and_then(|language| sap_language(&language)).unwrap_or_else(|| request_language(&req));
+	let brand = BshBrand::of_request(&req);
+	let simulation = bsh_simulate(req, username.clone(), password.clone()).await?;
+	let product = match model_number {
+		Some(model_number) => bsh_product(&model_number, brand, &language, username, password).await,
+		None => None,
+	};
+	Ok((simulation, product))
+}
+
+///
+/// The material description in `language`, or in the configured default language when BSH has none in
+/// `language`.
+///
+async fn bsh_product(model_number: &str, brand: BshBrand, language: &str, username: SecretString, password: SecretString) -> Option<ProductInfo> {
+	let localized = fetch_bsh_material(model_number, brand, language, username.clone(), password.clone()).await.ok().and_then(|material| material.product());
+	if let Some(product) = localized {
+		return Some(product.with_language(language));
+	}
+	let default_language = bsh_language();
+	if default_language.eq_ignore_ascii_case(language) {
+		return None;
+	}
+	telemetry::event("product.description.fallback", &[("manufacturer", brand.manufacturer().to_string()), ("model_number", model_number.to_string()), ("language", language.to_lowercase())]);
+	fetch_bsh_material(model_number, brand, &default_language, username, password).await.ok().and_then(|material| material.product()).map(|product| product.with_language(&default_language))
+}
+
+///
+/// Banner phrases of the BSH portal start page that mean the account cannot order.
+///
+const ACCOUNT_BLOCK_PHRASES: [&str; 4] = ["credit hold", "account is blocked", "account has been blocked", "order block"];
+
+///
+/// # BSH Account Check
+/// Looks for an account-level block banner, such as a credit hold, on the BSH portal start page.
+///
+/// # Errors
+/// Returns an error if the session or the start page is unavailable.
+///
+pub async fn bsh_account_check(username: SecretString, password: SecretString) -> Result<Option<AccountIssue>, String> {
+	let session = bsh_session(username, password).await?;
+	let mut headers = HeaderMap::new();
+	session.apply(&mut headers, BSH_PORTAL_URL);
+	let response = http_client::client().get(BSH_PORTAL_URL).headers(headers).send().await.map_err(|e| format!("Failed to get BSH portal start page: {e:?}"))?;
+	session.capture(&response);
+	let page = http_client::text(response).await.map_err(|e| format!("Failed to read BSH portal start page: {e:?}"))?;
+	save_bsh_session(&session).await;
+	Ok(account::find_block(&page, &ACCOUNT_BLOCK_PHRASES).map(|reason| AccountIssue::new("bsh".to_string(), reason.to_string())))
+}
+
+///
+/// # BSH Material
+/// Material master data for a single BSH model.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct BshMaterial {
+	pub material: String,
+	pub description: Option<String>,
+	pub ean: Option<String>,
+	pub list_price: Option<f64>,
+	pub currency: Option<String>,
+	pub status: Option<String>,
+}
+
+///
+/// # BSH Material Lookup
+/// Gets the material master data (description, EAN, list price and status) for a model from the BSH portal.
+///
+/// ## Inputs
+/// * `model`: &str - The BSH material number, e.g. `HBLP651RUC`.
+///
+/// ## Outputs
+/// `BshMaterial` - The material master data.
+///
+/// # Errors
+/// Returns an error if the portal cannot be reached or the material is unknown.
+pub async fn bsh_material(model: &str, username: SecretString, password: SecretString) -> Result<BshMaterial, String> {
+	fetch_bsh_material(model, BshBrand::Bosch, &bsh_language(), username, password).await
+}
+
+async fn fetch_bsh_material(model: &str, brand: BshBrand, language: &str, username: SecretString, password: SecretString) -> Result<BshMaterial, String> {
+	let session = bsh_session(username, password).await?;
+
+	let material = model.trim().to_uppercase();
+	let url = format!("{BSH_SERVICE_URL}MaterialSet(Country='US',Brand='{}',Material='{}')?$format=json&sap-language={language}", bsh_brand_config(brand).brand_code, urlencoding::encode(&material));
+	let mut headers = HeaderMap::new();
+	session.apply(&mut headers, &url);
+	headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+
+	let response = http_client::client().get(url).headers(headers).send().await.map_err(|e| format!("Failed to get material response: {e:?}"))?;
+	session.capture(&response);
+	save_bsh_session(&session).await;
+	if !response.status().is_success() {
+		return Err(format!("BSH material {material} not found: {}", response.status()));
+	}
+	let response_data: Value = http_client::json(response).await.map_err(|e| format!("Failed to parse material response: {e:?}"))?;
+	Ok(parse_bsh_material(&material, &response_data["d"]))
+}
+
+impl BshMaterial {
+	///
+	/// Product metadata for this material, if it has a description.
+	///
+	#[must_use]
+	pub fn product(&self) -> Option<ProductInfo> {
+		self.description.clone().map(|name| ProductInfo::new(name).with_brand("Bosch".to_string()))
+	}
+}
+
+fn parse_bsh_material(material: &str, data: &Value) -> BshMaterial {
+	let text = |key: &str| data[key].as_str().map(str::trim).filter(|value| !value.is_empty()).map(str::to_string);
+	BshMaterial {
+		material: text("Material").unwrap_or_else(|| material.to_string()),
+		description: text("MaterialText").or_else(|| text("Description")),
+		ean: text("Ean").or_else(|| text("EanUpc")),
+		list_price: text("ListPrice").and_then(|price| price.parse().ok()),
+		currency: text("Currency"),
+		status: text("MaterialStatusText").or_else(|| text("MaterialStatus")),
+	}
+}
+
+///
+/// Language BSH answers in when neither the configuration nor the user asks for another one.
+///
+const DEFAULT_BSH_LANGUAGE: &str = "EN";
+
+fn language_slot() -> &'static RwLock<String> {
+	static LANGUAGE: OnceLock<RwLock<String>> = OnceLock::new();
+	LANGUAGE.get_or_init(|| RwLock::new(DEFAULT_BSH_LANGUAGE.to_string()))
+}
+
+///
+/// # `set_bsh_language`
+/// Set the default language of text fields (statuses, rejection reasons, descriptions) in BSH responses.
+/// Accepts a two-letter code or a locale such as `de-DE`.
+///
+pub fn set_bsh_language(language: &str) {
+	if let Some(language) = sap_language(language) {
+		*language_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = language;
+	}
+}
+
+///
+/// # `bsh_language`
+/// The default language of BSH responses.
+///
+#[must_use]
+pub fn bsh_language() -> String {
+	language_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
+
+///
+/// The language to request for `req`: the user's locale when present, the configured default otherwise.
+///
+fn request_language(req: &AvailabilityRequest) -> String {
+	req.user.as_ref().and_then(|user| user.preferred_language.as_deref()).and_then(sap_language).unwrap_or_else(bsh_language)
+}
+
+///
+/// SAP language key of a locale, e.g. `de-DE` becomes `DE`.
+///
+fn sap_language(locale: &str) -> Option<String> {
+	let language = locale.trim().split(['-', '_']).next()?;
+	(language.len() == 2 && language.chars().all(|c| c.is_ascii_alphabetic())).then(|| language.to_uppercase())
+}
+
+///
+/// # Get BSH Session
+/// The session of the stored BSH token, logging in first if there is none.
+///
+async fn bsh_session(username: SecretString, password: SecretString) -> Result<SessionJar, String> {
+	let token = if let Ok(token) = get_bsh_token().await {
+		token
 	} else {
-		availability = availability.to_string();
+		bsh_login(username, password).await?;
+		get_bsh_token().await.map_err(|e| format!("Faild to login to BSH website: {e:?}"))?
+	};
+	SessionJar::new(BSH_PORTAL_URL, BSH_COOKIE_DOMAIN, token.bsh_cookies.iter().map(|cookie| SessionCookie::saved(&cookie.name, &cookie.value, cookie.domain.as_deref(), cookie.path.as_deref(), cookie.expires)))
+}
+
+///
+/// # Save BSH Session
+/// Save `session` as the BSH token if the portal refreshed it, so the next lookup starts from the
+/// refreshed cookies. A session that cannot be saved is kept for this lookup only.
+///
+#[cfg(feature = "browser-login")]
+async fn save_bsh_session(session: &SessionJar) {
+	if !session.take_changed() {
+		return;
+	}
+	let saved = match BSHJWTTokenClaims::encode(session.token_cookies()).await {
+		Ok(token) => tokens::save(BSH_TOKEN, &json!({ "token": token }).to_string()).await.map_err(|e| format!("{e:?}")),
+		Err(e) => Err(format!("{e:?}")),
+	};
+	match saved {
+		Ok(()) => telemetry::counter("session.saved", 1, &[("manufacturer", "bsh".to_string())]),
+		Err(e) => telemetry::event("session.save_failed", &[("manufacturer", "bsh".to_string()), ("error", e)]),
 	}
+}
 
-	Ok(availability)
+///
+/// # Save BSH Session
+/// Without the `browser-login` feature a BSH token cannot be written, so a refreshed session is kept for
+/// the lookup only.
+///
+#[cfg(not(feature = "browser-login"))]
+#[allow(clippy::unused_async)]
+async fn save_bsh_session(session: &SessionJar) {
+	session.take_changed();
 }
 
 ///
 /// Gets the `BSHJWTToken` from the the server storage.
 ///
 async fn get_bsh_token() -> Result<BSHJWTTokenClaims, String> {
-	let file = match File::open("/easfiles/appliances/cookies/bsh_cookies.json") {
-		Ok(file) => file,
+	let file = match tokens::load(BSH_TOKEN).await {
+		Ok(Some(file)) => file,
+		Ok(None) => return Err("No BSH token has been saved.".to_string()),
 		Err(e) => return Err(format!("Failed to open bsh_cookies.json: {e:?}")),
 	};
-	let file: Value = match serde_json::from_reader(file) {
+	let file: Value = match serde_json::from_str(&file) {
 		Ok(file) => file,
 		Err(e) => return Err(format!("Failed to parse bsh_cookies.json: {e:?}")),
 	};
@@ -188,35 +969,111 @@ async fn get_bsh_token() -> Result<BSHJWTTokenClaims, String> {
 
 ///
 /// # Login to BSH System
+/// Logs in with a context of the shared browser pool, so a repeated login does not launch a new browser.
 ///
 /// ## Outputs
 /// bool - True if login was successful, false otherwise.
 ///
 /// # Errors
 /// todo
-pub async fn bsh_login(username: String, password: String) -> Result<bool, String> {
-	let playwright = Playwright::initialize().await.map_err(|e| format!("Failed to initialize playwright: {e:?}"))?;
-	playwright.prepare().map_err(|e| format!("Failed to prepare playwright: {e:?}"))?;
+#[cfg(feature = "browser-login")]
+pub async fn bsh_login(username: SecretString, password: SecretString) -> Result<bool, String> {
+	telemetry::counter("vendor.login", 1, &[("manufacturer", "bsh".to_string())]);
+	let context = browser::context().await?;
+	let logged_in = bsh_login_with(&context, username, password).await;
+	if logged_in.is_ok() {
+		browser::release(context).await;
+	} else {
+		browser::discard(context).await;
+	}
+	logged_in
+}
 
-	let chromium = playwright.chromium();
-	let browser = chromium.launcher().headless(true).launch().await.map_err(|e| format!("Failed to launch chromium: {e:?}"))?;
-	let context = browser.context_builder().build().await.map_err(|e| format!("Failed to build context: {e:?}"))?;
+///
+/// Log in to the portal in a page of `context` and save the session cookies.
+///
+#[cfg(feature = "browser-login")]
+async fn bsh_login_with(context: &BrowserContext, username: SecretString, password: SecretString) -> Result<bool, String> {
 	let page = context.new_page().await.map_err(|e| format!("Failed to create new page: {e:?}"))?;
 
-	page.goto_builder("https://b2bportal.bsh-partner.com").goto().await.map_err(|e| format!("Failed to go to BSH website: {e:?}"))?;
-	page.fill_builder("input#username", &username).fill().await.map_err(|e| format!("Failed to fill username: {e:?}"))?;
-	page.fill_builder("#password", &password).fill().await.map_err(|e| format!("Failed to fill password: {e:?}"))?;
+	page.goto_builder(BSH_PORTAL_URL).goto().await.map_err(|e| format!("Failed to go to BSH website: {e:?}"))?;
+	page.fill_builder("input#username", username.expose()).fill().await.map_err(|e| format!("Failed to fill username: {e:?}"))?;
+	page.fill_builder("#password", password.expose()).fill().await.map_err(|e| format!("Failed to fill password: {e:?}"))?;
 	page.click_builder("body > div > div > section > div:nth-child(2) > div > form > div:nth-child(3) > div.small-12.medium-4.columns > button").click().await.map_err(|e| format!("Failed to click login: {e:?}"))?;
 	page.focus("#SD_OM-BDI-content", None).await.map_err(|e| format!("Failed to focus on SD_OM-BDI-content: {e:?}"))?;
 
 	let url = page.url().map_err(|e| format!("Failed to get page url: {e:?}"))?;
+	let cookies = context.cookies(&[url]).await;
+	// The context goes back to the pool, the page does not.
+	let _ = page.close(None).await;
 
-	if let Ok(cookies) = context.cookies(&[url]).await {
+	if let Ok(cookies) = cookies {
 		let token_json = json!({ "token": BSHJWTTokenClaims::encode(cookies).await.map_err(|_| "Faild to encode BSH Token.".to_string())? }).to_string();
-		let mut file = File::create("/easfiles/appliances/cookies/bsh_cookies.json").map_err(|e| format!("Failed to create bsh_cookies.json: {e:?}"))?;
-		file.write_all(token_json.as_bytes()).map_err(|e| format!("Failed to write bsh_cookies.json: {e:?}"))?;
+		tokens::save(BSH_TOKEN, &token_json).await.map_err(|e| format!("Failed to write bsh_cookies.json: {e:?}"))?;
 		Ok(true)
 	} else {
 		Ok(false)
 	}
 }
+
+///
+/// # Login to BSH System
+/// Without the `browser-login` feature there is no browser to log in with, so a session has to be saved
+/// by a build that has it.
+///
+/// # Errors
+/// Always returns an error.
+///
+#[cfg(not(feature = "browser-login"))]
+#[allow(clippy::unused_async)]
+pub async fn bsh_login(_username: SecretString, _password: SecretString) -> Result<bool, String> {
+	Err("BSH login needs the `browser-login` feature; no saved BSH session was found.".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const GATEWAY_ERROR: &str = include_str!("../tests/fixtures/bsh/simulate_gateway_error.json");
+	const REJECTED: &str = include_str!("../tests/fixtures/bsh/simulate_rejected.json");
+	const IN_STOCK: &str = include_str!("../tests/fixtures/bsh/simulate_in_stock.json");
+	const LOGIN_PAGE: &str = include_str!("../tests/fixtures/bsh/login_page.html");
+
+	#[test]
+	fn gateway_error_is_vendor_unavailable() {
+		let error = parse_simulation(GATEWAY_ERROR).unwrap_err();
+		assert_eq!(error, AvailabilityError::VendorUnavailable("BSH simulation failed: /IWBEP/CM_MGW_RT/020 Sold-to party 0004711 not maintained for sales area US10/10/00".to_string()));
+	}
+
+	#[test]
+	fn login_page_is_an_expired_session() {
+		assert_eq!(parse_simulation(LOGIN_PAGE).unwrap_err(), AvailabilityError::SessionExpired);
+	}
+
+	#[test]
+	fn other_answers_are_vendor_unavailable() {
+		for answer in ["{}", "<html><body>Service Unavailable</body></html>", ""] {
+			assert!(matches!(parse_simulation(answer), Err(AvailabilityError::VendorUnavailable(_))), "{answer}");
+		}
+	}
+
+	#[test]
+	fn rejected_line_carries_the_sap_message() {
+		let simulation = bsh_item_simulation(&parse_simulation(REJECTED).unwrap(), 0);
+		assert_eq!(simulation.availability, "Model availablility not found.");
+		let rejection = simulation.rejection.unwrap();
+		assert_eq!(rejection.code, "V1/391");
+		assert_eq!(rejection.reason, RejectionReason::MaterialBlocked);
+		assert!(simulation.tranches.is_empty());
+	}
+
+	#[test]
+	fn confirmed_line() {
+		let simulation = bsh_item_simulation(&parse_simulation(IN_STOCK).unwrap(), 0);
+		assert_eq!(simulation.availability, "\"Availableon10/20/2026\"");
+		assert!(simulation.rejection.is_none());
+		assert_eq!(simulation.tranches.len(), 1);
+		let line = simulation.line.unwrap();
+		assert_eq!((line.material.as_deref(), line.confirmed_quantity, line.delivery_date), (Some("HBL8453UC"), Some(2), NaiveDate::from_ymd_opt(2026, 10, 20)));
+	}
+}
@@ -0,0 +1,120 @@
+use chrono::NaiveDate;
+
+use super::AvailabilityRequest;
+
+///
+/// # `AvailabilityRequestBuilder`
+/// Builder for `AvailabilityRequest` that is changed in place, so one builder can be kept and adjusted
+/// between lookups. `build` returns a request with the manufacturer parsed and the warehouse resolved,
+/// ready for `AvailabilityRequest::get_availability`.
+///
+/// ## Example
+/// ```
+/// use eggersmann_app_server_appliance_availability::AvailabilityRequest;
+///
+/// let mut builder = AvailabilityRequest::builder();
+/// builder.manufacturer("Bosch").showroom("houston").model_number("HBLP651RUC").quantity(2);
+/// let houston = builder.build();
+/// assert_eq!(houston.warehouse.as_deref(), Some("US00002148"));
+///
+/// builder.showroom("dallas");
+/// assert_eq!(builder.build().warehouse.as_deref(), Some("US00003189"));
+/// assert_eq!(houston.requested_quantity(), 2);
+/// ```
+///
+#[derive(Debug, Clone, Default)]
+pub struct AvailabilityRequestBuilder {
+	manufacturer: String,
+	showroom: String,
+	model_number: String,
+	warehouse: Option<String>,
+	quantity: Option<u32>,
+	needed_by: Option<NaiveDate>,
+	requested_date: Option<NaiveDate>,
+	simulate: bool,
+	description_locale: Option<String>,
+	include_pricing: bool,
+}
+
+impl AvailabilityRequestBuilder {
+	pub fn manufacturer(&mut self, manufacturer: &str) -> &mut Self {
+		manufacturer.clone_into(&mut self.manufacturer);
+		self
+	}
+
+	pub fn showroom(&mut self, showroom: &str) -> &mut Self {
+		showroom.clone_into(&mut self.showroom);
+		self
+	}
+
+	pub fn model_number(&mut self, model_number: &str) -> &mut Self {
+		model_number.clone_into(&mut self.model_number);
+		self
+	}
+
+	///
+	/// Look the model up at `warehouse` instead of the showroom's, see `AvailabilityRequest::with_warehouse`.
+	///
+	pub fn warehouse(&mut self, warehouse: &str) -> &mut Self {
+		self.warehouse = Some(warehouse.to_string());
+		self
+	}
+
+	///
+	/// Go back to the warehouse of the showroom.
+	///
+	pub fn showroom_warehouse(&mut self) -> &mut Self {
+		self.warehouse = None;
+		self
+	}
+
+	pub const fn quantity(&mut self, quantity: u32) -> &mut Self {
+		self.quantity = Some(quantity);
+		self
+	}
+
+	pub const fn needed_by(&mut self, needed_by: NaiveDate) -> &mut Self {
+		self.needed_by = Some(needed_by);
+		self
+	}
+
+	pub const fn requested_date(&mut self, requested_date: NaiveDate) -> &mut Self {
+		self.requested_date = Some(requested_date);
+		self
+	}
+
+	pub const fn simulation(&mut self, simulate: bool) -> &mut Self {
+		self.simulate = simulate;
+		self
+	}
+
+	pub fn description_locale(&mut self, locale: &str) -> &mut Self {
+		self.description_locale = Some(locale.to_string());
+		self
+	}
+
+	pub const fn include_pricing(&mut self, include_pricing: bool) -> &mut Self {
+		self.include_pricing = include_pricing;
+		self
+	}
+
+	///
+	/// # `AvailabilityRequestBuilder::build`
+	/// The request, with `parse_manufacturer` and `get_warehouse` applied. It is stamped with the time when
+	/// it is looked up.
+	///
+	#[must_use]
+	pub fn build(&self) -> AvailabilityRequest {
+		let mut req = AvailabilityRequest::new(self.manufacturer.clone(), self.showroom.clone(), self.model_number.clone());
+		if let Some(warehouse) = &self.warehouse {
+			req = req.with_warehouse(warehouse);
+		}
+		req.quantity = self.quantity;
+		req.needed_by = self.needed_by;
+		req.requested_date = self.requested_date;
+		req.simulate = self.simulate;
+		req.description_locale.clone_from(&self.description_locale);
+		req.include_pricing = self.include_pricing;
+		req.parse_manufacturer().get_warehouse()
+	}
+}
@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+use super::memory::{memory_budget, Footprint};
+use super::telemetry;
+use super::{Availability, CatalogVersion, ProductInfo, Rejection};
+
+///
+/// # `TtlCache`
+/// Small thread-safe map whose entries expire after a fixed time to live.
+///
+/// With a byte budget set, the least recently used entries are evicted once the estimated footprint of
+/// the cache exceeds it.
+///
+#[derive(Debug)]
+pub struct TtlCache<K, V> {
+	ttl: Duration,
+	state: Mutex<CacheState<K, V>>,
+}
+
+#[derive(Debug)]
+struct CacheEntry<V> {
+	inserted: Instant,
+	ttl: Duration,
+	last_used: Instant,
+	bytes: usize,
+	value: V,
+}
+
+#[derive(Debug)]
+struct CacheState<K, V> {
+	entries: HashMap<K, CacheEntry<V>>,
+	bytes: usize,
+	max_bytes: Option<usize>,
+}
+
+impl<K: Eq + Hash + Clone, V> CacheState<K, V> {
+	fn remove(&mut self, key: &K) -> Option<CacheEntry<V>> {
+		let entry = self.entries.remove(key)?;
+		self.bytes -= entry.bytes;
+		Some(entry)
+	}
+
+	fn purge_expired(&mut self) {
+		let expired: Vec<K> = self.entries.iter().filter(|(_, entry)| expired(entry.inserted, entry.ttl)).map(|(key, _)| key.clone()).collect();
+		for key in &expired {
+			self.remove(key);
+		}
+	}
+
+	fn evict_to_budget(&mut self) -> usize {
+		let Some(max_bytes) = self.max_bytes else { return 0 };
+		let mut evicted = 0;
+		while self.bytes > max_bytes {
+			let Some(oldest) = self.entries.iter().min_by_key(|(_, entry)| entry.last_used).map(|(key, _)| key.clone()) else { break };
+			self.remove(&oldest);
+			evicted += 1;
+		}
+		evicted
+	}
+}
+
+///
+/// Whether an entry inserted at `inserted` with time to live `ttl` has expired.
+///
+fn expired(inserted: Instant, ttl: Duration) -> bool {
+	inserted.elapsed() >= ttl
+}
+
+impl<K: Eq + Hash + Clone + Footprint, V: Clone + Footprint> TtlCache<K, V> {
+	///
+	/// # `TtlCache::new`
+	/// Create an empty cache whose entries live for `ttl`.
+	///
+	#[must_use]
+	pub fn new(ttl: Duration) -> Self {
+		Self { ttl, state: Mutex::new(CacheState { entries: HashMap::new(), bytes: 0, max_bytes: None }) }
+	}
+
+	///
+	/// # `TtlCache::get`
+	/// Get a value if it is present and has not expired.
+	///
+	pub fn get(&self, key: &K) -> Option<V> {
+		let mut state = self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+		match state.entries.get_mut(key) {
+			Some(entry) if !expired(entry.inserted, entry.ttl) => {
+				entry.last_used = Instant::now();
+				Some(entry.value.clone())
+			}
+			Some(_) => {
+				state.remove(key);
+				None
+			}
+			None => None,
+		}
+	}
+
+	///
+	/// # `TtlCache::insert`
+	/// Insert or replace a value, resetting its time to live.
+	///
+	pub fn insert(&self, key: K, value: V) {
+		self.insert_with_ttl(key, value, self.ttl);
+	}
+
+	///
+	/// # `TtlCache::insert_with_ttl`
+	/// Insert or replace a value with its own time to live instead of the cache default.
+	///
+	pub fn insert_with_ttl(&self, key: K, value: V, ttl: Duration) {
+		let mut state = self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+		state.purge_expired();
+		state.remove(&key);
+		let now = Instant::now();
+		let bytes = std::mem::size_of::<(K, CacheEntry<V>)>() + key.heap_bytes() + value.heap_bytes();
+		state.bytes += bytes;
+		state.entries.insert(key, CacheEntry { inserted: now, ttl, last_used: now, bytes, value });
+		let evicted = state.evict_to_budget();
+		drop(state);
+		if evicted > 0 {
+			telemetry::counter("cache.evicted", evicted as u64, &[]);
+		}
+	}
+
+	///
+	/// # `TtlCache::invalidate`
+	/// Remove a single entry.
+	///
+	pub fn invalidate(&self, key: &K) {
+		self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner).remove(key);
+	}
+
+	///
+	/// # `TtlCache::clear`
+	/// Remove every entry.
+	///
+	pub fn clear(&self) {
+		let mut state = self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+		state.entries.clear();
+		state.bytes = 0;
+	}
+
+	///
+	/// # `TtlCache::set_max_bytes`
+	/// Set the byte budget, evicting least recently used entries right away if it is already exceeded.
+	/// `None` removes the budget.
+	///
+	pub fn set_max_bytes(&self, max_bytes: Option<usize>) {
+		let mut state = self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+		state.max_bytes = max_bytes;
+		state.evict_to_budget();
+	}
+
+	///
+	/// # `TtlCache::usage`
+	/// Number of entries and their estimated footprint in bytes.
+	///
+	pub fn usage(&self) -> (usize, usize) {
+		let state = self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+		(state.entries.len(), state.bytes)
+	}
+}
+
+///
+/// # `ResultCacheConfig`
+/// Time to live for cached availability results.
+///
+/// Hits and misses ("model not found") are cached separately, by the status of the answer. By default a
+/// hit is kept for a minute, so repeated lookups of the same model reach the vendor once while its
+/// availability stays fresh, and a miss for ten minutes, so typos and discontinued models are answered
+/// without a vendor call. A zero duration disables that half of the cache.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ResultCacheConfig {
+	pub positive_ttl: Duration,
+	pub negative_ttl: Duration,
+}
+
+impl Default for ResultCacheConfig {
+	fn default() -> Self {
+		Self { positive_ttl: Duration::from_mins(1), negative_ttl: Duration::from_mins(10) }
+	}
+}
+
+impl ResultCacheConfig {
+	#[must_use]
+	pub const fn new(positive_ttl: Duration, negative_ttl: Duration) -> Self {
+		Self { positive_ttl, negative_ttl }
+	}
+}
+
+fn result_cache_config_slot() -> &'static RwLock<ResultCacheConfig> {
+	static CONFIG: OnceLock<RwLock<ResultCacheConfig>> = OnceLock::new();
+	CONFIG.get_or_init(|| RwLock::new(ResultCacheConfig::default()))
+}
+
+///
+/// # `set_result_cache_config`
+/// Replace the process-wide result cache configuration. Already cached entries keep their original TTL.
+///
+pub fn set_result_cache_config(config: ResultCacheConfig) {
+	*result_cache_config_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = config;
+}
+
+///
+/// # `result_cache_config`
+/// The current result cache configuration.
+///
+pub fn result_cache_config() -> ResultCacheConfig {
+	*result_cache_config_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+///
+/// Key of a cached result: manufacturer, warehouse, model number, requested quantity, description
+/// language and requested delivery date.
+///
+pub type ResultKey = (String, String, String, u32, String, Option<NaiveDate>);
+
+///
+/// A vendor answer kept in the result cache.
+///
+#[derive(Debug, Clone)]
+pub struct CachedResult {
+	pub availability: String,
+	pub availability_detail: Option<Availability>,
+	pub product: Option<ProductInfo>,
+	pub rejection: Option<Rejection>,
+	pub catalog_version: Option<CatalogVersion>,
+	pub cached_at: DateTime<Utc>,
+}
+
+pub fn result_cache() -> &'static TtlCache<ResultKey, CachedResult> {
+	static CACHE: OnceLock<TtlCache<ResultKey, CachedResult>> = OnceLock::new();
+	CACHE.get_or_init(|| {
+		let cache = TtlCache::new(Duration::ZERO);
+		cache.set_max_bytes(memory_budget().result_cache_bytes);
+		cache
+	})
+}
+
+impl Footprint for CachedResult {
+	fn heap_bytes(&self) -> usize {
+		self.availability.heap_bytes() + self.availability_detail.heap_bytes() + self.product.heap_bytes() + self.rejection.heap_bytes() + self.catalog_version.heap_bytes()
+	}
+}
@@ -0,0 +1,125 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{OnceLock, RwLock};
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+
+///
+/// # `BusinessCalendar`
+/// The days a vendor ships and counts lead time on: every day except the weekend and its holidays.
+///
+/// ## Example
+/// ```
+/// use chrono::NaiveDate;
+/// use eggersmann_app_server_appliance_availability::calendar::BusinessCalendar;
+///
+/// let calendar = BusinessCalendar::default().with_holiday(NaiveDate::from_ymd_opt(2024, 7, 4).unwrap());
+/// // Wednesday 3 July to Monday 8 July skips the 4th and the weekend.
+/// assert_eq!(calendar.business_days_between(NaiveDate::from_ymd_opt(2024, 7, 3).unwrap(), NaiveDate::from_ymd_opt(2024, 7, 8).unwrap()), 2);
+/// assert_eq!(calendar.next_business_day(NaiveDate::from_ymd_opt(2024, 7, 4).unwrap()), NaiveDate::from_ymd_opt(2024, 7, 5).unwrap());
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct BusinessCalendar {
+	pub weekend: Vec<Weekday>,
+	pub holidays: BTreeSet<NaiveDate>,
+}
+
+impl Default for BusinessCalendar {
+	fn default() -> Self {
+		Self { weekend: vec![Weekday::Sat, Weekday::Sun], holidays: BTreeSet::new() }
+	}
+}
+
+impl BusinessCalendar {
+	#[must_use]
+	pub fn with_weekend(mut self, weekend: Vec<Weekday>) -> Self {
+		self.weekend = weekend;
+		self
+	}
+
+	#[must_use]
+	pub fn with_holiday(mut self, holiday: NaiveDate) -> Self {
+		self.holidays.insert(holiday);
+		self
+	}
+
+	#[must_use]
+	pub fn with_holidays(mut self, holidays: impl IntoIterator<Item = NaiveDate>) -> Self {
+		self.holidays.extend(holidays);
+		self
+	}
+
+	///
+	/// Whether the vendor works on `date`.
+	///
+	#[must_use]
+	pub fn is_business_day(&self, date: NaiveDate) -> bool {
+		!self.weekend.contains(&date.weekday()) && !self.holidays.contains(&date)
+	}
+
+	///
+	/// `date` itself if it is a business day, otherwise the first business day after it.
+	///
+	#[must_use]
+	pub fn next_business_day(&self, date: NaiveDate) -> NaiveDate {
+		date.iter_days().take(366).find(|date| self.is_business_day(*date)).unwrap_or(date)
+	}
+
+	///
+	/// Business days after `from` up to and including `to`, zero when `to` is not after `from`.
+	///
+	#[must_use]
+	pub fn business_days_between(&self, from: NaiveDate, to: NaiveDate) -> i64 {
+		let Some(start) = from.checked_add_days(Days::new(1)) else { return 0 };
+		let days = start.iter_days().take_while(|date| *date <= to).filter(|date| self.is_business_day(*date)).count();
+		i64::try_from(days).unwrap_or(i64::MAX)
+	}
+
+	///
+	/// The date `days` business days after `from`, `None` if the calendar runs out of business days first,
+	/// e.g. when every weekday is weekend.
+	///
+	#[must_use]
+	pub fn add_business_days(&self, from: NaiveDate, days: u32) -> Option<NaiveDate> {
+		let Some(nth) = (days as usize).checked_sub(1) else { return Some(from) };
+		if [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun].iter().all(|day| self.weekend.contains(day)) {
+			return None;
+		}
+		// each week has a day off the weekend unless all are, and each holiday takes at most one of them.
+		let weeks = (days as usize).saturating_add(self.holidays.len()).saturating_add(1);
+		from.iter_days().skip(1).take(weeks.saturating_mul(7)).filter(|date| self.is_business_day(*date)).nth(nth)
+	}
+}
+
+fn calendars() -> &'static RwLock<HashMap<String, BusinessCalendar>> {
+	static CALENDARS: OnceLock<RwLock<HashMap<String, BusinessCalendar>>> = OnceLock::new();
+	CALENDARS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+///
+/// # `set_business_calendar`
+/// Configure the weekend and holidays of `manufacturer`.
+///
+pub fn set_business_calendar(manufacturer: &str, calendar: BusinessCalendar) {
+	calendars().write().unwrap_or_else(std::sync::PoisonError::into_inner).insert(manufacturer.to_lowercase(), calendar);
+}
+
+///
+/// # `set_vendor_holidays`
+/// Replace the holidays of `manufacturer`, keeping its weekend.
+///
+pub fn set_vendor_holidays(manufacturer: &str, holidays: impl IntoIterator<Item = NaiveDate>) {
+	let holidays = holidays.into_iter().collect();
+	calendars().write().unwrap_or_else(std::sync::PoisonError::into_inner).entry(manufacturer.to_lowercase()).or_default().holidays = holidays;
+}
+
+///
+/// # `business_calendar`
+/// The calendar of `manufacturer`, Monday to Friday without holidays if none is configured.
+///
+#[must_use]
+pub fn business_calendar(manufacturer: &str) -> BusinessCalendar {
+	calendars().read().unwrap_or_else(std::sync::PoisonError::into_inner).get(&manufacturer.to_lowercase()).cloned().unwrap_or_default()
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::events::{self, AvailabilityEvent};
+use super::jobs::{Job, JobKind, JobQueue};
+use super::schedule::{self, Workload};
+use super::telemetry;
+
+///
+/// # `CanaryPolicy`
+/// How often the login canary of a vendor runs, and how many logins in a row the vendor must turn away
+/// before `AvailabilityEvent::CredentialRotationLikely` is published. One rejection can be a portal
+/// hiccup; a rejection on every run means the dealer password was reset.
+///
+/// ## Example
+/// ```
+/// use std::time::Duration;
+/// use eggersmann_app_server_appliance_availability::canary::{set_canary_policy, CanaryPolicy};
+///
+/// set_canary_policy(CanaryPolicy::new(Duration::from_secs(4 * 60 * 60)).with_failures_before_alert(3));
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct CanaryPolicy {
+	pub interval: Duration,
+	pub failures_before_alert: u32,
+}
+
+impl CanaryPolicy {
+	///
+	/// # `CanaryPolicy::new`
+	/// Run every `interval` and alert on the second rejection in a row.
+	///
+	#[must_use]
+	pub const fn new(interval: Duration) -> Self {
+		Self { interval, failures_before_alert: 2 }
+	}
+
+	#[must_use]
+	pub const fn with_failures_before_alert(mut self, failures_before_alert: u32) -> Self {
+		self.failures_before_alert = failures_before_alert;
+		self
+	}
+}
+
+impl Default for CanaryPolicy {
+	fn default() -> Self {
+		Self::new(Duration::from_hours(6))
+	}
+}
+
+///
+/// # `CanaryOutcome`
+/// The result of one canary login.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum CanaryOutcome {
+	/// The vendor accepted the credentials.
+	Passed,
+	/// The vendor answered and turned the credentials away.
+	CredentialsRejected,
+	/// The vendor or the secret store could not be reached, which says nothing about the credentials.
+	Unavailable { error: String },
+}
+
+///
+/// # `CredentialRotationLikely`
+/// A vendor turned the canary login away `consecutive_failures` times in a row since `first_failed_at`,
+/// published as `AvailabilityEvent::CredentialRotationLikely`. Rotate `secret` before lookups start
+/// failing.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct CredentialRotationLikely {
+	pub manufacturer: String,
+	pub secret: String,
+	pub consecutive_failures: u32,
+	pub first_failed_at: DateTime<Utc>,
+	pub at: DateTime<Utc>,
+}
+
+fn policy_slot() -> &'static RwLock<CanaryPolicy> {
+	static POLICY: OnceLock<RwLock<CanaryPolicy>> = OnceLock::new();
+	POLICY.get_or_init(|| RwLock::new(CanaryPolicy::default()))
+}
+
+///
+/// Rejections in a row per manufacturer and when the first of them happened.
+///
+type FailureTable = HashMap<String, (u32, DateTime<Utc>)>;
+
+fn failures() -> &'static Mutex<FailureTable> {
+	static FAILURES: OnceLock<Mutex<FailureTable>> = OnceLock::new();
+	FAILURES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+///
+/// # `set_canary_policy`
+/// Set how often login canaries run and when they alert.
+///
+pub fn set_canary_policy(policy: CanaryPolicy) {
+	*policy_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = policy;
+}
+
+///
+/// # `canary_policy`
+/// How often login canaries run and when they alert.
+///
+#[must_use]
+pub fn canary_policy() -> CanaryPolicy {
+	*policy_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+///
+/// # `schedule_login_canary`
+/// Queue the login canary of `manufacturer` to run now, or at the start of the next window `schedule`
+/// allows routine work for it. `jobs::run_due_jobs` runs it and queues the next run `CanaryPolicy::interval`
+/// later, moved into the window the same way, so scheduling it once keeps it running.
+///
+/// # Errors
+/// Returns an error if the queue cannot be persisted.
+///
+pub fn schedule_login_canary(queue: &dyn JobQueue, manufacturer: &str) -> Result<(), String> {
+	let manufacturer = manufacturer.to_lowercase();
+	let at = schedule::next_run(Workload::Routine, None, Some(&manufacturer), Utc::now());
+	queue.enqueue(Job::new(format!("canary:{manufacturer}"), JobKind::LoginCanary { manufacturer }, at))
+}
+
+///
+/// # Run Login Canary
+/// Log in to the vendor portal with a session of its own, apart from the one lookups use, and count the
+/// rejections in a row. The rejection that reaches `CanaryPolicy::failures_before_alert` publishes
+/// `AvailabilityEvent::CredentialRotationLikely`; a vendor that cannot be reached neither counts nor resets.
+///
+/// # Errors
+/// Returns an error if `manufacturer` has no login canary.
+///
+pub async fn run_login_canary(manufacturer: &str) -> Result<CanaryOutcome, String> {
+	let manufacturer = manufacturer.to_lowercase();
+	let Some((outcome, secret)) = login_check(&manufacturer).await else {
+		return Err(format!("No login canary for {manufacturer}."));
+	};
+	let label = match &outcome {
+		CanaryOutcome::Passed => "passed",
+		CanaryOutcome::CredentialsRejected => "rejected",
+		CanaryOutcome::Unavailable { .. } => "unavailable",
+	};
+	telemetry::counter("canary.login", 1, &[("manufacturer", manufacturer.clone()), ("outcome", label.to_string())]);
+	if let Some(alert) = record(&manufacturer, secret, &outcome) {
+		telemetry::event("canary.credential_rotation_likely", &[("manufacturer", alert.manufacturer.clone()), ("secret", alert.secret.clone()), ("consecutive_failures", alert.consecutive_failures.to_string())]);
+		events::publish(AvailabilityEvent::CredentialRotationLikely(alert));
+	}
+	Ok(outcome)
+}
+
+///
+/// The canary login of `manufacturer` and the secret to rotate when it is turned away, `None` for a
+/// vendor without one.
+///
+#[cfg_attr(not(feature = "subzero"), allow(clippy::unused_async))]
+async fn login_check(manufacturer: &str) -> Option<(CanaryOutcome, &'static str)> {
+	match manufacturer {
+		#[cfg(feature = "subzero")]
+		"subzero" | "wolf" => Some((subzero_canary().await, "subzero-password")),
+		_ => None,
+	}
+}
+
+#[cfg(feature = "subzero")]
+async fn subzero_canary() -> CanaryOutcome {
+	let (username, password) = match super::subzero::subzero_credentials().await {
+		Ok(credentials) => credentials,
+		Err(error) => return CanaryOutcome::Unavailable { error },
+	};
+	match super::subzero::subzero_login_check(username, password).await {
+		Ok(true) => CanaryOutcome::Passed,
+		Ok(false) => CanaryOutcome::CredentialsRejected,
+		Err(error) => CanaryOutcome::Unavailable { error },
+	}
+}
+
+///
+/// Count `outcome` against the rejections in a row of `manufacturer`, with the alert it raises, if any.
+///
+fn record(manufacturer: &str, secret: &str, outcome: &CanaryOutcome) -> Option<CredentialRotationLikely> {
+	match outcome {
+		CanaryOutcome::Passed => {
+			failures().lock().unwrap_or_else(std::sync::PoisonError::into_inner).remove(manufacturer);
+			None
+		}
+		CanaryOutcome::Unavailable { .. } => None,
+		CanaryOutcome::CredentialsRejected => {
+			let now = Utc::now();
+			let (count, first_failed_at) = *failures().lock().unwrap_or_else(std::sync::PoisonError::into_inner).entry(manufacturer.to_string()).and_modify(|(count, _)| *count += 1).or_insert((1, now));
+			(count == canary_policy().failures_before_alert.max(1)).then(|| CredentialRotationLikely { manufacturer: manufacturer.to_string(), secret: secret.to_string(), consecutive_failures: count, first_failed_at, at: now })
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn unavailable() -> CanaryOutcome {
+		CanaryOutcome::Unavailable { error: "dns error".to_string() }
+	}
+
+	#[test]
+	fn alerts_once_when_the_rejections_in_a_row_reach_the_policy() {
+		let threshold = canary_policy().failures_before_alert;
+		let alerts = (0..threshold + 2).map(|_| record("canary-threshold", "canary-password", &CanaryOutcome::CredentialsRejected)).collect::<Vec<_>>();
+		let alert = alerts[threshold as usize - 1].clone().unwrap();
+		assert_eq!((alert.manufacturer.as_str(), alert.secret.as_str(), alert.consecutive_failures), ("canary-threshold", "canary-password", threshold));
+		assert_eq!(alerts.iter().filter(|alert| alert.is_some()).count(), 1);
+	}
+
+	#[test]
+	fn unreachable_vendor_neither_counts_nor_resets() {
+		let threshold = canary_policy().failures_before_alert;
+		for _ in 1..threshold {
+			assert_eq!(record("canary-unreachable", "canary-password", &CanaryOutcome::CredentialsRejected), None);
+			assert_eq!(record("canary-unreachable", "canary-password", &unavailable()), None);
+		}
+		let alert = record("canary-unreachable", "canary-password", &CanaryOutcome::CredentialsRejected).unwrap();
+		assert_eq!(alert.consecutive_failures, threshold);
+	}
+
+	#[test]
+	fn accepted_login_starts_the_count_again() {
+		let threshold = canary_policy().failures_before_alert;
+		for _ in 1..threshold {
+			assert_eq!(record("canary-reset", "canary-password", &CanaryOutcome::CredentialsRejected), None);
+		}
+		assert_eq!(record("canary-reset", "canary-password", &CanaryOutcome::Passed), None);
+		for _ in 1..threshold {
+			assert_eq!(record("canary-reset", "canary-password", &CanaryOutcome::CredentialsRejected), None);
+		}
+		assert_eq!(record("canary-reset", "canary-password", &CanaryOutcome::CredentialsRejected).map(|alert| alert.consecutive_failures), Some(threshold));
+	}
+}
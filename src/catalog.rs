@@ -0,0 +1,206 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::jobs::{Job, JobKind, JobQueue};
+
+///
+/// # `CatalogSnapshot`
+/// What a cached vendor catalog is: when it was loaded, its version where the vendor has one, its
+/// warehouses and how many rows it holds.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct CatalogSnapshot {
+	pub manufacturer: String,
+	pub loaded_at: DateTime<Utc>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub version: Option<u64>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub digest: Option<String>,
+	pub warehouses: Vec<String>,
+	pub items: usize,
+}
+
+///
+/// # `CatalogItem`
+/// One row of a cached vendor catalog, as the vendor published it when the catalog was loaded. Prices
+/// are only known for vendors whose catalog is a price list.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct CatalogItem {
+	pub manufacturer: String,
+	pub warehouse: String,
+	pub model_number: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub description: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub category: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub list_price: Option<f64>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub dealer_price: Option<f64>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub available_qty: Option<u32>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub next_available_qty: Option<u32>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub next_available_date: Option<NaiveDate>,
+}
+
+impl CatalogItem {
+	#[must_use]
+	pub const fn new(manufacturer: String, warehouse: String, model_number: String) -> Self {
+		Self {
+			manufacturer,
+			warehouse,
+			model_number,
+			description: None,
+			category: None,
+			list_price: None,
+			dealer_price: None,
+			available_qty: None,
+			next_available_qty: None,
+			next_available_date: None,
+		}
+	}
+}
+
+///
+/// A vendor catalog held in memory, read without refreshing it.
+///
+pub(crate) trait CachedCatalog: Send + Sync {
+	fn snapshot(&self) -> CatalogSnapshot;
+
+	///
+	/// Model numbers in `warehouse`, or in every warehouse, sorted and without duplicates.
+	///
+	fn models(&self, warehouse: Option<&str>) -> Vec<String>;
+
+	fn item(&self, warehouse: &str, model_number: &str) -> Option<CatalogItem>;
+}
+
+///
+/// # `CatalogReader`
+/// Read-only view of the vendor catalogs cached in memory, for tools that browse them. Nothing is
+/// downloaded, refreshed or looked up live and no credentials are read: a catalog that has not been
+/// loaded yet is simply not listed.
+///
+/// ## Example
+/// ```
+/// use eggersmann_app_server_appliance_availability::catalog::CatalogReader;
+///
+/// let reader = CatalogReader::new();
+/// for vendor in reader.vendors() {
+///     let snapshot = reader.snapshot(&vendor).unwrap();
+///     println!("{vendor}: {} items loaded at {}", snapshot.items, snapshot.loaded_at);
+/// }
+/// ```
+///
+#[derive(Debug, Clone, Copy, Default)]
+#[non_exhaustive]
+pub struct CatalogReader;
+
+impl CatalogReader {
+	#[must_use]
+	pub const fn new() -> Self {
+		Self
+	}
+
+	///
+	/// # `CatalogReader::vendors`
+	/// Manufacturers with a catalog in memory.
+	///
+	#[must_use]
+	pub fn vendors(&self) -> Vec<String> {
+		CATALOG_VENDORS.iter().filter(|vendor| cached_catalog(vendor).is_some()).map(|vendor| (*vendor).to_string()).collect()
+	}
+
+	///
+	/// # `CatalogReader::snapshot`
+	/// When the catalog of `manufacturer` was loaded, its version and size.
+	///
+	#[must_use]
+	pub fn snapshot(&self, manufacturer: &str) -> Option<CatalogSnapshot> {
+		cached_catalog(manufacturer).map(|catalog| catalog.snapshot())
+	}
+
+	///
+	/// # `CatalogReader::models`
+	/// Model numbers in the catalog of `manufacturer`, in `warehouse` or in every warehouse. Empty if the
+	/// catalog is not loaded.
+	///
+	#[must_use]
+	pub fn models(&self, manufacturer: &str, warehouse: Option<&str>) -> Vec<String> {
+		cached_catalog(manufacturer).map(|catalog| catalog.models(warehouse)).unwrap_or_default()
+	}
+
+	///
+	/// # `CatalogReader::item`
+	/// The row of `model_number` in `warehouse` of the catalog of `manufacturer`, matched exactly apart from
+	/// case, spaces and dashes.
+	///
+	#[must_use]
+	pub fn item(&self, manufacturer: &str, warehouse: &str, model_number: &str) -> Option<CatalogItem> {
+		cached_catalog(manufacturer).and_then(|catalog| catalog.item(warehouse, model_number))
+	}
+}
+
+///
+/// Manufacturers whose lookups read a catalog.
+///
+const CATALOG_VENDORS: [&str; 4] = ["miele", "liebherr", "subzero", "bertazzoni"];
+
+fn cached_catalog(manufacturer: &str) -> Option<Arc<dyn CachedCatalog>> {
+	match manufacturer.to_lowercase().as_str() {
+		#[cfg(feature = "miele")]
+		"miele" => super::miele::current_miele_catalog().map(|catalog| catalog as Arc<dyn CachedCatalog>),
+		#[cfg(feature = "liebherr")]
+		"liebherr" => super::liebherr::cached_liebherr_feed().map(|feed| feed as Arc<dyn CachedCatalog>),
+		#[cfg(feature = "subzero")]
+		"subzero" => super::subzero::cached_subzero_price_list().map(|price_list| price_list as Arc<dyn CachedCatalog>),
+		#[cfg(feature = "bertazzoni")]
+		"bertazzoni" => super::bertazzoni::current_bertazzoni_catalog().map(|catalog| catalog as Arc<dyn CachedCatalog>),
+		_ => None,
+	}
+}
+
+///
+/// # `export_catalog`
+/// Download the full catalog of `manufacturer` into the catalog store now, for vendors whose catalog is
+/// not loaded by their lookups. Only `SubZero` has one, its dealer price list.
+///
+/// # Errors
+/// Returns an error if `manufacturer` has no catalog export or the export fails.
+///
+#[cfg_attr(not(feature = "subzero"), allow(clippy::unused_async))]
+pub async fn export_catalog(manufacturer: &str) -> Result<CatalogSnapshot, String> {
+	match manufacturer.to_lowercase().as_str() {
+		#[cfg(feature = "subzero")]
+		"subzero" => super::subzero::export_subzero_price_list().await.map(|price_list| price_list.snapshot()),
+		manufacturer => Err(format!("No catalog export for {manufacturer}.")),
+	}
+}
+
+///
+/// # `schedule_catalog_export`
+/// Queue an export of the catalog of `manufacturer`. It runs in the bulk window, replacing an export of
+/// the same vendor that is still queued, and is retried with the backoff of `jobs::job_retry_policy`
+/// until it succeeds or is dead-lettered.
+///
+/// # Errors
+/// Returns an error if the queue cannot be persisted.
+///
+pub fn schedule_catalog_export(queue: &dyn JobQueue, manufacturer: &str) -> Result<(), String> {
+	let manufacturer = manufacturer.to_lowercase();
+	queue.enqueue(Job::new(format!("catalog-export:{manufacturer}"), JobKind::CatalogExport { manufacturer }, Utc::now()))
+}
+
+///
+/// A model number the way catalog rows are matched, so `WWB 020-WCS` finds `WWB020WCS`.
+///
+pub(crate) fn normalize_model(model_number: &str) -> String {
+	model_number.chars().filter(|c| !c.is_whitespace() && *c != '-').collect::<String>().to_uppercase()
+}
@@ -0,0 +1,66 @@
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+
+///
+/// Bytes of a page searched for a `<meta>` charset declaration, as in the HTML prescan.
+///
+const META_PRESCAN_BYTES: usize = 1024;
+
+///
+/// # `decode_html`
+/// Decode a vendor page to text, picking the encoding the way a browser would: a byte order mark, then
+/// the `charset` of the `Content-Type` header, then a `<meta charset>` or `<meta http-equiv>` declaration
+/// near the top of the page. A page with none of these is read as UTF-8 when it is valid UTF-8 and as
+/// Windows-1252 otherwise, which is what the `SubZero` portal sends unannounced.
+///
+/// ## Inputs
+/// * `body`: &[u8] - The raw response body.
+/// * `content_type`: Option<&str> - The `Content-Type` header of the response, if any.
+///
+/// ## Example
+/// ```
+/// use eggersmann_app_server_appliance_availability::charset::decode_html;
+///
+/// assert_eq!(decode_html(b"Caf\xe9", Some("text/html; charset=windows-1252")), "Café");
+/// assert_eq!(decode_html(b"<meta charset=\"iso-8859-1\">Caf\xe9", None), "<meta charset=\"iso-8859-1\">Café");
+/// assert_eq!(decode_html("Café".as_bytes(), None), "Café");
+/// ```
+///
+#[must_use]
+pub fn decode_html(body: &[u8], content_type: Option<&str>) -> String {
+	if let Some((encoding, bom_length)) = Encoding::for_bom(body) {
+		return encoding.decode_without_bom_handling(&body[bom_length..]).0.into_owned();
+	}
+	let declared = content_type.and_then(header_charset).or_else(|| meta_charset(&body[..body.len().min(META_PRESCAN_BYTES)]));
+	let encoding = declared.unwrap_or_else(|| if std::str::from_utf8(body).is_ok() { UTF_8 } else { WINDOWS_1252 });
+	encoding.decode_without_bom_handling(body).0.into_owned()
+}
+
+///
+/// # `header_charset`
+/// The encoding named by the `charset` parameter of a `Content-Type` header.
+///
+#[must_use]
+pub fn header_charset(content_type: &str) -> Option<&'static Encoding> {
+	content_type.split(';').skip(1).find_map(|parameter| {
+		let (name, value) = parameter.split_once('=')?;
+		name.trim().eq_ignore_ascii_case("charset").then(|| Encoding::for_label(value.trim().trim_matches(['"', '\'']).as_bytes()))?
+	})
+}
+
+///
+/// # `meta_charset`
+/// The encoding declared by a `<meta charset>` or `<meta http-equiv="Content-Type">` tag in `head`.
+///
+/// A declared UTF-16 is read as UTF-8, since a page that could be scanned as ASCII is not UTF-16.
+///
+#[must_use]
+pub fn meta_charset(head: &[u8]) -> Option<&'static Encoding> {
+	let lower = String::from_utf8_lossy(head).to_ascii_lowercase();
+	lower.match_indices("<meta").find_map(|(start, _)| {
+		let tag = &lower[start..lower[start..].find('>').map_or(lower.len(), |end| start + end)];
+		let value = &tag[tag.find("charset")? + "charset".len()..];
+		let value = value.trim_start().strip_prefix('=')?.trim_start().trim_start_matches(['"', '\'']);
+		let label: String = value.chars().take_while(|c| !c.is_whitespace() && !matches!(c, '"' | '\'' | ';' | '/' | '>')).collect();
+		Encoding::for_label(label.as_bytes()).map(Encoding::output_encoding)
+	})
+}
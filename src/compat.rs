@@ -0,0 +1,46 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serializer};
+
+///
+/// Format of `AvailabilityRequest::utc_time` in the v1 JSON payload.
+///
+pub const V1_TIME_FORMAT: &str = "%m/%d/%Y %I:%M:%S %p";
+
+///
+/// # `lowercase`
+/// Serde adapter keeping typed values wire-compatible with the v1 payload, where they were plain
+/// lowercase strings. Use with `#[serde(default, with = "crate::compat::lowercase")]` on an `Option<T>`.
+///
+/// Values are written with their `Display` text lowercased and read back case-insensitively through
+/// `FromStr`.
+///
+pub mod lowercase {
+	use super::{Deserialize, Deserializer, Display, FromStr, Serializer};
+
+	///
+	/// # Errors
+	/// Returns the serializer's error.
+	///
+	#[allow(clippy::ref_option)]
+	pub fn serialize<T: Display, S: Serializer>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error> {
+		match value {
+			Some(value) => serializer.serialize_some(&value.to_string().to_lowercase()),
+			None => serializer.serialize_none(),
+		}
+	}
+
+	///
+	/// # Errors
+	/// Returns an error if the string is not a valid `T`.
+	///
+	pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+	where
+		T: FromStr,
+		T::Err: Display,
+		D: Deserializer<'de>,
+	{
+		Option::<String>::deserialize(deserializer)?.map(|value| value.to_lowercase().parse().map_err(serde::de::Error::custom)).transpose()
+	}
+}
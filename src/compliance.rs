@@ -0,0 +1,204 @@
+use std::sync::{Arc, OnceLock, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+use super::{AvailabilityRequest, Manufacturer};
+
+///
+/// # `RestrictedItem`
+/// Returned instead of availability when a model must not be quoted for the request.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct RestrictedItem {
+	/// Reference of the policy that blocks the model, e.g. a MAP agreement or regulation id.
+	pub policy: String,
+	pub reason: String,
+	/// State the restriction applies in, `None` when it applies everywhere.
+	pub state: Option<String>,
+}
+
+impl RestrictedItem {
+	///
+	/// # `RestrictedItem::new`
+	/// Create a restriction that applies everywhere.
+	///
+	#[must_use]
+	pub const fn new(policy: String, reason: String) -> Self {
+		Self { policy, reason, state: None }
+	}
+
+	#[must_use]
+	pub fn with_state(mut self, state: String) -> Self {
+		self.state = Some(state);
+		self
+	}
+
+	///
+	/// Text shown in place of the availability by the v1 response.
+	///
+	#[must_use]
+	pub fn message(&self) -> String {
+		let place = self.state.as_ref().map_or_else(String::new, |state| format!(" in {state}"));
+		format!("Model cannot be quoted{place}: {} ({})", self.reason, self.policy)
+	}
+}
+
+///
+/// # `ComplianceFilter`
+/// Decides whether a model may be quoted before any vendor is called.
+///
+pub trait ComplianceFilter: Send + Sync {
+	///
+	/// The restriction blocking `req`, `None` if it may be quoted.
+	///
+	fn check(&self, req: &AvailabilityRequest) -> Option<RestrictedItem>;
+}
+
+///
+/// # `BlockRule`
+/// One blocklist entry.
+///
+/// * `manufacturer` - Vendor the rule applies to, any vendor when `None`.
+/// * `model` - Model number, case-insensitive. A trailing `*` matches every model with that prefix.
+/// * `states` - Two-letter states the rule applies in, every state when empty.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct BlockRule {
+	#[serde(default)]
+	pub manufacturer: Option<String>,
+	pub model: String,
+	#[serde(default)]
+	pub states: Vec<String>,
+	pub policy: String,
+	pub reason: String,
+}
+
+impl BlockRule {
+	///
+	/// # `BlockRule::new`
+	/// Block `model` for every vendor in every state.
+	///
+	#[must_use]
+	pub const fn new(model: String, policy: String, reason: String) -> Self {
+		Self { manufacturer: None, model, states: Vec::new(), policy, reason }
+	}
+
+	#[must_use]
+	pub fn with_manufacturer(mut self, manufacturer: String) -> Self {
+		self.manufacturer = Some(manufacturer);
+		self
+	}
+
+	#[must_use]
+	pub fn with_states(mut self, states: Vec<String>) -> Self {
+		self.states = states;
+		self
+	}
+
+	fn matches_model(&self, model_number: &str) -> bool {
+		let model_number = model_number.trim().to_uppercase();
+		let pattern = self.model.trim().to_uppercase();
+		pattern.strip_suffix('*').map_or_else(|| model_number == pattern, |prefix| model_number.starts_with(prefix))
+	}
+}
+
+///
+/// # `Blocklist`
+/// Compliance filter backed by a list of `BlockRule`s. The first matching rule wins.
+///
+/// ## Example
+/// ```
+/// use eggersmann_app_server_appliance_availability::compliance::{Blocklist, ComplianceFilter};
+/// use eggersmann_app_server_appliance_availability::AvailabilityRequest;
+///
+/// let blocklist = Blocklist::from_json(r#"[{"model": "HBLP*", "states": ["CA"], "policy": "MAP-2024-07", "reason": "MAP restricted"}]"#).unwrap();
+/// let req = AvailabilityRequest::new("bsh".to_string(), "los angeles".to_string(), "HBLP651RUC".to_string());
+/// assert_eq!(blocklist.check(&req).unwrap().policy, "MAP-2024-07");
+/// ```
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Blocklist {
+	rules: Vec<BlockRule>,
+}
+
+impl Blocklist {
+	///
+	/// # `Blocklist::new`
+	/// Create a blocklist from its rules.
+	///
+	#[must_use]
+	pub const fn new(rules: Vec<BlockRule>) -> Self {
+		Self { rules }
+	}
+
+	///
+	/// # `Blocklist::from_json`
+	/// Read a blocklist from a JSON array of rules.
+	///
+	/// # Errors
+	/// Returns an error if the JSON is not a list of rules.
+	///
+	pub fn from_json(json: &str) -> Result<Self, String> {
+		serde_json::from_str(json).map_err(|e| format!("Failed to parse blocklist: {e}"))
+	}
+}
+
+impl ComplianceFilter for Blocklist {
+	fn check(&self, req: &AvailabilityRequest) -> Option<RestrictedItem> {
+		let model_number = req.model_number.as_deref()?;
+		let manufacturer = req.manufacturer.as_ref().map_or("", Manufacturer::as_str);
+		let state = showroom_state(req.showroom.as_deref());
+
+		self.rules.iter().filter(|rule| rule.manufacturer.as_deref().is_none_or(|rule_manufacturer| rule_manufacturer.eq_ignore_ascii_case(manufacturer))).filter(|rule| rule.matches_model(model_number)).find_map(|rule| {
+			if rule.states.is_empty() {
+				return Some(RestrictedItem::new(rule.policy.clone(), rule.reason.clone()));
+			}
+			let state = state?;
+			rule.states.iter().any(|rule_state| rule_state.eq_ignore_ascii_case(state)).then(|| RestrictedItem::new(rule.policy.clone(), rule.reason.clone()).with_state(state.to_string()))
+		})
+	}
+}
+
+///
+/// # `showroom_state`
+/// Two-letter state a showroom is located in.
+///
+#[must_use]
+pub fn showroom_state(showroom: Option<&str>) -> Option<&'static str> {
+	match showroom?.to_lowercase().as_str() {
+		"houston" | "dallas" => Some("TX"),
+		"florida" => Some("FL"),
+		"los angeles" => Some("CA"),
+		"chicago" => Some("IL"),
+		"new york" => Some("NY"),
+		_ => None,
+	}
+}
+
+fn filter_slot() -> &'static RwLock<Option<Arc<dyn ComplianceFilter>>> {
+	static FILTER: OnceLock<RwLock<Option<Arc<dyn ComplianceFilter>>>> = OnceLock::new();
+	FILTER.get_or_init(|| RwLock::new(None))
+}
+
+///
+/// # `set_compliance_filter`
+/// Install the filter every request is checked against before a vendor is called.
+///
+pub fn set_compliance_filter(filter: Arc<dyn ComplianceFilter>) {
+	*filter_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = Some(filter);
+}
+
+///
+/// # `compliance_filter`
+/// The installed compliance filter, if any.
+///
+pub fn compliance_filter() -> Option<Arc<dyn ComplianceFilter>> {
+	filter_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
+
+pub(crate) fn check(req: &AvailabilityRequest) -> Option<RestrictedItem> {
+	compliance_filter()?.check(req)
+}
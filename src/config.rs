@@ -0,0 +1,338 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, SystemTime};
+
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use super::cache::{set_result_cache_config, ResultCacheConfig};
+use super::schedule::{self, Workload};
+use super::{http_client, telemetry, WAREHOUSE_TABLE};
+
+///
+/// Manufacturers a warehouse mapping may name.
+///
+const MANUFACTURERS: [&str; 13] = ["bsh", "thermador", "gaggenau", "subzero", "wolf", "miele", "liebherr", "fisher_paykel", "jennair", "monogram", "true_residential", "dacor", "bertazzoni"];
+
+///
+/// Showroom to manufacturer to warehouse, as configured in `RuntimeConfig::warehouses`.
+///
+pub type WarehouseMap = BTreeMap<String, BTreeMap<String, String>>;
+
+///
+/// # `RuntimeConfig`
+/// Settings that can change while the service runs, usually read from a JSON or TOML file by
+/// `reload_config_file`. Maps replace what was configured before as a whole, so a host left out of
+/// `host_rate_limits_ms` is no longer throttled; a cache TTL left out keeps its current value.
+///
+/// ## Example
+/// ```
+/// use eggersmann_app_server_appliance_availability::config::RuntimeConfig;
+///
+/// let config: RuntimeConfig = serde_json::from_str(r#"{"warehouses": {"austin": {"bsh": "US00002148", "subzero": "99432040"}}, "host_rate_limits_ms": {"b2bportal.bsh-group.com": 250}, "negative_ttl_secs": 900}"#).unwrap();
+/// assert!(config.validate().is_ok());
+/// ```
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct RuntimeConfig {
+	/// Warehouse per showroom and manufacturer, used before the built-in `WAREHOUSE_TABLE`.
+	pub warehouses: WarehouseMap,
+	/// Milliseconds between two requests to a host.
+	pub host_rate_limits_ms: BTreeMap<String, u64>,
+	/// Most bytes read of a response body from a host.
+	pub response_limits: BTreeMap<String, usize>,
+	pub positive_ttl_secs: Option<u64>,
+	pub negative_ttl_secs: Option<u64>,
+	/// Locale product descriptions are asked for in per showroom, e.g. `florida` to `es-US`.
+	pub description_locales: BTreeMap<String, String>,
+	/// Job titles whose users see dealer net prices when they ask for them, see
+	/// `AvailabilityRequest::with_pricing`.
+	pub pricing_job_titles: Vec<String>,
+}
+
+impl RuntimeConfig {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	#[must_use]
+	pub fn with_warehouse(mut self, showroom: &str, manufacturer: &str, warehouse: &str) -> Self {
+		self.warehouses.entry(showroom.to_lowercase()).or_default().insert(manufacturer.to_lowercase(), warehouse.to_string());
+		self
+	}
+
+	#[must_use]
+	pub fn with_warehouses(mut self, warehouses: WarehouseMap) -> Self {
+		self.warehouses = warehouses.into_iter().map(|(showroom, warehouses)| (showroom.to_lowercase(), warehouses.into_iter().map(|(manufacturer, warehouse)| (manufacturer.to_lowercase(), warehouse)).collect())).collect();
+		self
+	}
+
+	#[must_use]
+	pub fn with_description_locale(mut self, showroom: &str, locale: &str) -> Self {
+		self.description_locales.insert(showroom.to_lowercase(), locale.to_string());
+		self
+	}
+
+	#[must_use]
+	pub fn with_pricing_job_title(mut self, job_title: &str) -> Self {
+		self.pricing_job_titles.push(job_title.to_string());
+		self
+	}
+
+	#[must_use]
+	pub fn with_host_rate_limit(mut self, host: &str, interval: Duration) -> Self {
+		self.host_rate_limits_ms.insert(host.to_lowercase(), u64::try_from(interval.as_millis()).unwrap_or(u64::MAX));
+		self
+	}
+
+	#[must_use]
+	pub fn with_response_limit(mut self, host: &str, bytes: usize) -> Self {
+		self.response_limits.insert(host.to_lowercase(), bytes);
+		self
+	}
+
+	///
+	/// # `RuntimeConfig::validate`
+	/// Check the configuration before it is applied.
+	///
+	/// # Errors
+	/// Returns every problem found, one per line: an unknown manufacturer, an empty warehouse or host, a
+	/// zero rate limit or response limit, a description locale without a two-letter language, or an empty
+	/// pricing job title.
+	///
+	pub fn validate(&self) -> Result<(), String> {
+		let mut problems = Vec::new();
+		for (showroom, warehouses) in &self.warehouses {
+			if showroom.trim().is_empty() {
+				problems.push("warehouse mapping with an empty showroom".to_string());
+			}
+			for (manufacturer, warehouse) in warehouses {
+				if !MANUFACTURERS.contains(&manufacturer.to_lowercase().as_str()) {
+					problems.push(format!("unknown manufacturer {manufacturer} for showroom {showroom}"));
+				}
+				if warehouse.trim().is_empty() {
+					problems.push(format!("empty warehouse for {manufacturer} at showroom {showroom}"));
+				}
+			}
+		}
+		for (host, interval) in &self.host_rate_limits_ms {
+			if host.trim().is_empty() || *interval == 0 {
+				problems.push(format!("invalid rate limit {interval}ms for host '{host}'"));
+			}
+		}
+		for (host, bytes) in &self.response_limits {
+			if host.trim().is_empty() || *bytes == 0 {
+				problems.push(format!("invalid response limit {bytes} bytes for host '{host}'"));
+			}
+		}
+		for (showroom, locale) in &self.description_locales {
+			if super::language_code(locale).is_none() {
+				problems.push(format!("invalid description locale '{locale}' for showroom {showroom}"));
+			}
+		}
+		if self.pricing_job_titles.iter().any(|job_title| job_title.trim().is_empty()) {
+			problems.push("empty pricing job title".to_string());
+		}
+		if problems.is_empty() {
+			Ok(())
+		} else {
+			Err(format!("Invalid configuration:\n{}", problems.join("\n")))
+		}
+	}
+
+	fn apply(&self) {
+		http_client::replace_host_rate_limits(self.host_rate_limits_ms.iter().map(|(host, interval)| (host.to_lowercase(), Duration::from_millis(*interval))).collect());
+		http_client::replace_response_limits(self.response_limits.iter().map(|(host, bytes)| (host.to_lowercase(), *bytes)).collect());
+		if self.positive_ttl_secs.is_some() || self.negative_ttl_secs.is_some() {
+			let current = super::cache::result_cache_config();
+			set_result_cache_config(ResultCacheConfig::new(self.positive_ttl_secs.map_or(current.positive_ttl, Duration::from_secs), self.negative_ttl_secs.map_or(current.negative_ttl, Duration::from_secs)));
+		}
+	}
+}
+
+#[derive(Debug, Default)]
+struct ConfigState {
+	current: Arc<RuntimeConfig>,
+	previous: Option<Arc<RuntimeConfig>>,
+}
+
+fn state() -> &'static RwLock<ConfigState> {
+	static STATE: OnceLock<RwLock<ConfigState>> = OnceLock::new();
+	STATE.get_or_init(|| RwLock::new(ConfigState::default()))
+}
+
+///
+/// # `reload_config`
+/// Validate `config` and swap it in for the warehouse mappings, the host rate and response limits and the
+/// result cache TTLs. Lookups already running finish with the configuration they started with.
+///
+/// # Errors
+/// Returns the validation problems; the configuration in use is left as it was.
+///
+pub fn reload_config(config: RuntimeConfig) -> Result<(), String> {
+	if let Err(e) = config.validate() {
+		telemetry::event("config.reload.failed", &[("error", e.clone())]);
+		return Err(e);
+	}
+	let config = Arc::new(config);
+	let mut state = state().write().unwrap_or_else(std::sync::PoisonError::into_inner);
+	config.apply();
+	state.previous = Some(std::mem::replace(&mut state.current, config));
+	drop(state);
+	telemetry::event("config.reloaded", &[]);
+	Ok(())
+}
+
+///
+/// # `rollback_config`
+/// Go back to the configuration in use before the last successful reload.
+///
+/// # Errors
+/// Returns an error if nothing was reloaded since the last rollback.
+///
+pub fn rollback_config() -> Result<(), String> {
+	let mut state = state().write().unwrap_or_else(std::sync::PoisonError::into_inner);
+	let previous = state.previous.take().ok_or_else(|| "No previous configuration to roll back to.".to_string())?;
+	previous.apply();
+	state.current = previous;
+	drop(state);
+	telemetry::event("config.rolled_back", &[]);
+	Ok(())
+}
+
+///
+/// # `runtime_config`
+/// The configuration in use, empty until one is loaded.
+///
+#[must_use]
+pub fn runtime_config() -> Arc<RuntimeConfig> {
+	state().read().unwrap_or_else(std::sync::PoisonError::into_inner).current.clone()
+}
+
+///
+/// # `reload_config_file`
+/// Read a `RuntimeConfig` from the file at `path` and reload it. A `.toml` file is read as TOML, which
+/// needs the `toml` feature; anything else as JSON.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or parsed, or the configuration is invalid; the
+/// configuration in use is left as it was.
+///
+pub fn reload_config_file(path: &Path) -> Result<(), String> {
+	reload_config(read_config_file(path)?)
+}
+
+///
+/// # `load_warehouse_file`
+/// Read a showroom to manufacturer to warehouse map from the file at `path` and use it in place of the
+/// configured warehouses, keeping the rest of the configuration. A showroom or manufacturer the file leaves
+/// out still gets its warehouse from `WAREHOUSE_TABLE`. Like `reload_config_file`, a `.toml` file is read
+/// as TOML and anything else as JSON.
+///
+/// Call it at service start, after `reload_config_file` if both are used.
+///
+/// ## Example
+/// ```toml
+/// [houston]
+/// bsh = "US00002148"
+/// liebherr = "TX"
+///
+/// ["new york"]
+/// subzero = "99103710"
+/// ```
+///
+/// # Errors
+/// Returns an error if the file cannot be read or parsed, or names an unknown manufacturer or an empty
+/// warehouse; the configuration in use is left as it was.
+///
+pub fn load_warehouse_file(path: &Path) -> Result<(), String> {
+	let warehouses: WarehouseMap = read_config_file(path)?;
+	reload_config(RuntimeConfig::clone(&runtime_config()).with_warehouses(warehouses))
+}
+
+///
+/// # `default_warehouses`
+/// `WAREHOUSE_TABLE` as a map in the layout `load_warehouse_file` reads, to start a mapping file from.
+///
+#[must_use]
+pub fn default_warehouses() -> WarehouseMap {
+	let mut warehouses = WarehouseMap::new();
+	for (showroom, manufacturers, warehouse) in WAREHOUSE_TABLE {
+		let showroom = warehouses.entry(showroom.to_string()).or_default();
+		for manufacturer in manufacturers {
+			showroom.entry(manufacturer.to_string()).or_insert_with(|| warehouse.to_string());
+		}
+	}
+	warehouses
+}
+
+fn read_config_file<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+	let contents = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {e:?}", path.display()))?;
+	if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("toml")) {
+		#[cfg(feature = "toml")]
+		return toml::from_str(&contents).map_err(|e| format!("Failed to parse {}: {e}", path.display()));
+		#[cfg(not(feature = "toml"))]
+		return Err(format!("Failed to parse {}: reading TOML needs the toml feature.", path.display()));
+	}
+	serde_json::from_str(&contents).map_err(|e| format!("Failed to parse {}: {e}", path.display()))
+}
+
+///
+/// # `watch_config_file`
+/// Load the file at `path` and start a Tokio task that reloads it whenever its modification time changes,
+/// checked every `interval`. A change that fails to load is reported as the `config.reload.failed` event
+/// and the previous configuration stays in use. Outside the hours `schedule` allows routine work, a change
+/// waits for the first check inside them.
+///
+/// Must be called inside a Tokio runtime.
+///
+/// # Errors
+/// Returns an error if the first load fails. The watch is started regardless.
+///
+pub fn watch_config_file(path: PathBuf, interval: Duration) -> Result<(), String> {
+	let modified = |path: &PathBuf| std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+	let mut seen: Option<SystemTime> = modified(&path);
+	let loaded = reload_config_file(&path);
+	tokio::spawn(async move {
+		loop {
+			tokio::time::sleep(interval).await;
+			let current = modified(&path);
+			if current.is_some() && current != seen && schedule::may_run(Workload::Routine, None, None, Utc::now()) {
+				seen = current;
+				if let Err(e) = reload_config_file(&path) {
+					telemetry::event("config.reload.failed", &[("path", path.display().to_string()), ("error", e)]);
+				}
+			}
+		}
+	});
+	loaded
+}
+
+///
+/// The warehouse configured for `manufacturer` at `showroom`, if any.
+///
+pub(crate) fn configured_warehouse(showroom: &str, manufacturer: &str) -> Option<String> {
+	let config = runtime_config();
+	let (_, warehouses) = config.warehouses.iter().find(|(configured, _)| configured.eq_ignore_ascii_case(showroom))?;
+	warehouses.iter().find(|(configured, _)| configured.eq_ignore_ascii_case(manufacturer)).map(|(_, warehouse)| warehouse.clone())
+}
+
+///
+/// Whether users with `job_title` may see dealer net prices.
+///
+pub(crate) fn pricing_allowed_for(job_title: &str) -> bool {
+	runtime_config().pricing_job_titles.iter().any(|allowed| allowed.trim().eq_ignore_ascii_case(job_title.trim()))
+}
+
+///
+/// The description locale configured for `showroom`, if any.
+///
+pub(crate) fn configured_description_locale(showroom: &str) -> Option<String> {
+	let config = runtime_config();
+	config.description_locales.iter().find(|(configured, _)| configured.eq_ignore_ascii_case(showroom)).map(|(_, locale)| locale.clone())
+}
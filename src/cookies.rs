@@ -0,0 +1,373 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use reqwest::cookie::{CookieStore, Jar};
+use reqwest::header::{self, HeaderMap};
+use reqwest::{Response, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::telemetry;
+use crate::tokens;
+
+///
+/// # `SessionCookie`
+/// A cookie of a vendor session with the attributes it was set with, so a saved session expires and is
+/// scoped as the portal set it. `None` for `domain` or `path` means the session's domain and `/`, and
+/// `None` for `expires` a cookie that lasts as long as the session.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionCookie {
+	pub name: String,
+	pub value: String,
+	pub domain: Option<String>,
+	pub path: Option<String>,
+	pub expires: Option<DateTime<Utc>>,
+	pub secure: bool,
+	pub http_only: bool,
+}
+
+///
+/// # `TokenCookie`
+/// A cookie as a browser token saves it, in the shape of a playwright cookie, so a session made over
+/// HTTP is saved like one made in a browser without needing playwright.
+///
+#[cfg(any(feature = "browser-login", feature = "subzero"))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenCookie {
+	pub name: String,
+	pub value: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub domain: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub path: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub expires: Option<f64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub http_only: Option<bool>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub secure: Option<bool>,
+}
+
+impl SessionCookie {
+	///
+	/// A cookie without attributes.
+	///
+	pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+		Self { name: name.into(), value: value.into(), domain: None, path: None, expires: None, secure: false, http_only: false }
+	}
+
+	///
+	/// A cookie as saved in a browser token, which gives the expiry in seconds since the epoch and a
+	/// negative expiry for a cookie without one.
+	///
+	#[allow(clippy::cast_possible_truncation)]
+	pub fn saved(name: &str, value: &str, domain: Option<&str>, path: Option<&str>, expires: Option<f64>) -> Self {
+		let mut cookie = Self::new(name, value);
+		cookie.domain = domain.filter(|domain| !domain.is_empty()).map(str::to_string);
+		cookie.path = path.filter(|path| !path.is_empty()).map(str::to_string);
+		cookie.expires = expires.filter(|expires| *expires >= 0.0).and_then(|expires| DateTime::from_timestamp(expires as i64, 0));
+		cookie
+	}
+
+	///
+	/// A cookie a response set, its `Max-Age` counted from `now`.
+	///
+	fn from_response(cookie: &reqwest::cookie::Cookie<'_>, now: DateTime<Utc>) -> Self {
+		let mut saved = Self::new(cookie.name(), cookie.value());
+		saved.domain = cookie.domain().map(|domain| domain.trim_start_matches('.').to_string());
+		saved.path = cookie.path().map(str::to_string);
+		saved.expires = cookie.max_age().and_then(|max_age| chrono::Duration::from_std(max_age).ok()).map(|max_age| now + max_age).or_else(|| cookie.expires().map(DateTime::<Utc>::from));
+		saved.secure = cookie.secure();
+		saved.http_only = cookie.http_only();
+		saved
+	}
+
+	fn is_expired(&self, now: DateTime<Utc>) -> bool {
+		self.expires.is_some_and(|expires| expires <= now)
+	}
+
+	///
+	/// Whether `other` is this cookie, possibly with another value or expiry. A session has one portal, so
+	/// a cookie the portal sets again for its host replaces the one saved for the session's domain.
+	///
+	fn is_same_cookie(&self, other: &Self) -> bool {
+		self.name == other.name && self.path.as_deref().unwrap_or("/") == other.path.as_deref().unwrap_or("/")
+	}
+
+	///
+	/// Whether `other` would be saved the same: the same value, and an expiry less than a minute off, so
+	/// a `Max-Age` repeated on every response does not count as a refresh.
+	///
+	fn saves_as(&self, other: &Self) -> bool {
+		let close = match (self.expires, other.expires) {
+			(Some(a), Some(b)) => (a - b).num_seconds().abs() < 60,
+			(a, b) => a == b,
+		};
+		self.is_same_cookie(other) && self.domain == other.domain && self.value == other.value && close
+	}
+
+	///
+	/// The `Set-Cookie` header the cookie is put in the reqwest store with, in `domain` unless it names one.
+	///
+	fn set_cookie(&self, domain: Option<&str>) -> String {
+		let mut set_cookie = format!("{}={}; Path={}", self.name, self.value, self.path.as_deref().unwrap_or("/"));
+		if let Some(domain) = self.domain.as_deref().or(domain) {
+			let _ = write!(set_cookie, "; Domain={domain}");
+		}
+		if let Some(expires) = self.expires {
+			let _ = write!(set_cookie, "; Expires={}", expires.format("%a, %d %b %Y %H:%M:%S GMT"));
+		}
+		if self.secure {
+			set_cookie.push_str("; Secure");
+		}
+		if self.http_only {
+			set_cookie.push_str("; HttpOnly");
+		}
+		set_cookie
+	}
+}
+
+///
+/// # `SessionJar`
+/// The cookies of a saved vendor session in a reqwest cookie store. Requests take their `Cookie` header
+/// from it by url, so expired cookies and cookies of another path or host are left out, and the
+/// `Set-Cookie` headers of every response are kept in it with their attributes, so a session the portal
+/// refreshes is sent as refreshed and can be saved again.
+///
+/// Clones share the same cookies.
+///
+#[derive(Debug, Clone)]
+pub struct SessionJar {
+	domain: String,
+	jar: Arc<Jar>,
+	saved: Arc<Mutex<Vec<SessionCookie>>>,
+	changed: Arc<AtomicBool>,
+}
+
+impl SessionJar {
+	///
+	/// A jar of the saved `cookies` of a session with the portal at `origin`. A saved cookie without
+	/// domain is sent to every host of `domain`, as the portal set it.
+	///
+	/// # Errors
+	/// Returns an error if `origin` is not a url.
+	///
+	pub fn new(origin: &str, domain: &str, cookies: impl IntoIterator<Item = SessionCookie>) -> Result<Self, String> {
+		let origin = Url::parse(origin).map_err(|e| format!("Invalid portal url {origin}: {e:?}"))?;
+		let now = Utc::now();
+		let jar = Jar::default();
+		let mut saved = Vec::new();
+		for cookie in cookies.into_iter().filter(|cookie| !cookie.is_expired(now)) {
+			jar.add_cookie_str(&cookie.set_cookie(Some(domain)), &origin);
+			saved.push(cookie);
+		}
+		Ok(Self { domain: domain.to_string(), jar: Arc::new(jar), saved: Arc::new(Mutex::new(saved)), changed: Arc::new(AtomicBool::new(false)) })
+	}
+
+	///
+	/// A jar of the saved `cookies` of a session with the portal at `url`, whose cookies without domain
+	/// are sent to the portal's host.
+	///
+	/// # Errors
+	/// Returns an error if `url` is not a url with a host.
+	///
+	pub fn for_portal(url: &str, cookies: impl IntoIterator<Item = SessionCookie>) -> Result<Self, String> {
+		let host = Url::parse(url).ok().and_then(|url| url.host_str().map(str::to_string)).ok_or_else(|| format!("Invalid portal url {url}"))?;
+		Self::new(url, &host, cookies)
+	}
+
+	///
+	/// Set the `Cookie` header of a request to `url`, or leave it out if no cookie of the session applies.
+	///
+	pub fn apply(&self, headers: &mut HeaderMap, url: &str) {
+		if let Some(cookies) = Url::parse(url).ok().and_then(|url| self.jar.cookies(&url)) {
+			headers.insert(header::COOKIE, cookies);
+		}
+	}
+
+	///
+	/// Keep the cookies `response` set. The session counts as changed if one of them was new, had a new
+	/// value or expiry, or was removed.
+	///
+	pub fn capture(&self, response: &Response) {
+		self.keep(response.url(), response.cookies());
+	}
+
+	fn keep<'a>(&self, url: &Url, set_cookies: impl Iterator<Item = reqwest::cookie::Cookie<'a>>) {
+		let now = Utc::now();
+		let mut saved = self.saved.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+		for cookie in set_cookies.map(|cookie| SessionCookie::from_response(&cookie, now)) {
+			let previous = saved.iter().position(|saved| saved.is_same_cookie(&cookie)).map(|index| saved.remove(index));
+			if let Some(previous) = &previous {
+				// the store keeps a cookie of another domain apart, so the replaced one is removed from it first.
+				let removed = SessionCookie { expires: Some(DateTime::UNIX_EPOCH), ..previous.clone() };
+				self.jar.add_cookie_str(&removed.set_cookie(Some(&self.domain)), url);
+			}
+			self.jar.add_cookie_str(&cookie.set_cookie(None), url);
+			// a cookie the session never had is only news if it is still alive.
+			let unchanged = previous.as_ref().map_or_else(|| cookie.is_expired(now), |previous| previous.saves_as(&cookie));
+			if !unchanged {
+				self.changed.store(true, Ordering::Relaxed);
+			}
+			if !cookie.is_expired(now) {
+				saved.push(cookie);
+			}
+		}
+	}
+
+	///
+	/// Whether the session changed since it was loaded or last saved, clearing the mark.
+	///
+	pub fn take_changed(&self) -> bool {
+		self.changed.swap(false, Ordering::Relaxed)
+	}
+
+	///
+	/// Every cookie of the session that has not expired, with the attributes it was set with.
+	///
+	pub fn cookies(&self) -> Vec<SessionCookie> {
+		let now = Utc::now();
+		self.saved.lock().unwrap_or_else(std::sync::PoisonError::into_inner).iter().filter(|cookie| !cookie.is_expired(now)).cloned().collect()
+	}
+
+	///
+	/// The session as the browser cookies vendor tokens are saved with.
+	///
+	#[cfg(any(feature = "browser-login", feature = "subzero"))]
+	#[allow(clippy::cast_precision_loss)]
+	pub fn token_cookies(&self) -> Vec<TokenCookie> {
+		self.cookies()
+			.into_iter()
+			.map(|cookie| TokenCookie {
+				domain: Some(cookie.domain.unwrap_or_else(|| self.domain.clone())),
+				path: Some(cookie.path.unwrap_or_else(|| "/".to_string())),
+				expires: cookie.expires.map(|expires| expires.timestamp() as f64),
+				http_only: Some(cookie.http_only),
+				secure: Some(cookie.secure),
+				name: cookie.name,
+				value: cookie.value,
+			})
+			.collect()
+	}
+}
+
+///
+/// The session saved under `name` in the `TokenStore` for the portal at `url`, `None` if there is none.
+///
+pub async fn load_session(name: &str, url: &str) -> Option<SessionJar> {
+	let file: Value = serde_json::from_str(&tokens::load(name).await.ok()??).ok()?;
+	let cookies: Vec<SessionCookie> = serde_json::from_value(file["cookies"].clone()).ok()?;
+	if cookies.is_empty() {
+		return None;
+	}
+	SessionJar::for_portal(url, cookies).ok()
+}
+
+///
+/// Save `session` under `name` in the `TokenStore`, every cookie with its attributes.
+///
+/// # Errors
+/// Returns an error if the session cannot be written.
+///
+pub async fn save_session(name: &str, session: &SessionJar) -> Result<(), String> {
+	session.take_changed();
+	let token_json = json!({ "cookies": session.cookies(), "saved_at": Utc::now().to_rfc3339() }).to_string();
+	tokens::save(name, &token_json).await.map_err(|e| format!("Failed to write session {name}: {e:?}"))
+}
+
+///
+/// Save `session` under `name` if the portal refreshed it. A session of `manufacturer` that cannot be
+/// saved is kept for this lookup only.
+///
+pub async fn save_refreshed_session(name: &str, manufacturer: &str, session: &SessionJar) {
+	if !session.take_changed() {
+		return;
+	}
+	match save_session(name, session).await {
+		Ok(()) => telemetry::counter("session.saved", 1, &[("manufacturer", manufacturer.to_string())]),
+		Err(e) => telemetry::event("session.save_failed", &[("manufacturer", manufacturer.to_string()), ("error", e)]),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn response(set_cookies: &[&str]) -> Response {
+		let mut response = http::Response::builder();
+		for set_cookie in set_cookies {
+			response = response.header(header::SET_COOKIE, *set_cookie);
+		}
+		Response::from(response.body(String::new()).unwrap())
+	}
+
+	fn portal() -> Url {
+		Url::parse("https://portal.example.com/app/login").unwrap()
+	}
+
+	#[test]
+	fn captured_cookies_keep_their_attributes() {
+		let jar = SessionJar::new("https://portal.example.com/", "example.com", []).unwrap();
+		jar.keep(&portal(), response(&["SESSION=a1; Path=/app; Expires=Wed, 01 Jan 2031 00:00:00 GMT", "TRACK=t; Domain=.example.com; Path=/; Max-Age=3600; Secure; HttpOnly"]).cookies());
+
+		let cookies = jar.cookies();
+		let session = cookies.iter().find(|cookie| cookie.name == "SESSION").unwrap();
+		assert_eq!((session.value.as_str(), session.path.as_deref(), session.domain.as_deref()), ("a1", Some("/app"), None));
+		assert_eq!(session.expires, DateTime::from_timestamp(1_924_992_000, 0));
+		let track = cookies.iter().find(|cookie| cookie.name == "TRACK").unwrap();
+		assert_eq!((track.domain.as_deref(), track.secure, track.http_only), (Some("example.com"), true, true));
+		let expires_in = track.expires.unwrap() - Utc::now();
+		assert!(expires_in > chrono::Duration::minutes(59) && expires_in <= chrono::Duration::hours(1));
+
+		// only cookies of the path and host are sent.
+		let mut headers = HeaderMap::new();
+		jar.apply(&mut headers, "https://portal.example.com/app/stock");
+		let mut sent: Vec<&str> = headers[header::COOKIE].to_str().unwrap().split("; ").collect();
+		sent.sort_unstable();
+		assert_eq!(sent, ["SESSION=a1", "TRACK=t"]);
+		let mut headers = HeaderMap::new();
+		jar.apply(&mut headers, "https://cdn.example.com/");
+		assert_eq!(headers[header::COOKIE], "TRACK=t");
+	}
+
+	#[test]
+	fn saved_cookies_expire_as_set() {
+		let expired = SessionCookie::saved("OLD", "o", None, None, Some(1_000_000_000.0));
+		let session = SessionCookie::saved("SESSION", "s", Some(""), Some(""), Some(-1.0));
+		let jar = SessionJar::new("https://portal.example.com/", "example.com", [expired, session]).unwrap();
+		assert_eq!(jar.cookies(), vec![SessionCookie::new("SESSION", "s")]);
+		let mut headers = HeaderMap::new();
+		jar.apply(&mut headers, "https://portal.example.com/");
+		assert_eq!(headers[header::COOKIE], "SESSION=s");
+	}
+
+	#[test]
+	fn take_changed_marks_refreshes_only() {
+		let jar = SessionJar::new("https://portal.example.com/", "example.com", [SessionCookie::new("SESSION", "a1")]).unwrap();
+		assert!(!jar.take_changed());
+
+		// the same value again is no refresh, nor is a Max-Age repeated on every response.
+		jar.keep(&portal(), response(&["SESSION=a1"]).cookies());
+		assert!(!jar.take_changed());
+		jar.keep(&portal(), response(&["TRACK=t; Path=/; Max-Age=3600"]).cookies());
+		assert!(jar.take_changed());
+		jar.keep(&portal(), response(&["TRACK=t; Path=/; Max-Age=3600"]).cookies());
+		assert!(!jar.take_changed());
+
+		// a new value or a longer life is, and the mark is cleared once taken.
+		jar.keep(&portal(), response(&["TRACK=t; Path=/; Max-Age=7200"]).cookies());
+		assert!(jar.take_changed());
+		assert!(!jar.take_changed());
+		jar.keep(&portal(), response(&["SESSION=b2"]).cookies());
+		assert!(jar.take_changed());
+
+		// a cookie the portal removes is left out of the saved session.
+		jar.keep(&portal(), response(&["TRACK=; Path=/; Max-Age=0"]).cookies());
+		assert!(jar.take_changed());
+		assert_eq!(jar.cookies().iter().map(|cookie| cookie.name.as_str()).collect::<Vec<_>>(), ["SESSION"]);
+	}
+}
@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+
+#[cfg(feature = "keyvault")]
+use azure_security_keyvault::KeyvaultClient;
+use zeroize::Zeroize;
+
+pub use super::secrets::DEFAULT_SECRET_MAX_AGE;
+
+///
+/// Vault the vendor credentials are read from when no provider is configured.
+///
+#[cfg(feature = "keyvault")]
+pub const DEFAULT_KEY_VAULT_URL: &str = "https://eggappserverkeyvault.vault.azure.net";
+
+///
+/// # `SecretString`
+/// A credential such as a vendor password. Its memory is overwritten with zeros when it is dropped and it
+/// prints as `[redacted]`, so it does not outlive its use or end up in a log; `expose` reads it.
+///
+/// ## Example
+/// ```
+/// use eggersmann_app_server_appliance_availability::credentials::SecretString;
+///
+/// let password = SecretString::from("hunter2");
+/// assert_eq!(format!("{password:?}"), "[redacted]");
+/// assert_eq!(password.expose(), "hunter2");
+/// ```
+///
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretString(String);
+
+impl SecretString {
+	#[must_use]
+	pub const fn new(secret: String) -> Self {
+		Self(secret)
+	}
+
+	///
+	/// The secret itself, for the one place that has to send it.
+	///
+	#[must_use]
+	pub fn expose(&self) -> &str {
+		&self.0
+	}
+
+	#[must_use]
+	pub const fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+}
+
+impl fmt::Debug for SecretString {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("[redacted]")
+	}
+}
+
+impl Drop for SecretString {
+	fn drop(&mut self) {
+		self.0.zeroize();
+	}
+}
+
+impl From<String> for SecretString {
+	fn from(secret: String) -> Self {
+		Self(secret)
+	}
+}
+
+impl From<&str> for SecretString {
+	fn from(secret: &str) -> Self {
+		Self(secret.to_string())
+	}
+}
+
+///
+/// # `CredentialProvider`
+/// Where vendor credentials are read from. Secrets are asked for by the names the crate uses, e.g.
+/// `bsh-username`, after `set_secret_name` renames. Fetched values are cached process-wide for
+/// `secret_max_age`, so a provider does not need to cache itself; `invalidate_secret` drops a value early,
+/// e.g. after a password was rotated.
+///
+/// ## Example
+/// ```
+/// use std::sync::Arc;
+/// use eggersmann_app_server_appliance_availability::credentials::{set_credential_provider, StaticProvider};
+///
+/// set_credential_provider(Arc::new(StaticProvider::new().with_secret("bsh-username", "dev").with_secret("bsh-password", "dev")));
+/// ```
+///
+#[async_trait::async_trait]
+pub trait CredentialProvider: Send + Sync {
+	///
+	/// The value of the secret `name`.
+	///
+	async fn secret(&self, name: &str) -> Result<SecretString, String>;
+
+	///
+	/// Where the secret `name` is kept, for the operator who has to rotate it, e.g. `Key Vault https://…`.
+	///
+	fn location(&self, _name: &str) -> Option<String> {
+		None
+	}
+}
+
+///
+/// # `KeyVaultProvider`
+/// Secrets from an Azure Key Vault, authenticated with the Azure identity of the service. The client of
+/// each vault is created once and shared by every fetch.
+///
+#[cfg(feature = "keyvault")]
+#[cfg_attr(docsrs, doc(cfg(feature = "keyvault")))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct KeyVaultProvider {
+	pub url: String,
+}
+
+#[cfg(feature = "keyvault")]
+impl KeyVaultProvider {
+	#[must_use]
+	pub const fn new(url: String) -> Self {
+		Self { url }
+	}
+}
+
+#[cfg(feature = "keyvault")]
+impl Default for KeyVaultProvider {
+	fn default() -> Self {
+		Self::new(DEFAULT_KEY_VAULT_URL.to_string())
+	}
+}
+
+#[cfg(feature = "keyvault")]
+#[async_trait::async_trait]
+impl CredentialProvider for KeyVaultProvider {
+	async fn secret(&self, name: &str) -> Result<SecretString, String> {
+		let client = keyvault_client(&self.url)?;
+		Ok(SecretString::new(client.secret_client().get(name).await.map_err(|e| format!("Failed to get secret {name}: {e}"))?.value))
+	}
+
+	fn location(&self, _name: &str) -> Option<String> {
+		Some(format!("Key Vault {}", self.url))
+	}
+}
+
+///
+/// The client of the vault at `url`, created on first use.
+///
+#[cfg(feature = "keyvault")]
+fn keyvault_client(url: &str) -> Result<Arc<KeyvaultClient>, String> {
+	static CLIENTS: OnceLock<RwLock<HashMap<String, Arc<KeyvaultClient>>>> = OnceLock::new();
+	let clients = CLIENTS.get_or_init(|| RwLock::new(HashMap::new()));
+	let cached = clients.read().unwrap_or_else(std::sync::PoisonError::into_inner).get(url).cloned();
+	if let Some(client) = cached {
+		return Ok(client);
+	}
+	let azure_credentials = azure_identity::create_credential().map_err(|e| format!("Failed to get Azure Identity: {e}"))?;
+	let client = Arc::new(KeyvaultClient::new(url, azure_credentials).map_err(|e| format!("Failed to get Keyvault Client: {e}"))?);
+	clients.write().unwrap_or_else(std::sync::PoisonError::into_inner).insert(url.to_string(), client.clone());
+	Ok(client)
+}
+
+///
+/// # `EnvironmentProvider`
+/// Secrets from environment variables. The variable is `prefix` followed by the secret name uppercased
+/// with dashes turned into underscores, so `bsh-username` is `BSH_USERNAME` without a prefix.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EnvironmentProvider {
+	pub prefix: String,
+}
+
+impl EnvironmentProvider {
+	#[must_use]
+	pub const fn new() -> Self {
+		Self { prefix: String::new() }
+	}
+
+	#[must_use]
+	pub fn with_prefix(mut self, prefix: String) -> Self {
+		self.prefix = prefix;
+		self
+	}
+
+	///
+	/// The variable holding the secret `name`.
+	///
+	#[must_use]
+	pub fn variable(&self, name: &str) -> String {
+		format!("{}{}", self.prefix, name.to_uppercase().replace('-', "_"))
+	}
+}
+
+#[async_trait::async_trait]
+impl CredentialProvider for EnvironmentProvider {
+	async fn secret(&self, name: &str) -> Result<SecretString, String> {
+		let variable = self.variable(name);
+		std::env::var(&variable).map(SecretString::new).map_err(|_| format!("Failed to get secret {name}: {variable} is not set."))
+	}
+
+	fn location(&self, name: &str) -> Option<String> {
+		Some(format!("environment variable {}", self.variable(name)))
+	}
+}
+
+///
+/// # `StaticProvider`
+/// Secrets held in memory, for development and tests without access to a vault.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct StaticProvider {
+	pub secrets: HashMap<String, SecretString>,
+}
+
+impl StaticProvider {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	#[must_use]
+	pub fn with_secret(mut self, name: &str, value: &str) -> Self {
+		self.secrets.insert(name.to_string(), SecretString::from(value));
+		self
+	}
+}
+
+#[async_trait::async_trait]
+impl CredentialProvider for StaticProvider {
+	async fn secret(&self, name: &str) -> Result<SecretString, String> {
+		self.secrets.get(name).cloned().ok_or_else(|| format!("Failed to get secret {name}."))
+	}
+}
+
+///
+/// Key Vault with the `keyvault` feature, the environment without it.
+///
+fn default_provider() -> Arc<dyn CredentialProvider> {
+	#[cfg(feature = "keyvault")]
+	let provider: Arc<dyn CredentialProvider> = Arc::new(KeyVaultProvider::default());
+	#[cfg(not(feature = "keyvault"))]
+	let provider: Arc<dyn CredentialProvider> = Arc::new(EnvironmentProvider::new());
+	provider
+}
+
+fn provider_slot() -> &'static RwLock<Arc<dyn CredentialProvider>> {
+	static PROVIDER: OnceLock<RwLock<Arc<dyn CredentialProvider>>> = OnceLock::new();
+	PROVIDER.get_or_init(|| RwLock::new(default_provider()))
+}
+
+fn secret_names() -> &'static RwLock<HashMap<String, String>> {
+	static NAMES: OnceLock<RwLock<HashMap<String, String>>> = OnceLock::new();
+	NAMES.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+///
+/// # `set_credential_provider`
+/// Read vendor credentials from `provider` from now on, dropping the values fetched from the previous one.
+///
+pub fn set_credential_provider(provider: Arc<dyn CredentialProvider>) {
+	*provider_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = provider;
+	super::secrets::clear();
+}
+
+///
+/// # `credential_provider`
+/// The provider vendor credentials are read from.
+///
+#[must_use]
+pub fn credential_provider() -> Arc<dyn CredentialProvider> {
+	provider_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
+
+///
+/// # `set_secret_name`
+/// Ask the provider for `stored` whenever the crate needs the secret `name`, e.g. `bsh-username` stored
+/// as `Bsh-Portal-User`.
+///
+pub fn set_secret_name(name: &str, stored: &str) {
+	secret_names().write().unwrap_or_else(std::sync::PoisonError::into_inner).insert(name.to_string(), stored.to_string());
+	super::secrets::clear();
+}
+
+///
+/// # `secret_name`
+/// The name the secret `name` is stored under at the provider.
+///
+#[must_use]
+pub fn secret_name(name: &str) -> String {
+	secret_names().read().unwrap_or_else(std::sync::PoisonError::into_inner).get(name).cloned().unwrap_or_else(|| name.to_string())
+}
+
+///
+/// # `set_secret_max_age`
+/// Use a fetched secret for `max_age` before fetching it again, one hour by default.
+///
+pub fn set_secret_max_age(max_age: Duration) {
+	super::secrets::set_max_age(max_age);
+}
+
+///
+/// # `secret_max_age`
+/// How long a fetched secret is used before it is fetched again.
+///
+#[must_use]
+pub fn secret_max_age() -> Duration {
+	super::secrets::max_age()
+}
+
+///
+/// # `invalidate_secret`
+/// Fetch the secret `name` from the provider again on its next use.
+///
+pub fn invalidate_secret(name: &str) {
+	super::secrets::invalidate(name);
+}
+
+///
+/// # `invalidate_secrets`
+/// Fetch every secret from the provider again on its next use.
+///
+pub fn invalidate_secrets() {
+	super::secrets::clear();
+}
@@ -0,0 +1,232 @@
+use std::sync::{OnceLock, RwLock};
+
+use reqwest::header::{self, HeaderMap, HeaderValue};
+use reqwest::StatusCode;
+use serde_json::Value;
+
+use super::backend::{BackendAnswer, ManufacturerBackend};
+use super::{Availability, AvailabilityError, AvailabilityRequest, ProductInfo};
+use crate::cookies::{self, SessionJar};
+use crate::credentials::SecretString;
+use crate::http_client::{self, HttpClient};
+use crate::stock::{self, StockKeys};
+use crate::telemetry;
+
+///
+/// Name the Dacor session saved by `dacor_login` is kept under in the `TokenStore`.
+///
+pub const DACOR_TOKEN: &str = "dacor_cookies.json";
+
+///
+/// # `DacorConfig`
+/// Endpoints of the Samsung Dacor dealer portal: the form login and the stock query, which takes the
+/// model as `model` and the warehouse as `warehouse` and answers with JSON, the current lead time
+/// included. The portal addresses come with the dealer account, so there is no default and lookups fail
+/// until `set_dacor_config` is called.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DacorConfig {
+	pub login_url: String,
+	pub stock_url: String,
+}
+
+impl DacorConfig {
+	#[must_use]
+	pub const fn new(login_url: String, stock_url: String) -> Self {
+		Self { login_url, stock_url }
+	}
+}
+
+fn config_slot() -> &'static RwLock<Option<DacorConfig>> {
+	static CONFIG: OnceLock<RwLock<Option<DacorConfig>>> = OnceLock::new();
+	CONFIG.get_or_init(|| RwLock::new(None))
+}
+
+///
+/// # `set_dacor_config`
+/// Set the endpoints of the Dacor dealer portal.
+///
+pub fn set_dacor_config(config: DacorConfig) {
+	*config_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = Some(config);
+}
+
+///
+/// # `dacor_config`
+/// The endpoints of the Dacor dealer portal, `None` until they are set.
+///
+#[must_use]
+pub fn dacor_config() -> Option<DacorConfig> {
+	config_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
+
+fn configured() -> Result<DacorConfig, String> {
+	dacor_config().ok_or_else(|| "No Dacor dealer portal configured, see `set_dacor_config`.".to_string())
+}
+
+///
+/// # `DacorBackend`
+/// Dacor lookups through the Samsung Dacor dealer portal, registered as `dacor`. The login and the stock
+/// query go through `client`, the shared client unless one is given.
+///
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct DacorBackend {
+	pub client: HttpClient,
+}
+
+impl DacorBackend {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	#[must_use]
+	pub fn with_client(mut self, client: HttpClient) -> Self {
+		self.client = client;
+		self
+	}
+}
+
+#[async_trait::async_trait]
+impl ManufacturerBackend for DacorBackend {
+	fn name(&self) -> &'static str {
+		"dacor"
+	}
+
+	async fn availability(&self, req: &AvailabilityRequest) -> Result<BackendAnswer, AvailabilityError> {
+		let (username, password) = crate::secrets::vendor_credentials("dacor").await?;
+		let (availability, product) = http_client::scope(self.client.clone(), dacor_lookup(req.clone(), username, password)).await?;
+		Ok(BackendAnswer::new(availability).with_product(product))
+	}
+
+	async fn login(&self) -> Result<(), AvailabilityError> {
+		let (username, password) = crate::secrets::vendor_credentials("dacor").await?;
+		Ok(http_client::scope(self.client.clone(), dacor_login(username, password)).await?)
+	}
+}
+
+///
+/// # Dacor Lookup
+/// Gets the availability of a Dacor appliance at the request's warehouse together with its description.
+/// A model without stock that Samsung builds to order is available after its lead time.
+///
+/// A session the portal turns away is logged in again once and the query repeated.
+///
+/// # Errors
+/// Returns `AvailabilityError::VendorUnavailable` if the portal cannot be reached or its answer cannot be
+/// read, and `AvailabilityError::Other` without a configured portal, for a failed login or a request
+/// without model number.
+///
+pub async fn dacor_lookup(req: AvailabilityRequest, username: SecretString, password: SecretString) -> Result<(Availability, Option<ProductInfo>), AvailabilityError> {
+	let today = req.business_today();
+	let model_number = requested_model(&req)?;
+	Ok(dacor_query(&req, username, password).await?.map_or_else(|| (Availability::not_found(), None), |data| stock::json_stock(&data, &STOCK_KEYS, "Dacor", &model_number, today)))
+}
+
+///
+/// # Dacor Lead Time
+/// Gets how many days Samsung currently needs to build and ship the requested Dacor model to the
+/// request's warehouse, whether or not there is stock.
+///
+/// ## Outputs
+/// `Option<u32>` - The lead time in days, `None` for a model the portal does not know or gives no lead
+/// time for.
+///
+/// # Errors
+/// Returns the errors of `dacor_lookup`.
+///
+pub async fn dacor_lead_time(req: AvailabilityRequest) -> Result<Option<u32>, AvailabilityError> {
+	requested_model(&req)?;
+	let (username, password) = crate::secrets::vendor_credentials("dacor").await?;
+	Ok(dacor_query(&req, username, password).await?.and_then(|data| stock::lead_time_days(&data, &STOCK_KEYS)))
+}
+
+fn requested_model(req: &AvailabilityRequest) -> Result<String, AvailabilityError> {
+	req.model_number.as_deref().map(|model_number| model_number.trim().to_uppercase()).ok_or_else(|| AvailabilityError::Other("No model number found.".to_string()))
+}
+
+///
+/// The stock answer for the requested model, logging in again once if the session was turned away.
+/// `None` if the portal does not know the model.
+///
+async fn dacor_query(req: &AvailabilityRequest, username: SecretString, password: SecretString) -> Result<Option<Value>, AvailabilityError> {
+	let config = configured()?;
+	let session = dacor_session(&config, username.clone(), password.clone()).await?;
+	match dacor_stock(&config, req, &session).await {
+		Err(AvailabilityError::SessionExpired) => {
+			telemetry::counter("session.refreshed", 1, &[("manufacturer", "dacor".to_string())]);
+			let session = login_session(&config, username, password).await?;
+			dacor_stock(&config, req, &session).await
+		}
+		result => result,
+	}
+}
+
+///
+/// Query the stock of the requested model with `session`, saving the session if the portal refreshed it.
+///
+async fn dacor_stock(config: &DacorConfig, req: &AvailabilityRequest, session: &SessionJar) -> Result<Option<Value>, AvailabilityError> {
+	let model_number = requested_model(req)?;
+	let mut headers = HeaderMap::new();
+	session.apply(&mut headers, &config.stock_url);
+	headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+
+	let query = [("model", model_number.as_str()), ("warehouse", req.warehouse.as_deref().unwrap_or_default()), ("quantity", &req.requested_quantity().to_string())];
+	let response = http_client::client().get(&config.stock_url).headers(headers).query(&query).send().await.map_err(|e| AvailabilityError::VendorUnavailable(format!("Failed to get Dacor stock: {e:?}")))?;
+	session.capture(&response);
+	cookies::save_refreshed_session(DACOR_TOKEN, "dacor", session).await;
+	if http_client::auth_rejected(&response) {
+		return Err(AvailabilityError::SessionExpired);
+	}
+	match response.status() {
+		StatusCode::NOT_FOUND => return Ok(None),
+		status if !status.is_success() => return Err(AvailabilityError::VendorUnavailable(format!("Failed to get Dacor stock: {status}"))),
+		_ => {}
+	}
+	Ok(Some(http_client::json(response).await?))
+}
+
+///
+/// Fields of the stock answer, for `stock::json_stock`. A model without stock that Samsung builds to
+/// order is expected after its lead time, given in days or in weeks.
+///
+const STOCK_KEYS: StockKeys = StockKeys::new("items", "model", "availableQuantity", "nextAvailableDate").with_next_quantity("nextAvailableQuantity").with_lead_time("leadTimeDays", "leadTimeWeeks");
+
+///
+/// The saved session, logging in first when there is none.
+///
+async fn dacor_session(config: &DacorConfig, username: SecretString, password: SecretString) -> Result<SessionJar, String> {
+	if let Some(session) = cookies::load_session(DACOR_TOKEN, &config.login_url).await {
+		return Ok(session);
+	}
+	login_session(config, username, password).await
+}
+
+///
+/// # Login to Dacor System
+/// Log in to the Samsung Dacor dealer portal and save the session cookies it sets.
+///
+/// # Errors
+/// Returns an error if the portal is not configured or cannot be reached, rejects the credentials or sets
+/// no session cookie, or the session cannot be saved.
+///
+pub async fn dacor_login(username: SecretString, password: SecretString) -> Result<(), String> {
+	login_session(&configured()?, username, password).await.map(drop)
+}
+
+async fn login_session(config: &DacorConfig, username: SecretString, password: SecretString) -> Result<SessionJar, String> {
+	telemetry::counter("vendor.login", 1, &[("manufacturer", "dacor".to_string())]);
+	let form = [("username", username.expose()), ("password", password.expose())];
+	let response = http_client::client().post(&config.login_url).form(&form).send().await.map_err(|e| format!("Failed to log in to Dacor: {e:?}"))?;
+	if !response.status().is_success() {
+		return Err(format!("Dacor login was rejected: {}", response.status()));
+	}
+	let session = SessionJar::for_portal(&config.login_url, [])?;
+	session.capture(&response);
+	if session.cookies().is_empty() {
+		return Err("Dacor login set no session cookie.".to_string());
+	}
+	cookies::save_session(DACOR_TOKEN, &session).await?;
+	Ok(session)
+}
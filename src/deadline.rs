@@ -0,0 +1,126 @@
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use super::AvailabilityError;
+
+///
+/// A timeout for interactive lookups, vendor logins included, for callers that want one, e.g.
+/// `RequestOptions::new().with_timeout(DEFAULT_LOOKUP_TIMEOUT)`. Lookups are not limited unless asked.
+///
+pub const DEFAULT_LOOKUP_TIMEOUT: Duration = Duration::from_mins(2);
+
+tokio::task_local! {
+	static CURRENT_DEADLINE: Instant;
+}
+
+///
+/// # `RequestOptions`
+/// Limits on how long a lookup may take. Every vendor HTTP call and browser login made for the lookup
+/// stops at the earlier of `timeout` after the lookup started and `deadline`, and the lookup fails with
+/// `AvailabilityError::DeadlineExceeded`. Without either the lookup takes as long as the vendors do,
+/// unless it runs under the deadline of an outer lookup.
+///
+/// ## Example
+/// ```no_run
+/// use std::time::Duration;
+/// use eggersmann_app_server_appliance_availability::{AvailabilityRequest, RequestOptions};
+///
+/// # async fn lookup() -> Result<(), String> {
+/// let req = AvailabilityRequest::new("bsh".to_string(), "houston".to_string(), "HBLP651RUC".to_string());
+/// let req = req.parse_manufacturer().get_warehouse().get_time().into_answered_with(RequestOptions::new().with_timeout(Duration::from_secs(20))).await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RequestOptions {
+	pub timeout: Option<Duration>,
+	pub deadline: Option<Instant>,
+}
+
+impl Default for RequestOptions {
+	fn default() -> Self {
+		Self::unbounded()
+	}
+}
+
+impl RequestOptions {
+	///
+	/// # `RequestOptions::new`
+	/// No limits, add them with `with_timeout` and `with_deadline`.
+	///
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	///
+	/// # `RequestOptions::unbounded`
+	/// Let the lookup take as long as the vendors do.
+	///
+	#[must_use]
+	pub const fn unbounded() -> Self {
+		Self { timeout: None, deadline: None }
+	}
+
+	#[must_use]
+	pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+		self.timeout = Some(timeout);
+		self
+	}
+
+	#[must_use]
+	pub const fn with_deadline(mut self, deadline: Instant) -> Self {
+		self.deadline = Some(deadline);
+		self
+	}
+
+	///
+	/// The instant the work started at `started` has to stop, the earlier of both limits and any deadline
+	/// the calling task already runs under.
+	///
+	fn expires_at(&self, started: Instant) -> Option<Instant> {
+		[self.timeout.map(|timeout| started + timeout), self.deadline, CURRENT_DEADLINE.try_with(|deadline| *deadline).ok()].into_iter().flatten().min()
+	}
+
+	///
+	/// # `RequestOptions::run`
+	/// Run `future` under these limits, so the HTTP calls it makes are cut off at the deadline as well.
+	///
+	/// # Errors
+	/// Returns `AvailabilityError::DeadlineExceeded` if `future` did not finish in time, otherwise what it
+	/// returned.
+	///
+	pub async fn run<T, E, F>(&self, future: F) -> Result<T, E>
+	where
+		F: Future<Output = Result<T, E>>,
+		E: From<AvailabilityError>,
+	{
+		let Some(expires_at) = self.expires_at(Instant::now()) else { return future.await };
+		CURRENT_DEADLINE.scope(expires_at, async move { tokio::time::timeout_at(expires_at, future).await.unwrap_or_else(|_| Err(AvailabilityError::DeadlineExceeded.into())) }).await
+	}
+}
+
+///
+/// Run `future`, e.g. a task spawned for a lookup, under the deadline of the current task. A spawned task
+/// does not inherit task-locals, so without this it would run without the caller's deadline.
+///
+pub fn inherit<F: Future>(future: F) -> impl Future<Output = F::Output> {
+	let deadline = CURRENT_DEADLINE.try_with(|deadline| *deadline).ok();
+	async move {
+		match deadline {
+			Some(deadline) => CURRENT_DEADLINE.scope(deadline, future).await,
+			None => future.await,
+		}
+	}
+}
+
+///
+/// Time left before the deadline the current task runs under, `None` without one.
+///
+pub fn remaining() -> Option<Duration> {
+	CURRENT_DEADLINE.try_with(|deadline| deadline.saturating_duration_since(Instant::now())).ok()
+}
@@ -0,0 +1,384 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinSet;
+
+use super::backend::{backend, registered_backends};
+use super::deadline;
+use super::{Availability, AvailabilityRequest, AvailabilityResponse, AvailabilityStatus, Manufacturer, Warehouse};
+
+///
+/// # `BackendError`
+/// A backend that failed while a model was looked up at every manufacturer.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct BackendError {
+	pub manufacturer: String,
+	pub error: String,
+}
+
+///
+/// # `DiscoveryResult`
+/// The outcome of looking a model up at every registered manufacturer.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct DiscoveryResult {
+	/// Manufacturer of `best`.
+	pub manufacturer: Option<String>,
+	/// The most useful answer among the manufacturers that know the model, see `lookup_any_manufacturer`.
+	pub best: Option<AvailabilityResponse>,
+	/// Every manufacturer that knows the model, in the order they answered.
+	pub matches: Vec<AvailabilityResponse>,
+	pub errors: Vec<BackendError>,
+}
+
+///
+/// Ranking of an answer, higher is more useful to a rep: stock on hand before a promised date before an
+/// answer that could not be read.
+///
+fn rank(response: &AvailabilityResponse) -> u8 {
+	match response.availability_detail.as_ref().map(|availability| availability.status) {
+		Some(AvailabilityStatus::InStock) => 4,
+		Some(AvailabilityStatus::Backordered) => 3,
+		Some(AvailabilityStatus::Discontinued) => 1,
+		_ => 2,
+	}
+}
+
+///
+/// Whether a manufacturer's answer shows it knows `model_number`: the vendor answered for exactly that
+/// model. An answer for a loosely matched model, or one that does not say which model it is for, such as
+/// a vendor error read as `Unknown`, does not count.
+///
+fn knows_model(response: &AvailabilityResponse, model_number: &str) -> bool {
+	response.availability_detail.as_ref().is_some_and(|availability| availability.status != AvailabilityStatus::NotFound && availability.model_number.as_deref().is_some_and(|answered| answered.trim().eq_ignore_ascii_case(model_number.trim())))
+}
+
+///
+/// # `lookup_any_manufacturer`
+/// Look `model_number` up at every registered backend at once, for a rep who does not know the
+/// manufacturer.
+///
+/// The best match is the one in stock, then backordered, then with an unreadable answer, then
+/// discontinued; among equals the manufacturer that answered first wins. Only answers for exactly
+/// `model_number` count. Backends that fail are listed in `errors` and do not hold up the others; backends
+/// whose lookups go through a shared cart are not asked. The lookups stop at the deadline of the caller,
+/// if it runs under one.
+///
+/// ## Inputs
+/// * `showroom`: &str - The showroom location of the user.
+/// * `model_number`: &str - The model of the appliance.
+///
+pub async fn lookup_any_manufacturer(showroom: &str, model_number: &str) -> DiscoveryResult {
+	lookup_manufacturers(showroom, model_number, discoverable_backends()).await
+}
+
+///
+/// The registered backends a model is looked up at when the manufacturer is not known: every one whose
+/// lookups leave the vendor as it was.
+///
+fn discoverable_backends() -> Vec<String> {
+	registered_backends().into_iter().filter(|manufacturer| backend(manufacturer).is_some_and(|backend| !backend.mutates_cart())).collect()
+}
+
+///
+/// Look `model_number` up at each of `manufacturers` at once, see `lookup_any_manufacturer`.
+///
+async fn lookup_manufacturers(showroom: &str, model_number: &str, manufacturers: Vec<String>) -> DiscoveryResult {
+	let mut lookups = JoinSet::new();
+	for (position, manufacturer) in manufacturers.iter().enumerate() {
+		let req = AvailabilityRequest::new(manufacturer.clone(), showroom.to_string(), model_number.to_string());
+		let lookup = deadline::inherit(Box::pin(req.parse_manufacturer().get_warehouse().get_time().into_answered()));
+		lookups.spawn(async move { (position, lookup.await) });
+	}
+
+	let mut pending: Vec<Option<String>> = manufacturers.into_iter().map(Some).collect();
+	let mut matches = Vec::new();
+	let mut errors = Vec::new();
+	let mut failed_tasks = Vec::new();
+	while let Some(joined) = lookups.join_next().await {
+		let (position, outcome) = match joined {
+			Ok(answered) => answered,
+			Err(e) => {
+				failed_tasks.push(format!("Lookup task failed: {e}"));
+				continue;
+			}
+		};
+		let manufacturer = pending.get_mut(position).and_then(Option::take).unwrap_or_default();
+		match outcome {
+			Ok(req) => {
+				let response = req.response();
+				if knows_model(&response, model_number) {
+					matches.push(response);
+				}
+			}
+			Err(error) => errors.push(BackendError { manufacturer, error }),
+		}
+	}
+	// a task that panicked never said where it was, so it is one of the manufacturers left unanswered.
+	errors.extend(pending.into_iter().flatten().zip(failed_tasks).map(|(manufacturer, error)| BackendError { manufacturer, error }));
+
+	// `max_by_key` keeps the last of equals, so search from the back to prefer the first to answer.
+	let best = matches.iter().rev().max_by_key(|response| rank(response)).cloned();
+	DiscoveryResult { manufacturer: best.as_ref().and_then(|best| best.manufacturer.clone()), best, matches, errors }
+}
+
+///
+/// # `WarehouseAvailability`
+/// The answer of one warehouse in `check_all_warehouses`.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct WarehouseAvailability {
+	pub warehouse: String,
+	/// Showrooms supplied from the warehouse, empty for a warehouse the caller set.
+	pub showrooms: Vec<String>,
+	pub response: Option<AvailabilityResponse>,
+	pub error: Option<String>,
+}
+
+///
+/// # `WarehouseCheck`
+/// The outcome of looking a model up at every warehouse of its manufacturer.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct WarehouseCheck {
+	pub manufacturer: Option<String>,
+	/// The warehouse `get_warehouse` resolves for the request.
+	pub mapped_warehouse: Option<String>,
+	/// The warehouse to source from, see `check_all_warehouses`. `None` if no warehouse knows the model.
+	pub recommended: Option<String>,
+	/// Every warehouse, the mapped one first, then in the order of `Warehouse::all_for`.
+	pub warehouses: Vec<WarehouseAvailability>,
+}
+
+impl WarehouseCheck {
+	///
+	/// The answer of the recommended warehouse.
+	///
+	#[must_use]
+	pub fn recommended_response(&self) -> Option<&AvailabilityResponse> {
+		let recommended = self.recommended.as_deref()?;
+		self.warehouses.iter().find(|warehouse| warehouse.warehouse == recommended)?.response.as_ref()
+	}
+}
+
+///
+/// # `check_all_warehouses`
+/// Look `req` up at every warehouse its manufacturer supplies a showroom from, at once, for a rep whose
+/// own warehouse has nothing. Opt-in: it asks the vendor once per warehouse.
+///
+/// The recommended warehouse is the one in stock, then the one with the earliest date; among equals the
+/// warehouse `get_warehouse` resolves for the request wins, then the order of `Warehouse::all_for`.
+/// Warehouses whose lookup fails keep their error and do not hold up the others.
+///
+pub async fn check_all_warehouses(req: AvailabilityRequest) -> WarehouseCheck {
+	let req = req.parse_manufacturer();
+	let mapped_warehouse = req.clone().get_warehouse().warehouse;
+	let mut warehouses = manufacturer_warehouses(&req);
+	if let Some(mapped) = &mapped_warehouse {
+		let position = warehouses.iter().position(|(warehouse, _)| warehouse == mapped);
+		let first = position.map_or_else(|| (mapped.clone(), Vec::new()), |position| warehouses.remove(position));
+		warehouses.insert(0, first);
+	}
+	let results = lookup_warehouses(&req, warehouses).await;
+
+	// every warehouse is asked by the same vendor for the same model, so any answer that found it counts.
+	// `max_by_key` keeps the last of equals, so search from the back to prefer the mapped warehouse.
+	let recommended = results.iter().rev().filter_map(|result| result.response.as_ref().filter(|response| response.found()).map(|response| (result, response))).max_by_key(|(_, response)| (rank(response), Reverse(response.availability_detail.as_ref().and_then(|availability| availability.available_date).unwrap_or(NaiveDate::MAX)))).map(|(result, _)| result.warehouse.clone());
+	WarehouseCheck { manufacturer: req.manufacturer.as_ref().map(Manufacturer::to_string), mapped_warehouse, recommended, warehouses: results }
+}
+
+///
+/// Every warehouse of the manufacturer of `req` with the showrooms it supplies, see `Warehouse::all_for`.
+///
+fn manufacturer_warehouses(req: &AvailabilityRequest) -> Vec<(String, Vec<String>)> {
+	req.manufacturer.as_ref().map(Warehouse::all_for).unwrap_or_default().into_iter().map(|(warehouse, showrooms)| (warehouse.to_string(), showrooms.iter().map(ToString::to_string).collect())).collect()
+}
+
+///
+/// Look `req` up at each of `warehouses` at once, answers in the order of `warehouses`.
+///
+async fn lookup_warehouses(req: &AvailabilityRequest, warehouses: Vec<(String, Vec<String>)>) -> Vec<WarehouseAvailability> {
+	let mut lookups = JoinSet::new();
+	for (position, (warehouse, _)) in warehouses.iter().enumerate() {
+		let lookup = deadline::inherit(Box::pin(req.clone().with_warehouse(warehouse).get_warehouse().get_time().into_answered()));
+		lookups.spawn(async move { (position, lookup.await) });
+	}
+	let mut results: Vec<WarehouseAvailability> = warehouses.into_iter().map(|(warehouse, showrooms)| WarehouseAvailability { warehouse, showrooms, response: None, error: None }).collect();
+	let mut failed_tasks = Vec::new();
+	while let Some(joined) = lookups.join_next().await {
+		let (position, outcome) = match joined {
+			Ok(answered) => answered,
+			Err(e) => {
+				failed_tasks.push(format!("Lookup task failed: {e}"));
+				continue;
+			}
+		};
+		let Some(result) = results.get_mut(position) else { continue };
+		match outcome {
+			Ok(req) => result.response = Some(req.response()),
+			Err(error) => result.error = Some(error),
+		}
+	}
+	// a task that panicked never said where it was, so it is one of the warehouses left unanswered.
+	let unanswered = results.iter_mut().filter(|result| result.response.is_none() && result.error.is_none());
+	for (result, error) in unanswered.zip(failed_tasks) {
+		result.error = Some(error);
+	}
+	results
+}
+
+///
+/// # `NationwideAvailability`
+/// The availability of one model at every warehouse of its manufacturer, for purchasing to decide where
+/// to transfer stock from.
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct NationwideAvailability {
+	pub manufacturer: Option<String>,
+	pub model_number: Option<String>,
+	/// Structured availability per warehouse that answered with one.
+	pub warehouses: BTreeMap<String, Availability>,
+	/// Why a warehouse is missing from `warehouses`: the failed lookup, or an answer without availability
+	/// such as an account issue.
+	pub errors: BTreeMap<String, String>,
+}
+
+impl NationwideAvailability {
+	///
+	/// # `NationwideAvailability::in_stock`
+	/// Warehouses with the model in stock, most units first.
+	///
+	#[must_use]
+	pub fn in_stock(&self) -> Vec<(&str, &Availability)> {
+		let mut in_stock: Vec<(&str, &Availability)> = self.warehouses.iter().filter(|(_, availability)| availability.status == AvailabilityStatus::InStock).map(|(warehouse, availability)| (warehouse.as_str(), availability)).collect();
+		in_stock.sort_by_key(|(_, availability)| Reverse(availability.quantity.unwrap_or_default()));
+		in_stock
+	}
+
+	///
+	/// # `NationwideAvailability::total_quantity`
+	/// Units in stock over every warehouse that reported a quantity.
+	///
+	#[must_use]
+	pub fn total_quantity(&self) -> u32 {
+		self.warehouses.values().filter(|availability| availability.status == AvailabilityStatus::InStock).filter_map(|availability| availability.quantity).sum()
+	}
+}
+
+///
+/// The lookups of `AvailabilityRequest::get_nationwide_availability`.
+///
+pub(crate) async fn nationwide_availability(req: AvailabilityRequest) -> Result<NationwideAvailability, String> {
+	let req = req.parse_manufacturer();
+	let Some(manufacturer) = req.manufacturer.as_ref().map(Manufacturer::to_string) else {
+		return Err("No known manufacturer found.".to_string());
+	};
+	if req.model_number.as_deref().is_none_or(|model_number| model_number.trim().is_empty()) {
+		return Err("No model number found.".to_string());
+	}
+	let warehouses = manufacturer_warehouses(&req);
+	if warehouses.is_empty() {
+		return Err(format!("No warehouses configured for {manufacturer}."));
+	}
+	let mut nationwide = NationwideAvailability { manufacturer: Some(manufacturer), model_number: req.model_number.clone(), ..NationwideAvailability::default() };
+	for result in lookup_warehouses(&req, warehouses).await {
+		match (result.response.map(|response| response.availability_detail.ok_or(response.availability)), result.error) {
+			(Some(Ok(availability)), _) => {
+				nationwide.warehouses.insert(result.warehouse, availability);
+			}
+			(_, Some(error)) => {
+				nationwide.errors.insert(result.warehouse, error);
+			}
+			(Some(Err(message)), None) => {
+				nationwide.errors.insert(result.warehouse, message.unwrap_or_else(|| "No availability in the answer.".to_string()));
+			}
+			(None, None) => {}
+		}
+	}
+	Ok(nationwide)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use super::*;
+	use crate::backend::{register_backend, BackendAnswer, ManufacturerBackend};
+	use crate::AvailabilityError;
+
+	///
+	/// A vendor that gives every model the same answer.
+	///
+	struct Vendor {
+		name: &'static str,
+		answer: Result<Availability, AvailabilityError>,
+		cart: bool,
+	}
+
+	#[async_trait::async_trait]
+	impl ManufacturerBackend for Vendor {
+		fn name(&self) -> &str {
+			self.name
+		}
+
+		async fn availability(&self, _req: &AvailabilityRequest) -> Result<BackendAnswer, AvailabilityError> {
+			self.answer.clone().map(BackendAnswer::new)
+		}
+
+		fn mutates_cart(&self) -> bool {
+			self.cart
+		}
+	}
+
+	fn vendor(name: &'static str, answer: Result<Availability, AvailabilityError>) -> String {
+		register_backend(Arc::new(Vendor { name, answer, cart: false }));
+		name.to_string()
+	}
+
+	fn in_stock(model_number: &str) -> Availability {
+		Availability::new(AvailabilityStatus::InStock, format!("Found: {model_number}, In stock: 2")).with_quantity(2).with_model_number(model_number)
+	}
+
+	#[tokio::test]
+	async fn only_exact_answers_count() {
+		let manufacturers = vec![
+			vendor("discovery-exact", Ok(in_stock("RB-100"))),
+			// a loose match, as Miele's report gives for any model.
+			vendor("discovery-loose", Ok(in_stock("RB-1000"))),
+			// a vendor error read as unknown, without the model it is for.
+			vendor("discovery-unknown", Ok(Availability::new(AvailabilityStatus::Unknown, "Service temporarily unavailable".to_string()))),
+			vendor("discovery-not-found", Ok(Availability::not_found())),
+			vendor("discovery-failing", Err(AvailabilityError::VendorUnavailable("portal is down".to_string()))),
+		];
+		let result = lookup_manufacturers("houston", "rb-100 ", manufacturers).await;
+		assert_eq!(result.matches.iter().map(|response| response.manufacturer.clone().unwrap_or_default()).collect::<Vec<_>>(), ["discovery-exact"]);
+		assert_eq!(result.manufacturer.as_deref(), Some("discovery-exact"));
+		assert_eq!(result.errors.iter().map(|error| error.manufacturer.as_str()).collect::<Vec<_>>(), ["discovery-failing"]);
+	}
+
+	#[tokio::test]
+	async fn stock_on_hand_is_the_best_match() {
+		let backordered = Availability::new(AvailabilityStatus::Backordered, "Found: WD-30, Available: 01/15/2099".to_string()).with_available_date(NaiveDate::from_ymd_opt(2099, 1, 15).unwrap()).with_model_number("WD-30");
+		let manufacturers = vec![vendor("discovery-backordered", Ok(backordered)), vendor("discovery-in-stock", Ok(in_stock("WD-30")))];
+		let result = lookup_manufacturers("houston", "WD-30", manufacturers).await;
+		assert_eq!(result.matches.len(), 2);
+		assert_eq!(result.manufacturer.as_deref(), Some("discovery-in-stock"));
+	}
+
+	#[test]
+	fn backends_with_a_shared_cart_are_not_asked() {
+		register_backend(Arc::new(Vendor { name: "discovery-cart", answer: Ok(in_stock("RB-100")), cart: true }));
+		let asked = vendor("discovery-read-only", Ok(in_stock("RB-100")));
+		let discoverable = discoverable_backends();
+		assert!(discoverable.contains(&asked));
+		assert!(!discoverable.contains(&"discovery-cart".to_string()));
+	}
+}
@@ -0,0 +1,148 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::account::AccountIssue;
+use crate::compliance::RestrictedItem;
+use crate::credentials::{credential_provider, secret_name};
+
+///
+/// # `AvailabilityError`
+/// Typed failure of an availability lookup.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AvailabilityError {
+	/// The vendor session is no longer valid, logging in again should fix it.
+	SessionExpired,
+	/// The vendor does not know the requested model.
+	NotFound(String),
+	/// The vendor system reported itself as unavailable.
+	VendorUnavailable(String),
+	/// The vendor answered with a body larger than `http_client::response_limit` allows for its host; it was
+	/// not read past the limit. `url` is the address without its query.
+	ResponseTooLarge { url: String, limit: usize },
+	/// The lookup ran past the timeout or deadline of its `RequestOptions`.
+	DeadlineExceeded,
+	/// The vendor turned the credentials in `secret` away, e.g. `liebherr-password` after a password change.
+	CredentialsRejected { manufacturer: String, secret: String },
+	/// The shared `SubZero` cart still had `items` after the most removals one lookup makes.
+	CartStuck { items: u32 },
+	/// A compliance rule blocks quoting the model for the request.
+	Restricted(RestrictedItem),
+	/// The vendor account is blocked, e.g. on credit hold, so no lookup there is answered.
+	AccountIssue(AccountIssue),
+	/// Any other failure.
+	Other(String),
+}
+
+impl fmt::Display for AvailabilityError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::SessionExpired => write!(f, "Vendor session expired."),
+			Self::NotFound(model_number) => write!(f, "Model {model_number} not found."),
+			Self::VendorUnavailable(message) => write!(f, "Vendor system unavailable: {message}"),
+			Self::ResponseTooLarge { url, limit } => write!(f, "Response from {url} is larger than the {limit} byte limit."),
+			Self::DeadlineExceeded => write!(f, "Lookup deadline exceeded."),
+			Self::CredentialsRejected { manufacturer, secret } => write!(f, "{manufacturer} turned the credentials in {secret} away."),
+			Self::CartStuck { items } => write!(f, "SubZero cart still has {items} items after clearing it."),
+			Self::Restricted(restricted) => write!(f, "{}", restricted.message()),
+			Self::AccountIssue(issue) => write!(f, "{}", issue.message()),
+			Self::Other(message) => write!(f, "{message}"),
+		}
+	}
+}
+
+impl std::error::Error for AvailabilityError {}
+
+impl AvailabilityError {
+	///
+	/// # `AvailabilityError::remediation`
+	/// The next thing on-call can do about the error, `None` when there is nothing to fix on our side, such
+	/// as a model the vendor does not know.
+	///
+	/// ## Example
+	/// ```
+	/// use eggersmann_app_server_appliance_availability::{AvailabilityError, RemediationHint};
+	///
+	/// let error = AvailabilityError::CartStuck { items: 51 };
+	/// assert_eq!(error.remediation(), Some(RemediationHint::ClearSubzeroCart));
+	/// assert_eq!(AvailabilityError::NotFound("X1".to_string()).remediation(), None);
+	/// ```
+	///
+	#[must_use]
+	pub fn remediation(&self) -> Option<RemediationHint> {
+		match self {
+			Self::SessionExpired => Some(RemediationHint::LogIn),
+			Self::VendorUnavailable(_) => Some(RemediationHint::RetryLater),
+			Self::ResponseTooLarge { url, limit } => Some(RemediationHint::RaiseResponseLimit { host: reqwest::Url::parse(url).ok().and_then(|url| url.host_str().map(str::to_string)).unwrap_or_else(|| url.clone()), limit: *limit }),
+			Self::DeadlineExceeded => Some(RemediationHint::ExtendDeadline),
+			Self::CredentialsRejected { secret, .. } => {
+				let stored = secret_name(secret);
+				Some(RemediationHint::RotateSecret { location: credential_provider().location(&stored), secret: stored })
+			}
+			Self::CartStuck { .. } => Some(RemediationHint::ClearSubzeroCart),
+			Self::Restricted(restricted) => Some(RemediationHint::QuoteAnotherModel { policy: restricted.policy.clone() }),
+			Self::AccountIssue(issue) => Some(RemediationHint::ContactVendor { manufacturer: issue.manufacturer.clone(), reason: issue.reason.clone() }),
+			Self::NotFound(_) | Self::Other(_) => None,
+		}
+	}
+}
+
+///
+/// # `RemediationHint`
+/// What to do about an `AvailabilityError`, for on-call tooling to show next to it. Serialized with the
+/// kind of action as `action`, e.g. `{"action":"rotate_secret","secret":"bsh-password","location":"Key Vault https://…"}`;
+/// `Display` is the same as a sentence.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum RemediationHint {
+	/// Store current credentials as `secret`, the name at the provider, where `location` says, e.g. the
+	/// vault.
+	RotateSecret { secret: String, location: Option<String> },
+	/// Log in to the vendor portal again with `ManufacturerBackend::login`.
+	LogIn,
+	/// Empty the shared cart with `clear_subzero_cart`.
+	ClearSubzeroCart,
+	/// Raise the response limit of `host` above `limit` bytes with `set_response_limit` or
+	/// `RuntimeConfig::response_limits`.
+	RaiseResponseLimit { host: String, limit: usize },
+	/// Give the lookup a longer timeout or deadline in its `RequestOptions`.
+	ExtendDeadline,
+	/// Nothing to change on our side; retry once the vendor is back.
+	RetryLater,
+	/// Offer the customer another model, `policy` does not allow quoting this one.
+	QuoteAnotherModel { policy: String },
+	/// Settle the issue with the account at `manufacturer`, e.g. a credit hold, with the vendor.
+	ContactVendor { manufacturer: String, reason: String },
+}
+
+impl fmt::Display for RemediationHint {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::RotateSecret { secret, location: Some(location) } => write!(f, "Rotate secret {secret} in {location}."),
+			Self::RotateSecret { secret, location: None } => write!(f, "Rotate secret {secret}."),
+			Self::LogIn => write!(f, "Log in to the vendor portal again."),
+			Self::ClearSubzeroCart => write!(f, "Clear the SubZero cart with clear_subzero_cart."),
+			Self::RaiseResponseLimit { host, limit } => write!(f, "Raise the response limit of {host} above {limit} bytes."),
+			Self::ExtendDeadline => write!(f, "Give the lookup a longer timeout or deadline."),
+			Self::RetryLater => write!(f, "Retry once the vendor is back."),
+			Self::QuoteAnotherModel { policy } => write!(f, "Quote another model, {policy} does not allow this one."),
+			Self::ContactVendor { manufacturer, reason } => write!(f, "Contact {manufacturer} about the account: {reason}."),
+		}
+	}
+}
+
+impl From<String> for AvailabilityError {
+	fn from(message: String) -> Self {
+		Self::Other(message)
+	}
+}
+
+impl From<AvailabilityError> for String {
+	fn from(error: AvailabilityError) -> Self {
+		error.to_string()
+	}
+}
@@ -0,0 +1,78 @@
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use super::canary::CredentialRotationLikely;
+use super::sla::SlaBreach;
+
+///
+/// Number of events a slow subscriber may fall behind before it starts missing events.
+///
+const EVENT_CAPACITY: usize = 4096;
+
+///
+/// # `AvailabilityEvent`
+/// Events published by the crate for the watch and notification subsystems.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum AvailabilityEvent {
+	/// A row of a vendor catalog changed between two refreshes.
+	CatalogChange(CatalogChange),
+	/// A vendor started missing an expectation of its `SlaPolicy`.
+	SlaBreached(SlaBreach),
+	/// A vendor is back within an expectation it had breached.
+	SlaRecovered(SlaBreach),
+	/// A vendor kept turning the login canary away, its dealer password was likely reset.
+	CredentialRotationLikely(CredentialRotationLikely),
+}
+
+///
+/// # `CatalogChange`
+/// A row-level difference between two versions of a vendor catalog.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct CatalogChange {
+	pub manufacturer: String,
+	pub warehouse: String,
+	pub model_number: String,
+	pub kind: CatalogChangeKind,
+}
+
+///
+/// # `CatalogChangeKind`
+/// What changed about a catalog row.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum CatalogChangeKind {
+	ItemAdded,
+	ItemRemoved,
+	QuantityChanged { from: String, to: String },
+	DateChanged { from: String, to: String },
+}
+
+fn sender() -> &'static broadcast::Sender<AvailabilityEvent> {
+	static SENDER: OnceLock<broadcast::Sender<AvailabilityEvent>> = OnceLock::new();
+	SENDER.get_or_init(|| broadcast::channel(EVENT_CAPACITY).0)
+}
+
+///
+/// # `subscribe`
+/// Receive every event published from now on.
+///
+#[must_use]
+pub fn subscribe() -> broadcast::Receiver<AvailabilityEvent> {
+	sender().subscribe()
+}
+
+///
+/// Publish an event to all current subscribers. Events published without subscribers are dropped.
+///
+pub(crate) fn publish(event: AvailabilityEvent) {
+	let _ = sender().send(event);
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next};
+
+///
+/// # `Fault`
+/// A failure injected into a vendor request.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Fault {
+	/// The request runs with a 1 ms timeout and fails with a genuine (retryable) timeout error.
+	Timeout,
+	/// The vendor answers 500 without being called.
+	ServerError,
+	/// The vendor answers 200 with a body no vendor module can parse.
+	MalformedBody,
+	/// The request is sent after the given delay.
+	Slow(Duration),
+	/// The request goes to a local socket that resets the connection once it is sent, and fails with a genuine
+	/// (retryable) connection error.
+	Reset,
+}
+
+///
+/// # `FaultConfig`
+/// Inject one of `faults`, picked at random, into a share `probability` (0.0 to 1.0) of requests, and
+/// into no more than `limit` of them if set.
+///
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct FaultConfig {
+	pub probability: f64,
+	pub faults: Vec<Fault>,
+	pub limit: Option<u32>,
+}
+
+impl FaultConfig {
+	///
+	/// # `FaultConfig::new`
+	/// Create a fault configuration.
+	///
+	#[must_use]
+	pub const fn new(probability: f64, faults: Vec<Fault>) -> Self {
+		Self { probability, faults, limit: None }
+	}
+
+	///
+	/// # `FaultConfig::with_limit`
+	/// Inject into the first `limit` requests only and let the rest through, so a retry can recover.
+	///
+	#[must_use]
+	pub const fn with_limit(mut self, limit: u32) -> Self {
+		self.limit = Some(limit);
+		self
+	}
+}
+
+fn fault_configs() -> &'static RwLock<HashMap<String, FaultConfig>> {
+	static FAULTS: OnceLock<RwLock<HashMap<String, FaultConfig>>> = OnceLock::new();
+	FAULTS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+///
+/// # `set_faults`
+/// Inject faults into the requests of a vendor (`bsh`, `subzero`, `miele`, `liebherr`, `fisher_paykel`, `jennair`, `monogram`, `true_residential`, `dacor`, `bertazzoni`), of an exact host:port or of an exact host.
+///
+pub fn set_faults(vendor: &str, config: FaultConfig) {
+	fault_configs().write().unwrap_or_else(std::sync::PoisonError::into_inner).insert(vendor.to_lowercase(), config);
+}
+
+///
+/// # `clear_faults`
+/// Stop injecting faults.
+///
+pub fn clear_faults() {
+	fault_configs().write().unwrap_or_else(std::sync::PoisonError::into_inner).clear();
+}
+
+fn vendor_of(host: &str) -> Option<&'static str> {
+	if host.ends_with("bsh-partner.com") {
+		Some("bsh")
+	} else if host.ends_with("subzero.com") {
+		Some("subzero")
+	} else if host.contains("miele") {
+		Some("miele")
+	} else if host.ends_with("liebherr.com") {
+		Some("liebherr")
+	} else if host.ends_with("fisherpaykel.com") {
+		Some("fisher_paykel")
+	} else if host.ends_with("whirlpool.com") {
+		Some("jennair")
+	} else if host.ends_with("geappliances.com") {
+		Some("monogram")
+	} else if host.ends_with("true-residential.com") {
+		Some("true_residential")
+	} else if host.ends_with("dacor.com") {
+		Some("dacor")
+	} else if host.ends_with("bertazzoni.com") {
+		Some("bertazzoni")
+	} else {
+		None
+	}
+}
+
+fn pick_fault(host: &str, port: Option<u16>) -> Option<Fault> {
+	let keys = [vendor_of(host).map(str::to_string), port.map(|port| format!("{host}:{port}")), Some(host.to_string())];
+	let mut configs = fault_configs().write().unwrap_or_else(std::sync::PoisonError::into_inner);
+	let key = keys.into_iter().flatten().find(|key| configs.contains_key(key))?;
+	let config = configs.get_mut(&key)?;
+	if config.faults.is_empty() || config.limit == Some(0) || fastrand::f64() >= config.probability {
+		return None;
+	}
+	if let Some(limit) = config.limit.as_mut() {
+		*limit -= 1;
+	}
+	let fault = config.faults[fastrand::usize(..config.faults.len())];
+	drop(configs);
+	Some(fault)
+}
+
+///
+/// Point `req` at a local socket that closes the connection once the request arrives. Closing with the
+/// request unread makes the kernel reset the connection.
+///
+async fn reset_connection(req: &mut Request) -> std::io::Result<()> {
+	let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+	let port = listener.local_addr()?.port();
+	tokio::spawn(async move {
+		if let Ok((stream, _)) = listener.accept().await {
+			let _ = stream.readable().await;
+		}
+	});
+	let url = req.url_mut();
+	let _ = url.set_scheme("http");
+	let _ = url.set_host(Some("127.0.0.1"));
+	let _ = url.set_port(Some(port));
+	Ok(())
+}
+
+fn canned_response(status: u16, body: &'static str) -> Response {
+	let response = ::http::Response::builder().status(status).body(body).unwrap_or_else(|_| ::http::Response::new(body));
+	Response::from(response)
+}
+
+///
+/// Innermost layer, so each retry draws its own fault.
+///
+pub struct FaultInjectionMiddleware;
+
+#[async_trait::async_trait]
+impl Middleware for FaultInjectionMiddleware {
+	async fn handle(&self, mut req: Request, extensions: &mut ::http::Extensions, next: Next<'_>) -> reqwest_middleware::Result<Response> {
+		let host = req.url().host_str().unwrap_or_default().to_lowercase();
+		match pick_fault(&host, req.url().port_or_known_default()) {
+			None => next.run(req, extensions).await,
+			Some(Fault::Timeout) => {
+				*req.timeout_mut() = Some(Duration::from_millis(1));
+				next.run(req, extensions).await
+			}
+			Some(Fault::ServerError) => Ok(canned_response(500, "Injected server error")),
+			Some(Fault::MalformedBody) => Ok(canned_response(200, "\u{0}{\"d\": <malformed")),
+			Some(Fault::Slow(delay)) => {
+				tokio::time::sleep(delay).await;
+				next.run(req, extensions).await
+			}
+			Some(Fault::Reset) => {
+				reset_connection(&mut req).await.map_err(reqwest_middleware::Error::middleware)?;
+				next.run(req, extensions).await
+			}
+		}
+	}
+}
@@ -0,0 +1,208 @@
+use std::sync::{OnceLock, RwLock};
+
+use reqwest::header::{self, HeaderMap, HeaderValue};
+use reqwest::StatusCode;
+use serde_json::Value;
+
+use super::backend::{BackendAnswer, ManufacturerBackend};
+use super::{Availability, AvailabilityError, AvailabilityRequest, ProductInfo};
+use crate::cookies::{self, SessionJar};
+use crate::credentials::SecretString;
+use crate::http_client::{self, HttpClient};
+use crate::stock::{self, StockKeys};
+use crate::telemetry;
+
+///
+/// Name the Fisher & Paykel session saved by `fisher_paykel_login` is kept under in the `TokenStore`.
+///
+pub const FISHER_PAYKEL_TOKEN: &str = "fisher_paykel_cookies.json";
+
+///
+/// # `FisherPaykelConfig`
+/// Endpoints of the Fisher & Paykel dealer portal: the form login and the stock query, which takes the
+/// model as `sku` and the warehouse as `warehouse` and answers with JSON. The portal addresses come with
+/// the dealer account, so there is no default and lookups fail until `set_fisher_paykel_config` is called.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FisherPaykelConfig {
+	pub login_url: String,
+	pub stock_url: String,
+}
+
+impl FisherPaykelConfig {
+	#[must_use]
+	pub const fn new(login_url: String, stock_url: String) -> Self {
+		Self { login_url, stock_url }
+	}
+}
+
+fn config_slot() -> &'static RwLock<Option<FisherPaykelConfig>> {
+	static CONFIG: OnceLock<RwLock<Option<FisherPaykelConfig>>> = OnceLock::new();
+	CONFIG.get_or_init(|| RwLock::new(None))
+}
+
+///
+/// # `set_fisher_paykel_config`
+/// Set the endpoints of the Fisher & Paykel dealer portal.
+///
+pub fn set_fisher_paykel_config(config: FisherPaykelConfig) {
+	*config_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = Some(config);
+}
+
+///
+/// # `fisher_paykel_config`
+/// The endpoints of the Fisher & Paykel dealer portal, `None` until they are set.
+///
+#[must_use]
+pub fn fisher_paykel_config() -> Option<FisherPaykelConfig> {
+	config_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
+
+fn configured() -> Result<FisherPaykelConfig, String> {
+	fisher_paykel_config().ok_or_else(|| "No Fisher & Paykel dealer portal configured, see `set_fisher_paykel_config`.".to_string())
+}
+
+///
+/// # `FisherPaykelBackend`
+/// Fisher & Paykel lookups through the dealer portal, registered as `fisher_paykel`. The login and the
+/// stock query go through `client`, the shared client unless one is given.
+///
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct FisherPaykelBackend {
+	pub client: HttpClient,
+}
+
+impl FisherPaykelBackend {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	#[must_use]
+	pub fn with_client(mut self, client: HttpClient) -> Self {
+		self.client = client;
+		self
+	}
+}
+
+#[async_trait::async_trait]
+impl ManufacturerBackend for FisherPaykelBackend {
+	fn name(&self) -> &'static str {
+		"fisher_paykel"
+	}
+
+	async fn availability(&self, req: &AvailabilityRequest) -> Result<BackendAnswer, AvailabilityError> {
+		let (username, password) = crate::secrets::vendor_credentials("fisher-paykel").await?;
+		let (availability, product) = http_client::scope(self.client.clone(), fisher_paykel_lookup(req.clone(), username, password)).await?;
+		Ok(BackendAnswer::new(availability).with_product(product))
+	}
+
+	async fn login(&self) -> Result<(), AvailabilityError> {
+		let (username, password) = crate::secrets::vendor_credentials("fisher-paykel").await?;
+		Ok(http_client::scope(self.client.clone(), fisher_paykel_login(username, password)).await?)
+	}
+}
+
+///
+/// # Fisher & Paykel Lookup
+/// Gets the availability of a Fisher & Paykel appliance at the request's warehouse together with its
+/// description.
+///
+/// A session the portal turns away is logged in again once and the query repeated.
+///
+/// # Errors
+/// Returns `AvailabilityError::VendorUnavailable` if the portal cannot be reached or its answer cannot be
+/// read, `AvailabilityError::CredentialsRejected` if it turns the dealer login away, and
+/// `AvailabilityError::Other` without a configured portal, for a failed login or a request without model
+/// number.
+///
+pub async fn fisher_paykel_lookup(req: AvailabilityRequest, username: SecretString, password: SecretString) -> Result<(Availability, Option<ProductInfo>), AvailabilityError> {
+	let config = configured()?;
+	let session = fisher_paykel_session(&config, username.clone(), password.clone()).await?;
+	match fisher_paykel_stock(&config, &req, &session).await {
+		Err(AvailabilityError::SessionExpired) => {
+			telemetry::counter("session.refreshed", 1, &[("manufacturer", "fisher_paykel".to_string())]);
+			let session = login_session(&config, username, password).await?;
+			fisher_paykel_stock(&config, &req, &session).await
+		}
+		result => result,
+	}
+}
+
+///
+/// Query the stock of the requested model with `session`, saving the session if the portal refreshed it.
+///
+async fn fisher_paykel_stock(config: &FisherPaykelConfig, req: &AvailabilityRequest, session: &SessionJar) -> Result<(Availability, Option<ProductInfo>), AvailabilityError> {
+	let today = req.business_today();
+	let model_number = req.model_number.as_deref().map(|model_number| model_number.trim().to_uppercase()).ok_or_else(|| AvailabilityError::Other("No model number found.".to_string()))?;
+	let mut headers = HeaderMap::new();
+	session.apply(&mut headers, &config.stock_url);
+	headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+
+	let query = [("sku", model_number.as_str()), ("warehouse", req.warehouse.as_deref().unwrap_or_default()), ("quantity", &req.requested_quantity().to_string())];
+	let response = http_client::client().get(&config.stock_url).headers(headers).query(&query).send().await.map_err(|e| AvailabilityError::VendorUnavailable(format!("Failed to get Fisher & Paykel stock: {e:?}")))?;
+	session.capture(&response);
+	cookies::save_refreshed_session(FISHER_PAYKEL_TOKEN, "fisher_paykel", session).await;
+	if http_client::auth_rejected(&response) {
+		return Err(AvailabilityError::SessionExpired);
+	}
+	match response.status() {
+		StatusCode::NOT_FOUND => return Ok((Availability::not_found(), None)),
+		status if !status.is_success() => return Err(AvailabilityError::VendorUnavailable(format!("Failed to get Fisher & Paykel stock: {status}"))),
+		_ => {}
+	}
+	let data: Value = http_client::json(response).await?;
+	Ok(stock::json_stock(&data, &STOCK_KEYS, "Fisher & Paykel", &model_number, today))
+}
+
+///
+/// Fields of the stock answer, for `stock::json_stock`.
+///
+const STOCK_KEYS: StockKeys = StockKeys::new("items", "sku", "availableQuantity", "nextAvailableDate").with_next_quantity("nextAvailableQuantity");
+
+///
+/// The saved session, logging in first when there is none.
+///
+async fn fisher_paykel_session(config: &FisherPaykelConfig, username: SecretString, password: SecretString) -> Result<SessionJar, AvailabilityError> {
+	if let Some(session) = cookies::load_session(FISHER_PAYKEL_TOKEN, &config.login_url).await {
+		return Ok(session);
+	}
+	login_session(config, username, password).await
+}
+
+///
+/// # Login to Fisher & Paykel System
+/// Log in to the dealer portal and save the session cookies it sets.
+///
+/// # Errors
+/// Returns `AvailabilityError::VendorUnavailable` if the portal cannot be reached,
+/// `AvailabilityError::CredentialsRejected` if it turns the dealer login away, and
+/// `AvailabilityError::Other` without a configured portal, if it sets no session cookie or the session
+/// cannot be saved.
+///
+pub async fn fisher_paykel_login(username: SecretString, password: SecretString) -> Result<(), AvailabilityError> {
+	login_session(&configured()?, username, password).await.map(drop)
+}
+
+async fn login_session(config: &FisherPaykelConfig, username: SecretString, password: SecretString) -> Result<SessionJar, AvailabilityError> {
+	telemetry::counter("vendor.login", 1, &[("manufacturer", "fisher_paykel".to_string())]);
+	let form = [("username", username.expose()), ("password", password.expose())];
+	let response = http_client::client().post(&config.login_url).form(&form).send().await.map_err(|e| AvailabilityError::VendorUnavailable(format!("Failed to log in to Fisher & Paykel: {e:?}")))?;
+	// the login form is itself a login page, so only a redirect elsewhere to one is a rejection.
+	let redirected = reqwest::Url::parse(&config.login_url).is_ok_and(|login_url| login_url.path() != response.url().path());
+	if matches!(response.status(), StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) || (redirected && http_client::auth_rejected(&response)) {
+		return Err(AvailabilityError::CredentialsRejected { manufacturer: "Fisher & Paykel".to_string(), secret: "fisher-paykel-password".to_string() });
+	}
+	if !response.status().is_success() {
+		return Err(AvailabilityError::VendorUnavailable(format!("Fisher & Paykel login failed: {}", response.status())));
+	}
+	let session = SessionJar::for_portal(&config.login_url, [])?;
+	session.capture(&response);
+	if session.cookies().is_empty() {
+		return Err(AvailabilityError::Other("Fisher & Paykel login set no session cookie.".to_string()));
+	}
+	cookies::save_session(FISHER_PAYKEL_TOKEN, &session).await?;
+	Ok(session)
+}
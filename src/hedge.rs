@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+
+use super::telemetry;
+
+///
+/// # `HedgePolicy`
+/// When to launch a second, parallel attempt of a slow vendor call.
+///
+/// With a `threshold` set, an attempt that has not finished after `threshold` is raced against a fresh
+/// attempt and whichever finishes first wins. Without a threshold calls are never hedged.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct HedgePolicy {
+	pub threshold: Option<Duration>,
+}
+
+impl HedgePolicy {
+	///
+	/// # `HedgePolicy::after`
+	/// Hedge attempts that take longer than `threshold`.
+	///
+	#[must_use]
+	pub const fn after(threshold: Duration) -> Self {
+		Self { threshold: Some(threshold) }
+	}
+
+	///
+	/// # `HedgePolicy::disabled`
+	/// Never hedge.
+	///
+	#[must_use]
+	pub const fn disabled() -> Self {
+		Self { threshold: None }
+	}
+}
+
+fn policies() -> &'static RwLock<HashMap<String, HedgePolicy>> {
+	static POLICIES: OnceLock<RwLock<HashMap<String, HedgePolicy>>> = OnceLock::new();
+	POLICIES.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+///
+/// # `set_hedge_policy`
+/// Set the hedging policy for a manufacturer (`"subzero"`, `"bsh"`, `"miele"`).
+///
+/// ## Example
+/// ```
+/// use std::time::Duration;
+/// use eggersmann_app_server_appliance_availability::{set_hedge_policy, HedgePolicy};
+///
+/// set_hedge_policy("subzero", HedgePolicy::after(Duration::from_secs(8)));
+/// ```
+///
+pub fn set_hedge_policy(manufacturer: &str, policy: HedgePolicy) {
+	policies().write().unwrap_or_else(std::sync::PoisonError::into_inner).insert(manufacturer.to_lowercase(), policy);
+}
+
+///
+/// # `hedge_policy`
+/// The hedging policy for a manufacturer, disabled unless one was set.
+///
+pub fn hedge_policy(manufacturer: &str) -> HedgePolicy {
+	policies().read().unwrap_or_else(std::sync::PoisonError::into_inner).get(&manufacturer.to_lowercase()).copied().unwrap_or_default()
+}
+
+///
+/// Run `attempt`, launching a second attempt if the first is slower than the manufacturer's threshold.
+///
+/// Only for reads that are safe to run twice at once, such as a catalog suggestion or a product page.
+/// Never hedge a call that changes vendor state, such as a cart add or removal: both attempts would run.
+///
+pub async fn hedged<F, Fut, T>(manufacturer: &'static str, attempt: F) -> T
+where
+	F: Fn() -> Fut,
+	Fut: Future<Output = T>,
+{
+	let Some(threshold) = hedge_policy(manufacturer).threshold else { return attempt().await };
+
+	let first = attempt();
+	tokio::pin!(first);
+	tokio::select! {
+		out = &mut first => return out,
+		() = tokio::time::sleep(threshold) => {}
+	}
+
+	telemetry::counter("availability.hedge.launched", 1, &[("manufacturer", manufacturer.to_string())]);
+	let second = attempt();
+	tokio::pin!(second);
+	tokio::select! {
+		out = &mut first => out,
+		out = &mut second => {
+			telemetry::counter("availability.hedge.won", 1, &[("manufacturer", manufacturer.to_string())]);
+			out
+		}
+	}
+}
@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::calendar::business_calendar;
+use super::timezone::business_today;
+use super::{Availability, AvailabilityResponse, AvailabilityStatus};
+
+///
+/// # `HistoryRecord`
+/// One availability answer as kept in the history.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct HistoryRecord {
+	pub recorded_at: DateTime<Utc>,
+	pub manufacturer: String,
+	pub showroom: Option<String>,
+	pub warehouse: Option<String>,
+	pub model_number: String,
+	pub brand: Option<String>,
+	pub category: Option<String>,
+	pub found: bool,
+	pub in_stock: bool,
+	pub available_date: Option<NaiveDate>,
+	pub availability: String,
+}
+
+impl HistoryRecord {
+	///
+	/// # `HistoryRecord::from_response`
+	/// Build a history record from a lookup response. Whether the model was found and is in stock comes from
+	/// the typed `availability_detail`, read from the availability text only for responses without one.
+	/// Returns `None` for responses without a manufacturer, model number or availability text.
+	///
+	#[must_use]
+	pub fn from_response(response: &AvailabilityResponse, recorded_at: DateTime<Utc>) -> Option<Self> {
+		let availability = response.availability.clone()?;
+		let today = recorded_at.date_naive();
+		let detail = response.availability_detail.clone().unwrap_or_else(|| Availability::from_text(&availability, today));
+		let found = response.found();
+		let available_date = if found { detail.available_date.or_else(|| parse_available_date(&availability)) } else { None };
+		let in_stock = found && (detail.status == AvailabilityStatus::InStock || available_date.is_some_and(|date| date <= today));
+
+		Some(Self {
+			recorded_at,
+			manufacturer: response.manufacturer.clone()?,
+			showroom: response.showroom.clone(),
+			warehouse: response.warehouse.clone(),
+			model_number: response.model_number.clone()?,
+			brand: response.product.as_ref().and_then(|product| product.brand.clone()),
+			category: response.product.as_ref().and_then(|product| product.category.clone()),
+			found,
+			in_stock,
+			available_date,
+			availability,
+		})
+	}
+
+	///
+	/// # `HistoryRecord::lead_time_days`
+	/// Business days of the vendor's `BusinessCalendar` between the lookup and the promised availability
+	/// date, zero when already available. The lookup day is taken in the showroom's time zone, so evening
+	/// lookups and daylight-saving changes do not shift it.
+	///
+	#[must_use]
+	pub fn lead_time_days(&self) -> Option<i64> {
+		let looked_up = business_today(self.showroom.as_deref(), self.recorded_at);
+		self.available_date.map(|date| business_calendar(&self.manufacturer).business_days_between(looked_up, date))
+	}
+}
+
+///
+/// Find the first date in a vendor availability text.
+///
+pub(crate) fn parse_available_date(availability: &str) -> Option<NaiveDate> {
+	availability.split(|c: char| c.is_whitespace() || c == ',' || c == '"' || c == '<' || c == '>').find_map(|token| ["%m/%d/%Y", "%m/%d/%y", "%Y-%m-%d", "%Y%m%d", "%d.%m.%Y"].iter().find_map(|format| NaiveDate::parse_from_str(token, format).ok()))
+}
+
+///
+/// # `HistoryStore`
+/// Storage for availability history.
+///
+pub trait HistoryStore: Send + Sync {
+	///
+	/// Append a record.
+	///
+	fn record(&self, record: HistoryRecord);
+
+	///
+	/// All records taken at or after `since`, oldest first.
+	///
+	fn records_since(&self, since: DateTime<Utc>) -> Vec<HistoryRecord>;
+}
+
+///
+/// # `InMemoryHistory`
+/// History kept in process memory, lost on restart.
+///
+#[derive(Debug, Default)]
+pub struct InMemoryHistory {
+	records: Mutex<Vec<HistoryRecord>>,
+}
+
+impl HistoryStore for InMemoryHistory {
+	fn record(&self, record: HistoryRecord) {
+		self.records.lock().unwrap_or_else(std::sync::PoisonError::into_inner).push(record);
+	}
+
+	fn records_since(&self, since: DateTime<Utc>) -> Vec<HistoryRecord> {
+		self.records.lock().unwrap_or_else(std::sync::PoisonError::into_inner).iter().filter(|record| record.recorded_at >= since).cloned().collect()
+	}
+}
+
+fn store_slot() -> &'static RwLock<Option<Arc<dyn HistoryStore>>> {
+	static STORE: OnceLock<RwLock<Option<Arc<dyn HistoryStore>>>> = OnceLock::new();
+	STORE.get_or_init(|| RwLock::new(None))
+}
+
+///
+/// # `set_history_store`
+/// Install the store every successful lookup is recorded to. History is not kept until one is set.
+///
+pub fn set_history_store(store: Arc<dyn HistoryStore>) {
+	*store_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = Some(store);
+}
+
+///
+/// # `history_store`
+/// The installed history store, if any.
+///
+pub fn history_store() -> Option<Arc<dyn HistoryStore>> {
+	store_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
+
+pub(crate) fn record_response(response: &AvailabilityResponse) {
+	if let (Some(store), Some(record)) = (history_store(), HistoryRecord::from_response(response, Utc::now())) {
+		store.record(record);
+	}
+}
+
+///
+/// # `WeeklyAggregate`
+/// Availability history of one brand and category rolled up into an ISO week.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct WeeklyAggregate {
+	pub iso_year: i32,
+	pub iso_week: u32,
+	pub brand: String,
+	pub category: Option<String>,
+	pub samples: usize,
+	pub average_lead_time_days: Option<f64>,
+	pub in_stock_ratio: f64,
+}
+
+///
+/// # `aggregate_iso_weeks`
+/// Roll history records into ISO-week buckets per brand and category.
+///
+/// Records for models the vendor did not find are skipped. The brand falls back to the manufacturer when
+/// the vendor catalog did not provide one. Buckets are ordered by week, then brand, then category.
+///
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn aggregate_iso_weeks(records: &[HistoryRecord]) -> Vec<WeeklyAggregate> {
+	let mut buckets: BTreeMap<(i32, u32, String, Option<String>), Vec<&HistoryRecord>> = BTreeMap::new();
+	for record in records.iter().filter(|record| record.found) {
+		let week = record.recorded_at.iso_week();
+		let brand = record.brand.clone().unwrap_or_else(|| record.manufacturer.clone());
+		buckets.entry((week.year(), week.week(), brand, record.category.clone())).or_default().push(record);
+	}
+
+	buckets
+		.into_iter()
+		.map(|((iso_year, iso_week, brand, category), records)| {
+			let lead_times: Vec<i64> = records.iter().filter_map(|record| record.lead_time_days()).collect();
+			let average_lead_time_days = (!lead_times.is_empty()).then(|| lead_times.iter().sum::<i64>() as f64 / lead_times.len() as f64);
+			let in_stock_ratio = records.iter().filter(|record| record.in_stock).count() as f64 / records.len() as f64;
+			WeeklyAggregate { iso_year, iso_week, brand, category, samples: records.len(), average_lead_time_days, in_stock_ratio }
+		})
+		.collect()
+}
@@ -0,0 +1,477 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+use reqwest::{Client, Method, Request, Response, StatusCode};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, Middleware, Next};
+use reqwest_retry::policies::ExponentialBackoff;
+use reqwest_retry::{Jitter, RetryDecision, RetryPolicy, RetryTransientMiddleware};
+use serde::de::DeserializeOwned;
+
+use crate::charset;
+use crate::deadline;
+use crate::error::AvailabilityError;
+use crate::ratelimit::RateLimiter;
+use crate::support;
+use crate::telemetry;
+
+///
+/// # `BackoffPolicy`
+/// How a vendor GET or HEAD that failed with a transient error (connection errors, timeouts, 5xx, 429) is
+/// retried: up to `max_attempts` attempts in all, waiting twice as long before each retry, from
+/// `min_backoff` up to `max_backoff`. With `jitter` each wait is a random share of that, so instances
+/// that failed together do not retry together.
+///
+/// A retry that would start after the deadline of the lookup is not made, so the lookup gets the last
+/// answer of the vendor instead of `DeadlineExceeded`. This is the only layer that retries a vendor.
+///
+/// ## Example
+/// ```
+/// use std::time::Duration;
+/// use eggersmann_app_server_appliance_availability::{set_backoff_policy, BackoffPolicy};
+///
+/// set_backoff_policy(BackoffPolicy::new(5).with_backoff(Duration::from_millis(500), Duration::from_secs(20)));
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BackoffPolicy {
+	pub max_attempts: u32,
+	pub min_backoff: Duration,
+	pub max_backoff: Duration,
+	pub jitter: bool,
+}
+
+impl Default for BackoffPolicy {
+	fn default() -> Self {
+		Self::new(3)
+	}
+}
+
+impl BackoffPolicy {
+	///
+	/// # `BackoffPolicy::new`
+	/// Make up to `max_attempts` attempts, backing off from half a second to five seconds with jitter.
+	///
+	#[must_use]
+	pub const fn new(max_attempts: u32) -> Self {
+		Self { max_attempts, min_backoff: Duration::from_millis(500), max_backoff: Duration::from_secs(5), jitter: true }
+	}
+
+	///
+	/// # `BackoffPolicy::disabled`
+	/// Never retry.
+	///
+	#[must_use]
+	pub const fn disabled() -> Self {
+		Self::new(1)
+	}
+
+	#[must_use]
+	pub const fn with_backoff(mut self, min_backoff: Duration, max_backoff: Duration) -> Self {
+		self.min_backoff = min_backoff;
+		self.max_backoff = max_backoff;
+		self
+	}
+
+	#[must_use]
+	pub const fn with_jitter(mut self, jitter: bool) -> Self {
+		self.jitter = jitter;
+		self
+	}
+}
+
+fn backoff_slot() -> &'static RwLock<BackoffPolicy> {
+	static POLICY: OnceLock<RwLock<BackoffPolicy>> = OnceLock::new();
+	POLICY.get_or_init(|| RwLock::new(BackoffPolicy::default()))
+}
+
+///
+/// # `set_backoff_policy`
+/// Retry vendor requests with `policy` from now on, requests in flight included.
+///
+pub fn set_backoff_policy(policy: BackoffPolicy) {
+	*backoff_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = policy;
+}
+
+///
+/// # `backoff_policy`
+/// How vendor requests are retried.
+///
+#[must_use]
+pub fn backoff_policy() -> BackoffPolicy {
+	*backoff_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+///
+/// Retry policy of the client, reading the `BackoffPolicy` in force at every decision. A retry due after
+/// the deadline of the lookup is given up.
+///
+struct ConfiguredBackoff;
+
+impl RetryPolicy for ConfiguredBackoff {
+	fn should_retry(&self, request_start_time: SystemTime, n_past_retries: u32) -> RetryDecision {
+		let policy = backoff_policy();
+		let max_backoff = policy.max_backoff.max(policy.min_backoff);
+		let jitter = if policy.jitter { Jitter::Bounded } else { Jitter::None };
+		let decision = ExponentialBackoff::builder().retry_bounds(policy.min_backoff, max_backoff).jitter(jitter).build_with_max_retries(policy.max_attempts.saturating_sub(1)).should_retry(request_start_time, n_past_retries);
+		match (decision, deadline::remaining()) {
+			(RetryDecision::Retry { execute_after }, Some(remaining)) if execute_after > SystemTime::now() + remaining => {
+				telemetry::counter("http.retry.deadline", 1, &[]);
+				RetryDecision::DoNotRetry
+			}
+			(decision, _) => decision,
+		}
+	}
+}
+
+///
+/// Largest response body read from a host without its own limit, 32 MiB. Vendor pages are a few hundred
+/// KiB and the Miele report a few MiB.
+///
+pub const DEFAULT_RESPONSE_LIMIT: usize = 32 * 1024 * 1024;
+
+///
+/// # `HttpClient`
+/// A client with its own connection pool, cheap to clone; clones share the pool. Every vendor request goes
+/// through one, rate limited per host and reported to telemetry. GETs and HEADs are retried on transient
+/// failures as `backoff_policy` says; posts, such as logins and cart changes, are sent once.
+///
+#[derive(Debug, Clone)]
+pub struct HttpClient(ClientWithMiddleware);
+
+impl Default for HttpClient {
+	///
+	/// The client shared by every backend that was not given its own. Should the TLS backend reject the
+	/// default pool settings, it is reqwest's default client with the same middleware, and
+	/// `http.client.build_failed` is reported.
+	///
+	fn default() -> Self {
+		static SHARED: OnceLock<HttpClient> = OnceLock::new();
+		SHARED
+			.get_or_init(|| {
+				ClientFactory::default().build().unwrap_or_else(|e| {
+					telemetry::event("http.client.build_failed", &[("error", e.to_string())]);
+					with_middleware(Client::new())
+				})
+			})
+			.clone()
+	}
+}
+
+///
+/// Wrap `client` in the middleware every vendor request goes through.
+///
+fn with_middleware(client: Client) -> HttpClient {
+	let builder = ClientBuilder::new(client).with(TelemetryMiddleware).with(IdempotentRetryMiddleware(RetryTransientMiddleware::new_with_policy(ConfiguredBackoff))).with(RateLimitMiddleware).with(DeadlineMiddleware);
+	#[cfg(feature = "fault-injection")]
+	let builder = builder.with(super::faults::FaultInjectionMiddleware);
+	HttpClient(builder.build())
+}
+
+impl std::ops::Deref for HttpClient {
+	type Target = ClientWithMiddleware;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+///
+/// # `ClientFactory`
+/// Builds `HttpClient`s with the middleware every vendor request needs and the given connection pool
+/// settings. Idle connections and their TLS sessions are kept for `pool_idle_timeout`, so the steps of a
+/// multi-step vendor flow reuse one connection.
+///
+/// ## Example
+/// ```
+/// use std::time::Duration;
+/// use eggersmann_app_server_appliance_availability::{BshBackend, ClientFactory};
+///
+/// let client = ClientFactory::default().with_pool_max_idle_per_host(4).with_pool_idle_timeout(Duration::from_secs(300)).build()?;
+/// let backend = BshBackend::new().with_client(client);
+/// # Ok::<(), reqwest::Error>(())
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ClientFactory {
+	pub pool_idle_timeout: Duration,
+	pub pool_max_idle_per_host: usize,
+	pub tcp_keepalive: Duration,
+	pub connect_timeout: Duration,
+}
+
+impl Default for ClientFactory {
+	fn default() -> Self {
+		Self { pool_idle_timeout: Duration::from_secs(90), pool_max_idle_per_host: 8, tcp_keepalive: Duration::from_mins(1), connect_timeout: Duration::from_secs(10) }
+	}
+}
+
+impl ClientFactory {
+	#[must_use]
+	pub const fn with_pool_idle_timeout(mut self, pool_idle_timeout: Duration) -> Self {
+		self.pool_idle_timeout = pool_idle_timeout;
+		self
+	}
+
+	#[must_use]
+	pub const fn with_pool_max_idle_per_host(mut self, pool_max_idle_per_host: usize) -> Self {
+		self.pool_max_idle_per_host = pool_max_idle_per_host;
+		self
+	}
+
+	#[must_use]
+	pub const fn with_tcp_keepalive(mut self, tcp_keepalive: Duration) -> Self {
+		self.tcp_keepalive = tcp_keepalive;
+		self
+	}
+
+	#[must_use]
+	pub const fn with_connect_timeout(mut self, connect_timeout: Duration) -> Self {
+		self.connect_timeout = connect_timeout;
+		self
+	}
+
+	///
+	/// # `ClientFactory::build`
+	/// A new client with its own pool.
+	///
+	/// # Errors
+	/// Returns the error of reqwest if the TLS backend cannot be initialized or rejects the settings.
+	///
+	pub fn build(&self) -> Result<HttpClient, reqwest::Error> {
+		let client = Client::builder().pool_idle_timeout(self.pool_idle_timeout).pool_max_idle_per_host(self.pool_max_idle_per_host).tcp_keepalive(self.tcp_keepalive).connect_timeout(self.connect_timeout).build()?;
+		Ok(with_middleware(client))
+	}
+}
+
+tokio::task_local! {
+	static CURRENT_CLIENT: HttpClient;
+}
+
+///
+/// Run `future` with `client` as the client its vendor requests go through.
+///
+pub async fn scope<F: Future>(client: HttpClient, future: F) -> F::Output {
+	CURRENT_CLIENT.scope(client, future).await
+}
+
+///
+/// # HTTP Client
+/// The client of the backend running on this task, the shared one outside a backend. With the
+/// `fault-injection` feature, faults configured through `faults::set_faults` are injected below the retry
+/// layer.
+///
+pub fn client() -> HttpClient {
+	CURRENT_CLIENT.try_with(Clone::clone).unwrap_or_default()
+}
+
+///
+/// # Response Text
+/// Read a response body as text, decoded with `charset::decode_html` instead of reqwest's UTF-8 fallback so
+/// pages sent in Windows-1252 keep their accented characters. The body is captured for the support bundle
+/// of the lookup running on this task.
+///
+/// # Errors
+/// Returns an error if the body cannot be read, `ResponseTooLarge` if it is over the limit of the host.
+///
+pub async fn text(response: Response) -> Result<String, AvailabilityError> {
+	let content_type = response.headers().get(reqwest::header::CONTENT_TYPE).and_then(|content_type| content_type.to_str().ok()).map(str::to_string);
+	let (url, status) = (response.url().clone(), response.status().as_u16());
+	let body = bytes(response).await?;
+	let text = charset::decode_html(&body, content_type.as_deref());
+	support::capture(&url, status, &text);
+	Ok(text)
+}
+
+///
+/// # Response JSON
+/// Read a response body and parse it as JSON, with the size limit of `bytes`.
+///
+/// # Errors
+/// Returns an error if the body cannot be read or parsed, `ResponseTooLarge` if it is over the limit of the
+/// host.
+///
+pub async fn json<T: DeserializeOwned>(response: Response) -> Result<T, AvailabilityError> {
+	let body = bytes(response).await?;
+	serde_json::from_slice(&body).map_err(|e| AvailabilityError::Other(format!("Failed to parse response: {e}")))
+}
+
+///
+/// # Response Bytes
+/// Read a response body chunk by chunk, giving up as soon as it grows past `response_limit` of its host.
+/// A `Content-Length` over the limit is refused before anything is read.
+///
+/// # Errors
+/// Returns an error if the body cannot be read, `ResponseTooLarge` if it is over the limit of the host.
+///
+pub async fn bytes(mut response: Response) -> Result<Vec<u8>, AvailabilityError> {
+	let limit = response_limit(response.url().host_str().unwrap_or_default());
+	let too_large = |response: &Response| {
+		telemetry::counter("http.response.too_large", 1, &[("host", response.url().host_str().unwrap_or_default().to_lowercase())]);
+		// without the query, which can carry a session id or SAS token.
+		let mut url = response.url().clone();
+		url.set_query(None);
+		url.set_fragment(None);
+		AvailabilityError::ResponseTooLarge { url: url.to_string(), limit }
+	};
+	if response.content_length().is_some_and(|length| length > limit as u64) {
+		return Err(too_large(&response));
+	}
+	let mut body = Vec::new();
+	while let Some(chunk) = response.chunk().await.map_err(|e| AvailabilityError::Other(format!("Failed to read response body: {:?}", e.without_url())))? {
+		if body.len() + chunk.len() > limit {
+			return Err(too_large(&response));
+		}
+		body.extend_from_slice(&chunk);
+	}
+	Ok(body)
+}
+
+///
+/// Path fragments of the pages a portal redirects to when it wants the user to log in again.
+///
+const LOGIN_PATH_MARKERS: [&str; 4] = ["login", "logon", "signin", "saml"];
+
+///
+/// # Auth Rejected
+/// Whether a vendor turned a request away because of its session: a 401 or 403, or redirects that ended on
+/// a login page.
+///
+pub fn auth_rejected(response: &Response) -> bool {
+	let path = response.url().path().to_lowercase();
+	matches!(response.status(), StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) || LOGIN_PATH_MARKERS.iter().any(|marker| path.contains(marker))
+}
+
+fn response_limits() -> &'static RwLock<HashMap<String, usize>> {
+	static LIMITS: OnceLock<RwLock<HashMap<String, usize>>> = OnceLock::new();
+	LIMITS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+///
+/// # `set_response_limit`
+/// Read at most `bytes` of a response body from `host`. Hosts without a limit use
+/// `DEFAULT_RESPONSE_LIMIT`.
+///
+pub fn set_response_limit(host: &str, bytes: usize) {
+	response_limits().write().unwrap_or_else(std::sync::PoisonError::into_inner).insert(host.to_lowercase(), bytes);
+}
+
+///
+/// # `response_limit`
+/// The most bytes of a response body read from `host`.
+///
+#[must_use]
+pub fn response_limit(host: &str) -> usize {
+	response_limits().read().unwrap_or_else(std::sync::PoisonError::into_inner).get(&host.to_lowercase()).copied().unwrap_or(DEFAULT_RESPONSE_LIMIT)
+}
+
+///
+/// Replace every response limit at once.
+///
+pub fn replace_response_limits(limits: HashMap<String, usize>) {
+	*response_limits().write().unwrap_or_else(std::sync::PoisonError::into_inner) = limits;
+}
+
+fn host_limiters() -> &'static RwLock<HashMap<String, Arc<RateLimiter>>> {
+	static LIMITERS: OnceLock<RwLock<HashMap<String, Arc<RateLimiter>>>> = OnceLock::new();
+	LIMITERS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+///
+/// # `set_host_rate_limit`
+/// Allow at most one request per `interval` to `host`, retries included. Hosts without a limit are not
+/// throttled.
+///
+pub fn set_host_rate_limit(host: &str, interval: Duration) {
+	host_limiters().write().unwrap_or_else(std::sync::PoisonError::into_inner).insert(host.to_lowercase(), Arc::new(RateLimiter::new(interval)));
+}
+
+///
+/// Replace every host rate limit at once.
+///
+pub fn replace_host_rate_limits(limits: HashMap<String, Duration>) {
+	let limiters = limits.into_iter().map(|(host, interval)| (host, Arc::new(RateLimiter::new(interval)))).collect();
+	*host_limiters().write().unwrap_or_else(std::sync::PoisonError::into_inner) = limiters;
+}
+
+fn host(req: &Request) -> String {
+	req.url().host_str().unwrap_or_default().to_lowercase()
+}
+
+///
+/// Below the retry layer, so every retry waits for its turn as well. Layers run, outermost first:
+/// telemetry, retry, rate limit, deadline, then fault injection with the `fault-injection` feature.
+///
+struct RateLimitMiddleware;
+
+#[async_trait::async_trait]
+impl Middleware for RateLimitMiddleware {
+	async fn handle(&self, req: Request, extensions: &mut ::http::Extensions, next: Next<'_>) -> reqwest_middleware::Result<Response> {
+		let limiter = host_limiters().read().unwrap_or_else(std::sync::PoisonError::into_inner).get(&host(&req)).cloned();
+		if let Some(limiter) = limiter {
+			limiter.acquire().await;
+		}
+		next.run(req, extensions).await
+	}
+}
+
+///
+/// Retries only the requests that are safe to repeat, GET and HEAD. A post is sent once: repeating a cart
+/// add leaves a second line in the cart, and repeating a login can lock the dealer account.
+///
+struct IdempotentRetryMiddleware(RetryTransientMiddleware<ConfiguredBackoff>);
+
+#[async_trait::async_trait]
+impl Middleware for IdempotentRetryMiddleware {
+	async fn handle(&self, req: Request, extensions: &mut ::http::Extensions, next: Next<'_>) -> reqwest_middleware::Result<Response> {
+		if matches!(*req.method(), Method::GET | Method::HEAD) {
+			self.0.handle(req, extensions, next).await
+		} else {
+			next.run(req, extensions).await
+		}
+	}
+}
+
+///
+/// Below the rate limit, so an attempt gets the time left after waiting for its turn.
+///
+struct DeadlineMiddleware;
+
+#[async_trait::async_trait]
+impl Middleware for DeadlineMiddleware {
+	async fn handle(&self, mut req: Request, extensions: &mut ::http::Extensions, next: Next<'_>) -> reqwest_middleware::Result<Response> {
+		if let Some(remaining) = deadline::remaining() {
+			if remaining.is_zero() {
+				return Err(reqwest_middleware::Error::middleware(AvailabilityError::DeadlineExceeded));
+			}
+			let timeout = req.timeout().map_or(remaining, |timeout| (*timeout).min(remaining));
+			*req.timeout_mut() = Some(timeout);
+		}
+		next.run(req, extensions).await
+	}
+}
+
+///
+/// Outermost layer, so the latency covers all retries of a request.
+///
+struct TelemetryMiddleware;
+
+#[async_trait::async_trait]
+impl Middleware for TelemetryMiddleware {
+	async fn handle(&self, req: Request, extensions: &mut ::http::Extensions, next: Next<'_>) -> reqwest_middleware::Result<Response> {
+		let labels = [("host", host(&req)), ("method", req.method().to_string())];
+		let path = req.url().path().to_string();
+		let start = Instant::now();
+		let result = next.run(req, extensions).await;
+		let status = result.as_ref().map_or_else(|_| "error".to_string(), |response| response.status().as_u16().to_string());
+		support::trace("http", &format!("{} {}{path} -> {status} in {}ms", labels[1].1, labels[0].1, start.elapsed().as_millis()));
+		telemetry::latency("http.request.latency", start.elapsed(), &[labels[0].clone(), labels[1].clone(), ("status", status)]);
+		if result.is_err() {
+			telemetry::counter("http.request.errors", 1, &labels);
+		}
+		result
+	}
+}
@@ -0,0 +1,158 @@
+use std::sync::{Arc, OnceLock, RwLock};
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use super::{telemetry, AvailabilityRequest, RequestOptions};
+
+///
+/// # `InboundPurchaseOrder`
+/// An open purchase order of ours for the model, not yet received.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct InboundPurchaseOrder {
+	pub po_number: String,
+	pub quantity: u32,
+	/// When the units are expected in our warehouse, if the ERP knows.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub expected: Option<NaiveDate>,
+}
+
+impl InboundPurchaseOrder {
+	#[must_use]
+	pub const fn new(po_number: String, quantity: u32) -> Self {
+		Self { po_number, quantity, expected: None }
+	}
+
+	#[must_use]
+	pub const fn with_expected(mut self, expected: NaiveDate) -> Self {
+		self.expected = Some(expected);
+		self
+	}
+}
+
+///
+/// # `InternalStock`
+/// What we hold of a model ourselves: units on hand in our warehouse and units on inbound purchase
+/// orders. Returned next to the vendor availability, never in place of it.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct InternalStock {
+	pub on_hand: u32,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub inbound: Vec<InboundPurchaseOrder>,
+	/// Our warehouse or showroom holding the units on hand.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub location: Option<String>,
+}
+
+impl InternalStock {
+	#[must_use]
+	pub const fn new(on_hand: u32) -> Self {
+		Self { on_hand, inbound: Vec::new(), location: None }
+	}
+
+	#[must_use]
+	pub fn with_inbound(mut self, inbound: InboundPurchaseOrder) -> Self {
+		self.inbound.push(inbound);
+		self
+	}
+
+	#[must_use]
+	pub fn with_location(mut self, location: String) -> Self {
+		self.location = Some(location);
+		self
+	}
+
+	///
+	/// Units on all inbound purchase orders.
+	///
+	#[must_use]
+	pub fn inbound_quantity(&self) -> u32 {
+		self.inbound.iter().map(|order| order.quantity).sum()
+	}
+
+	///
+	/// The earliest expected date of an inbound purchase order.
+	///
+	#[must_use]
+	pub fn next_inbound(&self) -> Option<NaiveDate> {
+		self.inbound.iter().filter_map(|order| order.expected).min()
+	}
+}
+
+///
+/// # `InternalInventorySource`
+/// Our own stock, usually read from the ERP by the host app. Asked after every successful lookup, cached
+/// or not; a source that fails leaves the response without internal stock.
+///
+/// ## Example
+/// ```
+/// use std::sync::Arc;
+/// use eggersmann_app_server_appliance_availability::inventory::{set_inventory_source, InternalInventorySource, InternalStock};
+///
+/// struct Erp;
+///
+/// #[async_trait::async_trait]
+/// impl InternalInventorySource for Erp {
+///     async fn stock(&self, _manufacturer: &str, model_number: &str, _showroom: Option<&str>) -> Result<Option<InternalStock>, String> {
+///         Ok((model_number == "HBLP651RUC").then(|| InternalStock::new(2).with_location("Houston".to_string())))
+///     }
+/// }
+///
+/// set_inventory_source(Arc::new(Erp));
+/// ```
+///
+#[async_trait::async_trait]
+pub trait InternalInventorySource: Send + Sync {
+	///
+	/// Our stock of `model_number` by `manufacturer` for a lookup from `showroom`, `None` if we hold none
+	/// and have none on order.
+	///
+	async fn stock(&self, manufacturer: &str, model_number: &str, showroom: Option<&str>) -> Result<Option<InternalStock>, String>;
+}
+
+fn source_slot() -> &'static RwLock<Option<Arc<dyn InternalInventorySource>>> {
+	static SOURCE: OnceLock<RwLock<Option<Arc<dyn InternalInventorySource>>>> = OnceLock::new();
+	SOURCE.get_or_init(|| RwLock::new(None))
+}
+
+///
+/// # `set_inventory_source`
+/// Install the source internal stock is merged from. Responses carry no internal stock until one is set.
+///
+pub fn set_inventory_source(source: Arc<dyn InternalInventorySource>) {
+	*source_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = Some(source);
+}
+
+///
+/// # `clear_inventory_source`
+/// Stop merging internal stock.
+///
+pub fn clear_inventory_source() {
+	*source_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = None;
+}
+
+///
+/// # `inventory_source`
+/// The installed inventory source, if any.
+///
+pub fn inventory_source() -> Option<Arc<dyn InternalInventorySource>> {
+	source_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
+
+///
+/// Merge our stock into the answered `req`, within what is left of `options`. Internal stock is not
+/// cached, so a cached vendor answer still shows current stock.
+///
+pub(crate) async fn merge(mut req: AvailabilityRequest, options: &RequestOptions) -> AvailabilityRequest {
+	let Some(source) = inventory_source() else { return req };
+	let (Some(manufacturer), Some(model_number)) = (req.manufacturer.as_ref().map(ToString::to_string), req.model_number.clone()) else { return req };
+	match options.run(source.stock(&manufacturer, model_number.trim(), req.showroom.as_deref())).await {
+		Ok(stock) => req.internal_stock = stock,
+		Err(e) => telemetry::event("inventory.lookup.failed", &[("manufacturer", manufacturer), ("model_number", model_number), ("error", e)]),
+	}
+	req
+}
@@ -0,0 +1,201 @@
+use std::sync::{OnceLock, RwLock};
+
+use chrono::{DateTime, Duration, Utc};
+use reqwest::header;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+use super::backend::{BackendAnswer, ManufacturerBackend};
+use super::{Availability, AvailabilityError, AvailabilityRequest, ProductInfo};
+use crate::credentials::SecretString;
+use crate::http_client::{self, HttpClient};
+use crate::stock::{self, StockKeys};
+use crate::telemetry;
+use crate::tokens;
+
+///
+/// Name the Whirlpool trade portal token saved by `jennair_login` is kept under in the `TokenStore`.
+///
+pub const JENNAIR_TOKEN: &str = "jennair_token.json";
+
+///
+/// # `JennAirConfig`
+/// Endpoints of the Whirlpool trade portal: the token endpoint, which takes the portal login as JSON and
+/// answers with a bearer token, and the stock query, which takes the model as `model` and the warehouse
+/// as `warehouse`. The portal addresses come with the trade account, so there is no default and lookups
+/// fail until `set_jennair_config` is called.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct JennAirConfig {
+	pub token_url: String,
+	pub stock_url: String,
+}
+
+impl JennAirConfig {
+	#[must_use]
+	pub const fn new(token_url: String, stock_url: String) -> Self {
+		Self { token_url, stock_url }
+	}
+}
+
+fn config_slot() -> &'static RwLock<Option<JennAirConfig>> {
+	static CONFIG: OnceLock<RwLock<Option<JennAirConfig>>> = OnceLock::new();
+	CONFIG.get_or_init(|| RwLock::new(None))
+}
+
+///
+/// # `set_jennair_config`
+/// Set the endpoints of the Whirlpool trade portal.
+///
+pub fn set_jennair_config(config: JennAirConfig) {
+	*config_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = Some(config);
+}
+
+///
+/// # `jennair_config`
+/// The endpoints of the Whirlpool trade portal, `None` until they are set.
+///
+#[must_use]
+pub fn jennair_config() -> Option<JennAirConfig> {
+	config_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
+
+fn configured() -> Result<JennAirConfig, String> {
+	jennair_config().ok_or_else(|| "No Whirlpool trade portal configured, see `set_jennair_config`.".to_string())
+}
+
+///
+/// # `JennAirBackend`
+/// `JennAir` and Whirlpool lookups through the Whirlpool trade portal, registered as `jennair`. The login
+/// and the stock query go through `client`, the shared client unless one is given.
+///
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct JennAirBackend {
+	pub client: HttpClient,
+}
+
+impl JennAirBackend {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	#[must_use]
+	pub fn with_client(mut self, client: HttpClient) -> Self {
+		self.client = client;
+		self
+	}
+}
+
+#[async_trait::async_trait]
+impl ManufacturerBackend for JennAirBackend {
+	fn name(&self) -> &'static str {
+		"jennair"
+	}
+
+	async fn availability(&self, req: &AvailabilityRequest) -> Result<BackendAnswer, AvailabilityError> {
+		let (username, password) = crate::secrets::vendor_credentials("jennair").await?;
+		let (availability, product) = http_client::scope(self.client.clone(), jennair_lookup(req.clone(), username, password)).await?;
+		Ok(BackendAnswer::new(availability).with_product(product))
+	}
+
+	async fn login(&self) -> Result<(), AvailabilityError> {
+		let (username, password) = crate::secrets::vendor_credentials("jennair").await?;
+		http_client::scope(self.client.clone(), jennair_login(username, password)).await?;
+		Ok(())
+	}
+}
+
+///
+/// # `JennAir` Lookup
+/// Gets the stock and ETA of a `JennAir` or Whirlpool appliance at the request's warehouse together with its
+/// description.
+///
+/// A token the portal turns away is replaced once and the query repeated.
+///
+/// # Errors
+/// Returns `AvailabilityError::VendorUnavailable` if the portal cannot be reached or its answer cannot be
+/// read, and `AvailabilityError::Other` without a configured portal, for a failed login or a request
+/// without model number.
+///
+pub async fn jennair_lookup(req: AvailabilityRequest, username: SecretString, password: SecretString) -> Result<(Availability, Option<ProductInfo>), AvailabilityError> {
+	configured()?;
+	let token = match saved_token().await {
+		Some(token) => token,
+		None => jennair_login(username.clone(), password.clone()).await?,
+	};
+	match jennair_stock(&req, &token).await {
+		Err(AvailabilityError::SessionExpired) => {
+			telemetry::counter("session.refreshed", 1, &[("manufacturer", "jennair".to_string())]);
+			let token = jennair_login(username, password).await?;
+			jennair_stock(&req, &token).await
+		}
+		result => result,
+	}
+}
+
+///
+/// Query the stock of the requested model with the bearer `token`.
+///
+async fn jennair_stock(req: &AvailabilityRequest, token: &str) -> Result<(Availability, Option<ProductInfo>), AvailabilityError> {
+	let today = req.business_today();
+	let model_number = req.model_number.as_deref().map(|model_number| model_number.trim().to_uppercase()).ok_or_else(|| AvailabilityError::Other("No model number found.".to_string()))?;
+	let query = [("model", model_number.as_str()), ("warehouse", req.warehouse.as_deref().unwrap_or_default()), ("quantity", &req.requested_quantity().to_string())];
+	let response = http_client::client().get(configured()?.stock_url).bearer_auth(token).query(&query).send().await.map_err(|e| AvailabilityError::VendorUnavailable(format!("Failed to get JennAir stock: {e:?}")))?;
+	if http_client::auth_rejected(&response) {
+		return Err(AvailabilityError::SessionExpired);
+	}
+	match response.status() {
+		StatusCode::NOT_FOUND => return Ok((Availability::not_found(), None)),
+		status if !status.is_success() => return Err(AvailabilityError::VendorUnavailable(format!("Failed to get JennAir stock: {status}"))),
+		_ => {}
+	}
+	let data: Value = http_client::json(response).await?;
+	Ok(stock::json_stock(&data, &STOCK_KEYS, "JennAir", &model_number, today))
+}
+
+///
+/// Fields of the stock answer, for `stock::json_stock`. The brand is taken from the answer, `JennAir`
+/// unless the portal names another Whirlpool brand.
+///
+const STOCK_KEYS: StockKeys = StockKeys::new("items", "model", "onHand", "eta").with_next_quantity("etaQuantity").with_brand("brand");
+
+///
+/// The saved bearer token, `None` if there is none or it expires within a minute.
+///
+async fn saved_token() -> Option<String> {
+	let file: Value = serde_json::from_str(&tokens::load(JENNAIR_TOKEN).await.ok()??).ok()?;
+	let expires_at = file["expires_at"].as_str().and_then(|expires_at| DateTime::parse_from_rfc3339(expires_at).ok())?;
+	if expires_at <= Utc::now() + Duration::minutes(1) {
+		return None;
+	}
+	file["access_token"].as_str().map(str::to_string).filter(|token| !token.is_empty())
+}
+
+///
+/// # Login to Whirlpool Trade Portal
+/// Get a bearer token for the portal login and save it until it expires.
+///
+/// ## Outputs
+/// `String` - The bearer token.
+///
+/// # Errors
+/// Returns an error if the portal is not configured or cannot be reached, rejects the credentials or
+/// answers without token, or the token cannot be saved.
+///
+pub async fn jennair_login(username: SecretString, password: SecretString) -> Result<String, String> {
+	telemetry::counter("vendor.login", 1, &[("manufacturer", "jennair".to_string())]);
+	let body = json!({ "username": username.expose(), "password": password.expose() });
+	let response = http_client::client().post(configured()?.token_url).header(header::CONTENT_TYPE, "application/json").body(body.to_string()).send().await.map_err(|e| format!("Failed to log in to the Whirlpool trade portal: {e:?}"))?;
+	if http_client::auth_rejected(&response) || !response.status().is_success() {
+		return Err(format!("Whirlpool trade portal login was rejected: {}", response.status()));
+	}
+	let data: Value = http_client::json(response).await.map_err(|e| format!("Failed to parse Whirlpool trade portal token: {e:?}"))?;
+	let token = data["access_token"].as_str().filter(|token| !token.is_empty()).ok_or_else(|| "Whirlpool trade portal answered without token.".to_string())?.to_string();
+	let expires_in = data["expires_in"].as_i64().unwrap_or(3600);
+	let token_json = json!({ "access_token": token, "expires_at": (Utc::now() + Duration::seconds(expires_in)).to_rfc3339() }).to_string();
+	tokens::save(JENNAIR_TOKEN, &token_json).await.map_err(|e| format!("Failed to write JennAir token: {e:?}"))?;
+	Ok(token)
+}
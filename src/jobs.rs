@@ -0,0 +1,517 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::batch::group_requests;
+use super::canary;
+use super::catalog;
+use super::schedule::{self, Workload};
+use super::snapshot::write_canonical_json;
+use super::telemetry;
+use super::watch;
+use super::AvailabilityRequest;
+
+///
+/// # `JobKind`
+/// The background work a job performs.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum JobKind {
+	/// Re-check a watched model, see `watch::schedule_watch`.
+	WatchCheck { request: Box<AvailabilityRequest> },
+	/// Look up a list of models.
+	BulkLookup { requests: Vec<AvailabilityRequest> },
+	/// Log in to a vendor apart from user traffic, see `canary::run_login_canary`.
+	LoginCanary { manufacturer: String },
+	/// Download the full catalog of a vendor, see `catalog::export_catalog`.
+	CatalogExport { manufacturer: String },
+}
+
+impl JobKind {
+	///
+	/// Watch checks and login canaries are routine work, bulk lookups and catalog exports run in the
+	/// overnight window.
+	///
+	#[must_use]
+	pub const fn workload(&self) -> Workload {
+		match self {
+			Self::WatchCheck { .. } | Self::LoginCanary { .. } => Workload::Routine,
+			Self::BulkLookup { .. } | Self::CatalogExport { .. } => Workload::Bulk,
+		}
+	}
+
+	fn requests(&self) -> Vec<AvailabilityRequest> {
+		match self {
+			Self::WatchCheck { request } => vec![(**request).clone()],
+			Self::BulkLookup { requests } => requests.clone(),
+			Self::LoginCanary { .. } | Self::CatalogExport { .. } => Vec::new(),
+		}
+	}
+
+	///
+	/// The showroom and vendor whose schedule the job runs in.
+	///
+	fn scope(&self) -> (Option<String>, Option<String>) {
+		match self {
+			Self::LoginCanary { manufacturer } | Self::CatalogExport { manufacturer } => (None, Some(manufacturer.clone())),
+			_ => self.requests().into_iter().next().map(|request| (request.showroom, request.manufacturer.map(|manufacturer| manufacturer.to_string()))).unwrap_or_default(),
+		}
+	}
+}
+
+///
+/// # `Job`
+/// A pending or scheduled unit of background work.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Job {
+	pub id: String,
+	pub kind: JobKind,
+	pub scheduled_for: DateTime<Utc>,
+	pub attempts: u32,
+	/// Why the last attempt failed.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub last_error: Option<String>,
+}
+
+impl Job {
+	///
+	/// # `Job::new`
+	/// Create a job that runs at `scheduled_for`. The `id` must be unique, it is what makes result
+	/// recording idempotent across retries.
+	///
+	#[must_use]
+	pub const fn new(id: String, kind: JobKind, scheduled_for: DateTime<Utc>) -> Self {
+		Self { id, kind, scheduled_for, attempts: 0, last_error: None }
+	}
+}
+
+///
+/// # `JobRetryPolicy`
+/// How a failed job is retried by `run_due_jobs`: up to `max_attempts` attempts in all, waiting twice as
+/// long before each retry, from `min_backoff` up to `max_backoff`. A job that fails its last attempt is
+/// moved to the dead letters of its queue, see `JobQueue::dead_letters`.
+///
+/// ## Example
+/// ```
+/// use std::time::Duration;
+/// use eggersmann_app_server_appliance_availability::jobs::{set_job_retry_policy, JobRetryPolicy};
+///
+/// set_job_retry_policy(JobRetryPolicy::new(5).with_backoff(Duration::from_secs(30), Duration::from_secs(30 * 60)));
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct JobRetryPolicy {
+	pub max_attempts: u32,
+	pub min_backoff: Duration,
+	pub max_backoff: Duration,
+}
+
+impl Default for JobRetryPolicy {
+	fn default() -> Self {
+		Self::new(8)
+	}
+}
+
+impl JobRetryPolicy {
+	///
+	/// # `JobRetryPolicy::new`
+	/// Make up to `max_attempts` attempts, backing off from one minute to one hour.
+	///
+	#[must_use]
+	pub const fn new(max_attempts: u32) -> Self {
+		Self { max_attempts, min_backoff: Duration::from_mins(1), max_backoff: Duration::from_hours(1) }
+	}
+
+	#[must_use]
+	pub const fn with_backoff(mut self, min_backoff: Duration, max_backoff: Duration) -> Self {
+		self.min_backoff = min_backoff;
+		self.max_backoff = max_backoff;
+		self
+	}
+
+	///
+	/// # `JobRetryPolicy::backoff`
+	/// How long to wait after the failed attempt number `attempt`, counted from one.
+	///
+	#[must_use]
+	pub fn backoff(&self, attempt: u32) -> Duration {
+		let doublings = attempt.saturating_sub(1).min(31);
+		self.min_backoff.saturating_mul(1 << doublings).min(self.max_backoff)
+	}
+}
+
+fn retry_slot() -> &'static RwLock<JobRetryPolicy> {
+	static POLICY: OnceLock<RwLock<JobRetryPolicy>> = OnceLock::new();
+	POLICY.get_or_init(|| RwLock::new(JobRetryPolicy::default()))
+}
+
+///
+/// # `set_job_retry_policy`
+/// Retry failed jobs with `policy` from the next failure on.
+///
+pub fn set_job_retry_policy(policy: JobRetryPolicy) {
+	*retry_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = policy;
+}
+
+///
+/// # `job_retry_policy`
+/// How failed jobs are retried.
+///
+#[must_use]
+pub fn job_retry_policy() -> JobRetryPolicy {
+	*retry_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+///
+/// # `JobQueue`
+/// Durable storage for background jobs.
+///
+/// Jobs are only removed by `complete`, so a job that was running when the process died is handed out
+/// again by `due` after a restart (at-least-once delivery).
+///
+pub trait JobQueue: Send + Sync {
+	///
+	/// Add or replace a job.
+	///
+	/// # Errors
+	/// Returns an error if the queue cannot be persisted.
+	fn enqueue(&self, job: Job) -> Result<(), String>;
+
+	///
+	/// All jobs scheduled at or before `now`, with their attempt counter incremented.
+	///
+	/// # Errors
+	/// Returns an error if the queue cannot be persisted.
+	fn due(&self, now: DateTime<Utc>) -> Result<Vec<Job>, String>;
+
+	///
+	/// Every job in the queue.
+	///
+	fn pending(&self) -> Vec<Job>;
+
+	///
+	/// Remove a finished job.
+	///
+	/// # Errors
+	/// Returns an error if the queue cannot be persisted.
+	fn complete(&self, id: &str) -> Result<(), String>;
+
+	///
+	/// Record that result `result_id` was stored.
+	///
+	/// # Errors
+	/// Returns an error if the queue cannot be persisted.
+	fn mark_recorded(&self, result_id: &str) -> Result<(), String>;
+
+	///
+	/// Whether result `result_id` was already stored by an earlier attempt.
+	///
+	fn is_recorded(&self, result_id: &str) -> bool;
+
+	///
+	/// Move a job that used up its attempts out of the queue and into the dead letters.
+	///
+	/// # Errors
+	/// Returns an error if the queue cannot be persisted.
+	fn dead_letter(&self, job: Job) -> Result<(), String>;
+
+	///
+	/// Every job that used up its attempts, oldest first. Enqueue one again to retry it.
+	///
+	fn dead_letters(&self) -> Vec<Job>;
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct QueueState {
+	jobs: Vec<Job>,
+	recorded: BTreeSet<String>,
+	#[serde(default)]
+	dead_letters: Vec<Job>,
+}
+
+impl QueueState {
+	fn enqueue(&mut self, job: Job) {
+		self.jobs.retain(|existing| existing.id != job.id);
+		self.jobs.push(job);
+	}
+
+	fn due(&mut self, now: DateTime<Utc>) -> Vec<Job> {
+		self.jobs
+			.iter_mut()
+			.filter(|job| job.scheduled_for <= now)
+			.map(|job| {
+				job.attempts += 1;
+				job.clone()
+			})
+			.collect()
+	}
+
+	fn complete(&mut self, id: &str) {
+		self.jobs.retain(|job| job.id != id);
+		let prefix = format!("{id}:");
+		self.recorded.retain(|result_id| !result_id.starts_with(&prefix));
+	}
+
+	/// The lines it already recorded are kept, so a dead letter enqueued again skips them.
+	fn dead_letter(&mut self, job: Job) {
+		self.jobs.retain(|existing| existing.id != job.id);
+		self.dead_letters.retain(|existing| existing.id != job.id);
+		self.dead_letters.push(job);
+	}
+}
+
+///
+/// # `InMemoryJobQueue`
+/// Job queue kept in process memory, for tests and single-run tools.
+///
+#[derive(Debug, Default)]
+pub struct InMemoryJobQueue {
+	state: Mutex<QueueState>,
+}
+
+impl JobQueue for InMemoryJobQueue {
+	fn enqueue(&self, job: Job) -> Result<(), String> {
+		self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner).enqueue(job);
+		Ok(())
+	}
+
+	fn due(&self, now: DateTime<Utc>) -> Result<Vec<Job>, String> {
+		Ok(self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner).due(now))
+	}
+
+	fn pending(&self) -> Vec<Job> {
+		self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner).jobs.clone()
+	}
+
+	fn complete(&self, id: &str) -> Result<(), String> {
+		self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner).complete(id);
+		Ok(())
+	}
+
+	fn mark_recorded(&self, result_id: &str) -> Result<(), String> {
+		self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner).recorded.insert(result_id.to_string());
+		Ok(())
+	}
+
+	fn is_recorded(&self, result_id: &str) -> bool {
+		self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner).recorded.contains(result_id)
+	}
+
+	fn dead_letter(&self, job: Job) -> Result<(), String> {
+		self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner).dead_letter(job);
+		Ok(())
+	}
+
+	fn dead_letters(&self) -> Vec<Job> {
+		self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner).dead_letters.clone()
+	}
+}
+
+///
+/// # `FileJobQueue`
+/// Job queue persisted as a JSON file, written after every change so it survives redeploys.
+///
+#[derive(Debug)]
+pub struct FileJobQueue {
+	path: PathBuf,
+	state: Mutex<QueueState>,
+}
+
+impl FileJobQueue {
+	///
+	/// # `FileJobQueue::open`
+	/// Open the queue stored at `path`, resuming any jobs left from a previous run.
+	///
+	/// # Errors
+	/// Returns an error if an existing queue file cannot be read or parsed.
+	///
+	pub fn open(path: impl Into<PathBuf>) -> Result<Self, String> {
+		let path = path.into();
+		let state = if path.exists() {
+			let contents = fs::read_to_string(&path).map_err(|e| format!("Failed to read job queue {}: {e:?}", path.display()))?;
+			serde_json::from_str(&contents).map_err(|e| format!("Failed to parse job queue {}: {e:?}", path.display()))?
+		} else {
+			QueueState::default()
+		};
+		Ok(Self { path, state: Mutex::new(state) })
+	}
+
+	// the lock is held while writing so concurrent updates reach the file in the order they were made.
+	#[allow(clippy::significant_drop_tightening)]
+	fn update<T>(&self, change: impl FnOnce(&mut QueueState) -> T) -> Result<T, String> {
+		let mut state = self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+		let out = change(&mut state);
+		let tmp = self.path.with_extension("tmp");
+		write_canonical_json(&tmp, &*state)?;
+		fs::rename(&tmp, &self.path).map_err(|e| format!("Failed to replace job queue {}: {e:?}", self.path.display()))?;
+		Ok(out)
+	}
+}
+
+impl JobQueue for FileJobQueue {
+	fn enqueue(&self, job: Job) -> Result<(), String> {
+		self.update(|state| state.enqueue(job))
+	}
+
+	fn due(&self, now: DateTime<Utc>) -> Result<Vec<Job>, String> {
+		self.update(|state| state.due(now))
+	}
+
+	fn pending(&self) -> Vec<Job> {
+		self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner).jobs.clone()
+	}
+
+	fn complete(&self, id: &str) -> Result<(), String> {
+		self.update(|state| state.complete(id))
+	}
+
+	fn mark_recorded(&self, result_id: &str) -> Result<(), String> {
+		self.update(|state| {
+			state.recorded.insert(result_id.to_string());
+		})
+	}
+
+	fn is_recorded(&self, result_id: &str) -> bool {
+		self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner).recorded.contains(result_id)
+	}
+
+	fn dead_letter(&self, job: Job) -> Result<(), String> {
+		self.update(|state| state.dead_letter(job))
+	}
+
+	fn dead_letters(&self) -> Vec<Job> {
+		self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner).dead_letters.clone()
+	}
+}
+
+///
+/// The longest a failed job waits for its next attempt, however long its backoff.
+///
+const MAX_RETRY_BACKOFF: TimeDelta = match TimeDelta::try_days(365) {
+	Some(backoff) => backoff,
+	None => TimeDelta::zero(),
+};
+
+///
+/// Queue a failed job again after the backoff of its attempt, or move it to the dead letters once it has
+/// made `JobRetryPolicy::max_attempts` attempts.
+///
+fn retry_later(queue: &dyn JobQueue, mut job: Job, error: String, now: DateTime<Utc>) -> Result<(), String> {
+	let policy = job_retry_policy();
+	job.last_error = Some(error);
+	if job.attempts >= policy.max_attempts {
+		telemetry::event("jobs.dead_lettered", &[("job", job.id.clone()), ("attempts", job.attempts.to_string()), ("error", job.last_error.clone().unwrap_or_default())]);
+		return queue.dead_letter(job);
+	}
+	let backoff = TimeDelta::from_std(policy.backoff(job.attempts)).map_or(MAX_RETRY_BACKOFF, |backoff| backoff.min(MAX_RETRY_BACKOFF));
+	job.scheduled_for = now.checked_add_signed(backoff).unwrap_or(DateTime::<Utc>::MAX_UTC);
+	queue.enqueue(job)
+}
+
+///
+/// # `run_due_jobs`
+/// Run every job that is due and remove the jobs that finished. Lines already recorded by an earlier
+/// attempt of the same job are skipped, and identical lines of a bulk lookup are looked up once.
+///
+/// Call this on startup to resume work left over from before a restart, then periodically. A job outside
+/// the window `schedule_policy` allows for its showroom and vendor is moved to the start of the next one.
+/// A watch check of a vendor with a `watch::WatchPolicy` is queued again for its next check.
+///
+/// A job that fails is queued again after the backoff of `job_retry_policy`, and moved to the dead
+/// letters of the queue once it has made `JobRetryPolicy::max_attempts` attempts.
+///
+/// ## Outputs
+/// Vec<String> - Ids of the jobs that completed.
+///
+/// # Errors
+/// Returns an error if the queue cannot be read or persisted.
+///
+pub async fn run_due_jobs(queue: &dyn JobQueue) -> Result<Vec<String>, String> {
+	let mut completed = Vec::new();
+	let now = Utc::now();
+	for mut job in queue.due(now)? {
+		let (showroom, vendor) = job.kind.scope();
+		let workload = job.kind.workload();
+		if !schedule::may_run(workload, showroom.as_deref(), vendor.as_deref(), now) {
+			// not an attempt, put the job back for the next window.
+			job.attempts = job.attempts.saturating_sub(1);
+			job.scheduled_for = schedule::next_run(workload, showroom.as_deref(), vendor.as_deref(), now);
+			queue.enqueue(job)?;
+			continue;
+		}
+		if let JobKind::LoginCanary { manufacturer } = &job.kind {
+			// the outcome is published by the canary, the next run replaces this one.
+			let _ = canary::run_login_canary(manufacturer).await;
+			let next = Job::new(job.id.clone(), job.kind.clone(), schedule::next_run(workload, showroom.as_deref(), vendor.as_deref(), now + canary::canary_policy().interval));
+			queue.complete(&job.id)?;
+			queue.enqueue(next)?;
+			completed.push(job.id);
+			continue;
+		}
+		if let JobKind::WatchCheck { request } = &job.kind {
+			let request = (**request).clone().parse_manufacturer().get_warehouse().get_time();
+			if let Some(policy) = request.manufacturer.as_ref().and_then(|manufacturer| watch::watch_policy(manufacturer.as_str())) {
+				// a failed check is retried, a successful one is replaced by the next check.
+				let answer = match request.into_answered().await {
+					Ok(answer) => answer,
+					Err(e) => {
+						retry_later(queue, job, e, now)?;
+						continue;
+					}
+				};
+				queue.complete(&job.id)?;
+				if let Some(next) = watch::next_check(&policy, queue, &answer, now) {
+					queue.enqueue(Job::new(job.id.clone(), job.kind.clone(), next))?;
+				}
+				completed.push(job.id);
+				continue;
+			}
+		}
+		if let JobKind::CatalogExport { manufacturer } = &job.kind {
+			// a failed export is retried, the previous catalog is kept meanwhile.
+			match catalog::export_catalog(manufacturer).await {
+				Ok(_) => {
+					queue.complete(&job.id)?;
+					completed.push(job.id);
+				}
+				Err(e) => retry_later(queue, job, e, now)?,
+			}
+			continue;
+		}
+		let requests: Vec<AvailabilityRequest> = job.kind.requests().into_iter().map(|request| request.parse_manufacturer().get_warehouse().get_time()).collect();
+		let mut failed = None;
+		// identical lines share one lookup.
+		for group in group_requests(&requests) {
+			let pending: Vec<usize> = group.into_iter().filter(|i| !queue.is_recorded(&format!("{}:{i}", job.id))).collect();
+			let Some(&first) = pending.first() else { continue };
+			// `into_answered` records the answer to the history, marking it afterwards keeps a retried
+			// job from recording the same line twice.
+			match requests[first].clone().into_answered().await {
+				Ok(_) => {
+					for i in pending {
+						queue.mark_recorded(&format!("{}:{i}", job.id))?;
+					}
+				}
+				Err(e) => failed = Some(e),
+			}
+		}
+		match failed {
+			None => {
+				queue.complete(&job.id)?;
+				completed.push(job.id);
+			}
+			Some(e) => retry_later(queue, job, e, now)?,
+		}
+	}
+	Ok(completed)
+}
@@ -1,24 +1,303 @@
+//!
+//! # Appliance availability
+//! Availability lookups against the appliance vendor portals for the app server.
+//!
+//! ## Features
+//! Each vendor and each heavy dependency sits behind a Cargo feature. The request and response types,
+//! caching, batching, history, telemetry and the training `simulation` are always built.
+//!
+//! | Feature | Adds | Pulls in |
+//! |---|---|---|
+//! | `bsh` | BSH, Thermador and Gaggenau lookups over HTTP with a saved session | `auth` |
+//! | `browser-login` | `BshBackend::login`, sharing one browser between logins through `browser` | `bsh`, playwright |
+//! | `subzero` | `SubZero` and Wolf lookups, `subzero_suggest`, the price list export | `auth`, scraper, sha2 |
+//! | `miele` | Miele lookups and catalog | office, fuzzy-matcher |
+//! | `liebherr` | Liebherr lookups from the dealer stock feed | |
+//! | `fisher-paykel` | Fisher & Paykel lookups through the dealer portal | |
+//! | `jennair` | `JennAir` and Whirlpool lookups through the Whirlpool trade portal | |
+//! | `bertazzoni` | Bertazzoni lookups from the distributor availability feed | office, fuzzy-matcher, sha2 |
+//! | `dacor` | Dacor lookups and lead times through the Samsung Dacor dealer portal | |
+//! | `true-residential` | True Residential lookups through the dealer availability service | |
+//! | `monogram` | GE Monogram lookups through the GE Appliances dealer API | |
+//! | `keyvault` | `credentials::KeyVaultProvider`, the default credential provider instead of the environment | azure SDKs |
+//! | `auth` | `AvailabilityRequest::add_user`, `access::AvailabilityService` | egg-server-auth |
+//! | `tower` | `service` | tower |
+//! | `toml` | TOML files for `config::reload_config_file` and `config::load_warehouse_file` | toml |
+//! | `xlsx` | `SupplierScorecard::write_xlsx` | `rust_xlsxwriter` |
+//! | `telemetry-tracing` | `telemetry::TracingExporter` | tracing |
+//! | `metrics` | `telemetry::MetricsExporter`, for a Prometheus or other `metrics` recorder | metrics |
+//! | `fault-injection` | `faults` (tests only) | fastrand |
+//! | `test-support` | `testing`, a scripted backend, fake responses and assertions for tests of dependent crates | |
+//!
+//! `default` enables `bsh`, `browser-login`, `subzero`, `miele`, `liebherr`, `fisher-paykel`, `jennair`, `monogram`, `true-residential`, `dacor`, `bertazzoni` and `keyvault`. A lookup for a vendor whose
+//! feature is off fails with an error naming the feature.
+//!
+//! ## Supported API
+//! `prelude` holds the supported API. The per-vendor `*_availability` and `*_login` functions at the crate
+//! root are deprecated shims kept for existing callers.
+//!
 #![warn(clippy::pedantic, clippy::nursery, clippy::all, clippy::cargo)]
-#![allow(clippy::multiple_crate_versions, clippy::module_name_repetitions)]
+#![allow(clippy::multiple_crate_versions, clippy::module_name_repetitions, clippy::redundant_feature_names)]
 #![allow(dead_code)]
+#![cfg_attr(docsrs, feature(doc_cfg))]
 
-use azure_security_keyvault::KeyvaultClient;
-pub use bsh::{bsh_availability, bsh_login};
-use chrono::Utc;
+use std::borrow::Cow;
+use std::time::Instant;
+
+pub use account::{account_issue, clear_account_checks, AccountIssue, ACCOUNT_CHECK_TTL};
+use annotations::Annotation;
+#[cfg(feature = "bertazzoni")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bertazzoni")))]
+pub use bertazzoni::{bertazzoni_feed_config, current_bertazzoni_catalog, refresh_bertazzoni_catalog, set_bertazzoni_feed_config, BertazzoniBackend, BertazzoniCatalog, BertazzoniFeedConfig};
+#[cfg(feature = "bsh")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bsh")))]
+pub use bsh::{bsh_availability_multi, bsh_brand_config, bsh_language, bsh_material, set_bsh_brand_config, set_bsh_language, BshBackend, BshBrand, BshBrandConfig, BshMaterial, LineItem, LineItemAvailability};
+pub use builder::AvailabilityRequestBuilder;
+pub use cache::{result_cache_config, set_result_cache_config, ResultCacheConfig};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+pub use chrono_tz::Tz;
+pub use compliance::RestrictedItem;
+pub use credentials::SecretString;
+#[cfg(feature = "dacor")]
+#[cfg_attr(docsrs, doc(cfg(feature = "dacor")))]
+pub use dacor::{dacor_config, dacor_lead_time, set_dacor_config, DacorBackend, DacorConfig};
+pub use deadline::{RequestOptions, DEFAULT_LOOKUP_TIMEOUT};
+use discovery::NationwideAvailability;
+#[cfg(feature = "auth")]
 use eggersmann_app_server_auth::User;
-pub use miele::miele_availability;
+pub use error::{AvailabilityError, RemediationHint};
+#[cfg(feature = "fisher-paykel")]
+#[cfg_attr(docsrs, doc(cfg(feature = "fisher-paykel")))]
+pub use fisher_paykel::{fisher_paykel_config, set_fisher_paykel_config, FisherPaykelBackend, FisherPaykelConfig};
+pub use hedge::{hedge_policy, set_hedge_policy, HedgePolicy};
+pub use http_client::{backoff_policy, response_limit, set_backoff_policy, set_host_rate_limit, set_response_limit, BackoffPolicy, ClientFactory, HttpClient, DEFAULT_RESPONSE_LIMIT};
+use inventory::InternalStock;
+#[cfg(feature = "jennair")]
+#[cfg_attr(docsrs, doc(cfg(feature = "jennair")))]
+pub use jennair::{jennair_config, set_jennair_config, JennAirBackend, JennAirConfig};
+#[cfg(feature = "liebherr")]
+#[cfg_attr(docsrs, doc(cfg(feature = "liebherr")))]
+pub use liebherr::{liebherr_config, set_liebherr_config, LiebherrBackend, LiebherrConfig};
+pub use manufacturer::Manufacturer;
+#[cfg(feature = "miele")]
+#[cfg_attr(docsrs, doc(cfg(feature = "miele")))]
+pub use miele::{catalog_status, current_miele_catalog, miele_report_config, miele_source, refresh_miele_catalog, set_miele_report_config, set_miele_source, MieleApiConfig, MieleBackend, MieleCatalog, MieleCatalogStatus, MieleReportConfig, MieleSource};
+#[cfg(feature = "monogram")]
+#[cfg_attr(docsrs, doc(cfg(feature = "monogram")))]
+pub use monogram::{monogram_config, set_monogram_config, MonogramBackend, MonogramConfig};
+pub use rejection::{Rejection, RejectionReason};
+pub use response::{Availability, AvailabilityResponse, AvailabilityResponseBuilder, AvailabilityResponseV2, AvailabilityStatus, CatalogVersion, FieldProvenance, OrderLine, ProductInfo, Provenance, ScheduleLine, Tranche};
 use serde::{Deserialize, Serialize};
-pub use subzero::{subzero_availability, subzero_login};
+#[cfg(feature = "subzero")]
+#[cfg_attr(docsrs, doc(cfg(feature = "subzero")))]
+pub use subzero::{cached_subzero_price_list, clear_subzero_cart, export_subzero_price_list, set_subzero_fingerprints, set_subzero_lookup_config, set_subzero_price_list_config, subzero_cart_dirty, subzero_fingerprints, subzero_lookup_config, subzero_price_list_config, subzero_suggest, Fingerprint, SubzeroBackend, SubzeroLookupConfig, SubzeroPriceList, SubzeroPriceListConfig, Suggestion, WolfBackend};
+pub use support::{support_bundle, EnvironmentInfo, PayloadCapture, SessionStatus, SupportBundle, TraceStep, MAX_TRACKED_REQUESTS};
+pub use timezone::{business_today, set_showroom_time_zone, showroom_local_time, showroom_time_zone, DEFAULT_BUSINESS_TIME_ZONE};
+#[cfg(feature = "true-residential")]
+#[cfg_attr(docsrs, doc(cfg(feature = "true-residential")))]
+pub use true_residential::{set_true_residential_config, true_residential_config, TrueResidentialBackend, TrueResidentialConfig};
+pub use warehouse::{clear_vendor_selector, set_vendor_selector, vendor_selector, Showroom, VendorRoute, VendorSelector, Warehouse, WarehouseDecision, WarehouseSource, WAREHOUSE_TABLE};
 
+#[cfg(feature = "auth")]
+#[cfg_attr(docsrs, doc(cfg(feature = "auth")))]
+pub mod access;
+mod account;
+pub mod annotations;
+pub mod backend;
+pub mod batch;
+#[cfg(feature = "bertazzoni")]
+mod bertazzoni;
+#[cfg(feature = "browser-login")]
+#[cfg_attr(docsrs, doc(cfg(feature = "browser-login")))]
+pub mod browser;
+#[cfg(feature = "bsh")]
 mod bsh;
+mod builder;
+mod cache;
+pub mod calendar;
+pub mod canary;
+pub mod catalog;
+pub mod charset;
+pub mod compat;
+pub mod compliance;
+pub mod config;
+#[cfg(any(feature = "bsh", feature = "subzero", feature = "fisher-paykel", feature = "dacor"))]
+mod cookies;
+pub mod credentials;
+#[cfg(feature = "dacor")]
+mod dacor;
+mod deadline;
+pub mod discovery;
+mod error;
+pub mod events;
+#[cfg(feature = "fault-injection")]
+#[cfg_attr(docsrs, doc(cfg(feature = "fault-injection")))]
+pub mod faults;
+#[cfg(feature = "fisher-paykel")]
+mod fisher_paykel;
+mod hedge;
+pub mod history;
+mod http_client;
+pub mod inventory;
+#[cfg(feature = "jennair")]
+mod jennair;
+pub mod jobs;
+#[cfg(feature = "liebherr")]
+mod liebherr;
+mod manufacturer;
+pub mod memory;
+#[cfg(feature = "miele")]
 mod miele;
+#[cfg(feature = "monogram")]
+mod monogram;
+pub mod prelude;
+pub mod quote;
+mod ratelimit;
+mod rejection;
+mod response;
+pub mod schedule;
+pub mod scorecard;
+mod secrets;
+#[cfg(feature = "tower")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tower")))]
+pub mod service;
+pub mod simulation;
+pub mod sla;
+pub mod snapshot;
+#[cfg(any(feature = "fisher-paykel", feature = "jennair", feature = "monogram", feature = "true-residential", feature = "dacor"))]
+mod stock;
+#[cfg(feature = "subzero")]
 mod subzero;
+mod support;
+pub mod telemetry;
+#[cfg(feature = "test-support")]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-support")))]
+pub mod testing;
+mod timezone;
+pub mod tokens;
+#[cfg(feature = "true-residential")]
+mod true_residential;
+mod warehouse;
+pub mod watch;
+
+///
+/// # `initialize`
+/// Prepare the crate at service start: fetch the credentials of every enabled vendor into memory and
+/// keep them refreshed in the background, so the first lookup after a deploy does not wait on the
+/// credential provider.
+///
+/// Must be called inside a Tokio runtime. Call it after `set_miele_source` so the Miele API credentials
+/// are included.
+///
+/// # Errors
+/// Returns an error naming the secrets that could not be fetched. Lookups still fetch them on demand.
+///
+pub async fn initialize() -> Result<(), String> {
+	#[cfg_attr(not(any(feature = "bsh", feature = "subzero", feature = "miele", feature = "liebherr", feature = "fisher-paykel", feature = "jennair", feature = "monogram", feature = "true-residential", feature = "dacor")), allow(unused_mut))]
+	let mut names: Vec<String> = Vec::new();
+	#[cfg(feature = "bsh")]
+	names.extend(["bsh-username".to_string(), "bsh-password".to_string()]);
+	#[cfg(feature = "subzero")]
+	names.extend(["subzero-username".to_string(), "subzero-password".to_string()]);
+	#[cfg(feature = "liebherr")]
+	names.extend(["liebherr-username".to_string(), "liebherr-password".to_string()]);
+	#[cfg(feature = "fisher-paykel")]
+	names.extend(["fisher-paykel-username".to_string(), "fisher-paykel-password".to_string()]);
+	#[cfg(feature = "jennair")]
+	names.extend(["jennair-username".to_string(), "jennair-password".to_string()]);
+	#[cfg(feature = "true-residential")]
+	names.extend(["true-residential-username".to_string(), "true-residential-password".to_string()]);
+	#[cfg(feature = "dacor")]
+	names.extend(["dacor-username".to_string(), "dacor-password".to_string()]);
+	#[cfg(feature = "monogram")]
+	if let Some(config) = monogram::monogram_config() {
+		names.extend([config.client_id_secret, config.client_secret_secret]);
+	}
+	#[cfg(feature = "miele")]
+	if let MieleSource::Api(config) = miele_source() {
+		names.extend([config.client_id_secret, config.client_secret_secret]);
+	}
+	secrets::prefetch(&names).await
+}
+
+///
+/// # BSH Availability
+/// Look `req` up at BSH with the given credentials, each a `String`, `&str` or `SecretString`.
+///
+/// # Errors
+/// Returns an error if the lookup fails.
+///
+#[cfg(feature = "bsh")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bsh")))]
+#[deprecated(note = "use `AvailabilityRequest::get_availability`, or `backend::backend(\"bsh\")` with a `CredentialProvider`")]
+pub async fn bsh_availability(req: AvailabilityRequest, username: impl Into<SecretString>, password: impl Into<SecretString>) -> Result<String, String> {
+	bsh::bsh_availability(req, username.into(), password.into()).await
+}
+
+///
+/// # Login to BSH System
+/// Log in to the BSH portal with the given credentials, each a `String`, `&str` or `SecretString`, and save
+/// the session.
+///
+/// # Errors
+/// Returns an error if the login fails or needs the `browser-login` feature.
+///
+#[cfg(feature = "bsh")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bsh")))]
+#[deprecated(note = "use `ManufacturerBackend::login` of `backend::backend(\"bsh\")` with a `CredentialProvider`")]
+pub async fn bsh_login(username: impl Into<SecretString>, password: impl Into<SecretString>) -> Result<bool, String> {
+	bsh::bsh_login(username.into(), password.into()).await
+}
+
+///
+/// # `SubZero` Availability
+/// Look `req` up at `SubZero` with the given credentials, each a `String`, `&str` or `SecretString`.
+///
+/// # Errors
+/// Returns an error if the lookup fails.
+///
+#[cfg(feature = "subzero")]
+#[cfg_attr(docsrs, doc(cfg(feature = "subzero")))]
+#[deprecated(note = "use `AvailabilityRequest::get_availability`, or `backend::backend(\"subzero\")` with a `CredentialProvider`")]
+pub async fn subzero_availability(req: AvailabilityRequest, username: impl Into<SecretString>, password: impl Into<SecretString>) -> Result<String, String> {
+	subzero::subzero_availability(req, username.into(), password.into()).await
+}
+
+///
+/// # Login to `SubZero` System
+/// Log in to the `SubZero` portal with the given credentials, each a `String`, `&str` or `SecretString`, and
+/// save the session.
+///
+/// # Errors
+/// Returns an error if the login fails.
+///
+#[cfg(feature = "subzero")]
+#[cfg_attr(docsrs, doc(cfg(feature = "subzero")))]
+#[deprecated(note = "use `ManufacturerBackend::login` of `backend::backend(\"subzero\")` with a `CredentialProvider`")]
+pub async fn subzero_login(username: impl Into<SecretString>, password: impl Into<SecretString>) -> Result<(), String> {
+	subzero::subzero_login(username.into(), password.into()).await
+}
+
+///
+/// # Miele Availability
+/// Look `req` up at Miele.
+///
+/// # Errors
+/// Returns an error if the lookup fails.
+///
+#[cfg(feature = "miele")]
+#[cfg_attr(docsrs, doc(cfg(feature = "miele")))]
+#[deprecated(note = "use `AvailabilityRequest::get_availability`, or `backend::backend(\"miele\")`")]
+pub async fn miele_availability(req: AvailabilityRequest) -> Result<String, String> {
+	miele::miele_availability(req).await
+}
 
 ///
 /// # `AvailabilityRequestUser`
 /// User struct for use in the availability request.
 ///
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct AvailabilityRequestUser {
 	pub id: String,
 	pub given_name: Option<String>,
@@ -27,21 +306,172 @@ pub struct AvailabilityRequestUser {
 	pub job_title: Option<String>,
 	pub user_principal_name: Option<String>,
 	pub office_location: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub preferred_language: Option<String>,
+}
+
+impl AvailabilityRequestUser {
+	///
+	/// # `AvailabilityRequestUser::new`
+	/// Create a new `AvailabilityRequestUser` with only the id set.
+	///
+	/// ## Example
+	/// ```
+	/// use eggersmann_app_server_appliance_availability::AvailabilityRequestUser;
+	///
+	/// let user = AvailabilityRequestUser::new("0000-0000".to_string()).with_job_title("Designer".to_string());
+	/// assert_eq!(user.job_title.as_deref(), Some("Designer"));
+	/// ```
+	///
+	#[must_use]
+	pub const fn new(id: String) -> Self {
+		Self { id, given_name: None, surname: None, display_name: None, job_title: None, user_principal_name: None, office_location: None, preferred_language: None }
+	}
+
+	#[must_use]
+	pub fn with_given_name(mut self, given_name: String) -> Self {
+		self.given_name = Some(given_name);
+		self
+	}
+
+	#[must_use]
+	pub fn with_surname(mut self, surname: String) -> Self {
+		self.surname = Some(surname);
+		self
+	}
+
+	#[must_use]
+	pub fn with_display_name(mut self, display_name: String) -> Self {
+		self.display_name = Some(display_name);
+		self
+	}
+
+	#[must_use]
+	pub fn with_job_title(mut self, job_title: String) -> Self {
+		self.job_title = Some(job_title);
+		self
+	}
+
+	///
+	/// # `AvailabilityRequestUser::may_see_pricing`
+	/// Whether the user's job title is one of the `pricing_job_titles` of the runtime configuration.
+	///
+	#[must_use]
+	pub fn may_see_pricing(&self) -> bool {
+		self.job_title.as_deref().is_some_and(config::pricing_allowed_for)
+	}
+
+	#[must_use]
+	pub fn with_user_principal_name(mut self, user_principal_name: String) -> Self {
+		self.user_principal_name = Some(user_principal_name);
+		self
+	}
+
+	#[must_use]
+	pub fn with_office_location(mut self, office_location: String) -> Self {
+		self.office_location = Some(office_location);
+		self
+	}
+
+	///
+	/// The user's locale, e.g. `de-DE`. Vendors that support it answer in this language.
+	///
+	#[must_use]
+	pub fn with_preferred_language(mut self, preferred_language: String) -> Self {
+		self.preferred_language = Some(preferred_language);
+		self
+	}
 }
 
 ///
 /// # `AvailabilityRequest`
 /// Struct for use in the availability request.
 ///
+/// The JSON shape is what existing app server consumers already store and send: every field keeps its
+/// name, strings stay lowercase where they were, and fields added later are omitted when empty. New
+/// typed fields go through the adapters in `compat` so the wire format does not change.
+///
+/// ## Example
+/// ```
+/// use eggersmann_app_server_appliance_availability::AvailabilityRequest;
+///
+/// let v1 = r#"{"manufacturer":"bsh","showroom":"houston","model_number":"HBLP651RUC","warehouse":"4401","utc_time":"03/14/2024 09:26:53 AM","availability":"Available","user":null}"#;
+/// let req: AvailabilityRequest = serde_json::from_str(v1).unwrap();
+/// assert_eq!(serde_json::to_string(&req).unwrap(), v1);
+/// ```
+///
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct AvailabilityRequest {
-	pub manufacturer: Option<String>,
+	#[serde(default, with = "crate::compat::lowercase")]
+	pub manufacturer: Option<Manufacturer>,
 	pub showroom: Option<String>,
 	pub model_number: Option<String>,
 	pub warehouse: Option<String>,
+	/// `requested_at` in `compat::V1_TIME_FORMAT`, kept for the v1 payload. It looks like a local time but
+	/// is UTC.
+	#[deprecated(note = "use `requested_at`, or `local_time` for display")]
 	pub utc_time: Option<String>,
+	/// When the lookup was made, set by `get_time`. Every date of the lookup is taken at this instant.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub requested_at: Option<DateTime<Utc>>,
 	pub availability: Option<String>,
+	/// `availability` read into status, date and quantity by the backend that answered.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub availability_detail: Option<Availability>,
 	pub user: Option<AvailabilityRequestUser>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub product: Option<ProductInfo>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub cached_at: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub restricted: Option<RestrictedItem>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub warehouse_decision: Option<WarehouseDecision>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub quantity: Option<u32>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub rejection: Option<Rejection>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub account_issue: Option<AccountIssue>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub catalog_version: Option<CatalogVersion>,
+	/// Id of the lookup, assigned when the request is looked up without one. Pass it to `support_bundle`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub request_id: Option<String>,
+	/// Date the customer needs the appliance by, checked against the vendor's `BusinessCalendar`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub needed_by: Option<NaiveDate>,
+	/// Date to ask the vendor for delivery on, e.g. the install date, instead of today. Only vendors whose
+	/// lookup takes a date use it, see `requested_delivery_date`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub requested_date: Option<NaiveDate>,
+	/// Answer from the simulation catalog instead of the vendor, for training.
+	#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+	pub simulate: bool,
+	/// Our own stock of the model, merged from the `inventory::InternalInventorySource` after the lookup.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub internal_stock: Option<InternalStock>,
+	/// Notes on the model shown to this showroom, merged from the `annotations::AnnotationStore`.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub annotations: Vec<Annotation>,
+	/// Locale to ask the vendor catalog for product descriptions in, e.g. `es-US`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub description_locale: Option<String>,
+	/// Keep the dealer net price of the vendor's order line, for a user allowed to see it.
+	#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+	pub include_pricing: bool,
+	/// Where `availability` and `product` came from, recorded when they are filled in.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub provenance: Option<FieldProvenance>,
+}
+
+///
+/// Lowercase two-letter language of a locale, e.g. `es-US` becomes `es`.
+///
+pub(crate) fn language_code(locale: &str) -> Option<String> {
+	let language = locale.trim().split(['-', '_']).next()?;
+	(language.len() == 2 && language.chars().all(|c| c.is_ascii_alphabetic())).then(|| language.to_lowercase())
 }
 
 impl AvailabilityRequest {
@@ -63,8 +493,152 @@ impl AvailabilityRequest {
 	/// let req: AvailabilityRequest = AvailabilityRequest::new("bsh", "houston", "HBLP651RUC");
 	/// ```
 	#[must_use]
-	pub const fn new(manufacturer: String, showroom: String, model_number: String) -> Self {
-		Self { manufacturer: Some(manufacturer), showroom: Some(showroom), model_number: Some(model_number), warehouse: None, utc_time: None, availability: None, user: None }
+	#[allow(deprecated, clippy::needless_pass_by_value)]
+	pub fn new(manufacturer: String, showroom: String, model_number: String) -> Self {
+		Self {
+			manufacturer: manufacturer.parse().ok(),
+			showroom: Some(showroom),
+			model_number: Some(model_number),
+			warehouse: None,
+			utc_time: None,
+			requested_at: None,
+			availability: None,
+			availability_detail: None,
+			user: None,
+			product: None,
+			cached_at: None,
+			restricted: None,
+			warehouse_decision: None,
+			quantity: None,
+			rejection: None,
+			account_issue: None,
+			catalog_version: None,
+			request_id: None,
+			needed_by: None,
+			requested_date: None,
+			simulate: false,
+			internal_stock: None,
+			annotations: Vec::new(),
+			description_locale: None,
+			include_pricing: false,
+			provenance: None,
+		}
+	}
+
+	///
+	/// # `AvailabilityRequest::builder`
+	/// A builder changed in place, for callers that adjust a request between lookups, see
+	/// `AvailabilityRequestBuilder`.
+	///
+	#[must_use]
+	pub fn builder() -> AvailabilityRequestBuilder {
+		AvailabilityRequestBuilder::default()
+	}
+
+	///
+	/// # `AvailabilityRequest::with_quantity`
+	/// Ask for the availability of `quantity` units instead of one. Vendors without quantity support
+	/// ignore it.
+	///
+	#[must_use]
+	pub const fn with_quantity(mut self, quantity: u32) -> Self {
+		self.quantity = Some(quantity);
+		self
+	}
+
+	///
+	/// # `AvailabilityRequest::with_warehouse`
+	/// Look the model up at `warehouse`, e.g. a distribution center not mapped to the showroom, instead of
+	/// the warehouse `get_warehouse` would resolve.
+	///
+	#[must_use]
+	pub fn with_warehouse(mut self, warehouse: &str) -> Self {
+		self.warehouse = Some(warehouse.trim().to_string()).filter(|warehouse| !warehouse.is_empty());
+		self.warehouse_decision = None;
+		self
+	}
+
+	///
+	/// # `AvailabilityRequest::with_needed_by`
+	/// Check the answer against the date the customer needs the appliance by, see
+	/// `AvailabilityResponse::meets_needed_by`.
+	///
+	#[must_use]
+	pub const fn with_needed_by(mut self, needed_by: NaiveDate) -> Self {
+		self.needed_by = Some(needed_by);
+		self
+	}
+
+	///
+	/// # `AvailabilityRequest::with_requested_date`
+	/// Ask the vendor whether the appliance can be delivered on `requested_date`, e.g. the install date,
+	/// rather than as soon as possible. BSH answers relative to it.
+	///
+	#[must_use]
+	pub const fn with_requested_date(mut self, requested_date: NaiveDate) -> Self {
+		self.requested_date = Some(requested_date);
+		self
+	}
+
+	///
+	/// # `AvailabilityRequest::requested_delivery_date`
+	/// The delivery date to send the vendor: `requested_date`, or the showroom's today when it is missing or
+	/// already past.
+	///
+	#[must_use]
+	pub fn requested_delivery_date(&self) -> NaiveDate {
+		let today = self.business_today();
+		self.requested_date.filter(|requested_date| *requested_date > today).unwrap_or(today)
+	}
+
+	///
+	/// # `AvailabilityRequest::with_simulation`
+	/// Answer from the simulation catalog instead of the vendor, see `simulation::simulate`.
+	///
+	#[must_use]
+	pub const fn with_simulation(mut self) -> Self {
+		self.simulate = true;
+		self
+	}
+
+	///
+	/// # `AvailabilityRequest::with_description_locale`
+	/// Ask for the product description in `locale`, e.g. `es-US`. Vendors whose catalog has no description
+	/// in that language answer with the English one, see `ProductInfo::language`.
+	///
+	#[must_use]
+	pub fn with_description_locale(mut self, locale: String) -> Self {
+		self.description_locale = Some(locale);
+		self
+	}
+
+	///
+	/// # `AvailabilityRequest::with_pricing`
+	/// Ask for the dealer net price and currency of the vendor's order line. They are only kept when the
+	/// request's user may see them, see `AvailabilityRequestUser::may_see_pricing`.
+	///
+	#[must_use]
+	pub const fn with_pricing(mut self, include_pricing: bool) -> Self {
+		self.include_pricing = include_pricing;
+		self
+	}
+
+	///
+	/// The two-letter language to ask for product descriptions in: the request's `description_locale`, else
+	/// the locale configured for the showroom, else the user's preferred language. `None` leaves the vendor
+	/// default.
+	///
+	#[must_use]
+	pub(crate) fn description_language(&self) -> Option<String> {
+		self.description_locale.clone().or_else(|| self.showroom.as_deref().and_then(config::configured_description_locale)).or_else(|| self.user.as_ref().and_then(|user| user.preferred_language.clone())).as_deref().and_then(language_code)
+	}
+
+	///
+	/// The number of units to ask the vendor for.
+	///
+	#[must_use]
+	pub fn requested_quantity(&self) -> u32 {
+		self.quantity.unwrap_or(1).max(1)
 	}
 
 	///
@@ -90,6 +664,8 @@ impl AvailabilityRequest {
 	/// ```
 	///
 	#[must_use]
+	#[cfg(feature = "auth")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "auth")))]
 	pub fn add_user(mut self, user: User) -> Self {
 		self.user = Some(AvailabilityRequestUser {
 			id: user.token.id,
@@ -99,13 +675,17 @@ impl AvailabilityRequest {
 			job_title: user.token.job_title,
 			user_principal_name: user.token.user_principal_name,
 			office_location: user.token.office_location,
+			preferred_language: None,
 		});
 		self
 	}
 
 	///
 	/// # `AvailabilityRequest::parse_manufacturer`
-	/// Parse the manufacturer from the request.
+	/// Keep the manufacturer of the request if it is built in or a backend was added for it with
+	/// `backend::register_backend`, and clear it otherwise. Aliases are resolved when the name is parsed into
+	/// a `Manufacturer`: Cove is ordered with Wolf and parsed as `wolf`; Thermador and Gaggenau are ordered
+	/// through BSH under their own brand codes.
 	///
 	/// ## Example
 	/// ```
@@ -118,249 +698,556 @@ impl AvailabilityRequest {
 	///
 	#[must_use]
 	pub fn parse_manufacturer(mut self) -> Self {
-		if let Some(manufacturer) = self.manufacturer {
-			match manufacturer.to_lowercase().as_str() {
-				"bsh" => {
-					self.manufacturer = Some("bsh".to_string());
-					self
-				}
-				"subzero" => {
-					self.manufacturer = Some("subzero".to_string());
-					self
-				}
-				"miele" => {
-					self.manufacturer = Some("miele".to_string());
-					self
-				}
-				_ => {
-					self.manufacturer = None;
-					self
-				}
-			}
-		} else {
-			self.manufacturer = None;
-			self
-		}
+		self.manufacturer = self.manufacturer.filter(|manufacturer| manufacturer.is_builtin() || backend::backend(manufacturer.as_str()).is_some());
+		self
 	}
 
 	///
 	/// # `AvailabilityRequest::get_warehouse`
 	/// Get the warehouse from the request and pasrse it into a format that can be read by the manufacture interface.
 	///
-	#[allow(clippy::too_many_lines)]
-	#[must_use]
-	pub fn get_warehouse(mut self) -> Self {
-		if let Some(showroom) = self.showroom.clone() {
-			match showroom.to_lowercase().as_str() {
-				"houston" => {
-					if let Some(manufacturer) = self.manufacturer.clone() {
-						match manufacturer.to_lowercase().as_str() {
-							"bsh" => {
-								self.warehouse = Some("US00002148".to_string());
-								self
-							}
-							"subzero" => {
-								self.warehouse = Some("99432040".to_string());
-								self
-							}
-							"miele" => {
-								self.warehouse = Some("Forest Park, IL".to_string());
-								self
-							}
-							_ => {
-								self.warehouse = None;
-								self
-							}
-						}
-					} else {
-						self.warehouse = None;
-						self
-					}
-				}
-				"florida" => {
-					if let Some(manufacturer) = self.manufacturer.clone() {
-						match manufacturer.to_lowercase().as_str() {
-							"bsh" => {
-								self.warehouse = Some("US00000103".to_string());
-								self
-							}
-							"subzero" => {
-								self.warehouse = Some("99211620".to_string());
-								self
-							}
-							"miele" => {
-								self.warehouse = Some("Pompano Beach, FL".to_string());
-								self
-							}
-							_ => {
-								self.warehouse = None;
-								self
-							}
-						}
-					} else {
-						self.warehouse = None;
-						self
-					}
-				}
-				"los angeles" => {
-					if let Some(manufacturer) = self.manufacturer.clone() {
-						match manufacturer.to_lowercase().as_str() {
-							"bsh" => {
-								self.warehouse = Some("US00003803".to_string());
-								self
-							}
-							"subzero" => {
-								self.warehouse = Some("99614560".to_string());
-								self
-							}
-							"miele" => {
-								self.warehouse = Some("Stockton, CA".to_string());
-								self
-							}
-							_ => {
-								self.warehouse = None;
-								self
-							}
-						}
-					} else {
-						self.warehouse = None;
-						self
-					}
-				}
-				"chicago" => {
-					if let Some(manufacturer) = self.manufacturer.clone() {
-						match manufacturer.to_lowercase().as_str() {
-							"bsh" => {
-								self.warehouse = Some("US00001842".to_string());
-								self
-							}
-							"subzero" => {
-								self.warehouse = Some("99311630".to_string());
-								self
-							}
-							"miele" => {
-								self.warehouse = Some("Forest Park, IL".to_string());
-								self
-							}
-							_ => {
-								self.warehouse = None;
-								self
-							}
-						}
-					} else {
-						self.warehouse = None;
-						self
-					}
-				}
-				"new york" => {
-					if let Some(manufacturer) = self.manufacturer.clone() {
-						match manufacturer.to_lowercase().as_str() {
-							"bsh" => {
-								self.warehouse = Some("US00002933".to_string());
-								self
-							}
-							"subzero" => {
-								self.warehouse = Some("99103710".to_string());
-								self
-							}
-							"miele" => {
-								self.warehouse = Some("South Brunswick, NJ".to_string());
-								self
-							}
-							_ => {
-								self.warehouse = None;
-								self
-							}
-						}
-					} else {
-						self.warehouse = None;
-						self
-					}
-				}
-				"dallas" => {
-					if let Some(manufacturer) = self.manufacturer.clone() {
-						match manufacturer.to_lowercase().as_str() {
-							"bsh" => {
-								self.warehouse = Some("US00003189".to_string());
-								self
-							}
-							"subzero" => {
-								self.warehouse = Some("99411540".to_string());
-								self
-							}
-							"miele" => {
-								self.warehouse = Some("Forest Park, IL".to_string());
-								self
-							}
-							_ => {
-								self.warehouse = None;
-								self
-							}
-						}
-					} else {
-						self.warehouse = None;
-						self
-					}
-				}
-				_ => {
-					self.warehouse = None;
-					self
-				}
+	/// Mappings loaded with `config::reload_config` are used before the built-in showroom table. The
+	/// decision (input showroom and manufacturer, resolved code and why) is kept in `warehouse_decision`
+	/// and emitted to telemetry. A `VendorSelector` installed with `set_vendor_selector` may then reroute
+	/// the request to another manufacturer or warehouse.
+	///
+	/// A warehouse the caller set on the request, with `with_warehouse` or in the payload, is kept as it is
+	/// and the selector is not asked. A warehouse an earlier `get_warehouse` resolved is resolved again.
+	///
+	#[must_use]
+	pub fn get_warehouse(self) -> Self {
+		if self.warehouse.as_deref().is_some_and(|warehouse| !warehouse.trim().is_empty()) && self.warehouse_decision.as_ref().is_none_or(|decision| decision.source == WarehouseSource::Caller) {
+			return self.keep_warehouse();
+		}
+		let mut req = self.map_warehouse();
+		let mut decision = WarehouseDecision::from_table(req.showroom.as_deref(), req.manufacturer.as_ref().map(Manufacturer::as_str), req.warehouse.as_deref());
+		if let Some(route) = vendor_selector().and_then(|selector| selector.select(&req)) {
+			if let Some(manufacturer) = route.manufacturer {
+				req.manufacturer = manufacturer.parse().ok();
+				req.warehouse = None;
+				req = req.parse_manufacturer().map_warehouse();
 			}
-		} else {
-			self.warehouse = None;
-			self
+			if let Some(warehouse) = route.warehouse {
+				req.warehouse = Some(warehouse);
+			}
+			decision.inference = Some(format!("table resolved {}", decision.warehouse.clone().unwrap_or_else(|| "nothing".to_string())));
+			decision.manufacturer = req.manufacturer.as_ref().map(ToString::to_string);
+			decision.warehouse.clone_from(&req.warehouse);
+			decision.source = WarehouseSource::Selector;
+			decision.reason = route.reason;
 		}
+		decision.emit();
+		req.warehouse_decision = Some(decision);
+		req
+	}
+
+	///
+	/// Record the caller's warehouse as the decision, with what the showroom table would have chosen.
+	///
+	fn keep_warehouse(mut self) -> Self {
+		let resolved = self.clone().map_warehouse().warehouse;
+		let mut decision = WarehouseDecision::from_table(self.showroom.as_deref(), self.manufacturer.as_ref().map(Manufacturer::as_str), self.warehouse.as_deref());
+		decision.source = WarehouseSource::Caller;
+		decision.inference = Some(format!("table resolved {}", resolved.unwrap_or_else(|| "nothing".to_string())));
+		decision.reason = "warehouse set on the request".to_string();
+		decision.emit();
+		self.warehouse_decision = Some(decision);
+		self
+	}
+
+	fn map_warehouse(mut self) -> Self {
+		self.warehouse = match (self.showroom.as_deref(), self.manufacturer.as_ref()) {
+			(Some(showroom), Some(manufacturer)) => Warehouse::for_showroom(&Showroom::new(showroom), manufacturer).map(|warehouse| warehouse.to_string()),
+			_ => None,
+		};
+		self
 	}
 
 	///
 	/// # `AvailabilityRequest::get_time`
-	/// Get the current time in the format %m/%d/%Y %I:%M:%S %p
+	/// Stamp the request with the current time as `requested_at`.
+	///
+	#[must_use]
+	pub fn get_time(self) -> Self {
+		self.with_requested_at(Utc::now())
+	}
+
+	///
+	/// # `AvailabilityRequest::with_requested_at`
+	/// Stamp the request with `requested_at`, and the deprecated `utc_time` with the same instant.
 	///
 	#[must_use]
-	pub fn get_time(mut self) -> Self {
-		let utc_time = Utc::now().format("%m/%d/%Y %I:%M:%S %p").to_string();
-		self.utc_time = Some(utc_time);
+	#[allow(deprecated)]
+	pub fn with_requested_at(mut self, requested_at: DateTime<Utc>) -> Self {
+		self.utc_time = Some(requested_at.format(compat::V1_TIME_FORMAT).to_string());
+		self.requested_at = Some(requested_at);
 		self
 	}
 
+	///
+	/// # `AvailabilityRequest::looked_up_at`
+	/// When the lookup was made: `requested_at`, or the `utc_time` of a v1 payload that has no
+	/// `requested_at`.
+	///
+	#[must_use]
+	#[allow(deprecated)]
+	pub fn looked_up_at(&self) -> Option<DateTime<Utc>> {
+		self.requested_at.or_else(|| self.utc_time.as_deref().and_then(|utc_time| NaiveDateTime::parse_from_str(utc_time, compat::V1_TIME_FORMAT).ok()).map(|utc_time| utc_time.and_utc()))
+	}
+
+	///
+	/// # `AvailabilityRequest::local_time`
+	/// When the lookup was made on the showroom's wall clock, for display.
+	///
+	#[must_use]
+	pub fn local_time(&self) -> Option<String> {
+		self.looked_up_at().map(|looked_up_at| showroom_local_time(self.showroom.as_deref(), looked_up_at))
+	}
+
+	///
+	/// # `AvailabilityRequest::business_today`
+	/// The showroom's date when the lookup was made, or now for a request that was never stamped. Backends
+	/// read promised dates against it, so every date of one lookup agrees even around midnight.
+	///
+	#[must_use]
+	pub fn business_today(&self) -> NaiveDate {
+		business_today(self.showroom.as_deref(), self.looked_up_at().unwrap_or_else(Utc::now))
+	}
+
+	///
+	/// # `AvailabilityRequest::response`
+	/// The result of the lookup, from which both API versions are produced.
+	///
+	#[must_use]
+	pub fn response(&self) -> AvailabilityResponse {
+		AvailabilityResponse::from_request(self)
+	}
+
+	///
+	/// # `AvailabilityRequest::v1`
+	/// Legacy string-shaped availability response.
+	///
+	#[must_use]
+	pub fn v1(&self) -> String {
+		self.response().v1()
+	}
+
+	///
+	/// # `AvailabilityRequest::v2`
+	/// Structured availability response.
+	///
+	#[must_use]
+	pub fn v2(&self) -> AvailabilityResponseV2 {
+		self.response().v2()
+	}
+
+	///
+	/// # `AvailabilityRequest::get_nationwide_availability`
+	/// Get the availability of the requested product at every warehouse of its manufacturer at once,
+	/// whatever warehouse the request's showroom maps to. Each warehouse is a lookup of its own, stopped at
+	/// the deadline of the caller if it runs under one; see also `discovery::check_all_warehouses`.
+	///
+	/// # Errors
+	/// Returns an error if the request has no known manufacturer or no model number, or the manufacturer
+	/// has no warehouses. A warehouse whose lookup fails is listed in `NationwideAvailability::errors`.
+	pub async fn get_nationwide_availability(self) -> Result<NationwideAvailability, String> {
+		discovery::nationwide_availability(self).await
+	}
+
 	///
 	/// # `AvailabilityRequest::get_availability`
-	/// Get the availability for the requested product.
+	/// Get the availability of the requested product without giving up the request, taking as long as the
+	/// vendor does. Use `get_availability_with` to limit it.
+	///
+	/// A request from `AvailabilityRequestBuilder::build`, or one `get_warehouse` was applied to, is looked
+	/// up as it is; any other is resolved into a copy first. The request itself is left as it was, so it
+	/// can be looked up again.
+	///
+	/// ## Example
+	/// ```no_run
+	/// use eggersmann_app_server_appliance_availability::{AvailabilityError, AvailabilityRequest};
+	///
+	/// # async fn lookup() -> Result<(), AvailabilityError> {
+	/// let req = AvailabilityRequest::builder().manufacturer("bsh").showroom("houston").model_number("HBLP651RUC").build();
+	/// let now = req.get_availability().await?;
+	/// let later = req.get_availability().await?;
+	/// # Ok(())
+	/// # }
+	/// ```
 	///
 	/// # Errors
-	/// todo
-	pub async fn get_availability(mut self) -> Result<Self, String> {
-		if let Some(manufacturer) = self.manufacturer.clone() {
-			match manufacturer.to_lowercase().as_str() {
-				"bsh" => {
-					let azure_credentials = azure_identity::create_credential().map_err(|e| format!("Faild to get Azure Identity: {e}"))?;
-					let client = KeyvaultClient::new("https://eggappserverkeyvault.vault.azure.net", azure_credentials).map_err(|e| format!("Failed to get Keyvault Client: {e}"))?;
-					let bsh_username = client.secret_client().get("bsh-username").await.map_err(|_| "Faild to get BSH Username.".to_string())?.value;
-					let bsh_password = client.secret_client().get("bsh-password").await.map_err(|_| "Faild to get BSH Password.".to_string())?.value;
-					self.availability = Some(bsh::bsh_availability(self.clone(), bsh_username, bsh_password).await?);
-					Ok(self)
-				}
-				"subzero" => {
-					let azure_credentials = azure_identity::create_credential().map_err(|e| format!("Faild to get Azure Identity: {e}"))?;
-					let client = KeyvaultClient::new("https://eggappserverkeyvault.vault.azure.net", azure_credentials).map_err(|e| format!("Failed to get Keyvault Client: {e}"))?;
-					let subzero_username = client.secret_client().get("subzero-username").await.map_err(|_| "Faild to get Subzero Username.".to_string())?.value;
-					let subzero_password = client.secret_client().get("subzero-password").await.map_err(|_| "Faild to get Subzero Password.".to_string())?.value;
-					self.availability = Some(subzero::subzero_availability(self.clone(), subzero_username, subzero_password).await?);
-					Ok(self)
-				}
-				"miele" => {
-					self.availability = Some(miele::miele_availability(self.clone()).await?);
-					Ok(self)
-				}
-				_ => {
-					self.availability = None;
-					Ok(self)
+	/// Returns the error of the vendor lookup, `AvailabilityError::Restricted` for a model that must not be
+	/// quoted, and `AvailabilityError::AccountIssue` for a vendor account that is blocked.
+	pub async fn get_availability(&self) -> Result<Availability, AvailabilityError> {
+		self.get_availability_with(RequestOptions::default()).await
+	}
+
+	///
+	/// # `AvailabilityRequest::get_availability_with`
+	/// `get_availability` within the timeout and deadline of `options`.
+	///
+	/// # Errors
+	/// Returns the errors of `get_availability`, or `AvailabilityError::DeadlineExceeded` when `options` ran
+	/// out first.
+	pub async fn get_availability_with(&self, options: RequestOptions) -> Result<Availability, AvailabilityError> {
+		let answer = self.resolved().answer(options).await?;
+		if let Some(restricted) = answer.restricted {
+			return Err(AvailabilityError::Restricted(restricted));
+		}
+		if let Some(account_issue) = answer.account_issue {
+			return Err(AvailabilityError::AccountIssue(account_issue));
+		}
+		Ok(answer.availability_detail.unwrap_or_else(Availability::not_found))
+	}
+
+	///
+	/// The request as it is looked up: borrowed when `get_warehouse` already resolved it, resolved into a
+	/// copy otherwise.
+	///
+	fn resolved(&self) -> Cow<'_, Self> {
+		if self.warehouse_decision.is_some() {
+			Cow::Borrowed(self)
+		} else {
+			Cow::Owned(self.clone().parse_manufacturer().get_warehouse())
+		}
+	}
+
+	///
+	/// # `AvailabilityRequest::into_answered`
+	/// The request with the answer of the lookup filled in, taking as long as the vendor does. Use
+	/// `into_answered_with` to limit it. `response`, `v1` and `v2` then give the answer in each shape.
+	///
+	/// # Errors
+	/// Returns the error of the vendor lookup as text.
+	pub async fn into_answered(self) -> Result<Self, String> {
+		self.into_answered_with(RequestOptions::default()).await
+	}
+
+	///
+	/// # `AvailabilityRequest::into_answered_with`
+	/// `into_answered` within the timeout and deadline of `options`, with our stock and the notes for the
+	/// model merged in.
+	///
+	/// # Errors
+	/// Returns the error of the vendor lookup, or `AvailabilityError::DeadlineExceeded` as text when
+	/// `options` ran out first.
+	pub async fn into_answered_with(self, options: RequestOptions) -> Result<Self, String> {
+		let answer = self.answer(options).await?;
+		let merge = answer.restricted.is_none() && !self.simulate;
+		let request_id = answer.request_id.clone();
+		let mut req = self.answered(answer);
+		req.request_id = Some(request_id.clone());
+		if !merge {
+			return Ok(req);
+		}
+		Ok(support::scope(request_id, Box::pin(async move { annotations::merge(inventory::merge(req, &options).await).await })).await)
+	}
+
+	///
+	/// Answer the request within `options`: compliance, simulation, the result cache and then the vendor,
+	/// with the lookup traced, timed and recorded in the history.
+	///
+	async fn answer(&self, options: RequestOptions) -> Result<Answer, AvailabilityError> {
+		let started = Instant::now();
+		let labels = [("manufacturer", self.manufacturer.as_ref().map(ToString::to_string).unwrap_or_default())];
+		let request_id = self.request_id.clone().unwrap_or_else(support::next_request_id);
+		support::begin(&request_id, self);
+		if let Some(restricted) = compliance::check(self) {
+			telemetry::counter("availability.lookup.restricted", 1, &[labels[0].clone(), ("policy", restricted.policy.clone())]);
+			support::trace_request(&request_id, "restricted", &restricted.policy);
+			let answer = Answer { request_id: request_id.clone(), restricted: Some(restricted), ..Answer::default() };
+			support::finish(&request_id, &Ok(answer.v1()));
+			return Ok(answer);
+		}
+		if self.simulate {
+			let answer = support::scope(request_id.clone(), async { Answer::simulated(request_id.clone(), self) }).await;
+			support::finish(&request_id, &Ok(answer.v1()));
+			return Ok(answer);
+		}
+		let result: Result<Answer, AvailabilityError> = support::scope(
+			request_id.clone(),
+			Box::pin(async {
+				let answer = self.cached_answer(&request_id, options).await?;
+				Ok(self.redact_pricing(answer))
+			}),
+		)
+		.await;
+		support::finish(&request_id, &result.as_ref().map(Answer::v1).map_err(ToString::to_string));
+		telemetry::latency("availability.lookup.duration", started.elapsed(), &labels);
+		match &result {
+			Ok(answer) => {
+				telemetry::counter("availability.lookup.success", 1, &labels);
+				if answer.cached_at.is_none() && history::history_store().is_some() {
+					history::record_response(&self.clone().answered(answer.clone()).response());
 				}
 			}
-		} else {
-			self.availability = None;
-			Ok(self)
+			Err(e) => {
+				telemetry::counter("availability.lookup.failure", 1, &labels);
+				telemetry::event("availability.lookup.failed", &[("manufacturer", labels[0].1.clone()), ("error", e.to_string())]);
+			}
+		}
+		result
+	}
+
+	///
+	/// The request with `answer` filled in, keeping its request id.
+	///
+	fn answered(mut self, answer: Answer) -> Self {
+		self.availability = answer.availability;
+		self.availability_detail = answer.availability_detail;
+		self.product = answer.product;
+		self.rejection = answer.rejection;
+		self.catalog_version = answer.catalog_version;
+		self.cached_at = answer.cached_at;
+		self.restricted = answer.restricted;
+		self.account_issue = answer.account_issue;
+		self.provenance = answer.provenance;
+		self
+	}
+
+	///
+	/// # `AvailabilityRequest::pricing_allowed`
+	/// Whether the answer keeps the net price and currency of the vendor's order line: the request asked
+	/// for pricing and its user may see it.
+	///
+	#[must_use]
+	pub fn pricing_allowed(&self) -> bool {
+		self.include_pricing && self.user.as_ref().is_some_and(AvailabilityRequestUser::may_see_pricing)
+	}
+
+	///
+	/// Leave the pricing out of `answer` unless `pricing_allowed`. Cached answers keep it, so it is left
+	/// out again for every request they answer.
+	///
+	fn redact_pricing(&self, mut answer: Answer) -> Answer {
+		if self.include_pricing && !self.pricing_allowed() {
+			telemetry::event("pricing.denied", &[("user_id", self.user.as_ref().map(|user| user.id.clone()).unwrap_or_default())]);
 		}
+		if !self.pricing_allowed() {
+			answer.availability_detail = answer.availability_detail.map(Availability::without_pricing);
+		}
+		answer
+	}
+
+	///
+	/// Key of this request in the result cache, `None` for simulated requests so they never share an answer
+	/// with real ones.
+	///
+	fn cache_key(&self) -> Option<cache::ResultKey> {
+		if self.simulate {
+			return None;
+		}
+		match (&self.manufacturer, &self.model_number) {
+			(Some(manufacturer), Some(model_number)) => Some((manufacturer.to_string(), self.warehouse.clone().unwrap_or_default(), model_number.trim().to_uppercase(), self.requested_quantity(), self.description_language().unwrap_or_default(), self.requested_date)),
+			_ => None,
+		}
+	}
+
+	///
+	/// Answer from the result cache when possible, otherwise ask the vendor and cache the answer with the
+	/// positive or negative TTL.
+	///
+	async fn cached_answer(&self, request_id: &str, options: RequestOptions) -> Result<Answer, AvailabilityError> {
+		if let Some(cached) = self.answer_from_cache(request_id) {
+			return Ok(cached);
+		}
+		if self.cache_key().is_some() {
+			telemetry::counter("availability.cache.miss", 1, &[("manufacturer", self.manufacturer.as_ref().map(ToString::to_string).unwrap_or_default())]);
+		}
+		let answer = self.vendor_answer(request_id, options).await?;
+		self.cache_answer(&answer);
+		Ok(answer)
+	}
+
+	///
+	/// The answer in the result cache, `None` on a miss.
+	///
+	fn answer_from_cache(&self, request_id: &str) -> Option<Answer> {
+		let cached = cache::result_cache().get(&self.cache_key()?)?;
+		telemetry::counter("availability.cache.hit", 1, &[("manufacturer", self.manufacturer.as_ref().map(ToString::to_string).unwrap_or_default())]);
+		support::trace("cache.hit", &format!("cached at {}", cached.cached_at.to_rfc3339()));
+		let age_secs = u64::try_from((self.looked_up_at().unwrap_or_else(Utc::now) - cached.cached_at).num_seconds()).unwrap_or(0);
+		let provenance = FieldProvenance::recorded(&Provenance::Cache { age_secs }, true, cached.product.is_some());
+		Some(Answer {
+			request_id: request_id.to_string(),
+			availability: Some(cached.availability),
+			availability_detail: cached.availability_detail,
+			product: cached.product,
+			rejection: cached.rejection,
+			catalog_version: cached.catalog_version,
+			cached_at: Some(cached.cached_at.to_rfc3339()),
+			provenance,
+			..Answer::default()
+		})
+	}
+
+	///
+	/// The request answered from the result cache, `None` on a miss.
+	///
+	fn answered_from_cache(&self) -> Option<Self> {
+		let answer = self.answer_from_cache(self.request_id.as_deref().unwrap_or_default())?;
+		Some(self.clone().answered(answer))
+	}
+
+	///
+	/// Cache a vendor answer with the positive or negative TTL.
+	///
+	fn cache_answer(&self, answer: &Answer) {
+		if let (Some(key), Some(availability)) = (self.cache_key(), &answer.availability) {
+			let config = cache::result_cache_config();
+			let not_found = answer.availability_detail.as_ref().map_or_else(|| Availability::from_text(availability, NaiveDate::MIN).is_not_found(), Availability::is_not_found);
+			let ttl = if not_found { config.negative_ttl } else { config.positive_ttl };
+			if !ttl.is_zero() {
+				cache::result_cache().insert_with_ttl(
+					key,
+					cache::CachedResult {
+						availability: availability.clone(),
+						availability_detail: answer.availability_detail.clone(),
+						product: answer.product.clone(),
+						rejection: answer.rejection.clone(),
+						catalog_version: answer.catalog_version.clone(),
+						cached_at: Utc::now(),
+					},
+					ttl,
+				);
+			}
+		}
+	}
+
+	///
+	/// Cache the answer the request was filled with.
+	///
+	fn store_in_result_cache(&self) {
+		self.cache_answer(&Answer::of(self));
+	}
+
+	///
+	/// Ask the backend registered for the manufacturer, see `backend::register_backend`. A simulated
+	/// request is answered from the simulation catalog here, so no entry point reaches a vendor with it.
+	///
+	async fn vendor_answer(&self, request_id: &str, options: RequestOptions) -> Result<Answer, AvailabilityError> {
+		if self.simulate {
+			return Ok(Answer::simulated(request_id.to_string(), self));
+		}
+		let unanswered = || Answer { request_id: request_id.to_string(), ..Answer::default() };
+		let Some(manufacturer) = &self.manufacturer else {
+			return Ok(unanswered());
+		};
+		let Some(backend) = backend::backend(manufacturer.as_str()) else {
+			return match manufacturer.as_str() {
+				#[cfg(not(feature = "bsh"))]
+				"bsh" => Err(AvailabilityError::Other("BSH lookups need the `bsh` feature.".to_string())),
+				#[cfg(not(feature = "bsh"))]
+				"thermador" | "gaggenau" => Err(AvailabilityError::Other("Thermador and Gaggenau lookups need the `bsh` feature.".to_string())),
+				#[cfg(not(feature = "subzero"))]
+				"subzero" => Err(AvailabilityError::Other("SubZero lookups need the `subzero` feature.".to_string())),
+				#[cfg(not(feature = "subzero"))]
+				"wolf" => Err(AvailabilityError::Other("Wolf lookups need the `subzero` feature.".to_string())),
+				#[cfg(not(feature = "miele"))]
+				"miele" => Err(AvailabilityError::Other("Miele lookups need the `miele` feature.".to_string())),
+				#[cfg(not(feature = "liebherr"))]
+				"liebherr" => Err(AvailabilityError::Other("Liebherr lookups need the `liebherr` feature.".to_string())),
+				#[cfg(not(feature = "fisher-paykel"))]
+				"fisher_paykel" => Err(AvailabilityError::Other("Fisher & Paykel lookups need the `fisher-paykel` feature.".to_string())),
+				#[cfg(not(feature = "jennair"))]
+				"jennair" => Err(AvailabilityError::Other("JennAir lookups need the `jennair` feature.".to_string())),
+				#[cfg(not(feature = "monogram"))]
+				"monogram" => Err(AvailabilityError::Other("Monogram lookups need the `monogram` feature.".to_string())),
+				#[cfg(not(feature = "true-residential"))]
+				"true_residential" => Err(AvailabilityError::Other("True Residential lookups need the `true-residential` feature.".to_string())),
+				#[cfg(not(feature = "dacor"))]
+				"dacor" => Err(AvailabilityError::Other("Dacor lookups need the `dacor` feature.".to_string())),
+				#[cfg(not(feature = "bertazzoni"))]
+				"bertazzoni" => Err(AvailabilityError::Other("Bertazzoni lookups need the `bertazzoni` feature.".to_string())),
+				_ => Ok(unanswered()),
+			};
+		};
+		let labels = [("manufacturer", manufacturer.to_string())];
+		let started = Instant::now();
+		let answer = backend.availability_with(self, &options).await;
+		telemetry::latency("vendor.request.duration", started.elapsed(), &labels);
+		sla::record(manufacturer.as_str(), started.elapsed(), answer.is_ok());
+		telemetry::counter(if answer.is_ok() { "vendor.request.success" } else { "vendor.request.failure" }, 1, &labels);
+		let answer = answer?;
+		if let Some(issue) = answer.account_issue {
+			telemetry::counter("availability.lookup.account_issue", 1, &[("manufacturer", issue.manufacturer.clone())]);
+			support::trace("account.issue", &issue.reason);
+			return Ok(Answer { account_issue: Some(issue), ..unanswered() });
+		}
+		let source = answer.catalog_version.as_ref().map_or(Provenance::Live, |catalog_version| Provenance::Snapshot { version: catalog_version.version });
+		let provenance = FieldProvenance::recorded(&source, answer.availability.is_some(), answer.product.is_some());
+		Ok(Answer {
+			request_id: request_id.to_string(),
+			availability: answer.availability.as_ref().map(|availability| availability.raw.clone()),
+			availability_detail: answer.availability,
+			product: answer.product,
+			rejection: answer.rejection,
+			catalog_version: answer.catalog_version,
+			provenance,
+			..Answer::default()
+		})
+	}
+
+	///
+	/// Ask the vendor and fill the request with its answer, see `service::ProviderService`.
+	///
+	async fn lookup_availability(self, options: RequestOptions) -> Result<Self, String> {
+		let request_id = self.request_id.clone().unwrap_or_default();
+		let answer = self.vendor_answer(&request_id, options).await?;
+		Ok(self.answered(answer))
+	}
+}
+
+///
+/// What a lookup answered, kept apart from the request so a borrowed request can be answered; see
+/// `AvailabilityRequest::answered` for filling a request with it.
+///
+#[derive(Debug, Clone, Default)]
+struct Answer {
+	request_id: String,
+	availability: Option<String>,
+	availability_detail: Option<Availability>,
+	product: Option<ProductInfo>,
+	rejection: Option<Rejection>,
+	catalog_version: Option<CatalogVersion>,
+	cached_at: Option<String>,
+	restricted: Option<RestrictedItem>,
+	account_issue: Option<AccountIssue>,
+	provenance: Option<FieldProvenance>,
+}
+
+impl Answer {
+	///
+	/// The answer `req` was filled with.
+	///
+	fn of(req: &AvailabilityRequest) -> Self {
+		Self {
+			request_id: req.request_id.clone().unwrap_or_default(),
+			availability: req.availability.clone(),
+			availability_detail: req.availability_detail.clone(),
+			product: req.product.clone(),
+			rejection: req.rejection.clone(),
+			catalog_version: req.catalog_version.clone(),
+			cached_at: req.cached_at.clone(),
+			restricted: req.restricted.clone(),
+			account_issue: req.account_issue.clone(),
+			provenance: req.provenance.clone(),
+		}
+	}
+
+	///
+	/// The answer of the simulation catalog, see `simulation::simulate`.
+	///
+	fn simulated(request_id: String, req: &AvailabilityRequest) -> Self {
+		let (availability, product, rejection) = simulation::simulated_answer(req);
+		let provenance = FieldProvenance::recorded(&Provenance::Simulated, true, product.is_some());
+		Self { request_id, availability: Some(availability.raw.clone()), availability_detail: Some(availability), product, rejection, provenance, ..Self::default() }
+	}
+
+	///
+	/// The legacy string answer, as `AvailabilityResponse::v1` gives it.
+	///
+	fn v1(&self) -> String {
+		self.restricted.as_ref().map(RestrictedItem::message).or_else(|| self.account_issue.as_ref().map(AccountIssue::message)).or_else(|| self.rejection.as_ref().map(Rejection::message)).or_else(|| self.availability.clone()).unwrap_or_else(|| response::V1_NOT_FOUND.to_string())
 	}
 }
@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use reqwest::StatusCode;
+
+use super::backend::{BackendAnswer, ManufacturerBackend};
+use super::catalog::{normalize_model, CachedCatalog, CatalogItem, CatalogSnapshot};
+use super::{Availability, AvailabilityError, AvailabilityRequest, ProductInfo};
+use crate::http_client::{self, HttpClient};
+use crate::schedule::{self, Workload};
+use crate::telemetry;
+
+///
+/// # `LiebherrConfig`
+/// Where the Liebherr dealer stock feed is downloaded from. The feed is a CSV file with a header row,
+/// read with the dealer portal login (`liebherr-username`, `liebherr-password`) as basic auth, and kept
+/// for `max_age` before it is downloaded again.
+///
+/// Columns are found by their header: the model (`Material` or `Model`), `Description`, the stock
+/// location (`Warehouse` or `Location`), the quantity on hand (`Available`) and the next delivery
+/// (`Next Date`, `Next Quantity`). The location is matched against the request's warehouse, the state
+/// of the showroom's Liebherr distribution center (e.g. `TX`) unless `config::reload_config` maps another.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct LiebherrConfig {
+	pub feed_url: String,
+	pub max_age: Duration,
+}
+
+impl LiebherrConfig {
+	#[must_use]
+	pub const fn new(feed_url: String) -> Self {
+		Self { feed_url, max_age: Duration::from_mins(15) }
+	}
+
+	#[must_use]
+	pub const fn with_max_age(mut self, max_age: Duration) -> Self {
+		self.max_age = max_age;
+		self
+	}
+}
+
+impl Default for LiebherrConfig {
+	fn default() -> Self {
+		Self::new("https://dealer.liebherr.com/us/stock/availability.csv".to_string())
+	}
+}
+
+fn config_slot() -> &'static RwLock<LiebherrConfig> {
+	static CONFIG: OnceLock<RwLock<LiebherrConfig>> = OnceLock::new();
+	CONFIG.get_or_init(|| RwLock::new(LiebherrConfig::default()))
+}
+
+///
+/// # `set_liebherr_config`
+/// Set where the Liebherr stock feed is downloaded from. The feed already downloaded is dropped.
+///
+pub fn set_liebherr_config(config: LiebherrConfig) {
+	*config_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = config;
+	*feed_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = None;
+}
+
+///
+/// # `liebherr_config`
+/// Where the Liebherr stock feed is downloaded from.
+///
+#[must_use]
+pub fn liebherr_config() -> LiebherrConfig {
+	config_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
+
+///
+/// # `LiebherrBackend`
+/// Liebherr lookups against the dealer stock feed, registered as `liebherr`. The feed is downloaded
+/// through `client`, the shared client unless one is given; `login` downloads it again.
+///
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct LiebherrBackend {
+	pub client: HttpClient,
+}
+
+impl LiebherrBackend {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	#[must_use]
+	pub fn with_client(mut self, client: HttpClient) -> Self {
+		self.client = client;
+		self
+	}
+}
+
+#[async_trait::async_trait]
+impl ManufacturerBackend for LiebherrBackend {
+	fn name(&self) -> &'static str {
+		"liebherr"
+	}
+
+	async fn availability(&self, req: &AvailabilityRequest) -> Result<BackendAnswer, AvailabilityError> {
+		let (availability, product) = http_client::scope(self.client.clone(), liebherr_lookup(req.clone())).await?;
+		Ok(BackendAnswer::new(availability).with_product(product))
+	}
+
+	async fn login(&self) -> Result<(), AvailabilityError> {
+		http_client::scope(self.client.clone(), refresh_feed()).await?;
+		Ok(())
+	}
+}
+
+///
+/// # Liebherr Lookup
+/// Gets the availability of a Liebherr appliance at the request's warehouse together with its description.
+///
+/// # Errors
+/// Returns `AvailabilityError::VendorUnavailable` if the feed cannot be downloaded or read,
+/// `AvailabilityError::CredentialsRejected` if the dealer login is turned away, and
+/// `AvailabilityError::Other` for a request without warehouse or model number.
+///
+pub async fn liebherr_lookup(req: AvailabilityRequest) -> Result<(Availability, Option<ProductInfo>), AvailabilityError> {
+	let today = req.business_today();
+	let warehouse = req.warehouse.clone().ok_or_else(|| AvailabilityError::Other("No warehouse found.".to_string()))?;
+	let model_number = req.model_number.clone().ok_or_else(|| AvailabilityError::Other("No model number found.".to_string()))?;
+	let feed = liebherr_feed().await?;
+	let model_number = normalize_model(&model_number);
+	let Some(row) = feed.rows.get(&model_number).and_then(|rows| rows.iter().find(|row| row.warehouse.eq_ignore_ascii_case(&warehouse))) else {
+		return Ok((Availability::not_found(), None));
+	};
+	Ok(row_availability(row, today))
+}
+
+///
+/// One row of the stock feed.
+///
+#[derive(Debug, Clone, Default)]
+struct LiebherrRow {
+	model_number: String,
+	description: String,
+	warehouse: String,
+	available_qty: Option<u32>,
+	next_available_qty: Option<u32>,
+	next_available_date: Option<NaiveDate>,
+}
+
+///
+/// The feed, rows by normalized model number.
+///
+#[derive(Debug, Default)]
+pub struct LiebherrFeed {
+	loaded_at: DateTime<Utc>,
+	rows: HashMap<String, Vec<LiebherrRow>>,
+}
+
+impl LiebherrFeed {
+	fn catalog_item(row: &LiebherrRow) -> CatalogItem {
+		let mut item = CatalogItem::new("liebherr".to_string(), row.warehouse.clone(), row.model_number.clone());
+		item.description = Some(row.description.clone()).filter(|description| !description.is_empty());
+		item.available_qty = row.available_qty;
+		item.next_available_qty = row.next_available_qty;
+		item.next_available_date = row.next_available_date;
+		item
+	}
+}
+
+impl CachedCatalog for LiebherrFeed {
+	fn snapshot(&self) -> CatalogSnapshot {
+		let mut warehouses: Vec<String> = self.rows.values().flatten().map(|row| row.warehouse.clone()).collect();
+		warehouses.sort();
+		warehouses.dedup();
+		CatalogSnapshot { manufacturer: "liebherr".to_string(), loaded_at: self.loaded_at, version: None, digest: None, warehouses, items: self.rows.values().map(Vec::len).sum() }
+	}
+
+	fn models(&self, warehouse: Option<&str>) -> Vec<String> {
+		let mut models: Vec<String> = self.rows.values().flatten().filter(|row| warehouse.is_none_or(|warehouse| row.warehouse.eq_ignore_ascii_case(warehouse))).map(|row| row.model_number.clone()).collect();
+		models.sort();
+		models.dedup();
+		models
+	}
+
+	fn item(&self, warehouse: &str, model_number: &str) -> Option<CatalogItem> {
+		self.rows.get(&normalize_model(model_number))?.iter().find(|row| row.warehouse.eq_ignore_ascii_case(warehouse)).map(Self::catalog_item)
+	}
+}
+
+///
+/// The availability of a feed row. Stock on hand makes it in stock; otherwise the next quantity is
+/// expected on the next date.
+///
+fn row_availability(row: &LiebherrRow, today: NaiveDate) -> (Availability, Option<ProductInfo>) {
+	let product = Some(row.description.clone()).filter(|description| !description.is_empty()).map(|description| ProductInfo::new(description).with_brand("Liebherr".to_string()));
+	let mut availability = Availability::stock(&row.model_number, row.available_qty, row.next_available_date, today);
+	if row.available_qty.is_none() {
+		availability.quantity = row.next_available_qty;
+	}
+	(availability, product)
+}
+
+///
+/// The feed and when it was downloaded.
+///
+type LoadedFeed = Option<(Instant, Arc<LiebherrFeed>)>;
+
+fn feed_slot() -> &'static RwLock<LoadedFeed> {
+	static FEED: OnceLock<RwLock<LoadedFeed>> = OnceLock::new();
+	FEED.get_or_init(|| RwLock::new(None))
+}
+
+///
+/// The feed downloaded last, however old, without downloading it.
+///
+pub fn cached_liebherr_feed() -> Option<Arc<LiebherrFeed>> {
+	feed_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).as_ref().map(|(_, feed)| feed.clone())
+}
+
+///
+/// The downloaded feed, downloaded again once older than `LiebherrConfig::max_age` when `schedule` allows
+/// bulk work for Liebherr.
+///
+async fn liebherr_feed() -> Result<Arc<LiebherrFeed>, AvailabilityError> {
+	let max_age = liebherr_config().max_age;
+	let current = feed_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone();
+	if let Some((_, feed)) = current.filter(|(loaded_at, _)| loaded_at.elapsed() < max_age || !schedule::may_run(Workload::Bulk, None, Some("liebherr"), Utc::now())) {
+		return Ok(feed);
+	}
+	refresh_feed().await
+}
+
+async fn refresh_feed() -> Result<Arc<LiebherrFeed>, AvailabilityError> {
+	let (username, password) = crate::secrets::vendor_credentials("liebherr").await?;
+	let config = liebherr_config();
+	let response = http_client::client().get(&config.feed_url).basic_auth(username.expose(), Some(password.expose())).send().await.map_err(|e| AvailabilityError::VendorUnavailable(format!("Failed to get Liebherr stock feed: {e:?}")))?;
+	match response.status() {
+		StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => return Err(AvailabilityError::CredentialsRejected { manufacturer: "Liebherr".to_string(), secret: "liebherr-password".to_string() }),
+		status if !status.is_success() => return Err(AvailabilityError::VendorUnavailable(format!("Failed to get Liebherr stock feed: {status}"))),
+		_ => {}
+	}
+	let body = http_client::text(response).await?;
+	let feed = Arc::new(parse_feed(&body)?);
+	telemetry::counter("liebherr.feed.loaded", 1, &[]);
+	*feed_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = Some((Instant::now(), feed.clone()));
+	Ok(feed)
+}
+
+///
+/// Parse the feed, separated by commas or semicolons, whichever the header uses.
+///
+fn parse_feed(body: &str) -> Result<LiebherrFeed, AvailabilityError> {
+	let mut lines = body.lines().filter(|line| !line.trim().is_empty());
+	let header = lines.next().ok_or_else(|| AvailabilityError::VendorUnavailable("Liebherr stock feed is empty.".to_string()))?;
+	let separator = if header.matches(';').count() > header.matches(',').count() { ';' } else { ',' };
+	let header: Vec<String> = split_line(header, separator).into_iter().map(|name| name.to_lowercase()).collect();
+	let column = |names: &[&str]| header.iter().position(|name| names.contains(&name.as_str()));
+	let model_column = column(&["material", "model", "model number", "material number"]).ok_or_else(|| AvailabilityError::VendorUnavailable("Liebherr stock feed has no model column.".to_string()))?;
+	let description_column = column(&["description", "material description"]);
+	let warehouse_column = column(&["warehouse", "location", "plant"]);
+	let available_column = column(&["available", "available qty", "available quantity", "on hand"]);
+	let next_qty_column = column(&["next quantity", "next available qty", "next available quantity"]);
+	let next_date_column = column(&["next date", "next available date"]);
+
+	let quantity = |value: &str| value.trim().split('.').next().and_then(|whole| whole.parse::<u32>().ok()).filter(|quantity| *quantity > 0);
+	let date = |value: &str| ["%Y-%m-%d", "%m/%d/%Y", "%d.%m.%Y"].iter().find_map(|format| NaiveDate::parse_from_str(value.trim(), format).ok());
+
+	let mut feed = LiebherrFeed { loaded_at: Utc::now(), ..LiebherrFeed::default() };
+	for line in lines {
+		let cells = split_line(line, separator);
+		let cell = |index: Option<usize>| index.and_then(|index| cells.get(index)).map_or("", String::as_str);
+		let model_number = cell(Some(model_column)).to_string();
+		if model_number.is_empty() {
+			continue;
+		}
+		let row = LiebherrRow {
+			description: cell(description_column).to_string(),
+			warehouse: cell(warehouse_column).to_string(),
+			available_qty: quantity(cell(available_column)),
+			next_available_qty: quantity(cell(next_qty_column)),
+			next_available_date: date(cell(next_date_column)),
+			model_number,
+		};
+		feed.rows.entry(normalize_model(&row.model_number)).or_default().push(row);
+	}
+	Ok(feed)
+}
+
+///
+/// Cells of a CSV line, with double-quoted cells unquoted.
+///
+fn split_line(line: &str, separator: char) -> Vec<String> {
+	let mut cells = Vec::new();
+	let mut cell = String::new();
+	let mut quoted = false;
+	let mut chars = line.chars().peekable();
+	while let Some(c) = chars.next() {
+		match c {
+			'"' if quoted && chars.peek() == Some(&'"') => {
+				cell.push('"');
+				chars.next();
+			}
+			'"' => quoted = !quoted,
+			c if c == separator && !quoted => cells.push(std::mem::take(&mut cell).trim().to_string()),
+			c => cell.push(c),
+		}
+	}
+	cells.push(cell.trim().to_string());
+	cells
+}
@@ -0,0 +1,113 @@
+use std::fmt;
+use std::str::FromStr;
+
+///
+/// # `Manufacturer`
+/// The vendor a request is for. Built-in vendors have a variant of their own; a vendor served by a
+/// backend registered under another name is `Other` with that name.
+///
+/// Parsed case-insensitively from the canonical name or an alias, and written back as the canonical
+/// name, which is also the name the vendor's backend is registered under.
+///
+/// ## Example
+/// ```
+/// use eggersmann_app_server_appliance_availability::Manufacturer;
+///
+/// assert_eq!("Bosch".parse::<Manufacturer>().unwrap(), Manufacturer::Bsh);
+/// assert_eq!("sub-zero".parse::<Manufacturer>().unwrap(), Manufacturer::Subzero);
+/// assert_eq!(Manufacturer::FisherPaykel.to_string(), "fisher_paykel");
+/// assert_eq!("Acme".parse::<Manufacturer>().unwrap(), Manufacturer::Other("acme".to_string()));
+/// assert!("  ".parse::<Manufacturer>().is_err());
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Manufacturer {
+	Bsh,
+	Thermador,
+	Gaggenau,
+	Subzero,
+	Wolf,
+	Miele,
+	Liebherr,
+	FisherPaykel,
+	JennAir,
+	Monogram,
+	TrueResidential,
+	Dacor,
+	Bertazzoni,
+	/// Lowercase name of a vendor without a built-in backend.
+	Other(String),
+}
+
+///
+/// Names each built-in manufacturer is parsed from, canonical name first.
+///
+const ALIASES: [(Manufacturer, &[&str]); 13] = [
+	(Manufacturer::Bsh, &["bsh", "bosch"]),
+	(Manufacturer::Thermador, &["thermador"]),
+	(Manufacturer::Gaggenau, &["gaggenau"]),
+	(Manufacturer::Subzero, &["subzero", "sub-zero", "sub zero"]),
+	(Manufacturer::Wolf, &["wolf", "cove"]),
+	(Manufacturer::Miele, &["miele"]),
+	(Manufacturer::Liebherr, &["liebherr"]),
+	(Manufacturer::FisherPaykel, &["fisher_paykel", "fisher & paykel", "fisher and paykel", "fisher paykel", "fisherpaykel", "fisher-paykel", "f&p"]),
+	(Manufacturer::JennAir, &["jennair", "jenn-air", "jenn air", "whirlpool"]),
+	(Manufacturer::Monogram, &["monogram", "ge monogram", "ge", "ge appliances"]),
+	(Manufacturer::TrueResidential, &["true_residential", "true residential", "true-residential"]),
+	(Manufacturer::Dacor, &["dacor", "samsung dacor"]),
+	(Manufacturer::Bertazzoni, &["bertazzoni"]),
+];
+
+impl Manufacturer {
+	///
+	/// # `Manufacturer::as_str`
+	/// The canonical name, e.g. `fisher_paykel`.
+	///
+	#[must_use]
+	pub fn as_str(&self) -> &str {
+		match self {
+			Self::Bsh => "bsh",
+			Self::Thermador => "thermador",
+			Self::Gaggenau => "gaggenau",
+			Self::Subzero => "subzero",
+			Self::Wolf => "wolf",
+			Self::Miele => "miele",
+			Self::Liebherr => "liebherr",
+			Self::FisherPaykel => "fisher_paykel",
+			Self::JennAir => "jennair",
+			Self::Monogram => "monogram",
+			Self::TrueResidential => "true_residential",
+			Self::Dacor => "dacor",
+			Self::Bertazzoni => "bertazzoni",
+			Self::Other(name) => name,
+		}
+	}
+
+	///
+	/// # `Manufacturer::is_builtin`
+	/// Whether the crate has a backend for the manufacturer, when its feature is on.
+	///
+	#[must_use]
+	pub const fn is_builtin(&self) -> bool {
+		!matches!(self, Self::Other(_))
+	}
+}
+
+impl fmt::Display for Manufacturer {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(self.as_str())
+	}
+}
+
+impl FromStr for Manufacturer {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let name = s.trim().to_lowercase();
+		if name.is_empty() {
+			return Err("No manufacturer given.".to_string());
+		}
+		Ok(ALIASES.iter().find(|(_, aliases)| aliases.contains(&name.as_str())).map_or_else(|| Self::Other(name.clone()), |(manufacturer, _)| manufacturer.clone()))
+	}
+}
@@ -0,0 +1,192 @@
+use std::sync::{OnceLock, RwLock};
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "miele")]
+use super::miele;
+#[cfg(feature = "subzero")]
+use super::subzero;
+use super::{cache, telemetry, Availability, CatalogVersion, ProductInfo, Rejection};
+
+///
+/// # `Footprint`
+/// Estimate of the heap memory owned by a value, used to keep caches and catalogs within their budgets.
+///
+pub trait Footprint {
+	///
+	/// Bytes owned on the heap, not counting the value itself.
+	///
+	fn heap_bytes(&self) -> usize;
+}
+
+impl Footprint for String {
+	fn heap_bytes(&self) -> usize {
+		self.capacity()
+	}
+}
+
+impl Footprint for u32 {
+	fn heap_bytes(&self) -> usize {
+		0
+	}
+}
+
+impl Footprint for NaiveDate {
+	fn heap_bytes(&self) -> usize {
+		0
+	}
+}
+
+impl<T: Footprint> Footprint for Option<T> {
+	fn heap_bytes(&self) -> usize {
+		self.as_ref().map_or(0, Footprint::heap_bytes)
+	}
+}
+
+impl<T: Footprint> Footprint for Vec<T> {
+	fn heap_bytes(&self) -> usize {
+		self.capacity() * std::mem::size_of::<T>() + self.iter().map(Footprint::heap_bytes).sum::<usize>()
+	}
+}
+
+impl<A: Footprint, B: Footprint, C: Footprint, D: Footprint> Footprint for (A, B, C, D) {
+	fn heap_bytes(&self) -> usize {
+		self.0.heap_bytes() + self.1.heap_bytes() + self.2.heap_bytes() + self.3.heap_bytes()
+	}
+}
+
+impl<A: Footprint, B: Footprint, C: Footprint, D: Footprint, E: Footprint> Footprint for (A, B, C, D, E) {
+	fn heap_bytes(&self) -> usize {
+		self.0.heap_bytes() + self.1.heap_bytes() + self.2.heap_bytes() + self.3.heap_bytes() + self.4.heap_bytes()
+	}
+}
+
+impl<A: Footprint, B: Footprint, C: Footprint, D: Footprint, E: Footprint, F: Footprint> Footprint for (A, B, C, D, E, F) {
+	fn heap_bytes(&self) -> usize {
+		self.0.heap_bytes() + self.1.heap_bytes() + self.2.heap_bytes() + self.3.heap_bytes() + self.4.heap_bytes() + self.5.heap_bytes()
+	}
+}
+
+impl Footprint for Availability {
+	fn heap_bytes(&self) -> usize {
+		self.raw.heap_bytes()
+	}
+}
+
+impl Footprint for ProductInfo {
+	fn heap_bytes(&self) -> usize {
+		self.name.heap_bytes() + self.category.heap_bytes() + self.brand.heap_bytes() + self.language.heap_bytes() + self.names.iter().map(|(language, name)| language.heap_bytes() + name.heap_bytes()).sum::<usize>()
+	}
+}
+
+impl Footprint for CatalogVersion {
+	fn heap_bytes(&self) -> usize {
+		self.digest.heap_bytes()
+	}
+}
+
+impl Footprint for Rejection {
+	fn heap_bytes(&self) -> usize {
+		self.code.heap_bytes() + self.message.heap_bytes()
+	}
+}
+
+///
+/// # `MemoryBudget`
+/// Upper bounds, in bytes, for the in-memory caches and catalogs. `None` means unbounded.
+///
+/// Caches evict their least recently used entries to stay within budget. A catalog cannot be partially
+/// evicted, so exceeding its budget only raises the `memory.budget.exceeded` telemetry event.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct MemoryBudget {
+	pub result_cache_bytes: Option<usize>,
+	pub suggest_cache_bytes: Option<usize>,
+	pub catalog_bytes: Option<usize>,
+}
+
+impl Default for MemoryBudget {
+	fn default() -> Self {
+		Self { result_cache_bytes: Some(64 * 1024 * 1024), suggest_cache_bytes: Some(8 * 1024 * 1024), catalog_bytes: Some(256 * 1024 * 1024) }
+	}
+}
+
+impl MemoryBudget {
+	#[must_use]
+	pub const fn new(result_cache_bytes: Option<usize>, suggest_cache_bytes: Option<usize>, catalog_bytes: Option<usize>) -> Self {
+		Self { result_cache_bytes, suggest_cache_bytes, catalog_bytes }
+	}
+}
+
+fn budget_slot() -> &'static RwLock<MemoryBudget> {
+	static BUDGET: OnceLock<RwLock<MemoryBudget>> = OnceLock::new();
+	BUDGET.get_or_init(|| RwLock::new(MemoryBudget::default()))
+}
+
+///
+/// # `set_memory_budget`
+/// Replace the memory budget. Caches already over their new budget are trimmed immediately.
+///
+pub fn set_memory_budget(budget: MemoryBudget) {
+	*budget_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = budget;
+	cache::result_cache().set_max_bytes(budget.result_cache_bytes);
+	#[cfg(feature = "subzero")]
+	subzero::suggest_cache().set_max_bytes(budget.suggest_cache_bytes);
+}
+
+///
+/// # `memory_budget`
+/// The current memory budget.
+///
+#[must_use]
+pub fn memory_budget() -> MemoryBudget {
+	*budget_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+///
+/// # `MemoryReport`
+/// Current estimated footprint of the in-memory caches and catalogs.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct MemoryReport {
+	pub result_cache_entries: usize,
+	pub result_cache_bytes: usize,
+	pub suggest_cache_entries: usize,
+	pub suggest_cache_bytes: usize,
+	pub catalog_bytes: usize,
+}
+
+///
+/// # `memory_report`
+/// Measure the caches and catalogs and publish the figures as `memory.*` telemetry histograms.
+///
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn memory_report() -> MemoryReport {
+	let (result_cache_entries, result_cache_bytes) = cache::result_cache().usage();
+	#[cfg(feature = "subzero")]
+	let (suggest_cache_entries, suggest_cache_bytes) = subzero::suggest_cache().usage();
+	#[cfg(not(feature = "subzero"))]
+	let (suggest_cache_entries, suggest_cache_bytes) = (0, 0);
+	#[cfg(feature = "miele")]
+	let catalog_bytes = miele::current_miele_catalog().map_or(0, |catalog| catalog.heap_bytes());
+	#[cfg(not(feature = "miele"))]
+	let catalog_bytes = 0;
+
+	telemetry::histogram("memory.result_cache.bytes", result_cache_bytes as f64, &[]);
+	telemetry::histogram("memory.suggest_cache.bytes", suggest_cache_bytes as f64, &[]);
+	telemetry::histogram("memory.catalog.bytes", catalog_bytes as f64, &[("manufacturer", "miele".to_string())]);
+	MemoryReport { result_cache_entries, result_cache_bytes, suggest_cache_entries, suggest_cache_bytes, catalog_bytes }
+}
+
+///
+/// Raise `memory.budget.exceeded` when a catalog of `bytes` is over budget.
+///
+pub(crate) fn check_catalog_budget(manufacturer: &str, bytes: usize) {
+	if let Some(budget) = memory_budget().catalog_bytes.filter(|budget| bytes > *budget) {
+		telemetry::event("memory.budget.exceeded", &[("manufacturer", manufacturer.to_string()), ("bytes", bytes.to_string()), ("budget", budget.to_string())]);
+	}
+}
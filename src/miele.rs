@@ -1,14 +1,189 @@
-use std::fs::File;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, Instant};
 
+use chrono::{DateTime, Days, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
 use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
-use office::{DataType, Excel};
-use reqwest::Client;
+use office::{DataType, Excel, Range};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
 use urlencoding::decode;
 
-use super::AvailabilityRequest;
+use super::backend::{BackendAnswer, ManufacturerBackend};
+use super::catalog::{normalize_model, CachedCatalog, CatalogItem, CatalogSnapshot};
+use super::{Availability, AvailabilityError, AvailabilityRequest, AvailabilityStatus, CatalogVersion, ProductInfo};
+use crate::events::{self, AvailabilityEvent, CatalogChange, CatalogChangeKind};
+use crate::history::parse_available_date;
+use crate::http_client;
+use crate::memory::{self, Footprint};
+use crate::response::V1_NOT_FOUND;
+use crate::schedule::{self, Workload};
+use crate::secrets::get_secret;
+use crate::telemetry;
+use crate::timezone::DEFAULT_BUSINESS_TIME_ZONE;
+
+const MIELE_REPORT_URL: &str = "https://ws15.mieleusa.com/sbo-reports/reports/download.php?id=SlyUOJt9vOFlwUcXZleX";
+const MIELE_DATA_PATH: &str = "/easfiles/appliances/data/";
+const MIELE_FILE_NAME: &str = "miele_appliance_availability.xlsx";
+const MIELE_DIGESTS_FILE_NAME: &str = "miele_report_digests.json";
+
+///
+/// Report digests remembered in `MIELE_DIGESTS_FILE_NAME`, oldest dropped first.
+///
+const MAX_STORED_DIGESTS: usize = 100;
+
+///
+/// API tokens are renewed this long before they expire.
+///
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+///
+/// Age after which a lookup triggers a refresh of the catalog.
+///
+const CATALOG_MAX_AGE: Duration = Duration::from_mins(15);
+
+///
+/// # `MieleReportConfig`
+/// Where and when the availability report is downloaded.
+///
+/// `urls` are tried in order until one answers. Miele regenerates the report at `regenerated_at` in
+/// `time_zone`; a catalog loaded before the latest regeneration (plus `settle`, while the new report is
+/// written) is refreshed regardless of its age. After every URL failed, lookups keep the previous catalog
+/// and the download is not tried again for `retry_interval`.
+///
+/// ## Example
+/// ```
+/// use std::time::Duration;
+/// use chrono::NaiveTime;
+/// use eggersmann_app_server_appliance_availability::{set_miele_report_config, MieleReportConfig};
+///
+/// set_miele_report_config(MieleReportConfig::default().with_mirror("https://mirror.example.com/miele.xlsx".to_string()).with_regenerated_at(vec![NaiveTime::from_hms_opt(5, 30, 0).unwrap()]).with_retry_interval(Duration::from_mins(5)));
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MieleReportConfig {
+	pub urls: Vec<String>,
+	pub regenerated_at: Vec<NaiveTime>,
+	pub time_zone: Tz,
+	pub settle: Duration,
+	pub retry_interval: Duration,
+}
+
+impl Default for MieleReportConfig {
+	fn default() -> Self {
+		Self::new(vec![MIELE_REPORT_URL.to_string()])
+	}
+}
+
+impl MieleReportConfig {
+	#[must_use]
+	pub const fn new(urls: Vec<String>) -> Self {
+		Self { urls, regenerated_at: Vec::new(), time_zone: DEFAULT_BUSINESS_TIME_ZONE, settle: Duration::from_mins(10), retry_interval: Duration::from_mins(2) }
+	}
+
+	#[must_use]
+	pub fn with_mirror(mut self, url: String) -> Self {
+		self.urls.push(url);
+		self
+	}
+
+	#[must_use]
+	pub fn with_regenerated_at(mut self, regenerated_at: Vec<NaiveTime>) -> Self {
+		self.regenerated_at = regenerated_at;
+		self
+	}
+
+	#[must_use]
+	pub const fn with_time_zone(mut self, time_zone: Tz) -> Self {
+		self.time_zone = time_zone;
+		self
+	}
+
+	#[must_use]
+	pub const fn with_settle(mut self, settle: Duration) -> Self {
+		self.settle = settle;
+		self
+	}
+
+	#[must_use]
+	pub const fn with_retry_interval(mut self, retry_interval: Duration) -> Self {
+		self.retry_interval = retry_interval;
+		self
+	}
+
+	///
+	/// The latest time at or before `now` the report was regenerated and settled, `None` without a schedule.
+	///
+	#[must_use]
+	pub fn last_regeneration(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+		let today = now.with_timezone(&self.time_zone).date_naive();
+		let settle = chrono::Duration::from_std(self.settle).unwrap_or_default();
+		[today.checked_sub_days(Days::new(1)), Some(today)].into_iter().flatten().flat_map(|date| self.regenerated_at.iter().filter_map(move |time| self.time_zone.from_local_datetime(&date.and_time(*time)).earliest())).map(|regenerated| regenerated.with_timezone(&Utc) + settle).filter(|settled| *settled <= now).max()
+	}
+}
+
+fn report_config_slot() -> &'static RwLock<MieleReportConfig> {
+	static CONFIG: OnceLock<RwLock<MieleReportConfig>> = OnceLock::new();
+	CONFIG.get_or_init(|| RwLock::new(MieleReportConfig::default()))
+}
+
+///
+/// # `set_miele_report_config`
+/// Configure the report URLs and regeneration schedule.
+///
+pub fn set_miele_report_config(config: MieleReportConfig) {
+	*report_config_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = config;
+}
+
+///
+/// # `miele_report_config`
+/// The report URLs and regeneration schedule in use.
+///
+#[must_use]
+pub fn miele_report_config() -> MieleReportConfig {
+	report_config_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
+
+///
+/// # `MieleCatalogStatus`
+/// How the downloads of the availability report have gone.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct MieleCatalogStatus {
+	/// When the report was last downloaded, and from where.
+	pub last_success: Option<DateTime<Utc>>,
+	pub last_success_url: Option<String>,
+	/// When a download last failed at every URL, and the error of the last URL.
+	pub last_failure: Option<DateTime<Utc>>,
+	pub last_error: Option<String>,
+	/// Version of the catalog in memory.
+	pub catalog_version: Option<CatalogVersion>,
+}
+
+fn status_slot() -> &'static RwLock<MieleCatalogStatus> {
+	static STATUS: OnceLock<RwLock<MieleCatalogStatus>> = OnceLock::new();
+	STATUS.get_or_init(|| RwLock::new(MieleCatalogStatus::default()))
+}
+
+///
+/// # `catalog_status`
+/// When the Miele report was last downloaded, from which URL, and the last failure.
+///
+#[must_use]
+pub fn catalog_status() -> MieleCatalogStatus {
+	let status = status_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone();
+	MieleCatalogStatus { catalog_version: current_miele_catalog().map(|catalog| catalog.catalog_version()), ..status }
+}
 
 ///
 /// # Miele Availability
@@ -21,185 +196,606 @@ use super::AvailabilityRequest;
 /// String - The availability of the Miele appliances.
 ///
 /// # Errors
-/// todo
-#[allow(clippy::cast_precision_loss, clippy::too_many_lines)]
+/// Returns an error if the report cannot be loaded or has no worksheet for the warehouse, or the request
+/// has no warehouse or model number.
 pub async fn miele_availability(req: AvailabilityRequest) -> Result<String, String> {
-	let file_name = "miele_appliance_availability.xlsx";
-	let root_path = Path::new("/easfiles/appliances/data/");
-	let file_path = Path::join(root_path, file_name);
-
-	let client = Client::new();
-	let response = match client.get("https://ws15.mieleusa.com/sbo-reports/reports/download.php?id=SlyUOJt9vOFlwUcXZleX").send().await {
-		Ok(response) => response,
-		Err(e) => {
-			return Ok(format!("Failed to get Miele appliance availability spreadsheet: {e:?}"));
-		}
-	};
+	Ok(miele_lookup(req).await?.0.raw)
+}
 
-	let mut file = match File::create(&file_path) {
-		Ok(file) => file,
-		Err(e) => {
-			return Ok(format!("Failed to create Miele appliance availability spreadsheet: {e:?}"));
-		}
-	};
-	let response_bytes = match response.bytes().await {
-		Ok(response_bytes) => response_bytes,
-		Err(e) => {
-			return Ok(format!("Failed to get Miele appliance availability spreadsheet: {e:?}"));
+///
+/// # `MieleBackend`
+/// Miele lookups against the availability report or the stock API, registered as `miele`. The report
+/// needs no session, so `login` refreshes the catalog instead.
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MieleBackend;
+
+#[async_trait::async_trait]
+impl ManufacturerBackend for MieleBackend {
+	fn name(&self) -> &'static str {
+		"miele"
+	}
+
+	async fn availability(&self, req: &AvailabilityRequest) -> Result<BackendAnswer, AvailabilityError> {
+		let (availability, product, catalog_version) = miele_lookup(req.clone()).await?;
+		Ok(BackendAnswer::new(availability).with_product(product).with_catalog_version(catalog_version))
+	}
+
+	async fn login(&self) -> Result<(), AvailabilityError> {
+		refresh_miele_catalog().await?;
+		Ok(())
+	}
+}
+
+///
+/// # Miele Lookup
+/// Gets the availability of a Miele appliance together with its catalog description and category, and the
+/// version of the report it was read from.
+///
+/// # Errors
+/// Returns `AvailabilityError::VendorUnavailable` if the report cannot be loaded, and
+/// `AvailabilityError::Other` for a request without warehouse or model number or a warehouse the report
+/// has no worksheet for.
+///
+pub async fn miele_lookup(req: AvailabilityRequest) -> Result<(Availability, Option<ProductInfo>, Option<CatalogVersion>), AvailabilityError> {
+	let today = req.business_today();
+	if let MieleSource::Api(config) = miele_source() {
+		match miele_api_lookup(&config, &req).await {
+			Ok(Some(appliance)) => {
+				let (availability, product) = appliance_availability(&appliance, today);
+				return Ok((availability, product, None));
+			}
+			Ok(None) => return Ok((Availability::from_text(V1_NOT_FOUND, today), None, None)),
+			Err(e) => {
+				telemetry::event("miele.api.fallback", &[("error", e)]);
+			}
 		}
-	};
-	match file.write_all(&response_bytes) {
-		Ok(()) => (),
-		Err(e) => {
-			return Ok(format!("Failed to write Miele appliance availability spreadsheet to file: {e:?}"));
+	}
+	miele_report_lookup(req, today).await
+}
+
+///
+/// Language of Miele descriptions when no other is asked for, and of every description in the report.
+///
+const DEFAULT_LANGUAGE: &str = "en";
+
+///
+/// Look the model up in the availability report.
+///
+async fn miele_report_lookup(req: AvailabilityRequest, today: NaiveDate) -> Result<(Availability, Option<ProductInfo>, Option<CatalogVersion>), AvailabilityError> {
+	let catalog = miele_catalog().await.map_err(AvailabilityError::VendorUnavailable)?;
+	let version = Some(catalog.catalog_version());
+
+	let warehouse = req.warehouse.clone().ok_or_else(|| AvailabilityError::Other("No warehouse found.".to_string()))?;
+	let model_number = req.model_number.clone().ok_or_else(|| AvailabilityError::Other("No model number found.".to_string()))?;
+	let appliances = catalog.sheets.get(&warehouse).ok_or_else(|| AvailabilityError::Other(format!("Worksheet {warehouse} not found")))?;
+
+	let mut best_match = best_match(appliances, &model_number)?;
+	best_match.language = DEFAULT_LANGUAGE.to_string();
+	let (availability, product) = appliance_availability(&best_match, today);
+	Ok((availability, product, version))
+}
+
+fn availability_text(appliance: &MieleAppliance) -> (String, Option<ProductInfo>) {
+	let product = appliance.product();
+	if appliance.model_number.trim().is_empty() {
+		return (V1_NOT_FOUND.to_string(), product);
+	}
+	match appliance.next_available_date.as_str() {
+		"" => (format!("Next avalability for {} is unknown.", appliance.model_number), product),
+		_ => (format!("Found: {}, Available: {}", appliance.model_number, appliance.next_available_date), product),
+	}
+}
+
+///
+/// The availability of a report or API row. Stock on hand makes it in stock whatever the next date says;
+/// otherwise the next quantity is expected on the next date. A sales status of discontinued wins.
+///
+fn appliance_availability(appliance: &MieleAppliance, today: NaiveDate) -> (Availability, Option<ProductInfo>) {
+	let (text, product) = availability_text(appliance);
+	let mut availability = Availability::from_text(&text, today).with_model_number(&appliance.model_number);
+	let quantity = |value: &str| value.trim().split('.').next().and_then(|whole| whole.parse::<u32>().ok()).filter(|quantity| *quantity > 0);
+	if let Some(on_hand) = quantity(&appliance.available_qty) {
+		availability.status = AvailabilityStatus::InStock;
+		availability.quantity = Some(on_hand);
+	} else if let Some(next) = quantity(&appliance.next_available_qty) {
+		availability.quantity = Some(next);
+	}
+	if appliance.sales_status.to_lowercase().contains("discontinued") {
+		availability.status = AvailabilityStatus::Discontinued;
+	}
+	(availability, product)
+}
+
+///
+/// # `MieleSource`
+/// Where Miele availability is read from.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum MieleSource {
+	/// The availability report spreadsheet.
+	#[default]
+	Report,
+	/// The authenticated B2B API, falling back to the report when the API is unavailable.
+	Api(MieleApiConfig),
+}
+
+///
+/// # `MieleApiConfig`
+/// Endpoints of the Miele B2B API and the secrets holding its OAuth client credentials.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MieleApiConfig {
+	pub token_url: String,
+	pub stock_url: String,
+	pub client_id_secret: String,
+	pub client_secret_secret: String,
+}
+
+impl MieleApiConfig {
+	///
+	/// # `MieleApiConfig::new`
+	/// API configuration reading the client credentials from the `miele-client-id` and `miele-client-secret`
+	/// secrets.
+	///
+	#[must_use]
+	pub fn new(token_url: String, stock_url: String) -> Self {
+		Self { token_url, stock_url, client_id_secret: "miele-client-id".to_string(), client_secret_secret: "miele-client-secret".to_string() }
+	}
+
+	#[must_use]
+	pub fn with_client_secrets(mut self, client_id_secret: String, client_secret_secret: String) -> Self {
+		self.client_id_secret = client_id_secret;
+		self.client_secret_secret = client_secret_secret;
+		self
+	}
+}
+
+fn source_slot() -> &'static RwLock<MieleSource> {
+	static SOURCE: OnceLock<RwLock<MieleSource>> = OnceLock::new();
+	SOURCE.get_or_init(|| RwLock::new(MieleSource::Report))
+}
+
+///
+/// # `set_miele_source`
+/// Choose where Miele availability is read from.
+///
+pub fn set_miele_source(source: MieleSource) {
+	*source_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = source;
+}
+
+///
+/// # `miele_source`
+/// Where Miele availability is currently read from.
+///
+#[must_use]
+pub fn miele_source() -> MieleSource {
+	source_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
+
+///
+/// Bearer token of the B2B API and the instant it stops being valid.
+///
+fn token_slot() -> &'static RwLock<Option<(String, Instant)>> {
+	static TOKEN: OnceLock<RwLock<Option<(String, Instant)>>> = OnceLock::new();
+	TOKEN.get_or_init(|| RwLock::new(None))
+}
+
+///
+/// Get a bearer token with the OAuth client credentials grant, reusing the previous one until shortly
+/// before it expires.
+///
+async fn miele_api_token(config: &MieleApiConfig) -> Result<String, String> {
+	if let Some((token, _)) = token_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).as_ref().filter(|(_, expires)| Instant::now() < *expires) {
+		return Ok(token.clone());
+	}
+
+	let client_id = get_secret(&config.client_id_secret).await?;
+	let client_secret = get_secret(&config.client_secret_secret).await?;
+	let response = http_client::client().post(&config.token_url).form(&[("grant_type", "client_credentials"), ("client_id", client_id.expose()), ("client_secret", client_secret.expose())]).send().await.map_err(|e| format!("Failed to get Miele API token: {e:?}"))?;
+	if !response.status().is_success() {
+		return Err(format!("Failed to get Miele API token: {}", response.status()));
+	}
+	let data: Value = http_client::json(response).await.map_err(|e| format!("Failed to parse Miele API token: {e:?}"))?;
+	let token = data["access_token"].as_str().ok_or_else(|| "Miele API token response has no access_token.".to_string())?.to_string();
+	let expires_in = Duration::from_secs(data["expires_in"].as_u64().unwrap_or(300)).saturating_sub(TOKEN_EXPIRY_MARGIN);
+	*token_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = Some((token.clone(), Instant::now() + expires_in));
+	Ok(token)
+}
+
+///
+/// Look the model up through the B2B API. `Ok(None)` means the API answered but does not know the model.
+///
+async fn miele_api_lookup(config: &MieleApiConfig, req: &AvailabilityRequest) -> Result<Option<MieleAppliance>, String> {
+	let Some(model_number) = req.model_number.as_deref() else { return Err("No model number found.".to_string()) };
+	let token = miele_api_token(config).await?;
+	let model_number = model_number.trim().to_uppercase();
+	let language = req.description_language();
+
+	let mut query = vec![("materialNumber", model_number.as_str()), ("warehouse", req.warehouse.as_deref().unwrap_or_default())];
+	if let Some(language) = language.as_deref() {
+		query.push(("language", language));
+	}
+	let response = http_client::client().get(&config.stock_url).bearer_auth(token).query(&query).send().await.map_err(|e| format!("Failed to get Miele API stock: {e:?}"))?;
+	match response.status() {
+		StatusCode::NOT_FOUND => return Ok(None),
+		StatusCode::UNAUTHORIZED => {
+			*token_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = None;
+			return Err("Miele API token was rejected.".to_string());
 		}
+		status if !status.is_success() => return Err(format!("Failed to get Miele API stock: {status}")),
+		_ => {}
+	}
+	let data: Value = http_client::json(response).await.map_err(|e| format!("Failed to parse Miele API stock: {e:?}"))?;
+	Ok(parse_api_stock(&data, &model_number, language.as_deref()))
+}
+
+///
+/// Read the API answer. The description is in the language the item names, else in English when no
+/// language was asked for; an API that ignores the asked language leaves it unknown.
+///
+fn parse_api_stock(data: &Value, model_number: &str, language: Option<&str>) -> Option<MieleAppliance> {
+	let item = data.get("items").and_then(|items| items.get(0)).unwrap_or(data);
+	let text = |key: &str| match &item[key] {
+		Value::String(s) => s.trim().to_string(),
+		Value::Number(n) => n.to_string(),
+		_ => String::new(),
 	};
+	if item.is_null() || item.as_object().is_some_and(serde_json::Map::is_empty) {
+		return None;
+	}
+	let found_model = text("materialNumber");
+	Some(MieleAppliance {
+		model_number: if found_model.is_empty() { model_number.to_string() } else { found_model },
+		description: text("description"),
+		category: text("productGroup"),
+		upc: text("ean"),
+		available_qty: text("availableQuantity"),
+		next_available_qty: text("nextAvailableQuantity"),
+		next_available_date: text("nextAvailableDate"),
+		language: Some(text("language")).filter(|language| !language.is_empty()).or_else(|| language.is_none().then(|| DEFAULT_LANGUAGE.to_string())).unwrap_or_default(),
+		..MieleAppliance::default()
+	})
+}
+
+///
+/// # Miele Catalog
+/// The parsed Miele availability report, one list of appliances per warehouse worksheet.
+///
+/// A catalog is immutable once built; a refresh builds a new one and swaps it in, so a reader always
+/// sees one complete report. A refresh that downloads the same report again, by SHA-256 digest, keeps the
+/// parsed sheets and only moves `loaded_at`.
+///
+#[derive(Debug, Clone)]
+pub struct MieleCatalog {
+	/// When the report was last downloaded and found current.
+	pub loaded_at: DateTime<Utc>,
+	/// Sequence number of the report among the distinct reports downloaded, kept across restarts.
+	pub version: u64,
+	/// Hex SHA-256 digest of the report workbook.
+	pub digest: String,
+	sheets: Arc<HashMap<String, Vec<MieleAppliance>>>,
+}
+
+impl MieleCatalog {
+	///
+	/// # `MieleCatalog::catalog_version`
+	/// The version and digest of the report, as reported on responses.
+	///
+	#[must_use]
+	pub fn catalog_version(&self) -> CatalogVersion {
+		CatalogVersion::new(self.version, self.digest.clone())
+	}
+}
 
-	let mut excel = match Excel::open(&file_path) {
-		Ok(excel) => excel,
-		Err(e) => {
-			return Ok(format!("Failed to open Miele appliance availability spreadsheet: {e:?}"));
+fn catalog_slot() -> &'static RwLock<Option<Arc<MieleCatalog>>> {
+	static CATALOG: OnceLock<RwLock<Option<Arc<MieleCatalog>>>> = OnceLock::new();
+	CATALOG.get_or_init(|| RwLock::new(None))
+}
+
+///
+/// Only one refresh downloads and parses the report at a time.
+///
+static REFRESH_LOCK: Mutex<()> = Mutex::const_new(());
+
+///
+/// # Current Miele Catalog
+/// The catalog currently in memory, without refreshing it.
+///
+pub fn current_miele_catalog() -> Option<Arc<MieleCatalog>> {
+	catalog_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
+
+///
+/// Whether the catalog is young enough and no newer report has been generated since it was loaded.
+///
+fn is_fresh(catalog: &MieleCatalog) -> bool {
+	let now = Utc::now();
+	let young = (now - catalog.loaded_at).to_std().is_ok_and(|age| age < CATALOG_MAX_AGE);
+	young && miele_report_config().last_regeneration(now).is_none_or(|regenerated| catalog.loaded_at >= regenerated)
+}
+
+///
+/// Whether the last download failed less than `retry_interval` ago, so the previous catalog is used as is.
+///
+fn backing_off() -> bool {
+	let last_failure = status_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).last_failure;
+	last_failure.is_some_and(|failed| (Utc::now() - failed).to_std().is_ok_and(|since| since < miele_report_config().retry_interval))
+}
+
+///
+/// The in-memory catalog, refreshed first if it is missing or stale. A stale catalog keeps answering while
+/// the last download failed recently or `schedule` does not allow bulk work for Miele now.
+///
+async fn miele_catalog() -> Result<Arc<MieleCatalog>, String> {
+	if let Some(catalog) = current_miele_catalog().filter(|catalog| is_fresh(catalog)) {
+		return Ok(catalog);
+	}
+
+	let _refresh = REFRESH_LOCK.lock().await;
+	// another request may have refreshed while we waited for the lock.
+	if let Some(catalog) = current_miele_catalog().filter(|catalog| is_fresh(catalog)) {
+		return Ok(catalog);
+	}
+	let previous = current_miele_catalog();
+	if let Some(previous) = previous.clone().filter(|_| backing_off() || !schedule::may_run(Workload::Bulk, None, Some("miele"), Utc::now())) {
+		return Ok(previous);
+	}
+	match refresh_miele_catalog_locked().await {
+		Ok(catalog) => Ok(catalog),
+		Err(e) => previous.ok_or(e),
+	}
+}
+
+///
+/// # Refresh Miele Catalog
+/// Download the Miele availability report, parse it and atomically replace the in-memory catalog.
+///
+/// # Errors
+/// Returns an error if the report cannot be downloaded from any URL or parsed. The previous catalog stays
+/// in place.
+///
+pub async fn refresh_miele_catalog() -> Result<Arc<MieleCatalog>, String> {
+	let _refresh = REFRESH_LOCK.lock().await;
+	refresh_miele_catalog_locked().await
+}
+
+async fn refresh_miele_catalog_locked() -> Result<Arc<MieleCatalog>, String> {
+	let report = download_report().await?;
+	let digest = format!("{:x}", Sha256::digest(&report));
+	if let Some(current) = current_miele_catalog().filter(|catalog| catalog.digest == digest) {
+		telemetry::counter("miele.catalog.unchanged", 1, &[]);
+		let catalog = Arc::new(MieleCatalog { loaded_at: Utc::now(), ..(*current).clone() });
+		catalog_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner).replace(catalog.clone());
+		return Ok(catalog);
+	}
+
+	let file_path = save_report(&report)?;
+	let version = record_digest(&digest);
+	let catalog = Arc::new(parse_report(&file_path, version, digest)?);
+	memory::check_catalog_budget("miele", catalog.heap_bytes());
+	let previous = catalog_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner).replace(catalog.clone());
+
+	if let Some(previous) = previous {
+		let changes = diff_catalogs(&previous, &catalog);
+		telemetry::counter("miele.catalog.changes", changes.len() as u64, &[]);
+		for change in changes {
+			events::publish(AvailabilityEvent::CatalogChange(change));
 		}
-	};
+	}
+	Ok(catalog)
+}
+
+///
+/// Row-level differences between two catalogs, keyed by warehouse and model number.
+///
+fn diff_catalogs(previous: &MieleCatalog, next: &MieleCatalog) -> Vec<CatalogChange> {
+	let index = |catalog: &MieleCatalog| -> BTreeMap<(String, String), MieleAppliance> { catalog.sheets.iter().flat_map(|(warehouse, appliances)| appliances.iter().filter(|appliance| !appliance.model_number.trim().is_empty()).map(move |appliance| ((warehouse.clone(), appliance.model_number.trim().to_uppercase()), appliance.clone()))).collect() };
+	let previous = index(previous);
+	let next = index(next);
+	let change = |(warehouse, model_number): &(String, String), kind| CatalogChange { manufacturer: "miele".to_string(), warehouse: warehouse.clone(), model_number: model_number.clone(), kind };
 
-	let Some(warehouse) = req.warehouse.clone() else { return Ok("No warehouse found.".to_string()) };
-	let Some(model_number) = req.model_number.clone() else { return Ok("No model number found.".to_string()) };
-
-	match excel.worksheet_range(&warehouse) {
-		Ok(range) => {
-			let mut headers: Vec<(String, usize)> = Vec::new();
-			let mut i: usize = 0;
-
-			match range.rows().next() {
-				Some(row) => {
-					for cell in row {
-						let value = match cell {
-							DataType::String(s) => s.to_string(),
-							DataType::Float(f) => f.to_string(),
-							DataType::Int(i) => i.to_string(),
-							DataType::Bool(b) => b.to_string(),
-							_ => String::new(),
-						};
-						headers.push((value, i));
-						i += 1;
-					}
+	let mut changes = Vec::new();
+	for (key, old) in &previous {
+		match next.get(key) {
+			None => changes.push(change(key, CatalogChangeKind::ItemRemoved)),
+			Some(new) => {
+				if old.available_qty != new.available_qty {
+					changes.push(change(key, CatalogChangeKind::QuantityChanged { from: old.available_qty.clone(), to: new.available_qty.clone() }));
+				}
+				if old.next_available_date != new.next_available_date {
+					changes.push(change(key, CatalogChangeKind::DateChanged { from: old.next_available_date.clone(), to: new.next_available_date.clone() }));
 				}
-				None => return Ok("Failed to get row from Miele appliance availability spreadsheet.".to_string()),
 			}
+		}
+	}
+	for key in next.keys().filter(|key| !previous.contains_key(*key)) {
+		changes.push(change(key, CatalogChangeKind::ItemAdded));
+	}
+	changes
+}
 
-			let mut miele_appliances: Vec<MieleAppliance> = range
-				.rows()
-				.skip(1)
-				.map(|row| {
-					let mut appliance = MieleAppliance {
-						timestamp: String::new(),
-						sku: String::new(),
-						upc: String::new(),
-						category: String::new(),
-						subcategory: String::new(),
-						model_number: String::new(),
-						description: String::new(),
-						current_umrp: String::new(),
-						new_umrp: String::new(),
-						dealer_cost_level: String::new(),
-						warehouse_number: String::new(),
-						available_qty: String::new(),
-						sales_status: String::new(),
-						next_available_qty: String::new(),
-						next_available_date: String::new(),
-						score: 0.0,
-					};
-
-					row.iter().enumerate().for_each(|(i, cell)| {
-						let value = match cell {
-							DataType::String(s) => s.to_string(),
-							DataType::Float(f) => f.to_string(),
-							DataType::Int(i) => i.to_string(),
-							DataType::Bool(b) => b.to_string(),
-							_ => String::new(),
-						};
-						let header = headers.iter().find(|header| header.1 == i).map_or_else(String::new, |header| header.0.clone());
-						match header.as_str().to_lowercase().as_str() {
-							"timestamp" => appliance.timestamp = value,
-							"sku#" => appliance.sku = value,
-							"ean/upc" => appliance.upc = value,
-							"category" => appliance.category = value,
-							"subcategory" => appliance.subcategory = value,
-							"model number" => appliance.model_number = value,
-							"description" => appliance.description = value,
-							"current umrp/map" => appliance.current_umrp = value,
-							"new umrp/map" => appliance.new_umrp = value,
-							"dealer cost level" => appliance.dealer_cost_level = value,
-							"warehouse no" => appliance.warehouse_number = value,
-							"available qty" => appliance.available_qty = value,
-							"sales status" => appliance.sales_status = value,
-							"next available qty" => appliance.next_available_qty = value,
-							"next available date" => appliance.next_available_date = value,
-							_ => {}
-						}
-					});
-					appliance
-				})
-				.collect();
-
-			let mut best_match = MieleAppliance {
-				timestamp: String::new(),
-				sku: String::new(),
-				upc: String::new(),
-				category: String::new(),
-				subcategory: String::new(),
-				model_number: String::new(),
-				description: String::new(),
-				current_umrp: String::new(),
-				new_umrp: String::new(),
-				dealer_cost_level: String::new(),
-				warehouse_number: String::new(),
-				available_qty: String::new(),
-				sales_status: String::new(),
-				next_available_qty: String::new(),
-				next_available_date: String::new(),
-				score: 0.0,
-			};
-
-			for (i, miele_appliance) in miele_appliances.clone().iter().enumerate() {
-				let matcher = SkimMatcherV2::default();
-
-				let app_m_n: String = miele_appliance.model_number.to_lowercase().trim().to_string().chars().filter(|c| !c.is_whitespace()).collect();
-				let app_desc: String = miele_appliance.description.to_lowercase().trim().to_string().chars().filter(|c| !c.is_whitespace()).collect();
-				let m_n: String = match decode(&model_number) {
-					Ok(m_n) => m_n.to_lowercase().trim().to_string().chars().filter(|c| !c.is_whitespace()).collect(),
-					Err(_) => {
-						return Ok("Cannot decode model number.".to_string());
-					}
-				};
-
-				let model_number_result = matcher.fuzzy_match(app_m_n.as_str(), m_n.as_str());
-				let model_number_score: f64 = model_number_result.map_or(0.0, |model_number_result| model_number_result as f64);
-
-				let description_result = matcher.fuzzy_match(app_desc.as_str(), m_n.as_str());
-				let description_score: f64 = description_result.map_or(0.0, |description_result| description_result as f64);
-
-				let score = model_number_score + description_score;
-				miele_appliances[i].score = score;
-				if score > best_match.score {
-					best_match = miele_appliances[i].clone();
-				}
+///
+/// Download the report workbook from the first configured URL that answers, recording the outcome for
+/// `catalog_status`.
+///
+async fn download_report() -> Result<Vec<u8>, String> {
+	let mut error = "No Miele report URL is configured.".to_string();
+	for url in miele_report_config().urls {
+		match download_report_from(&url).await {
+			Ok(report) => {
+				let mut status = status_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner);
+				status.last_success = Some(Utc::now());
+				status.last_success_url = Some(url);
+				drop(status);
+				return Ok(report);
+			}
+			Err(e) => {
+				telemetry::event("miele.report.download_failed", &[("url", url), ("error", e.clone())]);
+				error = e;
 			}
+		}
+	}
+	let mut status = status_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner);
+	status.last_failure = Some(Utc::now());
+	status.last_error = Some(error.clone());
+	drop(status);
+	Err(error)
+}
+
+async fn download_report_from(url: &str) -> Result<Vec<u8>, String> {
+	let response = http_client::client().get(url).send().await.map_err(|e| format!("Failed to get Miele appliance availability spreadsheet: {e:?}"))?;
+	if !response.status().is_success() {
+		return Err(format!("Failed to get Miele appliance availability spreadsheet: {}", response.status()));
+	}
+	http_client::bytes(response).await.map_err(|e| format!("Failed to get Miele appliance availability spreadsheet: {e:?}"))
+}
+
+///
+/// Write the report to a temporary file and move it into place once complete.
+///
+fn save_report(response_bytes: &[u8]) -> Result<PathBuf, String> {
+	let file_path = Path::new(MIELE_DATA_PATH).join(MIELE_FILE_NAME);
+	let tmp_path = file_path.with_extension("xlsx.part");
+
+	let mut file = File::create(&tmp_path).map_err(|e| format!("Failed to create Miele appliance availability spreadsheet: {e:?}"))?;
+	file.write_all(response_bytes).map_err(|e| format!("Failed to write Miele appliance availability spreadsheet to file: {e:?}"))?;
+	fs::rename(&tmp_path, &file_path).map_err(|e| format!("Failed to write Miele appliance availability spreadsheet to file: {e:?}"))?;
+	Ok(file_path)
+}
+
+///
+/// A report digest seen before, with the catalog version it was given.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredDigest {
+	digest: String,
+	version: u64,
+	first_seen: DateTime<Utc>,
+}
+
+///
+/// The catalog version of the report with `digest`: the version it was given when first downloaded, or
+/// the next version if it is new. Versions are kept in `MIELE_DIGESTS_FILE_NAME` so they survive a
+/// restart; if the file cannot be written the version is still returned.
+///
+fn record_digest(digest: &str) -> u64 {
+	let path = Path::new(MIELE_DATA_PATH).join(MIELE_DIGESTS_FILE_NAME);
+	let mut stored: Vec<StoredDigest> = fs::read(&path).ok().and_then(|bytes| serde_json::from_slice(&bytes).ok()).unwrap_or_default();
+	if let Some(seen) = stored.iter().find(|seen| seen.digest == digest) {
+		return seen.version;
+	}
+
+	let version = stored.iter().map(|seen| seen.version).max().unwrap_or(0) + 1;
+	stored.push(StoredDigest { digest: digest.to_string(), version, first_seen: Utc::now() });
+	if stored.len() > MAX_STORED_DIGESTS {
+		stored.drain(..stored.len() - MAX_STORED_DIGESTS);
+	}
+	if let Err(e) = serde_json::to_vec(&stored).map_err(|e| e.to_string()).and_then(|json| fs::write(&path, json).map_err(|e| e.to_string())) {
+		telemetry::event("miele.catalog.digest_store_failed", &[("error", e)]);
+	}
+	version
+}
+
+///
+/// Parse every worksheet of the report, one per Miele warehouse and named after it, so a warehouse Miele
+/// adds is in the catalog without a release. Empty worksheets are left out.
+///
+fn parse_report(file_path: &Path, version: u64, digest: String) -> Result<MieleCatalog, String> {
+	let mut excel = Excel::open(file_path).map_err(|e| format!("Failed to open Miele appliance availability spreadsheet: {e:?}"))?;
+	let warehouses = excel.sheet_names().map_err(|e| format!("Failed to read the worksheets of the Miele appliance availability spreadsheet: {e:?}"))?;
+	let mut sheets = HashMap::new();
+	for warehouse in warehouses {
+		if let Ok(appliances) = excel.worksheet_range(&warehouse).map_err(|e| e.to_string()).and_then(|range| parse_sheet(&range)) {
+			sheets.insert(warehouse, appliances);
+		}
+	}
+	if sheets.is_empty() {
+		return Err("Failed to get row from Miele appliance availability spreadsheet.".to_string());
+	}
+	Ok(MieleCatalog { loaded_at: Utc::now(), version, digest, sheets: Arc::new(sheets) })
+}
+
+fn cell_value(cell: &DataType) -> String {
+	match cell {
+		DataType::String(s) => s.clone(),
+		DataType::Float(f) => f.to_string(),
+		DataType::Int(i) => i.to_string(),
+		DataType::Bool(b) => b.to_string(),
+		_ => String::new(),
+	}
+}
+
+fn parse_sheet(range: &Range) -> Result<Vec<MieleAppliance>, String> {
+	let Some(header_row) = range.rows().next() else { return Err("Failed to get row from Miele appliance availability spreadsheet.".to_string()) };
+	let headers: Vec<String> = header_row.iter().map(|cell| cell_value(cell).to_lowercase()).collect();
 
-			match best_match.next_available_date.as_str() {
-				"" => Ok(format!("Next avalability for {} is unknown.", best_match.model_number)),
-				_ => Ok(format!("Found: {}, Available: {}", best_match.model_number, best_match.next_available_date)),
+	Ok(range
+		.rows()
+		.skip(1)
+		.map(|row| {
+			let mut appliance = MieleAppliance::default();
+			for (i, cell) in row.iter().enumerate() {
+				let value = cell_value(cell);
+				match headers.get(i).map_or("", String::as_str) {
+					"timestamp" => appliance.timestamp = value,
+					"sku#" => appliance.sku = value,
+					"ean/upc" => appliance.upc = value,
+					"category" => appliance.category = value,
+					"subcategory" => appliance.subcategory = value,
+					"model number" => appliance.model_number = value,
+					"description" => appliance.description = value,
+					"current umrp/map" => appliance.current_umrp = value,
+					"new umrp/map" => appliance.new_umrp = value,
+					"dealer cost level" => appliance.dealer_cost_level = value,
+					"warehouse no" => appliance.warehouse_number = value,
+					"available qty" => appliance.available_qty = value,
+					"sales status" => appliance.sales_status = value,
+					"next available qty" => appliance.next_available_qty = value,
+					"next available date" => appliance.next_available_date = value,
+					_ => {}
+				}
 			}
+			appliance
+		})
+		.collect())
+}
+
+///
+/// Fuzzy match the requested model number against the model numbers and descriptions of a worksheet.
+///
+#[allow(clippy::cast_precision_loss)]
+fn best_match(appliances: &[MieleAppliance], model_number: &str) -> Result<MieleAppliance, String> {
+	let matcher = SkimMatcherV2::default();
+	let m_n: String = match decode(model_number) {
+		Ok(m_n) => m_n.to_lowercase().trim().chars().filter(|c| !c.is_whitespace()).collect(),
+		Err(_) => return Err("Cannot decode model number.".to_string()),
+	};
+
+	let mut best_match = MieleAppliance::default();
+	for miele_appliance in appliances {
+		let app_m_n: String = miele_appliance.model_number.to_lowercase().trim().chars().filter(|c| !c.is_whitespace()).collect();
+		let app_desc: String = miele_appliance.description.to_lowercase().trim().chars().filter(|c| !c.is_whitespace()).collect();
+
+		let model_number_score: f64 = matcher.fuzzy_match(app_m_n.as_str(), m_n.as_str()).map_or(0.0, |model_number_result| model_number_result as f64);
+		let description_score: f64 = matcher.fuzzy_match(app_desc.as_str(), m_n.as_str()).map_or(0.0, |description_result| description_result as f64);
+
+		let score = model_number_score + description_score;
+		if score > best_match.score {
+			best_match = miele_appliance.clone();
+			best_match.score = score;
 		}
-		Err(err) => Ok(format!("Error: {err}")),
 	}
+	Ok(best_match)
 }
 
 ///
 /// # Miele Appliance
 /// Struct to hold the data from the Miele Excel file.
 ///
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 struct MieleAppliance {
 	timestamp: String,
 	sku: String,
@@ -216,5 +812,75 @@ struct MieleAppliance {
 	sales_status: String,
 	next_available_qty: String,
 	next_available_date: String,
+	/// Language of `description`, empty if the source does not say.
+	language: String,
 	score: f64,
 }
+
+impl MieleAppliance {
+	///
+	/// Product metadata for this row, if the row has a description.
+	///
+	fn product(&self) -> Option<ProductInfo> {
+		if self.description.trim().is_empty() {
+			return None;
+		}
+		let mut product = ProductInfo::new(self.description.trim().to_string()).with_brand("Miele".to_string());
+		if !self.category.trim().is_empty() {
+			product = product.with_category(self.category.trim().to_string());
+		}
+		if !self.language.is_empty() {
+			product = product.with_language(&self.language);
+		}
+		Some(product)
+	}
+}
+
+impl CachedCatalog for MieleCatalog {
+	fn snapshot(&self) -> CatalogSnapshot {
+		let mut warehouses: Vec<String> = self.sheets.keys().cloned().collect();
+		warehouses.sort();
+		CatalogSnapshot {
+			manufacturer: "miele".to_string(),
+			loaded_at: self.loaded_at,
+			version: Some(self.version),
+			digest: Some(self.digest.clone()),
+			warehouses,
+			items: self.sheets.values().map(Vec::len).sum(),
+		}
+	}
+
+	fn models(&self, warehouse: Option<&str>) -> Vec<String> {
+		let mut models: Vec<String> = self.sheets.iter().filter(|(sheet, _)| warehouse.is_none_or(|warehouse| sheet.eq_ignore_ascii_case(warehouse))).flat_map(|(_, appliances)| appliances.iter().map(|appliance| appliance.model_number.trim().to_string())).filter(|model_number| !model_number.is_empty()).collect();
+		models.sort();
+		models.dedup();
+		models
+	}
+
+	fn item(&self, warehouse: &str, model_number: &str) -> Option<CatalogItem> {
+		let (sheet, appliances) = self.sheets.iter().find(|(sheet, _)| sheet.eq_ignore_ascii_case(warehouse))?;
+		let model_number = normalize_model(model_number);
+		let appliance = appliances.iter().find(|appliance| normalize_model(&appliance.model_number) == model_number)?;
+		let text = |value: &str| Some(value.trim().to_string()).filter(|value| !value.is_empty());
+		let quantity = |value: &str| value.trim().split('.').next().and_then(|whole| whole.parse::<u32>().ok());
+		let mut item = CatalogItem::new("miele".to_string(), sheet.clone(), appliance.model_number.trim().to_string());
+		item.description = text(&appliance.description);
+		item.category = text(&appliance.category);
+		item.available_qty = quantity(&appliance.available_qty);
+		item.next_available_qty = quantity(&appliance.next_available_qty);
+		item.next_available_date = parse_available_date(&appliance.next_available_date);
+		Some(item)
+	}
+}
+
+impl Footprint for MieleAppliance {
+	fn heap_bytes(&self) -> usize {
+		[&self.timestamp, &self.sku, &self.upc, &self.category, &self.subcategory, &self.model_number, &self.description, &self.current_umrp, &self.new_umrp, &self.dealer_cost_level, &self.warehouse_number, &self.available_qty, &self.sales_status, &self.next_available_qty, &self.next_available_date, &self.language].iter().map(|field| field.heap_bytes()).sum()
+	}
+}
+
+impl Footprint for MieleCatalog {
+	fn heap_bytes(&self) -> usize {
+		self.sheets.iter().map(|(warehouse, appliances)| warehouse.heap_bytes() + appliances.heap_bytes()).sum()
+	}
+}
@@ -0,0 +1,194 @@
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+use reqwest::header;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+use super::backend::{BackendAnswer, ManufacturerBackend};
+use super::{Availability, AvailabilityError, AvailabilityRequest, ProductInfo};
+use crate::http_client::{self, HttpClient};
+use crate::secrets::get_secret;
+use crate::stock::{self, StockKeys};
+use crate::telemetry;
+
+///
+/// How long before its expiry a dealer API token is no longer used.
+///
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+///
+/// # `MonogramConfig`
+/// The GE Appliances dealer API: the OAuth token endpoint, the availability check, which takes one line
+/// per model for the dealer `account` and the warehouse as ship-from location, and the secrets holding
+/// the OAuth client credentials. The endpoints come with the dealer's API access, so there is no default
+/// and lookups fail until `set_monogram_config` is called.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MonogramConfig {
+	pub token_url: String,
+	pub availability_url: String,
+	pub account: String,
+	pub client_id_secret: String,
+	pub client_secret_secret: String,
+}
+
+impl MonogramConfig {
+	///
+	/// # `MonogramConfig::new`
+	/// Dealer API configuration for `account`, reading the client credentials from the
+	/// `monogram-client-id` and `monogram-client-secret` secrets.
+	///
+	#[must_use]
+	pub fn new(token_url: String, availability_url: String, account: String) -> Self {
+		Self { token_url, availability_url, account, client_id_secret: "monogram-client-id".to_string(), client_secret_secret: "monogram-client-secret".to_string() }
+	}
+
+	#[must_use]
+	pub fn with_client_secrets(mut self, client_id_secret: String, client_secret_secret: String) -> Self {
+		self.client_id_secret = client_id_secret;
+		self.client_secret_secret = client_secret_secret;
+		self
+	}
+}
+
+fn config_slot() -> &'static RwLock<Option<MonogramConfig>> {
+	static CONFIG: OnceLock<RwLock<Option<MonogramConfig>>> = OnceLock::new();
+	CONFIG.get_or_init(|| RwLock::new(None))
+}
+
+///
+/// # `set_monogram_config`
+/// Set the GE Appliances dealer API to use. The token of the previous one is dropped.
+///
+pub fn set_monogram_config(config: MonogramConfig) {
+	*config_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = Some(config);
+	*token_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = None;
+}
+
+///
+/// # `monogram_config`
+/// The GE Appliances dealer API in use, `None` until one is set.
+///
+#[must_use]
+pub fn monogram_config() -> Option<MonogramConfig> {
+	config_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
+
+fn configured() -> Result<MonogramConfig, AvailabilityError> {
+	monogram_config().filter(|config| !config.account.trim().is_empty()).ok_or_else(|| AvailabilityError::Other("No GE dealer API configured, see `set_monogram_config`.".to_string()))
+}
+
+///
+/// # `MonogramBackend`
+/// GE Monogram lookups through the GE Appliances dealer API, registered as `monogram`. The token and the
+/// availability check go through `client`, the shared client unless one is given.
+///
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct MonogramBackend {
+	pub client: HttpClient,
+}
+
+impl MonogramBackend {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	#[must_use]
+	pub fn with_client(mut self, client: HttpClient) -> Self {
+		self.client = client;
+		self
+	}
+}
+
+#[async_trait::async_trait]
+impl ManufacturerBackend for MonogramBackend {
+	fn name(&self) -> &'static str {
+		"monogram"
+	}
+
+	async fn availability(&self, req: &AvailabilityRequest) -> Result<BackendAnswer, AvailabilityError> {
+		let (availability, product) = http_client::scope(self.client.clone(), monogram_lookup(req.clone())).await?;
+		Ok(BackendAnswer::new(availability).with_product(product))
+	}
+
+	async fn login(&self) -> Result<(), AvailabilityError> {
+		*token_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = None;
+		http_client::scope(self.client.clone(), monogram_token(&configured()?)).await?;
+		Ok(())
+	}
+}
+
+///
+/// # Monogram Lookup
+/// Gets the availability of a GE Monogram appliance from the request's warehouse together with its
+/// description.
+///
+/// # Errors
+/// Returns `AvailabilityError::VendorUnavailable` if the dealer API cannot be reached or its answer cannot
+/// be read, `AvailabilityError::SessionExpired` if it turns the token away, and `AvailabilityError::Other`
+/// without a configured dealer API or for a request without model number.
+///
+pub async fn monogram_lookup(req: AvailabilityRequest) -> Result<(Availability, Option<ProductInfo>), AvailabilityError> {
+	let today = req.business_today();
+	let config = configured()?;
+	let model_number = req.model_number.as_deref().map(|model_number| model_number.trim().to_uppercase()).ok_or_else(|| AvailabilityError::Other("No model number found.".to_string()))?;
+	let token = monogram_token(&config).await?;
+
+	let body = json!({
+		"account": config.account,
+		"shipFrom": req.warehouse.clone().unwrap_or_default(),
+		"lines": [{ "sku": model_number, "quantity": req.requested_quantity() }],
+	});
+	let response = http_client::client().post(&config.availability_url).bearer_auth(token).header(header::CONTENT_TYPE, "application/json").body(body.to_string()).send().await.map_err(|e| AvailabilityError::VendorUnavailable(format!("Failed to get GE availability: {e:?}")))?;
+	if http_client::auth_rejected(&response) {
+		*token_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = None;
+		return Err(AvailabilityError::SessionExpired);
+	}
+	match response.status() {
+		StatusCode::NOT_FOUND => return Ok((Availability::not_found(), None)),
+		status if !status.is_success() => return Err(AvailabilityError::VendorUnavailable(format!("Failed to get GE availability: {status}"))),
+		_ => {}
+	}
+	let data: Value = http_client::json(response).await?;
+	Ok(stock::json_stock(&data, &STOCK_KEYS, "Monogram", &model_number, today))
+}
+
+///
+/// Fields of the availability answer, for `stock::json_stock`. A line GE reports as invalid is not found.
+///
+const STOCK_KEYS: StockKeys = StockKeys::new("lines", "sku", "availableQuantity", "nextShipDate").with_not_found_status("invalid");
+
+///
+/// Bearer token of the dealer API and the instant it stops being valid.
+///
+fn token_slot() -> &'static RwLock<Option<(String, Instant)>> {
+	static TOKEN: OnceLock<RwLock<Option<(String, Instant)>>> = OnceLock::new();
+	TOKEN.get_or_init(|| RwLock::new(None))
+}
+
+///
+/// Get a bearer token with the OAuth client credentials grant, reusing the previous one until shortly
+/// before it expires.
+///
+async fn monogram_token(config: &MonogramConfig) -> Result<String, String> {
+	if let Some((token, _)) = token_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).as_ref().filter(|(_, expires)| Instant::now() < *expires) {
+		return Ok(token.clone());
+	}
+
+	telemetry::counter("vendor.login", 1, &[("manufacturer", "monogram".to_string())]);
+	let client_id = get_secret(&config.client_id_secret).await?;
+	let client_secret = get_secret(&config.client_secret_secret).await?;
+	let response = http_client::client().post(&config.token_url).form(&[("grant_type", "client_credentials"), ("client_id", client_id.expose()), ("client_secret", client_secret.expose())]).send().await.map_err(|e| format!("Failed to get GE dealer API token: {e:?}"))?;
+	if !response.status().is_success() {
+		return Err(format!("Failed to get GE dealer API token: {}", response.status()));
+	}
+	let data: Value = http_client::json(response).await.map_err(|e| format!("Failed to parse GE dealer API token: {e:?}"))?;
+	let token = data["access_token"].as_str().ok_or_else(|| "GE dealer API token response has no access_token.".to_string())?.to_string();
+	let expires_in = Duration::from_secs(data["expires_in"].as_u64().unwrap_or(300)).saturating_sub(TOKEN_EXPIRY_MARGIN);
+	*token_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = Some((token.clone(), Instant::now() + expires_in));
+	Ok(token)
+}
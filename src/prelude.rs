@@ -0,0 +1,25 @@
+//!
+//! # Prelude
+//! The supported API of the crate: the request and response types, the error, the backend and provider
+//! traits and, with `auth`, the service handle. `use eggersmann_app_server_appliance_availability::prelude::*;`
+//! brings it all in.
+//!
+//! ## Example
+//! ```no_run
+//! use eggersmann_app_server_appliance_availability::prelude::*;
+//!
+//! # async fn lookup() -> Result<(), String> {
+//! let req = AvailabilityRequest::new("bsh".to_string(), "houston".to_string(), "HBLP651RUC".to_string()).parse_manufacturer().get_warehouse().get_time().into_answered().await?;
+//! let response: AvailabilityResponse = req.response();
+//! # Ok(())
+//! # }
+//! ```
+//!
+
+#[cfg(feature = "auth")]
+#[cfg_attr(docsrs, doc(cfg(feature = "auth")))]
+pub use crate::access::AvailabilityService;
+pub use crate::backend::{register_backend, BackendAnswer, ManufacturerBackend};
+pub use crate::credentials::{set_credential_provider, CredentialProvider, SecretString};
+pub use crate::tokens::{set_token_store, TokenStore};
+pub use crate::{initialize, Availability, AvailabilityError, AvailabilityRequest, AvailabilityResponse, AvailabilityResponseV2, AvailabilityStatus, ProductInfo, Rejection, RejectionReason, RemediationHint, RequestOptions};
@@ -0,0 +1,224 @@
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+
+use chrono::{Datelike, Days, NaiveDate, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+
+use super::history::{parse_available_date, HistoryRecord};
+use super::{cache, AvailabilityRequest, AvailabilityResponse, ProductInfo};
+
+///
+/// How long an answer may be quoted to a customer unless configured otherwise.
+///
+pub const DEFAULT_QUOTE_VALIDITY: Duration = Duration::from_hours(24);
+
+fn validity_slot() -> &'static RwLock<Duration> {
+	static VALIDITY: OnceLock<RwLock<Duration>> = OnceLock::new();
+	VALIDITY.get_or_init(|| RwLock::new(DEFAULT_QUOTE_VALIDITY))
+}
+
+///
+/// # `set_quote_validity`
+/// Set how long an availability answer may be quoted after the vendor gave it.
+///
+pub fn set_quote_validity(validity: Duration) {
+	*validity_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = validity;
+}
+
+///
+/// # `quote_validity`
+/// How long an availability answer may be quoted after the vendor gave it.
+///
+#[must_use]
+pub fn quote_validity() -> Duration {
+	*validity_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+///
+/// # `Revalidation`
+/// Outcome of re-running the lookup behind a quote.
+///
+/// `still_valid` is true when the model is still found (or still not found) and the promised date did not
+/// change. Without a date on either side the availability texts are compared.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Revalidation {
+	pub still_valid: bool,
+	pub original: AvailabilityResponse,
+	pub current: AvailabilityResponse,
+}
+
+///
+/// # `revalidate`
+/// Ask the vendor again for the model, warehouse and showroom of `response`, bypassing the result cache,
+/// and report whether the original answer still holds.
+///
+/// # Errors
+/// Returns an error if the new lookup fails.
+///
+pub async fn revalidate(response: &AvailabilityResponse) -> Result<Revalidation, String> {
+	let mut req = AvailabilityRequest::new(String::new(), String::new(), String::new());
+	req.manufacturer = response.manufacturer.as_deref().and_then(|manufacturer| manufacturer.parse().ok());
+	req.showroom.clone_from(&response.showroom);
+	req.model_number.clone_from(&response.model_number);
+	req.warehouse.clone_from(&response.warehouse);
+	req.quantity = response.quantity;
+	req.requested_date = response.requested_date;
+	if let Some(key) = req.cache_key() {
+		cache::result_cache().invalidate(&key);
+	}
+
+	let current = req.get_time().into_answered().await?.response();
+	Ok(Revalidation { still_valid: answer_holds(response, &current), original: response.clone(), current })
+}
+
+fn answer_holds(original: &AvailabilityResponse, current: &AvailabilityResponse) -> bool {
+	if original.restricted.is_some() || current.restricted.is_some() {
+		return original.restricted == current.restricted;
+	}
+	let (original_v2, current_v2) = (original.v2(), current.v2());
+	if original_v2.found != current_v2.found {
+		return false;
+	}
+	let original_date = original.availability.as_deref().and_then(parse_available_date);
+	let current_date = current.availability.as_deref().and_then(parse_available_date);
+	match (original_date, current_date) {
+		(None, None) => original.availability == current.availability,
+		(original_date, current_date) => original_date == current_date,
+	}
+}
+
+///
+/// # `CustomerDatePolicy`
+/// How vendor dates are padded before they are shown to customers: `buffer_days` are added, then the date
+/// moves forward to the next `round_to` weekday. The default leaves dates unchanged.
+///
+/// ## Example
+/// ```
+/// use chrono::{NaiveDate, Weekday};
+/// use eggersmann_app_server_appliance_availability::quote::CustomerDatePolicy;
+///
+/// let policy = CustomerDatePolicy::new(7).with_round_to(Weekday::Mon);
+/// // Wednesday + 7 days is a Wednesday, rounded up to the following Monday.
+/// assert_eq!(policy.apply(NaiveDate::from_ymd_opt(2024, 3, 13).unwrap()), NaiveDate::from_ymd_opt(2024, 3, 25).unwrap());
+/// ```
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct CustomerDatePolicy {
+	pub buffer_days: u32,
+	pub round_to: Option<Weekday>,
+}
+
+impl CustomerDatePolicy {
+	///
+	/// # `CustomerDatePolicy::new`
+	/// Pad dates by `buffer_days` without rounding.
+	///
+	#[must_use]
+	pub const fn new(buffer_days: u32) -> Self {
+		Self { buffer_days, round_to: None }
+	}
+
+	#[must_use]
+	pub const fn with_round_to(mut self, round_to: Weekday) -> Self {
+		self.round_to = Some(round_to);
+		self
+	}
+
+	///
+	/// # `CustomerDatePolicy::apply`
+	/// The customer-facing date for a vendor date.
+	///
+	#[must_use]
+	pub fn apply(&self, date: NaiveDate) -> NaiveDate {
+		let padded = date.checked_add_days(Days::new(u64::from(self.buffer_days))).unwrap_or(date);
+		self.round_to.map_or(padded, |round_to| {
+			let days = (7 + round_to.num_days_from_monday() - padded.weekday().num_days_from_monday()) % 7;
+			padded.checked_add_days(Days::new(u64::from(days))).unwrap_or(padded)
+		})
+	}
+}
+
+fn policy_slot() -> &'static RwLock<CustomerDatePolicy> {
+	static POLICY: OnceLock<RwLock<CustomerDatePolicy>> = OnceLock::new();
+	POLICY.get_or_init(|| RwLock::new(CustomerDatePolicy::default()))
+}
+
+///
+/// # `set_customer_date_policy`
+/// Set the padding applied to dates in customer views.
+///
+pub fn set_customer_date_policy(policy: CustomerDatePolicy) {
+	*policy_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = policy;
+}
+
+///
+/// # `customer_date_policy`
+/// The padding applied to dates in customer views.
+///
+#[must_use]
+pub fn customer_date_policy() -> CustomerDatePolicy {
+	*policy_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+///
+/// # `CustomerView`
+/// The customer-facing form of an availability answer. `available_date` has the `CustomerDatePolicy`
+/// applied; the raw vendor answer stays on the `AvailabilityResponse` for internal use.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct CustomerView {
+	pub manufacturer: Option<String>,
+	pub model_number: Option<String>,
+	pub found: bool,
+	pub in_stock: bool,
+	pub available_date: Option<NaiveDate>,
+	pub message: String,
+	pub product: Option<ProductInfo>,
+	pub valid_until: Option<String>,
+}
+
+impl AvailabilityResponse {
+	///
+	/// # `AvailabilityResponse::customer_view`
+	/// The answer as it may be shown to a customer, with the configured `CustomerDatePolicy` applied.
+	///
+	#[must_use]
+	pub fn customer_view(&self) -> CustomerView {
+		self.customer_view_with(&customer_date_policy())
+	}
+
+	///
+	/// # `AvailabilityResponse::customer_view_with`
+	/// The customer view with an explicit date policy.
+	///
+	#[must_use]
+	pub fn customer_view_with(&self, policy: &CustomerDatePolicy) -> CustomerView {
+		let record = HistoryRecord::from_response(self, self.answered_at().unwrap_or_else(Utc::now));
+		let found = self.restricted.is_none() && record.as_ref().is_some_and(|record| record.found);
+		let in_stock = found && record.as_ref().is_some_and(|record| record.in_stock);
+		let available_date = if found && !in_stock { record.and_then(|record| record.available_date).map(|date| policy.apply(date)) } else { None };
+
+		let message = match (&self.restricted, found, in_stock, available_date) {
+			(Some(restricted), ..) => restricted.message(),
+			(None, false, ..) => self.v1(),
+			(None, true, true, _) => "Available now".to_string(),
+			(None, true, false, Some(date)) => format!("Estimated availability: {}", date.format("%m/%d/%Y")),
+			(None, true, false, None) => "Availability date unknown".to_string(),
+		};
+
+		CustomerView {
+			manufacturer: self.manufacturer.clone(),
+			model_number: self.model_number.clone(),
+			found,
+			in_stock,
+			available_date,
+			message,
+			product: self.product.clone(),
+			valid_until: self.valid_until().map(|valid_until| valid_until.to_rfc3339()),
+		}
+	}
+}
@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+use tokio::time::{sleep_until, Instant};
+
+///
+/// # `RateLimiter`
+/// Spaces calls out so that at most one call starts per `interval`.
+///
+#[derive(Debug)]
+pub struct RateLimiter {
+	interval: Duration,
+	next: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+	///
+	/// # `RateLimiter::new`
+	/// Create a limiter allowing one call per `interval`.
+	///
+	#[must_use]
+	pub const fn new(interval: Duration) -> Self {
+		Self { interval, next: Mutex::const_new(None) }
+	}
+
+	///
+	/// # `RateLimiter::acquire`
+	/// Wait until the next call is allowed to start.
+	///
+	pub async fn acquire(&self) {
+		let mut next = self.next.lock().await;
+		let now = Instant::now();
+		if let Some(at) = *next {
+			if at > now {
+				sleep_until(at).await;
+			}
+		}
+		*next = Some(Instant::now() + self.interval);
+	}
+}
@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+///
+/// # `RejectionReason`
+/// Why a vendor refused an order line it knows the model of.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum RejectionReason {
+	/// The sold-to account is on credit hold.
+	CreditHold,
+	/// The material is blocked for sales.
+	MaterialBlocked,
+	/// The material is discontinued and can no longer be ordered.
+	Discontinued,
+	/// Deliveries to the ship-to are blocked.
+	DeliveryBlocked,
+	/// The material is not sold in the sales area of the request.
+	NotInSalesArea,
+	/// A rejection code without a known meaning, see `Rejection::code`.
+	Unknown,
+}
+
+///
+/// SAP rejection codes and `ID/NUMBER` message codes of a sales order simulation with a known meaning.
+///
+const KNOWN_CODES: [(&str, RejectionReason); 12] = [("V1/154", RejectionReason::CreditHold), ("V1/155", RejectionReason::CreditHold), ("V1/156", RejectionReason::CreditHold), ("V1/028", RejectionReason::DeliveryBlocked), ("V1/391", RejectionReason::MaterialBlocked), ("V1/392", RejectionReason::MaterialBlocked), ("V1/393", RejectionReason::NotInSalesArea), ("M3/351", RejectionReason::NotInSalesArea), ("CH", RejectionReason::CreditHold), ("MB", RejectionReason::MaterialBlocked), ("DC", RejectionReason::Discontinued), ("DB", RejectionReason::DeliveryBlocked)];
+
+///
+/// Phrases of the message text that identify a reason when the code is not in `KNOWN_CODES`.
+///
+const KNOWN_PHRASES: [(&str, RejectionReason); 6] = [("credit", RejectionReason::CreditHold), ("discontinued", RejectionReason::Discontinued), ("delivery block", RejectionReason::DeliveryBlocked), ("is blocked", RejectionReason::MaterialBlocked), ("not defined for sales org", RejectionReason::NotInSalesArea), ("not maintained for sales", RejectionReason::NotInSalesArea)];
+
+impl RejectionReason {
+	///
+	/// # `RejectionReason::classify`
+	/// The reason for a vendor rejection code, falling back to the message text.
+	///
+	/// ## Example
+	/// ```
+	/// use eggersmann_app_server_appliance_availability::RejectionReason;
+	///
+	/// assert_eq!(RejectionReason::classify("V1/154", None), RejectionReason::CreditHold);
+	/// assert_eq!(RejectionReason::classify("ZZ/001", Some("Material HBLP651RUC is blocked")), RejectionReason::MaterialBlocked);
+	/// assert_eq!(RejectionReason::classify("ZZ/001", None), RejectionReason::Unknown);
+	/// ```
+	///
+	#[must_use]
+	pub fn classify(code: &str, message: Option<&str>) -> Self {
+		let code = code.trim().to_uppercase();
+		if let Some((_, reason)) = KNOWN_CODES.iter().find(|(known, _)| *known == code) {
+			return *reason;
+		}
+		let message = message.unwrap_or_default().to_lowercase();
+		KNOWN_PHRASES.iter().find(|(phrase, _)| message.contains(phrase)).map_or(Self::Unknown, |(_, reason)| *reason)
+	}
+
+	///
+	/// Text shown to users for the reason.
+	///
+	#[must_use]
+	pub const fn description(self) -> &'static str {
+		match self {
+			Self::CreditHold => "credit hold",
+			Self::MaterialBlocked => "material blocked for sales",
+			Self::Discontinued => "discontinued",
+			Self::DeliveryBlocked => "delivery block",
+			Self::NotInSalesArea => "not sold in this region",
+			Self::Unknown => "rejected by the vendor",
+		}
+	}
+}
+
+///
+/// # `Rejection`
+/// A vendor's refusal of the requested line, returned alongside the availability text.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Rejection {
+	pub reason: RejectionReason,
+	/// The vendor's own code, e.g. an SAP rejection reason or `ID/NUMBER` message code.
+	pub code: String,
+	/// The vendor's message text, if it sent one.
+	pub message: Option<String>,
+}
+
+impl Rejection {
+	///
+	/// # `Rejection::new`
+	/// Create a rejection, classifying the code and message with `RejectionReason::classify`.
+	///
+	#[must_use]
+	pub fn new(code: String, message: Option<String>) -> Self {
+		Self { reason: RejectionReason::classify(&code, message.as_deref()), code, message }
+	}
+
+	///
+	/// Text shown in place of the availability by the v1 response.
+	///
+	#[must_use]
+	pub fn message(&self) -> String {
+		format!("Line rejected by the vendor: {} ({})", self.reason.description(), self.code)
+	}
+}
@@ -0,0 +1,1021 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::annotations::Annotation;
+use super::calendar::business_calendar;
+use super::compat::V1_TIME_FORMAT;
+use super::history::parse_available_date;
+use super::inventory::InternalStock;
+use super::quote::quote_validity;
+use super::timezone::{business_today, showroom_local_time};
+use super::{AccountIssue, AvailabilityRequest, Rejection, RejectionReason, RestrictedItem, WarehouseDecision};
+
+///
+/// Legacy text returned by `v1()` when a lookup produced no availability.
+///
+pub const V1_NOT_FOUND: &str = "Model availablility not found.";
+
+///
+/// Texts vendor modules answer a model they do not know with: the v1 text and the `SubZero` cart line.
+///
+const NOT_FOUND_TEXTS: [&str; 2] = [V1_NOT_FOUND, "Error finding item."];
+
+///
+/// # `AvailabilityResponse`
+/// Internal result of an availability lookup.
+///
+/// Consumers pick the shape they need: `v1()` for the legacy string response used by the mobile app,
+/// `v2()` for the structured response used by the web app.
+///
+/// Marked `#[non_exhaustive]` so fields can be added as the response grows; construct it with
+/// `AvailabilityResponse::builder()` outside this crate.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct AvailabilityResponse {
+	pub manufacturer: Option<String>,
+	pub showroom: Option<String>,
+	pub model_number: Option<String>,
+	pub warehouse: Option<String>,
+	/// `requested_at` in `compat::V1_TIME_FORMAT`. It looks like a local time but is UTC.
+	#[deprecated(note = "use `requested_at`, or `local_time` for display")]
+	pub utc_time: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub requested_at: Option<DateTime<Utc>>,
+	pub availability: Option<String>,
+	pub availability_detail: Option<Availability>,
+	pub product: Option<ProductInfo>,
+	pub cached_at: Option<String>,
+	pub restricted: Option<RestrictedItem>,
+	pub warehouse_decision: Option<WarehouseDecision>,
+	pub rejection: Option<Rejection>,
+	pub account_issue: Option<AccountIssue>,
+	pub catalog_version: Option<CatalogVersion>,
+	pub request_id: Option<String>,
+	pub needed_by: Option<NaiveDate>,
+	/// Units the vendor was asked for, `None` for one.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub quantity: Option<u32>,
+	/// Delivery date the vendor was asked for, `None` for as soon as possible.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub requested_date: Option<NaiveDate>,
+	pub simulated: bool,
+	pub internal_stock: Option<InternalStock>,
+	pub annotations: Vec<Annotation>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub provenance: Option<FieldProvenance>,
+}
+
+impl AvailabilityResponse {
+	///
+	/// # `AvailabilityResponse::builder`
+	/// Start building an `AvailabilityResponse`.
+	///
+	/// ## Example
+	/// ```
+	/// use eggersmann_app_server_appliance_availability::AvailabilityResponse;
+	///
+	/// let res = AvailabilityResponse::builder().manufacturer("bsh").model_number("HBLP651RUC").availability("In stock").build();
+	/// assert_eq!(res.v1(), "In stock");
+	/// ```
+	///
+	#[must_use]
+	pub fn builder() -> AvailabilityResponseBuilder {
+		AvailabilityResponseBuilder::default()
+	}
+
+	///
+	/// # `AvailabilityResponse::from_request`
+	/// Build the response from a request that has been through `into_answered`.
+	///
+	#[must_use]
+	#[allow(deprecated)]
+	pub fn from_request(req: &AvailabilityRequest) -> Self {
+		let requested_at = req.looked_up_at();
+		Self {
+			manufacturer: req.manufacturer.as_ref().map(ToString::to_string),
+			showroom: req.showroom.clone(),
+			model_number: req.model_number.clone(),
+			warehouse: req.warehouse.clone(),
+			utc_time: requested_at.map(|requested_at| requested_at.format(V1_TIME_FORMAT).to_string()).or_else(|| req.utc_time.clone()),
+			requested_at,
+			availability: req.availability.clone(),
+			availability_detail: req.availability_detail.clone(),
+			product: req.product.clone(),
+			cached_at: req.cached_at.clone(),
+			restricted: req.restricted.clone(),
+			warehouse_decision: req.warehouse_decision.clone(),
+			rejection: req.rejection.clone(),
+			account_issue: req.account_issue.clone(),
+			catalog_version: req.catalog_version.clone(),
+			request_id: req.request_id.clone(),
+			needed_by: req.needed_by,
+			quantity: req.quantity,
+			requested_date: req.requested_date,
+			simulated: req.simulate,
+			internal_stock: req.internal_stock.clone(),
+			annotations: req.annotations.clone(),
+			provenance: req.provenance.clone(),
+		}
+	}
+
+	///
+	/// # `AvailabilityResponse::v1`
+	/// Legacy string-shaped response.
+	///
+	/// ## Outputs
+	/// String - The availability text exactly as the vendor module produced it, the restriction message
+	/// for a model that must not be quoted, the account issue blocking every lookup at the vendor, or the
+	/// rejection message for a line the vendor refused.
+	///
+	#[must_use]
+	pub fn v1(&self) -> String {
+		if let Some(restricted) = &self.restricted {
+			return restricted.message();
+		}
+		if let Some(account_issue) = &self.account_issue {
+			return account_issue.message();
+		}
+		if let Some(rejection) = &self.rejection {
+			return rejection.message();
+		}
+		self.availability.clone().unwrap_or_else(|| V1_NOT_FOUND.to_string())
+	}
+
+	///
+	/// # `AvailabilityResponse::answered_at`
+	/// When the vendor gave this answer: the time it was cached for a cached answer, the lookup time
+	/// otherwise.
+	///
+	#[must_use]
+	pub fn answered_at(&self) -> Option<DateTime<Utc>> {
+		self.cached_at.as_deref().and_then(|cached_at| DateTime::parse_from_rfc3339(cached_at).ok()).map(|cached_at| cached_at.with_timezone(&Utc)).or_else(|| self.looked_up_at())
+	}
+
+	///
+	/// # `AvailabilityResponse::looked_up_at`
+	/// When the lookup was made: `requested_at`, or the `utc_time` of a response that has no
+	/// `requested_at`.
+	///
+	#[must_use]
+	#[allow(deprecated)]
+	pub fn looked_up_at(&self) -> Option<DateTime<Utc>> {
+		self.requested_at.or_else(|| self.utc_time.as_deref().and_then(|utc_time| NaiveDateTime::parse_from_str(utc_time, V1_TIME_FORMAT).ok()).map(|utc_time| utc_time.and_utc()))
+	}
+
+	///
+	/// # `AvailabilityResponse::local_time`
+	/// When the lookup was made on the showroom's wall clock, for display.
+	///
+	#[must_use]
+	pub fn local_time(&self) -> Option<String> {
+		self.looked_up_at().map(|looked_up_at| showroom_local_time(self.showroom.as_deref(), looked_up_at))
+	}
+
+	///
+	/// # `AvailabilityResponse::provenance`
+	/// Where the availability and product of this response came from, as recorded when each was filled
+	/// in: `Live` from the vendor, `Snapshot` from a catalog such as the Miele report, `Cache` with its age
+	/// at the time of the lookup, or `Simulated`. A field without a recorded source has none.
+	///
+	/// ## Example
+	/// ```
+	/// use eggersmann_app_server_appliance_availability::{AvailabilityResponse, FieldProvenance, Provenance};
+	///
+	/// let response = AvailabilityResponse::builder().availability("Found: HBLP651RUC, in stock").provenance(FieldProvenance::new().with_availability(Provenance::Cache { age_secs: 60 })).build();
+	/// assert_eq!(response.provenance().availability, Some(Provenance::Cache { age_secs: 60 }));
+	/// assert_eq!(response.provenance().product, None);
+	/// ```
+	///
+	#[must_use]
+	pub fn provenance(&self) -> FieldProvenance {
+		self.provenance.clone().unwrap_or_default()
+	}
+
+	///
+	/// # `AvailabilityResponse::valid_until`
+	/// Until when this answer may be quoted to a customer, `quote_validity()` after it was given. Past
+	/// that, run `quote::revalidate` first.
+	///
+	#[must_use]
+	pub fn valid_until(&self) -> Option<DateTime<Utc>> {
+		let validity = chrono::Duration::from_std(quote_validity()).ok()?;
+		self.answered_at().map(|answered_at| answered_at + validity)
+	}
+
+	///
+	/// # `AvailabilityResponse::found`
+	/// Whether the vendor knew the model, from the status of `availability_detail`. A response with only the
+	/// vendor text, such as one stored before the status was kept, is read with `Availability::from_text`.
+	///
+	#[must_use]
+	pub fn found(&self) -> bool {
+		match (&self.availability_detail, &self.availability) {
+			(Some(availability), _) => !availability.is_not_found(),
+			(None, Some(raw)) => !Availability::from_text(raw, NaiveDate::MIN).is_not_found(),
+			(None, None) => false,
+		}
+	}
+
+	///
+	/// # `AvailabilityResponse::meets_needed_by`
+	/// Whether the vendor can ship by `needed_by`: the promised date, moved to the vendor's next business
+	/// day when it falls on a weekend or holiday, is on or before it. `None` without a needed-by date or a
+	/// promised date, or when the model was not found, restricted or rejected.
+	///
+	#[must_use]
+	pub fn meets_needed_by(&self) -> Option<bool> {
+		let needed_by = self.needed_by?;
+		if self.restricted.is_some() || self.account_issue.is_some() || self.rejection.is_some() {
+			return None;
+		}
+		if !self.found() {
+			return None;
+		}
+		let availability = self.availability.as_deref()?;
+		let calendar = business_calendar(self.manufacturer.as_deref().unwrap_or_default());
+		let complete_on = self.availability_detail.as_ref().and_then(Availability::complete_on);
+		complete_on.or_else(|| parse_available_date(availability)).map(|date| calendar.next_business_day(date) <= needed_by)
+	}
+
+	///
+	/// # `AvailabilityResponse::meets_quantity`
+	/// Whether all the units asked for are in stock on the day of the answer: the confirmed tranches due by
+	/// then add up to `quantity`, or the vendor's stock count covers it. `None` when no quantity was asked
+	/// for, the vendor gave no count, or the model was not found, restricted or rejected.
+	///
+	/// ## Example
+	/// ```
+	/// use eggersmann_app_server_appliance_availability::{Availability, AvailabilityResponse, AvailabilityStatus};
+	///
+	/// let three = Availability::new(AvailabilityStatus::InStock, "In stock: 3".to_string()).with_quantity(3);
+	/// let res = AvailabilityResponse::builder().availability("In stock: 3").availability_detail(three).quantity(4).build();
+	/// assert_eq!(res.meets_quantity(), Some(false));
+	/// ```
+	///
+	#[must_use]
+	pub fn meets_quantity(&self) -> Option<bool> {
+		let quantity = self.quantity?;
+		if self.restricted.is_some() || self.account_issue.is_some() || self.rejection.is_some() {
+			return None;
+		}
+		let availability = self.availability_detail.as_ref()?;
+		if !availability.tranches.is_empty() {
+			let today = business_today(self.showroom.as_deref(), self.answered_at().unwrap_or_else(Utc::now));
+			return Some(availability.quantity_by(today) >= quantity);
+		}
+		match availability.status {
+			AvailabilityStatus::InStock => availability.quantity.map(|in_stock| in_stock >= quantity),
+			AvailabilityStatus::Backordered | AvailabilityStatus::Discontinued => Some(false),
+			AvailabilityStatus::NotFound | AvailabilityStatus::Unknown => None,
+		}
+	}
+
+	///
+	/// # `AvailabilityResponse::v2`
+	/// Structured response.
+	///
+	#[must_use]
+	pub fn v2(&self) -> AvailabilityResponseV2 {
+		AvailabilityResponseV2 {
+			manufacturer: self.manufacturer.clone(),
+			showroom: self.showroom.clone(),
+			model_number: self.model_number.clone(),
+			warehouse: self.warehouse.clone(),
+			checked_at: self.looked_up_at().map(|looked_up_at| looked_up_at.format(V1_TIME_FORMAT).to_string()),
+			checked_at_local: self.local_time(),
+			found: self.found(),
+			message: self.availability.clone(),
+			availability: self.availability_detail.clone(),
+			product: self.product.clone(),
+			cached_at: self.cached_at.clone(),
+			restricted: self.restricted.clone(),
+			valid_until: self.valid_until().map(|valid_until| valid_until.to_rfc3339()),
+			warehouse_decision: self.warehouse_decision.clone(),
+			rejection: self.rejection.clone(),
+			account_issue: self.account_issue.clone(),
+			catalog_version: self.catalog_version.clone(),
+			request_id: self.request_id.clone(),
+			needed_by: self.needed_by,
+			meets_needed_by: self.meets_needed_by(),
+			requested_quantity: self.quantity,
+			meets_quantity: self.meets_quantity(),
+			requested_date: self.requested_date,
+			simulated: self.simulated,
+			provenance: Some(self.provenance()).filter(|provenance| provenance.availability.is_some() || provenance.product.is_some()),
+			internal_stock: self.internal_stock.clone(),
+			annotations: self.annotations.clone(),
+		}
+	}
+}
+
+///
+/// # `AvailabilityResponseV2`
+/// Structured availability response.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct AvailabilityResponseV2 {
+	pub manufacturer: Option<String>,
+	pub showroom: Option<String>,
+	pub model_number: Option<String>,
+	pub warehouse: Option<String>,
+	pub checked_at: Option<String>,
+	/// `checked_at` on the showroom's wall clock.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub checked_at_local: Option<String>,
+	pub found: bool,
+	pub message: Option<String>,
+	/// `message` read into status, date and quantity.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub availability: Option<Availability>,
+	pub product: Option<ProductInfo>,
+	pub cached_at: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub restricted: Option<RestrictedItem>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub valid_until: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub warehouse_decision: Option<WarehouseDecision>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub rejection: Option<Rejection>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub account_issue: Option<AccountIssue>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub catalog_version: Option<CatalogVersion>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub request_id: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub needed_by: Option<NaiveDate>,
+	/// See `AvailabilityResponse::meets_needed_by`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub meets_needed_by: Option<bool>,
+	/// Units the vendor was asked for, `None` for one.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub requested_quantity: Option<u32>,
+	/// See `AvailabilityResponse::meets_quantity`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub meets_quantity: Option<bool>,
+	/// Delivery date the vendor was asked for, `None` for as soon as possible.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub requested_date: Option<NaiveDate>,
+	/// The answer came from the simulation catalog, not the vendor.
+	#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+	pub simulated: bool,
+	/// See `AvailabilityResponse::provenance`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub provenance: Option<FieldProvenance>,
+	/// Our own on-hand and inbound stock, next to the vendor's `availability`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub internal_stock: Option<InternalStock>,
+	/// Notes showroom managers attached to the model.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub annotations: Vec<Annotation>,
+}
+
+///
+/// # `Provenance`
+/// Where a field of a response came from.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Provenance {
+	/// Asked from the vendor for this lookup.
+	Live,
+	/// Taken from the result cache, answered by the vendor `age_secs` before the lookup.
+	Cache { age_secs: u64 },
+	/// Read from the vendor catalog snapshot with this version.
+	Snapshot { version: u64 },
+	/// Made up by the simulation catalog.
+	Simulated,
+}
+
+///
+/// # `FieldProvenance`
+/// The `Provenance` of each field of a response that has a value.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct FieldProvenance {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub availability: Option<Provenance>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub product: Option<Provenance>,
+}
+
+impl FieldProvenance {
+	#[must_use]
+	pub const fn new() -> Self {
+		Self { availability: None, product: None }
+	}
+
+	#[must_use]
+	pub const fn with_availability(mut self, availability: Provenance) -> Self {
+		self.availability = Some(availability);
+		self
+	}
+
+	#[must_use]
+	pub const fn with_product(mut self, product: Provenance) -> Self {
+		self.product = Some(product);
+		self
+	}
+
+	///
+	/// `source` for the availability and the product that were filled in, `None` if neither was.
+	///
+	pub(crate) fn recorded(source: &Provenance, availability: bool, product: bool) -> Option<Self> {
+		(availability || product).then(|| Self { availability: availability.then(|| source.clone()), product: product.then(|| source.clone()) })
+	}
+}
+
+///
+/// # `ProductInfo`
+/// Human-readable product metadata taken from the vendor catalog.
+///
+/// `language` is the language of `name` when the vendor says, e.g. `es` when a Spanish description was
+/// asked for and found. `names` holds every description the vendor returned, by language.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ProductInfo {
+	pub name: String,
+	pub category: Option<String>,
+	pub brand: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub language: Option<String>,
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub names: BTreeMap<String, String>,
+}
+
+impl ProductInfo {
+	///
+	/// # `ProductInfo::new`
+	/// Create product metadata with only the name set.
+	///
+	#[must_use]
+	pub const fn new(name: String) -> Self {
+		Self { name, category: None, brand: None, language: None, names: BTreeMap::new() }
+	}
+
+	#[must_use]
+	pub fn with_category(mut self, category: String) -> Self {
+		self.category = Some(category);
+		self
+	}
+
+	#[must_use]
+	pub fn with_brand(mut self, brand: String) -> Self {
+		self.brand = Some(brand);
+		self
+	}
+
+	///
+	/// # `ProductInfo::with_language`
+	/// Mark `name` as written in `language`, a two-letter code such as `es`.
+	///
+	#[must_use]
+	pub fn with_language(mut self, language: &str) -> Self {
+		let language = language.to_lowercase();
+		self.names.insert(language.clone(), self.name.clone());
+		self.language = Some(language);
+		self
+	}
+
+	///
+	/// # `ProductInfo::with_translation`
+	/// Add the description in another `language`, keeping `name`.
+	///
+	#[must_use]
+	pub fn with_translation(mut self, language: &str, name: String) -> Self {
+		self.names.insert(language.to_lowercase(), name);
+		self
+	}
+
+	///
+	/// # `ProductInfo::name_in`
+	/// The description in `language`, `None` if the vendor returned none in it.
+	///
+	#[must_use]
+	pub fn name_in(&self, language: &str) -> Option<&str> {
+		self.names.get(&language.to_lowercase()).map(String::as_str)
+	}
+}
+
+///
+/// # `CatalogVersion`
+/// Which download of a vendor report an answer was read from.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct CatalogVersion {
+	/// Sequence number of the report among the distinct reports downloaded.
+	pub version: u64,
+	/// Hex SHA-256 digest of the report file.
+	pub digest: String,
+}
+
+impl CatalogVersion {
+	///
+	/// # `CatalogVersion::new`
+	/// Create a catalog version.
+	///
+	#[must_use]
+	pub const fn new(version: u64, digest: String) -> Self {
+		Self { version, digest }
+	}
+}
+
+///
+/// # `AvailabilityStatus`
+/// What an availability answer means, whichever vendor gave it.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum AvailabilityStatus {
+	InStock,
+	Backordered,
+	Discontinued,
+	/// The vendor does not know the model.
+	NotFound,
+	/// The answer could not be interpreted, see `Availability::raw`.
+	Unknown,
+}
+
+///
+/// # `Availability`
+/// An availability answer read into status, date and quantity, next to the vendor text it came from.
+///
+/// ## Example
+/// ```
+/// use chrono::NaiveDate;
+/// use eggersmann_app_server_appliance_availability::{Availability, AvailabilityStatus};
+///
+/// let today = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+/// let availability = Availability::from_text("Found: H7880BP, Available: 06/20/2024", today);
+/// assert_eq!(availability.status, AvailabilityStatus::Backordered);
+/// assert_eq!(availability.available_date, NaiveDate::from_ymd_opt(2024, 6, 20));
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Availability {
+	pub status: AvailabilityStatus,
+	pub available_date: Option<NaiveDate>,
+	pub quantity: Option<u32>,
+	/// The availability text as the vendor module produced it.
+	pub raw: String,
+	/// Deliveries the vendor confirmed the requested quantity in, earliest first. Empty unless the vendor
+	/// reports them; only BSH does.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub tranches: Vec<Tranche>,
+	/// The line of the vendor's order simulation the answer was read from, see `OrderLine`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub order_line: Option<OrderLine>,
+	/// The model the vendor answered for, which can differ from the requested one where the vendor matches
+	/// models loosely, as the Miele report does. `None` when the answer does not say.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub model_number: Option<String>,
+}
+
+///
+/// # `OrderLine`
+/// A simulated order line as the vendor answered it, with everything the availability is not read from.
+/// Only BSH simulates orders.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct OrderLine {
+	pub item_number: Option<String>,
+	pub material: Option<String>,
+	pub requested_quantity: Option<u32>,
+	pub confirmed_quantity: Option<u32>,
+	/// The plant the line would ship from.
+	pub plant: Option<String>,
+	/// The net price of the line as the decimal the vendor sent, e.g. `1299.00`. Only kept for a request
+	/// that asked for pricing on behalf of a user allowed to see it.
+	pub net_price: Option<String>,
+	pub currency: Option<String>,
+	pub delivery_date: Option<NaiveDate>,
+	/// Every schedule line of the item, including those nothing was confirmed on.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub schedule: Vec<ScheduleLine>,
+}
+
+///
+/// # `ScheduleLine`
+/// One schedule line of an `OrderLine`: the quantity asked for and confirmed on a date.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ScheduleLine {
+	pub requested_quantity: Option<u32>,
+	pub confirmed_quantity: Option<u32>,
+	pub date: Option<NaiveDate>,
+}
+
+///
+/// # `Tranche`
+/// Part of the requested quantity the vendor confirmed for one date.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Tranche {
+	pub quantity: u32,
+	pub date: NaiveDate,
+}
+
+impl Tranche {
+	#[must_use]
+	pub const fn new(quantity: u32, date: NaiveDate) -> Self {
+		Self { quantity, date }
+	}
+}
+
+impl Availability {
+	///
+	/// # `Availability::new`
+	/// Create an availability without date or quantity.
+	///
+	#[must_use]
+	pub const fn new(status: AvailabilityStatus, raw: String) -> Self {
+		Self { status, available_date: None, quantity: None, raw, tranches: Vec::new(), order_line: None, model_number: None }
+	}
+
+	#[must_use]
+	pub const fn with_available_date(mut self, available_date: NaiveDate) -> Self {
+		self.available_date = Some(available_date);
+		self
+	}
+
+	#[must_use]
+	pub const fn with_quantity(mut self, quantity: u32) -> Self {
+		self.quantity = Some(quantity);
+		self
+	}
+
+	#[must_use]
+	pub fn with_order_line(mut self, order_line: Option<OrderLine>) -> Self {
+		self.order_line = order_line;
+		self
+	}
+
+	#[must_use]
+	pub fn with_model_number(mut self, model_number: &str) -> Self {
+		self.model_number = Some(model_number.trim().to_string());
+		self
+	}
+
+	///
+	/// # `Availability::without_pricing`
+	/// The availability with the net price and currency of its order line left out.
+	///
+	#[must_use]
+	pub fn without_pricing(mut self) -> Self {
+		if let Some(order_line) = self.order_line.as_mut() {
+			order_line.net_price = None;
+			order_line.currency = None;
+		}
+		self
+	}
+
+	///
+	/// # `Availability::with_tranches`
+	/// The availability of a split confirmation: the quantity is the sum of `tranches`, and while part of it
+	/// is still to come after `today` the model is backordered until the last tranche. Tranches of the same
+	/// date are merged. No tranches leave the availability as it is.
+	///
+	/// ## Example
+	/// ```
+	/// use chrono::NaiveDate;
+	/// use eggersmann_app_server_appliance_availability::{Availability, AvailabilityStatus, Tranche};
+	///
+	/// let today = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+	/// let later = NaiveDate::from_ymd_opt(2024, 7, 12).unwrap();
+	/// let availability = Availability::from_text("Found: HBLP651RUC, Available: 06/01/2024", today).with_tranches(vec![Tranche::new(1, today), Tranche::new(2, later)], today);
+	/// assert_eq!(availability.status, AvailabilityStatus::Backordered);
+	/// assert_eq!(availability.available_date, Some(later));
+	/// assert_eq!(availability.quantity_by(today), 1);
+	/// assert_eq!(availability.quantity, Some(3));
+	/// ```
+	///
+	#[must_use]
+	pub fn with_tranches(mut self, mut tranches: Vec<Tranche>, today: NaiveDate) -> Self {
+		if tranches.is_empty() {
+			return self;
+		}
+		tranches.sort_by_key(|tranche| tranche.date);
+		tranches.dedup_by(|later, earlier| {
+			let same = later.date == earlier.date;
+			if same {
+				earlier.quantity += later.quantity;
+			}
+			same
+		});
+		self.tranches = tranches;
+		self.quantity = Some(self.tranches.iter().map(|tranche| tranche.quantity).sum());
+		if matches!(self.status, AvailabilityStatus::InStock | AvailabilityStatus::Backordered | AvailabilityStatus::Unknown) {
+			let complete_on = self.complete_on();
+			self.status = if complete_on.is_some_and(|date| date <= today) { AvailabilityStatus::InStock } else { AvailabilityStatus::Backordered };
+			self.available_date = complete_on;
+		}
+		self
+	}
+
+	///
+	/// # `Availability::quantity_by`
+	/// How much of the confirmed quantity arrives on or before `date`. Without tranches, the quantity of an
+	/// answer in stock by then.
+	///
+	#[must_use]
+	pub fn quantity_by(&self, date: NaiveDate) -> u32 {
+		if self.tranches.is_empty() {
+			let in_stock = self.status == AvailabilityStatus::InStock && self.available_date.is_none_or(|available| available <= date);
+			return if in_stock { self.quantity.unwrap_or(1) } else { 0 };
+		}
+		self.tranches.iter().filter(|tranche| tranche.date <= date).map(|tranche| tranche.quantity).sum()
+	}
+
+	///
+	/// # `Availability::complete_on`
+	/// The date of the last tranche, when the whole confirmed quantity has arrived.
+	///
+	#[must_use]
+	pub fn complete_on(&self) -> Option<NaiveDate> {
+		self.tranches.last().map(|tranche| tranche.date)
+	}
+
+	///
+	/// # `Availability::from_text`
+	/// Read a vendor availability text: the first date in it decides between in stock and backordered
+	/// relative to `today`, otherwise wording such as `in stock` or `discontinued` does.
+	///
+	#[must_use]
+	pub fn from_text(raw: &str, today: NaiveDate) -> Self {
+		if NOT_FOUND_TEXTS.contains(&raw) {
+			return Self::new(AvailabilityStatus::NotFound, raw.to_string());
+		}
+		let lowercase = raw.to_lowercase();
+		if lowercase.contains("discontinued") {
+			return Self::new(AvailabilityStatus::Discontinued, raw.to_string());
+		}
+		if let Some(date) = parse_available_date(raw) {
+			let status = if date <= today { AvailabilityStatus::InStock } else { AvailabilityStatus::Backordered };
+			return Self::new(status, raw.to_string()).with_available_date(date);
+		}
+		if lowercase.contains("in stock") || lowercase.contains("available now") {
+			return Self::new(AvailabilityStatus::InStock, raw.to_string());
+		}
+		Self::new(AvailabilityStatus::Unknown, raw.to_string())
+	}
+
+	///
+	/// The availability of a vendor stock answer for `model_number`: `on_hand` units in stock, or else more
+	/// arriving on `next_date`, in stock if that is not after `today`. An answer with neither is `Unknown`.
+	/// The v1 text is written from them, and `model_number` is kept as the model answered for.
+	///
+	pub(crate) fn stock(model_number: &str, on_hand: Option<u32>, next_date: Option<NaiveDate>, today: NaiveDate) -> Self {
+		let availability = match (on_hand, next_date) {
+			(Some(on_hand), _) => Self::new(AvailabilityStatus::InStock, format!("Found: {model_number}, In stock: {on_hand}")).with_quantity(on_hand),
+			(None, Some(date)) => {
+				let status = if date <= today { AvailabilityStatus::InStock } else { AvailabilityStatus::Backordered };
+				Self::new(status, format!("Found: {model_number}, Available: {}", date.format("%m/%d/%Y"))).with_available_date(date)
+			}
+			(None, None) => Self::new(AvailabilityStatus::Unknown, format!("Next availability for {model_number} is unknown.")),
+		};
+		availability.with_model_number(model_number)
+	}
+
+	///
+	/// # `Availability::is_not_found`
+	/// Whether the vendor does not know the model.
+	///
+	#[must_use]
+	pub fn is_not_found(&self) -> bool {
+		self.status == AvailabilityStatus::NotFound
+	}
+
+	///
+	/// The availability of a model the vendor does not know, with the v1 text.
+	///
+	pub(crate) fn not_found() -> Self {
+		Self::new(AvailabilityStatus::NotFound, V1_NOT_FOUND.to_string())
+	}
+
+	///
+	/// The availability with the status of a vendor rejection applied: a discontinued model is discontinued
+	/// whatever date the vendor printed.
+	///
+	#[must_use]
+	pub fn with_rejection(mut self, rejection: Option<&Rejection>) -> Self {
+		if rejection.is_some_and(|rejection| rejection.reason == RejectionReason::Discontinued) {
+			self.status = AvailabilityStatus::Discontinued;
+		}
+		self
+	}
+}
+
+///
+/// # `AvailabilityResponseBuilder`
+/// Builder for `AvailabilityResponse`.
+///
+#[derive(Debug, Clone, Default)]
+pub struct AvailabilityResponseBuilder {
+	manufacturer: Option<String>,
+	showroom: Option<String>,
+	model_number: Option<String>,
+	warehouse: Option<String>,
+	requested_at: Option<DateTime<Utc>>,
+	availability: Option<String>,
+	availability_detail: Option<Availability>,
+	product: Option<ProductInfo>,
+	cached_at: Option<String>,
+	restricted: Option<RestrictedItem>,
+	warehouse_decision: Option<WarehouseDecision>,
+	rejection: Option<Rejection>,
+	account_issue: Option<AccountIssue>,
+	catalog_version: Option<CatalogVersion>,
+	request_id: Option<String>,
+	needed_by: Option<NaiveDate>,
+	quantity: Option<u32>,
+	requested_date: Option<NaiveDate>,
+	simulated: bool,
+	internal_stock: Option<InternalStock>,
+	annotations: Vec<Annotation>,
+	provenance: Option<FieldProvenance>,
+}
+
+impl AvailabilityResponseBuilder {
+	#[must_use]
+	pub fn manufacturer(mut self, manufacturer: impl Into<String>) -> Self {
+		self.manufacturer = Some(manufacturer.into());
+		self
+	}
+
+	#[must_use]
+	pub fn showroom(mut self, showroom: impl Into<String>) -> Self {
+		self.showroom = Some(showroom.into());
+		self
+	}
+
+	#[must_use]
+	pub fn model_number(mut self, model_number: impl Into<String>) -> Self {
+		self.model_number = Some(model_number.into());
+		self
+	}
+
+	#[must_use]
+	pub fn warehouse(mut self, warehouse: impl Into<String>) -> Self {
+		self.warehouse = Some(warehouse.into());
+		self
+	}
+
+	#[must_use]
+	#[deprecated(note = "use `requested_at`")]
+	pub fn utc_time(mut self, utc_time: impl Into<String>) -> Self {
+		self.requested_at = NaiveDateTime::parse_from_str(&utc_time.into(), V1_TIME_FORMAT).ok().map(|utc_time| utc_time.and_utc());
+		self
+	}
+
+	#[must_use]
+	pub const fn requested_at(mut self, requested_at: DateTime<Utc>) -> Self {
+		self.requested_at = Some(requested_at);
+		self
+	}
+
+	#[must_use]
+	pub fn availability(mut self, availability: impl Into<String>) -> Self {
+		self.availability = Some(availability.into());
+		self
+	}
+
+	#[must_use]
+	pub fn availability_detail(mut self, availability_detail: Availability) -> Self {
+		self.availability_detail = Some(availability_detail);
+		self
+	}
+
+	#[must_use]
+	pub fn product(mut self, product: ProductInfo) -> Self {
+		self.product = Some(product);
+		self
+	}
+
+	#[must_use]
+	pub fn cached_at(mut self, cached_at: impl Into<String>) -> Self {
+		self.cached_at = Some(cached_at.into());
+		self
+	}
+
+	#[must_use]
+	pub fn restricted(mut self, restricted: RestrictedItem) -> Self {
+		self.restricted = Some(restricted);
+		self
+	}
+
+	#[must_use]
+	pub fn warehouse_decision(mut self, warehouse_decision: WarehouseDecision) -> Self {
+		self.warehouse_decision = Some(warehouse_decision);
+		self
+	}
+
+	#[must_use]
+	pub fn rejection(mut self, rejection: Rejection) -> Self {
+		self.rejection = Some(rejection);
+		self
+	}
+
+	#[must_use]
+	pub fn account_issue(mut self, account_issue: AccountIssue) -> Self {
+		self.account_issue = Some(account_issue);
+		self
+	}
+
+	#[must_use]
+	pub fn catalog_version(mut self, catalog_version: CatalogVersion) -> Self {
+		self.catalog_version = Some(catalog_version);
+		self
+	}
+
+	#[must_use]
+	pub fn request_id(mut self, request_id: impl Into<String>) -> Self {
+		self.request_id = Some(request_id.into());
+		self
+	}
+
+	#[must_use]
+	pub const fn needed_by(mut self, needed_by: NaiveDate) -> Self {
+		self.needed_by = Some(needed_by);
+		self
+	}
+
+	#[must_use]
+	pub const fn quantity(mut self, quantity: u32) -> Self {
+		self.quantity = Some(quantity);
+		self
+	}
+
+	#[must_use]
+	pub const fn requested_date(mut self, requested_date: NaiveDate) -> Self {
+		self.requested_date = Some(requested_date);
+		self
+	}
+
+	#[must_use]
+	pub const fn simulated(mut self, simulated: bool) -> Self {
+		self.simulated = simulated;
+		self
+	}
+
+	#[must_use]
+	pub fn internal_stock(mut self, internal_stock: InternalStock) -> Self {
+		self.internal_stock = Some(internal_stock);
+		self
+	}
+
+	#[must_use]
+	pub fn annotation(mut self, annotation: Annotation) -> Self {
+		self.annotations.push(annotation);
+		self
+	}
+
+	#[must_use]
+	pub const fn provenance(mut self, provenance: FieldProvenance) -> Self {
+		self.provenance = Some(provenance);
+		self
+	}
+
+	#[must_use]
+	#[allow(deprecated)]
+	pub fn build(self) -> AvailabilityResponse {
+		AvailabilityResponse {
+			manufacturer: self.manufacturer,
+			showroom: self.showroom,
+			model_number: self.model_number,
+			warehouse: self.warehouse,
+			utc_time: self.requested_at.map(|requested_at| requested_at.format(V1_TIME_FORMAT).to_string()),
+			requested_at: self.requested_at,
+			availability: self.availability,
+			availability_detail: self.availability_detail,
+			product: self.product,
+			cached_at: self.cached_at,
+			restricted: self.restricted,
+			warehouse_decision: self.warehouse_decision,
+			rejection: self.rejection,
+			account_issue: self.account_issue,
+			catalog_version: self.catalog_version,
+			request_id: self.request_id,
+			needed_by: self.needed_by,
+			quantity: self.quantity,
+			requested_date: self.requested_date,
+			simulated: self.simulated,
+			internal_stock: self.internal_stock,
+			annotations: self.annotations,
+			provenance: self.provenance,
+		}
+	}
+}
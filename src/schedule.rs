@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+use chrono::{DateTime, Datelike, Days, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+
+use super::timezone::showroom_time_zone;
+
+///
+/// # `Workload`
+/// How heavy a piece of background work is, which decides the window it may run in.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Workload {
+	/// Watch checks, session keep-alive and secret refreshes, run during business hours.
+	Routine,
+	/// Bulk lookups and syncs, run in the overnight window.
+	Bulk,
+}
+
+///
+/// # `SchedulePolicy`
+/// When background work may talk to the vendors, in the showroom's business time zone. Routine work
+/// runs between `opens` and `closes` on `business_days`, bulk work between `overnight_start` and
+/// `overnight_end`. A window that is not set does not restrict its work.
+///
+/// ## Example
+/// ```
+/// use chrono::{NaiveTime, TimeZone, Utc};
+/// use eggersmann_app_server_appliance_availability::schedule::{SchedulePolicy, Workload};
+///
+/// let policy = SchedulePolicy::business_hours(NaiveTime::from_hms_opt(8, 0, 0).unwrap(), NaiveTime::from_hms_opt(18, 0, 0).unwrap()).with_overnight(NaiveTime::from_hms_opt(22, 0, 0).unwrap(), NaiveTime::from_hms_opt(5, 0, 0).unwrap());
+/// // Tuesday 23:00 in Houston.
+/// let late = Utc.with_ymd_and_hms(2024, 3, 6, 5, 0, 0).unwrap().with_timezone(&chrono_tz::America::Chicago).naive_local();
+/// assert!(!policy.allows(Workload::Routine, late));
+/// assert!(policy.allows(Workload::Bulk, late));
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct SchedulePolicy {
+	pub business_days: Vec<Weekday>,
+	pub opens: Option<NaiveTime>,
+	pub closes: Option<NaiveTime>,
+	pub overnight_start: Option<NaiveTime>,
+	pub overnight_end: Option<NaiveTime>,
+}
+
+impl Default for SchedulePolicy {
+	fn default() -> Self {
+		Self::always()
+	}
+}
+
+impl SchedulePolicy {
+	///
+	/// # `SchedulePolicy::always`
+	/// Run every kind of work at any time.
+	///
+	#[must_use]
+	pub fn always() -> Self {
+		Self { business_days: vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri], opens: None, closes: None, overnight_start: None, overnight_end: None }
+	}
+
+	///
+	/// # `SchedulePolicy::business_hours`
+	/// Run routine work from `opens` to `closes`, Monday to Friday.
+	///
+	#[must_use]
+	pub fn business_hours(opens: NaiveTime, closes: NaiveTime) -> Self {
+		Self { opens: Some(opens), closes: Some(closes), ..Self::always() }
+	}
+
+	#[must_use]
+	pub fn with_business_days(mut self, business_days: Vec<Weekday>) -> Self {
+		self.business_days = business_days;
+		self
+	}
+
+	///
+	/// Run bulk work from `start` to `end`, which may be past midnight.
+	///
+	#[must_use]
+	pub const fn with_overnight(mut self, start: NaiveTime, end: NaiveTime) -> Self {
+		self.overnight_start = Some(start);
+		self.overnight_end = Some(end);
+		self
+	}
+
+	///
+	/// Whether `workload` may run at the local time `local`.
+	///
+	#[must_use]
+	pub fn allows(&self, workload: Workload, local: NaiveDateTime) -> bool {
+		let time = local.time();
+		match workload {
+			Workload::Routine => match (self.opens, self.closes) {
+				(Some(opens), Some(closes)) => self.business_days.contains(&local.weekday()) && within(time, opens, closes),
+				_ => true,
+			},
+			Workload::Bulk => match (self.overnight_start, self.overnight_end) {
+				(Some(start), Some(end)) => within(time, start, end),
+				_ => true,
+			},
+		}
+	}
+
+	///
+	/// The earliest time at or after `now` that `workload` may run, given the business time zone `tz`.
+	///
+	#[must_use]
+	pub fn next_allowed(&self, workload: Workload, now: DateTime<Utc>, tz: chrono_tz::Tz) -> DateTime<Utc> {
+		if self.allows(workload, now.with_timezone(&tz).naive_local()) {
+			return now;
+		}
+		let start = match workload {
+			Workload::Routine => self.opens,
+			Workload::Bulk => self.overnight_start,
+		};
+		let Some(start) = start else { return now };
+		let today = now.with_timezone(&tz).date_naive();
+		(0..=7).filter_map(|days| today.checked_add_days(Days::new(days))).filter_map(|date| tz.from_local_datetime(&date.and_time(start)).earliest()).map(|candidate| candidate.with_timezone(&Utc)).find(|candidate| *candidate > now && self.allows(workload, candidate.with_timezone(&tz).naive_local())).unwrap_or(now)
+	}
+}
+
+///
+/// Whether `time` is in `[start, end)`, wrapping past midnight when `end` is before `start`.
+///
+fn within(time: NaiveTime, start: NaiveTime, end: NaiveTime) -> bool {
+	if start <= end {
+		start <= time && time < end
+	} else {
+		time >= start || time < end
+	}
+}
+
+fn policies() -> &'static RwLock<HashMap<(String, String), SchedulePolicy>> {
+	static POLICIES: OnceLock<RwLock<HashMap<(String, String), SchedulePolicy>>> = OnceLock::new();
+	POLICIES.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+fn key(scope: Option<&str>) -> String {
+	scope.map_or_else(|| "*".to_string(), str::to_lowercase)
+}
+
+///
+/// # `set_schedule_policy`
+/// Set when background work for `showroom` and `vendor` may run. `None` stands for every showroom or
+/// every vendor; the most specific policy wins, a showroom's before a vendor's.
+///
+/// ## Example
+/// ```
+/// use chrono::NaiveTime;
+/// use eggersmann_app_server_appliance_availability::schedule::{set_schedule_policy, SchedulePolicy};
+///
+/// set_schedule_policy(None, None, SchedulePolicy::business_hours(NaiveTime::from_hms_opt(8, 0, 0).unwrap(), NaiveTime::from_hms_opt(18, 0, 0).unwrap()));
+/// set_schedule_policy(Some("los angeles"), Some("subzero"), SchedulePolicy::always());
+/// ```
+///
+pub fn set_schedule_policy(showroom: Option<&str>, vendor: Option<&str>, policy: SchedulePolicy) {
+	policies().write().unwrap_or_else(std::sync::PoisonError::into_inner).insert((key(showroom), key(vendor)), policy);
+}
+
+///
+/// # `schedule_policy`
+/// The policy background work for `showroom` and `vendor` follows, `SchedulePolicy::always` unless one
+/// was set.
+///
+#[must_use]
+pub fn schedule_policy(showroom: Option<&str>, vendor: Option<&str>) -> SchedulePolicy {
+	let (showroom, vendor) = (key(showroom), key(vendor));
+	let policies = policies().read().unwrap_or_else(std::sync::PoisonError::into_inner);
+	let policy = [(showroom.clone(), vendor.clone()), (showroom, "*".to_string()), ("*".to_string(), vendor), ("*".to_string(), "*".to_string())].iter().find_map(|scope| policies.get(scope).cloned()).unwrap_or_default();
+	drop(policies);
+	policy
+}
+
+///
+/// # `may_run`
+/// Whether `workload` for `showroom` and `vendor` may run at `now`.
+///
+#[must_use]
+pub fn may_run(workload: Workload, showroom: Option<&str>, vendor: Option<&str>, now: DateTime<Utc>) -> bool {
+	schedule_policy(showroom, vendor).allows(workload, now.with_timezone(&showroom_time_zone(showroom)).naive_local())
+}
+
+///
+/// # `next_run`
+/// The earliest time at or after `now` that `workload` for `showroom` and `vendor` may run.
+///
+#[must_use]
+pub fn next_run(workload: Workload, showroom: Option<&str>, vendor: Option<&str>, now: DateTime<Utc>) -> DateTime<Utc> {
+	schedule_policy(showroom, vendor).next_allowed(workload, now, showroom_time_zone(showroom))
+}
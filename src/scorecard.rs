@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::history::{history_store, HistoryRecord};
+
+///
+/// # `SupplierScorecard`
+/// Per-vendor performance over a reporting window, built from the availability history.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct SupplierScorecard {
+	pub window_start: DateTime<Utc>,
+	pub window_end: DateTime<Utc>,
+	pub vendors: Vec<VendorScore>,
+}
+
+///
+/// # `VendorScore`
+/// Scorecard metrics of a single vendor.
+///
+/// * `promise_accuracy` - Share of promised dates that were met: a later lookup taken on or after the
+///   promised date showed the model in stock. `None` when no promise could be checked yet.
+/// * `average_lead_time_days` - Mean business days between lookup and promised date.
+/// * `stockout_rate` - Share of lookups for tracked SKUs that were not in stock.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct VendorScore {
+	pub manufacturer: String,
+	pub lookups: usize,
+	pub tracked_skus: usize,
+	pub promises_checked: usize,
+	pub promise_accuracy: Option<f64>,
+	pub average_lead_time_days: Option<f64>,
+	pub stockout_rate: f64,
+}
+
+///
+/// # `supplier_scorecard`
+/// Build the supplier scorecard for the `window` ending now from the installed history store.
+///
+/// # Errors
+/// Returns an error if no history store is installed.
+///
+pub fn supplier_scorecard(window: Duration) -> Result<SupplierScorecard, String> {
+	let store = history_store().ok_or_else(|| "No history store installed.".to_string())?;
+	let window_end = Utc::now();
+	let window_start = window_end - window;
+	Ok(scorecard_from_records(&store.records_since(window_start), window_start, window_end))
+}
+
+///
+/// # `scorecard_from_records`
+/// Build a supplier scorecard from an explicit set of history records.
+///
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn scorecard_from_records(records: &[HistoryRecord], window_start: DateTime<Utc>, window_end: DateTime<Utc>) -> SupplierScorecard {
+	let mut by_vendor: BTreeMap<String, Vec<&HistoryRecord>> = BTreeMap::new();
+	for record in records.iter().filter(|record| record.found && record.recorded_at >= window_start && record.recorded_at <= window_end) {
+		by_vendor.entry(record.manufacturer.to_lowercase()).or_default().push(record);
+	}
+
+	let vendors = by_vendor
+		.into_iter()
+		.map(|(manufacturer, mut records)| {
+			records.sort_by_key(|record| record.recorded_at);
+
+			let mut by_sku: BTreeMap<(Option<String>, String), Vec<&HistoryRecord>> = BTreeMap::new();
+			for record in &records {
+				by_sku.entry((record.warehouse.clone(), record.model_number.to_uppercase())).or_default().push(record);
+			}
+
+			let mut promises_checked: usize = 0;
+			let mut promises_kept: usize = 0;
+			for sku_records in by_sku.values() {
+				for (i, record) in sku_records.iter().enumerate() {
+					let Some(promised) = record.available_date.filter(|date| *date > record.recorded_at.date_naive()) else { continue };
+					if let Some(check) = sku_records[i + 1..].iter().find(|later| later.recorded_at.date_naive() >= promised) {
+						promises_checked += 1;
+						if check.in_stock {
+							promises_kept += 1;
+						}
+					}
+				}
+			}
+
+			let lead_times: Vec<i64> = records.iter().filter_map(|record| record.lead_time_days()).collect();
+			VendorScore {
+				manufacturer,
+				lookups: records.len(),
+				tracked_skus: by_sku.len(),
+				promises_checked,
+				promise_accuracy: (promises_checked > 0).then(|| promises_kept as f64 / promises_checked as f64),
+				average_lead_time_days: (!lead_times.is_empty()).then(|| lead_times.iter().sum::<i64>() as f64 / lead_times.len() as f64),
+				stockout_rate: records.iter().filter(|record| !record.in_stock).count() as f64 / records.len() as f64,
+			}
+		})
+		.collect();
+
+	SupplierScorecard { window_start, window_end, vendors }
+}
+
+#[cfg(feature = "xlsx")]
+impl SupplierScorecard {
+	///
+	/// # `SupplierScorecard::write_xlsx`
+	/// Write the scorecard as a single-sheet Excel workbook.
+	///
+	/// # Errors
+	/// Returns an error if the workbook cannot be written.
+	///
+	pub fn write_xlsx(&self, path: &std::path::Path) -> Result<(), String> {
+		let mut workbook = rust_xlsxwriter::Workbook::new();
+		let sheet = workbook.add_worksheet();
+		let map_err = |e: rust_xlsxwriter::XlsxError| format!("Failed to write scorecard workbook: {e}");
+
+		sheet.write_string(0, 0, format!("Supplier scorecard {} - {}", self.window_start.format("%Y-%m-%d"), self.window_end.format("%Y-%m-%d"))).map_err(map_err)?;
+		for (col, header) in ["Vendor", "Lookups", "Tracked SKUs", "Promises checked", "Promise accuracy", "Avg lead time (business days)", "Stockout rate"].iter().enumerate() {
+			sheet.write_string(2, u16::try_from(col).unwrap_or_default(), *header).map_err(map_err)?;
+		}
+		for (i, vendor) in self.vendors.iter().enumerate() {
+			let row = u32::try_from(i + 3).map_err(|e| format!("Too many vendors for one sheet: {e}"))?;
+			sheet.write_string(row, 0, &vendor.manufacturer).map_err(map_err)?;
+			sheet.write_number(row, 1, u32::try_from(vendor.lookups).unwrap_or(u32::MAX)).map_err(map_err)?;
+			sheet.write_number(row, 2, u32::try_from(vendor.tracked_skus).unwrap_or(u32::MAX)).map_err(map_err)?;
+			sheet.write_number(row, 3, u32::try_from(vendor.promises_checked).unwrap_or(u32::MAX)).map_err(map_err)?;
+			if let Some(accuracy) = vendor.promise_accuracy {
+				sheet.write_number(row, 4, accuracy).map_err(map_err)?;
+			}
+			if let Some(lead_time) = vendor.average_lead_time_days {
+				sheet.write_number(row, 5, lead_time).map_err(map_err)?;
+			}
+			sheet.write_number(row, 6, vendor.stockout_rate).map_err(map_err)?;
+		}
+
+		workbook.save(path).map_err(map_err)
+	}
+}
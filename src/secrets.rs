@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+
+use crate::credentials::{credential_provider, secret_name, SecretString};
+use crate::ratelimit::RateLimiter;
+use crate::schedule::{self, Workload};
+use crate::telemetry;
+
+///
+/// How long a fetched secret is used before it is fetched again on demand, unless changed with
+/// `credentials::set_secret_max_age`.
+///
+pub const DEFAULT_SECRET_MAX_AGE: Duration = Duration::from_hours(1);
+
+///
+/// How often the background refresh started by `prefetch` fetches every known secret again.
+///
+const SECRET_REFRESH_INTERVAL: Duration = Duration::from_mins(30);
+
+///
+/// Spacing of secret fetches, well inside the Key Vault limit on secret reads per vault.
+///
+const FETCH_INTERVAL: Duration = Duration::from_millis(50);
+
+fn secret_cache() -> &'static RwLock<HashMap<String, (SecretString, Instant)>> {
+	static SECRETS: OnceLock<RwLock<HashMap<String, (SecretString, Instant)>>> = OnceLock::new();
+	SECRETS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+fn max_age_slot() -> &'static RwLock<Duration> {
+	static MAX_AGE: OnceLock<RwLock<Duration>> = OnceLock::new();
+	MAX_AGE.get_or_init(|| RwLock::new(DEFAULT_SECRET_MAX_AGE))
+}
+
+///
+/// How long a fetched secret is used before it is fetched again on demand.
+///
+pub fn max_age() -> Duration {
+	*max_age_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+pub fn set_max_age(max_age: Duration) {
+	*max_age_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = max_age;
+}
+
+fn fetch_limiter() -> &'static RateLimiter {
+	static LIMITER: RateLimiter = RateLimiter::new(FETCH_INTERVAL);
+	&LIMITER
+}
+
+///
+/// # Get Secret
+/// A vendor secret, from memory when it was fetched less than `max_age` ago. An older value is still used
+/// if fetching it again fails.
+///
+/// # Errors
+/// Returns an error if the secret has never been fetched and cannot be fetched now.
+///
+pub async fn get_secret(name: &str) -> Result<SecretString, String> {
+	let cached = secret_cache().read().unwrap_or_else(std::sync::PoisonError::into_inner).get(name).cloned();
+	telemetry::counter(if cached.is_some() { "secrets.cache.hit" } else { "secrets.cache.miss" }, 1, &[("secret", name.to_string())]);
+	match cached {
+		Some((value, fetched)) if fetched.elapsed() < max_age() => Ok(value),
+		Some((value, _)) => Ok(refresh_secret(name).await.unwrap_or(value)),
+		None => refresh_secret(name).await,
+	}
+}
+
+///
+/// # Vendor Credentials
+/// The dealer login of `vendor`, kept as the secrets `{vendor}-username` and `{vendor}-password`.
+///
+/// # Errors
+/// Returns the error of `get_secret` for whichever of the two cannot be had.
+///
+pub async fn vendor_credentials(vendor: &str) -> Result<(SecretString, SecretString), String> {
+	let username = get_secret(&format!("{vendor}-username")).await.map_err(|e| format!("Failed to get the {vendor} username: {e}"))?;
+	let password = get_secret(&format!("{vendor}-password")).await.map_err(|e| format!("Failed to get the {vendor} password: {e}"))?;
+	Ok((username, password))
+}
+
+///
+/// Fetch a secret and cache it.
+///
+async fn refresh_secret(name: &str) -> Result<SecretString, String> {
+	fetch_limiter().acquire().await;
+	let value = fetch_secret(name).await?;
+	secret_cache().write().unwrap_or_else(std::sync::PoisonError::into_inner).insert(name.to_string(), (value.clone(), Instant::now()));
+	Ok(value)
+}
+
+///
+/// # Prefetch Secrets
+/// Fetch `names` into memory and, the first time this is called, start a Tokio task fetching every cached
+/// secret again each `SECRET_REFRESH_INTERVAL` while the schedule policy allows routine work. A secret whose refresh fails keeps its previous value.
+///
+/// Must be called inside a Tokio runtime.
+///
+/// # Errors
+/// Returns an error listing the secrets that could not be fetched. The others are cached and the refresh
+/// is started regardless.
+///
+pub async fn prefetch(names: &[String]) -> Result<(), String> {
+	static REFRESHING: AtomicBool = AtomicBool::new(false);
+
+	let mut failed = Vec::new();
+	for name in names {
+		if let Err(e) = refresh_secret(name).await {
+			telemetry::event("secrets.prefetch.failed", &[("secret", name.clone()), ("error", e)]);
+			failed.push(name.as_str());
+		}
+	}
+
+	if !REFRESHING.swap(true, Ordering::SeqCst) {
+		tokio::spawn(async {
+			loop {
+				tokio::time::sleep(SECRET_REFRESH_INTERVAL).await;
+				// outside business hours secrets are fetched when used instead.
+				if !schedule::may_run(Workload::Routine, None, None, Utc::now()) {
+					continue;
+				}
+				let names: Vec<String> = secret_cache().read().unwrap_or_else(std::sync::PoisonError::into_inner).keys().cloned().collect();
+				for name in names {
+					if let Err(e) = refresh_secret(&name).await {
+						telemetry::event("secrets.refresh.failed", &[("secret", name), ("error", e)]);
+					}
+				}
+			}
+		});
+	}
+
+	if failed.is_empty() {
+		Ok(())
+	} else {
+		Err(format!("Failed to prefetch secrets: {}", failed.join(", ")))
+	}
+}
+
+///
+/// Read a secret from the configured `CredentialProvider`, under the name it is stored as there.
+///
+async fn fetch_secret(name: &str) -> Result<SecretString, String> {
+	credential_provider().secret(&secret_name(name)).await
+}
+
+///
+/// Forget every fetched secret, so the next use fetches it from the current provider.
+///
+pub fn clear() {
+	secret_cache().write().unwrap_or_else(std::sync::PoisonError::into_inner).clear();
+}
+
+///
+/// Forget the fetched value of `name`, so the next use fetches it again.
+///
+pub fn invalidate(name: &str) {
+	secret_cache().write().unwrap_or_else(std::sync::PoisonError::into_inner).remove(name);
+}
+
+///
+/// # Redact Secrets
+/// `text` with every cached secret value replaced by `[redacted]`, for anything leaving the service such as
+/// a support bundle.
+///
+pub fn redact(text: &str) -> String {
+	let secrets = secret_cache().read().unwrap_or_else(std::sync::PoisonError::into_inner);
+	secrets.values().filter(|(value, _)| !value.is_empty()).fold(text.to_string(), |text, (value, _)| text.replace(value.expose(), "[redacted]"))
+}
@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+use tower::buffer::BufferLayer;
+use tower::limit::RateLimitLayer;
+use tower::{BoxError, Layer, Service, ServiceBuilder};
+
+use super::batch::fan_out;
+use super::cache::ResultKey;
+use super::{AvailabilityRequest, RequestOptions};
+
+///
+/// Future returned by the services of this module.
+///
+pub type BoxFuture<T> = Pin<Box<dyn Future<Output = Result<T, String>> + Send>>;
+
+///
+/// # `ProviderService`
+/// Innermost service: asks the vendor of the request for its availability, without caching, history or
+/// compliance checks.
+///
+/// The request must have been through `parse_manufacturer`, `get_warehouse` and `get_time`. Each call is
+/// limited by `options`, not at all by default.
+///
+#[derive(Debug, Clone, Copy, Default)]
+#[non_exhaustive]
+pub struct ProviderService {
+	pub options: RequestOptions,
+}
+
+impl ProviderService {
+	#[must_use]
+	pub const fn new(options: RequestOptions) -> Self {
+		Self { options }
+	}
+}
+
+impl Service<AvailabilityRequest> for ProviderService {
+	type Error = String;
+	type Future = BoxFuture<AvailabilityRequest>;
+	type Response = AvailabilityRequest;
+
+	fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+		Poll::Ready(Ok(()))
+	}
+
+	fn call(&mut self, req: AvailabilityRequest) -> Self::Future {
+		Box::pin(req.lookup_availability(self.options))
+	}
+}
+
+///
+/// # `CacheLayer`
+/// Answers from the shared result cache and stores the answers of the inner service in it, with the TTLs
+/// of `result_cache_config()`.
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CacheLayer;
+
+impl<S> Layer<S> for CacheLayer {
+	type Service = CacheService<S>;
+
+	fn layer(&self, inner: S) -> Self::Service {
+		CacheService { inner }
+	}
+}
+
+///
+/// # `CacheService`
+/// Service produced by `CacheLayer`.
+///
+#[derive(Debug, Clone)]
+pub struct CacheService<S> {
+	inner: S,
+}
+
+impl<S> Service<AvailabilityRequest> for CacheService<S>
+where
+	S: Service<AvailabilityRequest, Response = AvailabilityRequest, Error = String> + Clone + Send + 'static,
+	S::Future: Send,
+{
+	type Error = String;
+	type Future = BoxFuture<AvailabilityRequest>;
+	type Response = AvailabilityRequest;
+
+	fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+		self.inner.poll_ready(cx)
+	}
+
+	fn call(&mut self, req: AvailabilityRequest) -> Self::Future {
+		if let Some(cached) = req.answered_from_cache() {
+			return Box::pin(async move { Ok(cached) });
+		}
+		let future = self.inner.call(req);
+		Box::pin(async move {
+			let req = future.await?;
+			req.store_in_result_cache();
+			Ok(req)
+		})
+	}
+}
+
+type Waiters = Arc<Mutex<HashMap<ResultKey, Vec<oneshot::Sender<Result<AvailabilityRequest, String>>>>>>;
+
+///
+/// # `DedupeLayer`
+/// Lets concurrent requests for the same vendor, model, warehouse and quantity share one call to the inner
+/// service. If the call that leads is dropped before it answers, e.g. by a timeout or a client that went
+/// away, the requests waiting on it fail and the next request leads a call of its own.
+///
+#[derive(Debug, Clone, Default)]
+pub struct DedupeLayer {
+	waiters: Waiters,
+}
+
+impl<S> Layer<S> for DedupeLayer {
+	type Service = DedupeService<S>;
+
+	fn layer(&self, inner: S) -> Self::Service {
+		DedupeService { inner, waiters: self.waiters.clone() }
+	}
+}
+
+///
+/// # `DedupeService`
+/// Service produced by `DedupeLayer`.
+///
+#[derive(Debug, Clone)]
+pub struct DedupeService<S> {
+	inner: S,
+	waiters: Waiters,
+}
+
+impl<S> Service<AvailabilityRequest> for DedupeService<S>
+where
+	S: Service<AvailabilityRequest, Response = AvailabilityRequest, Error = String> + Clone + Send + 'static,
+	S::Future: Send,
+{
+	type Error = String;
+	type Future = BoxFuture<AvailabilityRequest>;
+	type Response = AvailabilityRequest;
+
+	fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+		self.inner.poll_ready(cx)
+	}
+
+	fn call(&mut self, req: AvailabilityRequest) -> Self::Future {
+		let Some(key) = req.cache_key() else { return Box::pin(self.inner.call(req)) };
+
+		let mut waiters = self.waiters.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+		if let Some(queue) = waiters.get_mut(&key) {
+			let (sender, receiver) = oneshot::channel();
+			queue.push(sender);
+			drop(waiters);
+			return Box::pin(async move {
+				let answer = receiver.await.map_err(|_| "Shared lookup was dropped.".to_string())??;
+				Ok(fan_out(&answer, req))
+			});
+		}
+		waiters.insert(key.clone(), Vec::new());
+		drop(waiters);
+
+		let future = self.inner.call(req);
+		let leader = Leader { key: Some(key), waiters: self.waiters.clone() };
+		Box::pin(async move {
+			let answer = future.await;
+			leader.finish(&answer);
+			answer
+		})
+	}
+}
+
+///
+/// The call leading a key of `DedupeService`. Answers its waiters when it finishes, and fails them when it
+/// is dropped first, so the key is never left waiting on a call that is gone.
+///
+struct Leader {
+	key: Option<ResultKey>,
+	waiters: Waiters,
+}
+
+impl Leader {
+	fn finish(mut self, answer: &Result<AvailabilityRequest, String>) {
+		self.answer(answer);
+	}
+
+	fn answer(&mut self, answer: &Result<AvailabilityRequest, String>) {
+		let Some(key) = self.key.take() else { return };
+		let queue = self.waiters.lock().unwrap_or_else(std::sync::PoisonError::into_inner).remove(&key).unwrap_or_default();
+		for sender in queue {
+			let _ = sender.send(answer.clone());
+		}
+	}
+}
+
+impl Drop for Leader {
+	fn drop(&mut self) {
+		self.answer(&Err("Shared lookup was cancelled.".to_string()));
+	}
+}
+
+///
+/// Requests waiting for the rate limiter before callers are back-pressured.
+///
+pub const BUFFER_CAPACITY: usize = 1024;
+
+///
+/// # `availability_service`
+/// The default lookup stack: dedupe, cache and rate limit around the vendor provider. Build your own
+/// `ServiceBuilder` from the same layers to reorder or extend it.
+///
+/// Failed lookups are not retried here: the vendor requests of a lookup are already retried by the HTTP
+/// client as `backoff_policy` says, so a second retry layer would multiply the calls to a failing vendor.
+///
+/// The rate limiter sits behind a `Buffer` of `BUFFER_CAPACITY` requests so the stack can be cloned,
+/// which means this must be called inside a Tokio runtime.
+///
+/// ## Inputs
+/// * `rate`: (u64, Duration) - At most this many vendor calls per period.
+///
+#[must_use]
+pub fn availability_service(rate: (u64, Duration)) -> impl Service<AvailabilityRequest, Response = AvailabilityRequest, Error = String> + Clone {
+	ServiceBuilder::new().layer(DedupeLayer::default()).layer(CacheLayer).map_err(|e: BoxError| e.to_string()).layer(BufferLayer::new(BUFFER_CAPACITY)).layer(RateLimitLayer::new(rate.0, rate.1)).service(ProviderService::default())
+}
@@ -0,0 +1,157 @@
+use std::sync::{Arc, OnceLock, RwLock};
+
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+
+use super::response::V1_NOT_FOUND;
+use super::{support, telemetry, Availability, AvailabilityRequest, AvailabilityStatus, FieldProvenance, ProductInfo, Provenance, Rejection};
+
+///
+/// # `Scenario`
+/// The scripted answer a simulated model gives.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Scenario {
+	/// Available today.
+	InStock,
+	/// Available the given number of days after today in the showroom's time zone.
+	Backorder(u32),
+	/// Rejected by the vendor as discontinued.
+	Discontinued,
+}
+
+///
+/// # `SimulatedItem`
+/// A model of the simulation catalog and the scenario it plays.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct SimulatedItem {
+	pub manufacturer: String,
+	pub model_number: String,
+	pub name: String,
+	pub category: Option<String>,
+	pub scenario: Scenario,
+}
+
+impl SimulatedItem {
+	///
+	/// # `SimulatedItem::new`
+	/// Create a catalog entry for `model_number` at `manufacturer`.
+	///
+	#[must_use]
+	pub const fn new(manufacturer: String, model_number: String, name: String, scenario: Scenario) -> Self {
+		Self { manufacturer, model_number, name, category: None, scenario }
+	}
+
+	#[must_use]
+	pub fn with_category(mut self, category: String) -> Self {
+		self.category = Some(category);
+		self
+	}
+}
+
+///
+/// Catalog the simulation starts with: one model of each scenario per vendor.
+///
+const FIXTURE_CATALOG: [(&str, &str, &str, &str, Scenario); 9] = [
+	("bsh", "HBLP651RUC", "Bosch Benchmark 30\" Single Wall Oven", "Wall Oven", Scenario::InStock),
+	("bsh", "SHX78CM5N", "Bosch 800 Series Dishwasher", "Dishwasher", Scenario::Backorder(21)),
+	("bsh", "HGI8054UC", "Bosch 800 Series Gas Range", "Range", Scenario::Discontinued),
+	("subzero", "CL3650UID/S", "Sub-Zero 36\" Classic Refrigerator", "Refrigerator", Scenario::InStock),
+	("subzero", "DF366", "Wolf 36\" Dual Fuel Range", "Range", Scenario::Backorder(45)),
+	("subzero", "UC-15I", "Sub-Zero 15\" Undercounter Ice Maker", "Ice Maker", Scenario::Discontinued),
+	("miele", "G7366SCVISF", "Miele G 7366 SCVi SF Dishwasher", "Dishwasher", Scenario::InStock),
+	("miele", "H7880BP", "Miele H 7880 BP Wall Oven", "Wall Oven", Scenario::Backorder(10)),
+	("miele", "KM6360", "Miele KM 6360 Induction Cooktop", "Cooktop", Scenario::Discontinued),
+];
+
+///
+/// # `fixture_catalog`
+/// The catalog the simulation uses until `set_simulation_catalog` replaces it.
+///
+#[must_use]
+pub fn fixture_catalog() -> Vec<SimulatedItem> {
+	FIXTURE_CATALOG.iter().map(|(manufacturer, model_number, name, category, scenario)| SimulatedItem::new((*manufacturer).to_string(), (*model_number).to_string(), (*name).to_string(), *scenario).with_category((*category).to_string())).collect()
+}
+
+fn catalog_slot() -> &'static RwLock<Arc<Vec<SimulatedItem>>> {
+	static CATALOG: OnceLock<RwLock<Arc<Vec<SimulatedItem>>>> = OnceLock::new();
+	CATALOG.get_or_init(|| RwLock::new(Arc::new(fixture_catalog())))
+}
+
+///
+/// # `set_simulation_catalog`
+/// Replace the models simulated requests are answered from, e.g. with the scenarios of a training course.
+///
+pub fn set_simulation_catalog(catalog: Vec<SimulatedItem>) {
+	*catalog_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = Arc::new(catalog);
+}
+
+///
+/// # `simulation_catalog`
+/// The models simulated requests are answered from.
+///
+#[must_use]
+pub fn simulation_catalog() -> Arc<Vec<SimulatedItem>> {
+	catalog_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
+
+///
+/// # `reset_simulation_catalog`
+/// Go back to the fixture catalog.
+///
+pub fn reset_simulation_catalog() {
+	set_simulation_catalog(fixture_catalog());
+}
+
+///
+/// # Simulate
+/// Answer `req` from the simulation catalog. Nothing leaves the process: no vendor, cache, account check
+/// or history is touched. Models not in the catalog are not found.
+///
+#[must_use]
+pub fn simulate(mut req: AvailabilityRequest) -> AvailabilityRequest {
+	let (availability, product, rejection) = simulated_answer(&req);
+	req.availability = Some(availability.raw.clone());
+	req.availability_detail = Some(availability);
+	req.provenance = FieldProvenance::recorded(&Provenance::Simulated, true, product.is_some());
+	req.product = product;
+	req.rejection = rejection;
+	req
+}
+
+///
+/// The availability, product and rejection the simulation catalog answers `req` with.
+///
+pub(crate) fn simulated_answer(req: &AvailabilityRequest) -> (Availability, Option<ProductInfo>, Option<Rejection>) {
+	let manufacturer = req.manufacturer.as_ref().map(ToString::to_string).unwrap_or_default();
+	let model_number = req.model_number.clone().unwrap_or_default().trim().to_uppercase();
+	telemetry::counter("availability.lookup.simulated", 1, &[("manufacturer", manufacturer.clone())]);
+	let catalog = simulation_catalog();
+	let Some(item) = catalog.iter().find(|item| item.manufacturer.eq_ignore_ascii_case(&manufacturer) && item.model_number.eq_ignore_ascii_case(&model_number)) else {
+		support::trace("simulation", &format!("{model_number} is not in the simulation catalog"));
+		return (Availability::new(AvailabilityStatus::NotFound, V1_NOT_FOUND.to_string()), None, None);
+	};
+	support::trace("simulation", &format!("{} plays {:?}", item.model_number, item.scenario));
+	let today = req.business_today();
+	let mut product = ProductInfo::new(item.name.clone());
+	if let Some(category) = &item.category {
+		product = product.with_category(category.clone());
+	}
+	let mut rejection = None;
+	let availability = match item.scenario {
+		Scenario::InStock => Availability::new(AvailabilityStatus::InStock, format!("Found: {}, Available: {}", item.model_number, today.format("%m/%d/%Y"))).with_available_date(today),
+		Scenario::Backorder(days) => {
+			let available_date = today + Duration::days(i64::from(days));
+			Availability::new(AvailabilityStatus::Backordered, format!("Found: {}, Available: {}", item.model_number, available_date.format("%m/%d/%Y"))).with_available_date(available_date)
+		}
+		Scenario::Discontinued => {
+			rejection = Some(Rejection::new("DC".to_string(), Some(format!("Material {} is discontinued", item.model_number))));
+			Availability::new(AvailabilityStatus::Discontinued, format!("Found: {}, Available: discontinued", item.model_number))
+		}
+	};
+	(availability, Some(product), rejection)
+}
@@ -0,0 +1,239 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::events::{self, AvailabilityEvent};
+use super::telemetry;
+
+///
+/// # `SlaPolicy`
+/// What a vendor is expected to deliver over a sliding `window`: 95th percentile lookup latency at most
+/// `max_latency` and a share of failed lookups at most `max_error_rate`. Nothing is evaluated until the
+/// window holds `min_samples` lookups.
+///
+/// ## Example
+/// ```
+/// use std::time::Duration;
+/// use eggersmann_app_server_appliance_availability::sla::{set_sla_policy, SlaPolicy};
+///
+/// set_sla_policy("subzero", SlaPolicy::new(Duration::from_secs(10), 0.05).with_window(Duration::from_secs(30 * 60)));
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct SlaPolicy {
+	pub max_latency: Duration,
+	pub max_error_rate: f64,
+	pub window: Duration,
+	pub min_samples: usize,
+}
+
+impl SlaPolicy {
+	///
+	/// # `SlaPolicy::new`
+	/// Create a policy evaluated over the last hour once it holds 20 lookups.
+	///
+	#[must_use]
+	pub const fn new(max_latency: Duration, max_error_rate: f64) -> Self {
+		Self { max_latency, max_error_rate, window: Duration::from_hours(1), min_samples: 20 }
+	}
+
+	#[must_use]
+	pub const fn with_window(mut self, window: Duration) -> Self {
+		self.window = window;
+		self
+	}
+
+	#[must_use]
+	pub const fn with_min_samples(mut self, min_samples: usize) -> Self {
+		self.min_samples = min_samples;
+		self
+	}
+}
+
+///
+/// # `SlaMetric`
+/// The expectation of an `SlaPolicy` that was breached.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum SlaMetric {
+	/// 95th percentile lookup latency, measured in milliseconds.
+	Latency,
+	/// Share of failed lookups, measured in basis points (1/100 of a percent).
+	ErrorRate,
+}
+
+///
+/// # `SlaBreach`
+/// A vendor outside one expectation of its policy, published as `AvailabilityEvent::SlaBreached` when it
+/// starts and `AvailabilityEvent::SlaRecovered` when it ends.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct SlaBreach {
+	pub manufacturer: String,
+	pub metric: SlaMetric,
+	/// In the unit of `metric`.
+	pub observed: u64,
+	pub limit: u64,
+	pub samples: usize,
+	pub at: DateTime<Utc>,
+}
+
+///
+/// # `SlaStatus`
+/// How a vendor performs against its policy over the current window.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct SlaStatus {
+	pub manufacturer: String,
+	pub policy: SlaPolicy,
+	pub samples: usize,
+	pub p95_latency: Option<Duration>,
+	pub error_rate: Option<f64>,
+	pub breached: Vec<SlaMetric>,
+}
+
+#[derive(Debug, Default)]
+struct VendorWindow {
+	samples: VecDeque<(Instant, Duration, bool)>,
+	breached: Vec<SlaMetric>,
+}
+
+fn policies() -> &'static RwLock<HashMap<String, SlaPolicy>> {
+	static POLICIES: OnceLock<RwLock<HashMap<String, SlaPolicy>>> = OnceLock::new();
+	POLICIES.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+fn windows() -> &'static Mutex<HashMap<String, VendorWindow>> {
+	static WINDOWS: OnceLock<Mutex<HashMap<String, VendorWindow>>> = OnceLock::new();
+	WINDOWS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+///
+/// # `set_sla_policy`
+/// Track `manufacturer` against `policy`, starting from an empty window.
+///
+pub fn set_sla_policy(manufacturer: &str, policy: SlaPolicy) {
+	let manufacturer = manufacturer.to_lowercase();
+	windows().lock().unwrap_or_else(std::sync::PoisonError::into_inner).remove(&manufacturer);
+	policies().write().unwrap_or_else(std::sync::PoisonError::into_inner).insert(manufacturer, policy);
+}
+
+///
+/// # `sla_policy`
+/// The policy `manufacturer` is tracked against, `None` if it is not tracked.
+///
+#[must_use]
+pub fn sla_policy(manufacturer: &str) -> Option<SlaPolicy> {
+	policies().read().unwrap_or_else(std::sync::PoisonError::into_inner).get(&manufacturer.to_lowercase()).copied()
+}
+
+///
+/// # `clear_sla_policy`
+/// Stop tracking `manufacturer`.
+///
+pub fn clear_sla_policy(manufacturer: &str) {
+	let manufacturer = manufacturer.to_lowercase();
+	policies().write().unwrap_or_else(std::sync::PoisonError::into_inner).remove(&manufacturer);
+	windows().lock().unwrap_or_else(std::sync::PoisonError::into_inner).remove(&manufacturer);
+}
+
+///
+/// # `sla_status`
+/// How `manufacturer` performs against its policy right now, `None` if it is not tracked.
+///
+#[must_use]
+pub fn sla_status(manufacturer: &str) -> Option<SlaStatus> {
+	let manufacturer = manufacturer.to_lowercase();
+	let policy = sla_policy(&manufacturer)?;
+	let mut windows = windows().lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+	let window = windows.entry(manufacturer.clone()).or_default();
+	prune(window, policy.window);
+	let (p95_latency, error_rate) = measure(window);
+	let (samples, breached) = (window.samples.len(), window.breached.clone());
+	drop(windows);
+	Some(SlaStatus { manufacturer, policy, samples, p95_latency, error_rate, breached })
+}
+
+///
+/// # Record Lookup
+/// Add a call to the vendor of `manufacturer` to its window and publish the breaches it starts or ends.
+/// Only calls that reach the vendor are recorded, so answers from the result cache do not pull the
+/// latency down.
+///
+pub fn record(manufacturer: &str, elapsed: Duration, succeeded: bool) {
+	let manufacturer = manufacturer.to_lowercase();
+	let Some(policy) = sla_policy(&manufacturer) else { return };
+	let (started, ended) = {
+		let mut windows = windows().lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+		let window = windows.entry(manufacturer.clone()).or_default();
+		window.samples.push_back((Instant::now(), elapsed, succeeded));
+		prune(window, policy.window);
+		if window.samples.len() < policy.min_samples {
+			return;
+		}
+		let (p95_latency, error_rate) = measure(window);
+		let observed = [(SlaMetric::Latency, p95_latency.map(millis), millis(policy.max_latency)), (SlaMetric::ErrorRate, error_rate.map(basis_points), basis_points(policy.max_error_rate))];
+		let breach = |metric: SlaMetric, observed: u64, limit: u64| SlaBreach { manufacturer: manufacturer.clone(), metric, observed, limit, samples: window.samples.len(), at: Utc::now() };
+		let mut started = Vec::new();
+		let mut ended = Vec::new();
+		for (metric, value, limit) in observed {
+			let Some(value) = value else { continue };
+			let was_breached = window.breached.contains(&metric);
+			if value > limit && !was_breached {
+				started.push(breach(metric, value, limit));
+			} else if value <= limit && was_breached {
+				ended.push(breach(metric, value, limit));
+			}
+		}
+		window.breached.retain(|metric| !ended.iter().any(|breach| breach.metric == *metric));
+		window.breached.extend(started.iter().map(|breach| breach.metric));
+		drop(windows);
+		(started, ended)
+	};
+	for breach in started {
+		telemetry::event("sla.breached", &[("manufacturer", breach.manufacturer.clone()), ("metric", format!("{:?}", breach.metric)), ("observed", breach.observed.to_string()), ("limit", breach.limit.to_string())]);
+		events::publish(AvailabilityEvent::SlaBreached(breach));
+	}
+	for breach in ended {
+		telemetry::event("sla.recovered", &[("manufacturer", breach.manufacturer.clone()), ("metric", format!("{:?}", breach.metric)), ("observed", breach.observed.to_string())]);
+		events::publish(AvailabilityEvent::SlaRecovered(breach));
+	}
+}
+
+fn millis(duration: Duration) -> u64 {
+	u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn basis_points(rate: f64) -> u64 {
+	(rate * 10_000.0).round().max(0.0) as u64
+}
+
+fn prune(window: &mut VendorWindow, length: Duration) {
+	while window.samples.front().is_some_and(|(at, _, _)| at.elapsed() > length) {
+		window.samples.pop_front();
+	}
+}
+
+///
+/// 95th percentile latency and error rate of the samples in a window.
+///
+#[allow(clippy::cast_precision_loss)]
+fn measure(window: &VendorWindow) -> (Option<Duration>, Option<f64>) {
+	if window.samples.is_empty() {
+		return (None, None);
+	}
+	let mut latencies: Vec<Duration> = window.samples.iter().map(|(_, latency, _)| *latency).collect();
+	latencies.sort_unstable();
+	let p95 = latencies[(latencies.len() * 95).div_ceil(100).saturating_sub(1)];
+	let failures = window.samples.iter().filter(|(_, _, succeeded)| !succeeded).count();
+	(Some(p95), Some(failures as f64 / window.samples.len() as f64))
+}
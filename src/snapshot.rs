@@ -0,0 +1,128 @@
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::Value;
+
+///
+/// Number of decimal places every float is rendered with in canonical JSON.
+///
+pub const CANONICAL_FLOAT_PRECISION: usize = 6;
+
+///
+/// # `to_canonical_json`
+/// Serialize a value into canonical JSON.
+///
+/// Canonical JSON is byte-stable for identical data:
+/// * object keys are sorted lexicographically at every depth,
+/// * floats are rendered with `CANONICAL_FLOAT_PRECISION` decimals and trailing zeros trimmed (`1.5`, `2.0`),
+/// * output is pretty printed with tab indentation and ends with a newline.
+///
+/// ## Example
+/// ```
+/// use eggersmann_app_server_appliance_availability::snapshot::to_canonical_json;
+/// use serde_json::json;
+///
+/// let json = to_canonical_json(&json!({ "b": 1.50, "a": "x" })).unwrap();
+/// assert_eq!(json, "{\n\t\"a\": \"x\",\n\t\"b\": 1.5\n}\n");
+/// ```
+///
+/// # Errors
+/// Returns an error if the value cannot be represented as JSON.
+///
+pub fn to_canonical_json<T: Serialize + ?Sized>(value: &T) -> Result<String, String> {
+	let value = serde_json::to_value(value).map_err(|e| format!("Failed to serialize snapshot: {e}"))?;
+	let mut out = String::new();
+	write_value(&mut out, &value, 0)?;
+	out.push('\n');
+	Ok(out)
+}
+
+///
+/// # `write_canonical_json`
+/// Write a value as canonical JSON to `path`, replacing any existing file.
+///
+/// # Errors
+/// Returns an error if the value cannot be serialized or the file cannot be written.
+///
+pub fn write_canonical_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<(), String> {
+	let json = to_canonical_json(value)?;
+	let mut file = File::create(path).map_err(|e| format!("Failed to create snapshot file {}: {e:?}", path.display()))?;
+	file.write_all(json.as_bytes()).map_err(|e| format!("Failed to write snapshot file {}: {e:?}", path.display()))
+}
+
+fn write_value(out: &mut String, value: &Value, depth: usize) -> Result<(), String> {
+	match value {
+		Value::Null => out.push_str("null"),
+		Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+		Value::Number(n) => {
+			if n.is_f64() {
+				out.push_str(&format_float(n.as_f64().unwrap_or_default()));
+			} else {
+				write!(out, "{n}").map_err(|e| format!("Failed to write number: {e}"))?;
+			}
+		}
+		Value::String(s) => out.push_str(&serde_json::to_string(s).map_err(|e| format!("Failed to escape string: {e}"))?),
+		Value::Array(items) => {
+			if items.is_empty() {
+				out.push_str("[]");
+				return Ok(());
+			}
+			out.push_str("[\n");
+			for (i, item) in items.iter().enumerate() {
+				indent(out, depth + 1);
+				write_value(out, item, depth + 1)?;
+				if i + 1 < items.len() {
+					out.push(',');
+				}
+				out.push('\n');
+			}
+			indent(out, depth);
+			out.push(']');
+		}
+		Value::Object(map) => {
+			if map.is_empty() {
+				out.push_str("{}");
+				return Ok(());
+			}
+			let mut keys: Vec<&String> = map.keys().collect();
+			keys.sort();
+			out.push_str("{\n");
+			for (i, key) in keys.iter().enumerate() {
+				indent(out, depth + 1);
+				out.push_str(&serde_json::to_string(key).map_err(|e| format!("Failed to escape key: {e}"))?);
+				out.push_str(": ");
+				write_value(out, &map[key.as_str()], depth + 1)?;
+				if i + 1 < keys.len() {
+					out.push(',');
+				}
+				out.push('\n');
+			}
+			indent(out, depth);
+			out.push('}');
+		}
+	}
+	Ok(())
+}
+
+fn indent(out: &mut String, depth: usize) {
+	for _ in 0..depth {
+		out.push('\t');
+	}
+}
+
+fn format_float(f: f64) -> String {
+	if !f.is_finite() {
+		return "null".to_string();
+	}
+	let mut s = format!("{f:.CANONICAL_FLOAT_PRECISION$}");
+	while s.ends_with('0') && !s.ends_with(".0") {
+		s.pop();
+	}
+	if s == "-0.0" {
+		s = "0.0".to_string();
+	}
+	s
+}
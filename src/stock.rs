@@ -0,0 +1,173 @@
+use chrono::{Days, NaiveDate};
+use serde_json::Value;
+
+use super::{Availability, AvailabilityStatus, ProductInfo};
+
+///
+/// # `StockKeys`
+/// The names of the fields in a vendor's JSON stock answer, read by `json_stock`. Every vendor answers
+/// with the model, its description, the quantity on hand, the date more is expected and a sales status;
+/// the rest is optional.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StockKeys {
+	/// The list whose first entry is the answer, the answer itself when there is no such list.
+	pub list: &'static str,
+	pub model: &'static str,
+	pub on_hand: &'static str,
+	/// The date more stock is expected, as `%Y-%m-%d` or `%m/%d/%Y`.
+	pub next_date: &'static str,
+	/// The quantity arriving on `next_date`.
+	pub next_quantity: Option<&'static str>,
+	/// The lead time in days and in weeks, counted from today when there is no `next_date`.
+	pub lead_time: Option<(&'static str, &'static str)>,
+	/// The brand of the model, when the vendor sells more than one.
+	pub brand: Option<&'static str>,
+	/// A sales status containing it marks a model the vendor does not know.
+	pub not_found_status: Option<&'static str>,
+}
+
+impl StockKeys {
+	pub const fn new(list: &'static str, model: &'static str, on_hand: &'static str, next_date: &'static str) -> Self {
+		Self { list, model, on_hand, next_date, next_quantity: None, lead_time: None, brand: None, not_found_status: None }
+	}
+
+	pub const fn with_next_quantity(mut self, next_quantity: &'static str) -> Self {
+		self.next_quantity = Some(next_quantity);
+		self
+	}
+
+	pub const fn with_lead_time(mut self, days: &'static str, weeks: &'static str) -> Self {
+		self.lead_time = Some((days, weeks));
+		self
+	}
+
+	pub const fn with_brand(mut self, brand: &'static str) -> Self {
+		self.brand = Some(brand);
+		self
+	}
+
+	pub const fn with_not_found_status(mut self, status: &'static str) -> Self {
+		self.not_found_status = Some(status);
+		self
+	}
+
+	fn item<'a>(&self, data: &'a Value) -> &'a Value {
+		data.get(self.list).map_or(data, |list| &list[0])
+	}
+}
+
+///
+/// Text of a field of `item`, numbers written out, empty when it is missing.
+///
+fn text(item: &Value, key: &str) -> String {
+	match &item[key] {
+		Value::String(s) => s.trim().to_string(),
+		Value::Number(n) => n.to_string(),
+		_ => String::new(),
+	}
+}
+
+///
+/// A positive whole quantity of `item`, the fraction of a decimal one dropped.
+///
+fn quantity(item: &Value, key: &str) -> Option<u32> {
+	text(item, key).split('.').next().and_then(|whole| whole.parse::<u32>().ok()).filter(|quantity| *quantity > 0)
+}
+
+///
+/// # Stock Lead Time
+/// The lead time of a stock answer in days, given in days or in weeks. `None` without lead time keys.
+///
+pub fn lead_time_days(data: &Value, keys: &StockKeys) -> Option<u32> {
+	let (days, weeks) = keys.lead_time?;
+	let item = keys.item(data);
+	let number = |key: &str| match &item[key] {
+		Value::Number(n) => n.as_f64(),
+		Value::String(s) => s.trim().parse::<f64>().ok(),
+		_ => None,
+	};
+	#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+	number(days).or_else(|| number(weeks).map(|weeks| weeks * 7.0)).filter(|days| days.is_finite() && *days >= 0.0).map(|days| days.ceil() as u32)
+}
+
+///
+/// # JSON Stock
+/// Read a vendor's JSON stock answer for `model_number`: the quantity on hand, or else the quantity and
+/// date more is expected, or else the lead time counted from `today`. A sales status of discontinued wins,
+/// and an empty answer is not found. The product is described under `description`, of `brand` unless the
+/// answer names another.
+///
+pub fn json_stock(data: &Value, keys: &StockKeys, brand: &str, model_number: &str, today: NaiveDate) -> (Availability, Option<ProductInfo>) {
+	let item = keys.item(data);
+	let status = text(item, "status").to_lowercase();
+	if item.is_null() || item.as_object().is_some_and(serde_json::Map::is_empty) || keys.not_found_status.is_some_and(|not_found| status.contains(not_found)) {
+		return (Availability::not_found(), None);
+	}
+	let found_model = Some(text(item, keys.model)).filter(|model| !model.is_empty()).unwrap_or_else(|| model_number.to_string());
+	let brand = keys.brand.map(|key| text(item, key)).filter(|named| !named.is_empty()).unwrap_or_else(|| brand.to_string());
+	let product = Some(text(item, "description")).filter(|description| !description.is_empty()).map(|description| ProductInfo::new(description).with_brand(brand));
+
+	let next_date = ["%Y-%m-%d", "%m/%d/%Y"].iter().find_map(|format| NaiveDate::parse_from_str(&text(item, keys.next_date), format).ok()).or_else(|| lead_time_days(data, keys).and_then(|days| today.checked_add_days(Days::new(days.into()))));
+	let on_hand = quantity(item, keys.on_hand);
+
+	let mut availability = Availability::stock(&found_model, on_hand, next_date, today);
+	if on_hand.is_none() {
+		availability.quantity = keys.next_quantity.and_then(|key| quantity(item, key));
+	}
+	if status.contains("discontinued") {
+		availability.status = AvailabilityStatus::Discontinued;
+	}
+	(availability, product)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const KEYS: StockKeys = StockKeys::new("items", "sku", "onHand", "nextDate").with_next_quantity("nextQuantity").with_lead_time("leadDays", "leadWeeks").with_brand("brand").with_not_found_status("invalid");
+
+	#[test]
+	fn stock_answers() {
+		let today = NaiveDate::from_ymd_opt(2030, 1, 10).unwrap();
+		let date = |day| NaiveDate::from_ymd_opt(2030, 1, day);
+		let cases = [
+			("on hand", r#"{"items": [{"sku": "M1", "onHand": "4.000", "nextQuantity": "9", "nextDate": "2030-02-01"}]}"#, AvailabilityStatus::InStock, Some(4), None),
+			("on hand as a number", r#"{"sku": "M1", "onHand": 2}"#, AvailabilityStatus::InStock, Some(2), None),
+			("expected", r#"{"items": [{"sku": "M1", "onHand": "0", "nextQuantity": "6.000", "nextDate": "2030-01-20"}]}"#, AvailabilityStatus::Backordered, Some(6), date(20)),
+			("expected as m/d/Y", r#"{"sku": "M1", "nextDate": "01/20/2030"}"#, AvailabilityStatus::Backordered, None, date(20)),
+			("expected by today", r#"{"sku": "M1", "nextDate": "2030-01-10"}"#, AvailabilityStatus::InStock, None, date(10)),
+			("lead time in days", r#"{"sku": "M1", "onHand": "0", "leadDays": "4.5"}"#, AvailabilityStatus::Backordered, None, date(15)),
+			("lead time in weeks", r#"{"sku": "M1", "leadWeeks": 2}"#, AvailabilityStatus::Backordered, None, date(24)),
+			("date before lead time", r#"{"sku": "M1", "nextDate": "2030-01-12", "leadWeeks": 2}"#, AvailabilityStatus::Backordered, None, date(12)),
+			("discontinued wins", r#"{"sku": "M1", "onHand": "3", "status": "Discontinued"}"#, AvailabilityStatus::Discontinued, Some(3), None),
+			("unknown", r#"{"sku": "M1", "onHand": "0", "nextDate": ""}"#, AvailabilityStatus::Unknown, None, None),
+			("empty list", r#"{"items": []}"#, AvailabilityStatus::NotFound, None, None),
+			("empty answer", "{}", AvailabilityStatus::NotFound, None, None),
+			("not found status", r#"{"sku": "M1", "onHand": "3", "status": "INVALID SKU"}"#, AvailabilityStatus::NotFound, None, None),
+		];
+		for (case, answer, status, quantity, available_date) in cases {
+			let (availability, _) = json_stock(&serde_json::from_str(answer).unwrap(), &KEYS, "Vendor", "M1", today);
+			assert_eq!((availability.status, availability.quantity, availability.available_date), (status, quantity, available_date), "{case}");
+		}
+	}
+
+	#[test]
+	fn answer_text_and_product() {
+		let today = NaiveDate::from_ymd_opt(2030, 1, 10).unwrap();
+		let answer = serde_json::json!({ "items": [{ "sku": " M1-B ", "onHand": "2", "description": "Range", "brand": "Whirlpool" }] });
+		let (availability, product) = json_stock(&answer, &KEYS, "Vendor", "M1", today);
+		assert_eq!(availability.raw, "Found: M1-B, In stock: 2");
+		let product = product.unwrap();
+		assert_eq!((product.name.as_str(), product.brand.as_deref()), ("Range", Some("Whirlpool")));
+
+		// without model, description or brand the requested model and the vendor's brand are used.
+		let (availability, product) = json_stock(&serde_json::json!({ "nextDate": "2030-02-01" }), &KEYS, "Vendor", "M1", today);
+		assert_eq!(availability.raw, "Found: M1, Available: 02/01/2030");
+		assert_eq!(product, None);
+		let (availability, _) = json_stock(&serde_json::json!({ "sku": "M1" }), &KEYS, "Vendor", "M1", today);
+		assert_eq!(availability.raw, "Next availability for M1 is unknown.");
+		let (_, product) = json_stock(&serde_json::json!({ "sku": "M1", "description": "Range" }), &KEYS, "Vendor", "M1", today);
+		assert_eq!(product.unwrap().brand.as_deref(), Some("Vendor"));
+	}
+}
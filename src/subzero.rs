@@ -1,361 +1,1863 @@
-use std::fs::File;
-use std::io::Write;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, Instant};
 
 use chrono::DateTime;
 use chrono::Utc;
-use duration_string::DurationString;
 use eggersmann_app_server_auth::SubZeroJWTTokenClaims;
-use playwright::api::Cookie as PlaywrightCookie;
 use reqwest::header::{self, HeaderMap, HeaderValue};
 use reqwest::Body;
-use reqwest::Client;
 use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tokio::sync::{Mutex, MutexGuard};
 
-use super::AvailabilityRequest;
+use super::account::{self, AccountIssue};
+use super::backend::{BackendAnswer, ManufacturerBackend};
+use super::{Availability, AvailabilityRequest, ProductInfo};
+use crate::cache::TtlCache;
+use crate::catalog::{normalize_model, CachedCatalog, CatalogItem, CatalogSnapshot};
+use crate::cookies::{SessionCookie, SessionJar};
+use crate::credentials::SecretString;
+use crate::error::AvailabilityError;
+use crate::hedge::hedged;
+use crate::history::parse_available_date;
+use crate::http_client::{self, HttpClient};
+use crate::memory::{memory_budget, Footprint};
+use crate::ratelimit::RateLimiter;
+use crate::response::{CatalogVersion, V1_NOT_FOUND};
+use crate::telemetry;
+use crate::tokens;
+
+///
+/// Name the `SubZero` session token saved by `subzero_login` is kept under in the `TokenStore`.
+///
+pub const SUBZERO_TOKEN: &str = "subzero_cookies.json";
+
+const WEB_DISPATCHER_URL: &str = "https://order.subzero.com/instance1/servlet/WebDispatcher";
+
+///
+/// Domain the saved `SubZero` cookies are sent to.
+///
+const SUBZERO_COOKIE_DOMAIN: &str = "subzero.com";
+
+///
+/// # `Fingerprint`
+/// The browser headers a `SubZero` session presents. The portal drops sessions that keep the same
+/// fingerprint for weeks, so every `subzero_login` moves on to the next entry of `subzero_fingerprints`
+/// and saves it with the session token; every request of that session then sends the same headers.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Fingerprint {
+	pub user_agent: String,
+	pub accept_language: String,
+}
+
+impl Fingerprint {
+	#[must_use]
+	pub const fn new(user_agent: String, accept_language: String) -> Self {
+		Self { user_agent, accept_language }
+	}
+
+	///
+	/// Add the fingerprint headers to `headers`.
+	///
+	fn apply(&self, headers: &mut HeaderMap) -> Result<(), String> {
+		headers.insert(header::USER_AGENT, HeaderValue::from_str(&self.user_agent).map_err(|e| format!("Failed to add user agent to header: {e:?}"))?);
+		headers.insert(header::ACCEPT_LANGUAGE, HeaderValue::from_str(&self.accept_language).map_err(|e| format!("Failed to add accept language to header: {e:?}"))?);
+		Ok(())
+	}
+}
+
+///
+/// Current desktop browsers the portal is known to accept, used until `set_subzero_fingerprints` is called.
+///
+const CURATED_FINGERPRINTS: [(&str, &str); 5] = [
+	("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/129.0.0.0 Safari/537.36 Edg/129.0.0.0", "en-US,en;q=0.9"),
+	("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/129.0.0.0 Safari/537.36", "en-US,en;q=0.9"),
+	("Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:131.0) Gecko/20100101 Firefox/131.0", "en-US,en;q=0.5"),
+	("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.6 Safari/605.1.15", "en-US,en;q=0.9"),
+	("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/129.0.0.0 Safari/537.36", "en-US,en;q=0.9"),
+];
+
+fn fingerprints() -> &'static RwLock<Vec<Fingerprint>> {
+	static FINGERPRINTS: OnceLock<RwLock<Vec<Fingerprint>>> = OnceLock::new();
+	FINGERPRINTS.get_or_init(|| RwLock::new(curated_fingerprints()))
+}
+
+fn curated_fingerprints() -> Vec<Fingerprint> {
+	CURATED_FINGERPRINTS.iter().map(|(user_agent, accept_language)| Fingerprint::new((*user_agent).to_string(), (*accept_language).to_string())).collect()
+}
+
+///
+/// # `set_subzero_fingerprints`
+/// Rotate through `fingerprints` on re-login, in order. A single entry pins the fingerprint and an empty
+/// list restores the curated one.
+///
+pub fn set_subzero_fingerprints(fingerprints: Vec<Fingerprint>) {
+	let fingerprints = if fingerprints.is_empty() { curated_fingerprints() } else { fingerprints };
+	*self::fingerprints().write().unwrap_or_else(std::sync::PoisonError::into_inner) = fingerprints;
+}
+
+///
+/// # `subzero_fingerprints`
+/// The fingerprints `subzero_login` rotates through.
+///
+#[must_use]
+pub fn subzero_fingerprints() -> Vec<Fingerprint> {
+	fingerprints().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
+
+///
+/// The fingerprint after `previous` in the rotation, the first one if `previous` is not in it.
+///
+fn next_fingerprint(previous: Option<&Fingerprint>) -> Fingerprint {
+	let fingerprints = subzero_fingerprints();
+	let next = previous.and_then(|previous| fingerprints.iter().position(|fingerprint| fingerprint == previous)).map_or(0, |index| (index + 1) % fingerprints.len());
+	fingerprints.get(next).cloned().unwrap_or_else(|| curated_fingerprints().swap_remove(0))
+}
+
+///
+/// The fingerprint of the current session, `None` until it is read from the token file or set by a login.
+///
+static SESSION_FINGERPRINT: RwLock<Option<Fingerprint>> = RwLock::new(None);
+
+///
+/// The fingerprint saved with the current session, loaded from the token file on first use.
+///
+async fn session_fingerprint() -> Fingerprint {
+	let current = SESSION_FINGERPRINT.read().unwrap_or_else(std::sync::PoisonError::into_inner).clone();
+	if let Some(fingerprint) = current {
+		return fingerprint;
+	}
+	let fingerprint = saved_fingerprint().await.unwrap_or_else(|| next_fingerprint(None));
+	SESSION_FINGERPRINT.write().unwrap_or_else(std::sync::PoisonError::into_inner).get_or_insert(fingerprint).clone()
+}
+
+///
+/// The fingerprint saved with the token by the last login, `None` for a token saved without one.
+///
+async fn saved_fingerprint() -> Option<Fingerprint> {
+	let file: Value = serde_json::from_str(&tokens::load(SUBZERO_TOKEN).await.ok()??).ok()?;
+	serde_json::from_value(file["fingerprint"].clone()).ok()
+}
+
+///
+/// # `SubzeroBackend`
+/// `SubZero` lookups through the dealer portal, registered as `subzero`. Every step of the cart flow goes
+/// through `client`, the shared client unless one is given.
+///
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct SubzeroBackend {
+	pub client: HttpClient,
+}
+
+impl SubzeroBackend {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	#[must_use]
+	pub fn with_client(mut self, client: HttpClient) -> Self {
+		self.client = client;
+		self
+	}
+}
+
+#[async_trait::async_trait]
+impl ManufacturerBackend for SubzeroBackend {
+	fn name(&self) -> &'static str {
+		"subzero"
+	}
+
+	fn mutates_cart(&self) -> bool {
+		subzero_lookup_config().cart_fallback
+	}
+
+	async fn availability(&self, req: &AvailabilityRequest) -> Result<BackendAnswer, AvailabilityError> {
+		if let Some(answer) = price_list_answer(req) {
+			return Ok(answer);
+		}
+		let (username, password) = subzero_credentials().await?;
+		if let Some(issue) = account::checked("subzero", http_client::scope(self.client.clone(), subzero_account_check(username.clone(), password.clone()))).await {
+			return Ok(BackendAnswer::account_issue(issue));
+		}
+		let answer = http_client::scope(self.client.clone(), subzero_lookup(req.clone(), username, password)).await?;
+		Ok(BackendAnswer::new(Availability::from_text(&answer.availability, req.business_today()).with_model_number(&answer.model_number)).with_product(answer.product))
+	}
+
+	async fn login(&self) -> Result<(), AvailabilityError> {
+		let (username, password) = subzero_credentials().await?;
+		Ok(http_client::scope(self.client.clone(), subzero_login(username, password)).await?)
+	}
+}
+
+///
+/// # `WolfBackend`
+/// Wolf and Cove lookups, registered as `wolf`. They are ordered through the `SubZero` portal with the
+/// `SubZero` account and session, but only a Wolf or Cove model is looked up.
+///
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct WolfBackend {
+	pub client: HttpClient,
+}
+
+impl WolfBackend {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	#[must_use]
+	pub fn with_client(mut self, client: HttpClient) -> Self {
+		self.client = client;
+		self
+	}
+}
+
+#[async_trait::async_trait]
+impl ManufacturerBackend for WolfBackend {
+	fn name(&self) -> &'static str {
+		"wolf"
+	}
+
+	fn mutates_cart(&self) -> bool {
+		subzero_lookup_config().cart_fallback
+	}
+
+	async fn availability(&self, req: &AvailabilityRequest) -> Result<BackendAnswer, AvailabilityError> {
+		let (username, password) = subzero_credentials().await?;
+		if let Some(issue) = account::checked("subzero", http_client::scope(self.client.clone(), subzero_account_check(username.clone(), password.clone()))).await {
+			return Ok(BackendAnswer::account_issue(issue));
+		}
+		let answer = http_client::scope(self.client.clone(), portal_lookup(req.clone(), &WOLF_BRANDS, username, password)).await?;
+		Ok(BackendAnswer::new(Availability::from_text(&answer.availability, req.business_today()).with_model_number(&answer.model_number)).with_product(answer.product))
+	}
+
+	async fn login(&self) -> Result<(), AvailabilityError> {
+		let (username, password) = subzero_credentials().await?;
+		Ok(http_client::scope(self.client.clone(), subzero_login(username, password)).await?)
+	}
+}
+
+///
+/// # `PortalBrand`
+/// Brands ordered through the `SubZero` portal. The portal puts the brand in front of the catalog
+/// description, e.g. `Wolf 36" Dual Fuel Range`.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PortalBrand {
+	SubZero,
+	Wolf,
+	Cove,
+}
+
+impl PortalBrand {
+	const fn name(self) -> &'static str {
+		match self {
+			Self::SubZero => "Sub-Zero",
+			Self::Wolf => "Wolf",
+			Self::Cove => "Cove",
+		}
+	}
+
+	///
+	/// The brand a catalog or cart description starts with, if any.
+	///
+	fn of_description(description: &str) -> Option<Self> {
+		let description = description.trim_start().to_lowercase();
+		[("sub-zero", Self::SubZero), ("subzero", Self::SubZero), ("sub zero", Self::SubZero), ("wolf", Self::Wolf), ("cove", Self::Cove)].into_iter().find(|(prefix, _)| description.starts_with(prefix)).map(|(_, brand)| brand)
+	}
+}
+
+///
+/// Brands a `subzero` lookup accepts, the first being the brand of a product the portal does not name.
+///
+const SUBZERO_BRANDS: [PortalBrand; 3] = [PortalBrand::SubZero, PortalBrand::Wolf, PortalBrand::Cove];
+
+///
+/// Brands a `wolf` lookup accepts.
+///
+const WOLF_BRANDS: [PortalBrand; 2] = [PortalBrand::Wolf, PortalBrand::Cove];
+
+///
+/// The `SubZero` dealer login, see `secrets::vendor_credentials`.
+///
+/// # Errors
+/// Returns an error if either secret cannot be had.
+///
+pub async fn subzero_credentials() -> Result<(SecretString, SecretString), String> {
+	crate::secrets::vendor_credentials("subzero").await
+}
 
 ///
 /// # `SubZero` Availability
 /// Gets the availability of the `SubZero` appliances.
 ///
 /// ## Inputs
-/// * `req`: `AvailabilityRequest`
+/// * `req`: `AvailabilityRequest`
+///
+/// ## Outputs
+/// String - The availability of the `SubZero` appliances.
+///
+/// # Errors
+/// Returns an error if the login fails, the portal cannot be reached or answers with an error page, or
+/// no model number was given. A model the portal does not know is not an error.
+pub async fn subzero_availability(req: AvailabilityRequest, username: SecretString, password: SecretString) -> Result<String, String> {
+	Ok(subzero_lookup(req, username, password).await?.availability)
+}
+
+///
+/// # `SubZero` Lookup
+/// Gets the availability of a `SubZero` appliance together with its catalog description.
+///
+/// A session the portal turns away, with its session expired page, a 401 or 403 or a redirect to its
+/// login, is logged in again once and the lookup repeated.
+///
+/// The availability is read from the catalog suggestion or the product detail page, which leave the cart
+/// alone. A model neither shows an availability for is added to the cart only if
+/// `SubzeroLookupConfig::with_cart_fallback` turned that on, since it changes the dealer's cart.
+///
+/// The portal keeps one cart per account, so concurrent lookups queue for it in the order they arrived,
+/// each holding the session from loading it until its cart is read back, a repeated login included.
+///
+/// # Errors
+/// Returns `AvailabilityError::VendorUnavailable` if the portal cannot be reached, the typed error of a
+/// known error page, and `AvailabilityError::Other` for a failed login or a missing model number.
+///
+async fn subzero_lookup(req: AvailabilityRequest, username: SecretString, password: SecretString) -> Result<PortalAnswer, AvailabilityError> {
+	portal_lookup(req, &SUBZERO_BRANDS, username, password).await
+}
+
+///
+/// The cart flow of `subzero_lookup` for a model of one of `brands`. A model the catalog only knows under
+/// another brand is not found.
+///
+async fn portal_lookup(req: AvailabilityRequest, brands: &[PortalBrand], username: SecretString, password: SecretString) -> Result<PortalAnswer, AvailabilityError> {
+	let _session_lock = lock_session("lookup").await;
+	let session = subzero_session(username.clone(), password.clone()).await?;
+
+	let (session, result) = match subzero_portal_lookup(&req, brands, &session).await {
+		Err(AvailabilityError::SessionExpired) => {
+			telemetry::counter("session.refreshed", 1, &[("manufacturer", "subzero".to_string())]);
+			portal_login(username.clone(), password.clone()).await?;
+			let session = subzero_session(username, password).await?;
+			let result = subzero_portal_lookup(&req, brands, &session).await;
+			(session, result)
+		}
+		result => (session, result),
+	};
+	save_subzero_session(&session).await;
+
+	match result {
+		Ok(result) => Ok(result),
+		Err(AvailabilityError::NotFound(_)) => Ok(PortalAnswer { availability: V1_NOT_FOUND.to_string(), product: None, model_number: req.model_number.unwrap_or_default() }),
+		Err(e) => Err(e),
+	}
+}
+
+///
+/// # `SubZero` Portal Lookup
+/// The read-only lookup of a model, falling back to the cart if it shows no availability.
+///
+/// # Errors
+/// Returns `AvailabilityError::SessionExpired` if the portal turned the session away, and the error of the
+/// cart lookup. Without the cart fallback a model without a read-only availability is
+/// `AvailabilityError::VendorUnavailable`.
+///
+async fn subzero_portal_lookup(req: &AvailabilityRequest, brands: &[PortalBrand], session: &SessionJar) -> Result<PortalAnswer, AvailabilityError> {
+	let Some(model_number) = &req.model_number else { return Err(AvailabilityError::Other("No model number provided".to_string())) };
+	let config = subzero_lookup_config();
+	match subzero_read_only_lookup(model_number, brands, &config, session).await {
+		Ok(Some(found)) => {
+			telemetry::counter("subzero.lookup.read_only", 1, &[]);
+			return Ok(found);
+		}
+		Ok(None) => {}
+		Err(e @ (AvailabilityError::SessionExpired | AvailabilityError::NotFound(_))) => return Err(e),
+		Err(e) => telemetry::event("subzero.lookup.read_only_failed", &[("model_number", model_number.clone()), ("error", e.to_string())]),
+	}
+	if !config.cart_fallback {
+		return Err(AvailabilityError::VendorUnavailable(format!("The SubZero portal shows no availability for {model_number} outside the cart")));
+	}
+	telemetry::counter("subzero.lookup.cart_fallback", 1, &[]);
+	subzero_cart_lookup(req, brands, session).await
+}
+
+///
+/// # `SubZero` Read-Only Lookup
+/// The availability of `model_number` from its catalog suggestion, or else from its product detail page,
+/// `None` if neither shows one.
+///
+/// # Errors
+/// Returns `AvailabilityError::NotFound` for a model of another brand, and the typed error of the detail
+/// page.
+///
+async fn subzero_read_only_lookup(model_number: &str, brands: &[PortalBrand], config: &SubzeroLookupConfig, session: &SessionJar) -> Result<Option<PortalAnswer>, AvailabilityError> {
+	let suggestion = subzero_validate_model_number(model_number.to_string(), brands, session).await?;
+	let line = match suggestion.availability.clone() {
+		Some(availability) => CartLine { availability, description: None },
+		None => match hedged("subzero", || subzero_fetch_product_detail(&suggestion.model_number, &config.detail_url, session)).await? {
+			Some(line) => line,
+			None => return Ok(None),
+		},
+	};
+	let description = line.description.or(suggestion.description);
+	let brand = description.as_deref().and_then(PortalBrand::of_description).unwrap_or(brands[0]);
+	let product = description.map(|name| ProductInfo::new(name).with_brand(brand.name().to_string()));
+	Ok(Some(PortalAnswer { availability: line.availability, product, model_number: suggestion.model_number }))
+}
+
+///
+/// # Product Detail
+/// Reads the availability and description of `model_number` from its product detail page at `url`.
+///
+/// # Errors
+/// Returns the typed error if the portal answers with one of its known error pages.
+///
+async fn subzero_fetch_product_detail(model_number: &str, url: &str, session: &SessionJar) -> Result<Option<CartLine>, AvailabilityError> {
+	let mut headers = HeaderMap::new();
+	session.apply(&mut headers, url);
+	session_fingerprint().await.apply(&mut headers)?;
+	let response = http_client::client().get(url).headers(headers).query(&[("item", model_number)]).send().await.map_err(|e| AvailabilityError::VendorUnavailable(format!("Failed to get SubZero product detail: {e:?}")))?;
+	session.capture(&response);
+	if http_client::auth_rejected(&response) {
+		return Err(AvailabilityError::SessionExpired);
+	}
+	if !response.status().is_success() {
+		return Err(AvailabilityError::VendorUnavailable(format!("Failed to get SubZero product detail: {}", response.status())));
+	}
+	let response_data = http_client::text(response).await?;
+	if let Some(error) = recognize_error_page(&response_data) {
+		return Err(error);
+	}
+	parse_product_detail(&response_data)
+}
+
+///
+/// Parse the labelled rows of a product detail page, a label cell followed by its value. A page without
+/// an availability row is `None`.
+///
+/// # Errors
+/// Returns an error if a selector cannot be parsed.
+///
+fn parse_product_detail(response_data: &str) -> Result<Option<CartLine>, AvailabilityError> {
+	let document = Html::parse_document(response_data);
+	let row_selector = Selector::parse("tr").map_err(|e| format!("Failed to parse row selector: {e:?}"))?;
+	let cell_selector = Selector::parse("th, td").map_err(|e| format!("Failed to parse cell selector: {e:?}"))?;
+
+	let (mut availability, mut description) = (None, None);
+	for row in document.select(&row_selector) {
+		let mut cells = row.select(&cell_selector);
+		let (Some(label), Some(value)) = (cells.next(), cells.next()) else { continue };
+		let label = label.text().collect::<String>().trim().to_lowercase();
+		if label.starts_with("availab") {
+			availability = Some(value.inner_html().trim().to_string()).filter(|availability| !availability.is_empty());
+		} else if label.starts_with("description") {
+			description = Some(value.text().collect::<String>().trim().to_string()).filter(|description| !description.is_empty());
+		}
+	}
+	Ok(availability.map(|availability| CartLine { availability, description }))
+}
+
+///
+/// # `SubzeroLookupConfig`
+/// Where `SubZero` lookups read a model's availability without touching the cart, the product detail page
+/// at `detail_url` with the model number as `item`, and whether a model it shows no availability for is
+/// looked up through the shared cart; off by default.
+///
+/// ## Example
+/// ```
+/// use eggersmann_app_server_appliance_availability::{set_subzero_lookup_config, SubzeroLookupConfig};
+///
+/// set_subzero_lookup_config(SubzeroLookupConfig::default().with_cart_fallback());
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SubzeroLookupConfig {
+	pub detail_url: String,
+	pub cart_fallback: bool,
+}
+
+impl SubzeroLookupConfig {
+	#[must_use]
+	pub const fn new(detail_url: String) -> Self {
+		Self { detail_url, cart_fallback: false }
+	}
+
+	///
+	/// # `SubzeroLookupConfig::with_cart_fallback`
+	/// Add a model the read-only pages show no availability for to the shared cart and read it from there.
+	/// Only for accounts without orders in progress in the cart.
+	///
+	#[must_use]
+	pub const fn with_cart_fallback(mut self) -> Self {
+		self.cart_fallback = true;
+		self
+	}
+}
+
+impl Default for SubzeroLookupConfig {
+	fn default() -> Self {
+		Self::new("https://order.subzero.com/instance1/servlet/WebDispatcher?mode=detail".to_string())
+	}
+}
+
+fn lookup_config_slot() -> &'static RwLock<SubzeroLookupConfig> {
+	static CONFIG: OnceLock<RwLock<SubzeroLookupConfig>> = OnceLock::new();
+	CONFIG.get_or_init(|| RwLock::new(SubzeroLookupConfig::default()))
+}
+
+///
+/// # `set_subzero_lookup_config`
+/// Set the product detail page lookups read and whether they fall back to the cart.
+///
+pub fn set_subzero_lookup_config(config: SubzeroLookupConfig) {
+	*lookup_config_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = config;
+}
+
+///
+/// # `subzero_lookup_config`
+/// The product detail page lookups read and whether they fall back to the cart.
+///
+#[must_use]
+pub fn subzero_lookup_config() -> SubzeroLookupConfig {
+	lookup_config_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
+
+///
+/// # `SubZero` Cart Lookup
+/// Adds the requested model to the shared cart, reads its availability back from the cart table and
+/// removes the line it added again. Lines already in the cart, such as an order in progress, are left
+/// alone.
+///
+/// # Errors
+/// Returns the typed error for any known `WebDispatcher` error page.
+///
+/// The caller holds the session lock, the cart being shared by every lookup of the account.
+///
+async fn subzero_cart_lookup(req: &AvailabilityRequest, brands: &[PortalBrand], session: &SessionJar) -> Result<PortalAnswer, AvailabilityError> {
+	// validate the requested model number is in the SubZero catalog.
+	let suggestion = match &req.model_number {
+		Some(model_number) => hedged("subzero", || subzero_validate_model_number(model_number.clone(), brands, session)).await?,
+		None => return Err(AvailabilityError::Other("No model number provided".to_string())),
+	};
+
+	let lines_before = hedged("subzero", || subzero_get_number_of_items(session)).await?;
+	let cart = CartGuard::new(session, lines_before);
+	// add the item to the SubZero cart and return availability. Never hedged: a second add would leave a
+	// second line in the cart.
+	let lines = subzero_add_item(suggestion.model_number.clone(), req.requested_quantity(), session).await?;
+	let removed = match added_line_index(lines_before, lines.len()) {
+		Some(index) => subzero_remove_item(index, session).await,
+		None => Ok(()),
+	};
+	// a line that could not be removed is left to the guard, the availability is still answered.
+	if removed.is_ok() {
+		cart.disarm();
+	}
+	let line = lines.into_iter().last().unwrap_or_else(CartLine::not_found);
+	let description = line.description.or(suggestion.description);
+	let brand = description.as_deref().and_then(PortalBrand::of_description).unwrap_or(brands[0]);
+	let product = description.map(|name| ProductInfo::new(name).with_brand(brand.name().to_string()));
+	Ok(PortalAnswer { availability: line.availability, product, model_number: suggestion.model_number })
+}
+
+///
+/// Most items removed by one `subzero_clear_cart`, so a portal that never empties its cart cannot keep a
+/// lookup busy forever.
+///
+const MAX_CART_ITEMS_REMOVED: u32 = 50;
+
+///
+/// # Clear Cart
+/// Removes every item from the `SubZero` cart, the dealer's own lines included. Only run when asked to
+/// through `clear_subzero_cart`.
+///
+/// # Errors
+/// Returns the typed error of the portal, or `AvailabilityError::CartStuck` if the cart still has items
+/// after `MAX_CART_ITEMS_REMOVED` removals.
+///
+async fn subzero_clear_cart(session: &SessionJar) -> Result<(), AvailabilityError> {
+	let mut number_of_items = hedged("subzero", || subzero_get_number_of_items(session)).await?;
+	let mut removed = 0;
+	while number_of_items > 0 {
+		if removed == MAX_CART_ITEMS_REMOVED {
+			return Err(AvailabilityError::CartStuck { items: u32::try_from(number_of_items).unwrap_or(u32::MAX) });
+		}
+		subzero_remove_item(0, session).await?;
+		removed += 1;
+		number_of_items = hedged("subzero", || subzero_get_number_of_items(session)).await?;
+	}
+	CART_DIRTY.store(false, Ordering::Relaxed);
+	Ok(())
+}
+
+///
+/// # `clear_subzero_cart`
+/// Empty the shared `SubZero` cart with the saved session, logging in if there is none, the dealer's own
+/// lines included. Each call removes up to `MAX_CART_ITEMS_REMOVED` items; lookups wait while it runs.
+///
+/// # Errors
+/// Returns `AvailabilityError::CartStuck` if items are left, so the call can be repeated, or the error of
+/// the credentials, the login or the portal.
+///
+pub async fn clear_subzero_cart() -> Result<(), AvailabilityError> {
+	let (username, password) = subzero_credentials().await?;
+	let _session_lock = lock_session("clear").await;
+	let session = subzero_session(username, password).await?;
+	let cleared = subzero_clear_cart(&session).await;
+	save_subzero_session(&session).await;
+	cleared
+}
+
+///
+/// Set when a lookup could not remove the line it added to the `SubZero` cart, cleared by the next cart
+/// that is emptied.
+///
+static CART_DIRTY: AtomicBool = AtomicBool::new(false);
+
+///
+/// Whether a failed cleanup left a line of a lookup in the `SubZero` cart, for `clear_subzero_cart` to
+/// remove.
+///
+pub fn subzero_cart_dirty() -> bool {
+	CART_DIRTY.load(Ordering::Relaxed)
+}
+
+///
+/// # Cart Guard
+/// Removes the line a lookup added to the `SubZero` cart when the lookup fails or is cancelled, e.g. by
+/// its deadline, before it removed the line itself. The cart had `lines_before` lines before the add, so
+/// a line past those is the lookup's; the lines before it are left alone.
+///
+/// The cleanup runs on a spawned task under the session lock, with the client the lookup used. If it
+/// fails the `subzero.cart_dirty` event is emitted and `subzero_cart_dirty` is set.
+///
+struct CartGuard {
+	session: SessionJar,
+	client: HttpClient,
+	lines_before: usize,
+	armed: bool,
+}
+
+impl CartGuard {
+	fn new(session: &SessionJar, lines_before: usize) -> Self {
+		Self { session: session.clone(), client: http_client::client(), lines_before, armed: true }
+	}
+
+	fn disarm(mut self) {
+		self.armed = false;
+	}
+}
+
+impl Drop for CartGuard {
+	fn drop(&mut self) {
+		if !self.armed {
+			return;
+		}
+		let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+			CART_DIRTY.store(true, Ordering::Relaxed);
+			telemetry::event("subzero.cart_dirty", &[("cart_dirty", "true".to_string()), ("error", "no runtime to clean up on".to_string())]);
+			return;
+		};
+		let session = self.session.clone();
+		let client = self.client.clone();
+		let lines_before = self.lines_before;
+		runtime.spawn(async move {
+			let _session_lock = lock_session("cleanup").await;
+			if let Err(e) = http_client::scope(client, subzero_remove_added_line(lines_before, &session)).await {
+				CART_DIRTY.store(true, Ordering::Relaxed);
+				telemetry::counter("subzero.cart_dirty", 1, &[]);
+				telemetry::event("subzero.cart_dirty", &[("cart_dirty", "true".to_string()), ("error", e.to_string())]);
+			}
+		});
+	}
+}
+
+///
+/// Removes the last line of the cart if the cart has more than the `lines_before` it had before a lookup
+/// added its line.
+///
+async fn subzero_remove_added_line(lines_before: usize, session: &SessionJar) -> Result<(), AvailabilityError> {
+	let lines = hedged("subzero", || subzero_get_number_of_items(session)).await?;
+	match added_line_index(lines_before, lines) {
+		Some(index) => subzero_remove_item(index, session).await,
+		None => Ok(()),
+	}
+}
+
+///
+/// Index of the line an add appended to a cart of `lines_before` lines, now `lines` long. The portal
+/// appends new lines, and an add that made no new line, e.g. a model it does not know, has none.
+///
+const fn added_line_index(lines_before: usize, lines: usize) -> Option<usize> {
+	if lines > lines_before {
+		Some(lines - 1)
+	} else {
+		None
+	}
+}
+
+///
+/// # Recognize Error Page
+/// Maps the `WebDispatcher`'s known HTML error templates to typed errors.
+///
+/// Pages that contain the cart table are never treated as errors, so item descriptions that happen to
+/// contain one of the phrases are not misread.
+///
+fn recognize_error_page(response_data: &str) -> Option<AvailabilityError> {
+	let document = Html::parse_document(response_data);
+	if Selector::parse("#myScrollTable").is_ok_and(|selector| document.select(&selector).next().is_some()) {
+		return None;
+	}
+
+	let text = document.root_element().text().collect::<String>().to_lowercase();
+	if SESSION_EXPIRED_PHRASES.iter().any(|phrase| text.contains(phrase)) {
+		Some(AvailabilityError::SessionExpired)
+	} else if INVALID_ITEM_PHRASES.iter().any(|phrase| text.contains(phrase)) {
+		Some(AvailabilityError::NotFound(String::new()))
+	} else if SYSTEM_UNAVAILABLE_PHRASES.iter().any(|phrase| text.contains(phrase)) {
+		Some(AvailabilityError::VendorUnavailable("SubZero order portal reported the system as unavailable.".to_string()))
+	} else {
+		None
+	}
+}
+
+const SESSION_EXPIRED_PHRASES: [&str; 4] = ["session expired", "session has expired", "please log on", "please logon"];
+const INVALID_ITEM_PHRASES: [&str; 3] = ["invalid item", "is not a valid item", "item not found"];
+const SYSTEM_UNAVAILABLE_PHRASES: [&str; 3] = ["system unavailable", "system is unavailable", "temporarily unavailable"];
+
+///
+/// Phrases of the `WebDispatcher`'s account message page that mean the account cannot order.
+///
+const ACCOUNT_BLOCK_PHRASES: [&str; 5] = ["account is on hold", "account has been suspended", "account is inactive", "credit hold", "contact your credit department"];
+
+///
+/// # `SubZero` Account Check
+/// Looks for the account message page the `WebDispatcher` shows instead of the cart when the account is
+/// blocked.
+///
+/// Holds the session lock, so a login it makes does not replace the session of a running lookup.
+///
+/// # Errors
+/// Returns an error if the session or the cart page is unavailable.
+///
+pub async fn subzero_account_check(username: SecretString, password: SecretString) -> Result<Option<AccountIssue>, String> {
+	let _session_lock = lock_session("account").await;
+	let session = subzero_session(username, password).await?;
+	let mut headers = HeaderMap::new();
+	session.apply(&mut headers, WEB_DISPATCHER_URL);
+	session_fingerprint().await.apply(&mut headers)?;
+	let response = http_client::client().get("https://order.subzero.com/instance1/servlet/WebDispatcher?mode=view&error=0").headers(headers).send().await.map_err(|e| format!("Failed to get SubZero cart page: {e:?}"))?;
+	session.capture(&response);
+	save_subzero_session(&session).await;
+	let page = http_client::text(response).await.map_err(|e| format!("Failed to read SubZero cart page: {e:?}"))?;
+	let text = Html::parse_document(&page).root_element().text().collect::<String>();
+	Ok(account::find_block(&text, &ACCOUNT_BLOCK_PHRASES).map(|reason| AccountIssue::new("subzero".to_string(), reason.to_string())))
+}
+
+///
+/// # Get `SubZero` Token
+/// Retrives the `SubZero` token from the server.
+///
+/// ## Outputs
+/// Result<`SubZeroJWTTokenClaims`, String> - The `SubZero` token claims.
+///
+async fn get_subzero_token() -> Result<SubZeroJWTTokenClaims, String> {
+	let file = match tokens::load(SUBZERO_TOKEN).await {
+		Ok(Some(file)) => file,
+		Ok(None) => return Err("No SubZero token has been saved.".to_string()),
+		Err(e) => return Err(format!("Failed to open SubZero token file: {e:?}")),
+	};
+	let file: Value = match serde_json::from_str(&file) {
+		Ok(file) => file,
+		Err(e) => return Err(format!("Failed to parse SubZero token file: {e:?}")),
+	};
+	let Some(token) = file["token"].as_str() else { return Err("Failed to get SubZero token from file.".to_string()) };
+	SubZeroJWTTokenClaims::decode(token).await
+}
+
+///
+/// # Get the Number of Items in the `SubZero` Cart
+/// Gets the number of items in the `SubZero` cart.
+///
+/// ## Inputs
+/// * `session`: `SessionJar` - The session to send the request with.
+///
+/// ## Outputs
+/// usize - The number of lines in the `SubZero` cart.
+///
+/// # Errors
+/// Returns the typed error if the portal answers with one of its known error pages.
+///
+async fn subzero_get_number_of_items(session: &SessionJar) -> Result<usize, AvailabilityError> {
+	let client = http_client::client();
+	let data = json!({
+		"mode": " view",
+		"error": " 0",
+	})
+	.to_string();
+
+	let mut headers = HeaderMap::new();
+	session.apply(&mut headers, WEB_DISPATCHER_URL);
+	session_fingerprint().await.apply(&mut headers)?;
+	headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/x-www-form-urlencoded"));
+	headers.insert("data", HeaderValue::from_str(data.as_str()).map_err(|e| format!("Failed to add data to header: {e:?}"))?);
+
+	let response = client.get("https://order.subzero.com/instance1/servlet/WebDispatcher?mode=view&error=0").headers(headers).body(Body::from(data)).send().await.map_err(|e| AvailabilityError::VendorUnavailable(format!("Failed to get SubZero cart: {e:?}")))?;
+	session.capture(&response);
+	if http_client::auth_rejected(&response) {
+		return Err(AvailabilityError::SessionExpired);
+	}
+	if !response.status().is_success() {
+		return Err(AvailabilityError::VendorUnavailable(format!("Failed to get SubZero cart: {}", response.status())));
+	}
+	let response_data = http_client::text(response).await?;
+	if let Some(error) = recognize_error_page(&response_data) {
+		return Err(error);
+	}
+	// the portal may leave the table out of an empty cart.
+	Ok(parse_cart_lines(&response_data)?.map_or(0, |lines| lines.len()))
+}
+
+///
+/// # Remove Item
+/// Removes the line at `index` from the `SubZero` cart, `0` being the first.
+///
+/// ## Inputs
+/// * `index`: usize - The position of the line in the cart table.
+/// * `session`: `SessionJar` - The session to send the request with.
+///
+/// # Errors
+/// Returns `AvailabilityError::VendorUnavailable` if the portal cannot be reached or does not remove the
+/// item, and the typed error if it answers with one of its known error pages.
+///
+async fn subzero_remove_item(index: usize, session: &SessionJar) -> Result<(), AvailabilityError> {
+	let client = http_client::client();
+
+	let mut headers = HeaderMap::new();
+	session.apply(&mut headers, WEB_DISPATCHER_URL);
+	session_fingerprint().await.apply(&mut headers)?;
+	headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/x-www-form-urlencoded"));
+
+	let index = index.to_string();
+	let params = [("mode", "delete"), ("index", index.as_str()), ("x", "3"), ("y", "9")];
+	let response = client.post(format!("{WEB_DISPATCHER_URL}?mode=delete&index={index}&x=3&y=9")).headers(headers).form(&params).send().await.map_err(|e| AvailabilityError::VendorUnavailable(format!("Failed to remove item from cart: {e:?}")))?;
+	session.capture(&response);
+	if http_client::auth_rejected(&response) {
+		return Err(AvailabilityError::SessionExpired);
+	}
+	if !response.status().is_success() {
+		return Err(AvailabilityError::VendorUnavailable(format!("Failed to remove item from cart: {}", response.status())));
+	}
+	let response_data = http_client::text(response).await?;
+	recognize_error_page(&response_data).map_or(Ok(()), Err)
+}
+
+///
+/// # Add Item
+/// Adds an item to the `SubZero` cart and returns the lines of the cart after the add, the new line last.
+///
+/// ## Inputs
+/// * `session`: `SessionJar` - The session to send the request with.
+/// * `model_number`: String - The model number of the item to add.
+///
+/// ## Outputs
+/// Vec<`CartLine`> - The availability date and description of each line.
+///
+/// # Errors
+/// Returns the typed error if the portal answers with one of its known error pages.
+///
+async fn subzero_add_item(model_number: String, quantity: u32, session: &SessionJar) -> Result<Vec<CartLine>, AvailabilityError> {
+	let client = http_client::client();
+
+	let mut headers = HeaderMap::new();
+	session.apply(&mut headers, WEB_DISPATCHER_URL);
+	session_fingerprint().await.apply(&mut headers)?;
+	headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/x-www-form-urlencoded"));
+	let quantity = quantity.to_string();
+	let params = [("item", &model_number), ("quantity", &quantity)];
+
+	let data = json!({
+		"item": model_number,
+		"quantity": quantity,
+	});
+
+	let response = client.post("https://order.subzero.com/instance1/servlet/WebDispatcher?mode=add").headers(headers).body(Body::from(data.to_string())).form(&params).send().await.map_err(|e| AvailabilityError::VendorUnavailable(format!("Failed to add item to cart: {e:?}")))?;
+	session.capture(&response);
+	if http_client::auth_rejected(&response) {
+		return Err(AvailabilityError::SessionExpired);
+	}
+	if !response.status().is_success() {
+		return Err(AvailabilityError::VendorUnavailable(format!("Failed to add item to cart: {}", response.status())));
+	}
+
+	let response_data = http_client::text(response).await?;
+
+	parse_added_cart(&response_data)
+}
+
+///
+/// The lines of the cart page the portal answers an add with, the new line last.
+///
+/// # Errors
+/// Returns the typed error of a known error page, and `AvailabilityError::VendorUnavailable` for a page
+/// that is neither an error page nor the cart, so an unexpected page is not read as an unknown model.
+///
+fn parse_added_cart(response_data: &str) -> Result<Vec<CartLine>, AvailabilityError> {
+	if let Some(error) = recognize_error_page(response_data) {
+		return Err(error);
+	}
+	parse_cart_lines(response_data)?.ok_or_else(|| AvailabilityError::VendorUnavailable("SubZero answered the cart add without the cart table.".to_string()))
+}
+
+///
+/// # Portal Answer
+/// What the portal answered for a model: its availability text, its catalog description and the model
+/// number the portal was asked for, which is the one the availability is for.
+///
+#[derive(Debug, Clone)]
+struct PortalAnswer {
+	availability: String,
+	product: Option<ProductInfo>,
+	model_number: String,
+}
+
+///
+/// # Cart Line
+/// The line the `SubZero` cart table shows for an item.
+///
+#[derive(Debug, Clone)]
+struct CartLine {
+	availability: String,
+	description: Option<String>,
+}
+
+impl CartLine {
+	///
+	/// The line of a model the cart table does not show, which reads as not found.
+	///
+	fn not_found() -> Self {
+		Self { availability: "Error finding item.".to_string(), description: None }
+	}
+}
+
+const CART_DESCRIPTION_COLUMN: usize = 2;
+const CART_AVAILABILITY_COLUMN: usize = 7;
+
+///
+/// Parse every line of the `#myScrollTable` cart table in cart order, the rows with cells of its body.
+/// `None` for a page without the cart table.
+///
+/// # Errors
+/// Returns an error if a selector cannot be parsed.
+///
+fn parse_cart_lines(response_data: &str) -> Result<Option<Vec<CartLine>>, AvailabilityError> {
+	let document = Html::parse_document(response_data);
+	let my_scroll_table_selector = Selector::parse("#myScrollTable").map_err(|e| format!("Failed to parse my scroll table selector: {e:?}"))?;
+	let table_body_selector = Selector::parse("tbody").map_err(|e| format!("Failed to parse table body selector: {e:?}"))?;
+	let row_selector = Selector::parse("tr").map_err(|e| format!("Failed to parse row selector: {e:?}"))?;
+	let td_selector = Selector::parse("td").map_err(|e| format!("Failed to parse td selector: {e:?}"))?;
+
+	let Some(my_scroll_table) = document.select(&my_scroll_table_selector).next() else { return Ok(None) };
+	let Some(table_body) = my_scroll_table.select(&table_body_selector).next() else { return Ok(Some(Vec::new())) };
+	let mut lines = Vec::new();
+	for row in table_body.select(&row_selector) {
+		let mut line = CartLine::not_found();
+		let mut cells = 0;
+		for (i, cell) in row.select(&td_selector).enumerate() {
+			cells += 1;
+			match i {
+				CART_DESCRIPTION_COLUMN => line.description = Some(cell.text().collect::<String>().trim().to_string()).filter(|description| !description.is_empty()),
+				CART_AVAILABILITY_COLUMN => line.availability = cell.inner_html(),
+				_ => {}
+			}
+		}
+		if cells > 0 {
+			lines.push(line);
+		}
+	}
+	Ok(Some(lines))
+}
+
+///
+/// The catalog entry of `model_number` among `brands`, see `matching_suggestion`.
+///
+/// # Errors
+/// Returns `AvailabilityError::NotFound` if the catalog knows the model, or every model it suggests, only
+/// under other brands.
+///
+async fn subzero_validate_model_number(model_number: String, brands: &[PortalBrand], session: &SessionJar) -> Result<Suggestion, AvailabilityError> {
+	match subzero_fetch_suggestions(&model_number, session).await {
+		Ok(suggestions) => matching_suggestion(&model_number, suggestions, brands),
+		Err(AvailabilityError::SessionExpired) => Err(AvailabilityError::SessionExpired),
+		// the suggestion only adds a description, the model is still looked up as requested.
+		Err(e) => {
+			telemetry::event("subzero.suggest.failed", &[("model_number", model_number.clone()), ("error", e.to_string())]);
+			Ok(Suggestion::new(model_number))
+		}
+	}
+}
+
+///
+/// The suggestion for exactly `model_number`, compared trimmed and in upper case, so a sibling such as
+/// `DF366LP` never stands in for `DF366`. Without one the model is looked up as requested.
+///
+/// # Errors
+/// Returns `AvailabilityError::NotFound` if the suggestion for the model is of a brand other than
+/// `brands`, or there is none and every suggestion is of another brand.
+///
+fn matching_suggestion(model_number: &str, suggestions: Vec<Suggestion>, brands: &[PortalBrand]) -> Result<Suggestion, AvailabilityError> {
+	let of_brands = |suggestion: &Suggestion| suggestion.description.as_deref().and_then(PortalBrand::of_description).is_none_or(|brand| brands.contains(&brand));
+	let not_found = || AvailabilityError::NotFound(format!("{model_number} is not a {} model", brands.iter().map(|brand| brand.name()).collect::<Vec<_>>().join(" or ")));
+	let wanted = model_number.trim().to_uppercase();
+	if !suggestions.iter().any(of_brands) && !suggestions.is_empty() {
+		return Err(not_found());
+	}
+	match suggestions.into_iter().find(|suggestion| suggestion.model_number.trim().to_uppercase() == wanted) {
+		Some(suggestion) if of_brands(&suggestion) => Ok(suggestion),
+		Some(_) => Err(not_found()),
+		None => Ok(Suggestion::new(model_number.to_string())),
+	}
+}
+
+///
+/// # `SubZero` Suggestion
+/// A single entry from the `SubZero` catalog auto-complete.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Suggestion {
+	pub model_number: String,
+	pub description: Option<String>,
+	/// `Sub-Zero`, `Wolf` or `Cove`, read from the description.
+	#[serde(default)]
+	pub brand: Option<String>,
+	/// The availability the catalog shows with the suggestion, if it shows one.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub availability: Option<String>,
+}
+
+impl Suggestion {
+	const fn new(model_number: String) -> Self {
+		Self { model_number, description: None, brand: None, availability: None }
+	}
+
+	fn with_description(model_number: String, description: Option<String>) -> Self {
+		let brand = description.as_deref().and_then(PortalBrand::of_description).map(|brand| brand.name().to_string());
+		Self { model_number, description, brand, availability: None }
+	}
+
+	fn with_availability(mut self, availability: Option<String>) -> Self {
+		self.availability = availability;
+		self
+	}
+}
+
+impl Footprint for Suggestion {
+	fn heap_bytes(&self) -> usize {
+		self.model_number.heap_bytes() + self.description.heap_bytes() + self.brand.heap_bytes() + self.availability.heap_bytes()
+	}
+}
+
+const SUGGEST_CACHE_TTL: Duration = Duration::from_hours(24);
+const SUGGEST_INTERVAL: Duration = Duration::from_millis(500);
+
+pub fn suggest_cache() -> &'static TtlCache<String, Vec<Suggestion>> {
+	static CACHE: OnceLock<TtlCache<String, Vec<Suggestion>>> = OnceLock::new();
+	CACHE.get_or_init(|| {
+		let cache = TtlCache::new(SUGGEST_CACHE_TTL);
+		cache.set_max_bytes(memory_budget().suggest_cache_bytes);
+		cache
+	})
+}
+
+static SUGGEST_LIMITER: RateLimiter = RateLimiter::new(SUGGEST_INTERVAL);
+
+///
+/// Serializes use of the shared `SubZero` session, whose server-side cart every lookup mutates. Logins,
+/// account checks, suggestions and price list exports take it as well, since each may log in and replace
+/// the session or rotate its fingerprint while a lookup uses it.
+///
+static SESSION_LOCK: Mutex<()> = Mutex::const_new(());
+
+///
+/// Wait for the `SubZero` session, in the order callers arrived, recording how long `purpose` waited.
+///
+async fn lock_session(purpose: &'static str) -> MutexGuard<'static, ()> {
+	let started = Instant::now();
+	let guard = SESSION_LOCK.lock().await;
+	telemetry::latency("subzero.session.wait", started.elapsed(), &[("purpose", purpose.to_string())]);
+	guard
+}
+
+///
+/// # `SubZero` Suggest
+/// Auto-complete a model number prefix against the live `SubZero` catalog.
+///
+/// Results are cached per prefix for 24 hours and calls to the portal are rate limited, so this is
+/// safe to call on every keystroke.
+///
+/// ## Inputs
+/// * `prefix`: &str - The partial model number typed by the user.
 ///
 /// ## Outputs
-/// String - The availability of the `SubZero` appliances.
+/// Vec<`Suggestion`> - Matching catalog entries in portal order.
 ///
 /// # Errors
-/// todo
-pub async fn subzero_availability(mut req: AvailabilityRequest, username: String, password: String) -> Result<String, String> {
-	// get subzero token, if not already obtained then login.
-	let token = get_subzero_token().await;
-	let token = if let Ok(token) = token {
-		token
-	} else {
-		subzero_login(username, password).await?;
-		match get_subzero_token().await {
-			Ok(token) => token,
-			Err(e) => return Ok(format!("Failed to get SubZero token: {e:?}")),
-		}
-	};
+/// Returns an error if the portal cannot be reached, the login fails or the portal answers with an error
+/// or login page instead of suggestions. A session it turns away is logged in again once.
+pub async fn subzero_suggest(prefix: &str, username: SecretString, password: SecretString) -> Result<Vec<Suggestion>, String> {
+	let key = prefix.trim().to_uppercase();
+	if key.is_empty() {
+		return Ok(Vec::new());
+	}
+	if let Some(suggestions) = suggest_cache().get(&key) {
+		return Ok(suggestions);
+	}
 
-	// parse cookies from token
-	let cookies: String = {
-		let mut cookies: String = String::new();
-		for cookie in &token.subzero_cookies {
-			cookies.push_str(&cookie.name);
-			cookies.push('=');
-			cookies.push_str(&cookie.value);
-			cookies.push_str("; ");
+	let _session_lock = lock_session("suggest").await;
+	let session = subzero_session(username.clone(), password.clone()).await?;
+	SUGGEST_LIMITER.acquire().await;
+	let (session, suggestions) = match subzero_fetch_suggestions(&key, &session).await {
+		Err(AvailabilityError::SessionExpired) => {
+			telemetry::counter("session.refreshed", 1, &[("manufacturer", "subzero".to_string())]);
+			portal_login(username.clone(), password.clone()).await?;
+			let session = subzero_session(username, password).await?;
+			let suggestions = subzero_fetch_suggestions(&key, &session).await;
+			(session, suggestions)
 		}
-		cookies
+		suggestions => (session, suggestions),
 	};
+	save_subzero_session(&session).await;
+	// only a recognised answer is cached, never a login or error page.
+	let suggestions = suggestions?;
+	suggest_cache().insert(key, suggestions.clone());
+	Ok(suggestions)
+}
 
-	// get the number of items in the SubZero cart, if it contains items then clear the cart.
-	let mut number_of_items = subzero_get_number_of_items(&cookies).await;
-	while number_of_items > 0 {
-		subzero_remove_item(&cookies).await;
-		number_of_items = subzero_get_number_of_items(&cookies).await;
-	}
+///
+/// # Fetch Suggestions
+/// The catalog suggestions of the portal for `search`.
+///
+/// # Errors
+/// Returns `AvailabilityError::SessionExpired` if the portal turned the session away,
+/// `AvailabilityError::VendorUnavailable` if it cannot be reached or answers with an error status, and the
+/// error of `parse_suggest_answer` for a page that is not a suggestion list.
+///
+async fn subzero_fetch_suggestions(search: &str, session: &SessionJar) -> Result<Vec<Suggestion>, AvailabilityError> {
+	let client = http_client::client();
+	let mut headers = HeaderMap::new();
 
-	// validate the requested model number is in the SubZero catalog.
-	req.model_number = match &req.model_number {
-		Some(model_number) => Some(subzero_validate_model_number(model_number.to_string(), &cookies).await),
-		None => return Ok("No model number provided".to_string()),
-	};
+	headers.insert(header::ACCEPT, HeaderValue::from_static("*/*"));
+	session_fingerprint().await.apply(&mut headers)?;
+	headers.insert(header::HOST, HeaderValue::from_static("order.subzero.com"));
+	session.apply(&mut headers, WEB_DISPATCHER_URL);
 
-	// add items to the SubZero cart and return availability.
-	match &req.model_number {
-		Some(model_number) => Ok(subzero_add_item(model_number.to_string(), &cookies).await),
-		None => Ok("Model number not provided.".to_string()),
+	let url = format!("https://order.subzero.com/instance1/servlet/WebDispatcher?mode=suggest&type=advanced&search={}", urlencoding::encode(search));
+	let response = client.get(url).headers(headers).send().await.map_err(|e| AvailabilityError::VendorUnavailable(format!("Failed to get suggested items: {e:?}")))?;
+	session.capture(&response);
+	if http_client::auth_rejected(&response) {
+		return Err(AvailabilityError::SessionExpired);
 	}
+	if !response.status().is_success() {
+		return Err(AvailabilityError::VendorUnavailable(format!("Failed to get suggested items: {}", response.status())));
+	}
+	let response_data = http_client::text(response).await?;
+	parse_suggest_answer(&response_data)
 }
 
 ///
-/// # Get `SubZero` Token
-/// Retrives the `SubZero` token from the server.
+/// The suggestions of an answer of the `mode=suggest` servlet, see `parse_suggestions`.
 ///
-/// ## Outputs
-/// Result<`SubZeroJWTTokenClaims`, String> - The `SubZero` token claims.
+/// # Errors
+/// Returns `AvailabilityError::SessionExpired` for the logon form, the typed error of a known error page,
+/// and `AvailabilityError::VendorUnavailable` for any other HTML page, so a login or error page is never
+/// read as suggestions.
 ///
-async fn get_subzero_token() -> Result<SubZeroJWTTokenClaims, String> {
-	let file = match File::open("/easfiles/appliances/cookies/subzero_cookies.json") {
-		Ok(file) => file,
-		Err(e) => return Err(format!("Failed to open SubZero token file: {e:?}")),
+fn parse_suggest_answer(response_data: &str) -> Result<Vec<Suggestion>, AvailabilityError> {
+	if is_logon_form(response_data) {
+		return Err(AvailabilityError::SessionExpired);
+	}
+	if let Some(error) = recognize_error_page(response_data) {
+		return Err(error);
+	}
+	let lowercase = response_data.to_lowercase();
+	if lowercase.contains("<html") || lowercase.contains("<body") {
+		return Err(AvailabilityError::VendorUnavailable("SubZero answered the suggestion search with an unexpected page.".to_string()));
+	}
+	Ok(parse_suggestions(response_data))
+}
+
+///
+/// Parse the body returned by the `mode=suggest` servlet.
+///
+/// The servlet answers with one suggestion per line (`MODEL` or `MODEL|Description`) optionally followed
+/// by a JSON trailer with the same entries as `{"item": ..., "description": ...}` objects. The JSON form
+/// is preferred when present.
+///
+fn parse_suggestions(body: &str) -> Vec<Suggestion> {
+	let (head, trailer) = body.find(['{', '[']).map_or((body, ""), |index| body.split_at(index));
+
+	if let Ok(value) = serde_json::from_str::<Value>(trailer.trim()) {
+		let items = match &value {
+			Value::Array(items) => items.clone(),
+			Value::Object(object) => object.values().find_map(|v| v.as_array().cloned()).unwrap_or_else(|| vec![value.clone()]),
+			_ => Vec::new(),
+		};
+		let suggestions: Vec<Suggestion> = items
+			.iter()
+			.filter_map(|item| {
+				let model_number = ["item", "value", "model", "id"].iter().find_map(|key| item[key].as_str()).or_else(|| item.as_str())?.trim().to_string();
+				let description = ["description", "label", "desc"].iter().find_map(|key| item[key].as_str()).map(|d| d.trim().to_string()).filter(|d| !d.is_empty() && *d != model_number);
+				let availability = ["availability", "available", "avail"].iter().find_map(|key| item[key].as_str()).map(|a| a.trim().to_string()).filter(|a| !a.is_empty());
+				(!model_number.is_empty()).then(|| Suggestion::with_description(model_number, description).with_availability(availability))
+			})
+			.collect();
+		if !suggestions.is_empty() {
+			return suggestions;
+		}
+	}
+
+	head.lines()
+		.filter_map(|line| {
+			let mut parts = line.splitn(2, ['|', '\t']);
+			let model_number = parts.next()?.trim().to_string();
+			let description = parts.next().map(|d| d.trim().to_string()).filter(|d| !d.is_empty());
+			(!model_number.is_empty()).then(|| Suggestion::with_description(model_number, description))
+		})
+		.collect()
+}
+
+///
+/// # Get `SubZero` Session
+/// The session of the stored `SubZero` token, logging in first if there is none.
+///
+async fn subzero_session(username: SecretString, password: SecretString) -> Result<SessionJar, String> {
+	let token = if let Ok(token) = get_subzero_token().await {
+		token
+	} else {
+		portal_login(username, password).await?;
+		get_subzero_token().await.map_err(|e| format!("Failed to get SubZero token: {e:?}"))?
 	};
-	let file: Value = match serde_json::from_reader(file) {
-		Ok(file) => file,
-		Err(e) => return Err(format!("Failed to parse SubZero token file: {e:?}")),
+	SessionJar::new(WEB_DISPATCHER_URL, SUBZERO_COOKIE_DOMAIN, token.subzero_cookies.iter().map(|cookie| SessionCookie::saved(&cookie.name, &cookie.value, cookie.domain.as_deref(), cookie.path.as_deref(), cookie.expires)))
+}
+
+///
+/// # Save `SubZero` Session
+/// Save `session` as the `SubZero` token, with the fingerprint it was made with, if the portal refreshed
+/// it. A session that cannot be saved is kept for this lookup only.
+///
+async fn save_subzero_session(session: &SessionJar) {
+	if !session.take_changed() {
+		return;
+	}
+	let fingerprint = session_fingerprint().await;
+	let saved = match SubZeroJWTTokenClaims::encode(session.token_cookies()).await {
+		Ok(token) => tokens::save(SUBZERO_TOKEN, &json!({ "token": token, "fingerprint": fingerprint }).to_string()).await.map_err(|e| format!("{e:?}")),
+		Err(e) => Err(format!("{e:?}")),
 	};
-	let Some(token) = file["token"].as_str() else { return Err("Failed to get SubZero token from file.".to_string()) };
-	SubZeroJWTTokenClaims::decode(token).await
+	match saved {
+		Ok(()) => telemetry::counter("session.saved", 1, &[("manufacturer", "subzero".to_string())]),
+		Err(e) => telemetry::event("session.save_failed", &[("manufacturer", "subzero".to_string()), ("error", e)]),
+	}
 }
 
 ///
-/// # Get the Number of Items in the `SubZero` Cart
-/// Gets the number of items in the `SubZero` cart.
+/// # Login to `SubZero` System
+/// Log in to the dealer portal with the next fingerprint and save the session it sets. Waits for a running
+/// lookup first, so the session it uses is not replaced midway. A login answered without session cookie
+/// leaves the saved session as it was.
 ///
-/// ## Inputs
-/// * `cookies`: String - The cookies to use for the request.
+/// # Errors
+/// Returns an error if the fingerprint headers cannot be set, the portal cannot be reached, or the session
+/// cannot be encoded or saved.
 ///
-/// ## Outputs
-/// u32 - The number of items in the `SubZero` cart.
+pub async fn subzero_login(username: SecretString, password: SecretString) -> Result<(), String> {
+	let _session_lock = lock_session("login").await;
+	portal_login(username, password).await
+}
+
 ///
-async fn subzero_get_number_of_items(cookies: &str) -> u32 {
-	let client = Client::new();
-	let data = json!({
-		"mode": " view",
-		"error": " 0",
-	})
-	.to_string();
+/// The login of `subzero_login`, for callers that hold the session lock or do not use the cart.
+///
+async fn portal_login(username: SecretString, password: SecretString) -> Result<(), String> {
+	telemetry::counter("vendor.login", 1, &[("manufacturer", "subzero".to_string())]);
+	// a new session presents the next fingerprint, and keeps it until the next login.
+	let fingerprint = next_fingerprint(Some(&session_fingerprint().await));
+	let mut headers = HeaderMap::new();
+	fingerprint.apply(&mut headers)?;
+	headers.insert(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, "true".parse().map_err(|e| format!("Failed to add access control allow credentials to header: {e:?}"))?);
+
+	let response = http_client::client().post("https://order.subzero.com/instance1/servlet/WebDispatcher").headers(headers).form(&[("user", username.expose()), ("psswd", password.expose()), ("mode", "logon"), ("env", "EnvZZ")]).send().await.map_err(|e| format!("Failed to send login request: {e:?}"))?;
+
+	let session = SessionJar::new(WEB_DISPATCHER_URL, SUBZERO_COOKIE_DOMAIN, [])?;
+	session.capture(&response);
+	let subzero_cookies = session.token_cookies();
+
+	if !subzero_cookies.is_empty() {
+		let token_json = json!({ "token": SubZeroJWTTokenClaims::encode(subzero_cookies).await.map_err(|e| format!("Error encoding token: {e}"))?, "fingerprint": fingerprint }).to_string();
+		tokens::save(SUBZERO_TOKEN, &token_json).await.map_err(|e| format!("Failed to write SubZero token file: {e:?}"))?;
+		*SESSION_FINGERPRINT.write().unwrap_or_else(std::sync::PoisonError::into_inner) = Some(fingerprint);
+	}
+
+	Ok(())
+}
 
+///
+/// # `SubZero` Login Check
+/// Log in with a session of its own, which is neither saved nor shared with lookups, and report whether
+/// the portal accepted the credentials.
+///
+/// ## Outputs
+/// bool - False if the portal turned the credentials away.
+///
+/// # Errors
+/// Returns an error if the portal cannot be reached, answers with an error, or answers with neither a
+/// session nor the logon form, which says nothing about the credentials.
+///
+pub async fn subzero_login_check(username: SecretString, password: SecretString) -> Result<bool, String> {
 	let mut headers = HeaderMap::new();
-	match HeaderValue::from_str(cookies) {
-		Ok(cookies) => headers.insert(header::COOKIE, cookies),
-		Err(_) => return 0,
-	};
-	match HeaderValue::from_str(" Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/99.0.4844.51 Safari/537.36 Edg/99.0.1150.30") {
-		Ok(user_agent) => headers.insert(header::USER_AGENT, user_agent),
-		Err(_) => return 0,
-	};
-	match HeaderValue::from_str("application/x-www-form-urlencoded") {
-		Ok(content_type) => headers.insert(header::CONTENT_TYPE, content_type),
-		Err(_) => return 0,
-	};
-	match HeaderValue::from_str(data.as_str()) {
-		Ok(data) => headers.insert("data", data),
-		Err(_) => return 0,
-	};
+	next_fingerprint(None).apply(&mut headers)?;
+	let response = http_client::client().post("https://order.subzero.com/instance1/servlet/WebDispatcher").headers(headers).form(&[("user", username.expose()), ("psswd", password.expose()), ("mode", "logon"), ("env", "EnvZZ")]).send().await.map_err(|e| format!("Failed to send login request: {e:?}"))?;
+	if http_client::auth_rejected(&response) {
+		return Ok(false);
+	}
+	if !response.status().is_success() {
+		return Err(format!("SubZero login answered {}", response.status()));
+	}
+	let has_session = response.cookies().next().is_some();
+	let page = http_client::text(response).await.map_err(|e| format!("Failed to read SubZero login answer: {e:?}"))?;
+	login_accepted(has_session, &page)
+}
 
-	let Ok(response) = client.get("https://order.subzero.com/instance1/servlet/WebDispatcher?mode=view&error=0").headers(headers).body(Body::from(data)).send().await else { return 0 };
-	let Ok(response_data) = response.text().await else { return 0 };
-	let document = Html::parse_document(&response_data);
-	let Ok(tr_selector) = Selector::parse("tr") else { return 0 };
-	let Ok(my_scroll_table_selector) = Selector::parse("#myScrollTable") else { return 0 };
-	let my_scroll_table = document.select(&my_scroll_table_selector).next();
-	my_scroll_table.map_or(0, |my_scroll_table| {
-		let my_scroll_table_rows = my_scroll_table.select(&tr_selector);
-		u32::try_from(my_scroll_table_rows.into_iter().count()).unwrap_or(0)
-	})
+///
+/// Read the answer to a logon: the portal sends rejected credentials back to the logon form with its
+/// error, and sets the session cookie for accepted ones.
+///
+fn login_accepted(has_session: bool, page: &str) -> Result<bool, String> {
+	if is_logon_form(page) {
+		return Ok(false);
+	}
+	if has_session {
+		Ok(true)
+	} else {
+		Err("SubZero logon answered with neither a session cookie nor the logon form.".to_string())
+	}
 }
 
 ///
-/// # Remove Item
-/// Removes the first item from the `SubZero` cart.
+/// Whether `page` is the logon form of the portal.
 ///
-/// ## Inputs
-/// * `cookies`: String - The cookies to use for the request.
+fn is_logon_form(page: &str) -> bool {
+	let document = Html::parse_document(page);
+	Selector::parse("form[name=logon] input[name=psswd]").is_ok_and(|selector| document.select(&selector).next().is_some())
+}
+
 ///
-async fn subzero_remove_item(cookies: &str) {
-	let client = Client::new();
+/// # `SubzeroPriceListConfig`
+/// The dealer price list view of the `SubZero` portal, read a page at a time from `url` with the page
+/// number as `page`, up to `max_pages` pages. With `answer_from_snapshot` set, `SubZero` lookups of a model
+/// on the exported list are answered from it while the export is younger than `max_age`, instead of
+/// through the cart; off by default.
+///
+/// ## Example
+/// ```
+/// use std::time::Duration;
+/// use eggersmann_app_server_appliance_availability::{set_subzero_price_list_config, SubzeroPriceListConfig};
+///
+/// set_subzero_price_list_config(SubzeroPriceListConfig::default().with_answer_from_snapshot(Duration::from_secs(4 * 60 * 60)));
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SubzeroPriceListConfig {
+	pub url: String,
+	pub max_pages: u32,
+	pub answer_from_snapshot: bool,
+	pub max_age: Duration,
+}
 
-	let mut headers = HeaderMap::new();
-	match HeaderValue::from_str(cookies) {
-		Ok(cookies) => headers.insert(header::COOKIE, cookies),
-		Err(_) => return,
-	};
+impl SubzeroPriceListConfig {
+	#[must_use]
+	pub const fn new(url: String) -> Self {
+		Self { url, max_pages: 200, answer_from_snapshot: false, max_age: Duration::from_hours(4) }
+	}
 
-	match HeaderValue::from_str(" Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/99.0.4844.51 Safari/537.36 Edg/99.0.1150.30") {
-		Ok(user_agent) => headers.insert(header::USER_AGENT, user_agent),
-		Err(_) => return,
-	};
+	#[must_use]
+	pub const fn with_max_pages(mut self, max_pages: u32) -> Self {
+		self.max_pages = max_pages;
+		self
+	}
 
-	match HeaderValue::from_str("application/x-www-form-urlencoded") {
-		Ok(content_type) => headers.insert(header::CONTENT_TYPE, content_type),
-		Err(_) => return,
-	};
+	///
+	/// # `SubzeroPriceListConfig::with_answer_from_snapshot`
+	/// Answer lookups from an export younger than `max_age`.
+	///
+	#[must_use]
+	pub const fn with_answer_from_snapshot(mut self, max_age: Duration) -> Self {
+		self.answer_from_snapshot = true;
+		self.max_age = max_age;
+		self
+	}
+}
 
-	let params = [("mode", "delete"), ("index", "0"), ("x", "3"), ("y", "9")];
-	match client.post("https://order.subzero.com/instance1/servlet/WebDispatcher?mode=delete&index=0&x=3&y=9").headers(headers).form(&params).send().await {
-		Ok(_) => (),
-		Err(e) => panic!("Failed to remove item from cart: {e:?}"),
-	};
+impl Default for SubzeroPriceListConfig {
+	fn default() -> Self {
+		Self::new("https://order.subzero.com/instance1/servlet/WebDispatcher?mode=pricelist".to_string())
+	}
+}
+
+fn price_list_config_slot() -> &'static RwLock<SubzeroPriceListConfig> {
+	static CONFIG: OnceLock<RwLock<SubzeroPriceListConfig>> = OnceLock::new();
+	CONFIG.get_or_init(|| RwLock::new(SubzeroPriceListConfig::default()))
 }
 
 ///
-/// # Add Item
-/// Adds an item to the `SubZero` cart and returns the availablility date.
+/// # `set_subzero_price_list_config`
+/// Set the price list view to export and whether lookups are answered from the export.
 ///
-/// ## Inputs
-/// * `cookies`: String - The cookies to use for the request.
-/// * `model_number`: String - The model number of the item to add.
+pub fn set_subzero_price_list_config(config: SubzeroPriceListConfig) {
+	*price_list_config_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = config;
+}
+
 ///
-/// ## Outputs
-/// String - The availability date of the item.
+/// # `subzero_price_list_config`
+/// The price list view to export and whether lookups are answered from the export.
 ///
-async fn subzero_add_item(model_number: String, cookies: &str) -> String {
-	let client = Client::new();
+#[must_use]
+pub fn subzero_price_list_config() -> SubzeroPriceListConfig {
+	price_list_config_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
 
-	let mut headers = HeaderMap::new();
-	match HeaderValue::from_str(cookies) {
-		Ok(cookies) => headers.insert(header::COOKIE, cookies),
-		Err(e) => return format!("Faild to add cookies to header: {e:?}"),
-	};
-	match HeaderValue::from_str(" Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/99.0.4844.51 Safari/537.36 Edg/99.0.1150.30") {
-		Ok(user_agent) => headers.insert(header::USER_AGENT, user_agent),
-		Err(e) => return format!("Failed to add user agent to header: {e:?}"),
-	};
-	match HeaderValue::from_str("application/x-www-form-urlencoded") {
-		Ok(content_type) => headers.insert(header::CONTENT_TYPE, content_type),
-		Err(e) => return format!("Failed to add content type to header: {e:?}"),
-	};
-	let params = [("item", &model_number), ("quantity", &"1".to_string())];
+///
+/// One row of the dealer price list, as the portal shows it.
+///
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct PriceListRow {
+	model_number: String,
+	description: Option<String>,
+	category: Option<String>,
+	list_price: Option<f64>,
+	dealer_price: Option<f64>,
+	available_qty: Option<u32>,
+	availability: String,
+}
 
-	let data = json!({
-		"item": model_number,
-		"quantity": "1",
-	});
+///
+/// # `SubzeroPriceList`
+/// The last export of the dealer price list. The list is the dealer's and not split by warehouse, so
+/// every row is listed under every warehouse. An export with the same rows as the previous one, by
+/// SHA-256 digest, keeps its version.
+///
+#[derive(Debug)]
+pub struct SubzeroPriceList {
+	pub loaded_at: DateTime<Utc>,
+	pub version: u64,
+	/// Hex SHA-256 digest of the exported rows.
+	pub digest: String,
+	rows: HashMap<String, PriceListRow>,
+}
 
-	let response = match client.post("https://order.subzero.com/instance1/servlet/WebDispatcher?mode=add").headers(headers).body(Body::from(data.to_string())).form(&params).send().await {
-		Ok(response) => response,
-		Err(e) => return format!("Failed to add item to cart: {e:?}"),
-	};
+impl SubzeroPriceList {
+	///
+	/// # `SubzeroPriceList::catalog_version`
+	/// The version and digest of the export, as reported on responses answered from it.
+	///
+	#[must_use]
+	pub fn catalog_version(&self) -> CatalogVersion {
+		CatalogVersion::new(self.version, self.digest.clone())
+	}
+}
 
-	let response_data = match response.text().await {
-		Ok(response_data) => response_data,
-		Err(e) => return format!("Failed to get response data: {e:?}"),
-	};
+fn catalog_item(warehouse: &str, row: &PriceListRow) -> CatalogItem {
+	let mut item = CatalogItem::new("subzero".to_string(), warehouse.to_string(), row.model_number.clone());
+	item.description.clone_from(&row.description);
+	item.category.clone_from(&row.category);
+	item.list_price = row.list_price;
+	item.dealer_price = row.dealer_price;
+	item.available_qty = row.available_qty;
+	item.next_available_date = parse_available_date(&row.availability);
+	item
+}
 
-	let document = Html::parse_document(&response_data);
-	let my_scroll_table_selector = match Selector::parse("#myScrollTable") {
-		Ok(my_scroll_table_selector) => my_scroll_table_selector,
-		Err(e) => return format!("Failed to parse my scroll table selector: {e:?}"),
-	};
-	let table_body_selector = match Selector::parse("tbody") {
-		Ok(table_body_selector) => table_body_selector,
-		Err(e) => return format!("Failed to parse table body selector: {e:?}"),
-	};
-	let row_selector = match Selector::parse("tr") {
-		Ok(row_selector) => row_selector,
-		Err(e) => return format!("Failed to parse row selector: {e:?}"),
-	};
-	let td_selector = match Selector::parse("td") {
-		Ok(td_selector) => td_selector,
-		Err(e) => return format!("Failed to parse td selector: {e:?}"),
-	};
+impl CachedCatalog for SubzeroPriceList {
+	fn snapshot(&self) -> CatalogSnapshot {
+		CatalogSnapshot { manufacturer: "subzero".to_string(), loaded_at: self.loaded_at, version: Some(self.version), digest: Some(self.digest.clone()), warehouses: Vec::new(), items: self.rows.len() }
+	}
 
-	let my_scroll_table = document.select(&my_scroll_table_selector).next();
-	my_scroll_table.map_or_else(
-		|| "Error finding item.".to_string(),
-		|my_scroll_table| {
-			let table_body = my_scroll_table.select(&table_body_selector).next();
-			table_body.map_or_else(
-				|| "Error finding item.".to_string(),
-				|table_body| {
-					let mut availability: String = "Error finding item.".to_string();
-					let rows = table_body.select(&row_selector);
-					for row in rows {
-						let cells = row.select(&td_selector);
-						for (i, cell) in cells.enumerate() {
-							if i == 7 {
-								availability = cell.inner_html().to_string();
-							}
-						}
-					}
-					availability
-				},
-			)
-		},
-	)
-}
+	fn models(&self, _warehouse: Option<&str>) -> Vec<String> {
+		let mut models: Vec<String> = self.rows.values().map(|row| row.model_number.clone()).collect();
+		models.sort();
+		models.dedup();
+		models
+	}
 
-async fn subzero_validate_model_number(model_number: String, cookies: &str) -> String {
-	let client = Client::new();
-	let mut headers = HeaderMap::new();
+	fn item(&self, warehouse: &str, model_number: &str) -> Option<CatalogItem> {
+		self.rows.get(&normalize_model(model_number)).map(|row| catalog_item(warehouse, row))
+	}
+}
 
-	match HeaderValue::from_str("*/*") {
-		Ok(accept) => headers.insert(header::ACCEPT, accept),
-		Err(e) => return format!("Failed to add accept to header: {e:?}"),
-	};
+fn price_list_slot() -> &'static RwLock<Option<Arc<SubzeroPriceList>>> {
+	static PRICE_LIST: OnceLock<RwLock<Option<Arc<SubzeroPriceList>>>> = OnceLock::new();
+	PRICE_LIST.get_or_init(|| RwLock::new(None))
+}
 
-	// add user agent to header
-	match HeaderValue::from_str("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/99.0.4844.51 Safari/537.36 Edg/99.0.1150.30") {
-		Ok(user_agent) => headers.insert(header::USER_AGENT, user_agent),
-		Err(e) => return format!("Failed to add user agent to header: {e:?}"),
-	};
+///
+/// # `cached_subzero_price_list`
+/// The last export of the dealer price list, `None` before the first one.
+///
+#[must_use]
+pub fn cached_subzero_price_list() -> Option<Arc<SubzeroPriceList>> {
+	price_list_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
 
-	match HeaderValue::from_str(cookies) {
-		Ok(cookies) => headers.insert(header::COOKIE, cookies),
-		Err(e) => return format!("Faild to add cookies to header: {e:?}"),
+///
+/// # Export `SubZero` Price List
+/// Read every page of the dealer price list and replace the cached export with it, so `CatalogReader`
+/// and, when configured, `SubZero` lookups see it. Pages are read until one has no rows or
+/// `SubzeroPriceListConfig::max_pages` is reached; a session the portal turns away is replaced once.
+///
+/// # Errors
+/// Returns an error if the login fails, the portal cannot be reached or answers with an error page, or the
+/// list has no rows. The previous export is kept.
+///
+pub async fn export_subzero_price_list() -> Result<Arc<SubzeroPriceList>, String> {
+	let (username, password) = subzero_credentials().await?;
+	let config = subzero_price_list_config();
+	let session_lock = lock_session("pricelist").await;
+	let session = subzero_session(username.clone(), password.clone()).await?;
+	let (session, rows) = match fetch_price_list(&config, &session).await {
+		Err(AvailabilityError::SessionExpired) => {
+			telemetry::counter("session.refreshed", 1, &[("manufacturer", "subzero".to_string())]);
+			portal_login(username.clone(), password.clone()).await?;
+			let session = subzero_session(username, password).await?;
+			let rows = fetch_price_list(&config, &session).await;
+			(session, rows)
+		}
+		rows => (session, rows),
 	};
+	save_subzero_session(&session).await;
+	drop(session_lock);
+	let rows = rows.map_err(|e| format!("Failed to export SubZero price list: {e}"))?;
+	if rows.is_empty() {
+		return Err("SubZero price list has no rows.".to_string());
+	}
 
-	// add host to header
-	match HeaderValue::from_str("order.subzero.com") {
-		Ok(host) => headers.insert(header::HOST, host),
-		Err(e) => return format!("Failed to add host to header: {e:?}"),
+	let mut sorted: Vec<&PriceListRow> = rows.values().collect();
+	sorted.sort_by(|a, b| a.model_number.cmp(&b.model_number));
+	let digest = format!("{:x}", Sha256::digest(serde_json::to_vec(&sorted).map_err(|e| format!("Failed to serialize SubZero price list: {e:?}"))?));
+	let version = match cached_subzero_price_list() {
+		Some(current) if current.digest == digest => current.version,
+		Some(current) => current.version + 1,
+		None => 1,
 	};
+	let price_list = Arc::new(SubzeroPriceList { loaded_at: Utc::now(), version, digest, rows });
+	*price_list_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = Some(Arc::clone(&price_list));
+	telemetry::event("subzero.price_list.exported", &[("version", version.to_string()), ("items", price_list.rows.len().to_string())]);
+	Ok(price_list)
+}
 
-	let url = format!("https://order.subzero.com/instance1/servlet/WebDispatcher?mode=suggest&type=advanced&search={model_number}");
+///
+/// Every row of the price list by normalized model number.
+///
+async fn fetch_price_list(config: &SubzeroPriceListConfig, session: &SessionJar) -> Result<HashMap<String, PriceListRow>, AvailabilityError> {
+	let mut rows = HashMap::new();
+	for page in 1..=config.max_pages {
+		let mut headers = HeaderMap::new();
+		session.apply(&mut headers, &config.url);
+		session_fingerprint().await.apply(&mut headers)?;
+		let response = http_client::client().get(&config.url).headers(headers).query(&[("page", page)]).send().await.map_err(|e| AvailabilityError::VendorUnavailable(format!("Failed to get SubZero price list: {e:?}")))?;
+		session.capture(&response);
+		if http_client::auth_rejected(&response) {
+			return Err(AvailabilityError::SessionExpired);
+		}
+		if !response.status().is_success() {
+			return Err(AvailabilityError::VendorUnavailable(format!("Failed to get SubZero price list: {}", response.status())));
+		}
+		let response_data = http_client::text(response).await?;
+		// an invalid item phrase in a description is not an error page of the list.
+		if let Some(error) = recognize_error_page(&response_data).filter(|error| !matches!(error, AvailabilityError::NotFound(_))) {
+			return Err(error);
+		}
+		let page_rows = parse_price_list_page(&response_data);
+		if page_rows.is_empty() {
+			break;
+		}
+		rows.extend(page_rows.into_iter().map(|row| (normalize_model(&row.model_number), row)));
+	}
+	Ok(rows)
+}
 
-	let response = match client.get(url).headers(headers).send().await {
-		Ok(response) => response,
-		Err(e) => return format!("Failed to get suggested items: {e:?}"),
+///
+/// Read the rows of the price list table on a page. The table is the one whose header names a model
+/// column; the other columns are found by their header, so a reordered view still reads.
+///
+fn parse_price_list_page(response_data: &str) -> Vec<PriceListRow> {
+	let document = Html::parse_document(response_data);
+	let (Ok(table_selector), Ok(tr_selector), Ok(cell_selector)) = (Selector::parse("table"), Selector::parse("tr"), Selector::parse("th, td")) else {
+		return Vec::new();
 	};
+	let cell_text = |cell: scraper::ElementRef| cell.text().collect::<String>().split_whitespace().collect::<Vec<_>>().join(" ");
+	for table in document.select(&table_selector) {
+		let mut table_rows = table.select(&tr_selector);
+		let Some(header_row) = table_rows.next() else { continue };
+		let headers: Vec<String> = header_row.select(&cell_selector).map(|cell| cell_text(cell).to_lowercase()).collect();
+		let column = |names: &[&str]| headers.iter().position(|header| names.iter().any(|name| header.contains(name)));
+		let Some(model_column) = column(&["model", "item"]) else { continue };
+		let description_column = column(&["description"]);
+		let category_column = column(&["category", "product line"]);
+		let list_price_column = column(&["list", "msrp"]);
+		let dealer_price_column = column(&["dealer", "net", "your price"]);
+		let quantity_column = column(&["qty", "quantity"]);
+		let availability_column = column(&["availab", "ship"]);
 
-	let response_data = match response.text().await {
-		Ok(response_data) => response_data,
-		Err(e) => return format!("Failed to get suggested items: {e:?}"),
-	};
+		return table_rows
+			.filter_map(|row| {
+				let cells: Vec<String> = row.select(&cell_selector).map(cell_text).collect();
+				let at = |column: Option<usize>| column.and_then(|column| cells.get(column)).filter(|text| !text.is_empty()).cloned();
+				let model_number = at(Some(model_column))?;
+				Some(PriceListRow {
+					model_number,
+					description: at(description_column),
+					category: at(category_column),
+					list_price: at(list_price_column).and_then(|price| parse_price(&price)),
+					dealer_price: at(dealer_price_column).and_then(|price| parse_price(&price)),
+					available_qty: at(quantity_column).and_then(|quantity| quantity.replace(',', "").parse().ok()),
+					availability: at(availability_column).unwrap_or_default(),
+				})
+			})
+			.collect();
+	}
+	Vec::new()
+}
 
-	let response_data = response_data.split('{').collect::<Vec<&str>>()[0].to_string();
-	response_data
+fn parse_price(price: &str) -> Option<f64> {
+	price.chars().filter(|c| c.is_ascii_digit() || *c == '.').collect::<String>().parse().ok()
 }
 
 ///
-/// # Login to `SubZero` System
-///
-/// ## Outputs
-/// bool - True if login was successful, false otherwise.
+/// The answer to `req` from the exported price list, `None` unless answering from it is configured, the
+/// export is young enough and lists the model.
 ///
-/// # Errors
-/// todo
-pub async fn subzero_login(username: String, password: String) -> Result<(), String> {
-	let mut headers = HeaderMap::new();
-	headers.insert(header::USER_AGENT, " Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/99.0.4844.51 Safari/537.36 Edg/99.0.1150.30".parse().map_err(|e| format!("Failed to add user agent to header: {e:?}"))?);
-	headers.insert(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, "true".parse().map_err(|e| format!("Failed to add access control allow credentials to header: {e:?}"))?);
+fn price_list_answer(req: &AvailabilityRequest) -> Option<BackendAnswer> {
+	let config = subzero_price_list_config();
+	if !config.answer_from_snapshot {
+		return None;
+	}
+	let price_list = cached_subzero_price_list().filter(|price_list| (Utc::now() - price_list.loaded_at).to_std().is_ok_and(|age| age <= config.max_age))?;
+	let row = price_list.rows.get(&normalize_model(req.model_number.as_deref()?))?;
+	let today = req.business_today();
+	let availability = Availability::stock(&row.model_number, row.available_qty.filter(|quantity| *quantity > 0), parse_available_date(&row.availability), today);
+	telemetry::counter("subzero.price_list.answered", 1, &[]);
+	Some(BackendAnswer::new(availability).with_product(row.description.clone().map(ProductInfo::new)).with_catalog_version(Some(price_list.catalog_version())))
+}
 
-	let response = reqwest::Client::new().post("https://order.subzero.com/instance1/servlet/WebDispatcher").headers(headers).form(&[("user", username.as_str()), ("psswd", password.as_str()), ("mode", "logon"), ("env", "EnvZZ")]).send().await.map_err(|e| format!("Failed to send login request: {e:?}"))?;
+#[cfg(test)]
+mod tests {
+	use super::*;
 
-	// get response cookies into json
-	let mut cookies_json_vec: Vec<serde_json::Value> = Vec::new();
-	let mut subzero_cookies: Vec<PlaywrightCookie> = Vec::new();
+	const SUGGEST_JSON: &str = include_str!("../tests/fixtures/subzero/suggest_json.txt");
+	const SUGGEST_LINES: &str = include_str!("../tests/fixtures/subzero/suggest_lines.txt");
+	const SUGGEST_BROKEN_TRAILER: &str = include_str!("../tests/fixtures/subzero/suggest_broken_trailer.txt");
+	const PRODUCT_DETAIL: &str = include_str!("../tests/fixtures/subzero/product_detail.html");
+	const PRODUCT_DETAIL_WITHOUT_AVAILABILITY: &str = include_str!("../tests/fixtures/subzero/product_detail_without_availability.html");
+	const CART_LINES: &str = include_str!("../tests/fixtures/subzero/cart_lines.html");
+	const CART_EMPTY: &str = include_str!("../tests/fixtures/subzero/cart_empty.html");
+	const SESSION_EXPIRED: &str = include_str!("../tests/fixtures/subzero/session_expired.html");
+	const INVALID_ITEM: &str = include_str!("../tests/fixtures/subzero/invalid_item.html");
+	const UNEXPECTED_PAGE: &str = include_str!("../tests/fixtures/subzero/unexpected_page.html");
+	const LOGON_REJECTED: &str = include_str!("../tests/fixtures/subzero/logon_rejected.html");
+	const LOGON_ACCEPTED: &str = include_str!("../tests/fixtures/subzero/logon_accepted.html");
 
-	for cookie in response.cookies() {
-		let name = cookie.name().to_string();
-		let value = cookie.value().to_string();
-		let expires = cookie.expires().map_or_else(String::new, |expires| {
-			let time: DateTime<Utc> = expires.into();
-			time.to_string()
-		});
-		let max_age = cookie.max_age().map_or_else(String::new, |max_age| DurationString::from(max_age).into());
-		let domain = cookie.domain().map_or_else(String::new, std::string::ToString::to_string);
-		let path = cookie.path().map_or_else(String::new, std::string::ToString::to_string);
-
-		cookies_json_vec.push(json!({
-			"name": name,
-			"value": value,
-			"expires": expires,
-			"max_age": max_age,
-			"domain": domain,
-			"path": path,
-		}));
-	}
-
-	for cookie in cookies_json_vec {
-		let expires = cookie["expires"].as_str().map(std::string::ToString::to_string).and_then(|expires| expires.parse().map_err(|e| format!("Failed to parse expires: {e:?}")).ok());
-		let domain = cookie["domain"].as_str().map(std::string::ToString::to_string);
-		let path = cookie["path"].as_str().map(std::string::ToString::to_string);
-		let name = cookie["name"].as_str().ok_or_else(|| "Failed to get cookie name".to_string()).map_err(|err| format!("Failed to get cookie name: {err:?}"))?.to_string();
-		let value = cookie["value"].as_str().ok_or_else(|| "Failed to get cookie value".to_string()).map_err(|err| format!("Failed to get cookie value: {err:?}"))?.to_string();
-		let new_cookie: PlaywrightCookie = PlaywrightCookie { name, value, expires, domain, path, url: None, secure: None, http_only: None, same_site: None };
-		subzero_cookies.push(new_cookie);
+	#[test]
+	fn logon_form_is_a_rejection_and_a_missing_session_is_not() {
+		assert_eq!(login_accepted(false, LOGON_REJECTED), Ok(false));
+		// the portal sent the logon form back even though it set a cookie.
+		assert_eq!(login_accepted(true, LOGON_REJECTED), Ok(false));
+		assert_eq!(login_accepted(true, SESSION_EXPIRED), Ok(false));
+		assert_eq!(login_accepted(true, LOGON_ACCEPTED), Ok(true));
+		// a changed page without a cookie may be a portal change, not a password reset.
+		assert!(login_accepted(false, LOGON_ACCEPTED).is_err());
+		assert!(login_accepted(false, UNEXPECTED_PAGE).is_err());
 	}
 
-	if !subzero_cookies.is_empty() {
-		let token_json = json!({ "token": SubZeroJWTTokenClaims::encode(subzero_cookies).await.map_err(|e| format!("Error encoding token: {e}"))? }).to_string();
-		let mut file = File::create("/easfiles/appliances/cookies/subzero_cookies.json").map_err(|e| format!("Failed to create SubZero token file: {e:?}"))?;
-		file.write_all(token_json.as_bytes()).map_err(|e| format!("Failed to write SubZero token file: {e:?}"))?;
+	#[test]
+	fn suggestions_prefer_the_json_trailer() {
+		let suggestions = parse_suggestions(SUGGEST_JSON);
+		assert_eq!(suggestions.iter().map(|suggestion| suggestion.model_number.as_str()).collect::<Vec<_>>(), ["CL3650UID/S", "CL3650UID/O"]);
+		assert_eq!(suggestions[0].description.as_deref(), Some("Sub-Zero 36\" Classic Column Refrigerator"));
+		assert_eq!(suggestions[0].brand.as_deref(), Some("Sub-Zero"));
+		assert_eq!(suggestions[0].availability.as_deref(), Some("In Stock"));
+		assert_eq!(suggestions[1].availability, None);
 	}
 
-	Ok(())
+	#[test]
+	fn suggestions_from_lines() {
+		let suggestions = parse_suggestions(SUGGEST_LINES);
+		assert_eq!(suggestions.iter().map(|suggestion| suggestion.model_number.as_str()).collect::<Vec<_>>(), ["DF366LP", "DF366G", "DF366"]);
+		assert_eq!(suggestions[1].description.as_deref(), Some("Wolf 36\" Dual Fuel Range - 4 Burners and Infrared Griddle"));
+		assert_eq!(suggestions[1].brand.as_deref(), Some("Wolf"));
+		assert_eq!(suggestions[2].description, None);
+		assert!(suggestions.iter().all(|suggestion| suggestion.availability.is_none()));
+	}
+
+	#[test]
+	fn only_the_exact_model_is_looked_up() {
+		let suggestion = matching_suggestion("DF366", parse_suggestions(SUGGEST_LINES), &SUBZERO_BRANDS).unwrap();
+		assert_eq!((suggestion.model_number.as_str(), suggestion.description), ("DF366", None));
+		assert_eq!(matching_suggestion(" df366g", parse_suggestions(SUGGEST_LINES), &SUBZERO_BRANDS).unwrap().model_number, "DF366G");
+		// a prefix of the suggested models is looked up as requested.
+		assert_eq!(matching_suggestion("DF36", parse_suggestions(SUGGEST_LINES), &SUBZERO_BRANDS).unwrap().model_number, "DF36");
+		assert_eq!(matching_suggestion("CL3650", Vec::new(), &SUBZERO_BRANDS).unwrap().model_number, "CL3650");
+	}
+
+	#[test]
+	fn wolf_prefix_does_not_resolve_to_a_sibling() {
+		assert_eq!(matching_suggestion("DF366", parse_suggestions(SUGGEST_LINES), &WOLF_BRANDS).unwrap().model_number, "DF366");
+		assert_eq!(matching_suggestion("IT30", parse_suggestions(SUGGEST_BROKEN_TRAILER), &WOLF_BRANDS).unwrap().model_number, "IT30");
+		assert_eq!(matching_suggestion("it30ci", parse_suggestions(SUGGEST_BROKEN_TRAILER), &WOLF_BRANDS).unwrap().brand.as_deref(), Some("Cove"));
+		// Sub-Zero models, exact or by prefix, are not Wolf or Cove models.
+		assert!(matches!(matching_suggestion("CL3650UID/S", parse_suggestions(SUGGEST_JSON), &WOLF_BRANDS), Err(AvailabilityError::NotFound(_))));
+		assert!(matches!(matching_suggestion("CL3650", parse_suggestions(SUGGEST_JSON), &WOLF_BRANDS), Err(AvailabilityError::NotFound(_))));
+	}
+
+	#[test]
+	fn suggest_answers_that_are_pages_are_errors() {
+		assert_eq!(parse_suggest_answer(SUGGEST_LINES).unwrap().len(), 3);
+		assert!(matches!(parse_suggest_answer(SESSION_EXPIRED), Err(AvailabilityError::SessionExpired)));
+		assert!(matches!(parse_suggest_answer(UNEXPECTED_PAGE), Err(AvailabilityError::VendorUnavailable(_))));
+		assert!(matches!(parse_suggest_answer(LOGON_REJECTED), Err(AvailabilityError::SessionExpired)));
+	}
+
+	#[test]
+	fn suggestions_ignore_a_broken_trailer() {
+		let suggestions = parse_suggestions(SUGGEST_BROKEN_TRAILER);
+		assert_eq!(suggestions.len(), 1);
+		assert_eq!(suggestions[0].model_number, "IT30CI");
+		assert_eq!(suggestions[0].brand.as_deref(), Some("Cove"));
+		assert!(parse_suggestions("").is_empty());
+	}
+
+	#[test]
+	fn product_detail_availability_and_description() {
+		let line = parse_product_detail(PRODUCT_DETAIL).unwrap().unwrap();
+		assert_eq!(line.availability, "Available 11/16/2026");
+		assert_eq!(line.description.as_deref(), Some("Sub-Zero 36\" Classic Column Refrigerator"));
+	}
+
+	#[test]
+	fn product_detail_without_availability_is_none() {
+		assert!(parse_product_detail(PRODUCT_DETAIL_WITHOUT_AVAILABILITY).unwrap().is_none());
+		assert!(parse_product_detail("<html><body>Item not found</body></html>").unwrap().is_none());
+	}
+
+	#[test]
+	fn cart_lines_in_cart_order() {
+		let lines = parse_cart_lines(CART_LINES).unwrap().unwrap();
+		assert_eq!(lines.len(), 2);
+		assert_eq!(lines[0].availability, "In Stock");
+		assert_eq!(lines[1].availability, "12/07/2026<br>Backordered");
+		assert_eq!(lines[1].description.as_deref(), Some("Wolf 36\" Dual Fuel Range - 6 Burners"));
+	}
+
+	#[test]
+	fn cart_without_lines() {
+		assert!(parse_cart_lines(CART_EMPTY).unwrap().unwrap().is_empty());
+		assert!(parse_cart_lines("<html><body>Order Entry</body></html>").unwrap().is_none());
+	}
+
+	#[test]
+	fn cart_add_answers() {
+		assert_eq!(parse_added_cart(CART_LINES).unwrap().len(), 2);
+		assert!(matches!(parse_added_cart(SESSION_EXPIRED), Err(AvailabilityError::SessionExpired)));
+		assert!(matches!(parse_added_cart(INVALID_ITEM), Err(AvailabilityError::NotFound(_))));
+		// a page that is not the cart is not an unknown model.
+		assert!(matches!(parse_added_cart(UNEXPECTED_PAGE), Err(AvailabilityError::VendorUnavailable(_))));
+	}
+
+	#[test]
+	fn only_the_appended_line_is_removed() {
+		// a dealer's two lines stay, the line the lookup appended is the third.
+		assert_eq!(added_line_index(2, 3), Some(2));
+		assert_eq!(added_line_index(0, 1), Some(0));
+		// an add that made no line, or merged into one of the dealer's, removes nothing.
+		assert_eq!(added_line_index(2, 2), None);
+		assert_eq!(added_line_index(0, 0), None);
+	}
+
+	#[test]
+	fn guard_without_a_runtime_flags_the_cart() {
+		let session = SessionJar::new(WEB_DISPATCHER_URL, SUBZERO_COOKIE_DOMAIN, [SessionCookie::new("JSESSIONID", "abc")]).unwrap();
+		CartGuard::new(&session, 2).disarm();
+		assert!(!subzero_cart_dirty());
+		drop(CartGuard::new(&session, 2));
+		assert!(subzero_cart_dirty());
+		CART_DIRTY.store(false, Ordering::Relaxed);
+	}
+
+	///
+	/// The purposes the session lock was taken for, in the order it was.
+	///
+	#[derive(Default)]
+	struct SessionWaits(std::sync::Mutex<Vec<String>>);
+
+	impl telemetry::TelemetryExporter for SessionWaits {
+		fn counter(&self, _name: &'static str, _value: u64, _labels: &[(&'static str, String)]) {}
+
+		fn histogram(&self, name: &'static str, _value: f64, labels: &[(&'static str, String)]) {
+			if name == "subzero.session.wait" {
+				self.0.lock().unwrap().extend(labels.iter().map(|(_, purpose)| purpose.clone()));
+			}
+		}
+
+		fn event(&self, _name: &'static str, _fields: &[(&'static str, String)]) {}
+	}
+
+	#[tokio::test]
+	async fn account_check_suggest_and_price_list_wait_for_the_session() {
+		use crate::credentials::{set_credential_provider, StaticProvider};
+
+		set_credential_provider(Arc::new(StaticProvider::new().with_secret("subzero-username", "dealer").with_secret("subzero-password", "hunter2")));
+		let waits = Arc::new(SessionWaits::default());
+		telemetry::set_exporter(waits.clone());
+		for purpose in ["account", "suggest", "pricelist"] {
+			// a lookup holds the session, so the caller waits for it.
+			let lookup = lock_session("lookup").await;
+			let caller = tokio::spawn(async move {
+				let (username, password) = (SecretString::from("dealer"), SecretString::from("hunter2"));
+				match purpose {
+					"account" => drop(subzero_account_check(username, password).await),
+					"suggest" => drop(subzero_suggest("LOCK-TEST", username, password).await),
+					_ => drop(export_subzero_price_list().await),
+				}
+			});
+			tokio::time::sleep(Duration::from_millis(50)).await;
+			assert!(!waits.0.lock().unwrap().iter().any(|waited| waited == purpose), "{purpose} did not wait for the session");
+			drop(lookup);
+			tokio::time::timeout(Duration::from_secs(5), async {
+				while !waits.0.lock().unwrap().iter().any(|waited| waited == purpose) {
+					tokio::time::sleep(Duration::from_millis(10)).await;
+				}
+			})
+			.await
+			.unwrap();
+			caller.abort();
+			let _ = caller.await;
+		}
+	}
+
+	#[test]
+	fn cart_fallback_is_off_by_default() {
+		assert!(!SubzeroLookupConfig::default().cart_fallback);
+		assert!(SubzeroLookupConfig::default().with_cart_fallback().cart_fallback);
+	}
 }
@@ -0,0 +1,339 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::account::AccountIssue;
+use super::snapshot::to_canonical_json;
+use super::{cache, hedge, memory, quote, secrets, AvailabilityRequest};
+
+///
+/// Lookups whose trace and captures are kept for `support_bundle`, oldest dropped first.
+///
+pub const MAX_TRACKED_REQUESTS: usize = 200;
+
+///
+/// Bytes of a vendor response body kept in a payload capture.
+///
+pub const MAX_CAPTURE_BYTES: usize = 64 * 1024;
+
+tokio::task_local! {
+	static CURRENT_REQUEST: String;
+}
+
+///
+/// # `TraceStep`
+/// One step of a lookup, e.g. a cache hit or a vendor HTTP call.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct TraceStep {
+	pub at: DateTime<Utc>,
+	pub step: String,
+	pub detail: String,
+}
+
+///
+/// # `PayloadCapture`
+/// A vendor response body as the lookup received it, cut to `MAX_CAPTURE_BYTES`.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct PayloadCapture {
+	pub at: DateTime<Utc>,
+	/// Scheme, host and path of the request; the query is left out.
+	pub url: String,
+	pub status: u16,
+	pub body: String,
+	pub truncated: bool,
+}
+
+#[derive(Debug, Clone)]
+struct TrackedRequest {
+	id: String,
+	request: AvailabilityRequest,
+	trace: Vec<TraceStep>,
+	captures: Vec<PayloadCapture>,
+	outcome: Option<String>,
+	error: Option<String>,
+}
+
+fn tracked() -> &'static Mutex<VecDeque<TrackedRequest>> {
+	static TRACKED: OnceLock<Mutex<VecDeque<TrackedRequest>>> = OnceLock::new();
+	TRACKED.get_or_init(|| Mutex::new(VecDeque::new()))
+}
+
+fn with_tracked(id: &str, update: impl FnOnce(&mut TrackedRequest)) {
+	if let Some(tracked) = tracked().lock().unwrap_or_else(std::sync::PoisonError::into_inner).iter_mut().rev().find(|tracked| tracked.id == id) {
+		update(tracked);
+	}
+}
+
+///
+/// # Next Request Id
+/// A new id for a lookup: the UTC time and a sequence number, e.g. `20240611T143005-000042`.
+///
+pub fn next_request_id() -> String {
+	static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+	format!("{}-{:06}", Utc::now().format("%Y%m%dT%H%M%S"), SEQUENCE.fetch_add(1, Ordering::Relaxed) % 1_000_000)
+}
+
+///
+/// # Begin Tracking
+/// Start keeping the trace of the lookup `req` under the request id `id`.
+///
+pub fn begin(id: &str, req: &AvailabilityRequest) {
+	let id = id.to_string();
+	let mut request = req.clone();
+	request.request_id = Some(id.clone());
+	if let Some(user) = request.user.as_mut() {
+		user.given_name = None;
+		user.surname = None;
+		user.display_name = None;
+		user.user_principal_name = None;
+	}
+	let mut tracked = tracked().lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+	tracked.push_back(TrackedRequest { id, request, trace: Vec::new(), captures: Vec::new(), outcome: None, error: None });
+	while tracked.len() > MAX_TRACKED_REQUESTS {
+		tracked.pop_front();
+	}
+	drop(tracked);
+}
+
+///
+/// # Scope
+/// Run `future` as the lookup `id`, so vendor calls made inside it are traced and captured for it.
+///
+pub async fn scope<F: Future>(id: String, future: F) -> F::Output {
+	CURRENT_REQUEST.scope(id, future).await
+}
+
+fn current_request() -> Option<String> {
+	CURRENT_REQUEST.try_with(Clone::clone).ok()
+}
+
+///
+/// # Trace
+/// Add a step to the trace of the lookup running on this task, if any.
+///
+pub fn trace(step: &str, detail: &str) {
+	if let Some(id) = current_request() {
+		trace_request(&id, step, detail);
+	}
+}
+
+///
+/// # Trace Request
+/// Add a step to the trace of the lookup `id`.
+///
+pub fn trace_request(id: &str, step: &str, detail: &str) {
+	let step = TraceStep { at: Utc::now(), step: step.to_string(), detail: secrets::redact(detail) };
+	with_tracked(id, |tracked| tracked.trace.push(step));
+}
+
+///
+/// # Capture
+/// Keep a vendor response body for the lookup running on this task, if any.
+///
+pub fn capture(url: &reqwest::Url, status: u16, body: &str) {
+	let Some(id) = current_request() else { return };
+	let mut end = body.len().min(MAX_CAPTURE_BYTES);
+	while !body.is_char_boundary(end) {
+		end -= 1;
+	}
+	let capture = PayloadCapture { at: Utc::now(), url: format!("{}://{}{}", url.scheme(), url.host_str().unwrap_or_default(), url.path()), status, body: secrets::redact(&body[..end]), truncated: end < body.len() };
+	with_tracked(&id, |tracked| tracked.captures.push(capture));
+}
+
+///
+/// # Finish Tracking
+/// Record the v1 answer or the error the lookup `id` ended with.
+///
+pub fn finish(id: &str, result: &Result<String, String>) {
+	let (outcome, error) = match result {
+		Ok(v1) => (Some(v1.clone()), None),
+		Err(e) => (None, Some(secrets::redact(e))),
+	};
+	with_tracked(id, |tracked| {
+		tracked.outcome = outcome;
+		tracked.error = error;
+	});
+}
+
+///
+/// # `SessionStatus`
+/// State of the session with a vendor when the bundle was made.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct SessionStatus {
+	pub manufacturer: String,
+	/// When the saved session token was last written, `None` if this process has not loaded or saved one.
+	pub saved_at: Option<DateTime<Utc>>,
+	pub account_issue: Option<AccountIssue>,
+	pub detail: Option<String>,
+}
+
+///
+/// # `EnvironmentInfo`
+/// Build and host of the service that made the bundle.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct EnvironmentInfo {
+	pub crate_version: String,
+	pub features: Vec<String>,
+	pub os: String,
+	pub arch: String,
+}
+
+///
+/// # `SupportBundle`
+/// Everything known about one lookup, for attaching to a support ticket. Secret values are redacted
+/// everywhere and the user is reduced to id, job title and office.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct SupportBundle {
+	pub request_id: String,
+	pub generated_at: DateTime<Utc>,
+	pub request: AvailabilityRequest,
+	/// The v1 answer of a lookup that completed.
+	pub outcome: Option<String>,
+	pub error: Option<String>,
+	pub trace: Vec<TraceStep>,
+	pub captures: Vec<PayloadCapture>,
+	pub sessions: Vec<SessionStatus>,
+	pub config: BTreeMap<String, String>,
+	pub environment: EnvironmentInfo,
+}
+
+impl SupportBundle {
+	///
+	/// # `SupportBundle::to_json`
+	/// The bundle as canonical JSON, ready to attach.
+	///
+	/// # Errors
+	/// Returns an error if the bundle cannot be serialized.
+	///
+	pub fn to_json(&self) -> Result<String, String> {
+		to_canonical_json(self)
+	}
+}
+
+///
+/// # `support_bundle`
+/// Collect the trace, vendor payloads, session status, configuration and environment of the lookup with
+/// `request_id`, as returned on its response.
+///
+/// # Errors
+/// Returns an error if the lookup is unknown or older than the last `MAX_TRACKED_REQUESTS` lookups.
+///
+pub fn support_bundle(request_id: &str) -> Result<SupportBundle, String> {
+	let tracked = tracked().lock().unwrap_or_else(std::sync::PoisonError::into_inner).iter().rev().find(|tracked| tracked.id == request_id).cloned().ok_or_else(|| format!("No trace kept for request {request_id}."))?;
+	Ok(SupportBundle {
+		request_id: tracked.id,
+		generated_at: Utc::now(),
+		request: tracked.request,
+		outcome: tracked.outcome,
+		error: tracked.error,
+		trace: tracked.trace,
+		captures: tracked.captures,
+		sessions: sessions(),
+		config: config_snapshot(),
+		environment: environment(),
+	})
+}
+
+#[allow(clippy::vec_init_then_push)]
+#[cfg_attr(not(any(feature = "bsh", feature = "subzero", feature = "miele")), allow(unused_mut, clippy::missing_const_for_fn))]
+fn sessions() -> Vec<SessionStatus> {
+	let mut sessions = Vec::new();
+	#[cfg(feature = "bsh")]
+	sessions.push(SessionStatus { manufacturer: "bsh".to_string(), saved_at: super::tokens::saved_at(super::bsh::BSH_TOKEN), account_issue: super::account::account_issue("bsh"), detail: None });
+	#[cfg(feature = "subzero")]
+	sessions.push(SessionStatus {
+		manufacturer: "subzero".to_string(),
+		saved_at: super::tokens::saved_at(super::subzero::SUBZERO_TOKEN),
+		account_issue: super::account::account_issue("subzero"),
+		detail: super::subzero::subzero_cart_dirty().then(|| "cart left dirty by a failed cleanup".to_string()),
+	});
+	#[cfg(feature = "miele")]
+	sessions.push(SessionStatus {
+		manufacturer: "miele".to_string(),
+		saved_at: None,
+		account_issue: super::account::account_issue("miele"),
+		detail: super::miele::current_miele_catalog().map(|catalog| format!("catalog version {} ({}) loaded at {}", catalog.version, catalog.digest, catalog.loaded_at.to_rfc3339())),
+	});
+	sessions
+}
+
+fn config_snapshot() -> BTreeMap<String, String> {
+	let mut config = BTreeMap::new();
+	config.insert("result_cache".to_string(), format!("{:?}", cache::result_cache_config()));
+	config.insert("memory_budget".to_string(), format!("{:?}", memory::memory_budget()));
+	config.insert("quote_validity".to_string(), format!("{:?}", quote::quote_validity()));
+	config.insert("http.backoff".to_string(), format!("{:?}", super::http_client::backoff_policy()));
+	config.insert("schedule".to_string(), format!("{:?}", super::schedule::schedule_policy(None, None)));
+	config.insert("canary".to_string(), format!("{:?}", super::canary::canary_policy()));
+	for manufacturer in ["bsh", "subzero", "miele", "liebherr", "fisher_paykel", "jennair", "monogram", "true_residential", "dacor", "bertazzoni"] {
+		config.insert(format!("hedge.{manufacturer}"), format!("{:?}", hedge::hedge_policy(manufacturer)));
+	}
+	#[cfg(feature = "bsh")]
+	config.insert("bsh.language".to_string(), super::bsh::bsh_language());
+	#[cfg(feature = "bsh")]
+	for brand in [super::bsh::BshBrand::Bosch, super::bsh::BshBrand::Thermador, super::bsh::BshBrand::Gaggenau] {
+		config.insert(format!("bsh.brand.{}", brand.manufacturer()), format!("{:?}", super::bsh::bsh_brand_config(brand)));
+	}
+	#[cfg(feature = "subzero")]
+	config.insert("subzero.price_list".to_string(), format!("{:?}", super::subzero::subzero_price_list_config()));
+	#[cfg(feature = "miele")]
+	config.insert("miele.source".to_string(), format!("{:?}", super::miele::miele_source()));
+	#[cfg(feature = "liebherr")]
+	config.insert("liebherr.feed".to_string(), format!("{:?}", super::liebherr::liebherr_config()));
+	#[cfg(feature = "fisher-paykel")]
+	config.insert("fisher_paykel.portal".to_string(), format!("{:?}", super::fisher_paykel::fisher_paykel_config()));
+	#[cfg(feature = "jennair")]
+	config.insert("jennair.portal".to_string(), format!("{:?}", super::jennair::jennair_config()));
+	#[cfg(feature = "monogram")]
+	config.insert("monogram.api".to_string(), format!("{:?}", super::monogram::monogram_config()));
+	#[cfg(feature = "true-residential")]
+	config.insert("true_residential.service".to_string(), format!("{:?}", super::true_residential::true_residential_config()));
+	#[cfg(feature = "dacor")]
+	config.insert("dacor.portal".to_string(), format!("{:?}", super::dacor::dacor_config()));
+	#[cfg(feature = "bertazzoni")]
+	config.insert("bertazzoni.feed".to_string(), format!("{:?}", super::bertazzoni::bertazzoni_feed_config()));
+	config.into_iter().map(|(key, value)| (key, secrets::redact(&value))).collect()
+}
+
+fn environment() -> EnvironmentInfo {
+	let features = [
+		("bsh", cfg!(feature = "bsh")),
+		("browser-login", cfg!(feature = "browser-login")),
+		("subzero", cfg!(feature = "subzero")),
+		("miele", cfg!(feature = "miele")),
+		("liebherr", cfg!(feature = "liebherr")),
+		("fisher-paykel", cfg!(feature = "fisher-paykel")),
+		("jennair", cfg!(feature = "jennair")),
+		("monogram", cfg!(feature = "monogram")),
+		("true-residential", cfg!(feature = "true-residential")),
+		("dacor", cfg!(feature = "dacor")),
+		("bertazzoni", cfg!(feature = "bertazzoni")),
+		("keyvault", cfg!(feature = "keyvault")),
+		("auth", cfg!(feature = "auth")),
+		("tower", cfg!(feature = "tower")),
+		("xlsx", cfg!(feature = "xlsx")),
+		("telemetry-tracing", cfg!(feature = "telemetry-tracing")),
+		("metrics", cfg!(feature = "metrics")),
+		("fault-injection", cfg!(feature = "fault-injection")),
+	];
+	EnvironmentInfo {
+		crate_version: env!("CARGO_PKG_VERSION").to_string(),
+		features: features.iter().filter(|(_, enabled)| *enabled).map(|(feature, _)| (*feature).to_string()).collect(),
+		os: std::env::consts::OS.to_string(),
+		arch: std::env::consts::ARCH.to_string(),
+	}
+}
@@ -0,0 +1,140 @@
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+
+///
+/// # `TelemetryExporter`
+/// Sink for all metrics and tracing emitted by the crate.
+///
+/// The crate never talks to a metrics or tracing backend directly; every emission goes through the
+/// exporter installed with `set_exporter`. Until one is installed the `NoopExporter` is used.
+///
+pub trait TelemetryExporter: Send + Sync {
+	///
+	/// Increment the counter `name` by `value`.
+	///
+	fn counter(&self, name: &'static str, value: u64, labels: &[(&'static str, String)]);
+
+	///
+	/// Record a single observation for the histogram `name`.
+	///
+	fn histogram(&self, name: &'static str, value: f64, labels: &[(&'static str, String)]);
+
+	///
+	/// Emit a named event with structured fields.
+	///
+	fn event(&self, name: &'static str, fields: &[(&'static str, String)]);
+}
+
+///
+/// # `NoopExporter`
+/// Default exporter, discards everything.
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopExporter;
+
+impl TelemetryExporter for NoopExporter {
+	fn counter(&self, _name: &'static str, _value: u64, _labels: &[(&'static str, String)]) {}
+
+	fn histogram(&self, _name: &'static str, _value: f64, _labels: &[(&'static str, String)]) {}
+
+	fn event(&self, _name: &'static str, _fields: &[(&'static str, String)]) {}
+}
+
+///
+/// # `TracingExporter`
+/// Forwards every emission to the `tracing` crate as an `INFO` event on the `appliance_availability` target.
+///
+#[cfg(feature = "telemetry-tracing")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingExporter;
+
+#[cfg(feature = "telemetry-tracing")]
+impl TelemetryExporter for TracingExporter {
+	fn counter(&self, name: &'static str, value: u64, labels: &[(&'static str, String)]) {
+		tracing::info!(target: "appliance_availability", metric = name, kind = "counter", value, labels = ?labels);
+	}
+
+	fn histogram(&self, name: &'static str, value: f64, labels: &[(&'static str, String)]) {
+		tracing::info!(target: "appliance_availability", metric = name, kind = "histogram", value, labels = ?labels);
+	}
+
+	fn event(&self, name: &'static str, fields: &[(&'static str, String)]) {
+		tracing::info!(target: "appliance_availability", event = name, fields = ?fields);
+	}
+}
+
+///
+/// # `MetricsExporter`
+/// Records counters and histograms through the `metrics` facade, for whichever recorder the hosting server
+/// installed, e.g. a Prometheus exporter. Events are not metrics and are dropped.
+///
+/// Per manufacturer this covers lookups (`availability.lookup.success` and `.failure`, `.duration`), vendor
+/// calls (`vendor.request.success` and `.failure`, `.duration` in seconds), logins (`vendor.login`) and the
+/// result cache (`availability.cache.hit` and `.miss`).
+///
+#[cfg(feature = "metrics")]
+#[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsExporter;
+
+#[cfg(feature = "metrics")]
+fn metric_labels(labels: &[(&'static str, String)]) -> Vec<metrics::Label> {
+	labels.iter().map(|(key, value)| metrics::Label::new(*key, value.clone())).collect()
+}
+
+#[cfg(feature = "metrics")]
+impl TelemetryExporter for MetricsExporter {
+	fn counter(&self, name: &'static str, value: u64, labels: &[(&'static str, String)]) {
+		metrics::counter!(name, metric_labels(labels)).increment(value);
+	}
+
+	fn histogram(&self, name: &'static str, value: f64, labels: &[(&'static str, String)]) {
+		metrics::histogram!(name, metric_labels(labels)).record(value);
+	}
+
+	fn event(&self, _name: &'static str, _fields: &[(&'static str, String)]) {}
+}
+
+fn exporter_slot() -> &'static RwLock<Arc<dyn TelemetryExporter>> {
+	static EXPORTER: OnceLock<RwLock<Arc<dyn TelemetryExporter>>> = OnceLock::new();
+	EXPORTER.get_or_init(|| RwLock::new(Arc::new(NoopExporter)))
+}
+
+fn exporter() -> Arc<dyn TelemetryExporter> {
+	exporter_slot().read().map_or_else(|poisoned| poisoned.into_inner().clone(), |exporter| exporter.clone())
+}
+
+///
+/// # `set_exporter`
+/// Install the process-wide telemetry exporter.
+///
+/// ## Example
+/// ```
+/// use std::sync::Arc;
+/// use eggersmann_app_server_appliance_availability::telemetry::{set_exporter, NoopExporter};
+///
+/// set_exporter(Arc::new(NoopExporter));
+/// ```
+///
+pub fn set_exporter(new_exporter: Arc<dyn TelemetryExporter>) {
+	match exporter_slot().write() {
+		Ok(mut exporter) => *exporter = new_exporter,
+		Err(poisoned) => *poisoned.into_inner() = new_exporter,
+	}
+}
+
+pub(crate) fn counter(name: &'static str, value: u64, labels: &[(&'static str, String)]) {
+	exporter().counter(name, value, labels);
+}
+
+pub(crate) fn histogram(name: &'static str, value: f64, labels: &[(&'static str, String)]) {
+	exporter().histogram(name, value, labels);
+}
+
+pub(crate) fn latency(name: &'static str, elapsed: Duration, labels: &[(&'static str, String)]) {
+	exporter().histogram(name, elapsed.as_secs_f64(), labels);
+}
+
+pub(crate) fn event(name: &'static str, fields: &[(&'static str, String)]) {
+	exporter().event(name, fields);
+}
@@ -0,0 +1,268 @@
+//!
+//! # Test support
+//! Fakes for tests of code built on this crate, without network or credentials: `ScriptedBackend`
+//! answers a manufacturer from a script, the `*_response` functions build finished responses, and the
+//! `assert_*` functions check them.
+//!
+//! ## Example
+//! ```
+//! use eggersmann_app_server_appliance_availability::testing::{assert_in_stock, ScriptedBackend};
+//! use eggersmann_app_server_appliance_availability::AvailabilityRequest;
+//!
+//! # tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap().block_on(async {
+//! let vendor = ScriptedBackend::new("acme").in_stock("FRIDGE-1", 3).install();
+//! let req = AvailabilityRequest::new("acme".to_string(), "houston".to_string(), "FRIDGE-1".to_string()).parse_manufacturer().get_time();
+//! let answered = req.into_answered().await.unwrap();
+//! assert_in_stock(&answered.response());
+//! assert_eq!(vendor.call_count("FRIDGE-1"), 1);
+//! # });
+//! ```
+//!
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+
+use chrono::NaiveDate;
+
+use super::backend::{self, BackendAnswer, ManufacturerBackend};
+use super::response::V1_NOT_FOUND;
+use super::{Availability, AvailabilityError, AvailabilityRequest, AvailabilityResponse, AvailabilityStatus};
+
+type Script = HashMap<String, VecDeque<Result<BackendAnswer, AvailabilityError>>>;
+
+///
+/// # `ScriptedBackend`
+/// A manufacturer backend registered as `name` that answers from a script instead of a vendor. Each model
+/// has a queue of results, used one per lookup; the last one keeps being used. A model without a script
+/// is not found. Every request it receives is kept for `calls`.
+///
+/// The result cache keeps not-found answers for a while, so a test that scripts a model as not found and
+/// then found should use a model number of its own.
+///
+#[derive(Debug)]
+pub struct ScriptedBackend {
+	name: &'static str,
+	script: Mutex<Script>,
+	calls: Mutex<Vec<AvailabilityRequest>>,
+}
+
+impl ScriptedBackend {
+	#[must_use]
+	pub fn new(name: &'static str) -> Self {
+		Self { name, script: Mutex::new(HashMap::new()), calls: Mutex::new(Vec::new()) }
+	}
+
+	///
+	/// # `ScriptedBackend::answer`
+	/// Queue `answer` for the next lookup of `model_number`.
+	///
+	#[must_use]
+	pub fn answer(self, model_number: &str, answer: BackendAnswer) -> Self {
+		self.push(model_number, Ok(answer))
+	}
+
+	///
+	/// # `ScriptedBackend::fail`
+	/// Queue `error` for the next lookup of `model_number`.
+	///
+	#[must_use]
+	pub fn fail(self, model_number: &str, error: AvailabilityError) -> Self {
+		self.push(model_number, Err(error))
+	}
+
+	#[must_use]
+	pub fn in_stock(self, model_number: &str, quantity: u32) -> Self {
+		let availability = Availability::new(AvailabilityStatus::InStock, format!("Found: {}, In stock: {quantity}", key(model_number))).with_quantity(quantity).with_model_number(&key(model_number));
+		self.answer(model_number, BackendAnswer::new(availability))
+	}
+
+	#[must_use]
+	pub fn backordered(self, model_number: &str, available_date: NaiveDate) -> Self {
+		let availability = Availability::new(AvailabilityStatus::Backordered, format!("Found: {}, Available: {}", key(model_number), available_date.format("%m/%d/%Y"))).with_available_date(available_date).with_model_number(&key(model_number));
+		self.answer(model_number, BackendAnswer::new(availability))
+	}
+
+	#[must_use]
+	pub fn discontinued(self, model_number: &str) -> Self {
+		let availability = Availability::new(AvailabilityStatus::Discontinued, format!("Found: {}, Discontinued", key(model_number))).with_model_number(&key(model_number));
+		self.answer(model_number, BackendAnswer::new(availability))
+	}
+
+	#[must_use]
+	pub fn not_found(self, model_number: &str) -> Self {
+		self.answer(model_number, BackendAnswer::new(Availability::new(AvailabilityStatus::NotFound, V1_NOT_FOUND.to_string())))
+	}
+
+	fn push(self, model_number: &str, result: Result<BackendAnswer, AvailabilityError>) -> Self {
+		self.script.lock().unwrap_or_else(std::sync::PoisonError::into_inner).entry(key(model_number)).or_default().push_back(result);
+		self
+	}
+
+	///
+	/// # `ScriptedBackend::install`
+	/// Register the backend in place of any backend of the same name. The returned guard puts the
+	/// previous backend back when it is dropped, at the end of the test.
+	///
+	#[must_use]
+	pub fn install(self) -> InstalledBackend {
+		let backend = Arc::new(self);
+		let previous = backend::backend(backend.name);
+		backend::register_backend(Arc::clone(&backend) as Arc<dyn ManufacturerBackend>);
+		InstalledBackend { backend, previous }
+	}
+
+	///
+	/// # `ScriptedBackend::calls`
+	/// Every request the backend received, oldest first.
+	///
+	#[must_use]
+	pub fn calls(&self) -> Vec<AvailabilityRequest> {
+		self.calls.lock().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+	}
+
+	///
+	/// # `ScriptedBackend::call_count`
+	/// How many lookups of `model_number` the backend received.
+	///
+	#[must_use]
+	pub fn call_count(&self, model_number: &str) -> usize {
+		let model_number = key(model_number);
+		self.calls.lock().unwrap_or_else(std::sync::PoisonError::into_inner).iter().filter(|call| call.model_number.as_deref().map(key).as_ref() == Some(&model_number)).count()
+	}
+}
+
+#[async_trait::async_trait]
+impl ManufacturerBackend for ScriptedBackend {
+	fn name(&self) -> &'static str {
+		self.name
+	}
+
+	async fn availability(&self, req: &AvailabilityRequest) -> Result<BackendAnswer, AvailabilityError> {
+		self.calls.lock().unwrap_or_else(std::sync::PoisonError::into_inner).push(req.clone());
+		let model_number = req.model_number.as_deref().map(key).unwrap_or_default();
+		let mut script = self.script.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+		let Some(results) = script.get_mut(&model_number) else {
+			return Ok(BackendAnswer::new(Availability::new(AvailabilityStatus::NotFound, V1_NOT_FOUND.to_string())));
+		};
+		let result = if results.len() > 1 { results.pop_front() } else { results.front().cloned() };
+		drop(script);
+		result.unwrap_or_else(|| Ok(BackendAnswer::new(Availability::new(AvailabilityStatus::NotFound, V1_NOT_FOUND.to_string()))))
+	}
+}
+
+fn key(model_number: &str) -> String {
+	model_number.trim().to_uppercase()
+}
+
+///
+/// # `InstalledBackend`
+/// A `ScriptedBackend` answering lookups until this guard is dropped.
+///
+pub struct InstalledBackend {
+	backend: Arc<ScriptedBackend>,
+	previous: Option<Arc<dyn ManufacturerBackend>>,
+}
+
+impl fmt::Debug for InstalledBackend {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("InstalledBackend").field("backend", &self.backend).field("replaced", &self.previous.is_some()).finish()
+	}
+}
+
+impl Deref for InstalledBackend {
+	type Target = ScriptedBackend;
+
+	fn deref(&self) -> &ScriptedBackend {
+		&self.backend
+	}
+}
+
+impl Drop for InstalledBackend {
+	fn drop(&mut self) {
+		match self.previous.take() {
+			Some(previous) => backend::register_backend(previous),
+			None => backend::unregister_backend(self.backend.name),
+		}
+	}
+}
+
+///
+/// # `in_stock_response`
+/// A finished response for `quantity` of `model_number` in stock.
+///
+#[must_use]
+pub fn in_stock_response(manufacturer: &str, model_number: &str, quantity: u32) -> AvailabilityResponse {
+	let raw = format!("Found: {}, In stock: {quantity}", key(model_number));
+	AvailabilityResponse::builder().manufacturer(manufacturer).model_number(model_number).availability(raw.clone()).availability_detail(Availability::new(AvailabilityStatus::InStock, raw).with_quantity(quantity)).build()
+}
+
+///
+/// # `backordered_response`
+/// A finished response for `model_number` available from `available_date`.
+///
+#[must_use]
+pub fn backordered_response(manufacturer: &str, model_number: &str, available_date: NaiveDate) -> AvailabilityResponse {
+	let raw = format!("Found: {}, Available: {}", key(model_number), available_date.format("%m/%d/%Y"));
+	AvailabilityResponse::builder().manufacturer(manufacturer).model_number(model_number).availability(raw.clone()).availability_detail(Availability::new(AvailabilityStatus::Backordered, raw).with_available_date(available_date)).build()
+}
+
+///
+/// # `not_found_response`
+/// A finished response for a `model_number` the vendor does not know.
+///
+#[must_use]
+pub fn not_found_response(manufacturer: &str, model_number: &str) -> AvailabilityResponse {
+	AvailabilityResponse::builder().manufacturer(manufacturer).model_number(model_number).availability(V1_NOT_FOUND).availability_detail(Availability::new(AvailabilityStatus::NotFound, V1_NOT_FOUND.to_string())).build()
+}
+
+///
+/// # `assert_status`
+/// Panic unless the structured availability of `response` has `status`.
+///
+/// # Panics
+/// If the response has another status or no structured availability.
+///
+#[track_caller]
+pub fn assert_status(response: &AvailabilityResponse, status: AvailabilityStatus) {
+	let found = response.availability_detail.as_ref().map(|availability| availability.status);
+	assert_eq!(found, Some(status), "availability of {:?}: {:?}", response.model_number, response.availability);
+}
+
+///
+/// # `assert_in_stock`
+/// Panic unless `response` is in stock.
+///
+/// # Panics
+/// See `assert_status`.
+///
+#[track_caller]
+pub fn assert_in_stock(response: &AvailabilityResponse) {
+	assert_status(response, AvailabilityStatus::InStock);
+}
+
+///
+/// # `assert_not_found`
+/// Panic unless the vendor did not know the model of `response`.
+///
+/// # Panics
+/// See `assert_status`.
+///
+#[track_caller]
+pub fn assert_not_found(response: &AvailabilityResponse) {
+	assert_status(response, AvailabilityStatus::NotFound);
+}
+
+///
+/// # `assert_available_on`
+/// Panic unless `response` is available from `available_date`.
+///
+/// # Panics
+/// If the response has another or no available date.
+///
+#[track_caller]
+pub fn assert_available_on(response: &AvailabilityResponse, available_date: NaiveDate) {
+	let found = response.availability_detail.as_ref().and_then(|availability| availability.available_date);
+	assert_eq!(found, Some(available_date), "available date of {:?}: {:?}", response.model_number, response.availability);
+}
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+
+///
+/// Time zone used when a request has no showroom or the showroom has no configured zone.
+///
+pub const DEFAULT_BUSINESS_TIME_ZONE: Tz = chrono_tz::America::Chicago;
+
+fn time_zones() -> &'static RwLock<HashMap<String, Tz>> {
+	static TIME_ZONES: OnceLock<RwLock<HashMap<String, Tz>>> = OnceLock::new();
+	TIME_ZONES.get_or_init(|| RwLock::new(HashMap::from([("houston".to_string(), chrono_tz::America::Chicago), ("florida".to_string(), chrono_tz::America::New_York), ("los angeles".to_string(), chrono_tz::America::Los_Angeles), ("chicago".to_string(), chrono_tz::America::Chicago), ("new york".to_string(), chrono_tz::America::New_York), ("dallas".to_string(), chrono_tz::America::Chicago)])))
+}
+
+///
+/// # `set_showroom_time_zone`
+/// Configure the business time zone of a showroom.
+///
+pub fn set_showroom_time_zone(showroom: &str, time_zone: Tz) {
+	time_zones().write().unwrap_or_else(std::sync::PoisonError::into_inner).insert(showroom.to_lowercase(), time_zone);
+}
+
+///
+/// # `showroom_time_zone`
+/// The business time zone of a showroom, `DEFAULT_BUSINESS_TIME_ZONE` if none is configured.
+///
+#[must_use]
+pub fn showroom_time_zone(showroom: Option<&str>) -> Tz {
+	showroom.and_then(|showroom| time_zones().read().unwrap_or_else(std::sync::PoisonError::into_inner).get(&showroom.to_lowercase()).copied()).unwrap_or(DEFAULT_BUSINESS_TIME_ZONE)
+}
+
+///
+/// # `business_today`
+/// The calendar date at `now` in the showroom's business time zone.
+///
+/// A server running in UTC would otherwise report tomorrow's date during the US evening.
+///
+/// ## Example
+/// ```
+/// use chrono::{NaiveDate, TimeZone, Utc};
+/// use eggersmann_app_server_appliance_availability::business_today;
+///
+/// // 02:30 UTC is still the previous evening in Houston.
+/// let now = Utc.with_ymd_and_hms(2024, 3, 5, 2, 30, 0).unwrap();
+/// assert_eq!(business_today(Some("houston"), now), NaiveDate::from_ymd_opt(2024, 3, 4).unwrap());
+/// ```
+///
+#[must_use]
+pub fn business_today(showroom: Option<&str>, now: DateTime<Utc>) -> NaiveDate {
+	now.with_timezone(&showroom_time_zone(showroom)).date_naive()
+}
+
+///
+/// # `showroom_local_time`
+/// `at` on the showroom's wall clock, in the v1 time format followed by the zone abbreviation.
+///
+/// ## Example
+/// ```
+/// use chrono::{TimeZone, Utc};
+/// use eggersmann_app_server_appliance_availability::showroom_local_time;
+///
+/// let at = Utc.with_ymd_and_hms(2024, 3, 15, 4, 30, 0).unwrap();
+/// assert_eq!(showroom_local_time(Some("houston"), at), "03/14/2024 11:30:00 PM CDT");
+/// ```
+///
+#[must_use]
+pub fn showroom_local_time(showroom: Option<&str>, at: DateTime<Utc>) -> String {
+	at.with_timezone(&showroom_time_zone(showroom)).format(&format!("{} %Z", crate::compat::V1_TIME_FORMAT)).to_string()
+}
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use tokio::fs;
+
+use crate::credentials::SecretString;
+use crate::http_client;
+
+///
+/// Directory the vendor sessions are kept in when no store is configured.
+///
+pub const DEFAULT_TOKEN_DIR: &str = "/easfiles/appliances/cookies";
+
+///
+/// # `StoredToken`
+/// A saved vendor session as the store holds it.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct StoredToken {
+	pub contents: String,
+	/// When the session was saved, if the store knows.
+	pub saved_at: Option<DateTime<Utc>>,
+}
+
+impl StoredToken {
+	#[must_use]
+	pub const fn new(contents: String, saved_at: Option<DateTime<Utc>>) -> Self {
+		Self { contents, saved_at }
+	}
+}
+
+///
+/// # `TokenStore`
+/// Where the vendor sessions saved by `bsh_login` and `subzero_login` are kept, by name, e.g.
+/// `bsh_cookies.json`. Instances of a multi-instance deployment share a session by sharing a store.
+///
+#[async_trait::async_trait]
+pub trait TokenStore: Send + Sync {
+	///
+	/// The session saved as `name`, `None` if there is none.
+	///
+	async fn load(&self, name: &str) -> Result<Option<StoredToken>, String>;
+
+	///
+	/// Save `contents` as `name`, replacing the previous session.
+	///
+	async fn save(&self, name: &str, contents: &str) -> Result<(), String>;
+}
+
+///
+/// # `FileTokenStore`
+/// Sessions kept as files in `dir`, which is created on the first save.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FileTokenStore {
+	pub dir: PathBuf,
+}
+
+impl FileTokenStore {
+	#[must_use]
+	pub fn new(dir: impl Into<PathBuf>) -> Self {
+		Self { dir: dir.into() }
+	}
+}
+
+impl Default for FileTokenStore {
+	fn default() -> Self {
+		Self::new(DEFAULT_TOKEN_DIR)
+	}
+}
+
+#[async_trait::async_trait]
+impl TokenStore for FileTokenStore {
+	async fn load(&self, name: &str) -> Result<Option<StoredToken>, String> {
+		let path = self.dir.join(name);
+		let contents = match fs::read_to_string(&path).await {
+			Ok(contents) => contents,
+			Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+			Err(e) => return Err(format!("Failed to read {}: {e:?}", path.display())),
+		};
+		let saved_at = fs::metadata(&path).await.and_then(|metadata| metadata.modified()).ok().map(DateTime::<Utc>::from);
+		Ok(Some(StoredToken::new(contents, saved_at)))
+	}
+
+	async fn save(&self, name: &str, contents: &str) -> Result<(), String> {
+		fs::create_dir_all(&self.dir).await.map_err(|e| format!("Failed to create {}: {e:?}", self.dir.display()))?;
+		// write next to the session and move it into place, so a reader never sees half a session.
+		let path = self.dir.join(name);
+		let tmp = path.with_extension("tmp");
+		fs::write(&tmp, contents).await.map_err(|e| format!("Failed to write {}: {e:?}", tmp.display()))?;
+		fs::rename(&tmp, &path).await.map_err(|e| format!("Failed to replace {}: {e:?}", path.display()))
+	}
+}
+
+///
+/// # `InMemoryTokenStore`
+/// Sessions kept in process memory, lost on restart. For tests and single instances without a disk.
+///
+#[derive(Debug, Default)]
+pub struct InMemoryTokenStore {
+	tokens: Mutex<HashMap<String, StoredToken>>,
+}
+
+#[async_trait::async_trait]
+impl TokenStore for InMemoryTokenStore {
+	async fn load(&self, name: &str) -> Result<Option<StoredToken>, String> {
+		Ok(self.tokens.lock().unwrap_or_else(std::sync::PoisonError::into_inner).get(name).cloned())
+	}
+
+	async fn save(&self, name: &str, contents: &str) -> Result<(), String> {
+		self.tokens.lock().unwrap_or_else(std::sync::PoisonError::into_inner).insert(name.to_string(), StoredToken::new(contents.to_string(), Some(Utc::now())));
+		Ok(())
+	}
+}
+
+///
+/// # `AzureBlobTokenStore`
+/// Sessions kept as block blobs in an Azure Storage container, so every instance of a deployment uses
+/// the same session. `sas_token` needs read and write permission on the container.
+///
+/// ## Example
+/// ```
+/// use std::sync::Arc;
+/// use eggersmann_app_server_appliance_availability::tokens::{set_token_store, AzureBlobTokenStore};
+///
+/// set_token_store(Arc::new(AzureBlobTokenStore::new("https://eggstorage.blob.core.windows.net/sessions".to_string(), "sv=2022-11-02&sp=rw&sig=...".into())));
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AzureBlobTokenStore {
+	pub container_url: String,
+	pub sas_token: SecretString,
+}
+
+impl AzureBlobTokenStore {
+	#[must_use]
+	pub const fn new(container_url: String, sas_token: SecretString) -> Self {
+		Self { container_url, sas_token }
+	}
+
+	fn blob_url(&self, name: &str) -> String {
+		format!("{}/{}?{}", self.container_url.trim_end_matches('/'), urlencoding::encode(name), self.sas_token.expose().trim_start_matches('?'))
+	}
+
+	///
+	/// `e` as text without the blob url, whose query is the SAS token, and without the token itself in case
+	/// a middleware quoted it.
+	///
+	fn describe(&self, e: impl std::fmt::Display) -> String {
+		let token = self.sas_token.expose().trim_start_matches('?');
+		let text = e.to_string();
+		if token.is_empty() {
+			text
+		} else {
+			text.replace(token, "[redacted]")
+		}
+	}
+
+	fn describe_send(&self, e: reqwest_middleware::Error) -> String {
+		match e {
+			reqwest_middleware::Error::Reqwest(e) => self.describe(e.without_url()),
+			e @ reqwest_middleware::Error::Middleware(_) => self.describe(e),
+		}
+	}
+}
+
+#[async_trait::async_trait]
+impl TokenStore for AzureBlobTokenStore {
+	async fn load(&self, name: &str) -> Result<Option<StoredToken>, String> {
+		let response = http_client::client().get(self.blob_url(name)).send().await.map_err(|e| format!("Failed to get session blob {name}: {}", self.describe_send(e)))?;
+		match response.status() {
+			StatusCode::NOT_FOUND => return Ok(None),
+			status if !status.is_success() => return Err(format!("Failed to get session blob {name}: {status}")),
+			_ => {}
+		}
+		let saved_at = response.headers().get(reqwest::header::LAST_MODIFIED).and_then(|modified| modified.to_str().ok()).and_then(|modified| DateTime::parse_from_rfc2822(modified).ok()).map(|modified| modified.with_timezone(&Utc));
+		// read without `http_client::text`, which would keep the body and its url in the support bundle.
+		let body = http_client::bytes(response).await.map_err(|e| format!("Failed to read session blob {name}: {}", self.describe(e)))?;
+		let contents = String::from_utf8(body).map_err(|e| format!("Session blob {name} is not UTF-8: {e}"))?;
+		Ok(Some(StoredToken::new(contents, saved_at)))
+	}
+
+	async fn save(&self, name: &str, contents: &str) -> Result<(), String> {
+		let response = http_client::client().put(self.blob_url(name)).header("x-ms-blob-type", "BlockBlob").header(reqwest::header::CONTENT_TYPE, "application/json").body(contents.to_string()).send().await.map_err(|e| format!("Failed to save session blob {name}: {}", self.describe_send(e)))?;
+		if response.status().is_success() {
+			Ok(())
+		} else {
+			Err(format!("Failed to save session blob {name}: {}", response.status()))
+		}
+	}
+}
+
+fn store_slot() -> &'static RwLock<Arc<dyn TokenStore>> {
+	static STORE: OnceLock<RwLock<Arc<dyn TokenStore>>> = OnceLock::new();
+	STORE.get_or_init(|| RwLock::new(Arc::new(FileTokenStore::default())))
+}
+
+fn saved_times() -> &'static RwLock<HashMap<String, DateTime<Utc>>> {
+	static SAVED: OnceLock<RwLock<HashMap<String, DateTime<Utc>>>> = OnceLock::new();
+	SAVED.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+///
+/// # `set_token_store`
+/// Keep vendor sessions in `store` from now on. Sessions in the previous store are not moved, so the
+/// vendors are logged in to again on their next lookup.
+///
+pub fn set_token_store(store: Arc<dyn TokenStore>) {
+	*store_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = store;
+	saved_times().write().unwrap_or_else(std::sync::PoisonError::into_inner).clear();
+}
+
+///
+/// # `token_store`
+/// The store vendor sessions are kept in, files in `DEFAULT_TOKEN_DIR` unless one is set.
+///
+#[must_use]
+pub fn token_store() -> Arc<dyn TokenStore> {
+	store_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
+
+///
+/// Load the session `name` from the configured store.
+///
+pub(crate) async fn load(name: &str) -> Result<Option<String>, String> {
+	let Some(token) = token_store().load(name).await? else { return Ok(None) };
+	if let Some(saved_at) = token.saved_at {
+		saved_times().write().unwrap_or_else(std::sync::PoisonError::into_inner).insert(name.to_string(), saved_at);
+	}
+	Ok(Some(token.contents))
+}
+
+///
+/// Save the session `name` to the configured store.
+///
+pub(crate) async fn save(name: &str, contents: &str) -> Result<(), String> {
+	token_store().save(name, contents).await?;
+	saved_times().write().unwrap_or_else(std::sync::PoisonError::into_inner).insert(name.to_string(), Utc::now());
+	Ok(())
+}
+
+///
+/// When the session `name` was saved, as far as this process has seen it loaded or saved.
+///
+pub(crate) fn saved_at(name: &str) -> Option<DateTime<Utc>> {
+	saved_times().read().unwrap_or_else(std::sync::PoisonError::into_inner).get(name).copied()
+}
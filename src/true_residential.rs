@@ -0,0 +1,126 @@
+use std::sync::{OnceLock, RwLock};
+
+use reqwest::StatusCode;
+use serde_json::Value;
+
+use super::backend::{BackendAnswer, ManufacturerBackend};
+use super::{Availability, AvailabilityError, AvailabilityRequest, ProductInfo};
+use crate::credentials::SecretString;
+use crate::http_client::{self, HttpClient};
+use crate::stock::{self, StockKeys};
+
+///
+/// # `TrueResidentialConfig`
+/// The True Residential dealer availability service, which takes the dealer login as HTTP basic
+/// authentication on every request, the model as `sku` and the warehouse as `warehouse`. The service
+/// address comes with the dealer account, so there is no default and lookups fail until
+/// `set_true_residential_config` is called.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TrueResidentialConfig {
+	pub availability_url: String,
+}
+
+impl TrueResidentialConfig {
+	#[must_use]
+	pub const fn new(availability_url: String) -> Self {
+		Self { availability_url }
+	}
+}
+
+fn config_slot() -> &'static RwLock<Option<TrueResidentialConfig>> {
+	static CONFIG: OnceLock<RwLock<Option<TrueResidentialConfig>>> = OnceLock::new();
+	CONFIG.get_or_init(|| RwLock::new(None))
+}
+
+///
+/// # `set_true_residential_config`
+/// Set the True Residential dealer availability service to use.
+///
+pub fn set_true_residential_config(config: TrueResidentialConfig) {
+	*config_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = Some(config);
+}
+
+///
+/// # `true_residential_config`
+/// The True Residential dealer availability service in use, `None` until it is set.
+///
+#[must_use]
+pub fn true_residential_config() -> Option<TrueResidentialConfig> {
+	config_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
+
+fn configured() -> Result<TrueResidentialConfig, String> {
+	true_residential_config().ok_or_else(|| "No True Residential dealer service configured, see `set_true_residential_config`.".to_string())
+}
+
+///
+/// # `TrueResidentialBackend`
+/// True Residential lookups through the dealer availability service, registered as `true_residential`.
+/// Requests go through `client`, the shared client unless one is given.
+///
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct TrueResidentialBackend {
+	pub client: HttpClient,
+}
+
+impl TrueResidentialBackend {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	#[must_use]
+	pub fn with_client(mut self, client: HttpClient) -> Self {
+		self.client = client;
+		self
+	}
+}
+
+#[async_trait::async_trait]
+impl ManufacturerBackend for TrueResidentialBackend {
+	fn name(&self) -> &'static str {
+		"true_residential"
+	}
+
+	async fn availability(&self, req: &AvailabilityRequest) -> Result<BackendAnswer, AvailabilityError> {
+		let (username, password) = crate::secrets::vendor_credentials("true-residential").await?;
+		let (availability, product) = http_client::scope(self.client.clone(), true_residential_lookup(req.clone(), username, password)).await?;
+		Ok(BackendAnswer::new(availability).with_product(product))
+	}
+}
+
+///
+/// # True Residential Lookup
+/// Gets the availability of a True Residential appliance at the request's warehouse together with its
+/// description. There is no session: the dealer login goes with every request.
+///
+/// # Errors
+/// Returns `AvailabilityError::VendorUnavailable` if the service cannot be reached or its answer cannot
+/// be read, `AvailabilityError::CredentialsRejected` if it turns the dealer login away, and
+/// `AvailabilityError::Other` without a configured service or if the request has no model number.
+///
+pub async fn true_residential_lookup(req: AvailabilityRequest, username: SecretString, password: SecretString) -> Result<(Availability, Option<ProductInfo>), AvailabilityError> {
+	let config = configured()?;
+	let today = req.business_today();
+	let model_number = req.model_number.as_deref().map(|model_number| model_number.trim().to_uppercase()).ok_or_else(|| AvailabilityError::Other("No model number found.".to_string()))?;
+	let query = [("sku", model_number.as_str()), ("warehouse", req.warehouse.as_deref().unwrap_or_default()), ("quantity", &req.requested_quantity().to_string())];
+	let response = http_client::client().get(config.availability_url).basic_auth(username.expose(), Some(password.expose())).query(&query).send().await.map_err(|e| AvailabilityError::VendorUnavailable(format!("Failed to get True Residential availability: {e:?}")))?;
+	if http_client::auth_rejected(&response) {
+		return Err(AvailabilityError::CredentialsRejected { manufacturer: "True Residential".to_string(), secret: "true-residential-password".to_string() });
+	}
+	match response.status() {
+		StatusCode::NOT_FOUND => return Ok((Availability::not_found(), None)),
+		status if !status.is_success() => return Err(AvailabilityError::VendorUnavailable(format!("Failed to get True Residential availability: {status}"))),
+		_ => {}
+	}
+	let data: Value = http_client::json(response).await?;
+	Ok(stock::json_stock(&data, &STOCK_KEYS, "True Residential", &model_number, today))
+}
+
+///
+/// Fields of the availability answer, for `stock::json_stock`.
+///
+const STOCK_KEYS: StockKeys = StockKeys::new("results", "sku", "onHand", "nextAvailableDate").with_next_quantity("nextAvailableQuantity");
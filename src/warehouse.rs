@@ -0,0 +1,314 @@
+use std::fmt;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+use super::{config, AvailabilityRequest, Manufacturer};
+use crate::telemetry;
+
+///
+/// # `Showroom`
+/// A showroom, by the lowercase name requests give it, e.g. `los angeles`.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Showroom(String);
+
+impl Showroom {
+	#[must_use]
+	pub fn new(name: &str) -> Self {
+		Self(name.trim().to_lowercase())
+	}
+
+	#[must_use]
+	pub fn as_str(&self) -> &str {
+		&self.0
+	}
+}
+
+impl fmt::Display for Showroom {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.0)
+	}
+}
+
+///
+/// # `Warehouse`
+/// The code a vendor knows a warehouse or ship-to by, e.g. `US00002148` for BSH at Houston.
+///
+/// ## Example
+/// ```
+/// use eggersmann_app_server_appliance_availability::{Manufacturer, Showroom, Warehouse};
+///
+/// let warehouse = Warehouse::for_showroom(&Showroom::new("Houston"), &Manufacturer::Bsh).unwrap();
+/// assert_eq!(warehouse.as_str(), "US00002148");
+/// assert_eq!(Warehouse::for_showroom(&Showroom::new("new york"), &Manufacturer::Liebherr), Some(Warehouse::new("NY")));
+/// assert_eq!(Warehouse::for_showroom(&Showroom::new("austin"), &Manufacturer::Bsh), None);
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Warehouse(String);
+
+impl Warehouse {
+	#[must_use]
+	pub fn new(code: &str) -> Self {
+		Self(code.trim().to_string())
+	}
+
+	#[must_use]
+	pub fn as_str(&self) -> &str {
+		&self.0
+	}
+
+	///
+	/// # `Warehouse::for_showroom`
+	/// The warehouse `manufacturer` supplies `showroom` from: a mapping loaded with `config::reload_config`
+	/// first, then `WAREHOUSE_TABLE`.
+	///
+	#[must_use]
+	pub fn for_showroom(showroom: &Showroom, manufacturer: &Manufacturer) -> Option<Self> {
+		config::configured_warehouse(showroom.as_str(), manufacturer.as_str()).map(|code| Self::new(&code)).or_else(|| Self::from_table(showroom, manufacturer))
+	}
+
+	///
+	/// # `Warehouse::from_table`
+	/// The warehouse of `WAREHOUSE_TABLE` for `manufacturer` at `showroom`, ignoring loaded mappings.
+	///
+	#[must_use]
+	pub fn from_table(showroom: &Showroom, manufacturer: &Manufacturer) -> Option<Self> {
+		WAREHOUSE_TABLE.iter().find(|(table_showroom, manufacturers, _)| *table_showroom == showroom.as_str() && manufacturers.contains(manufacturer)).map(|(_, _, code)| Self::new(code))
+	}
+
+	///
+	/// # `Warehouse::all_for`
+	/// Every warehouse `manufacturer` supplies a showroom from, with the showrooms it supplies. Showrooms of
+	/// `WAREHOUSE_TABLE` come first in table order, then showrooms only a loaded mapping knows.
+	///
+	#[must_use]
+	pub fn all_for(manufacturer: &Manufacturer) -> Vec<(Self, Vec<Showroom>)> {
+		let mut showrooms: Vec<Showroom> = Vec::new();
+		for showroom in WAREHOUSE_TABLE.iter().map(|(showroom, ..)| Showroom::new(showroom)).chain(config::runtime_config().warehouses.keys().map(|showroom| Showroom::new(showroom))) {
+			if !showrooms.contains(&showroom) {
+				showrooms.push(showroom);
+			}
+		}
+		let mut warehouses: Vec<(Self, Vec<Showroom>)> = Vec::new();
+		for showroom in showrooms {
+			let Some(warehouse) = Self::for_showroom(&showroom, manufacturer) else { continue };
+			match warehouses.iter_mut().find(|(known, _)| *known == warehouse) {
+				Some((_, supplied)) => supplied.push(showroom),
+				None => warehouses.push((warehouse, vec![showroom])),
+			}
+		}
+		warehouses
+	}
+}
+
+impl fmt::Display for Warehouse {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.0)
+	}
+}
+
+const BSH: &[Manufacturer] = &[Manufacturer::Bsh, Manufacturer::Thermador, Manufacturer::Gaggenau];
+const SUBZERO: &[Manufacturer] = &[Manufacturer::Subzero, Manufacturer::Wolf];
+const MIELE: &[Manufacturer] = &[Manufacturer::Miele];
+///
+/// Vendors whose warehouse is the state the showroom is supplied from.
+///
+const BY_STATE: &[Manufacturer] = &[Manufacturer::Liebherr, Manufacturer::FisherPaykel, Manufacturer::JennAir, Manufacturer::Monogram, Manufacturer::TrueResidential, Manufacturer::Dacor, Manufacturer::Bertazzoni];
+
+///
+/// # `WAREHOUSE_TABLE`
+/// The built-in warehouse of each showroom and manufacturer, as (showroom, manufacturers, warehouse). The
+/// first row naming both wins, so an exception goes above the row it overrides.
+///
+pub const WAREHOUSE_TABLE: [(&str, &[Manufacturer], &str); 25] = [
+	("houston", BSH, "US00002148"),
+	("houston", SUBZERO, "99432040"),
+	("houston", MIELE, "Forest Park, IL"),
+	("houston", BY_STATE, "TX"),
+	("florida", BSH, "US00000103"),
+	("florida", SUBZERO, "99211620"),
+	("florida", MIELE, "Pompano Beach, FL"),
+	("florida", BY_STATE, "FL"),
+	("los angeles", BSH, "US00003803"),
+	("los angeles", SUBZERO, "99614560"),
+	("los angeles", MIELE, "Stockton, CA"),
+	("los angeles", BY_STATE, "CA"),
+	("chicago", BSH, "US00001842"),
+	("chicago", SUBZERO, "99311630"),
+	("chicago", MIELE, "Forest Park, IL"),
+	("chicago", BY_STATE, "IL"),
+	("new york", BSH, "US00002933"),
+	("new york", SUBZERO, "99103710"),
+	("new york", MIELE, "South Brunswick, NJ"),
+	("new york", &[Manufacturer::Liebherr], "NY"),
+	("new york", BY_STATE, "NJ"),
+	("dallas", BSH, "US00003189"),
+	("dallas", SUBZERO, "99411540"),
+	("dallas", MIELE, "Forest Park, IL"),
+	("dallas", BY_STATE, "TX"),
+];
+
+///
+/// # `WarehouseSource`
+/// Where a resolved warehouse code came from.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum WarehouseSource {
+	/// A mapping loaded with `config::reload_config`, or else `WAREHOUSE_TABLE`.
+	ShowroomTable,
+	/// The vendor selector installed by the host application.
+	Selector,
+	/// Set on the request by the caller, e.g. with `AvailabilityRequest::with_warehouse`.
+	Caller,
+	/// No warehouse could be resolved.
+	Unresolved,
+}
+
+///
+/// # `WarehouseDecision`
+/// Record of how the warehouse of a request was chosen, so a surprising answer can be traced back to the
+/// warehouse that was asked.
+///
+/// * `inference` - The assumption made when the input did not map directly, e.g. a default showroom.
+/// * `reason` - Why the warehouse was or was not resolved.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct WarehouseDecision {
+	pub showroom: Option<String>,
+	pub manufacturer: Option<String>,
+	pub warehouse: Option<String>,
+	pub source: WarehouseSource,
+	pub inference: Option<String>,
+	pub reason: String,
+}
+
+impl WarehouseDecision {
+	///
+	/// Record the outcome of the showroom table lookup.
+	///
+	pub fn from_table(showroom: Option<&str>, manufacturer: Option<&str>, warehouse: Option<&str>) -> Self {
+		let (source, reason) = match (showroom, manufacturer, warehouse) {
+			(_, _, Some(_)) => (WarehouseSource::ShowroomTable, "showroom and manufacturer found in the showroom table".to_string()),
+			(None, ..) => (WarehouseSource::Unresolved, "request has no showroom".to_string()),
+			(_, None, _) => (WarehouseSource::Unresolved, "request has no known manufacturer".to_string()),
+			(Some(showroom), Some(manufacturer), None) => (WarehouseSource::Unresolved, format!("no warehouse configured for {manufacturer} at showroom {showroom}")),
+		};
+		Self { showroom: showroom.map(str::to_string), manufacturer: manufacturer.map(str::to_string), warehouse: warehouse.map(str::to_string), source, inference: None, reason }
+	}
+
+	///
+	/// Emit the decision as a telemetry event.
+	///
+	pub fn emit(&self) {
+		let source = match self.source {
+			WarehouseSource::ShowroomTable => "showroom_table",
+			WarehouseSource::Selector => "selector",
+			WarehouseSource::Caller => "caller",
+			WarehouseSource::Unresolved => "unresolved",
+		};
+		telemetry::event("warehouse.resolved", &[("showroom", self.showroom.clone().unwrap_or_default()), ("manufacturer", self.manufacturer.clone().unwrap_or_default()), ("warehouse", self.warehouse.clone().unwrap_or_default()), ("source", source.to_string()), ("inference", self.inference.clone().unwrap_or_default()), ("reason", self.reason.clone())]);
+	}
+}
+
+///
+/// # `VendorRoute`
+/// Override of the vendor and warehouse a request is sent to. Fields left `None` keep what the showroom
+/// table resolved; a new manufacturer without a warehouse is looked up in the table again.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct VendorRoute {
+	pub manufacturer: Option<String>,
+	pub warehouse: Option<String>,
+	/// Why the request was rerouted, kept in the `WarehouseDecision`.
+	pub reason: String,
+}
+
+impl VendorRoute {
+	///
+	/// # `VendorRoute::new`
+	/// A route that changes nothing until a manufacturer or warehouse is set.
+	///
+	#[must_use]
+	pub const fn new(reason: String) -> Self {
+		Self { manufacturer: None, warehouse: None, reason }
+	}
+
+	#[must_use]
+	pub fn with_manufacturer(mut self, manufacturer: String) -> Self {
+		self.manufacturer = Some(manufacturer);
+		self
+	}
+
+	#[must_use]
+	pub fn with_warehouse(mut self, warehouse: String) -> Self {
+		self.warehouse = Some(warehouse);
+		self
+	}
+}
+
+///
+/// # `VendorSelector`
+/// Hook letting the host application reroute a request after the showroom table has resolved it, e.g.
+/// to send certain model prefixes to another account. Closures taking the request and returning an
+/// `Option<VendorRoute>` implement it.
+///
+pub trait VendorSelector: Send + Sync {
+	///
+	/// The route for `req`, `None` to keep the showroom table's resolution.
+	///
+	fn select(&self, req: &AvailabilityRequest) -> Option<VendorRoute>;
+}
+
+impl<F: Fn(&AvailabilityRequest) -> Option<VendorRoute> + Send + Sync> VendorSelector for F {
+	fn select(&self, req: &AvailabilityRequest) -> Option<VendorRoute> {
+		self(req)
+	}
+}
+
+fn selector_slot() -> &'static RwLock<Option<Arc<dyn VendorSelector>>> {
+	static SELECTOR: OnceLock<RwLock<Option<Arc<dyn VendorSelector>>>> = OnceLock::new();
+	SELECTOR.get_or_init(|| RwLock::new(None))
+}
+
+///
+/// # `set_vendor_selector`
+/// Install the selector consulted by `AvailabilityRequest::get_warehouse`.
+///
+/// ## Example
+/// ```
+/// use std::sync::Arc;
+///
+/// use eggersmann_app_server_appliance_availability::{set_vendor_selector, AvailabilityRequest, VendorRoute};
+///
+/// set_vendor_selector(Arc::new(|req: &AvailabilityRequest| req.model_number.as_deref().filter(|model| model.starts_with("TEST")).map(|_| VendorRoute::new("test models use the Florida account".to_string()).with_warehouse("US00002149".to_string()))));
+/// let req = AvailabilityRequest::new("bsh".to_string(), "houston".to_string(), "TEST123".to_string()).parse_manufacturer().get_warehouse();
+/// assert_eq!(req.warehouse.as_deref(), Some("US00002149"));
+/// ```
+///
+pub fn set_vendor_selector(selector: Arc<dyn VendorSelector>) {
+	*selector_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = Some(selector);
+}
+
+///
+/// # `clear_vendor_selector`
+/// Remove the installed selector, leaving resolution to the showroom table.
+///
+pub fn clear_vendor_selector() {
+	*selector_slot().write().unwrap_or_else(std::sync::PoisonError::into_inner) = None;
+}
+
+///
+/// # `vendor_selector`
+/// The installed vendor selector, if any.
+///
+pub fn vendor_selector() -> Option<Arc<dyn VendorSelector>> {
+	selector_slot().read().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::jobs::{Job, JobKind, JobQueue};
+use super::response::AvailabilityStatus;
+use super::timezone::business_today;
+use super::AvailabilityRequest;
+
+///
+/// # `WatchPolicy`
+/// How often a watched model of a vendor is checked again. A promised date within `near` is checked
+/// every `min_interval`, one `far` or more away, or no date at all, every `max_interval`, and the dates in
+/// between at an interval growing evenly from one to the other. Checks of the same vendor are at least
+/// `spacing` apart, so many watches falling due together do not run into the vendor's rate limit.
+///
+/// ## Example
+/// ```
+/// use std::time::Duration;
+/// use chrono::NaiveDate;
+/// use eggersmann_app_server_appliance_availability::watch::WatchPolicy;
+///
+/// let policy = WatchPolicy::new(Duration::from_secs(60 * 60), Duration::from_secs(7 * 24 * 60 * 60));
+/// let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+/// assert_eq!(policy.interval(NaiveDate::from_ymd_opt(2024, 3, 4), today), Duration::from_secs(60 * 60));
+/// assert_eq!(policy.interval(NaiveDate::from_ymd_opt(2024, 9, 1), today), Duration::from_secs(7 * 24 * 60 * 60));
+/// assert_eq!(policy.interval(None, today), Duration::from_secs(7 * 24 * 60 * 60));
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct WatchPolicy {
+	pub min_interval: Duration,
+	pub max_interval: Duration,
+	pub near: Duration,
+	pub far: Duration,
+	pub spacing: Duration,
+}
+
+impl WatchPolicy {
+	///
+	/// # `WatchPolicy::new`
+	/// Check between every `min_interval` and every `max_interval`, most often from a week before the
+	/// promised date and least often from three months before it, at least a minute apart per vendor.
+	///
+	#[must_use]
+	pub const fn new(min_interval: Duration, max_interval: Duration) -> Self {
+		Self { min_interval, max_interval, near: Duration::from_hours(7 * 24), far: Duration::from_hours(90 * 24), spacing: Duration::from_mins(1) }
+	}
+
+	#[must_use]
+	pub const fn with_horizon(mut self, near: Duration, far: Duration) -> Self {
+		self.near = near;
+		self.far = far;
+		self
+	}
+
+	#[must_use]
+	pub const fn with_spacing(mut self, spacing: Duration) -> Self {
+		self.spacing = spacing;
+		self
+	}
+
+	///
+	/// # `WatchPolicy::interval`
+	/// How long after a check on `today` that promised `promised` the next one runs. A date already past
+	/// is as near as it gets.
+	///
+	#[must_use]
+	pub fn interval(&self, promised: Option<NaiveDate>, today: NaiveDate) -> Duration {
+		let (min, max) = (self.min_interval.min(self.max_interval), self.max_interval.max(self.min_interval));
+		let Some(promised) = promised else { return max };
+		let ahead = (promised - today).to_std().unwrap_or_default();
+		if ahead <= self.near {
+			return min;
+		}
+		if ahead >= self.far {
+			return max;
+		}
+		let share = ahead.saturating_sub(self.near).as_secs_f64() / self.far.saturating_sub(self.near).as_secs_f64();
+		min + max.saturating_sub(min).mul_f64(share)
+	}
+}
+
+impl Default for WatchPolicy {
+	fn default() -> Self {
+		Self::new(Duration::from_hours(4), Duration::from_hours(7 * 24))
+	}
+}
+
+fn policies() -> &'static RwLock<HashMap<String, WatchPolicy>> {
+	static POLICIES: OnceLock<RwLock<HashMap<String, WatchPolicy>>> = OnceLock::new();
+	POLICIES.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+///
+/// # `set_watch_policy`
+/// Check watched models of `vendor`, or of every vendor for `None`, again and again following `policy`.
+/// Without a policy a watch check runs once.
+///
+pub fn set_watch_policy(vendor: Option<&str>, policy: WatchPolicy) {
+	policies().write().unwrap_or_else(std::sync::PoisonError::into_inner).insert(vendor.map_or_else(|| "*".to_string(), str::to_lowercase), policy);
+}
+
+///
+/// # `clear_watch_policies`
+/// Go back to checking every watch once.
+///
+pub fn clear_watch_policies() {
+	policies().write().unwrap_or_else(std::sync::PoisonError::into_inner).clear();
+}
+
+///
+/// # `watch_policy`
+/// The policy watched models of `vendor` are checked with, the vendor's own before the one for every
+/// vendor.
+///
+#[must_use]
+pub fn watch_policy(vendor: &str) -> Option<WatchPolicy> {
+	let policies = policies().read().unwrap_or_else(std::sync::PoisonError::into_inner);
+	policies.get(&vendor.to_lowercase()).or_else(|| policies.get("*")).copied()
+}
+
+///
+/// # `schedule_watch`
+/// Watch the model of `request`, checking it now. `jobs::run_due_jobs` checks it again following the
+/// `WatchPolicy` of its vendor until it is in stock or discontinued. Watching the same model for the same
+/// showroom again replaces the earlier watch.
+///
+/// # Errors
+/// Returns an error if the queue cannot be persisted.
+///
+pub fn schedule_watch(queue: &dyn JobQueue, request: AvailabilityRequest) -> Result<(), String> {
+	let request = request.parse_manufacturer();
+	let id = format!("watch:{}:{}:{}", request.manufacturer.as_ref().map(ToString::to_string).unwrap_or_default(), request.showroom.clone().unwrap_or_default().to_lowercase(), request.model_number.clone().unwrap_or_default().to_uppercase());
+	queue.enqueue(Job::new(id, JobKind::WatchCheck { request: Box::new(request) }, Utc::now()))
+}
+
+///
+/// When to check the watch of `answer` again, `None` once there is nothing left to wait for. Checks of
+/// the vendor already queued keep their time; this one is moved past any of them closer than `spacing`.
+///
+pub(crate) fn next_check(policy: &WatchPolicy, queue: &dyn JobQueue, answer: &AvailabilityRequest, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+	let availability = answer.availability_detail.as_ref()?;
+	if matches!(availability.status, AvailabilityStatus::InStock | AvailabilityStatus::Discontinued) {
+		return None;
+	}
+	let interval = policy.interval(availability.available_date, business_today(answer.showroom.as_deref(), now));
+	let mut at = now + chrono::Duration::from_std(interval).unwrap_or_else(|_| chrono::Duration::days(1));
+
+	let spacing = chrono::Duration::from_std(policy.spacing).unwrap_or_default();
+	let mut taken: Vec<DateTime<Utc>> = queue.pending().into_iter().filter(|job| matches!(&job.kind, JobKind::WatchCheck { request } if (**request).clone().parse_manufacturer().manufacturer == answer.manufacturer)).map(|job| job.scheduled_for).collect();
+	taken.sort();
+	for scheduled in taken {
+		if (scheduled - at).abs() < spacing {
+			at = scheduled + spacing;
+		}
+	}
+	Some(at)
+}
@@ -0,0 +1,37 @@
+//!
+//! # Calendar
+//! Business days are counted past weekends and holidays, and a calendar without business days gives no
+//! date instead of searching forever.
+//!
+
+use chrono::{NaiveDate, Weekday};
+use eggersmann_app_server_appliance_availability::calendar::BusinessCalendar;
+
+fn date(month: u32, day: u32) -> NaiveDate {
+	NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+}
+
+#[test]
+fn business_days_skip_weekends_and_holidays() {
+	let calendar = BusinessCalendar::default().with_holiday(date(7, 4));
+	assert_eq!(calendar.add_business_days(date(7, 3), 0), Some(date(7, 3)));
+	// Wednesday 3 July: the 4th is a holiday, the 6th and 7th the weekend.
+	assert_eq!(calendar.add_business_days(date(7, 3), 1), Some(date(7, 5)));
+	assert_eq!(calendar.add_business_days(date(7, 3), 2), Some(date(7, 8)));
+	assert_eq!(calendar.add_business_days(date(7, 3), 10), Some(date(7, 18)));
+}
+
+#[test]
+fn holidays_on_every_working_day_are_counted_past() {
+	let holidays = date(7, 1).iter_days().take(21);
+	let calendar = BusinessCalendar::default().with_holidays(holidays);
+	assert_eq!(calendar.add_business_days(date(7, 1), 1), Some(date(7, 22)));
+}
+
+#[test]
+fn calendar_without_business_days_has_no_date() {
+	let calendar = BusinessCalendar::default().with_weekend(vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun]);
+	assert_eq!(calendar.add_business_days(date(7, 3), 1), None);
+	assert_eq!(calendar.add_business_days(date(7, 3), u32::MAX), None);
+	assert_eq!(calendar.add_business_days(NaiveDate::MAX, 1), None);
+}
@@ -0,0 +1,59 @@
+//!
+//! # Vendor page charsets
+//! Decoding of `SubZero` pages that are not UTF-8, from the fixtures in `tests/fixtures/subzero`. Each
+//! fixture is a Windows-1252 page, announced in the `Content-Type` header, in a `<meta>` tag or not at all.
+//!
+
+use eggersmann_app_server_appliance_availability::charset::{decode_html, header_charset, meta_charset};
+
+const CART_HEADER_WINDOWS_1252: &[u8] = include_bytes!("fixtures/subzero/cart_header_windows1252.html");
+const CART_META_ISO_8859_1: &[u8] = include_bytes!("fixtures/subzero/cart_meta_iso88591.html");
+const SUGGEST_UNDECLARED: &[u8] = include_bytes!("fixtures/subzero/suggest_undeclared.html");
+
+#[test]
+fn fixtures_are_not_utf8() {
+	for fixture in [CART_HEADER_WINDOWS_1252, CART_META_ISO_8859_1, SUGGEST_UNDECLARED] {
+		assert!(std::str::from_utf8(fixture).is_err());
+	}
+}
+
+#[test]
+fn charset_from_content_type_header() {
+	let page = decode_html(CART_HEADER_WINDOWS_1252, Some("text/html; charset=windows-1252"));
+	assert!(page.contains("36\" Café Collection Integrated Column – Stainless"));
+	assert!(!page.contains('\u{fffd}'));
+}
+
+#[test]
+fn charset_from_meta_tag() {
+	let page = decode_html(CART_META_ISO_8859_1, Some("text/html"));
+	assert!(page.contains("Café Collection"));
+	assert!(!page.contains('\u{fffd}'));
+}
+
+#[test]
+fn undeclared_non_utf8_falls_back_to_windows_1252() {
+	let page = decode_html(SUGGEST_UNDECLARED, None);
+	assert!(page.contains("Dishwasher – Café Crème Panel"));
+}
+
+#[test]
+fn header_wins_over_meta() {
+	let page = decode_html("<meta charset=\"windows-1252\">Café".as_bytes(), Some("text/html; charset=\"UTF-8\""));
+	assert!(page.ends_with("Café"));
+}
+
+#[test]
+fn utf8_pages_are_unchanged() {
+	let page = "<ul><li>Café Crème</li></ul>";
+	assert_eq!(decode_html(page.as_bytes(), None), page);
+	assert_eq!(decode_html(page.as_bytes(), Some("text/html; charset=utf-8")), page);
+}
+
+#[test]
+fn charset_labels() {
+	assert_eq!(header_charset("text/html; charset=ISO-8859-1").map(encoding_rs::Encoding::name), Some("windows-1252"));
+	assert_eq!(header_charset("text/html"), None);
+	assert_eq!(meta_charset(b"<head><META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=utf-8\">").map(encoding_rs::Encoding::name), Some("UTF-8"));
+	assert_eq!(meta_charset(b"<head><meta name=\"viewport\" content=\"width=device-width\">"), None);
+}
@@ -0,0 +1,128 @@
+//!
+//! # Local vendor
+//! A plain HTTP/1.1 server on a free local port that answers every request from a handler, so vendor
+//! requests can go through the full client stack without leaving the machine.
+//!
+#![allow(dead_code)]
+
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+///
+/// What the server answers: status, headers and body.
+///
+#[derive(Debug, Clone)]
+pub struct Reply {
+	pub status: u16,
+	pub headers: Vec<(String, String)>,
+	pub body: Vec<u8>,
+}
+
+impl Reply {
+	pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+		Self { status, headers: Vec::new(), body: body.into() }
+	}
+
+	pub fn with_header(mut self, name: &str, value: &str) -> Self {
+		self.headers.push((name.to_string(), value.to_string()));
+		self
+	}
+}
+
+///
+/// A request the server received.
+///
+#[derive(Debug, Clone)]
+pub struct Received {
+	pub method: String,
+	pub path: String,
+	pub headers: Vec<(String, String)>,
+	pub body: String,
+}
+
+impl Received {
+	pub fn header(&self, name: &str) -> Option<&str> {
+		self.headers.iter().find(|(header, _)| header.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+	}
+}
+
+pub struct LocalVendor {
+	pub url: String,
+	received: Arc<Mutex<Vec<Received>>>,
+}
+
+impl LocalVendor {
+	///
+	/// Start a server that answers each request with `handler`. It runs until the test's runtime stops.
+	///
+	pub async fn start<F>(handler: F) -> Self
+	where
+		F: Fn(&Received) -> Reply + Send + Sync + 'static,
+	{
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let url = format!("http://{}", listener.local_addr().unwrap());
+		let received = Arc::new(Mutex::new(Vec::new()));
+		let log = received.clone();
+		let handler = Arc::new(handler);
+		tokio::spawn(async move {
+			while let Ok((mut stream, _)) = listener.accept().await {
+				let (log, handler) = (log.clone(), handler.clone());
+				tokio::spawn(async move {
+					while let Some(request) = read_request(&mut stream).await {
+						let reply = handler(&request);
+						log.lock().unwrap().push(request);
+						let mut head = format!("HTTP/1.1 {} Reply\r\ncontent-length: {}\r\n", reply.status, reply.body.len());
+						for (name, value) in &reply.headers {
+							head.push_str(&format!("{name}: {value}\r\n"));
+						}
+						head.push_str("\r\n");
+						if stream.write_all(head.as_bytes()).await.is_err() || stream.write_all(&reply.body).await.is_err() {
+							return;
+						}
+					}
+				});
+			}
+		});
+		Self { url, received }
+	}
+
+	pub fn received(&self) -> Vec<Received> {
+		self.received.lock().unwrap().clone()
+	}
+
+	pub fn hits(&self) -> usize {
+		self.received.lock().unwrap().len()
+	}
+}
+
+async fn read_request(stream: &mut tokio::net::TcpStream) -> Option<Received> {
+	let mut buffer = Vec::new();
+	let mut chunk = [0_u8; 4096];
+	let head_end = loop {
+		if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+			break end;
+		}
+		let read = stream.read(&mut chunk).await.ok()?;
+		if read == 0 {
+			return None;
+		}
+		buffer.extend_from_slice(&chunk[..read]);
+	};
+	let head = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+	let mut lines = head.lines();
+	let mut request_line = lines.next()?.split_whitespace();
+	let (method, path) = (request_line.next()?.to_string(), request_line.next()?.to_string());
+	let headers: Vec<(String, String)> = lines.filter_map(|line| line.split_once(':')).map(|(name, value)| (name.trim().to_string(), value.trim().to_string())).collect();
+	let length = headers.iter().find(|(name, _)| name.eq_ignore_ascii_case("content-length")).and_then(|(_, value)| value.parse::<usize>().ok()).unwrap_or(0);
+	let mut body = buffer[head_end + 4..].to_vec();
+	while body.len() < length {
+		let read = stream.read(&mut chunk).await.ok()?;
+		if read == 0 {
+			return None;
+		}
+		body.extend_from_slice(&chunk[..read]);
+	}
+	Some(Received { method, path, headers, body: String::from_utf8_lossy(&body).to_string() })
+}
@@ -0,0 +1,102 @@
+//!
+//! # Dacor
+//! Lookups and lead times against a local stand-in for the Samsung Dacor dealer portal that answers with
+//! the stock responses in `tests/fixtures/dacor`.
+//!
+#![cfg(feature = "dacor")]
+
+mod common;
+
+use std::sync::Arc;
+
+use chrono::{Days, NaiveDate};
+use common::{LocalVendor, Received, Reply};
+use eggersmann_app_server_appliance_availability::backend::backend;
+use eggersmann_app_server_appliance_availability::credentials::{set_credential_provider, StaticProvider};
+use eggersmann_app_server_appliance_availability::tokens::{set_token_store, InMemoryTokenStore};
+use eggersmann_app_server_appliance_availability::{dacor_lead_time, set_dacor_config, AvailabilityError, AvailabilityRequest, AvailabilityStatus, DacorConfig, Manufacturer};
+
+const IN_STOCK: &str = include_str!("fixtures/dacor/stock_in_stock.json");
+const EXPECTED: &str = include_str!("fixtures/dacor/stock_expected.json");
+const BUILT_TO_ORDER: &str = include_str!("fixtures/dacor/stock_built_to_order.json");
+const EMPTY: &str = include_str!("fixtures/dacor/stock_empty.json");
+
+fn dacor_request(manufacturer: &str, model_number: &str) -> AvailabilityRequest {
+	AvailabilityRequest::new(manufacturer.to_string(), "houston".to_string(), model_number.to_string()).parse_manufacturer().get_warehouse()
+}
+
+fn dealer_portal(req: &Received) -> Reply {
+	if req.path == "/api/login" {
+		return Reply::new(200, "{}").with_header("set-cookie", "DACORSESSION=c0ffee; Path=/; Secure; HttpOnly");
+	}
+	if !req.header("cookie").is_some_and(|cookies| cookies.contains("DACORSESSION=c0ffee")) {
+		return Reply::new(401, "");
+	}
+	let model = req.path.split(['?', '&']).find_map(|pair| pair.strip_prefix("model=")).unwrap_or_default();
+	match model {
+		"DOP36M96DLS" => Reply::new(200, IN_STOCK),
+		"DTT36M976LS" => Reply::new(200, EXPECTED),
+		"DRR30980LAP" => Reply::new(200, BUILT_TO_ORDER),
+		"XX0000" => Reply::new(200, EMPTY),
+		_ => Reply::new(404, ""),
+	}
+}
+
+#[test]
+fn aliases_route_to_dacor() {
+	for manufacturer in ["dacor", "Dacor", "Samsung Dacor"] {
+		assert_eq!(dacor_request(manufacturer, "DOP36M96DLS").manufacturer, Some(Manufacturer::Dacor), "{manufacturer}");
+	}
+}
+
+#[tokio::test]
+async fn answers_from_the_dealer_portal() {
+	let dacor = backend("dacor").expect("dacor backend is registered");
+	let lookup = |model_number: &str| {
+		let req = dacor_request("dacor", model_number);
+		let dacor = dacor.clone();
+		async move { dacor.availability(&req).await }
+	};
+	set_credential_provider(Arc::new(StaticProvider::new().with_secret("dacor-username", "eas").with_secret("dacor-password", "hunter2")));
+	set_token_store(Arc::new(InMemoryTokenStore::default()));
+	// without a configured portal nothing is sent.
+	assert_eq!(lookup("DOP36M96DLS").await.unwrap_err(), AvailabilityError::Other("No Dacor dealer portal configured, see `set_dacor_config`.".to_string()));
+
+	let vendor = LocalVendor::start(dealer_portal).await;
+	set_dacor_config(DacorConfig::new(format!("{}/api/login", vendor.url), format!("{}/api/stock", vendor.url)));
+
+	let answer = lookup("DOP36M96DLS").await.unwrap();
+	let availability = answer.availability.unwrap();
+	assert_eq!((availability.status, availability.quantity, availability.available_date), (AvailabilityStatus::InStock, Some(2), None));
+	assert_eq!(availability.raw, "Found: DOP36M96DLS, In stock: 2");
+	let product = answer.product.unwrap();
+	assert_eq!(product.name, "36\" Pro Dual-Fuel Steam Range, Silver Stainless Steel");
+	assert_eq!(product.brand.as_deref(), Some("Dacor"));
+
+	let received = vendor.received();
+	assert_eq!((received[0].method.as_str(), received[0].path.as_str(), received[0].body.as_str()), ("POST", "/api/login", "username=eas&password=hunter2"));
+	assert_eq!(received[1].path, "/api/stock?model=DOP36M96DLS&warehouse=TX&quantity=1");
+
+	let availability = lookup("DTT36M976LS").await.unwrap().availability.unwrap();
+	assert_eq!((availability.status, availability.quantity, availability.available_date), (AvailabilityStatus::Backordered, Some(4), NaiveDate::from_ymd_opt(2030, 6, 3)));
+	assert_eq!(availability.raw, "Found: DTT36M976LS, Available: 06/03/2030");
+
+	// without stock or a date, a model built to order is available after its lead time.
+	let req = dacor_request("dacor", "DRR30980LAP");
+	let after_lead_time = req.business_today().checked_add_days(Days::new(21));
+	let availability = lookup("DRR30980LAP").await.unwrap().availability.unwrap();
+	assert_eq!((availability.status, availability.available_date), (AvailabilityStatus::Backordered, after_lead_time));
+
+	// an empty answer and a 404 are both a model the portal does not know.
+	assert_eq!(lookup("XX0000").await.unwrap().availability.unwrap().status, AvailabilityStatus::NotFound);
+	assert_eq!(lookup("ZZ404").await.unwrap().availability.unwrap().status, AvailabilityStatus::NotFound);
+
+	// the lead time is given whether or not there is stock.
+	assert_eq!(dacor_lead_time(dacor_request("dacor", "DOP36M96DLS")).await, Ok(Some(10)));
+	assert_eq!(dacor_lead_time(dacor_request("dacor", "DRR30980LAP")).await, Ok(Some(21)));
+	assert_eq!(dacor_lead_time(dacor_request("dacor", "DTT36M976LS")).await, Ok(None));
+	assert_eq!(dacor_lead_time(dacor_request("dacor", "ZZ404")).await, Ok(None));
+
+	// one login serves every lookup.
+	assert_eq!(vendor.received().iter().filter(|req| req.path == "/api/login").count(), 1);
+}
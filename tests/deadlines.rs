@@ -0,0 +1,77 @@
+//!
+//! # Deadlines
+//! A lookup takes as long as the vendor does unless the caller limits it, and the limit holds for the
+//! lookups a check spawns for each warehouse.
+//!
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::{LocalVendor, Reply};
+use eggersmann_app_server_appliance_availability::backend::{register_backend, BackendAnswer, ManufacturerBackend};
+use eggersmann_app_server_appliance_availability::discovery::check_all_warehouses;
+use eggersmann_app_server_appliance_availability::{set_backoff_policy, set_result_cache_config, Availability, AvailabilityError, AvailabilityRequest, AvailabilityStatus, BackoffPolicy, ClientFactory, HttpClient, RequestOptions, ResultCacheConfig};
+
+///
+/// A vendor that takes three minutes to answer.
+///
+struct Slow;
+
+#[async_trait::async_trait]
+impl ManufacturerBackend for Slow {
+	fn name(&self) -> &'static str {
+		"bertazzoni"
+	}
+
+	async fn availability(&self, _req: &AvailabilityRequest) -> Result<BackendAnswer, AvailabilityError> {
+		tokio::time::sleep(Duration::from_secs(180)).await;
+		Ok(BackendAnswer::new(Availability::new(AvailabilityStatus::InStock, "In stock".to_string())))
+	}
+}
+
+///
+/// A vendor whose stock page keeps failing, asked over the retrying client.
+///
+struct Failing {
+	url: String,
+	client: HttpClient,
+}
+
+#[async_trait::async_trait]
+impl ManufacturerBackend for Failing {
+	fn name(&self) -> &'static str {
+		"liebherr"
+	}
+
+	async fn availability(&self, _req: &AvailabilityRequest) -> Result<BackendAnswer, AvailabilityError> {
+		let response = self.client.get(format!("{}/stock", self.url)).send().await.map_err(|e| AvailabilityError::VendorUnavailable(e.to_string()))?;
+		Err(AvailabilityError::VendorUnavailable(response.status().to_string()))
+	}
+}
+
+#[tokio::test(start_paused = true)]
+async fn lookup_without_a_timeout_waits_for_the_vendor() {
+	register_backend(Arc::new(Slow));
+	// the second lookup must reach the vendor, not the answer cached by the first.
+	set_result_cache_config(ResultCacheConfig::new(Duration::ZERO, Duration::from_mins(10)));
+	let request = || AvailabilityRequest::new("bertazzoni".to_string(), "houston".to_string(), "PROF304INSROT".to_string());
+	assert!(request().into_answered().await.is_ok());
+	let limited = request().into_answered_with(RequestOptions::new().with_timeout(Duration::from_secs(60))).await;
+	assert_eq!(limited.unwrap_err(), AvailabilityError::DeadlineExceeded.to_string());
+}
+
+#[tokio::test]
+async fn warehouse_lookups_stop_retrying_at_the_callers_deadline() {
+	set_backoff_policy(BackoffPolicy::new(3).with_backoff(Duration::from_secs(1), Duration::from_secs(1)).with_jitter(false));
+	let vendor = LocalVendor::start(|_| Reply::new(503, "busy")).await;
+	register_backend(Arc::new(Failing { url: vendor.url.clone(), client: ClientFactory::default().build().unwrap() }));
+	let request = AvailabilityRequest::new("liebherr".to_string(), "houston".to_string(), "CBS1660".to_string());
+
+	let check = RequestOptions::new().with_timeout(Duration::from_millis(500)).run(async { Ok::<_, AvailabilityError>(check_all_warehouses(request).await) }).await.unwrap();
+	assert_eq!(check.warehouses.len(), 5);
+	assert!(check.warehouses.iter().all(|warehouse| warehouse.error.is_some()));
+	// one call per warehouse, none of them retried past the deadline.
+	assert_eq!(vendor.hits(), 5);
+}
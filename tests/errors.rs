@@ -0,0 +1,41 @@
+//!
+//! # Vendor failures as errors
+//! A vendor that cannot answer fails the lookup with a typed error instead of passing its error text off
+//! as availability. Faults are injected into every Miele request, so no network access is needed.
+//!
+#![cfg(all(feature = "miele", feature = "fault-injection"))]
+
+use eggersmann_app_server_appliance_availability::backend::ManufacturerBackend;
+use eggersmann_app_server_appliance_availability::faults::{set_faults, Fault, FaultConfig};
+use eggersmann_app_server_appliance_availability::{set_backoff_policy, set_miele_report_config, support_bundle, AvailabilityError, AvailabilityRequest, BackoffPolicy, MieleBackend, MieleReportConfig};
+
+fn failing_miele() {
+	set_backoff_policy(BackoffPolicy::disabled());
+	set_miele_report_config(MieleReportConfig::new(vec!["https://miele.invalid/availability.xlsx".to_string()]));
+	set_faults("miele", FaultConfig::new(1.0, vec![Fault::ServerError]));
+}
+
+fn miele_request(request_id: &str) -> AvailabilityRequest {
+	let mut req = AvailabilityRequest::new("miele".to_string(), "houston".to_string(), "WWB020WCS".to_string());
+	req.warehouse = Some("Houston".to_string());
+	req.request_id = Some(request_id.to_string());
+	req
+}
+
+#[tokio::test]
+async fn unreachable_report_is_vendor_unavailable() {
+	failing_miele();
+	let result = MieleBackend.availability(&miele_request("errors-backend")).await;
+	assert!(matches!(result, Err(AvailabilityError::VendorUnavailable(_))), "{result:?}");
+}
+
+#[tokio::test]
+async fn failed_lookup_is_an_error_not_an_answer() {
+	failing_miele();
+	let result = miele_request("errors-lookup").parse_manufacturer().get_time().into_answered().await;
+	assert!(result.is_err(), "{result:?}");
+
+	let bundle = support_bundle("errors-lookup").unwrap();
+	assert!(bundle.outcome.is_none());
+	assert!(bundle.error.is_some());
+}
@@ -0,0 +1,109 @@
+//!
+//! # Faults
+//! Faults injected below the retry layer go through the full client stack: a timeout, a 5xx and a reset
+//! connection are each retried for a GET, surface as `VendorUnavailable` from a backend, and a vendor that
+//! recovers within the retries answers the lookup.
+//!
+#![cfg(all(feature = "fault-injection", feature = "liebherr"))]
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::{LocalVendor, Reply};
+use eggersmann_app_server_appliance_availability::credentials::{set_credential_provider, StaticProvider};
+use eggersmann_app_server_appliance_availability::faults::{clear_faults, set_faults, Fault, FaultConfig};
+use eggersmann_app_server_appliance_availability::{set_backoff_policy, set_liebherr_config, set_result_cache_config, AvailabilityError, AvailabilityRequest, AvailabilityStatus, BackoffPolicy, ClientFactory, LiebherrConfig, ResultCacheConfig};
+
+const FEED: &str = "Material;Description;Plant;Available;Next Qty;Next Date\nCBS1660;Fridge;TX;4;;\n";
+
+fn short_backoff() {
+	set_backoff_policy(BackoffPolicy::new(3).with_backoff(Duration::from_millis(50), Duration::from_millis(50)).with_jitter(false));
+}
+
+///
+/// The host:port of `vendor`, so each test injects into its own vendor only.
+///
+fn address(vendor: &LocalVendor) -> &str {
+	vendor.url.trim_start_matches("http://")
+}
+
+#[tokio::test]
+async fn injected_server_error_is_retried_without_reaching_the_vendor() {
+	short_backoff();
+	let vendor = LocalVendor::start(|_| Reply::new(200, "stock")).await;
+	set_faults(address(&vendor), FaultConfig::new(1.0, vec![Fault::ServerError]));
+	let response = ClientFactory::default().build().unwrap().get(format!("{}/stock", vendor.url)).send().await.unwrap();
+	assert_eq!(response.status(), 500);
+	assert_eq!(vendor.hits(), 0);
+}
+
+///
+/// A vendor that takes longer to answer than the injected timeout allows.
+///
+fn slow_reply(body: &'static str) -> Reply {
+	std::thread::sleep(Duration::from_millis(20));
+	Reply::new(200, body)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn injected_timeout_fails_every_attempt() {
+	short_backoff();
+	let vendor = LocalVendor::start(|_| slow_reply("stock")).await;
+	set_faults(address(&vendor), FaultConfig::new(1.0, vec![Fault::Timeout]));
+	let error = ClientFactory::default().build().unwrap().get(format!("{}/stock", vendor.url)).send().await.unwrap_err();
+	assert!(error.to_string().contains("after 2 retries"), "{error:?}");
+	assert!(format!("{error:?}").contains("timed out"), "{error:?}");
+}
+
+#[tokio::test]
+async fn injected_reset_is_retried_as_a_connection_error() {
+	short_backoff();
+	let vendor = LocalVendor::start(|_| Reply::new(200, "stock")).await;
+	set_faults(address(&vendor), FaultConfig::new(1.0, vec![Fault::Reset]));
+	let error = ClientFactory::default().build().unwrap().get(format!("{}/stock", vendor.url)).send().await.unwrap_err();
+	assert!(error.to_string().contains("after 2 retries"), "{error:?}");
+	assert_eq!(vendor.hits(), 0);
+}
+
+#[tokio::test]
+async fn limited_faults_are_retried_until_the_vendor_answers() {
+	short_backoff();
+	let vendor = LocalVendor::start(|_| Reply::new(200, "stock")).await;
+	set_faults(address(&vendor), FaultConfig::new(1.0, vec![Fault::ServerError, Fault::Reset]).with_limit(2));
+	let response = ClientFactory::default().build().unwrap().get(format!("{}/stock", vendor.url)).send().await.unwrap();
+	assert_eq!((response.status().as_u16(), response.text().await.unwrap()), (200, "stock".to_string()));
+	assert_eq!(vendor.hits(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn backend_lookup_survives_or_reports_each_fault() {
+	short_backoff();
+	set_credential_provider(Arc::new(StaticProvider::new().with_secret("liebherr-username", "dealer").with_secret("liebherr-password", "hunter2")));
+	let vendor = LocalVendor::start(|_| slow_reply(FEED)).await;
+	set_liebherr_config(LiebherrConfig::new(format!("{}/feed", vendor.url)).with_max_age(Duration::ZERO));
+	// every lookup must reach the feed, not the answer cached by the one before.
+	set_result_cache_config(ResultCacheConfig::new(Duration::ZERO, Duration::from_mins(10)));
+	let request = || AvailabilityRequest::new("liebherr".to_string(), "houston".to_string(), "CBS1660".to_string()).get_warehouse();
+
+	for fault in [Fault::ServerError, Fault::Reset] {
+		set_faults(address(&vendor), FaultConfig::new(1.0, vec![fault]));
+		let error = request().get_availability().await.unwrap_err();
+		assert!(matches!(error, AvailabilityError::VendorUnavailable(_)), "{fault:?}: {error:?}");
+	}
+	assert_eq!(vendor.hits(), 0);
+
+	set_faults(address(&vendor), FaultConfig::new(1.0, vec![Fault::ServerError, Fault::Reset]).with_limit(2));
+	let availability = request().get_availability().await.unwrap();
+	assert_eq!((availability.status, availability.quantity), (AvailabilityStatus::InStock, Some(4)));
+	assert_eq!(vendor.hits(), 1);
+
+	// a timed out attempt may still reach the vendor, so its hits are not counted.
+	set_faults(address(&vendor), FaultConfig::new(1.0, vec![Fault::Timeout]));
+	let error = request().get_availability().await.unwrap_err();
+	assert!(matches!(error, AvailabilityError::VendorUnavailable(_)), "{error:?}");
+
+	clear_faults();
+	assert_eq!(request().get_availability().await.unwrap().status, AvailabilityStatus::InStock);
+}
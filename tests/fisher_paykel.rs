@@ -0,0 +1,90 @@
+//!
+//! # Fisher & Paykel
+//! Lookups against a local stand-in for the Fisher & Paykel dealer portal that answers with the stock
+//! responses in `tests/fixtures/fisher_paykel`.
+//!
+#![cfg(feature = "fisher-paykel")]
+
+mod common;
+
+use std::sync::Arc;
+
+use chrono::NaiveDate;
+use common::{LocalVendor, Received, Reply};
+use eggersmann_app_server_appliance_availability::backend::backend;
+use eggersmann_app_server_appliance_availability::credentials::{set_credential_provider, StaticProvider};
+use eggersmann_app_server_appliance_availability::tokens::{set_token_store, InMemoryTokenStore};
+use eggersmann_app_server_appliance_availability::{set_fisher_paykel_config, AvailabilityError, AvailabilityRequest, AvailabilityStatus, FisherPaykelConfig};
+
+const IN_STOCK: &str = include_str!("fixtures/fisher_paykel/stock_in_stock.json");
+const EXPECTED: &str = include_str!("fixtures/fisher_paykel/stock_expected.json");
+const EMPTY: &str = include_str!("fixtures/fisher_paykel/stock_empty.json");
+
+fn dealer_portal(req: &Received) -> Reply {
+	if req.path == "/us/api/login" {
+		if req.body != "username=eas&password=hunter2" {
+			return Reply::new(401, "");
+		}
+		return Reply::new(200, "{}").with_header("set-cookie", "FPSESSION=f00d; Path=/; HttpOnly");
+	}
+	if !req.header("cookie").is_some_and(|cookies| cookies.contains("FPSESSION=f00d")) {
+		return Reply::new(401, "");
+	}
+	let sku = req.path.split(['?', '&']).find_map(|pair| pair.strip_prefix("sku=")).unwrap_or_default();
+	match sku {
+		"RS3084WRUK1" => Reply::new(200, IN_STOCK),
+		"OR36SCG6X1" => Reply::new(200, EXPECTED),
+		"XX0000" => Reply::new(200, EMPTY),
+		"EXPIRED" => Reply::new(401, ""),
+		_ => Reply::new(404, ""),
+	}
+}
+
+#[tokio::test]
+async fn answers_from_the_dealer_portal() {
+	let fisher_paykel = backend("fisher_paykel").expect("fisher_paykel backend is registered");
+	let lookup = |model_number: &str| {
+		let req = AvailabilityRequest::new("fisher & paykel".to_string(), "houston".to_string(), model_number.to_string()).parse_manufacturer().get_warehouse();
+		let fisher_paykel = fisher_paykel.clone();
+		async move { fisher_paykel.availability(&req).await }
+	};
+	set_credential_provider(Arc::new(StaticProvider::new().with_secret("fisher-paykel-username", "eas").with_secret("fisher-paykel-password", "hunter2")));
+	set_token_store(Arc::new(InMemoryTokenStore::default()));
+	// without a configured portal nothing is sent.
+	assert!(matches!(lookup("RS3084WRUK1").await, Err(AvailabilityError::Other(_))));
+
+	let vendor = LocalVendor::start(dealer_portal).await;
+	set_fisher_paykel_config(FisherPaykelConfig::new(format!("{}/us/api/login", vendor.url), format!("{}/us/api/stock", vendor.url)));
+
+	let answer = lookup("RS3084WRUK1").await.unwrap();
+	let availability = answer.availability.unwrap();
+	assert_eq!((availability.status, availability.quantity, availability.available_date), (AvailabilityStatus::InStock, Some(4), None));
+	assert_eq!(availability.raw, "Found: RS3084WRUK1, In stock: 4");
+	let product = answer.product.unwrap();
+	assert_eq!(product.name, "Integrated Column Refrigerator, 30\"");
+	assert_eq!(product.brand.as_deref(), Some("Fisher & Paykel"));
+
+	let received = vendor.received();
+	assert_eq!((received[0].method.as_str(), received[0].path.as_str()), ("POST", "/us/api/login"));
+	assert_eq!(received[0].body, "username=eas&password=hunter2");
+	assert_eq!(received[1].path, "/us/api/stock?sku=RS3084WRUK1&warehouse=TX&quantity=1");
+
+	let availability = lookup("OR36SCG6X1").await.unwrap().availability.unwrap();
+	assert_eq!((availability.status, availability.quantity, availability.available_date), (AvailabilityStatus::Backordered, Some(6), NaiveDate::from_ymd_opt(2030, 5, 20)));
+	assert_eq!(availability.raw, "Found: OR36SCG6X1, Available: 05/20/2030");
+
+	// an empty answer and a 404 are both a model the portal does not know.
+	assert_eq!(lookup("XX0000").await.unwrap().availability.unwrap().status, AvailabilityStatus::NotFound);
+	assert_eq!(lookup("ZZ404").await.unwrap().availability.unwrap().status, AvailabilityStatus::NotFound);
+
+	// the saved session is reused; one the portal turns away is logged in again once.
+	let logins = || vendor.received().iter().filter(|req| req.path == "/us/api/login").count();
+	assert_eq!(logins(), 1);
+	assert_eq!(lookup("EXPIRED").await.unwrap_err(), AvailabilityError::SessionExpired);
+	assert_eq!(logins(), 2);
+
+	// a login the portal turns away names the secret to rotate.
+	set_credential_provider(Arc::new(StaticProvider::new().with_secret("fisher-paykel-username", "eas").with_secret("fisher-paykel-password", "expired")));
+	set_token_store(Arc::new(InMemoryTokenStore::default()));
+	assert_eq!(lookup("RS3084WRUK1").await.unwrap_err(), AvailabilityError::CredentialsRejected { manufacturer: "Fisher & Paykel".to_string(), secret: "fisher-paykel-password".to_string() });
+}
@@ -0,0 +1,41 @@
+//!
+//! # History
+//! A history record takes whether the model was found, is in stock and when it is available from the typed
+//! status of the answer, whatever text the vendor answered with.
+//!
+
+use chrono::{NaiveDate, TimeZone, Utc};
+use eggersmann_app_server_appliance_availability::history::HistoryRecord;
+use eggersmann_app_server_appliance_availability::{Availability, AvailabilityResponse, AvailabilityStatus};
+
+fn record(availability: &str, detail: Option<Availability>) -> HistoryRecord {
+	let mut builder = AvailabilityResponse::builder().manufacturer("bsh").model_number("HBLP651RUC").availability(availability);
+	if let Some(detail) = detail {
+		builder = builder.availability_detail(detail);
+	}
+	HistoryRecord::from_response(&builder.build(), Utc.with_ymd_and_hms(2024, 3, 5, 15, 0, 0).unwrap()).unwrap()
+}
+
+#[test]
+fn vendor_not_found_text_is_not_found() {
+	let unknown = record("No such article: HBLP651RUC", Some(Availability::new(AvailabilityStatus::NotFound, "No such article: HBLP651RUC".to_string())));
+	assert_eq!((unknown.found, unknown.in_stock, unknown.available_date), (false, false, None));
+	let cart = record("Error finding item.", None);
+	assert_eq!((cart.found, cart.in_stock), (false, false));
+}
+
+#[test]
+fn status_and_date_come_from_the_detail() {
+	let ships = record("Ships today", Some(Availability::new(AvailabilityStatus::InStock, "Ships today".to_string()).with_quantity(2)));
+	assert_eq!((ships.found, ships.in_stock), (true, true));
+
+	let date = NaiveDate::from_ymd_opt(2024, 4, 1).unwrap();
+	let backordered = record("Backordered", Some(Availability::new(AvailabilityStatus::Backordered, "Backordered".to_string()).with_available_date(date)));
+	assert_eq!((backordered.found, backordered.in_stock, backordered.available_date), (true, false, Some(date)));
+}
+
+#[test]
+fn text_only_response_is_read_from_its_text() {
+	let legacy = record("Found: HBLP651RUC, Available: 03/01/2024", None);
+	assert_eq!((legacy.found, legacy.in_stock, legacy.available_date), (true, true, NaiveDate::from_ymd_opt(2024, 3, 1)));
+}
@@ -0,0 +1,67 @@
+//!
+//! # Jobs
+//! A failed job is retried with exponential backoff and moved to the dead letters of its queue once it has
+//! used up its attempts.
+//!
+#![cfg(feature = "test-support")]
+
+use std::time::Duration;
+
+use chrono::{TimeDelta, Utc};
+use eggersmann_app_server_appliance_availability::jobs::{run_due_jobs, set_job_retry_policy, InMemoryJobQueue, Job, JobKind, JobQueue, JobRetryPolicy};
+use eggersmann_app_server_appliance_availability::testing::ScriptedBackend;
+use eggersmann_app_server_appliance_availability::{AvailabilityError, AvailabilityRequest};
+
+fn bulk_job(id: &str, manufacturer: &str, model_number: &str) -> Job {
+	let request = AvailabilityRequest::new(manufacturer.to_string(), "houston".to_string(), model_number.to_string());
+	Job::new(id.to_string(), JobKind::BulkLookup { requests: vec![request] }, Utc::now())
+}
+
+#[test]
+fn backoff_doubles_up_to_the_limit() {
+	let policy = JobRetryPolicy::new(10).with_backoff(Duration::from_secs(60), Duration::from_secs(10 * 60));
+	let backoffs: Vec<u64> = (1..=6).map(|attempt| policy.backoff(attempt).as_secs()).collect();
+	assert_eq!(backoffs, [60, 120, 240, 480, 600, 600]);
+	assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(10 * 60));
+}
+
+#[tokio::test]
+async fn failed_job_is_retried_then_dead_lettered() {
+	let vendor = ScriptedBackend::new("jobs-failing").fail("HOOD-30", AvailabilityError::VendorUnavailable("portal is down".to_string())).install();
+	let queue = InMemoryJobQueue::default();
+	queue.enqueue(bulk_job("bulk:failing", "jobs-failing", "HOOD-30")).unwrap();
+
+	set_job_retry_policy(JobRetryPolicy::new(3).with_backoff(Duration::from_secs(60), Duration::from_secs(60 * 60)));
+	let started = Utc::now();
+	assert!(run_due_jobs(&queue).await.unwrap().is_empty());
+	let retried = queue.pending()[0].clone();
+	assert_eq!(retried.attempts, 1);
+	assert!(retried.scheduled_for >= started + TimeDelta::seconds(60), "{}", retried.scheduled_for);
+	assert!(retried.last_error.as_deref().is_some_and(|error| error.contains("portal is down")));
+
+	// the job is not due again before its backoff.
+	assert!(run_due_jobs(&queue).await.unwrap().is_empty());
+	assert_eq!(vendor.call_count("HOOD-30"), 1);
+
+	// run the remaining attempts without waiting.
+	for _ in 0..2 {
+		let mut job = queue.pending()[0].clone();
+		job.scheduled_for = Utc::now();
+		queue.enqueue(job).unwrap();
+		assert!(run_due_jobs(&queue).await.unwrap().is_empty());
+	}
+	assert!(queue.pending().is_empty());
+	let dead = queue.dead_letters();
+	assert_eq!((dead.len(), dead[0].id.as_str(), dead[0].attempts), (1, "bulk:failing", 3));
+	assert_eq!(vendor.call_count("HOOD-30"), 3);
+}
+
+#[tokio::test]
+async fn successful_job_completes_without_dead_letter() {
+	let _vendor = ScriptedBackend::new("jobs-working").in_stock("OVEN-1", 2).install();
+	let queue = InMemoryJobQueue::default();
+	queue.enqueue(bulk_job("bulk:working", "jobs-working", "OVEN-1")).unwrap();
+	assert_eq!(run_due_jobs(&queue).await.unwrap(), ["bulk:working"]);
+	assert!(queue.pending().is_empty());
+	assert!(queue.dead_letters().is_empty());
+}
@@ -0,0 +1,116 @@
+//!
+//! # Live portal checks
+//! Real lookups against the vendor portals, asserting the structure of what comes back so portal changes
+//! are caught before a release. Only built with the `it-live` feature and the three vendor features:
+//!
+//! ```text
+//! BSH_USERNAME=.. BSH_PASSWORD=.. SUBZERO_USERNAME=.. SUBZERO_PASSWORD=.. cargo test --features it-live --test live
+//! ```
+//!
+//! A vendor whose credentials are not set is skipped. The models looked up can be overridden with
+//! `BSH_MODEL`, `SUBZERO_MODEL` and `MIELE_MODEL`.
+//!
+#![cfg(all(feature = "it-live", feature = "bsh", feature = "subzero", feature = "miele"))]
+
+use std::env;
+use std::sync::Arc;
+
+use chrono::Utc;
+use eggersmann_app_server_appliance_availability::backend::backend;
+use eggersmann_app_server_appliance_availability::credentials::EnvironmentProvider;
+use eggersmann_app_server_appliance_availability::history::HistoryRecord;
+use eggersmann_app_server_appliance_availability::prelude::*;
+use eggersmann_app_server_appliance_availability::{bsh_availability_multi, bsh_material, subzero_suggest, LineItem};
+
+fn credentials(vendor: &str) -> Option<(SecretString, SecretString)> {
+	let credentials = env::var(format!("{vendor}_USERNAME")).ok().map(SecretString::new).zip(env::var(format!("{vendor}_PASSWORD")).ok().map(SecretString::new));
+	if credentials.is_none() {
+		eprintln!("{vendor}_USERNAME / {vendor}_PASSWORD not set, skipping.");
+	}
+	credentials
+}
+
+///
+/// Look `req` up through its registered backend, with the credentials read from the environment, and
+/// return the vendor's answer.
+///
+async fn lookup(req: &AvailabilityRequest) -> String {
+	set_credential_provider(Arc::new(EnvironmentProvider::new()));
+	let manufacturer = req.manufacturer.clone().expect("request has a manufacturer");
+	let answer = backend(manufacturer.as_str()).expect("backend is registered").availability(req).await.unwrap_or_else(|e| panic!("{manufacturer} lookup: {e}"));
+	answer.availability.expect("answer has an availability").raw
+}
+
+fn request(manufacturer: &str, model_env: &str, default_model: &str) -> AvailabilityRequest {
+	let model_number = env::var(model_env).unwrap_or_else(|_| default_model.to_string());
+	AvailabilityRequest::new(manufacturer.to_string(), "houston".to_string(), model_number).parse_manufacturer().get_warehouse().get_time()
+}
+
+///
+/// The answer is vendor text rather than one of our own error messages, and a found model carries a
+/// parseable date or an in-stock marker.
+///
+fn assert_structure(mut req: AvailabilityRequest, availability: &str) {
+	assert!(!availability.trim().is_empty(), "empty availability");
+	assert!(!availability.starts_with("Failed") && !availability.starts_with("Faild") && !availability.starts_with("Error"), "lookup failed: {availability}");
+
+	req.availability = Some(availability.to_string());
+	let record = HistoryRecord::from_response(&AvailabilityResponse::from_request(&req), Utc::now()).expect("request has manufacturer, model and availability");
+	if record.found {
+		assert!(record.available_date.is_some() || record.in_stock, "found model without a date: {availability}");
+	}
+}
+
+#[tokio::test]
+async fn bsh_availability_is_structured() {
+	if credentials("BSH").is_none() {
+		return;
+	}
+	let req = request("bsh", "BSH_MODEL", "HBLP651RUC");
+	let availability = lookup(&req).await;
+	assert_structure(req, &availability);
+}
+
+#[tokio::test]
+async fn bsh_material_has_description() {
+	let Some((username, password)) = credentials("BSH") else { return };
+	let model = env::var("BSH_MODEL").unwrap_or_else(|_| "HBLP651RUC".to_string());
+	let material = bsh_material(&model, username, password).await.expect("BSH material");
+	assert_eq!(material.material, model.trim().to_uppercase());
+	assert!(material.description.is_some(), "material without description");
+}
+
+#[tokio::test]
+async fn bsh_order_answers_every_line() {
+	let Some((username, password)) = credentials("BSH") else { return };
+	let req = request("bsh", "BSH_MODEL", "HBLP651RUC");
+	let items = [LineItem::new(req.model_number.as_deref().unwrap_or_default(), 2), LineItem::new("NOT-A-MODEL", 1)];
+	let lines = bsh_availability_multi(&req, &items, username, password).await.expect("BSH order simulation");
+	assert_eq!(lines.iter().map(|line| line.model_number.as_str()).collect::<Vec<_>>(), items.iter().map(|item| item.model_number.as_str()).collect::<Vec<_>>());
+	assert_structure(req, &lines[0].availability.raw);
+	assert_eq!(lines[1].availability.status, AvailabilityStatus::NotFound, "unknown model: {}", lines[1].availability.raw);
+}
+
+#[tokio::test]
+async fn subzero_availability_is_structured() {
+	if credentials("SUBZERO").is_none() {
+		return;
+	}
+	let req = request("subzero", "SUBZERO_MODEL", "BI3621GS");
+	let availability = lookup(&req).await;
+	assert_structure(req, &availability);
+}
+
+#[tokio::test]
+async fn subzero_suggestions_match_prefix() {
+	let Some((username, password)) = credentials("SUBZERO") else { return };
+	let suggestions = subzero_suggest("BI36", username, password).await.expect("SubZero suggestions");
+	assert!(suggestions.iter().all(|suggestion| suggestion.model_number.to_uppercase().starts_with("BI36")), "unexpected suggestions: {suggestions:?}");
+}
+
+#[tokio::test]
+async fn miele_availability_is_structured() {
+	let req = request("miele", "MIELE_MODEL", "G7106SCU");
+	let availability = lookup(&req).await;
+	assert_structure(req, &availability);
+}
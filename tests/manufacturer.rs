@@ -0,0 +1,42 @@
+//!
+//! # Manufacturer
+//! Requests carry a typed `Manufacturer`, parsed from the canonical name or an alias and written to the
+//! v1 payload as the same plain string as before.
+//!
+
+use eggersmann_app_server_appliance_availability::{AvailabilityRequest, Manufacturer};
+
+#[test]
+fn aliases_parse_to_the_builtin_manufacturer() {
+	for (name, manufacturer) in [("bsh", Manufacturer::Bsh), ("Bosch", Manufacturer::Bsh), ("sub-zero", Manufacturer::Subzero), ("Sub Zero", Manufacturer::Subzero), ("cove", Manufacturer::Wolf), ("F&P", Manufacturer::FisherPaykel), ("GE Appliances", Manufacturer::Monogram)] {
+		assert_eq!(name.parse::<Manufacturer>().unwrap(), manufacturer, "{name}");
+	}
+}
+
+#[test]
+fn display_is_the_canonical_name() {
+	for name in ["bsh", "thermador", "gaggenau", "subzero", "wolf", "miele", "liebherr", "fisher_paykel", "jennair", "monogram", "true_residential", "dacor", "bertazzoni"] {
+		let manufacturer = name.parse::<Manufacturer>().unwrap();
+		assert!(manufacturer.is_builtin(), "{name}");
+		assert_eq!(manufacturer.to_string(), name);
+	}
+}
+
+#[test]
+fn v1_payload_keeps_the_string_form() {
+	let v1 = r#"{"manufacturer":"Sub-Zero","showroom":"houston","model_number":"CL3650UID","warehouse":null,"utc_time":null,"availability":null,"user":null}"#;
+	let req: AvailabilityRequest = serde_json::from_str(v1).unwrap();
+	assert_eq!(req.manufacturer, Some(Manufacturer::Subzero));
+	assert!(serde_json::to_string(&req).unwrap().starts_with(r#"{"manufacturer":"subzero","#));
+
+	let without: AvailabilityRequest = serde_json::from_str(r#"{"showroom":"houston","model_number":"CL3650UID","warehouse":null,"utc_time":null,"availability":null,"user":null}"#).unwrap();
+	assert_eq!(without.manufacturer, None);
+}
+
+#[test]
+fn unknown_manufacturer_without_a_backend_is_cleared() {
+	let req = AvailabilityRequest::new("Acme".to_string(), "houston".to_string(), "X1".to_string());
+	assert_eq!(req.manufacturer, Some(Manufacturer::Other("acme".to_string())));
+	assert_eq!(req.parse_manufacturer().manufacturer, None);
+	assert_eq!(AvailabilityRequest::new(String::new(), "houston".to_string(), "X1".to_string()).manufacturer, None);
+}
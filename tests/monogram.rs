@@ -0,0 +1,98 @@
+//!
+//! # GE Monogram
+//! Lookups against a local stand-in for the GE Appliances dealer API that answers with the responses in
+//! `tests/fixtures/monogram`: the token, then one availability answer per model.
+//!
+#![cfg(feature = "monogram")]
+
+mod common;
+
+use std::sync::Arc;
+
+use chrono::NaiveDate;
+use common::{LocalVendor, Received, Reply};
+use eggersmann_app_server_appliance_availability::backend::backend;
+use eggersmann_app_server_appliance_availability::credentials::{set_credential_provider, StaticProvider};
+use eggersmann_app_server_appliance_availability::{set_monogram_config, AvailabilityError, AvailabilityRequest, AvailabilityStatus, Manufacturer, MonogramConfig};
+
+const TOKEN: &str = include_str!("fixtures/monogram/token.json");
+const IN_STOCK: &str = include_str!("fixtures/monogram/availability_in_stock.json");
+const BACKORDERED: &str = include_str!("fixtures/monogram/availability_backordered.json");
+const INVALID: &str = include_str!("fixtures/monogram/availability_invalid.json");
+
+fn monogram_request(manufacturer: &str, showroom: &str, model_number: &str) -> AvailabilityRequest {
+	AvailabilityRequest::new(manufacturer.to_string(), showroom.to_string(), model_number.to_string()).parse_manufacturer().get_warehouse()
+}
+
+fn dealer_api(req: &Received) -> Reply {
+	if req.path == "/oauth2/token" {
+		return Reply::new(200, TOKEN);
+	}
+	let body: serde_json::Value = serde_json::from_str(&req.body).unwrap_or_default();
+	match body["lines"][0]["sku"].as_str().unwrap_or_default() {
+		"ZV830SSSS" => Reply::new(200, IN_STOCK),
+		"ZIK30GNNII" => Reply::new(200, BACKORDERED),
+		"XX0000" => Reply::new(200, INVALID),
+		"EXPIRED" => Reply::new(401, ""),
+		_ => Reply::new(404, ""),
+	}
+}
+
+#[test]
+fn aliases_route_to_monogram() {
+	for manufacturer in ["monogram", "Monogram", "GE Monogram", "ge"] {
+		assert_eq!(monogram_request(manufacturer, "houston", "ZV830SSSS").manufacturer, Some(Manufacturer::Monogram), "{manufacturer}");
+	}
+}
+
+#[test]
+fn every_showroom_has_a_warehouse() {
+	for (showroom, warehouse) in [("houston", "TX"), ("florida", "FL"), ("los angeles", "CA"), ("chicago", "IL"), ("new york", "NJ"), ("dallas", "TX")] {
+		assert_eq!(monogram_request("monogram", showroom, "ZV830SSSS").warehouse.as_deref(), Some(warehouse), "{showroom}");
+	}
+}
+
+#[tokio::test]
+async fn answers_from_the_dealer_api() {
+	let monogram = backend("monogram").expect("monogram backend is registered");
+	let lookup = |model_number: &str| {
+		let req = monogram_request("monogram", "houston", model_number);
+		let monogram = monogram.clone();
+		async move { monogram.availability(&req).await }
+	};
+	// without a configured dealer API nothing is sent.
+	assert!(matches!(lookup("ZV830SSSS").await, Err(AvailabilityError::Other(_))));
+
+	set_credential_provider(Arc::new(StaticProvider::new().with_secret("monogram-client-id", "eas").with_secret("monogram-client-secret", "hunter2")));
+	let vendor = LocalVendor::start(dealer_api).await;
+	set_monogram_config(MonogramConfig::new(format!("{}/oauth2/token", vendor.url), format!("{}/dealer/availability", vendor.url), "0012345".to_string()));
+
+	let answer = lookup("ZV830SSSS").await.unwrap();
+	let availability = answer.availability.unwrap();
+	assert_eq!((availability.status, availability.quantity, availability.available_date), (AvailabilityStatus::InStock, Some(3), None));
+	assert_eq!(availability.raw, "Found: ZV830SSSS, In stock: 3");
+	let product = answer.product.unwrap();
+	assert_eq!(product.name, "Monogram 30\" Stainless Steel Wall-Mount Vent Hood");
+	assert_eq!(product.brand.as_deref(), Some("Monogram"));
+
+	let received = vendor.received();
+	assert_eq!(received[0].path, "/oauth2/token");
+	assert!(received[0].body.contains("grant_type=client_credentials"));
+	assert_eq!(received[1].header("authorization"), Some("Bearer eyJhbGciOiJSUzI1NiJ9.dealer"));
+	let sent: serde_json::Value = serde_json::from_str(&received[1].body).unwrap();
+	assert_eq!(sent, serde_json::json!({ "account": "0012345", "shipFrom": "TX", "lines": [{ "sku": "ZV830SSSS", "quantity": 1 }] }));
+
+	let availability = lookup("ZIK30GNNII").await.unwrap().availability.unwrap();
+	assert_eq!((availability.status, availability.quantity, availability.available_date), (AvailabilityStatus::Backordered, None, NaiveDate::from_ymd_opt(2030, 3, 14)));
+	assert_eq!(availability.raw, "Found: ZIK30GNNII, Available: 03/14/2030");
+
+	// a line GE reports as invalid and a 404 are both a model it does not know.
+	assert_eq!(lookup("XX0000").await.unwrap().availability.unwrap().status, AvailabilityStatus::NotFound);
+	assert_eq!(lookup("ZZ404").await.unwrap().availability.unwrap().status, AvailabilityStatus::NotFound);
+
+	// the token is reused until GE turns it away.
+	assert_eq!(vendor.received().iter().filter(|req| req.path == "/oauth2/token").count(), 1);
+	assert_eq!(lookup("EXPIRED").await.unwrap_err(), AvailabilityError::SessionExpired);
+	lookup("ZV830SSSS").await.unwrap();
+	assert_eq!(vendor.received().iter().filter(|req| req.path == "/oauth2/token").count(), 2);
+}
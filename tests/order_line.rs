@@ -0,0 +1,21 @@
+//!
+//! # Order line
+//! The order line a vendor simulated travels with the availability, and an answer without one serializes
+//! as it did before.
+//!
+
+use chrono::NaiveDate;
+use eggersmann_app_server_appliance_availability::{Availability, OrderLine};
+
+#[test]
+fn order_line_round_trips_and_is_left_out_when_missing() {
+	let today = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+	let availability = Availability::from_text("Found: HBLP651RUC, Available: 06/01/2024", today);
+	assert!(!serde_json::to_string(&availability).unwrap().contains("order_line"));
+
+	let line: OrderLine = serde_json::from_str(r#"{"item_number":"000010","material":"HBLP651RUC","requested_quantity":2,"confirmed_quantity":2,"plant":"US10","net_price":"1299.00","currency":"USD","delivery_date":"2024-06-14","schedule":[{"requested_quantity":2,"confirmed_quantity":2,"date":"2024-06-14"}]}"#).unwrap();
+	let availability = availability.with_order_line(Some(line.clone()));
+	let read: Availability = serde_json::from_str(&serde_json::to_string(&availability).unwrap()).unwrap();
+	assert_eq!(read.order_line, Some(line));
+	assert_eq!(read.order_line.unwrap().schedule[0].date, NaiveDate::from_ymd_opt(2024, 6, 14));
+}
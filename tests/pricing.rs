@@ -0,0 +1,47 @@
+//!
+//! # Pricing
+//! The dealer net price of the vendor's order line is only kept for a request that asks for it on behalf
+//! of a user whose job title may see it.
+//!
+
+use std::sync::Arc;
+
+use eggersmann_app_server_appliance_availability::backend::{register_backend, BackendAnswer, ManufacturerBackend};
+use eggersmann_app_server_appliance_availability::config::{reload_config, RuntimeConfig};
+use eggersmann_app_server_appliance_availability::{Availability, AvailabilityError, AvailabilityRequest, AvailabilityRequestUser, AvailabilityStatus, OrderLine};
+
+struct Priced;
+
+#[async_trait::async_trait]
+impl ManufacturerBackend for Priced {
+	fn name(&self) -> &'static str {
+		"bsh"
+	}
+
+	async fn availability(&self, _req: &AvailabilityRequest) -> Result<BackendAnswer, AvailabilityError> {
+		let line: OrderLine = serde_json::from_str(r#"{"item_number":"000010","material":"HBLP651RUC","plant":"US10","net_price":"1299.00","currency":"USD"}"#).unwrap();
+		Ok(BackendAnswer::new(Availability::new(AvailabilityStatus::InStock, "In stock".to_string()).with_order_line(Some(line))))
+	}
+}
+
+async fn net_price(job_title: Option<&str>, include_pricing: bool) -> Option<String> {
+	let mut req = AvailabilityRequest::new("bsh".to_string(), "houston".to_string(), "HBLP651RUC".to_string()).with_pricing(include_pricing);
+	req.user = job_title.map(|job_title| AvailabilityRequestUser::new("0000-0000".to_string()).with_job_title(job_title.to_string()));
+	let line = req.parse_manufacturer().get_warehouse().get_time().into_answered().await.unwrap().availability_detail.unwrap().order_line.unwrap();
+	assert_eq!(line.plant.as_deref(), Some("US10"));
+	assert_eq!(line.net_price.is_some(), line.currency.is_some());
+	line.net_price
+}
+
+#[tokio::test]
+async fn net_price_needs_the_flag_and_an_allowed_job_title() {
+	register_backend(Arc::new(Priced));
+	reload_config(RuntimeConfig::new().with_pricing_job_title("Purchasing")).unwrap();
+
+	assert_eq!(net_price(Some("purchasing"), true).await.as_deref(), Some("1299.00"));
+	assert_eq!(net_price(Some("Purchasing"), false).await, None);
+	assert_eq!(net_price(Some("Designer"), true).await, None);
+	assert_eq!(net_price(None, true).await, None);
+
+	assert!(reload_config(RuntimeConfig::new().with_pricing_job_title(" ")).is_err());
+}
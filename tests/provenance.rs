@@ -0,0 +1,45 @@
+//!
+//! # Provenance
+//! Each field of an answer records where it came from when it is filled in: the vendor, a catalog
+//! snapshot, the result cache or the simulation catalog.
+//!
+#![cfg(feature = "test-support")]
+
+use eggersmann_app_server_appliance_availability::backend::BackendAnswer;
+use eggersmann_app_server_appliance_availability::testing::ScriptedBackend;
+use eggersmann_app_server_appliance_availability::{Availability, AvailabilityRequest, AvailabilityStatus, CatalogVersion, FieldProvenance, ProductInfo, Provenance};
+
+fn request(manufacturer: &str, model_number: &str) -> AvailabilityRequest {
+	AvailabilityRequest::new(manufacturer.to_string(), "houston".to_string(), model_number.to_string()).parse_manufacturer().get_time()
+}
+
+#[tokio::test]
+async fn vendor_answer_is_live_and_then_cached() {
+	let _vendor = ScriptedBackend::new("provenance-live").not_found("OVEN-1").install();
+	let live = request("provenance-live", "OVEN-1").into_answered().await.unwrap();
+	assert_eq!(live.response().provenance(), FieldProvenance::new().with_availability(Provenance::Live));
+
+	let cached = request("provenance-live", "OVEN-1").into_answered().await.unwrap();
+	assert!(matches!(cached.response().provenance().availability, Some(Provenance::Cache { .. })));
+	assert_eq!(cached.response().provenance().product, None);
+}
+
+#[tokio::test]
+async fn catalog_answer_is_a_snapshot_for_each_field_it_fills() {
+	let availability = Availability::new(AvailabilityStatus::InStock, "Found: DISH-1, In stock: 1".to_string()).with_quantity(1);
+	let answer = BackendAnswer::new(availability).with_product(Some(ProductInfo::new("Dishwasher".to_string()))).with_catalog_version(Some(CatalogVersion::new(7, "ab12".to_string())));
+	let _vendor = ScriptedBackend::new("provenance-snapshot").answer("DISH-1", answer).install();
+	let answered = request("provenance-snapshot", "DISH-1").into_answered().await.unwrap();
+	assert_eq!(answered.response().provenance(), FieldProvenance::new().with_availability(Provenance::Snapshot { version: 7 }).with_product(Provenance::Snapshot { version: 7 }));
+}
+
+#[tokio::test]
+async fn simulated_answer_is_simulated_and_unanswered_has_none() {
+	let mut simulated = request("provenance-simulated", "RANGE-1");
+	simulated.simulate = true;
+	let simulated = simulated.into_answered().await.unwrap();
+	assert_eq!(simulated.response().provenance().availability, Some(Provenance::Simulated));
+
+	let unanswered = request("nobody-we-order-from", "RANGE-1").into_answered().await.unwrap();
+	assert_eq!(unanswered.response().provenance(), FieldProvenance::new());
+}
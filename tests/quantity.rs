@@ -0,0 +1,42 @@
+//!
+//! # Quantity
+//! The units a rep asks for go to the vendor and come back on the response, which says whether all of
+//! them are in stock.
+//!
+
+use chrono::Utc;
+use eggersmann_app_server_appliance_availability::{business_today, Availability, AvailabilityResponse, AvailabilityStatus, Tranche};
+
+#[test]
+fn stock_count_and_tranches_are_checked_against_the_quantity() {
+	let in_stock = |count| Availability::new(AvailabilityStatus::InStock, format!("In stock: {count}")).with_quantity(count);
+	let response = |availability: Availability, quantity| AvailabilityResponse::builder().showroom("houston").availability(availability.raw.clone()).availability_detail(availability).quantity(quantity).requested_at(Utc::now()).build();
+	assert_eq!(response(in_stock(4), 4).meets_quantity(), Some(true));
+	assert_eq!(response(in_stock(3), 4).meets_quantity(), Some(false));
+	assert_eq!(response(Availability::new(AvailabilityStatus::InStock, "In stock".to_string()), 4).meets_quantity(), None);
+	assert_eq!(response(Availability::new(AvailabilityStatus::Backordered, "Backordered".to_string()), 4).meets_quantity(), Some(false));
+
+	let today = business_today(Some("houston"), Utc::now());
+	let later = today + chrono::Days::new(30);
+	let split = Availability::new(AvailabilityStatus::InStock, "Split".to_string()).with_tranches(vec![Tranche::new(2, today), Tranche::new(2, later)], today);
+	assert_eq!(response(split.clone(), 2).meets_quantity(), Some(true));
+	assert_eq!(response(split, 4).meets_quantity(), Some(false));
+
+	let v2 = response(in_stock(4), 4).v2();
+	assert_eq!((v2.requested_quantity, v2.meets_quantity), (Some(4), Some(true)));
+	assert_eq!(AvailabilityResponse::builder().availability("In stock").build().v2().requested_quantity, None);
+}
+
+#[cfg(feature = "test-support")]
+#[tokio::test]
+async fn quantity_goes_to_the_vendor() {
+	use eggersmann_app_server_appliance_availability::testing::ScriptedBackend;
+	use eggersmann_app_server_appliance_availability::AvailabilityRequest;
+
+	let vendor = ScriptedBackend::new("quantity-vendor").in_stock("WINE-24", 6).install();
+	let req = AvailabilityRequest::new("quantity-vendor".to_string(), "houston".to_string(), "WINE-24".to_string()).with_quantity(4);
+	let response = req.parse_manufacturer().get_warehouse().into_answered().await.unwrap().response();
+	assert_eq!(vendor.calls()[0].quantity, Some(4));
+	assert_eq!(response.quantity, Some(4));
+	assert_eq!(response.meets_quantity(), Some(true));
+}
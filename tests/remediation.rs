@@ -0,0 +1,32 @@
+//!
+//! # Remediation
+//! Typed errors carry the next action for on-call, as data their tooling can show or act on.
+//!
+
+use std::sync::Arc;
+
+use eggersmann_app_server_appliance_availability::credentials::{set_credential_provider, set_secret_name, EnvironmentProvider};
+use eggersmann_app_server_appliance_availability::{AvailabilityError, RemediationHint};
+
+#[test]
+fn rejected_credentials_name_the_secret_and_where_it_is_kept() {
+	set_credential_provider(Arc::new(EnvironmentProvider::new().with_prefix("EAS_".to_string())));
+	set_secret_name("liebherr-password", "liebherr-dealer-password");
+	let error = AvailabilityError::CredentialsRejected { manufacturer: "Liebherr".to_string(), secret: "liebherr-password".to_string() };
+	let hint = error.remediation().unwrap();
+	assert_eq!(hint, RemediationHint::RotateSecret { secret: "liebherr-dealer-password".to_string(), location: Some("environment variable EAS_LIEBHERR_DEALER_PASSWORD".to_string()) });
+	assert_eq!(hint.to_string(), "Rotate secret liebherr-dealer-password in environment variable EAS_LIEBHERR_DEALER_PASSWORD.");
+	assert_eq!(serde_json::to_value(&hint).unwrap(), serde_json::json!({"action": "rotate_secret", "secret": "liebherr-dealer-password", "location": "environment variable EAS_LIEBHERR_DEALER_PASSWORD"}));
+}
+
+#[test]
+fn each_error_kind_has_its_action() {
+	assert_eq!(AvailabilityError::SessionExpired.remediation(), Some(RemediationHint::LogIn));
+	assert_eq!(AvailabilityError::CartStuck { items: 3 }.remediation(), Some(RemediationHint::ClearSubzeroCart));
+	assert_eq!(AvailabilityError::DeadlineExceeded.remediation(), Some(RemediationHint::ExtendDeadline));
+	assert_eq!(AvailabilityError::VendorUnavailable("maintenance".to_string()).remediation(), Some(RemediationHint::RetryLater));
+	let too_large = AvailabilityError::ResponseTooLarge { url: "https://b2bportal.bsh-group.com/sap/opu/odata?x=1".to_string(), limit: 1024 };
+	assert_eq!(too_large.remediation(), Some(RemediationHint::RaiseResponseLimit { host: "b2bportal.bsh-group.com".to_string(), limit: 1024 }));
+	assert_eq!(AvailabilityError::Other("No model number found.".to_string()).remediation(), None);
+	assert_eq!(serde_json::to_string(&RemediationHint::ClearSubzeroCart).unwrap(), r#"{"action":"clear_subzero_cart"}"#);
+}
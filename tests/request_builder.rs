@@ -0,0 +1,88 @@
+//!
+//! # Request builder
+//! A request is built in place and looked up by reference, so the caller keeps it for the next lookup.
+//!
+#![cfg(feature = "test-support")]
+
+use std::sync::Arc;
+
+use eggersmann_app_server_appliance_availability::backend::BackendAnswer;
+use eggersmann_app_server_appliance_availability::compliance::{set_compliance_filter, Blocklist};
+use eggersmann_app_server_appliance_availability::testing::ScriptedBackend;
+use eggersmann_app_server_appliance_availability::{AccountIssue, AvailabilityError, AvailabilityRequest, AvailabilityStatus, Manufacturer, RemediationHint};
+
+#[tokio::test]
+async fn request_is_looked_up_by_reference() {
+	let vendor = ScriptedBackend::new("builder-vendor").in_stock("OVEN-1", 4).in_stock("OVEN-2", 1).install();
+	let mut builder = AvailabilityRequest::builder();
+	builder.manufacturer("builder-vendor").showroom("houston").model_number("OVEN-1").quantity(2);
+	let req = builder.build();
+	assert_eq!(req.manufacturer, Some(Manufacturer::Other("builder-vendor".to_string())));
+	assert!(req.warehouse_decision.is_some());
+
+	let first = req.get_availability().await.unwrap();
+	let second = req.get_availability().await.unwrap();
+	assert_eq!((first.status, first.quantity), (AvailabilityStatus::InStock, Some(4)));
+	assert_eq!(second, first);
+	assert_eq!((req.availability.as_ref(), req.request_id.as_ref(), req.requested_at), (None, None, None));
+	assert_eq!(vendor.calls()[0].requested_quantity(), 2);
+	// the second lookup is answered from the result cache.
+	assert_eq!(vendor.call_count("OVEN-1"), 1);
+
+	// a request that was never resolved is resolved for the lookup and left unresolved.
+	let unresolved = AvailabilityRequest::new("Builder-Vendor".to_string(), "houston".to_string(), "OVEN-2".to_string());
+	assert_eq!(unresolved.get_availability().await.unwrap().status, AvailabilityStatus::InStock);
+	assert_eq!(unresolved.warehouse_decision, None);
+	assert_eq!(vendor.calls()[1].manufacturer, Some(Manufacturer::Other("builder-vendor".to_string())));
+}
+
+#[tokio::test]
+async fn vendor_errors_stay_typed() {
+	let _vendor = ScriptedBackend::new("builder-failing").fail("HOOD-30", AvailabilityError::VendorUnavailable("portal is down".to_string())).install();
+	let mut builder = AvailabilityRequest::builder();
+	builder.manufacturer("builder-failing").showroom("houston").model_number("HOOD-30");
+	assert_eq!(builder.build().get_availability().await, Err(AvailabilityError::VendorUnavailable("portal is down".to_string())));
+}
+
+#[tokio::test]
+async fn blocked_models_and_accounts_are_typed_errors() {
+	set_compliance_filter(Arc::new(Blocklist::from_json(r#"[{"manufacturer": "builder-blocked", "model": "GRILL-1", "policy": "MAP-2024-07", "reason": "MAP restricted"}]"#).unwrap()));
+	let vendor = ScriptedBackend::new("builder-blocked").in_stock("GRILL-1", 2).install();
+	let mut builder = AvailabilityRequest::builder();
+	builder.manufacturer("builder-blocked").showroom("houston").model_number("GRILL-1");
+	let Err(AvailabilityError::Restricted(restricted)) = builder.build().get_availability().await else { panic!("a blocked model is not restricted") };
+	assert_eq!((restricted.policy.as_str(), restricted.reason.as_str()), ("MAP-2024-07", "MAP restricted"));
+	assert_eq!(AvailabilityError::Restricted(restricted).remediation(), Some(RemediationHint::QuoteAnotherModel { policy: "MAP-2024-07".to_string() }));
+	assert_eq!(vendor.call_count("GRILL-1"), 0);
+
+	let _vendor = ScriptedBackend::new("builder-on-hold").answer("GRILL-2", BackendAnswer::account_issue(AccountIssue::new("builder-on-hold".to_string(), "credit hold".to_string()))).install();
+	builder.manufacturer("builder-on-hold").model_number("GRILL-2");
+	let Err(AvailabilityError::AccountIssue(issue)) = builder.build().get_availability().await else { panic!("a blocked account is not an account issue") };
+	assert_eq!((issue.manufacturer.as_str(), issue.reason.as_str()), ("builder-on-hold", "credit hold"));
+	let hint = AvailabilityError::AccountIssue(issue).remediation().unwrap();
+	assert_eq!(hint, RemediationHint::ContactVendor { manufacturer: "builder-on-hold".to_string(), reason: "credit hold".to_string() });
+	assert_eq!(hint.to_string(), "Contact builder-on-hold about the account: credit hold.");
+}
+
+#[tokio::test]
+async fn not_found_answers_come_from_the_cache() {
+	let vendor = ScriptedBackend::new("builder-cached").not_found("SINK-1").install();
+	let mut builder = AvailabilityRequest::builder();
+	builder.manufacturer("builder-cached").showroom("houston").model_number("SINK-1");
+	let req = builder.build();
+	assert_eq!(req.get_availability().await.unwrap().status, AvailabilityStatus::NotFound);
+	assert_eq!(req.get_availability().await.unwrap().status, AvailabilityStatus::NotFound);
+	assert_eq!(vendor.call_count("SINK-1"), 1);
+}
+
+#[tokio::test]
+async fn simulated_and_unknown_requests_never_reach_a_vendor() {
+	let vendor = ScriptedBackend::new("builder-simulated").in_stock("RANGE-1", 1).install();
+	let mut builder = AvailabilityRequest::builder();
+	builder.manufacturer("builder-simulated").showroom("houston").model_number("RANGE-1").simulation(true);
+	assert_eq!(builder.build().get_availability().await.unwrap().status, AvailabilityStatus::NotFound);
+	assert_eq!(vendor.call_count("RANGE-1"), 0);
+
+	builder.manufacturer("nobody-we-order-from").simulation(false);
+	assert_eq!(builder.build().get_availability().await.unwrap().status, AvailabilityStatus::NotFound);
+}
@@ -0,0 +1,60 @@
+//!
+//! # Request time
+//! A lookup is stamped once, as `requested_at` in UTC, and every date and rendering of it is taken from
+//! that instant. Near midnight the showroom's date and the UTC date differ; these check that each field
+//! says which one it is.
+//!
+#![allow(deprecated)]
+
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use eggersmann_app_server_appliance_availability::{AvailabilityRequest, AvailabilityResponse};
+
+fn request_at(showroom: &str, requested_at: DateTime<Utc>) -> AvailabilityRequest {
+	AvailabilityRequest::new("bsh".to_string(), showroom.to_string(), "HBLP651RUC".to_string()).with_requested_at(requested_at)
+}
+
+#[test]
+fn evening_in_the_showroom_is_tomorrow_in_utc() {
+	// 23:30 in Houston on March 14th, 04:30 UTC on the 15th.
+	let req = request_at("houston", Utc.with_ymd_and_hms(2024, 3, 15, 4, 30, 0).unwrap());
+	assert_eq!(req.business_today(), NaiveDate::from_ymd_opt(2024, 3, 14).unwrap());
+	assert_eq!(req.local_time().as_deref(), Some("03/14/2024 11:30:00 PM CDT"));
+	assert_eq!(req.utc_time.as_deref(), Some("03/15/2024 04:30:00 AM"));
+}
+
+#[test]
+fn one_instant_gives_each_showroom_its_own_date() {
+	// Just after midnight in New York, still the evening before in Los Angeles.
+	let requested_at = Utc.with_ymd_and_hms(2024, 7, 1, 4, 5, 0).unwrap();
+	assert_eq!(request_at("new york", requested_at).business_today(), NaiveDate::from_ymd_opt(2024, 7, 1).unwrap());
+	assert_eq!(request_at("los angeles", requested_at).business_today(), NaiveDate::from_ymd_opt(2024, 6, 30).unwrap());
+	assert_eq!(request_at("los angeles", requested_at).local_time().as_deref(), Some("06/30/2024 09:05:00 PM PDT"));
+}
+
+#[test]
+fn v1_payload_without_requested_at_reads_utc_time() {
+	let v1 = r#"{"manufacturer":"bsh","showroom":"houston","model_number":"HBLP651RUC","warehouse":"4401","utc_time":"03/15/2024 04:30:00 AM","availability":"Available","user":null}"#;
+	let req: AvailabilityRequest = serde_json::from_str(v1).unwrap();
+	assert_eq!(req.requested_at, None);
+	assert_eq!(req.looked_up_at(), Some(Utc.with_ymd_and_hms(2024, 3, 15, 4, 30, 0).unwrap()));
+	assert_eq!(req.business_today(), NaiveDate::from_ymd_opt(2024, 3, 14).unwrap());
+	assert_eq!(serde_json::to_string(&req).unwrap(), v1);
+}
+
+#[test]
+fn response_carries_the_request_instant() {
+	let requested_at = Utc.with_ymd_and_hms(2024, 3, 15, 4, 30, 0).unwrap();
+	let response = AvailabilityResponse::from_request(&request_at("houston", requested_at));
+	assert_eq!(response.requested_at, Some(requested_at));
+	assert_eq!(response.utc_time.as_deref(), Some("03/15/2024 04:30:00 AM"));
+	let v2 = response.v2();
+	assert_eq!(v2.checked_at.as_deref(), Some("03/15/2024 04:30:00 AM"));
+	assert_eq!(v2.checked_at_local.as_deref(), Some("03/14/2024 11:30:00 PM CDT"));
+}
+
+#[test]
+fn deprecated_builder_time_sets_requested_at() {
+	let response = AvailabilityResponse::builder().showroom("houston").utc_time("03/15/2024 04:30:00 AM").build();
+	assert_eq!(response.requested_at, Some(Utc.with_ymd_and_hms(2024, 3, 15, 4, 30, 0).unwrap()));
+	assert_eq!(response.local_time().as_deref(), Some("03/14/2024 11:30:00 PM CDT"));
+}
@@ -0,0 +1,38 @@
+//!
+//! # Requested date
+//! A lookup can ask for delivery on a date of the caller's choosing, such as the install date, instead of
+//! as soon as possible.
+//!
+
+use chrono::{NaiveDate, TimeZone, Utc};
+use eggersmann_app_server_appliance_availability::AvailabilityRequest;
+
+#[test]
+fn past_or_missing_date_asks_for_today() {
+	// 10:00 in Houston on June 3rd.
+	let req = AvailabilityRequest::new("bsh".to_string(), "houston".to_string(), "HBLP651RUC".to_string()).with_requested_at(Utc.with_ymd_and_hms(2024, 6, 3, 15, 0, 0).unwrap());
+	let today = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+	assert_eq!(req.requested_delivery_date(), today);
+	assert_eq!(req.clone().with_requested_date(NaiveDate::from_ymd_opt(2024, 5, 1).unwrap()).requested_delivery_date(), today);
+	let install = NaiveDate::from_ymd_opt(2024, 9, 16).unwrap();
+	let req = req.with_requested_date(install);
+	assert_eq!(req.requested_delivery_date(), install);
+	assert_eq!(req.response().v2().requested_date, Some(install));
+}
+
+#[cfg(feature = "test-support")]
+#[tokio::test]
+async fn each_requested_date_is_its_own_lookup() {
+	use eggersmann_app_server_appliance_availability::testing::ScriptedBackend;
+
+	// not-found answers are cached by default, found ones are not.
+	let vendor = ScriptedBackend::new("dated-vendor").not_found("HOOD-36").install();
+	let mut builder = AvailabilityRequest::builder();
+	builder.manufacturer("dated-vendor").showroom("houston").model_number("HOOD-36");
+	builder.build().get_availability().await.unwrap();
+	builder.build().get_availability().await.unwrap();
+	builder.requested_date(NaiveDate::from_ymd_opt(2099, 1, 15).unwrap());
+	builder.build().get_availability().await.unwrap();
+	assert_eq!(vendor.calls()[1].requested_date, NaiveDate::from_ymd_opt(2099, 1, 15));
+	assert_eq!(vendor.call_count("HOOD-36"), 2);
+}
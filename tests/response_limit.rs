@@ -0,0 +1,32 @@
+//!
+//! # Response limit
+//! A vendor body over the limit of its host is refused with `ResponseTooLarge`, naming the address
+//! without its query, and a body within the limit is read whole.
+//!
+#![cfg(feature = "liebherr")]
+
+mod common;
+
+use std::sync::Arc;
+
+use common::{LocalVendor, Reply};
+use eggersmann_app_server_appliance_availability::credentials::{set_credential_provider, StaticProvider};
+use eggersmann_app_server_appliance_availability::{set_liebherr_config, set_response_limit, AvailabilityError, AvailabilityRequest, LiebherrConfig};
+
+const FEED: &str = "Material;Description;Plant;Available;Next Qty;Next Date\nCBS1660;Fridge;TX;4;;\n";
+
+#[tokio::test]
+async fn feed_over_the_limit_is_refused() {
+	set_credential_provider(Arc::new(StaticProvider::new().with_secret("liebherr-username", "dealer").with_secret("liebherr-password", "hunter2")));
+	set_response_limit("127.0.0.1", 1024);
+	let vendor = LocalVendor::start(|req| if req.path.starts_with("/large") { Reply::new(200, FEED.repeat(64)) } else { Reply::new(200, FEED) }).await;
+	let request = || AvailabilityRequest::new("liebherr".to_string(), "houston".to_string(), "CBS1660".to_string()).get_warehouse();
+
+	set_liebherr_config(LiebherrConfig::new(format!("{}/large?token=abc123", vendor.url)));
+	let error = request().get_availability().await.unwrap_err();
+	assert_eq!(error, AvailabilityError::ResponseTooLarge { url: format!("{}/large", vendor.url), limit: 1024 });
+	assert!(!error.to_string().contains("abc123"));
+
+	set_liebherr_config(LiebherrConfig::new(format!("{}/small?token=abc123", vendor.url)));
+	assert!(request().get_availability().await.is_ok());
+}
@@ -0,0 +1,36 @@
+//!
+//! # Result cache
+//! Answers are cached by their status: a hit for the positive TTL, a model the vendor does not know for
+//! the negative TTL, whatever text the vendor answered it with.
+//!
+#![cfg(feature = "test-support")]
+
+use std::time::Duration;
+
+use eggersmann_app_server_appliance_availability::backend::BackendAnswer;
+use eggersmann_app_server_appliance_availability::testing::ScriptedBackend;
+use eggersmann_app_server_appliance_availability::{set_result_cache_config, Availability, AvailabilityRequest, AvailabilityStatus, ResultCacheConfig};
+
+fn request(manufacturer: &str, model_number: &str) -> AvailabilityRequest {
+	AvailabilityRequest::new(manufacturer.to_string(), "houston".to_string(), model_number.to_string()).parse_manufacturer().get_time()
+}
+
+#[tokio::test]
+async fn answers_are_cached_by_status() {
+	let unknown = Availability::new(AvailabilityStatus::NotFound, "No such article: HOOD-9".to_string());
+	let vendor = ScriptedBackend::new("cache-status").in_stock("OVEN-1", 3).answer("HOOD-9", BackendAnswer::new(unknown)).install();
+
+	// by default a hit is cached too.
+	for _ in 0..2 {
+		assert_eq!(request("cache-status", "OVEN-1").get_availability().await.unwrap().status, AvailabilityStatus::InStock);
+	}
+	assert_eq!(vendor.call_count("OVEN-1"), 1);
+
+	// a vendor's own not found text is a miss by its status.
+	set_result_cache_config(ResultCacheConfig::new(Duration::ZERO, Duration::from_mins(10)));
+	for _ in 0..2 {
+		let response = request("cache-status", "HOOD-9").into_answered().await.unwrap().response();
+		assert!(!response.found());
+	}
+	assert_eq!(vendor.call_count("HOOD-9"), 1);
+}
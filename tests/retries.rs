@@ -0,0 +1,47 @@
+//!
+//! # Retries
+//! Only requests that are safe to repeat are retried. A vendor that keeps failing is asked again for a
+//! GET, but a post, such as a cart add or a login form, is sent once.
+//!
+
+mod common;
+
+use std::time::Duration;
+
+use common::{LocalVendor, Reply};
+use eggersmann_app_server_appliance_availability::{set_backoff_policy, AvailabilityError, BackoffPolicy, ClientFactory, RequestOptions};
+
+fn short_backoff() {
+	set_backoff_policy(BackoffPolicy::new(3).with_backoff(Duration::from_millis(300), Duration::from_millis(300)).with_jitter(false));
+}
+
+#[tokio::test]
+async fn failed_get_is_retried() {
+	short_backoff();
+	let vendor = LocalVendor::start(|_| Reply::new(503, "busy")).await;
+	let response = ClientFactory::default().build().unwrap().get(format!("{}/stock", vendor.url)).send().await.unwrap();
+	assert_eq!(response.status(), 503);
+	assert_eq!(vendor.hits(), 3);
+}
+
+#[tokio::test]
+async fn failed_post_is_sent_once() {
+	short_backoff();
+	let vendor = LocalVendor::start(|_| Reply::new(503, "busy")).await;
+	let response = ClientFactory::default().build().unwrap().post(format!("{}/cart?mode=add", vendor.url)).form(&[("item", "BI3621GS")]).send().await.unwrap();
+	assert_eq!(response.status(), 503);
+	assert_eq!(vendor.hits(), 1);
+	assert_eq!(vendor.received()[0].method, "POST");
+}
+
+#[tokio::test]
+async fn retry_after_the_deadline_is_not_made() {
+	short_backoff();
+	let vendor = LocalVendor::start(|_| Reply::new(503, "busy")).await;
+	let client = ClientFactory::default().build().unwrap();
+	let started = std::time::Instant::now();
+	let response: Result<_, AvailabilityError> = RequestOptions::new().with_timeout(Duration::from_millis(150)).run(async { Ok(client.get(format!("{}/stock", vendor.url)).send().await) }).await;
+	assert_eq!(response.unwrap().unwrap().status(), 503);
+	assert_eq!(vendor.hits(), 1);
+	assert!(started.elapsed() < Duration::from_millis(150));
+}
@@ -0,0 +1,131 @@
+//!
+//! # Schedule
+//! Background work keeps to the windows of its `SchedulePolicy`: routine work such as login canaries and
+//! config reloads to business hours, bulk work such as catalog refreshes to the overnight window.
+//!
+
+mod common;
+
+use std::time::Duration;
+
+use chrono::{NaiveTime, TimeDelta, TimeZone, Timelike, Utc, Weekday};
+use chrono_tz::America::Chicago;
+use eggersmann_app_server_appliance_availability::canary::schedule_login_canary;
+use eggersmann_app_server_appliance_availability::config::{runtime_config, watch_config_file};
+use eggersmann_app_server_appliance_availability::jobs::{run_due_jobs, InMemoryJobQueue, Job, JobKind, JobQueue};
+use eggersmann_app_server_appliance_availability::schedule::{may_run, set_schedule_policy, SchedulePolicy, Workload};
+
+fn time(hour: u32, minute: u32) -> NaiveTime {
+	NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+const EVERY_DAY: [Weekday; 7] = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun];
+
+///
+/// Business hours that start two hours from now in the default business time zone and last an hour, so
+/// routine work is never allowed while the test runs.
+///
+fn hours_from_now() -> (NaiveTime, NaiveTime) {
+	let now = Utc::now().with_timezone(&Chicago).time().with_second(0).unwrap().with_nanosecond(0).unwrap();
+	(now + TimeDelta::hours(2), now + TimeDelta::hours(3))
+}
+
+#[test]
+fn business_hours_hold_routine_work_to_business_days() {
+	let policy = SchedulePolicy::business_hours(time(8, 0), time(18, 0));
+	// Tuesday 2024-03-05 and Saturday 2024-03-09.
+	let tuesday = chrono::NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+	let saturday = chrono::NaiveDate::from_ymd_opt(2024, 3, 9).unwrap();
+	assert!(policy.allows(Workload::Routine, tuesday.and_time(time(8, 0))));
+	assert!(policy.allows(Workload::Routine, tuesday.and_time(time(17, 59))));
+	assert!(!policy.allows(Workload::Routine, tuesday.and_time(time(18, 0))));
+	assert!(!policy.allows(Workload::Routine, tuesday.and_time(time(7, 59))));
+	assert!(!policy.allows(Workload::Routine, saturday.and_time(time(12, 0))));
+	assert!(policy.allows(Workload::Bulk, saturday.and_time(time(12, 0))));
+
+	// Saturday 12:00 in Chicago waits for Monday 08:00.
+	let saturday_noon = Chicago.with_ymd_and_hms(2024, 3, 9, 12, 0, 0).unwrap().with_timezone(&Utc);
+	assert_eq!(policy.next_allowed(Workload::Routine, saturday_noon, Chicago), Chicago.with_ymd_and_hms(2024, 3, 11, 8, 0, 0).unwrap().with_timezone(&Utc));
+}
+
+#[test]
+fn bulk_work_waits_for_the_overnight_window() {
+	let policy = SchedulePolicy::business_hours(time(8, 0), time(18, 0)).with_overnight(time(22, 0), time(5, 0));
+	let tuesday = chrono::NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+	assert!(!policy.allows(Workload::Bulk, tuesday.and_time(time(12, 0))));
+	assert!(policy.allows(Workload::Bulk, tuesday.and_time(time(23, 30))));
+	assert!(policy.allows(Workload::Bulk, tuesday.and_time(time(4, 59))));
+	assert!(!policy.allows(Workload::Bulk, tuesday.and_time(time(5, 0))));
+
+	let noon = Chicago.with_ymd_and_hms(2024, 3, 5, 12, 0, 0).unwrap().with_timezone(&Utc);
+	assert_eq!(policy.next_allowed(Workload::Bulk, noon, Chicago), Chicago.with_ymd_and_hms(2024, 3, 5, 22, 0, 0).unwrap().with_timezone(&Utc));
+}
+
+#[tokio::test]
+async fn login_canary_is_held_to_business_hours() {
+	let (opens, closes) = hours_from_now();
+	set_schedule_policy(None, Some("schedule-canary"), SchedulePolicy::business_hours(opens, closes).with_business_days(EVERY_DAY.to_vec()));
+	let now = Utc::now();
+	assert!(!may_run(Workload::Routine, None, Some("schedule-canary"), now));
+
+	let queue = InMemoryJobQueue::default();
+	schedule_login_canary(&queue, "schedule-canary").unwrap();
+	let scheduled_for = queue.pending()[0].scheduled_for;
+	assert!(scheduled_for > now + TimeDelta::minutes(110) && scheduled_for <= now + TimeDelta::hours(2), "{scheduled_for}");
+
+	// a canary found due outside the window is moved to it, not run.
+	let queue = InMemoryJobQueue::default();
+	queue.enqueue(Job::new("canary:schedule-canary".to_string(), JobKind::LoginCanary { manufacturer: "schedule-canary".to_string() }, now)).unwrap();
+	assert!(run_due_jobs(&queue).await.unwrap().is_empty());
+	assert!(queue.pending()[0].scheduled_for > now + TimeDelta::minutes(110));
+}
+
+#[tokio::test]
+async fn config_change_waits_for_business_hours() {
+	let path = std::env::temp_dir().join(format!("schedule-config-{}.json", std::process::id()));
+	std::fs::write(&path, r#"{"pricing_job_titles":["Designer"]}"#).unwrap();
+	watch_config_file(path.clone(), Duration::from_millis(20)).unwrap();
+	assert_eq!(runtime_config().pricing_job_titles, ["Designer"]);
+
+	let (opens, closes) = hours_from_now();
+	set_schedule_policy(None, None, SchedulePolicy::business_hours(opens, closes).with_business_days(EVERY_DAY.to_vec()));
+	tokio::time::sleep(Duration::from_millis(50)).await;
+	std::fs::write(&path, r#"{"pricing_job_titles":["Manager"]}"#).unwrap();
+	tokio::time::sleep(Duration::from_millis(200)).await;
+	assert_eq!(runtime_config().pricing_job_titles, ["Designer"]);
+
+	set_schedule_policy(None, None, SchedulePolicy::always());
+	tokio::time::sleep(Duration::from_millis(200)).await;
+	assert_eq!(runtime_config().pricing_job_titles, ["Manager"]);
+	let _ = std::fs::remove_file(path);
+}
+
+#[cfg(feature = "liebherr")]
+#[tokio::test]
+async fn stale_feed_keeps_answering_outside_the_overnight_window() {
+	use std::sync::Arc;
+
+	use common::{LocalVendor, Reply};
+	use eggersmann_app_server_appliance_availability::credentials::{set_credential_provider, StaticProvider};
+	use eggersmann_app_server_appliance_availability::{set_liebherr_config, set_result_cache_config, AvailabilityRequest, AvailabilityStatus, LiebherrConfig, ResultCacheConfig};
+
+	set_credential_provider(Arc::new(StaticProvider::new().with_secret("liebherr-username", "dealer").with_secret("liebherr-password", "hunter2")));
+	let vendor = LocalVendor::start(|_| Reply::new(200, "Material;Description;Plant;Available;Next Qty;Next Date\nCBS1660;Fridge;TX;4;;\n")).await;
+	set_liebherr_config(LiebherrConfig::new(format!("{}/feed", vendor.url)).with_max_age(Duration::ZERO));
+	// each lookup must reach the feed, not the answer cached by the one before.
+	set_result_cache_config(ResultCacheConfig::new(Duration::ZERO, Duration::from_mins(10)));
+	let lookup = || async { AvailabilityRequest::new("liebherr".to_string(), "houston".to_string(), "CBS1660".to_string()).get_warehouse().get_availability().await.unwrap().status };
+
+	set_schedule_policy(None, Some("liebherr"), SchedulePolicy::always());
+	assert_eq!(lookup().await, AvailabilityStatus::InStock);
+	assert_eq!(vendor.hits(), 1);
+
+	let (start, end) = hours_from_now();
+	set_schedule_policy(None, Some("liebherr"), SchedulePolicy::always().with_overnight(start, end));
+	assert_eq!(lookup().await, AvailabilityStatus::InStock);
+	assert_eq!(vendor.hits(), 1);
+
+	set_schedule_policy(None, Some("liebherr"), SchedulePolicy::always());
+	assert_eq!(lookup().await, AvailabilityStatus::InStock);
+	assert_eq!(vendor.hits(), 2);
+}
@@ -0,0 +1,65 @@
+//!
+//! # Tower services
+//! The lookup stack of `service` answers like `get_availability` does. A simulated request never reaches
+//! a vendor, whichever service it is sent to, and a shared lookup that is cancelled does not hold up
+//! the requests waiting on it.
+//!
+#![cfg(feature = "tower")]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use eggersmann_app_server_appliance_availability::service::{DedupeLayer, ProviderService};
+use eggersmann_app_server_appliance_availability::{AvailabilityRequest, AvailabilityStatus};
+use tower::{Layer, Service, ServiceExt};
+
+fn simulated(manufacturer: &str, model_number: &str) -> AvailabilityRequest {
+	AvailabilityRequest::new(manufacturer.to_string(), "houston".to_string(), model_number.to_string()).with_simulation().parse_manufacturer().get_warehouse().get_time()
+}
+
+#[tokio::test]
+async fn provider_answers_simulated_request_from_the_catalog() {
+	let mut provider = ProviderService::default();
+	let answered = provider.ready().await.unwrap().call(simulated("subzero", "CL3650UID/S")).await.unwrap();
+	let detail = answered.availability_detail.expect("simulated availability");
+	assert_eq!(detail.status, AvailabilityStatus::InStock);
+	assert_eq!(answered.product.map(|product| product.name).as_deref(), Some("Sub-Zero 36\" Classic Refrigerator"));
+}
+
+#[tokio::test]
+async fn provider_answers_unknown_simulated_model_as_not_found() {
+	let mut provider = ProviderService::default();
+	let answered = provider.ready().await.unwrap().call(simulated("bsh", "NOT-A-MODEL")).await.unwrap();
+	assert_eq!(answered.availability_detail.map(|detail| detail.status), Some(AvailabilityStatus::NotFound));
+}
+
+fn live(model_number: &str) -> AvailabilityRequest {
+	AvailabilityRequest::new("bsh".to_string(), "houston".to_string(), model_number.to_string()).parse_manufacturer().get_warehouse().get_time()
+}
+
+#[tokio::test]
+async fn cancelled_leader_releases_its_waiters() {
+	let calls = Arc::new(AtomicUsize::new(0));
+	let counted = calls.clone();
+	let inner = tower::service_fn(move |req: AvailabilityRequest| {
+		let call = counted.fetch_add(1, Ordering::SeqCst);
+		async move {
+			if call == 0 {
+				std::future::pending::<()>().await;
+			}
+			Ok::<_, String>(req)
+		}
+	});
+	let mut service = DedupeLayer::default().layer(inner);
+
+	let leader = service.ready().await.unwrap().call(live("DEDUPE-CANCEL"));
+	let waiter = service.ready().await.unwrap().call(live("DEDUPE-CANCEL"));
+	drop(leader);
+	let waited = tokio::time::timeout(Duration::from_secs(1), waiter).await.expect("waiter is released");
+	assert!(waited.is_err());
+
+	let next = tokio::time::timeout(Duration::from_secs(1), service.ready().await.unwrap().call(live("DEDUPE-CANCEL"))).await.expect("key is free again");
+	assert!(next.is_ok());
+	assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,19 @@
+//!
+//! # Shims
+//! The deprecated root functions keep taking the credentials existing callers pass: a `String`, a `&str`
+//! or a `SecretString`.
+//!
+#![cfg(all(feature = "bsh", feature = "subzero"))]
+#![allow(deprecated)]
+
+use eggersmann_app_server_appliance_availability::{bsh_availability, bsh_login, subzero_availability, subzero_login, AvailabilityRequest, SecretString};
+
+#[test]
+fn shims_take_strings_and_secrets() {
+	let request = || AvailabilityRequest::new("bsh".to_string(), "houston".to_string(), "HBLP651RUC".to_string());
+	// the lookups are only built, never run, so nothing is sent.
+	drop(bsh_availability(request(), "dealer".to_string(), SecretString::from("hunter2")));
+	drop(bsh_login("dealer", "hunter2".to_string()));
+	drop(subzero_availability(request(), SecretString::from("dealer"), "hunter2"));
+	drop(subzero_login("dealer".to_string(), "hunter2".to_string()));
+}
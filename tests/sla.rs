@@ -0,0 +1,30 @@
+//!
+//! # SLA
+//! Only lookups that reach the vendor are measured against its SLA policy; answers from the result cache
+//! are left out of the window.
+//!
+#![cfg(feature = "test-support")]
+
+use std::time::Duration;
+
+use eggersmann_app_server_appliance_availability::sla::{set_sla_policy, sla_status, SlaPolicy};
+use eggersmann_app_server_appliance_availability::testing::ScriptedBackend;
+use eggersmann_app_server_appliance_availability::{AvailabilityError, AvailabilityRequest};
+
+fn request(model_number: &str) -> AvailabilityRequest {
+	AvailabilityRequest::new("sla-vendor".to_string(), "houston".to_string(), model_number.to_string()).parse_manufacturer().get_time()
+}
+
+#[tokio::test]
+async fn cache_hits_are_not_vendor_samples() {
+	let _vendor = ScriptedBackend::new("sla-vendor").in_stock("OVEN-1", 2).fail("HOOD-30", AvailabilityError::VendorUnavailable("portal is down".to_string())).install();
+	set_sla_policy("sla-vendor", SlaPolicy::new(Duration::from_secs(10), 0.05));
+	for _ in 0..3 {
+		assert!(request("OVEN-1").get_availability().await.is_ok());
+	}
+	assert!(request("HOOD-30").get_availability().await.is_err());
+
+	let status = sla_status("sla-vendor").unwrap();
+	assert_eq!(status.samples, 2);
+	assert_eq!(status.error_rate, Some(0.5));
+}
@@ -0,0 +1,54 @@
+//!
+//! # Test support
+//! The scripted backend answers full lookups the way a vendor would, in order, and is gone again once
+//! its guard is dropped. No request leaves the process.
+//!
+#![cfg(feature = "test-support")]
+
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use eggersmann_app_server_appliance_availability::backend::backend;
+use eggersmann_app_server_appliance_availability::testing::{assert_available_on, assert_in_stock, backordered_response, in_stock_response, ScriptedBackend};
+use eggersmann_app_server_appliance_availability::{set_result_cache_config, AvailabilityError, AvailabilityRequest, ResultCacheConfig};
+
+fn request(manufacturer: &str, model_number: &str) -> AvailabilityRequest {
+	AvailabilityRequest::new(manufacturer.to_string(), "houston".to_string(), model_number.to_string()).parse_manufacturer().get_time()
+}
+
+#[tokio::test]
+async fn scripted_answers_are_used_in_order() {
+	let expected = NaiveDate::from_ymd_opt(2031, 5, 1).unwrap();
+	// each lookup must reach the vendor, not the answer cached by the one before.
+	set_result_cache_config(ResultCacheConfig::new(Duration::ZERO, Duration::from_mins(10)));
+	let vendor = ScriptedBackend::new("scripted-order").backordered("RANGE-36", expected).in_stock("RANGE-36", 2).install();
+
+	let first = request("scripted-order", "RANGE-36").into_answered().await.unwrap().response();
+	assert_available_on(&first, expected);
+	let second = request("scripted-order", "range-36").into_answered().await.unwrap().response();
+	assert_in_stock(&second);
+	let third = request("scripted-order", "RANGE-36").into_answered().await.unwrap().response();
+	assert_in_stock(&third);
+	assert_eq!(vendor.call_count("RANGE-36"), 3);
+}
+
+#[tokio::test]
+async fn scripted_errors_fail_the_lookup() {
+	let _vendor = ScriptedBackend::new("scripted-error").fail("HOOD-30", AvailabilityError::VendorUnavailable("portal down".to_string())).install();
+	assert!(request("scripted-error", "HOOD-30").into_answered().await.is_err());
+}
+
+#[test]
+fn dropping_the_guard_unregisters_the_backend() {
+	let vendor = ScriptedBackend::new("scripted-drop").install();
+	assert!(backend("scripted-drop").is_some());
+	drop(vendor);
+	assert!(backend("scripted-drop").is_none());
+}
+
+#[test]
+fn fake_responses_pass_their_assertions() {
+	assert_in_stock(&in_stock_response("bsh", "HBLP651RUC", 4));
+	let expected = NaiveDate::from_ymd_opt(2031, 1, 15).unwrap();
+	assert_available_on(&backordered_response("bsh", "HBLP651RUC", expected), expected);
+}
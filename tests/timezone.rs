@@ -0,0 +1,60 @@
+//!
+//! # Time zone
+//! The business date of a lookup rolls over at midnight on the showroom's wall clock, not at midnight UTC,
+//! and follows the showroom through daylight-saving changes.
+//!
+
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use eggersmann_app_server_appliance_availability::{business_today, set_showroom_time_zone, showroom_local_time};
+
+fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+	Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap()
+}
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+	NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+#[test]
+fn date_rolls_over_at_showroom_midnight() {
+	// Houston is six hours behind UTC in winter, Los Angeles seven in summer, New York five in winter.
+	assert_eq!(business_today(Some("houston"), utc(2024, 1, 16, 5, 59)), date(2024, 1, 15));
+	assert_eq!(business_today(Some("houston"), utc(2024, 1, 16, 6, 0)), date(2024, 1, 16));
+	assert_eq!(business_today(Some("Los Angeles"), utc(2024, 7, 1, 6, 59)), date(2024, 6, 30));
+	assert_eq!(business_today(Some("Los Angeles"), utc(2024, 7, 1, 7, 0)), date(2024, 7, 1));
+	assert_eq!(business_today(Some("new york"), utc(2024, 1, 1, 4, 59)), date(2023, 12, 31));
+	assert_eq!(business_today(Some("new york"), utc(2024, 1, 1, 5, 0)), date(2024, 1, 1));
+}
+
+#[test]
+fn midnight_follows_daylight_saving() {
+	// Chicago springs forward on 10 March 2024, so midnight of the 11th is at 05:00 UTC, not 06:00.
+	assert_eq!(business_today(Some("chicago"), utc(2024, 3, 10, 5, 59)), date(2024, 3, 9));
+	assert_eq!(business_today(Some("chicago"), utc(2024, 3, 10, 6, 0)), date(2024, 3, 10));
+	assert_eq!(business_today(Some("chicago"), utc(2024, 3, 11, 4, 59)), date(2024, 3, 10));
+	assert_eq!(business_today(Some("chicago"), utc(2024, 3, 11, 5, 0)), date(2024, 3, 11));
+
+	// and falls back on 3 November 2024, so midnight of the 4th is at 06:00 UTC again.
+	assert_eq!(business_today(Some("chicago"), utc(2024, 11, 3, 4, 59)), date(2024, 11, 2));
+	assert_eq!(business_today(Some("chicago"), utc(2024, 11, 3, 5, 0)), date(2024, 11, 3));
+	assert_eq!(business_today(Some("chicago"), utc(2024, 11, 4, 5, 59)), date(2024, 11, 3));
+	assert_eq!(business_today(Some("chicago"), utc(2024, 11, 4, 6, 0)), date(2024, 11, 4));
+}
+
+#[test]
+fn repeated_hour_keeps_its_zone_abbreviation() {
+	assert_eq!(showroom_local_time(Some("dallas"), utc(2024, 11, 3, 6, 30)), "11/03/2024 01:30:00 AM CDT");
+	assert_eq!(showroom_local_time(Some("dallas"), utc(2024, 11, 3, 7, 30)), "11/03/2024 01:30:00 AM CST");
+}
+
+#[test]
+fn unknown_and_configured_showrooms() {
+	// a showroom without a zone is on Chicago time.
+	assert_eq!(business_today(Some("timezone-nowhere"), utc(2024, 7, 1, 4, 59)), date(2024, 6, 30));
+	assert_eq!(business_today(None, utc(2024, 7, 1, 5, 0)), date(2024, 7, 1));
+
+	// Phoenix keeps standard time all year, so its midnight is at 07:00 UTC in summer too.
+	set_showroom_time_zone("Timezone-Phoenix", chrono_tz::America::Phoenix);
+	assert_eq!(business_today(Some("timezone-phoenix"), utc(2024, 7, 1, 6, 59)), date(2024, 6, 30));
+	assert_eq!(business_today(Some("timezone-phoenix"), utc(2024, 7, 1, 7, 0)), date(2024, 7, 1));
+}
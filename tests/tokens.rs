@@ -0,0 +1,75 @@
+//!
+//! # Token stores
+//! Vendor sessions saved to a store are loaded back as saved, a session that was never saved is `None`,
+//! and a failed blob request does not put the SAS token into the error.
+//!
+
+mod common;
+
+use common::{LocalVendor, Reply};
+use eggersmann_app_server_appliance_availability::tokens::{AzureBlobTokenStore, FileTokenStore, InMemoryTokenStore, TokenStore};
+
+#[tokio::test]
+async fn in_memory_store_round_trips() {
+	let store = InMemoryTokenStore::default();
+	assert_eq!(store.load("bsh_cookies.json").await, Ok(None));
+	store.save("bsh_cookies.json", r#"[{"name":"JSESSIONID"}]"#).await.unwrap();
+	let token = store.load("bsh_cookies.json").await.unwrap().unwrap();
+	assert_eq!(token.contents, r#"[{"name":"JSESSIONID"}]"#);
+	assert!(token.saved_at.is_some());
+}
+
+#[tokio::test]
+async fn file_store_round_trips() {
+	let dir = tempfile::tempdir().unwrap();
+	let store = FileTokenStore::new(dir.path().join("cookies"));
+	assert_eq!(store.load("subzero_cookies.json").await, Ok(None));
+	store.save("subzero_cookies.json", "first").await.unwrap();
+	store.save("subzero_cookies.json", "second").await.unwrap();
+	let token = store.load("subzero_cookies.json").await.unwrap().unwrap();
+	assert_eq!(token.contents, "second");
+	assert!(token.saved_at.is_some());
+	// the session is moved into place, nothing is left next to it.
+	assert_eq!(std::fs::read_dir(dir.path().join("cookies")).unwrap().count(), 1);
+}
+
+#[tokio::test]
+async fn blob_store_round_trips_and_misses() {
+	let blobs = std::sync::Arc::new(std::sync::Mutex::new(std::collections::HashMap::<String, String>::new()));
+	let saved = blobs.clone();
+	let vendor = LocalVendor::start(move |req| {
+		let name = req.path.split('?').next().unwrap_or_default().to_string();
+		if req.method == "PUT" {
+			saved.lock().unwrap().insert(name, req.body.clone());
+			return Reply::new(201, "");
+		}
+		saved.lock().unwrap().get(&name).map_or_else(|| Reply::new(404, "BlobNotFound"), |body| Reply::new(200, body.clone()).with_header("last-modified", "Wed, 14 Oct 2026 08:00:00 GMT"))
+	})
+	.await;
+	let store = AzureBlobTokenStore::new(format!("{}/sessions", vendor.url), "sv=2022-11-02&sp=rw&sig=c2VjcmV0".into());
+
+	assert_eq!(store.load("bsh_cookies.json").await, Ok(None));
+	store.save("bsh_cookies.json", "session").await.unwrap();
+	let token = store.load("bsh_cookies.json").await.unwrap().unwrap();
+	assert_eq!(token.contents, "session");
+	assert_eq!(token.saved_at.map(|saved_at| saved_at.to_rfc3339()).as_deref(), Some("2026-10-14T08:00:00+00:00"));
+	assert!(vendor.received().iter().all(|req| req.path.ends_with("sig=c2VjcmV0")));
+	assert_eq!(vendor.received()[1].header("x-ms-blob-type"), Some("BlockBlob"));
+}
+
+#[tokio::test]
+async fn blob_store_errors_do_not_carry_the_sas_token() {
+	// nothing listens on the port of a dropped listener.
+	let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+	let url = format!("http://{}/sessions", listener.local_addr().unwrap());
+	drop(listener);
+	let store = AzureBlobTokenStore::new(url, "sv=2022-11-02&sp=rw&sig=c2VjcmV0".into());
+
+	let load = store.load("bsh_cookies.json").await.unwrap_err();
+	let save = store.save("bsh_cookies.json", "session").await.unwrap_err();
+	for error in [load, save] {
+		assert!(error.contains("session blob bsh_cookies.json"), "{error}");
+		assert!(!error.contains("sig="), "{error}");
+		assert!(!error.contains("c2VjcmV0"), "{error}");
+	}
+}
@@ -0,0 +1,42 @@
+//!
+//! # Tranches
+//! A quantity confirmed in several deliveries keeps every delivery instead of collapsing into one
+//! backorder date, and the batch quantity check counts only what has arrived by the day of the answer.
+//!
+
+use chrono::NaiveDate;
+use eggersmann_app_server_appliance_availability::{Availability, AvailabilityStatus, Tranche};
+
+#[test]
+fn tranches_of_one_date_are_merged() {
+	let today = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+	let availability = Availability::from_text("Found: HBLP651RUC, Available: 06/01/2024", today).with_tranches(vec![Tranche::new(1, today), Tranche::new(2, today)], today);
+	assert_eq!(availability.status, AvailabilityStatus::InStock);
+	assert_eq!(availability.tranches, vec![Tranche::new(3, today)]);
+	assert_eq!(availability.quantity_by(today), 3);
+	assert_eq!(availability.complete_on(), Some(today));
+}
+
+#[cfg(feature = "test-support")]
+#[tokio::test]
+async fn batch_counts_the_tranches_due_now() {
+	use chrono::{Days, Utc};
+	use eggersmann_app_server_appliance_availability::backend::BackendAnswer;
+	use eggersmann_app_server_appliance_availability::batch::{lookup_batch_with, BatchLine, BatchOptions};
+	use eggersmann_app_server_appliance_availability::testing::ScriptedBackend;
+	use eggersmann_app_server_appliance_availability::{business_today, AvailabilityRequest};
+
+	let today = business_today(Some("houston"), Utc::now());
+	let later = today.checked_add_days(Days::new(42)).unwrap();
+	let split = Availability::from_text(&format!("Found: WALL-30, Available: {}", today.format("%m/%d/%Y")), today).with_tranches(vec![Tranche::new(1, today), Tranche::new(2, later)], today);
+	let _vendor = ScriptedBackend::new("split-vendor").answer("WALL-30", BackendAnswer::new(split)).install();
+
+	let line = |quantity: u32| BatchLine::new(AvailabilityRequest::new("split-vendor".to_string(), "houston".to_string(), "WALL-30".to_string()), quantity);
+	let results = lookup_batch_with(vec![line(1), line(2)], BatchOptions::default().with_aggregate_quantities(true)).await;
+	assert_eq!(results[0].aggregate_feasible, Some(false));
+	assert_eq!(results[0].line_feasible, Some(true));
+	assert_eq!(results[1].line_feasible, Some(false));
+	let availability = results[0].response.as_ref().and_then(|response| response.availability_detail.clone()).unwrap();
+	assert_eq!(availability.tranches, vec![Tranche::new(1, today), Tranche::new(2, later)]);
+	assert_eq!(availability.available_date, Some(later));
+}
@@ -0,0 +1,82 @@
+//!
+//! # True Residential
+//! Lookups against a local stand-in for the True Residential dealer availability service that answers
+//! with the responses in `tests/fixtures/true_residential`.
+//!
+#![cfg(feature = "true-residential")]
+
+mod common;
+
+use std::sync::Arc;
+
+use chrono::NaiveDate;
+use common::{LocalVendor, Received, Reply};
+use eggersmann_app_server_appliance_availability::backend::backend;
+use eggersmann_app_server_appliance_availability::credentials::{set_credential_provider, StaticProvider};
+use eggersmann_app_server_appliance_availability::{set_true_residential_config, AvailabilityError, AvailabilityRequest, AvailabilityStatus, Manufacturer, TrueResidentialConfig};
+
+const IN_STOCK: &str = include_str!("fixtures/true_residential/availability_in_stock.json");
+const EXPECTED: &str = include_str!("fixtures/true_residential/availability_expected.json");
+const EMPTY: &str = include_str!("fixtures/true_residential/availability_empty.json");
+
+fn true_residential_request(manufacturer: &str, model_number: &str) -> AvailabilityRequest {
+	AvailabilityRequest::new(manufacturer.to_string(), "houston".to_string(), model_number.to_string()).parse_manufacturer().get_warehouse()
+}
+
+fn availability_service(req: &Received) -> Reply {
+	if req.header("authorization") != Some("Basic ZWFzOmh1bnRlcjI=") {
+		return Reply::new(401, "");
+	}
+	let sku = req.path.split(['?', '&']).find_map(|pair| pair.strip_prefix("sku=")).unwrap_or_default();
+	match sku {
+		"TUR-24-SS-A" => Reply::new(200, IN_STOCK),
+		"TWC-15-SS-A" => Reply::new(200, EXPECTED),
+		"XX0000" => Reply::new(200, EMPTY),
+		_ => Reply::new(404, ""),
+	}
+}
+
+#[test]
+fn aliases_route_to_true_residential() {
+	for manufacturer in ["true_residential", "True Residential", "true-residential"] {
+		assert_eq!(true_residential_request(manufacturer, "TUR-24-SS-A").manufacturer, Some(Manufacturer::TrueResidential), "{manufacturer}");
+	}
+	// True also builds commercial refrigeration, which is not ordered through the residential service.
+	assert_eq!(true_residential_request("True", "TUR-24-SS-A").manufacturer, None);
+}
+
+#[tokio::test]
+async fn answers_from_the_availability_service() {
+	let true_residential = backend("true_residential").expect("true_residential backend is registered");
+	let lookup = |model_number: &str| {
+		let req = true_residential_request("true residential", model_number);
+		let true_residential = true_residential.clone();
+		async move { true_residential.availability(&req).await }
+	};
+	set_credential_provider(Arc::new(StaticProvider::new().with_secret("true-residential-username", "eas").with_secret("true-residential-password", "hunter2")));
+	// without a configured service nothing is sent.
+	assert_eq!(lookup("TUR-24-SS-A").await.unwrap_err(), AvailabilityError::Other("No True Residential dealer service configured, see `set_true_residential_config`.".to_string()));
+
+	let vendor = LocalVendor::start(availability_service).await;
+	set_true_residential_config(TrueResidentialConfig::new(format!("{}/api/availability", vendor.url)));
+
+	let answer = lookup("TUR-24-SS-A").await.unwrap();
+	let availability = answer.availability.unwrap();
+	assert_eq!((availability.status, availability.quantity, availability.available_date), (AvailabilityStatus::InStock, Some(2), None));
+	assert_eq!(availability.raw, "Found: TUR-24-SS-A, In stock: 2");
+	let product = answer.product.unwrap();
+	assert_eq!(product.name, "24\" Undercounter Refrigerator, Stainless Solid Door");
+	assert_eq!(product.brand.as_deref(), Some("True Residential"));
+	assert_eq!(vendor.received()[0].path, "/api/availability?sku=TUR-24-SS-A&warehouse=TX&quantity=1");
+
+	let availability = lookup("TWC-15-SS-A").await.unwrap().availability.unwrap();
+	assert_eq!((availability.status, availability.quantity, availability.available_date), (AvailabilityStatus::Backordered, Some(3), NaiveDate::from_ymd_opt(2030, 4, 2)));
+	assert_eq!(availability.raw, "Found: TWC-15-SS-A, Available: 04/02/2030");
+
+	// an empty answer and a 404 are both a model the service does not know.
+	assert_eq!(lookup("XX0000").await.unwrap().availability.unwrap().status, AvailabilityStatus::NotFound);
+	assert_eq!(lookup("ZZ404").await.unwrap().availability.unwrap().status, AvailabilityStatus::NotFound);
+
+	set_credential_provider(Arc::new(StaticProvider::new().with_secret("true-residential-username", "eas").with_secret("true-residential-password", "wrong")));
+	assert!(matches!(lookup("TUR-24-SS-A").await, Err(AvailabilityError::CredentialsRejected { .. })));
+}
@@ -0,0 +1,40 @@
+//!
+//! # v1 payload
+//! Requests stored and sent by existing app server consumers read into the typed fields and are written
+//! back byte for byte.
+//!
+
+use chrono::{TimeZone, Utc};
+use eggersmann_app_server_appliance_availability::{AvailabilityRequest, Manufacturer};
+
+const REQUESTS: &str = include_str!("fixtures/v1/requests.jsonl");
+
+#[test]
+fn v1_requests_round_trip_unchanged() {
+	for payload in REQUESTS.lines() {
+		let req: AvailabilityRequest = serde_json::from_str(payload).unwrap();
+		assert_eq!(serde_json::to_string(&req).unwrap(), payload);
+	}
+}
+
+#[test]
+fn v1_requests_read_into_typed_fields() {
+	let requests: Vec<AvailabilityRequest> = REQUESTS.lines().map(|payload| serde_json::from_str(payload).unwrap()).collect();
+	let manufacturers: Vec<Option<Manufacturer>> = requests.iter().map(|req| req.manufacturer.clone()).collect();
+	assert_eq!(manufacturers, [Some(Manufacturer::Bsh), Some(Manufacturer::Subzero), Some(Manufacturer::FisherPaykel), Some(Manufacturer::Other("acme".to_string())), None]);
+	assert_eq!(requests[0].looked_up_at(), Some(Utc.with_ymd_and_hms(2024, 3, 14, 9, 26, 53).unwrap()));
+	assert_eq!(requests[2].looked_up_at(), Some(Utc.with_ymd_and_hms(2024, 1, 31, 0, 0, 0).unwrap()));
+	assert_eq!(requests[3].looked_up_at(), None);
+	assert_eq!(requests[1].user.as_ref().and_then(|user| user.job_title.as_deref()), Some("Designer"));
+}
+
+#[test]
+fn typed_request_writes_the_v1_shape() {
+	let req = AvailabilityRequest::new("Sub-Zero".to_string(), "dallas".to_string(), "CL3650UID/S".to_string()).parse_manufacturer().with_requested_at(Utc.with_ymd_and_hms(2023, 11, 2, 16, 5, 0).unwrap());
+	let payload: serde_json::Value = serde_json::to_value(&req).unwrap();
+	assert_eq!(payload["manufacturer"], "subzero");
+	assert_eq!(payload["utc_time"], "11/02/2023 04:05:00 PM");
+	let read: AvailabilityRequest = serde_json::from_value(payload).unwrap();
+	assert_eq!(read.manufacturer, Some(Manufacturer::Subzero));
+	assert_eq!(read.looked_up_at(), req.looked_up_at());
+}
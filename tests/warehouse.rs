@@ -0,0 +1,76 @@
+//!
+//! # Warehouse
+//! Each showroom and manufacturer maps to one warehouse through `WAREHOUSE_TABLE`, unless a loaded
+//! configuration names another.
+//!
+
+use eggersmann_app_server_appliance_availability::config::{reload_config, RuntimeConfig};
+use eggersmann_app_server_appliance_availability::{AvailabilityRequest, Manufacturer, Showroom, Warehouse};
+
+fn warehouse(showroom: &str, manufacturer: &Manufacturer) -> Option<String> {
+	Warehouse::from_table(&Showroom::new(showroom), manufacturer).map(|warehouse| warehouse.to_string())
+}
+
+#[test]
+fn every_showroom_maps_every_builtin_vendor() {
+	let by_state = [Manufacturer::Liebherr, Manufacturer::FisherPaykel, Manufacturer::JennAir, Manufacturer::Monogram, Manufacturer::TrueResidential, Manufacturer::Dacor, Manufacturer::Bertazzoni];
+	for (showroom, bsh, subzero, miele, state) in [
+		("houston", "US00002148", "99432040", "Forest Park, IL", "TX"),
+		("florida", "US00000103", "99211620", "Pompano Beach, FL", "FL"),
+		("los angeles", "US00003803", "99614560", "Stockton, CA", "CA"),
+		("chicago", "US00001842", "99311630", "Forest Park, IL", "IL"),
+		("new york", "US00002933", "99103710", "South Brunswick, NJ", "NJ"),
+		("dallas", "US00003189", "99411540", "Forest Park, IL", "TX"),
+	] {
+		for manufacturer in [Manufacturer::Bsh, Manufacturer::Thermador, Manufacturer::Gaggenau] {
+			assert_eq!(warehouse(showroom, &manufacturer).as_deref(), Some(bsh), "{showroom} {manufacturer}");
+		}
+		for manufacturer in [Manufacturer::Subzero, Manufacturer::Wolf] {
+			assert_eq!(warehouse(showroom, &manufacturer).as_deref(), Some(subzero), "{showroom} {manufacturer}");
+		}
+		assert_eq!(warehouse(showroom, &Manufacturer::Miele).as_deref(), Some(miele), "{showroom}");
+		for manufacturer in &by_state {
+			let expected = if showroom == "new york" && *manufacturer == Manufacturer::Liebherr { "NY" } else { state };
+			assert_eq!(warehouse(showroom, manufacturer).as_deref(), Some(expected), "{showroom} {manufacturer}");
+		}
+	}
+}
+
+#[test]
+fn unknown_showroom_or_vendor_has_no_warehouse() {
+	assert_eq!(warehouse("austin", &Manufacturer::Bsh), None);
+	assert_eq!(warehouse("houston", &Manufacturer::Other("acme".to_string())), None);
+	assert_eq!(warehouse(" Los Angeles ", &Manufacturer::Miele).as_deref(), Some("Stockton, CA"));
+}
+
+#[test]
+fn configured_warehouse_comes_before_the_table() {
+	reload_config(RuntimeConfig::new().with_warehouse("austin", "bsh", "US00009999")).unwrap();
+	assert_eq!(Warehouse::for_showroom(&Showroom::new("Austin"), &Manufacturer::Bsh), Some(Warehouse::new("US00009999")));
+	assert_eq!(Warehouse::for_showroom(&Showroom::new("austin"), &Manufacturer::Subzero), None);
+	assert_eq!(Warehouse::for_showroom(&Showroom::new("houston"), &Manufacturer::Bsh), Some(Warehouse::new("US00002148")));
+
+	let req = AvailabilityRequest::new("Bosch".to_string(), "austin".to_string(), "HBLP651RUC".to_string()).get_warehouse();
+	assert_eq!(req.warehouse.as_deref(), Some("US00009999"));
+}
+
+#[test]
+fn warehouse_set_by_the_caller_is_kept() {
+	use eggersmann_app_server_appliance_availability::WarehouseSource;
+
+	let req = AvailabilityRequest::new("bsh".to_string(), "houston".to_string(), "HBLP651RUC".to_string()).with_warehouse(" US00003189 ").get_warehouse();
+	assert_eq!(req.warehouse.as_deref(), Some("US00003189"));
+	let decision = req.warehouse_decision.clone().unwrap();
+	assert_eq!(decision.source, WarehouseSource::Caller);
+	assert_eq!(decision.inference.as_deref(), Some("table resolved US00002148"));
+	assert_eq!(req.get_warehouse().warehouse.as_deref(), Some("US00003189"));
+
+	let mut resolved = AvailabilityRequest::new("bsh".to_string(), "dallas".to_string(), "HBLP651RUC".to_string()).get_warehouse();
+	assert_eq!(resolved.warehouse.as_deref(), Some("US00003189"));
+	resolved.showroom = Some("chicago".to_string());
+	assert_eq!(resolved.get_warehouse().warehouse.as_deref(), Some("US00001842"));
+
+	let v1 = r#"{"manufacturer":"miele","showroom":"houston","model_number":"G7106SCU","warehouse":"Stockton, CA","utc_time":null,"availability":null,"user":null}"#;
+	let req: AvailabilityRequest = serde_json::from_str(v1).unwrap();
+	assert_eq!(req.get_warehouse().warehouse.as_deref(), Some("Stockton, CA"));
+}
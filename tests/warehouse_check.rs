@@ -0,0 +1,78 @@
+//!
+//! # Warehouse check
+//! When the mapped warehouse has nothing, every warehouse of the manufacturer is asked and the best one
+//! is recommended.
+//!
+
+use std::sync::Arc;
+
+use chrono::NaiveDate;
+use eggersmann_app_server_appliance_availability::backend::{register_backend, BackendAnswer, ManufacturerBackend};
+use eggersmann_app_server_appliance_availability::discovery::check_all_warehouses;
+use eggersmann_app_server_appliance_availability::{Availability, AvailabilityError, AvailabilityRequest, AvailabilityStatus};
+
+///
+/// Liebherr stock per warehouse: `CBS1660` is only in stock in Illinois, `SBS7` in Texas and California,
+/// and `CNS5` is backordered everywhere it is known.
+///
+struct Regional;
+
+#[async_trait::async_trait]
+impl ManufacturerBackend for Regional {
+	fn name(&self) -> &'static str {
+		"liebherr"
+	}
+
+	async fn availability(&self, req: &AvailabilityRequest) -> Result<BackendAnswer, AvailabilityError> {
+		let date = |month, day| NaiveDate::from_ymd_opt(2030, month, day).unwrap();
+		let backordered = |date: NaiveDate| Availability::new(AvailabilityStatus::Backordered, format!("Available: {}", date.format("%m/%d/%Y"))).with_available_date(date);
+		let in_stock = || Availability::new(AvailabilityStatus::InStock, "In stock: 2".to_string()).with_quantity(2);
+		let availability = match (req.model_number.as_deref().unwrap_or_default(), req.warehouse.as_deref().unwrap_or_default()) {
+			("CBS1660" | "CNS5", "TX") => backordered(date(3, 1)),
+			("CBS1660" | "CNS5", "CA") => backordered(date(1, 15)),
+			("CBS1660", "FL") => return Err(AvailabilityError::VendorUnavailable("maintenance".to_string())),
+			("CBS1660", "IL") | ("SBS7", "TX" | "CA") => in_stock(),
+			_ => Availability::new(AvailabilityStatus::NotFound, "Not Found".to_string()),
+		};
+		Ok(BackendAnswer::new(availability))
+	}
+}
+
+#[tokio::test]
+async fn recommends_the_best_warehouse() {
+	register_backend(Arc::new(Regional));
+	let request = |model_number: &str| AvailabilityRequest::new("liebherr".to_string(), "houston".to_string(), model_number.to_string());
+
+	let check = check_all_warehouses(request("CBS1660")).await;
+	assert_eq!(check.mapped_warehouse.as_deref(), Some("TX"));
+	assert_eq!(check.warehouses.iter().map(|warehouse| warehouse.warehouse.as_str()).collect::<Vec<_>>(), ["TX", "FL", "CA", "IL", "NY"]);
+	assert_eq!(check.warehouses[0].showrooms, ["houston", "dallas"]);
+	assert!(check.warehouses[1].error.as_deref().unwrap().contains("maintenance"));
+	assert_eq!(check.recommended.as_deref(), Some("IL"));
+	assert_eq!(check.recommended_response().and_then(|response| response.warehouse.clone()).as_deref(), Some("IL"));
+
+	// a warehouse set by the caller is asked first.
+	let check = check_all_warehouses(request("CBS1660").with_warehouse("NY")).await;
+	assert_eq!(check.warehouses[0].warehouse, "NY");
+	assert_eq!(check.recommended.as_deref(), Some("IL"));
+	// without stock anywhere the earliest date wins, and among equals the showroom's own warehouse.
+	assert_eq!(check_all_warehouses(request("CNS5")).await.recommended.as_deref(), Some("CA"));
+	let check = check_all_warehouses(AvailabilityRequest::new("liebherr".to_string(), "los angeles".to_string(), "SBS7".to_string())).await;
+	assert_eq!(check.recommended.as_deref(), Some("CA"));
+	assert_eq!(check_all_warehouses(request("SBS7")).await.recommended.as_deref(), Some("TX"));
+	assert_eq!(check_all_warehouses(request("UNKNOWN")).await.recommended, None);
+}
+
+#[tokio::test]
+async fn nationwide_availability_maps_every_warehouse() {
+	register_backend(Arc::new(Regional));
+	let nationwide = AvailabilityRequest::new("liebherr".to_string(), "chicago".to_string(), "CBS1660".to_string()).get_nationwide_availability().await.unwrap();
+	assert_eq!(nationwide.warehouses.keys().map(String::as_str).collect::<Vec<_>>(), ["CA", "IL", "NY", "TX"]);
+	assert_eq!(nationwide.warehouses["CA"].available_date, NaiveDate::from_ymd_opt(2030, 1, 15));
+	assert_eq!(nationwide.warehouses["NY"].status, AvailabilityStatus::NotFound);
+	assert!(nationwide.errors["FL"].contains("maintenance"));
+	assert_eq!(nationwide.in_stock().iter().map(|(warehouse, _)| *warehouse).collect::<Vec<_>>(), ["IL"]);
+	assert_eq!(nationwide.total_quantity(), 2);
+
+	assert!(AvailabilityRequest::new("acme".to_string(), "chicago".to_string(), "CBS1660".to_string()).get_nationwide_availability().await.is_err());
+}
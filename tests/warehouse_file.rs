@@ -0,0 +1,53 @@
+//!
+//! # Warehouse file
+//! The showroom to warehouse map is loaded from a file at start, and anything the file leaves out keeps
+//! the built-in warehouse.
+//!
+
+use eggersmann_app_server_appliance_availability::config::{default_warehouses, load_warehouse_file, runtime_config};
+use eggersmann_app_server_appliance_availability::{Manufacturer, Showroom, Warehouse};
+
+fn warehouse(showroom: &str, manufacturer: &Manufacturer) -> Option<String> {
+	Warehouse::for_showroom(&Showroom::new(showroom), manufacturer).map(|warehouse| warehouse.to_string())
+}
+
+#[test]
+fn default_map_is_the_builtin_table() {
+	let warehouses = default_warehouses();
+	assert_eq!(warehouses.len(), 6);
+	assert_eq!(warehouses["new york"]["liebherr"], "NY");
+	assert_eq!(warehouses["new york"]["dacor"], "NJ");
+	assert_eq!(warehouses["houston"]["thermador"], "US00002148");
+}
+
+#[test]
+fn loaded_file_overrides_and_falls_back() {
+	let dir = std::env::temp_dir().join(format!("warehouse-file-{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+
+	let json = dir.join("warehouses.json");
+	std::fs::write(&json, r#"{"Houston": {"BSH": "US00009999"}, "austin": {"subzero": "99400000"}}"#).unwrap();
+	load_warehouse_file(&json).unwrap();
+	assert_eq!(warehouse("houston", &Manufacturer::Bsh).as_deref(), Some("US00009999"));
+	assert_eq!(warehouse("houston", &Manufacturer::Subzero).as_deref(), Some("99432040"));
+	assert_eq!(warehouse("austin", &Manufacturer::Wolf), None);
+	assert_eq!(warehouse("austin", &Manufacturer::Subzero).as_deref(), Some("99400000"));
+
+	let invalid = dir.join("invalid.json");
+	std::fs::write(&invalid, r#"{"houston": {"acme": "X"}}"#).unwrap();
+	assert!(load_warehouse_file(&invalid).unwrap_err().contains("unknown manufacturer acme"));
+	assert_eq!(runtime_config().warehouses["houston"]["bsh"], "US00009999");
+
+	let toml = dir.join("warehouses.toml");
+	std::fs::write(&toml, "[\"new york\"]\nliebherr = \"NJ\"\n").unwrap();
+	#[cfg(feature = "toml")]
+	{
+		load_warehouse_file(&toml).unwrap();
+		assert_eq!(warehouse("new york", &Manufacturer::Liebherr).as_deref(), Some("NJ"));
+		assert_eq!(warehouse("houston", &Manufacturer::Bsh).as_deref(), Some("US00002148"));
+	}
+	#[cfg(not(feature = "toml"))]
+	assert!(load_warehouse_file(&toml).unwrap_err().contains("toml feature"));
+
+	std::fs::remove_dir_all(&dir).unwrap();
+}